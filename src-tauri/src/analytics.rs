@@ -0,0 +1,164 @@
+//! Usage analytics for Intersect's dashboard
+//!
+//! Most numbers come straight from existing tables (messages, conversations, user_facts). Turn
+//! latency, debates and modes aren't stored anywhere else, so each finished turn also writes a
+//! row to `analytics_turn_metrics` (pruned with the other analytics tables after a year).
+
+use crate::clock;
+use crate::db::{self, TurnMetric};
+use crate::logging;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekCount {
+    pub week: String, // Local YYYY-MM-DD of the Monday starting the week
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Analytics {
+    pub range: String,
+    pub messages_by_agent: Vec<(String, i64)>, // Includes "user" and "governor"
+    pub conversations_per_week: Vec<WeekCount>,
+    pub facts_learned_per_week: Vec<WeekCount>,
+    pub turns: i64,
+    pub average_latency_ms: Option<f64>,
+    pub debates: i64,                          // Turns where agents pushed back on each other
+    pub turns_by_mode: Vec<(String, i64)>,     // "standard" | "disco" | "game" | "composite"
+    pub disco_ratio: Option<f64>,              // Share of turns with any agent in disco (game included)
+}
+
+/// Record a finished turn; `mode` comes from the result and the agents that were in disco
+pub fn record_turn(conversation_id: &str, message_id: &str, debate_mode: Option<&str>, disco: bool, response_count: usize, latency: std::time::Duration) {
+    let (mode, debate_mode) = match debate_mode {
+        Some(mode @ ("game" | "composite")) => (mode, None),
+        debate_mode => (if disco { "disco" } else { "standard" }, debate_mode),
+    };
+    let metric = TurnMetric {
+        conversation_id: conversation_id.to_string(),
+        message_id: Some(message_id.to_string()),
+        mode: mode.to_string(),
+        debate_mode: debate_mode.map(str::to_string),
+        response_count: response_count as i64,
+        latency_ms: latency.as_millis() as i64,
+        recorded_at: Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = db::record_turn_metric(&metric) {
+        logging::log_error(Some(conversation_id), &format!("Turn metric write failed: {}", e));
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Counts per local week, oldest first (weeks with nothing are left out)
+fn per_week(timestamps: &[String]) -> Vec<WeekCount> {
+    let mut weeks: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for ts in timestamps {
+        if let Ok(at) = DateTime::parse_from_rfc3339(ts) {
+            *weeks.entry(week_start(at.with_timezone(&Local).date_naive())).or_insert(0) += 1;
+        }
+    }
+    weeks.into_iter()
+        .map(|(week, count)| WeekCount { week: week.format("%Y-%m-%d").to_string(), count })
+        .collect()
+}
+
+struct TurnStats {
+    turns: i64,
+    average_latency_ms: Option<f64>,
+    debates: i64,
+    turns_by_mode: Vec<(String, i64)>,
+    disco_ratio: Option<f64>,
+}
+
+fn summarize_turns(metrics: &[TurnMetric]) -> TurnStats {
+    let turns = metrics.len() as i64;
+    let average_latency_ms = (turns > 0).then(|| metrics.iter().map(|m| m.latency_ms as f64).sum::<f64>() / turns as f64);
+    let debates = metrics.iter().filter(|m| m.debate_mode.is_some()).count() as i64;
+
+    let mut modes: BTreeMap<String, i64> = BTreeMap::new();
+    for metric in metrics {
+        *modes.entry(metric.mode.clone()).or_insert(0) += 1;
+    }
+    let disco_turns = metrics.iter().filter(|m| m.mode == "disco" || m.mode == "game").count();
+    let disco_ratio = (turns > 0).then(|| disco_turns as f64 / turns as f64);
+
+    let mut turns_by_mode: Vec<(String, i64)> = modes.into_iter().collect();
+    turns_by_mode.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    TurnStats { turns, average_latency_ms, debates, turns_by_mode, disco_ratio }
+}
+
+/// Dashboard numbers for a range: "week", "month", "year", "all" or "YYYY-MM-DD..YYYY-MM-DD"
+pub fn get(range: &str) -> Result<Analytics, String> {
+    let (since, until) = clock::parse_range(range, clock::now_local().date_naive())?;
+    let since = since.map(|t| t.to_rfc3339());
+    let until = until.map(|t| t.to_rfc3339());
+    let (since, until) = (since.as_deref(), until.as_deref());
+
+    let messages_by_agent = db::get_message_counts_by_role(since, until).map_err(|e| e.to_string())?;
+    let conversations = db::get_conversation_start_times(since, until).map_err(|e| e.to_string())?;
+    let facts = db::get_fact_learned_times(since, until).map_err(|e| e.to_string())?;
+    let metrics = db::get_turn_metrics(since, until).map_err(|e| e.to_string())?;
+    let stats = summarize_turns(&metrics);
+
+    Ok(Analytics {
+        range: range.to_string(),
+        messages_by_agent,
+        conversations_per_week: per_week(&conversations),
+        facts_learned_per_week: per_week(&facts),
+        turns: stats.turns,
+        average_latency_ms: stats.average_latency_ms,
+        debates: stats.debates,
+        turns_by_mode: stats.turns_by_mode,
+        disco_ratio: stats.disco_ratio,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(mode: &str, debate_mode: Option<&str>, latency_ms: i64) -> TurnMetric {
+        TurnMetric {
+            conversation_id: "c".to_string(),
+            message_id: None,
+            mode: mode.to_string(),
+            debate_mode: debate_mode.map(str::to_string),
+            response_count: 2,
+            latency_ms,
+            recorded_at: "2026-03-10T12:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn summarizes_turns() {
+        let metrics = vec![
+            metric("standard", None, 1000),
+            metric("standard", Some("mild"), 3000),
+            metric("disco", Some("intense"), 5000),
+            metric("game", None, 3000),
+        ];
+        let stats = summarize_turns(&metrics);
+        assert_eq!(stats.turns, 4);
+        assert_eq!(stats.average_latency_ms, Some(3000.0));
+        assert_eq!(stats.debates, 2);
+        assert_eq!(stats.turns_by_mode[0], ("standard".to_string(), 2));
+        assert_eq!(stats.disco_ratio, Some(0.5));
+        assert_eq!(summarize_turns(&[]).average_latency_ms, None);
+    }
+
+    #[test]
+    fn counts_per_week() {
+        let weeks = per_week(&[
+            "2026-03-10T12:00:00+00:00".to_string(),
+            "2026-03-12T12:00:00+00:00".to_string(),
+            "2026-03-18T12:00:00+00:00".to_string(),
+        ]);
+        assert_eq!(weeks.len(), 2);
+        assert_eq!((weeks[0].week.as_str(), weeks[0].count), ("2026-03-09", 2));
+    }
+}
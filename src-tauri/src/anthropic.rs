@@ -1,3 +1,5 @@
+use crate::retry;
+use crate::usage;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -12,6 +14,21 @@ pub const CLAUDE_HAIKU: &str = "claude-3-5-haiku-20241022";
 pub const CLAUDE_SONNET: &str = "claude-sonnet-4-20250514";
 pub const CLAUDE_OPUS: &str = "claude-opus-4-20250514";
 
+/// Marks the end of the static part of a system prompt. Everything before it is sent as a
+/// cached block (prompt caching), everything after as a normal block; see `cacheable`.
+pub const CACHE_BREAK: &str = "<|cache_break|>";
+
+/// Join a system prompt's static part (instructions, personas, knowledge) with the part that
+/// changes per call, so the static part is cached across calls
+pub fn cacheable(static_part: &str, dynamic_part: &str) -> String {
+    format!("{}{}{}", static_part, CACHE_BREAK, dynamic_part)
+}
+
+/// The system prompt without cache markers, for providers that don't support prompt caching
+pub fn strip_cache_break(system_prompt: &str) -> String {
+    system_prompt.replace(CACHE_BREAK, "")
+}
+
 /// Thinking budget levels for extended thinking
 #[derive(Debug, Clone, Copy)]
 pub enum ThinkingBudget {
@@ -43,12 +60,57 @@ struct ThinkingConfig {
     budget_tokens: u32,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum SystemPrompt {
+    Text(String),
+    Blocks(Vec<SystemBlock>),
+}
+
+impl SystemPrompt {
+    /// A plain prompt stays a string; one with a CACHE_BREAK becomes a cached static block
+    /// followed by the rest
+    fn from_prompt(prompt: &str) -> Self {
+        let Some((static_part, dynamic_part)) = prompt.rsplit_once(CACHE_BREAK) else {
+            return SystemPrompt::Text(prompt.to_string());
+        };
+        let static_part = static_part.replace(CACHE_BREAK, "");
+        let mut blocks = Vec::new();
+        if !static_part.trim().is_empty() {
+            blocks.push(SystemBlock {
+                block_type: "text",
+                text: static_part,
+                cache_control: Some(CacheControl { cache_type: "ephemeral" }),
+            });
+        }
+        if !dynamic_part.trim().is_empty() {
+            blocks.push(SystemBlock { block_type: "text", text: dynamic_part.to_string(), cache_control: None });
+        }
+        SystemPrompt::Blocks(blocks)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SystemBlock {
+    #[serde(rename = "type")]
+    block_type: &'static str,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheControl {
+    #[serde(rename = "type")]
+    cache_type: &'static str,
+}
+
 #[derive(Debug, Serialize)]
 struct MessagesRequest {
     model: String,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<SystemPrompt>,
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
@@ -59,6 +121,26 @@ struct MessagesRequest {
 #[derive(Debug, Deserialize)]
 struct MessagesResponse {
     content: Vec<ContentBlock>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    /// Input tokens at the base price: cache writes cost 1.25x, cache reads 0.1x
+    fn billed_input_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens * 5 / 4 + self.cache_read_input_tokens / 10
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -99,6 +181,45 @@ impl AnthropicClient {
         }
     }
     
+    /// Validate the API key with a minimal Haiku request
+    pub async fn validate_api_key(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let request = MessagesRequest {
+            model: CLAUDE_HAIKU.to_string(),
+            max_tokens: 5,
+            system: None,
+            messages: vec![AnthropicMessage {
+                role: "user".to_string(),
+                content: "Say 'ok'".to_string(),
+            }],
+            temperature: Some(0.0),
+            thinking: None,
+        };
+        
+        let response = self.client
+            .post(ANTHROPIC_API_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+        
+        if response.status().is_success() {
+            Ok(true)
+        } else {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            
+            if status.as_u16() == 401 {
+                return Err("Invalid API key".into());
+            } else if status.as_u16() == 429 {
+                return Err("Rate limited - too many requests".into());
+            }
+            
+            Err(format!("API error ({}): {}", status, error_text).into())
+        }
+    }
+    
     /// Send a chat completion with full control over model and thinking
     pub async fn chat_completion_advanced(
         &self,
@@ -109,6 +230,12 @@ impl AnthropicClient {
         max_tokens: Option<u32>,
         thinking: ThinkingBudget,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        // Over a degrading budget, Haiku answers instead (and it has no extended thinking)
+        let (model, thinking) = match usage::degraded_model("anthropic", model) {
+            Some(cheaper) => (cheaper, ThinkingBudget::None),
+            None => (model, thinking),
+        };
+        
         let thinking_config = thinking.to_tokens().map(|budget| ThinkingConfig {
             thinking_type: "enabled".to_string(),
             budget_tokens: budget,
@@ -131,20 +258,19 @@ impl AnthropicClient {
         let request = MessagesRequest {
             model: model.to_string(),
             max_tokens: tokens,
-            system: system_prompt.map(|s| s.to_string()),
+            system: system_prompt.map(SystemPrompt::from_prompt),
             messages,
             temperature: temp,
             thinking: thinking_config,
         };
         
-        let response = self.client
+        let response = retry::send("Anthropic chat", self.client
             .post(ANTHROPIC_API_URL)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", ANTHROPIC_VERSION)
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
-            .await?;
+        ).await?;
         
         if !response.status().is_success() {
             let status = response.status();
@@ -162,6 +288,9 @@ impl AnthropicClient {
         }
         
         let completion: MessagesResponse = response.json().await?;
+        if let Some(usage) = &completion.usage {
+            usage::record("anthropic", model, usage.billed_input_tokens(), usage.output_tokens);
+        }
         
         // Extract text from content blocks (skip thinking blocks, get final text)
         completion.content
@@ -171,4 +300,83 @@ impl AnthropicClient {
             .and_then(|c| c.text.clone())
             .ok_or_else(|| "No text response from Claude".into())
     }
+    
+    /// Send a raw Messages API body (e.g. one with `tools`) and return the response's content blocks.
+    /// A string `system` is split at CACHE_BREAK like other calls.
+    pub async fn messages_json(&self, mut request: serde_json::Value) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+        let requested = request["model"].as_str().unwrap_or(CLAUDE_HAIKU).to_string();
+        let model = usage::degraded_model("anthropic", &requested).unwrap_or(requested.as_str()).to_string();
+        request["model"] = serde_json::Value::String(model.clone());
+        if let Some(system) = request["system"].as_str() {
+            request["system"] = serde_json::to_value(SystemPrompt::from_prompt(system))?;
+        }
+        
+        let response = retry::send("Anthropic chat", self.client
+            .post(ANTHROPIC_API_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(&request)
+        ).await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("Anthropic API error ({}): {}", status, error_text).into());
+        }
+        
+        let mut completion: serde_json::Value = response.json().await?;
+        if let Ok(usage) = serde_json::from_value::<Usage>(completion["usage"].clone()) {
+            usage::record("anthropic", &model, usage.billed_input_tokens(), usage.output_tokens);
+        }
+        Ok(completion["content"].take())
+    }
+    
+    /// Ask a vision-capable model about images: each is (media type, base64 data), sent ahead of the prompt
+    pub async fn vision_completion(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        images: &[(String, String)],
+        prompt: &str,
+        max_tokens: u32,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut content: Vec<serde_json::Value> = images.iter()
+            .map(|(media_type, data)| serde_json::json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data }
+            }))
+            .collect();
+        content.push(serde_json::json!({ "type": "text", "text": prompt }));
+        let request = serde_json::json!({
+            "model": model,
+            "max_tokens": max_tokens,
+            "system": system_prompt,
+            "messages": [{ "role": "user", "content": content }],
+        });
+        
+        let response = retry::send("Anthropic vision", self.client
+            .post(ANTHROPIC_API_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(&request)
+        ).await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("Anthropic API error ({}): {}", status, error_text).into());
+        }
+        
+        let completion: MessagesResponse = response.json().await?;
+        if let Some(usage) = &completion.usage {
+            usage::record("anthropic", model, usage.billed_input_tokens(), usage.output_tokens);
+        }
+        completion.content
+            .iter()
+            .rfind(|c| c.content_type == "text")
+            .and_then(|c| c.text.clone())
+            .ok_or_else(|| "No text response from Claude".into())
+    }
 }
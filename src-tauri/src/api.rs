@@ -0,0 +1,341 @@
+//! Local HTTP API for Intersect
+//!
+//! Opt-in JSON API on 127.0.0.1 so scripts and other apps can use the running instance. Every
+//! route except /v1/health needs `Authorization: Bearer <token>`; the token is generated when the
+//! API is first enabled and can be regenerated from settings.
+//!
+//! - `GET  /v1/health` -> {"ok": true, "version"}
+//! - `POST /v1/conversations` -> a new conversation
+//! - `POST /v1/messages` {"conversation_id"?, "message", "active_agents"?, "disco_agents"?, "reply_to_message_id"?}
+//!   -> {"conversation_id", "result"} (same result as the send_message command; a new
+//!   conversation is created when no id is given)
+//! - `GET  /v1/memory/stats` -> the get_memory_stats result
+//! - `GET  /v1/conversations/<id>/export?format=markdown|json` -> the rendered export
+//!
+//! Settings are stored in app_settings as `local_api`.
+
+use crate::db;
+use crate::export;
+use crate::logging;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::{Method, Request, Response, StatusCode};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tauri::Emitter;
+
+const SETTINGS_KEY: &str = "local_api";
+pub const DEFAULT_PORT: u16 = 47821;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: Option<String>, // Generated on first enable
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self { enabled: false, port: DEFAULT_PORT, token: None }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SendMessageBody {
+    conversation_id: Option<String>,
+    message: String,
+    active_agents: Option<Vec<String>>,
+    #[serde(default)]
+    disco_agents: Vec<String>,
+    reply_to_message_id: Option<String>,
+}
+
+/// The running server: its port, the accept loop, and the signal that closes its open connections
+struct Server {
+    port: u16,
+    task: tauri::async_runtime::JoinHandle<()>,
+    shutdown: tokio::sync::watch::Sender<bool>,
+}
+
+static SERVER: Lazy<Mutex<Option<Server>>> = Lazy::new(|| Mutex::new(None));
+
+/// The token requests are checked against. It's read per request, so a regenerated token
+/// applies to keep-alive connections too; None while the API is off.
+static TOKEN: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn get_settings() -> ApiSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &ApiSettings) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+fn new_token() -> String {
+    format!("isk_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Turn the API on or off (and change its port), restarting the server
+pub async fn configure(app_handle: tauri::AppHandle, enabled: bool, port: Option<u16>) -> Result<ApiSettings, String> {
+    let mut settings = get_settings();
+    settings.enabled = enabled;
+    if let Some(port) = port {
+        if port < 1024 {
+            return Err("Use a port from 1024 up".to_string());
+        }
+        settings.port = port;
+    }
+    if enabled && settings.token.is_none() {
+        settings.token = Some(new_token());
+    }
+
+    if enabled {
+        let running_port = SERVER.lock().unwrap().as_ref().map(|server| server.port);
+        if running_port != Some(settings.port) {
+            // Bind before stopping the old server, so a taken port leaves the API as it was
+            let listener = bind(settings.port).await?;
+            stop();
+            *TOKEN.write().unwrap() = settings.token.clone();
+            serve(app_handle, listener, settings.port);
+        }
+    } else {
+        stop();
+    }
+    save_settings(&settings)?;
+    Ok(settings)
+}
+
+/// Replace the token; clients using the old one are rejected from now on
+pub fn regenerate_token() -> Result<ApiSettings, String> {
+    let mut settings = get_settings();
+    settings.token = Some(new_token());
+    save_settings(&settings)?;
+    if settings.enabled {
+        *TOKEN.write().unwrap() = settings.token.clone();
+    }
+    Ok(settings)
+}
+
+/// Start the server at launch if it's enabled
+pub fn start(app_handle: tauri::AppHandle) {
+    let settings = get_settings();
+    if !settings.enabled {
+        return;
+    }
+    if settings.token.is_none() {
+        logging::log_error(None, "Local API is enabled but has no token");
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        match bind(settings.port).await {
+            Ok(listener) => {
+                *TOKEN.write().unwrap() = settings.token.clone();
+                serve(app_handle, listener, settings.port);
+            }
+            Err(e) => logging::log_error(None, &format!("Local API failed to start: {}", e)),
+        }
+    });
+}
+
+/// Stop accepting, close open connections, and reject anything still in flight
+fn stop() {
+    *TOKEN.write().unwrap() = None;
+    if let Some(server) = SERVER.lock().unwrap().take() {
+        server.task.abort();
+        let _ = server.shutdown.send(true);
+    }
+}
+
+async fn bind(port: u16) -> Result<tokio::net::TcpListener, String> {
+    tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Couldn't listen on port {}: {}", port, e))
+}
+
+/// How long to wait after another failed accept
+fn next_backoff(current: Duration) -> Duration {
+    (current * 2).min(ACCEPT_BACKOFF_MAX)
+}
+
+fn serve(app_handle: tauri::AppHandle, listener: tokio::net::TcpListener, port: u16) {
+    logging::log_conversation(None, &format!("Local API listening on 127.0.0.1:{}", port));
+    let (shutdown, closed) = tokio::sync::watch::channel(false);
+
+    let task = tauri::async_runtime::spawn(async move {
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => {
+                    backoff = ACCEPT_BACKOFF_MIN;
+                    stream
+                }
+                Err(e) => {
+                    // Persistent errors (out of file descriptors, say) would otherwise spin the loop
+                    logging::log_error(None, &format!("Local API accept failed: {}", e));
+                    tokio::time::sleep(backoff).await;
+                    backoff = next_backoff(backoff);
+                    continue;
+                }
+            };
+            let app_handle = app_handle.clone();
+            let mut closed = closed.clone();
+            tauri::async_runtime::spawn(async move {
+                let service = hyper::service::service_fn(move |request| handle(app_handle.clone(), request));
+                let io = hyper_util::rt::TokioIo::new(stream);
+                tokio::select! {
+                    result = hyper::server::conn::http1::Builder::new().serve_connection(io, service) => {
+                        if let Err(e) = result {
+                            logging::log_error(None, &format!("Local API connection error: {}", e));
+                        }
+                    }
+                    // Fires on stop, or when the server is replaced and its sender dropped
+                    _ = closed.changed() => {}
+                }
+            });
+        }
+    });
+    *SERVER.lock().unwrap() = Some(Server { port, task, shutdown });
+}
+
+/// Compare tokens without leaking where they differ through timing
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn authorized(request: &Request<Incoming>, token: &str) -> bool {
+    request.headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|given| token_matches(given.trim(), token))
+        .unwrap_or(false)
+}
+
+fn respond(status: StatusCode, body: Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap_or_default()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    respond(status, json!({ "error": message }))
+}
+
+/// `?name=value` from a query string
+fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    query?.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(request: Request<Incoming>) -> Result<T, Response<Full<Bytes>>> {
+    let body = Limited::new(request.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+        .map_err(|_| error(StatusCode::PAYLOAD_TOO_LARGE, "Body too large or unreadable"))?
+        .to_bytes();
+    serde_json::from_slice(&body).map_err(|e| error(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {}", e)))
+}
+
+async fn handle(app_handle: tauri::AppHandle, request: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().trim_end_matches('/').to_string();
+    let query = request.uri().query().map(str::to_string);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    if method == Method::GET && segments == ["v1", "health"] {
+        return Ok(respond(StatusCode::OK, json!({ "ok": true, "version": env!("CARGO_PKG_VERSION") })));
+    }
+    let token = TOKEN.read().unwrap().clone();
+    if !token.is_some_and(|token| authorized(&request, &token)) {
+        return Ok(error(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token"));
+    }
+
+    let response = match (&method, segments.as_slice()) {
+        (&Method::POST, ["v1", "conversations"]) => match crate::create_conversation(false) {
+            Ok(conversation) => respond(StatusCode::CREATED, json!(conversation)),
+            Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e),
+        },
+        (&Method::POST, ["v1", "messages"]) => {
+            let body: SendMessageBody = match read_json(request).await {
+                Ok(body) => body,
+                Err(response) => return Ok(response),
+            };
+            let conversation_id = match body.conversation_id {
+                Some(id) => id,
+                None => match crate::create_conversation(false) {
+                    Ok(conversation) => conversation.id,
+                    Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
+                },
+            };
+            logging::log_conversation(Some(&conversation_id), "Message received through the local API");
+            match crate::send_message(
+                app_handle.clone(), conversation_id.clone(), body.message, body.active_agents, body.disco_agents, None, None, body.reply_to_message_id,
+            ).await {
+                Ok(result) => {
+                    // Let an open window pick up the new messages
+                    let _ = app_handle.emit("api-message-handled", &conversation_id);
+                    respond(StatusCode::OK, json!({ "conversation_id": conversation_id, "result": result }))
+                }
+                Err(e) => error(StatusCode::UNPROCESSABLE_ENTITY, &e),
+            }
+        }
+        (&Method::GET, ["v1", "memory", "stats"]) => match crate::get_memory_stats() {
+            Ok(stats) => respond(StatusCode::OK, json!(stats)),
+            Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e),
+        },
+        (&Method::GET, ["v1", "conversations", id, "export"]) => {
+            let format = query_param(query.as_deref(), "format").unwrap_or_else(|| "markdown".to_string());
+            match export::ConversationFormat::from_str(&format) {
+                Some(format) => match export::render_conversation(id, format) {
+                    Ok((file_name, contents)) => respond(StatusCode::OK, json!({ "file_name": file_name, "contents": contents })),
+                    Err(e) => error(StatusCode::NOT_FOUND, &e),
+                },
+                None => error(StatusCode::BAD_REQUEST, &format!("Unknown export format: {}", format)),
+            }
+        }
+        _ => error(StatusCode::NOT_FOUND, "No such route"),
+    };
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_and_query_params() {
+        assert!(token_matches("isk_abc", "isk_abc"));
+        assert!(!token_matches("isk_abd", "isk_abc"));
+        assert!(!token_matches("isk_ab", "isk_abc"));
+        assert_eq!(query_param(Some("a=1&format=json"), "format").as_deref(), Some("json"));
+        assert_eq!(query_param(None, "format"), None);
+    }
+
+    #[test]
+    fn accept_backoff_doubles_up_to_a_cap() {
+        assert_eq!(next_backoff(ACCEPT_BACKOFF_MIN), Duration::from_millis(200));
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        for _ in 0..20 {
+            backoff = next_backoff(backoff);
+        }
+        assert_eq!(backoff, ACCEPT_BACKOFF_MAX);
+    }
+}
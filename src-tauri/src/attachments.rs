@@ -0,0 +1,170 @@
+//! Message attachments for Intersect
+//!
+//! Attached files are copied into the app data dir (`attachments/<id>.<ext>`) and recorded in the
+//! attachments table, pending until the message they go with is sent. Agents don't see images
+//! directly: before routing, a vision model (Claude Sonnet, or GPT-4o when Claude fails and an
+//! OpenAI key is set) describes them once, the description is stored on the attachment, and the
+//! turn's message carries it so every agent -- on any provider -- can react to the image. Later
+//! turns see the stored description alongside that message in their history.
+
+use crate::anthropic::{AnthropicClient, CLAUDE_SONNET};
+use crate::db::{self, Attachment, Message};
+use crate::logging;
+use crate::openai::OpenAIClient;
+use std::path::PathBuf;
+use tauri::Manager;
+
+/// Claude's per-image limit
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+const VISION_MODEL_OPENAI: &str = "gpt-4o";
+
+const VISION_PROMPT: &str = "You describe images a user has attached to a message so that assistants who can't see them can respond. \
+Describe what's shown concretely: any text (quote it exactly), people and their expressions, setting, charts or UI and what they show. \
+Note anything that seems to be why the user shared it. 2-5 sentences, no preamble.";
+
+/// Media type for a supported file, by extension
+pub fn media_type(file_name: &str) -> Option<(&'static str, &'static str)> {
+    let extension = std::path::Path::new(file_name).extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some(("image", "image/png")),
+        "jpg" | "jpeg" => Some(("image", "image/jpeg")),
+        "gif" => Some(("image", "image/gif")),
+        "webp" => Some(("image", "image/webp")),
+        _ => None,
+    }
+}
+
+pub fn attachments_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app_handle.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data_dir.join("attachments"))
+}
+
+/// Copy a file into the attachments dir and record it as pending for the conversation
+pub fn store(app_handle: &tauri::AppHandle, conversation_id: &str, file_name: &str, bytes: &[u8]) -> Result<Attachment, String> {
+    let (kind, mime_type) = media_type(file_name)
+        .ok_or_else(|| format!("Unsupported attachment type: {}", file_name))?;
+    if bytes.is_empty() {
+        return Err("The file is empty".to_string());
+    }
+    if kind == "image" && bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!("Images can be at most {} MB", MAX_IMAGE_BYTES / 1024 / 1024));
+    }
+
+    let dir = attachments_dir(app_handle)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create attachments dir: {}", e))?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let extension = std::path::Path::new(file_name).extension().and_then(|e| e.to_str()).unwrap_or("bin").to_lowercase();
+    let path = dir.join(format!("{}.{}", id, extension));
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to save attachment: {}", e))?;
+
+    let attachment = Attachment {
+        id,
+        conversation_id: conversation_id.to_string(),
+        message_id: None,
+        kind: kind.to_string(),
+        mime_type: mime_type.to_string(),
+        file_name: file_name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        size_bytes: bytes.len() as i64,
+        description: None,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = db::save_attachment(&attachment) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e.to_string());
+    }
+    Ok(attachment)
+}
+
+/// Delete stored files (missing files are fine -- the row is what matters)
+pub fn remove_files(attachments: &[Attachment]) {
+    for attachment in attachments {
+        let _ = std::fs::remove_file(&attachment.path);
+    }
+}
+
+/// Describe a message's undescribed images with a vision model and save the descriptions
+async fn describe_images(images: &[Attachment], user_message: &str, anthropic_key: &str, openai_key: Option<&str>) -> Option<String> {
+    use base64::Engine;
+    let encoded: Vec<(String, String)> = images.iter()
+        .filter_map(|a| {
+            let bytes = std::fs::read(&a.path).ok()?;
+            Some((a.mime_type.clone(), base64::engine::general_purpose::STANDARD.encode(bytes)))
+        })
+        .collect();
+    if encoded.is_empty() {
+        return None;
+    }
+    let prompt = format!("The user's message with these images: \"{}\"", user_message);
+
+    let description = match AnthropicClient::new(anthropic_key)
+        .vision_completion(CLAUDE_SONNET, VISION_PROMPT, &encoded, &prompt, 400)
+        .await
+    {
+        Ok(text) => text,
+        Err(e) => {
+            logging::log_error(None, &format!("Claude vision failed: {}", e));
+            let key = openai_key?;
+            match OpenAIClient::new(key).vision_completion(VISION_MODEL_OPENAI, VISION_PROMPT, &encoded, &prompt, 400).await {
+                Ok(text) => text,
+                Err(e) => {
+                    logging::log_error(None, &format!("GPT-4o vision failed: {}", e));
+                    return None;
+                }
+            }
+        }
+    };
+    let description = description.trim().to_string();
+    for image in images {
+        let _ = db::set_attachment_description(&image.id, &description);
+    }
+    Some(description)
+}
+
+/// Context for agents about the images on a message, describing them first if needed.
+/// None when the message has no images or no description could be produced.
+pub async fn image_context(message_id: &str, user_message: &str, anthropic_key: &str, openai_key: Option<&str>) -> Option<String> {
+    let images: Vec<Attachment> = db::get_message_attachments(message_id).ok()?
+        .into_iter()
+        .filter(|a| a.kind == "image")
+        .collect();
+    if images.is_empty() {
+        return None;
+    }
+
+    let pending: Vec<Attachment> = images.iter().filter(|a| a.description.is_none()).cloned().collect();
+    let fresh = if pending.is_empty() {
+        None
+    } else {
+        describe_images(&pending, user_message, anthropic_key, openai_key).await
+    };
+    let mut descriptions: Vec<String> = images.iter().filter_map(|a| a.description.clone()).collect();
+    descriptions.extend(fresh);
+    context_line(images.len(), descriptions)
+}
+
+fn context_line(image_count: usize, mut descriptions: Vec<String>) -> Option<String> {
+    descriptions.dedup();
+    if descriptions.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "[The user attached {} image{}. What it shows: {}]",
+        image_count,
+        if image_count == 1 { "" } else { "s" },
+        descriptions.join(" ")
+    ))
+}
+
+/// Add the stored image descriptions to earlier messages in a history (loaded with their attachments),
+/// so later turns still know what was shown. `except` is the message this turn describes itself.
+pub fn describe_history(messages: &mut [Message], except: Option<&str>) {
+    for message in messages.iter_mut().filter(|m| Some(m.id.as_str()) != except) {
+        let images: Vec<&Attachment> = message.attachments.iter().filter(|a| a.kind == "image").collect();
+        let descriptions = images.iter().filter_map(|a| a.description.clone()).collect();
+        if let Some(context) = context_line(images.len(), descriptions) {
+            message.content = format!("{}\n\n{}", message.content, context);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Automatic local backups for Intersect
+//!
+//! Protects the memory database from a single corruption event:
+//! - A background tick snapshots intersect.db into app data/backups on a daily and a weekly cadence
+//! - Each cadence keeps a rotating set of snapshots; the oldest beyond the limit are deleted
+//! - Snapshots use VACUUM INTO, so they're consistent even while the app is writing
+//! - Restoring, importing and resetting take a safety snapshot of the current database first, so
+//!   they can be undone; schema migrations write one of their own (`pre-migration`, see db.rs)
+//! - Pre-reset snapshots hold exactly what the user asked to wipe, so they also expire after
+//!   PRE_RESET_RETENTION_DAYS rather than waiting to be rotated out
+//! - How many of each to keep is set in app_settings as `backup_settings`
+
+use crate::clock;
+use crate::db;
+use crate::logging;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::Manager;
+
+const CHECK_INTERVAL_SECS: u64 = 60 * 60;
+const FILE_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+const FILE_TIME_LEN: usize = 15; // "20250131-093000"
+
+const SETTINGS_KEY: &str = "backup_settings";
+/// (kind, days between snapshots)
+const CADENCES: [(&str, i64); 2] = [
+    ("daily", 1),
+    ("weekly", 7),
+];
+/// Safety snapshot kinds, each rotated on its own
+const SAFETY_KINDS: [&str; 4] = ["pre-restore", "pre-import", "pre-reset", "pre-migration"];
+const MAX_KEEP: usize = 100;
+/// Days a pre-reset snapshot is kept before it's deleted, however few there are
+pub const PRE_RESET_RETENTION_DAYS: i64 = 30;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static BACKUP_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Backup {
+    pub id: String,
+    pub kind: String,       // "daily" | "weekly" | "pre-restore" | "pre-import" | "pre-reset" | "pre-migration"
+    pub created_at: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub keep_safety: usize, // Per kind of safety snapshot (pre-restore, pre-import, pre-reset, pre-migration)
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self { keep_daily: 7, keep_weekly: 4, keep_safety: 3 }
+    }
+}
+
+impl BackupSettings {
+    fn keep(&self, kind: &str) -> usize {
+        match kind {
+            "daily" => self.keep_daily,
+            "weekly" => self.keep_weekly,
+            _ => self.keep_safety,
+        }
+    }
+}
+
+pub fn get_settings() -> BackupSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Save how many backups to keep and prune anything now beyond it
+pub fn set_settings(settings: &BackupSettings) -> Result<(), String> {
+    let counts = [settings.keep_daily, settings.keep_weekly, settings.keep_safety];
+    if counts.iter().any(|&keep| keep == 0 || keep > MAX_KEEP) {
+        return Err(format!("Keep between 1 and {} of each kind", MAX_KEEP));
+    }
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())?;
+    rotate_all(settings);
+    Ok(())
+}
+
+/// Start the backup tick (idempotent - init_app may run more than once)
+pub fn start(app_handle: &tauri::AppHandle) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    match app_handle.path().app_data_dir() {
+        Ok(dir) => { let _ = BACKUP_DIR.set(dir.join("backups")); }
+        Err(e) => {
+            logging::log_error(None, &format!("Backups disabled, no app data dir: {}", e));
+            return;
+        }
+    }
+
+    // Snapshots written before this start (pre-migration, at database open) are pruned here
+    rotate_all(&get_settings());
+
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            // VACUUM INTO reads the whole database, keep it off the async workers
+            let _ = tauri::async_runtime::spawn_blocking(run_due_backups).await;
+        }
+    });
+
+    logging::log_conversation(None, "Backup scheduler started");
+}
+
+fn backup_dir() -> Result<&'static Path, String> {
+    BACKUP_DIR.get().map(|p| p.as_path()).ok_or_else(|| "Backups are not available".to_string())
+}
+
+fn run_due_backups() {
+    let settings = get_settings();
+    for (kind, period_days) in CADENCES {
+        let latest = list_backups().unwrap_or_default()
+            .into_iter()
+            .find(|b| b.kind == kind);
+        let due = latest
+            .and_then(|b| chrono::DateTime::parse_from_rfc3339(&b.created_at).ok())
+            .map(|at| clock::now().signed_duration_since(at.with_timezone(&Utc)).num_days() >= period_days)
+            .unwrap_or(true);
+        if !due {
+            continue;
+        }
+
+        match create_backup(kind) {
+            Ok(backup) => {
+                logging::log_conversation(None, &format!("Backup written: {} ({} bytes)", backup.id, backup.size_bytes));
+                rotate(kind, settings.keep(kind));
+            }
+            Err(e) => logging::log_error(None, &format!("{} backup failed: {}", kind, e)),
+        }
+    }
+    prune_expired_resets();
+}
+
+/// Snapshot the database now
+pub fn create_backup(kind: &str) -> Result<Backup, String> {
+    let dir = backup_dir()?;
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    let id = format!("{}-{}", kind, clock::now().format(FILE_TIME_FORMAT));
+    let path = dir.join(format!("{}.db", id));
+    db::snapshot_to(&path).map_err(|e| e.to_string())?;
+
+    backup_from_path(&path).ok_or_else(|| "Backup was not written".to_string())
+}
+
+/// All backups on disk, newest first
+pub fn list_backups() -> Result<Vec<Backup>, String> {
+    let dir = backup_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<Backup> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| backup_from_path(&entry.path()))
+        .collect();
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// Replace the database with a backup. Returns the safety snapshot taken of the current database.
+pub fn restore_backup(id: &str) -> Result<Backup, String> {
+    // Only restore files we listed ourselves (the ID never becomes an arbitrary path)
+    let backup = list_backups()?
+        .into_iter()
+        .find(|b| b.id == id)
+        .ok_or_else(|| format!("Backup not found: {}", id))?;
+    let source = backup_dir()?.join(format!("{}.db", backup.id));
+
+    let safety = create_safety_backup("pre-restore")?;
+
+    db::with_database_closed(|live_path| {
+        std::fs::copy(&source, live_path).map_err(|e| format!("Failed to copy backup: {}", e))?;
+        Ok(())
+    })?;
+
+    logging::log_conversation(None, &format!("Restored backup {} (previous database saved as {})", backup.id, safety.id));
+    Ok(safety)
+}
+
+/// Snapshot the database before a destructive operation (restore, data import, reset), keeping the last few of each kind
+pub fn create_safety_backup(kind: &str) -> Result<Backup, String> {
+    let backup = create_backup(kind)?;
+    rotate(kind, get_settings().keep_safety);
+    Ok(backup)
+}
+
+fn rotate_all(settings: &BackupSettings) {
+    for kind in CADENCES.iter().map(|(kind, _)| *kind).chain(SAFETY_KINDS) {
+        rotate(kind, settings.keep(kind));
+    }
+    prune_expired_resets();
+}
+
+/// Delete pre-reset snapshots older than PRE_RESET_RETENTION_DAYS
+fn prune_expired_resets() {
+    let Ok(dir) = backup_dir() else { return };
+    let expired = list_backups().unwrap_or_default()
+        .into_iter()
+        .filter(|b| b.kind == "pre-reset")
+        .filter(|b| chrono::DateTime::parse_from_rfc3339(&b.created_at).is_ok_and(|at| {
+            clock::now().signed_duration_since(at.with_timezone(&Utc)).num_days() >= PRE_RESET_RETENTION_DAYS
+        }));
+    for backup in expired {
+        if std::fs::remove_file(dir.join(format!("{}.db", backup.id))).is_ok() {
+            logging::log_conversation(None, &format!("Expired pre-reset snapshot deleted: {}", backup.id));
+        }
+    }
+}
+
+/// Delete the oldest backups of a kind beyond `keep`
+fn rotate(kind: &str, keep: usize) {
+    let Ok(dir) = backup_dir() else { return };
+    let stale = list_backups().unwrap_or_default()
+        .into_iter()
+        .filter(|b| b.kind == kind)
+        .skip(keep);
+    for backup in stale {
+        let _ = std::fs::remove_file(dir.join(format!("{}.db", backup.id)));
+    }
+}
+
+/// "<kind>-<YYYYmmdd-HHMMSS>.db" -> Backup (anything else in the folder is ignored)
+fn backup_from_path(path: &Path) -> Option<Backup> {
+    if path.extension()? != "db" {
+        return None;
+    }
+    let id = path.file_stem()?.to_str()?.to_string();
+    let split = id.len().checked_sub(FILE_TIME_LEN)?;
+    let (kind, stamp) = (id.get(..split)?.strip_suffix('-')?, id.get(split..)?);
+    let created_at = NaiveDateTime::parse_from_str(stamp, FILE_TIME_FORMAT).ok()?.and_utc();
+    let size_bytes = std::fs::metadata(path).ok()?.len();
+
+    Some(Backup {
+        kind: kind.to_string(),
+        id,
+        created_at: created_at.to_rfc3339(),
+        size_bytes,
+    })
+}
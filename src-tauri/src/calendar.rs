@@ -0,0 +1,266 @@
+//! Calendar context for Intersect
+//!
+//! Off until the user turns it on. Today's events come from one of:
+//! - "calendar_app": Calendar.app via osascript (macOS asks for Automation permission the first
+//!   time). Recurring events only show on the day of their first occurrence.
+//! - "mcp": a calendar tool on a configured MCP server (`mcp__<server>__<tool>`), called with
+//!   {"date": "YYYY-MM-DD"} and expected to return a JSON list of events
+//!
+//! The day is summarized ("a packed afternoon", the next few events) for the Governor's greeting
+//! and for deep grounding when a message is about scheduling or stress. Events are cached for a
+//! few minutes so greetings and turns don't each re-read the calendar.
+//!
+//! Settings are stored in app_settings as `calendar_context`.
+
+use crate::clock;
+use crate::db;
+use crate::logging;
+use crate::mcp;
+use chrono::{DateTime, Local, Timelike};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const SETTINGS_KEY: &str = "calendar_context";
+pub const CALENDAR_SOURCES: [&str; 2] = ["calendar_app", "mcp"];
+const CACHE_TTL: Duration = Duration::from_secs(600);
+const READ_TIMEOUT_SECS: u64 = 20;
+const MAX_LISTED_EVENTS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CalendarSettings {
+    pub enabled: bool,            // The user's consent; nothing is read while false
+    pub source: String,           // "calendar_app" | "mcp"
+    pub mcp_tool: Option<String>, // Tool name for the "mcp" source
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        Self { enabled: false, source: "calendar_app".to_string(), mcp_tool: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CalendarEvent {
+    pub title: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    #[serde(default)]
+    pub all_day: bool,
+}
+
+// When the events were read, and the events
+type CachedEvents = Option<(Instant, Vec<CalendarEvent>)>;
+
+static CACHE: Lazy<Mutex<CachedEvents>> = Lazy::new(|| Mutex::new(None));
+
+pub fn get_settings() -> CalendarSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_settings(settings: &CalendarSettings) -> Result<(), String> {
+    if !CALENDAR_SOURCES.contains(&settings.source.as_str()) {
+        return Err(format!("Unknown calendar source: {}", settings.source));
+    }
+    let mcp_tool = settings.mcp_tool.as_deref().map(str::trim).filter(|t| !t.is_empty()).map(str::to_string);
+    if settings.source == "mcp" && !mcp_tool.as_deref().is_some_and(|t| t.starts_with(mcp::TOOL_PREFIX)) {
+        return Err("Choose the MCP calendar tool to read events with".to_string());
+    }
+    if settings.enabled && settings.source == "calendar_app" && !cfg!(target_os = "macos") {
+        return Err("Calendar.app is only available on macOS".to_string());
+    }
+    let settings = CalendarSettings { mcp_tool, ..settings.clone() };
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())?;
+    *CACHE.lock().unwrap() = None;
+    Ok(())
+}
+
+// Today's events from every calendar, as JSON
+const CALENDAR_APP_SCRIPT: &str = r#"
+const app = Application('Calendar');
+const start = new Date(); start.setHours(0, 0, 0, 0);
+const end = new Date(start); end.setDate(end.getDate() + 1);
+const events = [];
+app.calendars().forEach(cal => {
+  cal.events.whose({ _and: [{ startDate: { _lessThan: end } }, { endDate: { _greaterThan: start } }] })().forEach(e => {
+    events.push({ title: e.summary(), start: e.startDate().toISOString(), end: e.endDate().toISOString(), all_day: e.alldayEvent() });
+  });
+});
+JSON.stringify(events);
+"#;
+
+async fn read_calendar_app() -> Result<Value, String> {
+    let output = tokio::process::Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", CALENDAR_APP_SCRIPT])
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(READ_TIMEOUT_SECS), output)
+        .await
+        .map_err(|_| "Calendar.app took too long to answer".to_string())?
+        .map_err(|e| format!("Couldn't run osascript: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Calendar.app read failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("Unexpected Calendar.app output: {}", e))
+}
+
+async fn read_mcp(tool: &str) -> Result<Value, String> {
+    let today = clock::now_local().format("%Y-%m-%d").to_string();
+    let text = mcp::call_tool(tool, &json!({ "date": today })).await?;
+    serde_json::from_str(&text).map_err(|_| format!("{} didn't return a JSON list of events", tool))
+}
+
+fn parse_time(value: &Value) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value.as_str()?).ok().map(|t| t.with_timezone(&Local))
+}
+
+/// Events from a JSON list (or {"events": [...]}), accepting the common field spellings
+fn parse_events(value: &Value) -> Vec<CalendarEvent> {
+    let items = value.as_array().or_else(|| value["events"].as_array());
+    items.map(|items| items.iter()
+        .filter_map(|item| {
+            let field = |names: &[&str]| names.iter().map(|n| &item[*n]).find(|v| !v.is_null()).cloned().unwrap_or(Value::Null);
+            Some(CalendarEvent {
+                title: field(&["title", "summary", "name"]).as_str().unwrap_or("(untitled)").to_string(),
+                start: parse_time(&field(&["start", "start_time", "startDate"]))?,
+                end: parse_time(&field(&["end", "end_time", "endDate"]))?,
+                all_day: field(&["all_day", "allDay", "is_all_day"]).as_bool().unwrap_or(false),
+            })
+        })
+        .collect())
+        .unwrap_or_default()
+}
+
+/// Today's events (cached), or an error if the calendar couldn't be read
+pub async fn today_events() -> Result<Vec<CalendarEvent>, String> {
+    let settings = get_settings();
+    if !settings.enabled {
+        return Err("Calendar context is off".to_string());
+    }
+    if let Some((read_at, events)) = CACHE.lock().unwrap().as_ref() {
+        if read_at.elapsed() < CACHE_TTL {
+            return Ok(events.clone());
+        }
+    }
+    let raw = match (settings.source.as_str(), settings.mcp_tool.as_deref()) {
+        ("mcp", Some(tool)) => read_mcp(tool).await?,
+        _ => read_calendar_app().await?,
+    };
+    let mut events = parse_events(&raw);
+    events.sort_by_key(|e| e.start);
+    *CACHE.lock().unwrap() = Some((Instant::now(), events.clone()));
+    Ok(events)
+}
+
+fn minutes_between(start: DateTime<Local>, end: DateTime<Local>) -> i64 {
+    (end - start).num_minutes().max(0)
+}
+
+/// How the rest of the day looks, or None with nothing on the calendar today
+pub fn describe_day(events: &[CalendarEvent], now: DateTime<Local>) -> Option<String> {
+    let all_day: Vec<&str> = events.iter().filter(|e| e.all_day).map(|e| e.title.as_str()).collect();
+    let remaining: Vec<&CalendarEvent> = events.iter().filter(|e| !e.all_day && e.end > now).collect();
+    if remaining.is_empty() && all_day.is_empty() {
+        return None;
+    }
+
+    let booked: i64 = remaining.iter().map(|e| minutes_between(e.start.max(now), e.end)).sum();
+    let load = if booked >= 240 || remaining.len() >= 5 {
+        "packed"
+    } else if booked >= 120 || remaining.len() >= 3 {
+        "busy"
+    } else {
+        "light"
+    };
+    let rest_of = match now.hour() {
+        0..=11 => "day",
+        12..=16 => "afternoon",
+        _ => "evening",
+    };
+
+    let mut lines = vec![if remaining.is_empty() {
+        format!("Nothing scheduled for the rest of the {}.", rest_of)
+    } else {
+        format!(
+            "A {} {} ahead -- {} event{} left, about {}h{:02} booked.",
+            load, rest_of, remaining.len(), if remaining.len() == 1 { "" } else { "s" }, booked / 60, booked % 60
+        )
+    }];
+    // Back-to-back stretches are worth naming
+    let back_to_back = remaining.windows(2).filter(|pair| minutes_between(pair[0].end, pair[1].start) < 10).count();
+    if back_to_back >= 2 {
+        lines.push(format!("{} meetings run back-to-back.", back_to_back + 1));
+    }
+    for event in remaining.iter().take(MAX_LISTED_EVENTS) {
+        lines.push(format!("- {}-{} {}", event.start.format("%H:%M"), event.end.format("%H:%M"), event.title));
+    }
+    if !all_day.is_empty() {
+        lines.push(format!("All day: {}", all_day.join(", ")));
+    }
+    Some(lines.join("\n"))
+}
+
+/// Prompt context for today, or None when calendar context is off, unreadable or empty
+pub async fn day_context() -> Option<String> {
+    if !get_settings().enabled {
+        return None;
+    }
+    let events = match today_events().await {
+        Ok(events) => events,
+        Err(e) => {
+            logging::log_error(None, &format!("Calendar read failed: {}", e));
+            return None;
+        }
+    };
+    describe_day(&events, clock::now_local()).map(|day| format!(
+        "CALENDAR TODAY (shared by the user; use it only if it helps, don't recite their schedule):\n{}",
+        day
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn event(title: &str, start: (u32, u32), end: (u32, u32)) -> CalendarEvent {
+        let at = |(h, m): (u32, u32)| Local.with_ymd_and_hms(2026, 3, 10, h, m, 0).unwrap();
+        CalendarEvent { title: title.to_string(), start: at(start), end: at(end), all_day: false }
+    }
+
+    #[test]
+    fn describes_a_packed_afternoon() {
+        let events = vec![
+            event("Standup", (9, 0), (9, 15)),
+            event("Design review", (13, 0), (14, 30)),
+            event("1:1", (14, 30), (15, 0)),
+            event("Planning", (15, 0), (17, 0)),
+        ];
+        let now = Local.with_ymd_and_hms(2026, 3, 10, 12, 30, 0).unwrap();
+        let day = describe_day(&events, now).unwrap();
+        assert!(day.starts_with("A packed afternoon ahead -- 3 events left, about 4h00"), "{}", day);
+        assert!(day.contains("3 meetings run back-to-back."));
+        assert!(!day.contains("Standup"));
+
+        let evening = Local.with_ymd_and_hms(2026, 3, 10, 18, 0, 0).unwrap();
+        assert_eq!(describe_day(&events, evening), None);
+    }
+
+    #[test]
+    fn parses_common_event_shapes() {
+        let raw = json!({ "events": [
+            { "summary": "Dentist", "startDate": "2026-03-10T10:00:00Z", "endDate": "2026-03-10T11:00:00Z" },
+            { "title": "No times" },
+        ]});
+        let events = parse_events(&raw);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Dentist");
+    }
+}
@@ -0,0 +1,198 @@
+//! Quick capture for Intersect
+//!
+//! A global hotkey opens a small "capture" window instead of the full app. Whatever is typed
+//! there goes to a dedicated Inbox conversation (created on first use), either:
+//! - "send": through send_message in the background, so the agents reply by the time you look
+//! - "store": saved as a message with no reply, for later
+//!
+//! Off by default, so no global hotkey is registered until the user turns it on.
+//! Settings are stored in app_settings as `quick_capture`.
+
+use crate::db::{self, Message};
+use crate::logging;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tauri::{Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+const SETTINGS_KEY: &str = "quick_capture";
+pub const CAPTURE_MODES: [&str; 2] = ["send", "store"];
+const CAPTURE_WINDOW: &str = "capture";
+const INBOX_TITLE: &str = "Inbox";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CaptureSettings {
+    pub enabled: bool,
+    pub hotkey: String,                        // e.g. "CmdOrCtrl+Alt+Space"
+    pub mode: String,                          // "send" | "store"
+    pub inbox_conversation_id: Option<String>, // Created on first capture
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "CmdOrCtrl+Alt+Space".to_string(),
+            mode: "send".to_string(),
+            inbox_conversation_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureResult {
+    pub conversation_id: String,
+    pub mode: String,
+    pub message_id: Option<String>, // Set in "store" mode; "send" saves the message once the turn starts
+}
+
+pub fn get_settings() -> CaptureSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &CaptureSettings) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// Validate, re-register the hotkey and save. The Inbox stays the one already in use.
+pub fn set_settings(app_handle: &tauri::AppHandle, settings: &CaptureSettings) -> Result<CaptureSettings, String> {
+    if !CAPTURE_MODES.contains(&settings.mode.as_str()) {
+        return Err(format!("Unknown capture mode: {}", settings.mode));
+    }
+    let hotkey = settings.hotkey.trim().to_string();
+    Shortcut::from_str(&hotkey).map_err(|e| format!("Invalid hotkey \"{}\": {}", hotkey, e))?;
+
+    let settings = CaptureSettings {
+        hotkey,
+        inbox_conversation_id: get_settings().inbox_conversation_id,
+        ..settings.clone()
+    };
+    register(app_handle, &settings)?;
+    save_settings(&settings)?;
+    Ok(settings)
+}
+
+/// Register the hotkey at launch
+pub fn start(app_handle: &tauri::AppHandle) {
+    if let Err(e) = register(app_handle, &get_settings()) {
+        logging::log_error(None, &format!("Quick capture hotkey not registered: {}", e));
+    }
+}
+
+fn register(app_handle: &tauri::AppHandle, settings: &CaptureSettings) -> Result<(), String> {
+    let shortcuts = app_handle.global_shortcut();
+    shortcuts.unregister_all().map_err(|e| e.to_string())?;
+    if !settings.enabled {
+        return Ok(());
+    }
+    shortcuts.on_shortcut(settings.hotkey.as_str(), |app_handle, _, event| {
+        if event.state == ShortcutState::Pressed {
+            toggle_window(app_handle);
+        }
+    }).map_err(|e| e.to_string())
+}
+
+/// Show the capture window (creating it on first use), or hide it if it's already up
+fn toggle_window(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window(CAPTURE_WINDOW) {
+        if window.is_visible().unwrap_or(false) {
+            let _ = window.hide();
+        } else {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        return;
+    }
+    let built = WebviewWindowBuilder::new(app_handle, CAPTURE_WINDOW, WebviewUrl::App("index.html#/capture".into()))
+        .title("Quick Capture")
+        .inner_size(560.0, 120.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build();
+    if let Err(e) = built {
+        logging::log_error(None, &format!("Couldn't open the capture window: {}", e));
+    }
+}
+
+/// The Inbox conversation, created (and remembered) if it doesn't exist yet
+fn inbox_conversation() -> Result<String, String> {
+    let mut settings = get_settings();
+    if let Some(id) = &settings.inbox_conversation_id {
+        if db::get_conversation(id).map_err(|e| e.to_string())?.is_some() {
+            return Ok(id.clone());
+        }
+    }
+    let conversation = crate::create_conversation(false)?;
+    db::set_conversation_title(&conversation.id, INBOX_TITLE).map_err(|e| e.to_string())?;
+    settings.inbox_conversation_id = Some(conversation.id.clone());
+    save_settings(&settings)?;
+    logging::log_conversation(Some(&conversation.id), "Created the quick capture Inbox");
+    Ok(conversation.id)
+}
+
+/// Add captured text to the Inbox and put the capture window away
+pub fn capture(app_handle: &tauri::AppHandle, text: &str) -> Result<CaptureResult, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Nothing to capture".to_string());
+    }
+    let settings = get_settings();
+    let conversation_id = inbox_conversation()?;
+
+    let message_id = if settings.mode == "store" {
+        let message = Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            conversation_id: conversation_id.clone(),
+            role: "user".to_string(),
+            content: text.to_string(),
+            response_type: None,
+            references_message_id: None,
+            timestamp: Utc::now().to_rfc3339(),
+            attachments: Vec::new(),
+            starred: false,
+        };
+        db::save_message(&message).map_err(|e| e.to_string())?;
+        crate::annotate_message(&message.id, "captured", serde_json::json!(true));
+        Some(message.id)
+    } else {
+        let app_handle = app_handle.clone();
+        let (conversation_id, text) = (conversation_id.clone(), text.to_string());
+        tauri::async_runtime::spawn(async move {
+            match crate::send_message(app_handle.clone(), conversation_id.clone(), text, None, Vec::new(), None, None, None).await {
+                Ok(_) => {
+                    let _ = app_handle.emit("capture-processed", &conversation_id);
+                }
+                Err(e) => logging::log_error(Some(&conversation_id), &format!("Quick capture send failed: {}", e)),
+            }
+        });
+        None
+    };
+    logging::log_conversation(Some(&conversation_id), &format!("Quick capture ({})", settings.mode));
+
+    if let Some(window) = app_handle.get_webview_window(CAPTURE_WINDOW) {
+        let _ = window.hide();
+    }
+    Ok(CaptureResult { conversation_id, mode: settings.mode, message_id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_hotkey_parses() {
+        assert!(Shortcut::from_str(&CaptureSettings::default().hotkey).is_ok());
+        assert!(Shortcut::from_str("CmdOrCtrl+Nope").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Time and randomness seams for Intersect
+//!
+//! Logic that depends on "now" or on dice rolls goes through this module instead of
+//! calling the system clock / thread RNG directly, so it can be pinned:
+//! - `now()` / `now_local()` read the installed `Clock` (system clock by default)
+//! - `rng()` hands out an RNG that is seeded deterministically in fixture mode
+//! - Fixture mode is enabled with `set_fixture` or the INTERSECT_FIXTURE_NOW (RFC 3339)
+//!   and INTERSECT_FIXTURE_SEED env vars, for deterministic integration runs
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The real wall clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock frozen at one instant (fixture mode)
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+static CLOCK: Lazy<RwLock<Arc<dyn Clock>>> = Lazy::new(|| RwLock::new(Arc::new(SystemClock)));
+static RNG_SEED: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+// Each seeded rng() call gets its own stream, so repeated calls differ but stay reproducible
+static RNG_DRAWS: AtomicU64 = AtomicU64::new(0);
+
+pub fn now() -> DateTime<Utc> {
+    CLOCK.read().unwrap().now()
+}
+
+pub fn now_local() -> DateTime<Local> {
+    now().with_timezone(&Local)
+}
+
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    *CLOCK.write().unwrap() = clock;
+}
+
+/// RNG for routing and debate decisions (seeded in fixture mode, OS-seeded otherwise)
+pub fn rng() -> StdRng {
+    match *RNG_SEED.lock().unwrap() {
+        Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(RNG_DRAWS.fetch_add(1, Ordering::SeqCst))),
+        None => StdRng::from_rng(&mut rand::rng()),
+    }
+}
+
+/// Pin the clock and/or RNG seed (None leaves that seam on its production source)
+pub fn set_fixture(now: Option<DateTime<Utc>>, seed: Option<u64>) {
+    if let Some(instant) = now {
+        set_clock(Arc::new(FixedClock(instant)));
+    }
+    *RNG_SEED.lock().unwrap() = seed;
+    RNG_DRAWS.store(0, Ordering::SeqCst);
+}
+
+/// Enable fixture mode from INTERSECT_FIXTURE_NOW / INTERSECT_FIXTURE_SEED; returns true if either was set
+pub fn init_from_env() -> bool {
+    let fixed_now = std::env::var("INTERSECT_FIXTURE_NOW").ok()
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+    let seed = std::env::var("INTERSECT_FIXTURE_SEED").ok()
+        .and_then(|v| v.parse::<u64>().ok());
+
+    if fixed_now.is_none() && seed.is_none() {
+        return false;
+    }
+    set_fixture(fixed_now, seed);
+    true
+}
+
+pub fn local_midnight(date: NaiveDate) -> Option<DateTime<Utc>> {
+    Local.from_local_datetime(&date.and_time(NaiveTime::MIN)).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// (since, until); None = unbounded
+pub type RangeBounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// [since, until) for a history range: "week", "month", "year", "all", or "YYYY-MM-DD..YYYY-MM-DD" (inclusive dates)
+pub fn parse_range(range: &str, today: NaiveDate) -> Result<RangeBounds, String> {
+    let days_back = |days: i64| Ok((local_midnight(today - Duration::days(days - 1)), None));
+    match range.trim() {
+        "week" => days_back(7),
+        "month" => days_back(30),
+        "year" => days_back(365),
+        "all" | "" => Ok((None, None)),
+        custom => {
+            let (from, to) = custom.split_once("..").ok_or(format!("Unknown range: {}", custom))?;
+            let parse = |d: &str| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", d));
+            let (from, to) = (parse(from)?, parse(to)?);
+            if to < from {
+                return Err("The range ends before it starts".to_string());
+            }
+            Ok((local_midnight(from), local_midnight(to + Duration::days(1))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        assert_eq!(parse_range("all", today).unwrap(), (None, None));
+        let (since, until) = parse_range("week", today).unwrap();
+        assert_eq!(since, local_midnight(NaiveDate::from_ymd_opt(2026, 3, 4).unwrap()));
+        assert_eq!(until, None);
+        let (since, until) = parse_range("2026-03-01..2026-03-01", today).unwrap();
+        assert_eq!(until.unwrap() - since.unwrap(), Duration::days(1));
+        assert!(parse_range("2026-03-02..2026-03-01", today).is_err());
+        assert!(parse_range("fortnight", today).is_err());
+    }
+}
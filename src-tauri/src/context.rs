@@ -0,0 +1,229 @@
+//! Conversation history for agent calls
+//!
+//! Instead of a fixed number of recent messages, agents get as much recent history as fits a
+//! token budget. When older messages don't fit, the conversation's rolling summary stands in for
+//! them (its tokens come out of the same budget). The newest message is always kept, clipped if
+//! it alone is over budget. Tokens are counted with `tokenizer` (Claude estimate).
+//!
+//! Settings are stored in app_settings as `context_window`.
+
+use crate::db::{self, Message};
+use crate::openai::ChatMessage;
+use crate::tokenizer;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "context_window";
+pub const MIN_HISTORY_BUDGET: usize = 500;
+pub const MAX_HISTORY_BUDGET: usize = 100_000;
+/// Role and formatting overhead per message
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Messages fetched first by `fetch_history`; the window doubles until the budget is covered
+const INITIAL_FETCH: usize = 40;
+const MAX_FETCH: usize = 5000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ContextSettings {
+    pub history_token_budget: usize, // Tokens of history sent with each agent call
+    pub use_summary: bool,           // Put the rolling summary in place of history that didn't fit
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self { history_token_budget: 3000, use_summary: true }
+    }
+}
+
+pub fn get_settings() -> ContextSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_settings(settings: &ContextSettings) -> Result<(), String> {
+    if !(MIN_HISTORY_BUDGET..=MAX_HISTORY_BUDGET).contains(&settings.history_token_budget) {
+        return Err(format!(
+            "History budget must be between {} and {} tokens", MIN_HISTORY_BUDGET, MAX_HISTORY_BUDGET
+        ));
+    }
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+fn message_tokens(message: &Message) -> usize {
+    tokenizer::count(&message.content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Enough recent messages to fill `budget` tokens of history, for `build` to trim. `fetch(limit)`
+/// returns the newest `limit` messages; the window grows until its turns alone are over the budget
+/// or the conversation runs out.
+pub fn fetch_history<E>(budget: usize, mut fetch: impl FnMut(usize) -> Result<Vec<Message>, E>) -> Result<Vec<Message>, E> {
+    let mut limit = INITIAL_FETCH;
+    loop {
+        let messages = fetch(limit)?;
+        let tokens: usize = messages.iter().filter(|m| m.role != "system").map(message_tokens).sum();
+        if messages.len() < limit || tokens > budget || limit >= MAX_FETCH {
+            return Ok(messages);
+        }
+        limit *= 2;
+    }
+}
+
+/// The history that goes with an agent call
+#[derive(Debug, Clone)]
+pub struct ContextWindow {
+    pub messages: Vec<ChatMessage>, // Oldest first, roles mapped to user/assistant
+    pub summary: Option<String>,    // Rolling summary standing in for the omitted messages
+    pub omitted: usize,             // Older messages left out
+    pub tokens: usize,              // Estimated tokens of messages + summary
+}
+
+/// Fit the newest messages of `history` into `budget` tokens. `summary` is only asked for when
+/// something has to be left out.
+pub fn build(history: &[Message], budget: usize, summary: impl FnOnce() -> Option<String>) -> ContextWindow {
+    // Governor notices in the transcript are for the user, not conversation turns
+    let turns: Vec<&Message> = history.iter().filter(|m| m.role != "system").collect();
+    let total: usize = turns.iter().map(|m| message_tokens(m)).sum();
+
+    // The summary only earns its place when history is cut, and never takes more than half
+    let summary = if total > budget {
+        summary()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty() && tokenizer::count(s) <= budget / 2)
+    } else {
+        None
+    };
+    let summary_tokens = summary.as_deref().map(tokenizer::count).unwrap_or(0);
+
+    let available = budget - summary_tokens;
+    let mut used = 0;
+    let mut start = turns.len();
+    while start > 0 {
+        let cost = message_tokens(turns[start - 1]);
+        if used + cost > available {
+            break;
+        }
+        used += cost;
+        start -= 1;
+    }
+
+    let mut messages: Vec<ChatMessage> = turns[start..].iter().map(|m| chat_message(m, None)).collect();
+    // Even an oversized newest message goes in, clipped to what's left
+    if messages.is_empty() {
+        if let Some(last) = turns.last() {
+            // About three characters per token keeps the clipped text safely under
+            let room = available.saturating_sub(MESSAGE_OVERHEAD_TOKENS) * 3;
+            messages.push(chat_message(last, Some(room)));
+            used = available;
+            start = turns.len() - 1;
+        }
+    }
+
+    ContextWindow {
+        messages,
+        summary: summary.filter(|_| start > 0),
+        omitted: start,
+        tokens: used + summary_tokens,
+    }
+}
+
+/// What the next turn in a conversation would send, so the UI can warn before it's sent
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenEstimate {
+    pub conversation_tokens: usize, // The whole transcript
+    pub context_tokens: usize,      // History each agent call would carry (summary included)
+    pub draft_tokens: usize,        // The message being typed, counted for the pinned model if any
+    pub budget: usize,
+    pub included_messages: usize,
+    pub omitted_messages: usize,    // Older messages that won't be sent
+    pub truncated: bool,            // Something is left out or clipped
+}
+
+/// Token estimate for the next turn in a conversation, with an optional draft message
+pub fn estimate(conversation_id: &str, draft: Option<&str>) -> Result<TokenEstimate, String> {
+    let settings = get_settings();
+    let history = db::get_conversation_messages(conversation_id).map_err(|e| e.to_string())?;
+    let pinned_model = db::get_conversation(conversation_id)
+        .map_err(|e| e.to_string())?
+        .and_then(|c| c.model_override);
+    let conversation_tokens = history.iter()
+        .filter(|m| m.role != "system")
+        .map(message_tokens)
+        .sum();
+    let window = build(&history, settings.history_token_budget, || {
+        settings.use_summary
+            .then(|| db::get_conversation_summary(conversation_id).ok().flatten())
+            .flatten()
+            .map(|s| s.summary)
+    });
+    Ok(TokenEstimate {
+        conversation_tokens,
+        context_tokens: window.tokens,
+        draft_tokens: match (draft, pinned_model.as_deref()) {
+            (Some(draft), Some(model)) => tokenizer::count_for_model(draft, model),
+            (Some(draft), None) => tokenizer::count(draft),
+            (None, _) => 0,
+        },
+        budget: settings.history_token_budget,
+        included_messages: window.messages.len(),
+        omitted_messages: window.omitted,
+        truncated: conversation_tokens > settings.history_token_budget,
+    })
+}
+
+fn chat_message(message: &Message, max_chars: Option<usize>) -> ChatMessage {
+    let role = if message.role == "user" { "user" } else { "assistant" };
+    let content = match max_chars {
+        Some(max) if message.content.chars().count() > max => {
+            format!("{}…", message.content.chars().take(max).collect::<String>())
+        }
+        _ => message.content.clone(),
+    };
+    ChatMessage { role: role.to_string(), content }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            conversation_id: "c".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            response_type: None,
+            references_message_id: None,
+            timestamp: "2026-03-10T12:00:00+00:00".to_string(),
+            attachments: Vec::new(),
+            starred: false,
+        }
+    }
+
+    #[test]
+    fn short_history_fits_without_summary() {
+        let history = vec![message("user", "hi"), message("system", "notice"), message("logic", "hello")];
+        let window = build(&history, 1000, || panic!("summary not needed"));
+        assert_eq!(window.messages.len(), 2);
+        assert_eq!(window.messages[1].role, "assistant");
+        assert_eq!(window.omitted, 0);
+        assert!(window.summary.is_none());
+    }
+
+    #[test]
+    fn long_history_keeps_the_newest_and_summarizes_the_rest() {
+        let long = "word ".repeat(400); // ~440 tokens
+        let history: Vec<Message> = (0..10).map(|i| message(if i % 2 == 0 { "user" } else { "psyche" }, &long)).collect();
+        let window = build(&history, 1200, || Some("They talked about work.".to_string()));
+        assert_eq!(window.messages.len(), 2);
+        assert_eq!(window.omitted, 8);
+        assert_eq!(window.summary.as_deref(), Some("They talked about work."));
+        assert!(window.tokens <= 1200);
+
+        let window = build(&history[..1], 100, || None);
+        assert_eq!(window.messages.len(), 1);
+        assert!(window.messages[0].content.chars().count() < long.len());
+    }
+}
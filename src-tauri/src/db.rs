@@ -1,19 +1,41 @@
-use chrono::Utc;
-use rusqlite::{Connection, Result, params, OptionalExtension};
+use crate::clock;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OpenFlags, Result, params, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
 use once_cell::sync::Lazy;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
 use tauri::Manager;
 
-// Database connection singleton
-static DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+// Read-write connections. Each `with_connection` call runs in its own IMMEDIATE transaction, so
+// read-modify-write steps (sequence numbers, counters) stay atomic and concurrent writers queue in
+// SQLite (up to WRITER_BUSY_TIMEOUT_MS) rather than behind a process-wide lock; reads go to READERS.
+// In-memory databases get a single connection, since each connection would be its own database.
+static WRITERS: Lazy<RwLock<Option<Pool<SqliteConnectionManager>>>> = Lazy::new(|| RwLock::new(None));
+const WRITER_POOL_SIZE: u32 = 4;
+
+// Read-only connections (WAL mode lets them read while a background task is writing).
+// None for in-memory databases, whose reads stay on the write connection.
+static READERS: Lazy<RwLock<Option<Pool<SqliteConnectionManager>>>> = Lazy::new(|| RwLock::new(None));
+const READER_POOL_SIZE: u32 = 4;
+/// How long a connection waits on a lock held by another before giving up
+const BUSY_TIMEOUT_MS: u64 = 5000;
+/// Writers wait longer: bulk writes (archive import, sync, resets) hold the write lock for a while
+const WRITER_BUSY_TIMEOUT_MS: u64 = 30_000;
+
+// Conversations that received messages during this app session (never recovery candidates)
+static WRITTEN_THIS_SESSION: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserProfile {
     pub id: i64,
     pub api_key: Option<String>,
     pub anthropic_key: Option<String>,
+    pub gemini_key: Option<String>,
+    pub agent_provider: String,  // Default provider for agent responses ("openai" | "anthropic" | "ollama" | "gemini")
     pub instinct_weight: f64,
     pub logic_weight: f64,
     pub psyche_weight: f64,
@@ -27,11 +49,13 @@ pub struct Conversation {
     pub id: String,
     pub title: Option<String>,
     pub summary: Option<String>,
-    pub limbo_summary: Option<String>,
     pub processed: bool,
     pub is_disco: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub model_override: Option<String>,  // Agent model for this conversation (None = global default)
+    pub parent_conversation_id: Option<String>,  // Set when this conversation is a branch of another
+    pub branch_point_message_id: Option<String>, // The parent's user message that was edited to fork it
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,6 +67,24 @@ pub struct Message {
     pub response_type: Option<String>,
     pub references_message_id: Option<String>,
     pub timestamp: String,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>, // Loaded for transcript reads; empty elsewhere
+    #[serde(default)]
+    pub starred: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: String,
+    pub conversation_id: String,
+    pub message_id: Option<String>,   // None until the message it was attached to is sent
+    pub kind: String,                 // "image"
+    pub mime_type: String,
+    pub file_name: String,            // Name the user attached it as
+    pub path: String,                 // Stored copy under the app data dir
+    pub size_bytes: i64,
+    pub description: Option<String>,  // Vision model's description, reused on later turns
+    pub created_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -120,6 +162,7 @@ pub struct RecurringTheme {
     pub frequency: i64,
     pub last_mentioned: String,
     pub related_conversations: Option<String>, // JSON array of conversation IDs
+    pub muted: bool,                           // Still tracked, but hidden from greetings/reports/grounding
 }
 
 // ============ Multi-Profile System ============
@@ -151,7 +194,386 @@ fn get_db_path(app_handle: &tauri::AppHandle) -> PathBuf {
 
 pub fn init_database(app_handle: &tauri::AppHandle) -> Result<()> {
     let db_path = get_db_path(app_handle);
-    let conn = Connection::open(&db_path)?;
+    #[cfg(feature = "encryption")]
+    prepare_encryption(&db_path)?;
+    init_database_at(&db_path)
+}
+
+/// Open (or create) the database at a path and run schema setup and migrations.
+/// Pass ":memory:" for a throwaway in-memory database (tests, fixture runs).
+pub fn init_database_at(db_path: &Path) -> Result<()> {
+    let (writers, readers) = open_pools(db_path)?;
+    let mut db = WRITERS.write().unwrap();
+    *READERS.write().unwrap() = readers;
+    *db = Some(writers);
+    Ok(())
+}
+
+/// Open the writer pool, set up and migrate the schema, then open the reader pool
+fn open_pools(db_path: &Path) -> Result<(Pool<SqliteConnectionManager>, Option<Pool<SqliteConnectionManager>>)> {
+    let writers = open_writer_pool(db_path)?;
+    let conn = writers.get().map_err(pool_error)?;
+    // WAL so readers never wait on writers (it's stored in the file, so once is enough)
+    if conn.path().is_some_and(|p| !p.is_empty()) {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+    }
+    init_schema(&conn)?;
+    let readers = open_reader_pool(&conn)?;
+    drop(conn);
+    Ok((writers, readers))
+}
+
+/// Per-connection settings for writers. NORMAL sync is durable across app crashes in WAL mode;
+/// recursive triggers keep the full-text index right for INSERT OR REPLACE (see init_schema).
+fn configure_writer(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(std::time::Duration::from_millis(WRITER_BUSY_TIMEOUT_MS))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.pragma_update(None, "recursive_triggers", "ON")
+}
+
+fn open_writer_pool(db_path: &Path) -> Result<Pool<SqliteConnectionManager>> {
+    let in_memory = db_path == Path::new(":memory:");
+    let manager = if in_memory {
+        SqliteConnectionManager::memory()
+    } else {
+        SqliteConnectionManager::file(db_path)
+            .with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+    };
+    let manager = manager.with_init(|conn| {
+        #[cfg(feature = "encryption")]
+        if let Some(key) = DB_KEY.get() {
+            conn.pragma_update(None, "key", key)?;
+        }
+        configure_writer(conn)
+    });
+    let builder = Pool::builder().max_size(if in_memory { 1 } else { WRITER_POOL_SIZE });
+    // The in-memory database lives only as long as its one connection, so it's never recycled
+    let builder = if in_memory { builder.idle_timeout(None).max_lifetime(None) } else { builder };
+    builder.build(manager).map_err(pool_error)
+}
+
+fn pool_error(e: r2d2::Error) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN), Some(e.to_string()))
+}
+
+/// Read-only pool on the writer's file (None for in-memory databases)
+fn open_reader_pool(writer: &Connection) -> Result<Option<Pool<SqliteConnectionManager>>> {
+    let Some(path) = writer.path().filter(|p| !p.is_empty()).map(PathBuf::from) else {
+        return Ok(None);
+    };
+    let manager = SqliteConnectionManager::file(path)
+        .with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .with_init(|conn| {
+            #[cfg(feature = "encryption")]
+            if let Some(key) = DB_KEY.get() {
+                conn.pragma_update(None, "key", key)?;
+            }
+            conn.busy_timeout(std::time::Duration::from_millis(BUSY_TIMEOUT_MS))
+        });
+    let pool = Pool::builder()
+        .max_size(READER_POOL_SIZE)
+        .build(manager)
+        .map_err(pool_error)?;
+    Ok(Some(pool))
+}
+
+// ============ Encryption at Rest ============
+// With the `encryption` feature the database is a SQLCipher file keyed by a passphrase kept in the
+// Keychain (see keychain.rs). Without it, connections open plaintext exactly as before.
+
+#[cfg(feature = "encryption")]
+static DB_KEY: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+#[cfg(feature = "encryption")]
+const PLAINTEXT_HEADER: &[u8] = b"SQLite format 3\0";
+
+#[cfg(feature = "encryption")]
+fn encryption_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_AUTH), Some(message))
+}
+
+/// Load the passphrase and encrypt a plaintext database left by a build without encryption
+#[cfg(feature = "encryption")]
+fn prepare_encryption(db_path: &Path) -> Result<()> {
+    let key = crate::keychain::get_or_create_db_key().map_err(encryption_error)?;
+    let _ = DB_KEY.set(key);
+    encrypt_if_plaintext(db_path)
+}
+
+/// Re-write a plaintext database as an encrypted one in place (no-op if missing or already encrypted),
+/// along with any plaintext snapshots in backups/. The plaintext copy is only replaced once the
+/// encrypted export has fully succeeded; its -wal/-shm/-journal sidecars are checkpointed and removed.
+#[cfg(feature = "encryption")]
+fn encrypt_if_plaintext(db_path: &Path) -> Result<()> {
+    let Some(key) = DB_KEY.get() else { return Ok(()) };
+    encrypt_file(db_path, key)?;
+    let backups = db_path.parent().map(|dir| dir.join("backups"));
+    let snapshots = backups.and_then(|dir| std::fs::read_dir(dir).ok()).into_iter().flatten();
+    for path in snapshots.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == "db") {
+            encrypt_file(&path, key)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+fn encrypt_file(path: &Path, key: &str) -> Result<()> {
+    use std::io::Read;
+    
+    let mut header = [0u8; 16];
+    let is_plaintext = std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| header == PLAINTEXT_HEADER)
+        .unwrap_or(false);
+    if !is_plaintext {
+        return Ok(());
+    }
+    
+    let encrypted_path = path.with_extension("db.encrypting");
+    let _ = std::fs::remove_file(&encrypted_path);
+    {
+        let plain = Connection::open(path)?;
+        // Fold the write-ahead log into the file so nothing is left only in a plaintext sidecar
+        plain.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        plain.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            params![encrypted_path.to_string_lossy(), key]
+        )?;
+        plain.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        plain.execute("DETACH DATABASE encrypted", [])?;
+    }
+    std::fs::rename(&encrypted_path, path)
+        .map_err(|e| encryption_error(format!("Failed to replace plaintext database: {}", e)))?;
+    // Stale plaintext sidecars would leak data, and a WAL would be replayed into the encrypted file
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let _ = std::fs::remove_file(sidecar);
+    }
+    Ok(())
+}
+
+// ============ Schema Migrations ============
+// Column changes and data rewrites are numbered migrations, applied in order and recorded in
+// schema_version, each in its own transaction. Before any run on an existing database file, a
+// snapshot goes to backups/ next to it. New tables still go in init_schema (CREATE TABLE IF NOT
+// EXISTS). Migration 1 folds in the column checks that predate versioning, so it tolerates
+// databases that already have any of those columns.
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "Columns added before schema versioning", apply: migrate_legacy_columns },
+    Migration { version: 2, description: "Move limbo summaries into limbo_entries", apply: migrate_limbo_entries },
+    Migration { version: 3, description: "Queued messages keep what was sent with them", apply: migrate_pending_message_context },
+    Migration { version: 4, description: "Queued messages keep the message they quote-reply to", apply: migrate_pending_message_reply_to },
+    Migration { version: 5, description: "Queued messages remember they were dictated", apply: migrate_pending_message_voice },
+];
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+        params![column],
+        |row| Ok(row.get::<_, i64>(0)? > 0)
+    )
+}
+
+/// Add the (name, declaration) columns a table doesn't have yet; returns whether any were added
+fn add_missing_columns(conn: &Connection, table: &str, columns: &[(&str, &str)]) -> Result<bool> {
+    let mut added = false;
+    for (name, declaration) in columns {
+        if !has_column(conn, table, name)? {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, declaration), [])?;
+            added = true;
+        }
+    }
+    Ok(added)
+}
+
+/// The latest migration applied (0 for a database that predates versioning)
+fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+}
+
+/// Migrations not applied yet (creates the version table on first run)
+fn pending_migrations(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    let current = schema_version(conn)?;
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+}
+
+/// Snapshot the database into `backup_dir` if any migration is pending (None = nothing worth
+/// keeping yet). Runs before schema setup touches the file, so the copy is the untouched original.
+fn snapshot_before_migrations(conn: &Connection, backup_dir: Option<&Path>) -> Result<()> {
+    let Some(dir) = backup_dir else { return Ok(()) };
+    if pending_migrations(conn)?.is_empty() {
+        return Ok(());
+    }
+    // No migration runs without a copy of the database to go back to
+    std::fs::create_dir_all(dir).map_err(|e| migration_error(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let path = dir.join(format!("pre-migration-{}.db", Utc::now().format("%Y%m%d-%H%M%S")));
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+    Ok(())
+}
+
+/// Apply pending migrations in order
+fn run_migrations(conn: &Connection) -> Result<()> {
+    for migration in pending_migrations(conn)? {
+        let tx = conn.unchecked_transaction()?;
+        (migration.apply)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.description, Utc::now().to_rfc3339()]
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn migration_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN), Some(message))
+}
+
+fn migrate_legacy_columns(conn: &Connection) -> Result<()> {
+    add_missing_columns(conn, "user_profile", &[
+        ("anthropic_key", "TEXT"),
+        ("gemini_key", "TEXT"),
+        ("agent_provider", "TEXT DEFAULT 'openai'"),
+    ])?;
+    add_missing_columns(conn, "persona_profiles", &[
+        ("message_count", "INTEGER DEFAULT 0"),
+        ("journey_sessions_completed", "INTEGER DEFAULT 0"),
+    ])?;
+    
+    // Points default to 4/4/3 (total 11); existing profiles get theirs from their weights, clamped to 2-6
+    let added_points = add_missing_columns(conn, "persona_profiles", &[
+        ("instinct_points", "INTEGER DEFAULT 4"),
+        ("logic_points", "INTEGER DEFAULT 4"),
+        ("psyche_points", "INTEGER DEFAULT 3"),
+    ])?;
+    if added_points {
+        conn.execute(
+            "UPDATE persona_profiles SET instinct_points = CAST(ROUND(instinct_weight * 11) AS INTEGER), logic_points = CAST(ROUND(logic_weight * 11) AS INTEGER), psyche_points = CAST(ROUND(psyche_weight * 11) AS INTEGER)",
+            []
+        )?;
+        conn.execute(
+            "UPDATE persona_profiles SET 
+                instinct_points = MAX(2, MIN(6, instinct_points)),
+                logic_points = MAX(2, MIN(6, logic_points)),
+                psyche_points = MAX(2, MIN(6, psyche_points))",
+            []
+        )?;
+    }
+    
+    add_missing_columns(conn, "conversations", &[
+        ("limbo_summary", "TEXT"),
+        ("processed", "INTEGER DEFAULT 0"),
+        ("is_disco", "INTEGER DEFAULT 0"),          // Conversation-level disco mode
+        ("persona_profile_id", "TEXT"),             // Persona active when it started
+        ("recap", "TEXT"),                          // Cached "previously" recap for reopened conversations
+        ("recap_message_count", "INTEGER"),
+        ("model_override", "TEXT"),                 // Agent model pinned on the conversation
+        ("active_agents", "TEXT"),                  // JSON array of agents; NULL = all agents
+        ("parent_conversation_id", "TEXT"),         // Branches forked by editing an earlier user message
+        ("branch_point_message_id", "TEXT"),
+        ("is_journal", "INTEGER DEFAULT 0"),        // Daily journal conversations
+        ("extraction_watermark", "INTEGER"),        // Seq of the last message run through memory extraction
+    ])?;
+    add_missing_columns(conn, "recurring_themes", &[("muted", "INTEGER DEFAULT 0")])?; // Tracked but not surfaced
+    // When confidence decay was last applied (NULL means "not since it was last confirmed")
+    for table in ["user_facts", "user_patterns"] {
+        add_missing_columns(conn, table, &[("decayed_at", "TEXT")])?;
+    }
+    // End-of-session reflection question stored alongside the summary
+    add_missing_columns(conn, "conversation_summaries", &[
+        ("reflection_question", "TEXT"),
+        ("reflection_surfaced_at", "TEXT"),
+    ])?;
+    
+    // Monotonic per-conversation sequence numbers (ordering and recovery don't trust wall-clock time)
+    let added_seq = add_missing_columns(conn, "messages", &[("seq", "INTEGER")])?;
+    add_missing_columns(conn, "conversations", &[("last_seq", "INTEGER NOT NULL DEFAULT 0")])?;
+    if added_seq {
+        // Backfill existing history in timestamp order (rowid breaks ties)
+        conn.execute(
+            "UPDATE messages SET seq = (
+                SELECT COUNT(*) FROM messages m2
+                WHERE m2.conversation_id = messages.conversation_id
+                  AND (m2.timestamp < messages.timestamp OR (m2.timestamp = messages.timestamp AND m2.rowid <= messages.rowid))
+            )",
+            []
+        )?;
+        conn.execute(
+            "UPDATE conversations SET last_seq = COALESCE((SELECT MAX(seq) FROM messages WHERE conversation_id = conversations.id), 0)",
+            []
+        )?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_messages_conversation_seq ON messages(conversation_id, seq)", [])?;
+    
+    add_missing_columns(conn, "messages", &[
+        ("superseded_by", "TEXT"),      // turn_versions row that archived a response replaced by a re-run
+        ("metadata", "TEXT"),           // Optional JSON (e.g. hidden drafts behind a composite answer)
+        ("starred", "INTEGER DEFAULT 0"), // Saved insights
+    ])?;
+    Ok(())
+}
+
+/// Carry each conversation's old limbo blob over as a single entry
+fn migrate_limbo_entries(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "INSERT INTO limbo_entries (conversation_id, content, created_at)
+         SELECT id, limbo_summary, updated_at FROM conversations
+         WHERE limbo_summary IS NOT NULL AND limbo_summary != ''",
+        []
+    )?;
+    conn.execute("UPDATE conversations SET limbo_summary = NULL", [])?;
+    Ok(())
+}
+
+fn migrate_pending_message_context(conn: &Connection) -> Result<()> {
+    add_missing_columns(conn, "pending_messages", &[
+        ("attachment_ids", "TEXT"),     // JSON array of uploads to link when it's sent
+    ])?;
+    Ok(())
+}
+
+fn migrate_pending_message_reply_to(conn: &Connection) -> Result<()> {
+    add_missing_columns(conn, "pending_messages", &[
+        ("reply_to", "TEXT"),           // Agent message it quote-replies to
+    ])?;
+    Ok(())
+}
+
+fn migrate_pending_message_voice(conn: &Connection) -> Result<()> {
+    add_missing_columns(conn, "pending_messages", &[
+        ("voice", "INTEGER DEFAULT 0"), // Dictated rather than typed
+    ])?;
+    Ok(())
+}
+
+fn init_schema(conn: &Connection) -> Result<()> {
+    // Anything already in the file is worth a snapshot before migrating
+    let has_existing_tables: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table'",
+        [],
+        |row| Ok(row.get::<_, i64>(0)? > 0)
+    )?;
+    let backup_dir = conn.path()
+        .filter(|p| !p.is_empty() && has_existing_tables)
+        .and_then(|p| Path::new(p).parent().map(|dir| dir.join("backups")));
+    snapshot_before_migrations(conn, backup_dir.as_deref())?;
     
     // Create tables
     conn.execute_batch(
@@ -268,6 +690,13 @@ pub fn init_database(app_handle: &tauri::AppHandle) -> Result<()> {
             updated_at TEXT NOT NULL
         );
         
+        -- App settings (simple key/value preferences)
+        CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        
         -- Agent interaction tracking for relationship evolution
         CREATE TABLE IF NOT EXISTS agent_interactions (
             id INTEGER PRIMARY KEY,
@@ -284,182 +713,563 @@ pub fn init_database(app_handle: &tauri::AppHandle) -> Result<()> {
         "
     )?;
     
-    // Migration: Add anthropic_key column if it doesn't exist
-    let has_anthropic_key: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('user_profile') WHERE name='anthropic_key'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
-    
-    if !has_anthropic_key {
-        let _ = conn.execute("ALTER TABLE user_profile ADD COLUMN anthropic_key TEXT", []);
-    }
-    
-    // Migration: Add message_count column to persona_profiles if it doesn't exist
-    let has_persona_message_count: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('persona_profiles') WHERE name='message_count'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
-    
-    if !has_persona_message_count {
-        let _ = conn.execute("ALTER TABLE persona_profiles ADD COLUMN message_count INTEGER DEFAULT 0", []);
-    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS turn_versions (
+            id TEXT PRIMARY KEY,
+            user_message_id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            previous_content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (user_message_id) REFERENCES messages(id)
+        )",
+        []
+    )?;
     
-    // Migration: Add limbo_summary and processed columns to conversations table
-    let has_limbo_summary: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('conversations') WHERE name='limbo_summary'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
+    // Earlier contents of a single regenerated agent response
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS message_versions (
+            id TEXT PRIMARY KEY,
+            message_id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (message_id) REFERENCES messages(id)
+        )",
+        []
+    )?;
     
-    if !has_limbo_summary {
-        let _ = conn.execute("ALTER TABLE conversations ADD COLUMN limbo_summary TEXT", []);
-        let _ = conn.execute("ALTER TABLE conversations ADD COLUMN processed INTEGER DEFAULT 0", []);
-    }
+    // Tokens and estimated cost of every model call, for spend tracking and budgets
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS api_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cost_usd REAL NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_api_usage_created ON api_usage(created_at)", [])?;
     
-    // Migration: Add is_disco column to conversations table for conversation-level disco mode
-    let has_is_disco: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('conversations') WHERE name='is_disco'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
+    // Conversations brought in from ChatGPT / Claude exports (external_id dedupes re-imports)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS imported_conversations (
+            conversation_id TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            external_id TEXT NOT NULL,
+            extraction_pending INTEGER DEFAULT 0,
+            imported_at TEXT NOT NULL,
+            UNIQUE (source, external_id),
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        )",
+        []
+    )?;
     
-    if !has_is_disco {
-        let _ = conn.execute("ALTER TABLE conversations ADD COLUMN is_disco INTEGER DEFAULT 0", []);
-    }
+    // User messages written while offline, sent in order once connectivity returns
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_messages (
+            id TEXT PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            active_agents TEXT NOT NULL,
+            disco_agents TEXT NOT NULL,
+            queued_at TEXT NOT NULL,
+            attempts INTEGER DEFAULT 0,
+            last_error TEXT,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        )",
+        []
+    )?;
     
-    // Migration: Add points columns to persona_profiles table
-    let has_instinct_points: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('persona_profiles') WHERE name='instinct_points'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
+    // Fact keys the user asked to forget; extraction won't store them again until blocked_until
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS forgotten_facts (
+            key TEXT PRIMARY KEY,
+            forgotten_at TEXT NOT NULL,
+            blocked_until TEXT NOT NULL
+        )",
+        []
+    )?;
     
-    if !has_instinct_points {
-        // Add columns with defaults: 4, 4, 3 (total 11)
-        let _ = conn.execute("ALTER TABLE persona_profiles ADD COLUMN instinct_points INTEGER DEFAULT 4", []);
-        let _ = conn.execute("ALTER TABLE persona_profiles ADD COLUMN logic_points INTEGER DEFAULT 4", []);
-        let _ = conn.execute("ALTER TABLE persona_profiles ADD COLUMN psyche_points INTEGER DEFAULT 3", []);
-        
-        // For existing profiles, initialize points based on current weights
-        // Convert weights to points: points = round(weight * 11), but ensure valid range (2-6) and total = 11
-        let _ = conn.execute(
-            "UPDATE persona_profiles SET instinct_points = CAST(ROUND(instinct_weight * 11) AS INTEGER), logic_points = CAST(ROUND(logic_weight * 11) AS INTEGER), psyche_points = CAST(ROUND(psyche_weight * 11) AS INTEGER)",
-            []
-        );
-        
-        // Ensure points are in valid range (2-6) and total = 11
-        // Clamp each to 2-6 range, then normalize total to 11
-        let _ = conn.execute(
-            "UPDATE persona_profiles SET 
-                instinct_points = MAX(2, MIN(6, instinct_points)),
-                logic_points = MAX(2, MIN(6, logic_points)),
-                psyche_points = MAX(2, MIN(6, psyche_points))",
-            []
-        );
-        
-        // Normalize totals to 11 (this is approximate, but close enough for migration)
-        // We'll fix exact totals in a separate pass if needed
-    }
+    // Deletions other devices need to hear about (see the Sync section)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_tombstones (
+            kind TEXT NOT NULL,
+            key TEXT NOT NULL,
+            deleted_at TEXT NOT NULL,
+            PRIMARY KEY (kind, key)
+        )",
+        []
+    )?;
     
-    // Migration: Add journey_sessions_completed column to persona_profiles
-    let has_journey_sessions: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('persona_profiles') WHERE name='journey_sessions_completed'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
+    // Facts flagged for the user to double-check (e.g. learned from a message they later deleted)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS fact_reviews (
+            fact_id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL,
+            flagged_at TEXT NOT NULL
+        )",
+        []
+    )?;
     
-    if !has_journey_sessions {
-        let _ = conn.execute("ALTER TABLE persona_profiles ADD COLUMN journey_sessions_completed INTEGER DEFAULT 0", []);
-    }
+    // Tags for grouping conversations ("work", "health"); source is 'user' or 'auto' (from summary topics)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS conversation_tags (
+            conversation_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            source TEXT NOT NULL DEFAULT 'user',
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (conversation_id, tag),
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        )",
+        []
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_conversation_tags_tag ON conversation_tags(tag)", [])?;
     
-    // Create journey_sessions table for tracking individual Game Mode journeys
+    // Files attached to messages (stored under app data/attachments; message_id is set on send)
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS journey_sessions (
+        "CREATE TABLE IF NOT EXISTS attachments (
             id TEXT PRIMARY KEY,
-            profile_id TEXT NOT NULL,
             conversation_id TEXT NOT NULL,
-            phase TEXT NOT NULL DEFAULT 'exploration',
-            phase_confirmed INTEGER DEFAULT 0,
-            problem_summary TEXT,
-            resolution_summary TEXT,
-            acceptance_summary TEXT,
-            completed INTEGER DEFAULT 0,
-            started_at TEXT NOT NULL,
-            completed_at TEXT,
-            FOREIGN KEY (profile_id) REFERENCES persona_profiles(id),
+            message_id TEXT,
+            kind TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            description TEXT,
+            created_at TEXT NOT NULL,
             FOREIGN KEY (conversation_id) REFERENCES conversations(id)
         )",
         []
     )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_attachments_message ON attachments(message_id)", [])?;
     
-    // Ensure a user profile exists (for API keys and message count)
-    let count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM user_profile",
-        [],
-        |row| row.get(0)
+    // Ingested documents (knowledge base); chunks are embedded like messages and facts
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS documents (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            source_path TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            summary TEXT,
+            char_count INTEGER NOT NULL,
+            chunk_count INTEGER NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS document_chunks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            document_id TEXT NOT NULL,
+            chunk_index INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            FOREIGN KEY (document_id) REFERENCES documents(id)
+        )",
+        []
     )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_document_chunks_document ON document_chunks(document_id)", [])?;
     
-    if count == 0 {
-        let now = Utc::now().to_rfc3339();
-        // Default weights: Logic 50%, Psyche 30%, Instinct 20%
-        conn.execute(
-            "INSERT INTO user_profile (api_key, instinct_weight, logic_weight, psyche_weight, total_messages, created_at, updated_at)
-             VALUES (NULL, 0.20, 0.50, 0.30, 0, ?1, ?2)",
-            params![now, now]
-        )?;
-    }
+    // Drafting assistance (emails, texts, tough replies)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS drafts (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            context TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            content TEXT NOT NULL,
+            rationale TEXT,
+            revision INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        []
+    )?;
     
-    // Ensure exactly 3 fixed profiles exist (Logic, Instinct, Psyche)
-    // Each profile is dominant for one trait at 40%, others at 30%
-    let now = Utc::now().to_rfc3339();
+    // Check-ins: follow-ups proposed in conversation, scheduled once the user confirms
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS check_ins (
+            id TEXT PRIMARY KEY,
+            conversation_id TEXT,
+            topic TEXT NOT NULL,
+            due_at TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'proposed',
+            created_at TEXT NOT NULL,
+            delivered_at TEXT
+        )",
+        []
+    )?;
     
-    // Check for each required profile by dominant_trait
-    let has_logic: bool = conn.query_row(
-        "SELECT COUNT(*) FROM persona_profiles WHERE dominant_trait = 'logic'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
+    // Reminders agents set during a conversation ("do it by Friday"), fired as notifications
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reminders (
+            id TEXT PRIMARY KEY,
+            conversation_id TEXT,
+            agent TEXT,
+            text TEXT NOT NULL,
+            due_at TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT NOT NULL,
+            fired_at TEXT
+        )",
+        []
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_reminders_status_due ON reminders(status, due_at)", [])?;
     
-    let has_instinct: bool = conn.query_row(
-        "SELECT COUNT(*) FROM persona_profiles WHERE dominant_trait = 'instinct'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0)
-    ).unwrap_or(false);
+    // Thumbs up/down on agent messages (one rating per message)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS message_feedback (
+            message_id TEXT PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            rating INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    
+    // Mood over time: a sentiment score per user message, and the tone/state of each summary
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mood_log (
+            id INTEGER PRIMARY KEY,
+            conversation_id TEXT,
+            message_id TEXT,
+            source TEXT NOT NULL,
+            sentiment REAL,
+            emotional_tone TEXT,
+            user_state TEXT,
+            created_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_mood_log_created ON mood_log(created_at)", [])?;
+    conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS idx_mood_log_message ON mood_log(message_id)", [])?;
+    
+    // Habit tracking (declared or inferred) and their completion logs
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS habits (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            target_per_week INTEGER,
+            source TEXT NOT NULL,
+            active INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL
+        );
+        
+        CREATE TABLE IF NOT EXISTS habit_logs (
+            id INTEGER PRIMARY KEY,
+            habit_id TEXT NOT NULL,
+            logged_at TEXT NOT NULL,
+            note TEXT,
+            source_conversation_id TEXT,
+            FOREIGN KEY (habit_id) REFERENCES habits(id)
+        );
+        "
+    )?;
+    
+    // Decisions reached in conversations (for later Governor follow-up)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS decisions (
+            id TEXT PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            decision TEXT NOT NULL,
+            options TEXT NOT NULL,
+            followed_agent TEXT,
+            created_at TEXT NOT NULL,
+            followed_up_at TEXT,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        )",
+        []
+    )?;
+    
+    // Weight snapshots per persona (for trajectories) and saved persona comparisons
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS persona_weight_history (
+            id INTEGER PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            instinct_weight REAL NOT NULL,
+            logic_weight REAL NOT NULL,
+            psyche_weight REAL NOT NULL,
+            recorded_at TEXT NOT NULL
+        );
+        
+        CREATE TABLE IF NOT EXISTS persona_comparisons (
+            id TEXT PRIMARY KEY,
+            profile_a TEXT NOT NULL,
+            profile_b TEXT NOT NULL,
+            stats TEXT NOT NULL,
+            narrative TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        
+        -- Personality type assessments over time (recorded when the type or confidence moves)
+        CREATE TABLE IF NOT EXISTS personality_snapshots (
+            id INTEGER PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            code TEXT NOT NULL,
+            name TEXT NOT NULL,
+            type_group TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            message_count INTEGER NOT NULL,
+            recorded_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES persona_profiles(id)
+        );
+        "
+    )?;
+    
+    // Limbo summary entries (append-only; replaces the ever-growing conversations.limbo_summary
+    // blob, moved over by migration 2)
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS limbo_entries (
+            id INTEGER PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_limbo_entries_conversation ON limbo_entries(conversation_id);
+        "
+    )?;
+    
+    // Agents muted inside a conversation (seq bookmarks let a returning agent know what it missed)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_mutes (
+            id INTEGER PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            reason TEXT,
+            muted_at TEXT NOT NULL,
+            muted_seq INTEGER NOT NULL,
+            unmuted_at TEXT,
+            unmuted_seq INTEGER,
+            acknowledged INTEGER DEFAULT 0,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        )",
+        []
+    )?;
+    
+    // Which response forms (lists vs prose, questions vs statements) the user engages with, per agent
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_style_preferences (
+            profile_id TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            dimension TEXT NOT NULL,
+            style TEXT NOT NULL,
+            score REAL NOT NULL DEFAULT 0,
+            samples INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (profile_id, agent, dimension, style)
+        )",
+        []
+    )?;
+    
+    // Analytical layer: raw per-message trait analysis. Never read when building prompts,
+    // and pruned on a retention schedule (see ANALYTICS_RETENTION_DAYS) unlike facts/patterns.
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS analytics_engagement (
+            id INTEGER PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            message_id TEXT,
+            logic_score REAL NOT NULL,
+            instinct_score REAL NOT NULL,
+            psyche_score REAL NOT NULL,
+            reasoning TEXT,
+            recorded_at TEXT NOT NULL
+        );
+        
+        CREATE TABLE IF NOT EXISTS analytics_intrinsic_signals (
+            id INTEGER PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            message_id TEXT,
+            logic_signal REAL NOT NULL,
+            instinct_signal REAL NOT NULL,
+            psyche_signal REAL NOT NULL,
+            reasoning TEXT,
+            recorded_at TEXT NOT NULL
+        );
+        
+        CREATE TABLE IF NOT EXISTS analytics_turn_metrics (
+            id INTEGER PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            message_id TEXT,
+            mode TEXT NOT NULL,
+            debate_mode TEXT,
+            response_count INTEGER NOT NULL,
+            latency_ms INTEGER NOT NULL,
+            recorded_at TEXT NOT NULL
+        );
+        
+        CREATE INDEX IF NOT EXISTS idx_analytics_engagement_recorded ON analytics_engagement(recorded_at);
+        CREATE INDEX IF NOT EXISTS idx_analytics_intrinsic_recorded ON analytics_intrinsic_signals(recorded_at);
+        CREATE INDEX IF NOT EXISTS idx_analytics_turn_metrics_recorded ON analytics_turn_metrics(recorded_at);
+        "
+    )?;
+    
+    // Notification center (proactive reports and nudges surfaced outside a conversation)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notifications (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            subject TEXT,
+            title TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            read_at TEXT
+        )",
+        []
+    )?;
+    
+    // User overrides of agent persona prompts (mode: 'normal' | 'disco'); absent rows use the built-in prompt
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_prompts (
+            agent TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            prompt TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (agent, mode)
+        )",
+        []
+    )?;
+    
+    // Every saved prompt override, newest last, so an experiment can be rolled back
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_prompt_versions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            prompt TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_prompt_versions ON agent_prompt_versions(agent, mode, id)",
+        []
+    )?;
+    
+    // Generated reports kept for reading later (weekly digests)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reports (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT NOT NULL,
+            period_start TEXT NOT NULL,
+            period_end TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        []
+    )?;
+    
+    // Embedding vectors for semantic search (messages, facts and summaries).
+    // `content` is the text that was embedded, so edited sources can be detected and re-embedded.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS embeddings (
+            source_type TEXT NOT NULL,
+            source_id TEXT NOT NULL,
+            conversation_id TEXT,
+            content TEXT NOT NULL,
+            model TEXT NOT NULL,
+            vector BLOB NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (source_type, source_id)
+        )",
+        []
+    )?;
     
-    let has_psyche: bool = conn.query_row(
-        "SELECT COUNT(*) FROM persona_profiles WHERE dominant_trait = 'psyche'",
+    // Full-text index over message content; rowids mirror messages.rowid and triggers keep it in sync.
+    // Recursive triggers make INSERT OR REPLACE fire the delete trigger for the row it replaces.
+    conn.execute_batch("PRAGMA recursive_triggers = ON")?;
+    let has_messages_fts: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts'",
         [],
         |row| Ok(row.get::<_, i64>(0)? > 0)
     ).unwrap_or(false);
     
-    // Create missing profiles
-    if !has_logic {
-        let logic_id = uuid::Uuid::new_v4().to_string();
-        conn.execute(
-            "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
-             VALUES (?1, 'Logic', 1, 1, 'logic', 'logic', 0.30, 0.40, 0.30, 3, 4, 4, 0, ?2, ?3)",
-            params![logic_id, now, now]
+    if !has_messages_fts {
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE messages_fts USING fts5(content, tokenize = 'porter unicode61');
+             INSERT INTO messages_fts (rowid, content) SELECT rowid, content FROM messages;"
         )?;
     }
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+             INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+         END;
+         CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+             DELETE FROM messages_fts WHERE rowid = old.rowid;
+         END;
+         CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+             UPDATE messages_fts SET content = new.content WHERE rowid = old.rowid;
+         END;"
+    )?;
+    
+    // Create journey_sessions table for tracking individual Game Mode journeys
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS journey_sessions (
+            id TEXT PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            conversation_id TEXT NOT NULL,
+            phase TEXT NOT NULL DEFAULT 'exploration',
+            phase_confirmed INTEGER DEFAULT 0,
+            problem_summary TEXT,
+            resolution_summary TEXT,
+            acceptance_summary TEXT,
+            completed INTEGER DEFAULT 0,
+            started_at TEXT NOT NULL,
+            completed_at TEXT,
+            FOREIGN KEY (profile_id) REFERENCES persona_profiles(id),
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        )",
+        []
+    )?;
+    
+    run_migrations(conn)?;
+    
+    // Ensure a user profile exists (for API keys and message count)
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM user_profile",
+        [],
+        |row| row.get(0)
+    )?;
     
-    if !has_instinct {
-        let instinct_id = uuid::Uuid::new_v4().to_string();
+    if count == 0 {
+        let now = Utc::now().to_rfc3339();
+        // Default weights: Logic 50%, Psyche 30%, Instinct 20%
         conn.execute(
-            "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
-             VALUES (?1, 'Instinct', 0, 0, 'instinct', 'instinct', 0.40, 0.30, 0.30, 4, 3, 4, 0, ?2, ?3)",
-            params![instinct_id, now, now]
+            "INSERT INTO user_profile (api_key, instinct_weight, logic_weight, psyche_weight, total_messages, created_at, updated_at)
+             VALUES (NULL, 0.20, 0.50, 0.30, 0, ?1, ?2)",
+            params![now, now]
         )?;
     }
     
-    if !has_psyche {
-        let psyche_id = uuid::Uuid::new_v4().to_string();
-        conn.execute(
-            "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
-             VALUES (?1, 'Psyche', 0, 0, 'psyche', 'psyche', 0.30, 0.30, 0.40, 3, 3, 5, 0, ?2, ?3)",
-            params![psyche_id, now, now]
-        )?;
+    // Ensure exactly 3 fixed profiles exist (Logic, Instinct, Psyche)
+    // Each profile is dominant for one trait at 40%, others at 30%
+    let now = Utc::now().to_rfc3339();
+    
+    // Create any that are missing (Logic is the default and active one)
+    for DefaultPersona { name, dominant_trait: dominant, weights: (instinct_w, logic_w, psyche_w), points: (instinct_p, logic_p, psyche_p) } in DEFAULT_PERSONAS {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) FROM persona_profiles WHERE dominant_trait = ?1",
+            params![dominant],
+            |row| Ok(row.get::<_, i64>(0)? > 0)
+        ).unwrap_or(false);
+        if !exists {
+            let is_logic = dominant == "logic";
+            conn.execute(
+                "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?3, ?4, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, ?11, ?11)",
+                params![uuid::Uuid::new_v4().to_string(), name, is_logic, dominant, instinct_w, logic_w, psyche_w, instinct_p, logic_p, psyche_p, now]
+            )?;
+        }
     }
     
     // Ensure exactly one profile is active (prefer Logic if none)
@@ -528,64 +1338,208 @@ pub fn init_database(app_handle: &tauri::AppHandle) -> Result<()> {
         }
     }
     
-    let mut db = DB.lock().unwrap();
-    *db = Some(conn);
-    
     Ok(())
 }
 
+/// Run work on a write connection, in one IMMEDIATE transaction: committed if `f` succeeds,
+/// rolled back if it fails
 fn with_connection<F, T>(f: F) -> Result<T>
 where
     F: FnOnce(&Connection) -> Result<T>,
 {
-    let db = DB.lock().unwrap();
-    let conn = db.as_ref().expect("Database not initialized");
-    f(conn)
+    with_plain_connection(|conn| {
+        conn.execute_batch("BEGIN IMMEDIATE")?;
+        let result = f(conn);
+        let finish = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        if let Err(e) = conn.execute_batch(finish) {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+        result
+    })
 }
 
-// ============ User Profile ============
-
-pub fn get_user_profile() -> Result<UserProfile> {
-    with_connection(|conn| {
-        // Get base profile info (API keys, message count)
-        let base: (i64, Option<String>, Option<String>, i64, String, String) = conn.query_row(
-            "SELECT id, api_key, anthropic_key, total_messages, created_at, updated_at
-             FROM user_profile LIMIT 1",
-            [],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
-        )?;
-        
-        // Get weights from active persona profile, or fallback to user_profile weights
-        let weights: (f64, f64, f64) = conn.query_row(
-            "SELECT instinct_weight, logic_weight, psyche_weight FROM persona_profiles WHERE is_active = 1",
-            [],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-        ).unwrap_or_else(|_| {
-            // Fallback to user_profile weights if no active persona profile
-            conn.query_row(
-                "SELECT instinct_weight, logic_weight, psyche_weight FROM user_profile LIMIT 1",
-                [],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-            ).unwrap_or((0.2, 0.5, 0.3)) // Final fallback to defaults
-        });
-        
-        Ok(UserProfile {
-            id: base.0,
-            api_key: base.1,
-            anthropic_key: base.2,
-            instinct_weight: weights.0,
-            logic_weight: weights.1,
-            psyche_weight: weights.2,
-            total_messages: base.3,
-            created_at: base.4,
-            updated_at: base.5,
-        })
-    })
+/// A write connection outside any transaction, for statements SQLite won't run inside one
+/// (checkpoints, VACUUM INTO). The pool lock is held throughout, so `with_database_closed` waits.
+fn with_plain_connection<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce(&Connection) -> Result<T>,
+{
+    let writers = WRITERS.read().unwrap();
+    let conn = writers.as_ref().expect("Database not initialized").get().map_err(pool_error)?;
+    // A panic inside `f` can hand a connection back mid-transaction
+    if !conn.is_autocommit() {
+        conn.execute_batch("ROLLBACK")?;
+    }
+    f(&conn)
 }
 
-pub fn update_api_key(api_key: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-    with_connection(|conn| {
+/// Run a read-only query on a pooled reader, so it doesn't queue behind writes.
+/// Falls back to the write connection for in-memory databases or if the pool is exhausted.
+fn with_read_connection<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce(&Connection) -> Result<T>,
+{
+    let reader = READERS.read().unwrap().as_ref().and_then(|pool| pool.try_get());
+    match reader {
+        Some(conn) => f(&conn),
+        None => with_connection(f),
+    }
+}
+
+/// Run database work on tokio's blocking pool, for async tasks doing many queries in a row
+/// (e.g. saving an extraction) that would otherwise hold up the async runtime
+pub async fn run_blocking<F, T>(f: F) -> std::result::Result<T, String>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(|e| e.to_string())
+}
+
+// ============ Snapshots ============
+
+/// Fold the WAL back into the main database file (on shutdown, so the file on disk is complete)
+pub fn checkpoint() -> Result<()> {
+    with_plain_connection(|conn| {
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+    })
+}
+
+/// Write a consistent copy of the open database to `path` (safe while other writes are queued)
+pub fn snapshot_to(path: &Path) -> Result<()> {
+    with_plain_connection(|conn| {
+        conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+        Ok(())
+    })
+}
+
+/// Run `f` on the database file while no connection is open, then reopen (and migrate) it.
+/// The lock is held throughout (taking it waits for queries in progress), so queries from other
+/// threads wait instead of failing.
+pub fn with_database_closed<F>(f: F) -> std::result::Result<(), String>
+where
+    F: FnOnce(&Path) -> std::result::Result<(), String>,
+{
+    let mut db = WRITERS.write().unwrap();
+    let path = db.as_ref()
+        .and_then(|pool| pool.get().ok())
+        .and_then(|conn| conn.path().map(PathBuf::from))
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or("No file-backed database is open")?;
+    
+    // Dropping the pools closes their connections (readers first, so nothing holds the file)
+    *READERS.write().unwrap() = None;
+    *db = None;
+    let outcome = f(&path);
+    
+    // A restored plaintext file (e.g. a backup from before encryption) is encrypted on the way back in
+    #[cfg(feature = "encryption")]
+    encrypt_if_plaintext(&path).map_err(|e| e.to_string())?;
+    let (writers, readers) = open_pools(&path).map_err(|e| e.to_string())?;
+    *READERS.write().unwrap() = readers;
+    *db = Some(writers);
+    
+    outcome
+}
+
+// ============ User Profile ============
+
+// ============ Key Overrides ============
+// Development/test installs can supply keys via env vars; these win over stored keys
+
+pub const OPENAI_KEY_ENV: &str = "INTERSECT_OPENAI_KEY";
+pub const ANTHROPIC_KEY_ENV: &str = "INTERSECT_ANTHROPIC_KEY";
+pub const GEMINI_KEY_ENV: &str = "INTERSECT_GEMINI_KEY";
+
+fn env_key(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Where each provider key currently comes from: "env" | "stored" | "none"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeySources {
+    pub openai: String,
+    pub anthropic: String,
+    pub gemini: String,
+}
+
+pub fn get_key_sources() -> Result<KeySources> {
+    let (stored_openai, stored_anthropic, stored_gemini): (Option<String>, Option<String>, Option<String>) = with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT api_key, anthropic_key, gemini_key FROM user_profile LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        )
+    })?;
+    
+    let source = |var: &str, stored: &Option<String>| {
+        if env_key(var).is_some() {
+            "env".to_string()
+        } else if stored.is_some() {
+            "stored".to_string()
+        } else {
+            "none".to_string()
+        }
+    };
+    
+    Ok(KeySources {
+        openai: source(OPENAI_KEY_ENV, &stored_openai),
+        anthropic: source(ANTHROPIC_KEY_ENV, &stored_anthropic),
+        gemini: source(GEMINI_KEY_ENV, &stored_gemini),
+    })
+}
+
+pub fn get_user_profile() -> Result<UserProfile> {
+    with_read_connection(|conn| {
+        // Get base profile info (API keys, message count)
+        let base: (i64, Option<String>, Option<String>, i64, String, String) = conn.query_row(
+            "SELECT id, api_key, anthropic_key, total_messages, created_at, updated_at
+             FROM user_profile LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+        )?;
+        let (gemini_key, agent_provider): (Option<String>, Option<String>) = conn.query_row(
+            "SELECT gemini_key, agent_provider FROM user_profile LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        )?;
+        
+        // Get weights from active persona profile, or fallback to user_profile weights
+        let weights: (f64, f64, f64) = conn.query_row(
+            "SELECT instinct_weight, logic_weight, psyche_weight FROM persona_profiles WHERE is_active = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        ).unwrap_or_else(|_| {
+            // Fallback to user_profile weights if no active persona profile
+            conn.query_row(
+                "SELECT instinct_weight, logic_weight, psyche_weight FROM user_profile LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            ).unwrap_or((0.2, 0.5, 0.3)) // Final fallback to defaults
+        });
+        
+        Ok(UserProfile {
+            id: base.0,
+            api_key: env_key(OPENAI_KEY_ENV).or(base.1),
+            anthropic_key: env_key(ANTHROPIC_KEY_ENV).or(base.2),
+            gemini_key: env_key(GEMINI_KEY_ENV).or(gemini_key),
+            agent_provider: agent_provider.unwrap_or_else(|| "openai".to_string()),
+            instinct_weight: weights.0,
+            logic_weight: weights.1,
+            psyche_weight: weights.2,
+            total_messages: base.3,
+            created_at: base.4,
+            updated_at: base.5,
+        })
+    })
+}
+
+pub fn update_api_key(api_key: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
         conn.execute(
             "UPDATE user_profile SET api_key = ?1, updated_at = ?2",
             params![api_key, now]
@@ -627,6 +1581,80 @@ pub fn clear_anthropic_key() -> Result<()> {
     })
 }
 
+pub fn update_gemini_key(api_key: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE user_profile SET gemini_key = ?1, updated_at = ?2",
+            params![api_key, now]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn clear_gemini_key() -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE user_profile SET gemini_key = NULL, updated_at = ?1",
+            params![now]
+        )?;
+        Ok(())
+    })
+}
+
+/// Provider agents use unless one is configured for that agent specifically
+pub fn set_default_agent_provider(provider: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE user_profile SET agent_provider = ?1, updated_at = ?2",
+            params![provider, now]
+        )?;
+        Ok(())
+    })
+}
+
+// ============ App Settings ============
+
+pub fn get_setting(key: &str) -> Result<Option<String>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0)
+        ).optional()
+    })
+}
+
+pub fn set_setting(key: &str, value: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = ?3",
+            params![key, value, now]
+        )?;
+        Ok(())
+    })
+}
+
+/// Remove a setting; returns whether it was set
+pub fn delete_setting(key: &str) -> Result<bool> {
+    with_connection(|conn| {
+        Ok(conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])? > 0)
+    })
+}
+
+/// Every setting as (key, value), by key
+pub fn get_all_settings() -> Result<Vec<(String, String)>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare("SELECT key, value FROM app_settings ORDER BY key")?;
+        let settings = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        settings.collect()
+    })
+}
+
 /// Update points for the active persona profile
 /// NOTE: Points affect agent weightings but do NOT change the dominant_trait
 /// The dominant_trait is fixed per profile (selected when the profile is created/activated)
@@ -659,6 +1687,13 @@ pub fn update_weights(instinct: f64, logic: f64, psyche: f64) -> Result<()> {
                 "UPDATE user_profile SET instinct_weight = ?1, logic_weight = ?2, psyche_weight = ?3, updated_at = ?4",
                 params![instinct, logic, psyche, now]
             )?;
+        } else {
+            // Snapshot for weight trajectories
+            conn.execute(
+                "INSERT INTO persona_weight_history (profile_id, instinct_weight, logic_weight, psyche_weight, recorded_at)
+                 SELECT id, ?1, ?2, ?3, ?4 FROM persona_profiles WHERE is_active = 1",
+                params![instinct, logic, psyche, now]
+            )?;
         }
         
         Ok(())
@@ -747,38 +1782,43 @@ pub fn create_conversation(id: &str, is_disco: bool) -> Result<Conversation> {
     let now = Utc::now().to_rfc3339();
     with_connection(|conn| {
         conn.execute(
-            "INSERT INTO conversations (id, title, summary, limbo_summary, processed, is_disco, created_at, updated_at)
-             VALUES (?1, NULL, NULL, NULL, 0, ?2, ?3, ?4)",
+            "INSERT INTO conversations (id, title, summary, processed, is_disco, persona_profile_id, created_at, updated_at)
+             VALUES (?1, NULL, NULL, 0, ?2, (SELECT id FROM persona_profiles WHERE is_active = 1), ?3, ?4)",
             params![id, if is_disco { 1 } else { 0 }, now, now]
         )?;
         Ok(Conversation {
             id: id.to_string(),
             title: None,
             summary: None,
-            limbo_summary: None,
             processed: false,
             is_disco,
             created_at: now.clone(),
             updated_at: now,
+            model_override: None,
+            parent_conversation_id: None,
+            branch_point_message_id: None,
         })
     })
 }
 
 pub fn get_conversation(id: &str) -> Result<Option<Conversation>> {
-    with_connection(|conn| {
+    with_read_connection(|conn| {
         let result = conn.query_row(
-            "SELECT id, title, summary, limbo_summary, processed, is_disco, created_at, updated_at FROM conversations WHERE id = ?1",
+            "SELECT id, title, summary, processed, is_disco, created_at, updated_at, model_override, parent_conversation_id, branch_point_message_id
+             FROM conversations WHERE id = ?1",
             params![id],
             |row| {
                 Ok(Conversation {
                     id: row.get(0)?,
                     title: row.get(1)?,
                     summary: row.get(2)?,
-                    limbo_summary: row.get(3)?,
-                    processed: row.get::<_, i64>(4)? != 0,
-                    is_disco: row.get::<_, i64>(5).unwrap_or(0) != 0,
-                    created_at: row.get(6)?,
-                    updated_at: row.get(7)?,
+                    processed: row.get::<_, i64>(3)? != 0,
+                    is_disco: row.get::<_, i64>(4).unwrap_or(0) != 0,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                    model_override: row.get(7)?,
+                    parent_conversation_id: row.get(8)?,
+                    branch_point_message_id: row.get(9)?,
                 })
             }
         );
@@ -791,12 +1831,12 @@ pub fn get_conversation(id: &str) -> Result<Option<Conversation>> {
 }
 
 pub fn get_recent_conversations(limit: usize) -> Result<Vec<Conversation>> {
-    with_connection(|conn| {
+    with_read_connection(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT c.id, c.title, c.summary, c.limbo_summary, c.processed, c.is_disco, c.created_at, c.updated_at,
-                    (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id) as msg_count
+            "SELECT c.id, c.title, c.summary, c.processed, c.is_disco, c.created_at, c.updated_at, c.model_override, c.parent_conversation_id, c.branch_point_message_id,
+                    (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id AND role != 'system') as msg_count
              FROM conversations c
-             WHERE (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id) > 0
+             WHERE (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id AND role != 'system') > 0
              ORDER BY c.updated_at DESC 
              LIMIT ?1"
         )?;
@@ -806,11 +1846,42 @@ pub fn get_recent_conversations(limit: usize) -> Result<Vec<Conversation>> {
                 id: row.get(0)?,
                 title: row.get(1)?,
                 summary: row.get(2)?,
-                limbo_summary: row.get(3)?,
-                processed: row.get::<_, i64>(4)? != 0,
-                is_disco: row.get::<_, i64>(5).unwrap_or(0) != 0,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
+                processed: row.get::<_, i64>(3)? != 0,
+                is_disco: row.get::<_, i64>(4).unwrap_or(0) != 0,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+                model_override: row.get(7)?,
+                parent_conversation_id: row.get(8)?,
+                branch_point_message_id: row.get(9)?,
+            })
+        })?;
+        
+        convs.collect()
+    })
+}
+
+/// Every conversation that has messages, oldest first (used by exports)
+pub fn get_all_conversations() -> Result<Vec<Conversation>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.title, c.summary, c.processed, c.is_disco, c.created_at, c.updated_at, c.model_override, c.parent_conversation_id, c.branch_point_message_id
+             FROM conversations c
+             WHERE EXISTS (SELECT 1 FROM messages WHERE conversation_id = c.id)
+             ORDER BY c.created_at ASC"
+        )?;
+        
+        let convs = stmt.query_map([], |row| {
+            Ok(Conversation {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                summary: row.get(2)?,
+                processed: row.get::<_, i64>(3)? != 0,
+                is_disco: row.get::<_, i64>(4).unwrap_or(0) != 0,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+                model_override: row.get(7)?,
+                parent_conversation_id: row.get(8)?,
+                branch_point_message_id: row.get(9)?,
             })
         })?;
         
@@ -818,39 +1889,50 @@ pub fn get_recent_conversations(limit: usize) -> Result<Vec<Conversation>> {
     })
 }
 
-/// Get conversations that need recovery (unprocessed, have messages, older than 1 min)
+/// Unprocessed conversations written during this app session, i.e. the ones still open at shutdown
+pub fn get_open_session_conversations() -> Result<Vec<String>> {
+    let written_this_session = WRITTEN_THIS_SESSION.lock().unwrap().clone();
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare("SELECT id FROM conversations WHERE processed = 0")?;
+        let ids = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>>>()?;
+        Ok(ids.into_iter().filter(|id| written_this_session.contains(id)).collect())
+    })
+}
+
+/// Get conversations that need recovery (unprocessed, have messages, not written this session)
 /// Used on startup to finalize conversations from crashes/force-quits
 pub fn get_conversations_needing_recovery() -> Result<Vec<Conversation>> {
-    use chrono::Duration;
+    let written_this_session = WRITTEN_THIS_SESSION.lock().unwrap().clone();
     
-    with_connection(|conn| {
+    with_read_connection(|conn| {
         // Get conversations that:
         // 1. Are not processed
-        // 2. Are older than 1 minute (not currently being written to)
-        let cutoff = (Utc::now() - Duration::minutes(1)).to_rfc3339();
-        
+        // 2. Weren't written to by this app session (so they can't still be in progress)
         let mut stmt = conn.prepare(
-            "SELECT c.id, c.title, c.summary, c.limbo_summary, c.processed, c.is_disco, c.created_at, c.updated_at,
-                    (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id) as msg_count
+            "SELECT c.id, c.title, c.summary, c.processed, c.is_disco, c.created_at, c.updated_at, c.model_override, c.parent_conversation_id, c.branch_point_message_id,
+                    (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id AND role != 'system') as msg_count
              FROM conversations c
-             WHERE c.processed = 0 
-               AND c.updated_at < ?1
+             WHERE c.processed = 0
              ORDER BY c.updated_at DESC"
         )?;
         
-        let convs = stmt.query_map([cutoff], |row| {
-            let msg_count: i64 = row.get(8)?;
-            // Only include if has at least 2 messages (user + agent)
-            if msg_count >= 2 {
+        let convs = stmt.query_map([], |row| {
+            let id: String = row.get(0)?;
+            let message_count: i64 = row.get(10)?;
+            // Only include if has at least 2 messages (user + agent); system notices don't count
+            if message_count >= 2 && !written_this_session.contains(&id) {
                 Ok(Some(Conversation {
-                    id: row.get(0)?,
+                    id,
                     title: row.get(1)?,
                     summary: row.get(2)?,
-                    limbo_summary: row.get(3)?,
-                    processed: row.get::<_, i64>(4)? != 0,
-                    is_disco: row.get::<_, i64>(5).unwrap_or(0) != 0,
-                    created_at: row.get(6)?,
-                    updated_at: row.get(7)?,
+                    processed: row.get::<_, i64>(3)? != 0,
+                    is_disco: row.get::<_, i64>(4).unwrap_or(0) != 0,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                    model_override: row.get(7)?,
+                    parent_conversation_id: row.get(8)?,
+                    branch_point_message_id: row.get(9)?,
                 }))
             } else {
                 Ok(None)
@@ -862,31 +1944,40 @@ pub fn get_conversations_needing_recovery() -> Result<Vec<Conversation>> {
     })
 }
 
-/// Append to the limbo summary (incremental summary built during conversation)
+/// Append an exchange note to the limbo summary (crash-recovery context built during a conversation).
+/// Notes are stored append-only, so each exchange costs one small insert no matter how long the conversation gets.
 pub fn append_limbo_summary(conversation_id: &str, new_content: &str) -> Result<()> {
     let now = Utc::now().to_rfc3339();
     with_connection(|conn| {
-        // Get existing limbo summary
-        let existing: Option<String> = conn.query_row(
-            "SELECT limbo_summary FROM conversations WHERE id = ?1",
-            params![conversation_id],
-            |row| row.get(0)
-        ).ok();
-        
-        // Append new content
-        let updated = match existing {
-            Some(existing_text) => format!("{}\n\n{}", existing_text, new_content),
-            None => new_content.to_string(),
-        };
-        
         conn.execute(
-            "UPDATE conversations SET limbo_summary = ?1, updated_at = ?2 WHERE id = ?3",
-            params![updated, now, conversation_id]
+            "INSERT INTO limbo_entries (conversation_id, content, created_at) VALUES (?1, ?2, ?3)",
+            params![conversation_id, new_content, now]
+        )?;
+        conn.execute(
+            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+            params![now, conversation_id]
         )?;
         Ok(())
     })
 }
 
+/// The limbo summary assembled from its entries (None if nothing was recorded)
+pub fn get_limbo_summary(conversation_id: &str) -> Result<Option<String>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT content FROM limbo_entries WHERE conversation_id = ?1 ORDER BY id ASC"
+        )?;
+        let entries: Vec<String> = stmt.query_map(params![conversation_id], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        
+        if entries.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(entries.join("\n\n")))
+        }
+    })
+}
+
 /// Mark a conversation as fully processed (after finalization)
 pub fn mark_conversation_processed(conversation_id: &str, final_summary: Option<&str>) -> Result<()> {
     let now = Utc::now().to_rfc3339();
@@ -906,1006 +1997,6544 @@ pub fn mark_conversation_processed(conversation_id: &str, final_summary: Option<
     })
 }
 
-// ============ Messages ============
-
-pub fn save_message(message: &Message) -> Result<()> {
+/// Re-attribute a conversation to a persona (e.g. after accepting a suggestion)
+pub fn set_conversation_persona(conversation_id: &str, profile_id: &str) -> Result<()> {
     with_connection(|conn| {
         conn.execute(
-            "INSERT OR REPLACE INTO messages (id, conversation_id, role, content, response_type, references_message_id, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                message.id,
-                message.conversation_id,
-                message.role,
-                message.content,
-                message.response_type,
-                message.references_message_id,
-                message.timestamp
-            ]
-        )?;
-        
-        // Update conversation timestamp
-        let now = Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
-            params![now, message.conversation_id]
+            "UPDATE conversations SET persona_profile_id = ?1 WHERE id = ?2",
+            params![profile_id, conversation_id]
         )?;
-        
         Ok(())
     })
 }
 
-pub fn get_conversation_messages(conversation_id: &str) -> Result<Vec<Message>> {
+pub fn set_conversation_title(conversation_id: &str, title: &str) -> Result<()> {
     with_connection(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp 
-             FROM messages 
-             WHERE conversation_id = ?1 
-             ORDER BY timestamp ASC"
+        conn.execute(
+            "UPDATE conversations SET title = ?1 WHERE id = ?2",
+            params![title, conversation_id]
         )?;
-        
-        let messages = stmt.query_map([conversation_id], |row| {
-            Ok(Message {
-                id: row.get(0)?,
-                conversation_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                response_type: row.get(4)?,
-                references_message_id: row.get(5)?,
-                timestamp: row.get(6)?,
-            })
-        })?;
-        
-        messages.collect()
+        Ok(())
     })
 }
 
-pub fn get_recent_messages(conversation_id: &str, limit: usize) -> Result<Vec<Message>> {
+/// Pin (or clear, with None) the model agents use in this conversation
+pub fn set_conversation_model(conversation_id: &str, model: Option<&str>) -> Result<()> {
     with_connection(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp 
-             FROM messages 
-             WHERE conversation_id = ?1 
-             ORDER BY timestamp DESC 
-             LIMIT ?2"
+        conn.execute(
+            "UPDATE conversations SET model_override = ?1 WHERE id = ?2",
+            params![model, conversation_id]
         )?;
-        
-        let messages = stmt.query_map(params![conversation_id, limit], |row| {
-            Ok(Message {
-                id: row.get(0)?,
-                conversation_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                response_type: row.get(4)?,
-                references_message_id: row.get(5)?,
-                timestamp: row.get(6)?,
-            })
-        })?;
-        
-        let mut result: Vec<Message> = messages.collect::<Result<Vec<_>>>()?;
-        result.reverse();
-        Ok(result)
+        Ok(())
     })
 }
 
-pub fn clear_conversation_messages(conversation_id: &str) -> Result<()> {
+/// Mark a conversation as a journal (its user messages are journal entries)
+pub fn set_conversation_journal(conversation_id: &str) -> Result<()> {
     with_connection(|conn| {
-        conn.execute("DELETE FROM messages WHERE conversation_id = ?1", params![conversation_id])?;
+        conn.execute("UPDATE conversations SET is_journal = 1 WHERE id = ?1", params![conversation_id])?;
         Ok(())
     })
 }
 
-pub fn delete_conversation(conversation_id: &str) -> Result<()> {
-    with_connection(|conn| {
-        // Delete related data first (foreign key constraints)
-        conn.execute("DELETE FROM messages WHERE conversation_id = ?1", params![conversation_id])?;
-        conn.execute("DELETE FROM conversation_summaries WHERE conversation_id = ?1", params![conversation_id])?;
-        // Delete user_facts that reference this conversation
-        conn.execute("DELETE FROM user_facts WHERE source_conversation_id = ?1", params![conversation_id])?;
-        // Delete the conversation itself
-        conn.execute("DELETE FROM conversations WHERE id = ?1", params![conversation_id])?;
-        Ok(())
+pub fn is_journal_conversation(conversation_id: &str) -> Result<bool> {
+    with_read_connection(|conn| {
+        let is_journal: Option<i64> = conn.query_row(
+            "SELECT is_journal FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0)
+        ).optional()?.flatten();
+        Ok(is_journal == Some(1))
     })
 }
 
-// ============ User Context ============
+/// Messages in journal conversations created in [since, until) (RFC 3339; None = unbounded),
+/// oldest conversation first, in conversation order
+pub fn get_journal_messages(since: Option<&str>, until: Option<&str>) -> Result<Vec<Message>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.conversation_id, m.role, m.content, m.response_type, m.references_message_id, m.timestamp, COALESCE(m.starred, 0)
+             FROM messages m JOIN conversations c ON c.id = m.conversation_id
+             WHERE c.is_journal = 1 AND m.superseded_by IS NULL AND m.role != 'system'
+               AND (?1 IS NULL OR c.created_at >= ?1) AND (?2 IS NULL OR c.created_at < ?2)
+             ORDER BY c.created_at ASC, m.seq ASC"
+        )?;
+        let messages = stmt.query_map(params![since, until], row_to_message)?;
+        messages.collect()
+    })
+}
 
-pub fn get_all_user_context() -> Result<Vec<UserContext>> {
+/// Save (or clear, with None) the agents this conversation uses when a message doesn't name them
+pub fn set_conversation_agents(conversation_id: &str, agents: Option<&[String]>) -> Result<bool> {
+    let json = agents.map(|a| serde_json::to_string(a).unwrap_or_else(|_| "[]".to_string()));
     with_connection(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, key, value, confidence, source_agent, updated_at FROM user_context ORDER BY confidence DESC"
+        let updated = conn.execute(
+            "UPDATE conversations SET active_agents = ?1 WHERE id = ?2",
+            params![json, conversation_id]
         )?;
-        
-        let contexts = stmt.query_map([], |row| {
-            Ok(UserContext {
-                id: row.get(0)?,
-                key: row.get(1)?,
-                value: row.get(2)?,
-                confidence: row.get(3)?,
-                source_agent: row.get(4)?,
-                updated_at: row.get(5)?,
-            })
-        })?;
-        
-        contexts.collect()
+        Ok(updated > 0)
     })
 }
 
-pub fn clear_user_context() -> Result<()> {
-    with_connection(|conn| {
-        conn.execute("DELETE FROM user_context", [])?;
-        Ok(())
+/// The conversation's saved agent preset, if it has one
+pub fn get_conversation_agents(conversation_id: &str) -> Result<Option<Vec<String>>> {
+    with_read_connection(|conn| {
+        let json: Option<String> = conn.query_row(
+            "SELECT active_agents FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0)
+        ).optional()?.flatten();
+        Ok(json.and_then(|j| serde_json::from_str(&j).ok()))
     })
 }
 
-// ============ User Facts ============
-
-pub fn save_user_fact(fact: &UserFact) -> Result<()> {
+/// Fork a conversation at one of its user messages: the new conversation gets copies of every
+/// live message before that point (new ids, same order) and remembers where it branched from
+pub fn create_branch(id: &str, parent_conversation_id: &str, branch_point_message_id: &str) -> Result<Conversation> {
+    let now = Utc::now().to_rfc3339();
+    WRITTEN_THIS_SESSION.lock().unwrap().insert(id.to_string());
+    
     with_connection(|conn| {
         conn.execute(
-            "INSERT INTO user_facts (category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-             ON CONFLICT(category, key) DO UPDATE SET
-                value = ?3,
-                confidence = MAX(confidence, ?4),
-                last_confirmed = ?8,
-                mention_count = mention_count + 1",
-            params![
-                fact.category,
-                fact.key,
-                fact.value,
-                fact.confidence,
-                fact.source_type,
-                fact.source_conversation_id,
-                fact.first_mentioned,
-                fact.last_confirmed,
-                fact.mention_count
-            ]
-        )?;
-        Ok(())
-    })
-}
-
-pub fn get_all_user_facts() -> Result<Vec<UserFact>> {
-    with_connection(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count
-             FROM user_facts ORDER BY confidence DESC, mention_count DESC"
+            "INSERT INTO conversations (id, title, summary, processed, is_disco, persona_profile_id, created_at, updated_at,
+                                        model_override, active_agents, parent_conversation_id, branch_point_message_id)
+             SELECT ?1, title, NULL, 0, is_disco, persona_profile_id, ?2, ?2, model_override, active_agents, id, ?3
+             FROM conversations WHERE id = ?4",
+            params![id, now, branch_point_message_id, parent_conversation_id]
         )?;
         
-        let facts = stmt.query_map([], |row| {
-            Ok(UserFact {
-                id: row.get(0)?,
-                category: row.get(1)?,
-                key: row.get(2)?,
-                value: row.get(3)?,
-                confidence: row.get(4)?,
-                source_type: row.get(5)?,
-                source_conversation_id: row.get(6)?,
-                first_mentioned: row.get(7)?,
-                last_confirmed: row.get(8)?,
-                mention_count: row.get(9)?,
-            })
-        })?;
-        
-        facts.collect()
-    })
-}
-
-// ============ User Patterns ============
-
-pub fn save_user_pattern(pattern: &UserPattern) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-    with_connection(|conn| {
-        // Check if pattern with same type and similar description exists
-        let existing: Option<i64> = conn.query_row(
-            "SELECT id FROM user_patterns WHERE pattern_type = ?1 AND description = ?2",
-            params![pattern.pattern_type, pattern.description],
-            |row| row.get(0)
-        ).ok();
-        
-        if let Some(id) = existing {
-            // Update existing pattern
-            conn.execute(
-                "UPDATE user_patterns SET confidence = MIN(1.0, confidence + 0.1), observation_count = observation_count + 1, last_updated = ?1, evidence = ?2 WHERE id = ?3",
-                params![now, pattern.evidence, id]
+        let history: Vec<(Message, Option<String>)> = {
+            let mut stmt = conn.prepare(
+                "SELECT m.id, m.conversation_id, m.role, m.content, m.response_type, m.references_message_id, m.timestamp, COALESCE(m.starred, 0), m.metadata
+                 FROM messages m JOIN messages branch ON branch.id = ?1
+                 WHERE m.conversation_id = ?2 AND m.seq < branch.seq AND m.superseded_by IS NULL
+                 ORDER BY m.seq ASC"
             )?;
-        } else {
-            // Insert new pattern
+            let rows = stmt.query_map(params![branch_point_message_id, parent_conversation_id], |row| {
+                Ok((row_to_message(row)?, row.get(8)?))
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        
+        // References point at copies, not at the parent's messages
+        let new_ids: HashMap<String, String> = history.iter()
+            .map(|(m, _)| (m.id.clone(), uuid::Uuid::new_v4().to_string()))
+            .collect();
+        for (seq, (message, metadata)) in history.iter().enumerate() {
             conn.execute(
-                "INSERT INTO user_patterns (pattern_type, description, confidence, evidence, first_observed, last_updated, observation_count)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                "INSERT INTO messages (id, conversation_id, role, content, response_type, references_message_id, timestamp, seq, metadata)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
                 params![
-                    pattern.pattern_type,
-                    pattern.description,
-                    pattern.confidence,
-                    pattern.evidence,
-                    pattern.first_observed,
-                    pattern.last_updated,
-                    pattern.observation_count
+                    new_ids[&message.id],
+                    id,
+                    message.role,
+                    message.content,
+                    message.response_type,
+                    message.references_message_id.as_ref().and_then(|r| new_ids.get(r)),
+                    message.timestamp,
+                    seq as i64 + 1,
+                    metadata
                 ]
             )?;
         }
+        // The copies are the parent's to extract; only what's said in the branch is new
+        conn.execute(
+            "UPDATE conversations SET last_seq = ?1, extraction_watermark = ?1 WHERE id = ?2",
+            params![history.len() as i64, id]
+        )?;
         Ok(())
-    })
+    })?;
+    
+    get_conversation(id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn get_all_user_patterns() -> Result<Vec<UserPattern>> {
-    with_connection(|conn| {
+/// Branches forked from a conversation, newest first
+pub fn get_conversation_branches(parent_conversation_id: &str) -> Result<Vec<Conversation>> {
+    with_read_connection(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, pattern_type, description, confidence, evidence, first_observed, last_updated, observation_count
-             FROM user_patterns ORDER BY confidence DESC, observation_count DESC"
+            "SELECT c.id, c.title, c.summary, c.processed, c.is_disco, c.created_at, c.updated_at, c.model_override, c.parent_conversation_id, c.branch_point_message_id
+             FROM conversations c
+             WHERE c.parent_conversation_id = ?1
+             ORDER BY c.created_at DESC"
         )?;
         
-        let patterns = stmt.query_map([], |row| {
-            Ok(UserPattern {
+        let convs = stmt.query_map(params![parent_conversation_id], |row| {
+            Ok(Conversation {
                 id: row.get(0)?,
-                pattern_type: row.get(1)?,
-                description: row.get(2)?,
-                confidence: row.get(3)?,
-                evidence: row.get(4)?,
-                first_observed: row.get(5)?,
-                last_updated: row.get(6)?,
-                observation_count: row.get(7)?,
+                title: row.get(1)?,
+                summary: row.get(2)?,
+                processed: row.get::<_, i64>(3)? != 0,
+                is_disco: row.get::<_, i64>(4).unwrap_or(0) != 0,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+                model_override: row.get(7)?,
+                parent_conversation_id: row.get(8)?,
+                branch_point_message_id: row.get(9)?,
             })
         })?;
         
-        patterns.collect()
+        convs.collect()
     })
 }
 
-// ============ Conversation Summaries ============
+/// Cached recap and the message count it was generated at
+pub fn get_conversation_recap(conversation_id: &str) -> Result<Option<(String, i64)>> {
+    with_read_connection(|conn| {
+        let result: Option<(Option<String>, Option<i64>)> = conn.query_row(
+            "SELECT recap, recap_message_count FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?;
+        
+        Ok(match result {
+            Some((Some(recap), Some(count))) => Some((recap, count)),
+            _ => None,
+        })
+    })
+}
 
-pub fn save_conversation_summary(summary: &ConversationSummary) -> Result<()> {
+pub fn save_conversation_recap(conversation_id: &str, recap: &str, message_count: i64) -> Result<()> {
     with_connection(|conn| {
-        // Replace existing summary for this conversation
+        // Deliberately leaves updated_at alone so recaps don't reorder the history list
         conn.execute(
-            "INSERT OR REPLACE INTO conversation_summaries 
-             (conversation_id, summary, key_topics, emotional_tone, user_state, agents_involved, message_count, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                summary.conversation_id,
-                summary.summary,
-                summary.key_topics,
-                summary.emotional_tone,
-                summary.user_state,
-                summary.agents_involved,
-                summary.message_count,
-                summary.created_at
-            ]
+            "UPDATE conversations SET recap = ?1, recap_message_count = ?2 WHERE id = ?3",
+            params![recap, message_count, conversation_id]
         )?;
         Ok(())
     })
 }
 
-pub fn get_conversation_summary(conversation_id: &str) -> Result<Option<ConversationSummary>> {
-    with_connection(|conn| {
-        let result = conn.query_row(
-            "SELECT id, conversation_id, summary, key_topics, emotional_tone, user_state, agents_involved, message_count, created_at
-             FROM conversation_summaries WHERE conversation_id = ?1",
+/// Sequence number of the last message covered by memory extraction (None if nothing was extracted yet)
+pub fn get_extraction_watermark(conversation_id: &str) -> Result<Option<i64>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT extraction_watermark FROM conversations WHERE id = ?1",
             params![conversation_id],
-            |row| {
-                Ok(ConversationSummary {
-                    id: row.get(0)?,
-                    conversation_id: row.get(1)?,
-                    summary: row.get(2)?,
-                    key_topics: row.get(3)?,
-                    emotional_tone: row.get(4)?,
-                    user_state: row.get(5)?,
-                    agents_involved: row.get(6)?,
-                    message_count: row.get(7)?,
-                    created_at: row.get(8)?,
-                })
-            }
-        );
-        match result {
-            Ok(s) => Ok(Some(s)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e),
-        }
+            |row| row.get(0)
+        ).optional().map(|w| w.flatten())
     })
 }
 
-// ============ Recurring Themes ============
+/// Move the watermark forward (never backwards - extractions can finish out of order)
+pub fn advance_extraction_watermark(conversation_id: &str, seq: i64) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE conversations SET extraction_watermark = ?1
+             WHERE id = ?2 AND (extraction_watermark IS NULL OR extraction_watermark < ?1)",
+            params![seq, conversation_id]
+        )?;
+        Ok(())
+    })
+}
 
-pub fn save_recurring_theme(theme: &str, conversation_id: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
+// ============ Messages ============
+
+pub fn save_message(message: &Message) -> Result<()> {
+    // System notices take a place in the sequence, but aren't conversation activity
+    let is_notice = message.role == "system";
+    if !is_notice {
+        WRITTEN_THIS_SESSION.lock().unwrap().insert(message.conversation_id.clone());
+    }
+    
     with_connection(|conn| {
-        // Try to get existing theme
-        let existing: Option<(i64, String)> = conn.query_row(
-            "SELECT id, related_conversations FROM recurring_themes WHERE theme = ?1",
-            params![theme],
-            |row| Ok((row.get(0)?, row.get::<_, Option<String>>(1)?.unwrap_or_default()))
-        ).ok();
+        // Re-saving a message keeps its place; new messages take the conversation's next sequence number.
+        // The closure is one IMMEDIATE transaction, so concurrent writers can't interleave here.
+        let existing_seq: Option<i64> = conn.query_row(
+            "SELECT seq FROM messages WHERE id = ?1",
+            params![message.id],
+            |row| row.get(0)
+        ).optional()?.flatten();
         
-        if let Some((id, existing_convs)) = existing {
-            // Update existing theme
-            let mut convs: Vec<String> = if existing_convs.is_empty() {
-                Vec::new()
-            } else {
-                serde_json::from_str(&existing_convs).unwrap_or_default()
-            };
-            if !convs.contains(&conversation_id.to_string()) {
-                convs.push(conversation_id.to_string());
+        let now = Utc::now().to_rfc3339();
+        let seq = match existing_seq {
+            Some(seq) => seq,
+            None => {
+                conn.execute(
+                    "UPDATE conversations SET last_seq = last_seq + 1 WHERE id = ?1",
+                    params![message.conversation_id]
+                )?;
+                conn.query_row(
+                    "SELECT last_seq FROM conversations WHERE id = ?1",
+                    params![message.conversation_id],
+                    |row| row.get(0)
+                ).optional()?.unwrap_or(0)
             }
-            let convs_json = serde_json::to_string(&convs).unwrap_or_default();
-            
-            conn.execute(
-                "UPDATE recurring_themes SET frequency = frequency + 1, last_mentioned = ?1, related_conversations = ?2 WHERE id = ?3",
-                params![now, convs_json, id]
-            )?;
-        } else {
-            // Insert new theme
-            let convs_json = serde_json::to_string(&vec![conversation_id]).unwrap_or_default();
+        };
+        
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, role, content, response_type, references_message_id, timestamp, seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(id) DO UPDATE SET
+                conversation_id = excluded.conversation_id,
+                role = excluded.role,
+                content = excluded.content,
+                response_type = excluded.response_type,
+                references_message_id = excluded.references_message_id,
+                timestamp = excluded.timestamp,
+                seq = excluded.seq",
+            params![
+                message.id,
+                message.conversation_id,
+                message.role,
+                message.content,
+                message.response_type,
+                message.references_message_id,
+                message.timestamp,
+                seq
+            ]
+        )?;
+        
+        // Update conversation timestamp (display only - ordering and recovery use seq)
+        if !is_notice {
             conn.execute(
-                "INSERT INTO recurring_themes (theme, frequency, last_mentioned, related_conversations) VALUES (?1, 1, ?2, ?3)",
-                params![theme, now, convs_json]
+                "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+                params![now, message.conversation_id]
             )?;
         }
+        
         Ok(())
     })
 }
 
-pub fn get_all_recurring_themes() -> Result<Vec<RecurringTheme>> {
-    with_connection(|conn| {
+/// Sequence number of the newest message in a conversation (0 if empty)
+pub fn get_last_seq(conversation_id: &str) -> Result<i64> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT last_seq FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0)
+        ).optional().map(|seq| seq.unwrap_or(0))
+    })
+}
+
+/// Current (non-superseded) messages after a given sequence number, in order
+pub fn get_conversation_messages_after(conversation_id: &str, after_seq: i64) -> Result<Vec<Message>> {
+    with_read_connection(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, theme, frequency, last_mentioned, related_conversations
-             FROM recurring_themes ORDER BY frequency DESC"
+            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp, COALESCE(starred, 0)
+             FROM messages
+             WHERE conversation_id = ?1 AND superseded_by IS NULL AND seq > ?2
+             ORDER BY seq ASC"
         )?;
-        
-        let themes = stmt.query_map([], |row| {
-            Ok(RecurringTheme {
-                id: row.get(0)?,
-                theme: row.get(1)?,
-                frequency: row.get(2)?,
-                last_mentioned: row.get(3)?,
-                related_conversations: row.get(4)?,
-            })
-        })?;
-        
-        themes.collect()
+        let messages = stmt.query_map(params![conversation_id, after_seq], row_to_message)?;
+        messages.collect()
     })
 }
 
-pub fn get_top_themes(limit: usize) -> Result<Vec<RecurringTheme>> {
-    with_connection(|conn| {
+/// Timestamps of user messages, optionally only those at or after `since` (RFC 3339)
+pub fn get_user_message_timestamps(since: Option<&str>) -> Result<Vec<String>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp FROM messages WHERE role = 'user' AND (?1 IS NULL OR timestamp >= ?1)"
+        )?;
+        let timestamps = stmt.query_map(params![since], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        Ok(timestamps)
+    })
+}
+
+pub fn get_conversation_messages(conversation_id: &str) -> Result<Vec<Message>> {
+    with_read_connection(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, theme, frequency, last_mentioned, related_conversations
-             FROM recurring_themes ORDER BY frequency DESC LIMIT ?1"
+            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp, COALESCE(starred, 0) 
+             FROM messages 
+             WHERE conversation_id = ?1 AND superseded_by IS NULL
+             ORDER BY seq ASC, timestamp ASC"
         )?;
         
-        let themes = stmt.query_map([limit], |row| {
-            Ok(RecurringTheme {
+        let messages = stmt.query_map([conversation_id], |row| {
+            Ok(Message {
                 id: row.get(0)?,
-                theme: row.get(1)?,
-                frequency: row.get(2)?,
-                last_mentioned: row.get(3)?,
-                related_conversations: row.get(4)?,
+                conversation_id: row.get(1)?,
+                role: row.get(2)?,
+                content: row.get(3)?,
+                response_type: row.get(4)?,
+                references_message_id: row.get(5)?,
+                timestamp: row.get(6)?,
+                attachments: Vec::new(),
+                starred: row.get::<_, i64>(7)? != 0,
             })
         })?;
         
-        themes.collect()
+        let mut messages = messages.collect::<Result<Vec<_>>>()?;
+        load_attachments(conn, &mut messages)?;
+        Ok(messages)
     })
 }
 
-// ============ Reset ============
+fn row_to_message(row: &rusqlite::Row) -> Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        response_type: row.get(4)?,
+        references_message_id: row.get(5)?,
+        timestamp: row.get(6)?,
+        attachments: Vec::new(),
+        starred: row.get::<_, i64>(7)? != 0,
+    })
+}
 
-pub fn reset_all_data() -> Result<()> {
-    let now = Utc::now().to_rfc3339();
+pub fn set_message_metadata(id: &str, metadata: &str) -> Result<()> {
     with_connection(|conn| {
-        // Clear all conversation and memory data
-        conn.execute("DELETE FROM messages", [])?;
-        conn.execute("DELETE FROM conversations", [])?;
-        conn.execute("DELETE FROM user_context", [])?;
-        conn.execute("DELETE FROM user_facts", [])?;
-        conn.execute("DELETE FROM user_patterns", [])?;
-        conn.execute("DELETE FROM conversation_summaries", [])?;
-        conn.execute("DELETE FROM recurring_themes", [])?;
-        
-        // Delete all persona profiles (will be recreated on next init)
-        conn.execute("DELETE FROM persona_profiles", [])?;
-        
-        // Reset user_profile weights and message count, but KEEP API keys
         conn.execute(
-            "UPDATE user_profile SET instinct_weight = 0.333, logic_weight = 0.333, psyche_weight = 0.334, total_messages = 0, updated_at = ?1",
-            params![now]
+            "UPDATE messages SET metadata = ?1 WHERE id = ?2",
+            params![metadata, id]
         )?;
-        
-        // Recreate the 3 fixed persona profiles with default names and weights
-        // Format: (name, dominant_trait, instinct_weight, logic_weight, psyche_weight, is_default, is_active)
-        let profiles = [
-            ("Logic", "logic", 0.30, 0.40, 0.30, true, true),         // Logic dominant (40%), default and active
-            ("Instinct", "instinct", 0.40, 0.30, 0.30, false, false), // Instinct dominant (40%)
-            ("Psyche", "psyche", 0.30, 0.30, 0.40, false, false),     // Psyche dominant (40%)
-        ];
-        
-        for (name, dominant, instinct_w, logic_w, psyche_w, is_default, is_active) in profiles {
-            let id = uuid::Uuid::new_v4().to_string();
-        // Default points: 4, 4, 3 (total 11) - will be adjusted by user
-        conn.execute(
-            "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 4, 4, 3, 0, ?9, ?9)",
-                params![id, name, is_default, is_active, dominant, instinct_w, logic_w, psyche_w, now]
-            )?;
-        }
-        
         Ok(())
     })
 }
 
-// ============ Persona Profiles (Multi-Profile System) ============
-
-pub fn create_persona_profile(
-    name: &str,
-    dominant_trait: &str,
-    secondary_trait: &str,
-    is_default: bool,
-) -> Result<PersonaProfile> {
-    let now = Utc::now().to_rfc3339();
-    let id = uuid::Uuid::new_v4().to_string();
-    
-    // Calculate weights based on trait selection: dominant 50%, secondary 30%, third 20%
-    let (instinct_weight, logic_weight, psyche_weight) = calculate_trait_weights(dominant_trait, secondary_trait);
-    
-    with_connection(|conn| {
-        // If this is the first profile or marked as default, ensure only one is default
-        if is_default {
-            conn.execute("UPDATE persona_profiles SET is_default = 0", [])?;
-        }
-        
-        // Check if this is the first profile (make it active)
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM persona_profiles", [], |row| row.get(0))?;
-        let is_active = count == 0; // First profile is automatically active
-        
-        // Default points: 4, 4, 3 (total 11) - will be adjusted by user
-        conn.execute(
-            "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 4, 4, 3, 0, ?10, ?11)",
-            params![id, name, is_default || is_active, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, now, now]
-        )?;
-        
-        Ok(PersonaProfile {
-            id,
-            name: name.to_string(),
-            is_default: is_default || is_active,
-            is_active,
-            dominant_trait: dominant_trait.to_string(),
-            secondary_trait: secondary_trait.to_string(),
-            instinct_weight,
-            logic_weight,
-            psyche_weight,
-            instinct_points: 4,
-            logic_points: 4,
-            psyche_points: 3,
-            message_count: 0,
-            created_at: now.clone(),
-            updated_at: now,
-        })
+pub fn get_message_metadata(id: &str) -> Result<Option<String>> {
+    with_read_connection(|conn| {
+        let result: Option<Option<String>> = conn.query_row(
+            "SELECT metadata FROM messages WHERE id = ?1",
+            params![id],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(result.flatten())
     })
 }
 
-fn calculate_trait_weights(dominant: &str, secondary: &str) -> (f64, f64, f64) {
-    // dominant = 50%, secondary = 30%, third = 20%
-    let mut instinct = 0.2;
-    let mut logic = 0.2;
-    let mut psyche = 0.2;
-    
-    match dominant {
-        "instinct" => instinct = 0.5,
-        "logic" => logic = 0.5,
-        "psyche" => psyche = 0.5,
-        _ => {}
-    }
-    
-    match secondary {
-        "instinct" => instinct = 0.3,
-        "logic" => logic = 0.3,
-        "psyche" => psyche = 0.3,
-        _ => {}
-    }
-    
-    (instinct, logic, psyche)
+// ============ System Notices ============
+// Governor notices (weight shifts, mode changes, recovery) stored in the transcript as `system`
+// messages: response_type holds the notice kind, metadata holds its machine-readable payload
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SystemNotice {
+    pub message_id: String,
+    pub kind: String,
+    pub content: String,
+    pub payload: serde_json::Value,
+    pub timestamp: String,
 }
 
-pub fn get_all_persona_profiles() -> Result<Vec<PersonaProfile>> {
-    with_connection(|conn| {
+pub fn save_system_message(conversation_id: &str, kind: &str, content: &str, payload: &serde_json::Value) -> Result<Message> {
+    let message = Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.to_string(),
+        role: "system".to_string(),
+        content: content.to_string(),
+        response_type: Some(kind.to_string()),
+        references_message_id: None,
+        timestamp: Utc::now().to_rfc3339(),
+        attachments: Vec::new(),
+        starred: false,
+    };
+    save_message(&message)?;
+    set_message_metadata(&message.id, &payload.to_string())?;
+    Ok(message)
+}
+
+pub fn get_system_notices(conversation_id: &str, kind: Option<&str>) -> Result<Vec<SystemNotice>> {
+    with_read_connection(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at
-             FROM persona_profiles ORDER BY is_default DESC, message_count DESC"
+            "SELECT id, response_type, content, metadata, timestamp FROM messages
+             WHERE conversation_id = ?1 AND role = 'system' AND response_type IS NOT NULL
+               AND (?2 IS NULL OR response_type = ?2)
+             ORDER BY seq ASC"
         )?;
-        
-        let profiles = stmt.query_map([], |row| {
-            Ok(PersonaProfile {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                is_default: row.get::<_, i64>(2)? != 0,
-                is_active: row.get::<_, i64>(3)? != 0,
-                dominant_trait: row.get(4)?,
-                secondary_trait: row.get(5)?,
-                instinct_weight: row.get(6)?,
-                logic_weight: row.get(7)?,
-                psyche_weight: row.get(8)?,
-                instinct_points: row.get(9)?,
-                logic_points: row.get(10)?,
-                psyche_points: row.get(11)?,
-                message_count: row.get(12)?,
-                created_at: row.get(13)?,
-                updated_at: row.get(14)?,
+        let notices = stmt.query_map(params![conversation_id, kind], |row| {
+            let payload: Option<String> = row.get(3)?;
+            Ok(SystemNotice {
+                message_id: row.get(0)?,
+                kind: row.get(1)?,
+                content: row.get(2)?,
+                payload: payload.and_then(|p| serde_json::from_str(&p).ok()).unwrap_or(serde_json::Value::Null),
+                timestamp: row.get(4)?,
             })
         })?;
-        
-        profiles.collect()
+        notices.collect()
     })
 }
 
-pub fn get_active_persona_profile() -> Result<Option<PersonaProfile>> {
-    with_connection(|conn| {
+pub fn get_message(id: &str) -> Result<Option<Message>> {
+    with_read_connection(|conn| {
         conn.query_row(
-            "SELECT id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at
-             FROM persona_profiles WHERE is_active = 1",
-            [],
-            |row| Ok(PersonaProfile {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                is_default: row.get::<_, i64>(2)? != 0,
-                is_active: row.get::<_, i64>(3)? != 0,
-                dominant_trait: row.get(4)?,
-                secondary_trait: row.get(5)?,
-                instinct_weight: row.get(6)?,
-                logic_weight: row.get(7)?,
-                psyche_weight: row.get(8)?,
-                instinct_points: row.get(9)?,
-                logic_points: row.get(10)?,
-                psyche_points: row.get(11)?,
-                message_count: row.get(12)?,
-                created_at: row.get(13)?,
-                updated_at: row.get(14)?,
-            })
+            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp, COALESCE(starred, 0)
+             FROM messages WHERE id = ?1",
+            params![id],
+            row_to_message
         ).optional()
     })
 }
 
-pub fn get_persona_profile_count() -> Result<i64> {
+/// Flip a message's star; returns the new state, or None if there's no such message
+pub fn toggle_message_star(id: &str) -> Result<Option<bool>> {
     with_connection(|conn| {
-        conn.query_row("SELECT COUNT(*) FROM persona_profiles", [], |row| row.get(0))
+        let updated = conn.execute(
+            "UPDATE messages SET starred = 1 - COALESCE(starred, 0) WHERE id = ?1",
+            params![id]
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        let starred: i64 = conn.query_row("SELECT starred FROM messages WHERE id = ?1", params![id], |row| row.get(0))?;
+        Ok(Some(starred == 1))
     })
 }
 
-pub fn set_active_persona_profile(profile_id: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-    with_connection(|conn| {
-        // Deactivate all profiles
-        conn.execute("UPDATE persona_profiles SET is_active = 0", [])?;
-        // Activate the selected profile
-        conn.execute(
-            "UPDATE persona_profiles SET is_active = 1, updated_at = ?1 WHERE id = ?2",
-            params![now, profile_id]
+/// A starred message with the title of the conversation it came from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StarredMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    pub conversation_title: Option<String>,
+}
+
+/// Starred messages that are still current, newest first
+pub fn get_starred_messages(limit: Option<usize>) -> Result<Vec<StarredMessage>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.conversation_id, m.role, m.content, m.response_type, m.references_message_id, m.timestamp, COALESCE(m.starred, 0), c.title
+             FROM messages m LEFT JOIN conversations c ON c.id = m.conversation_id
+             WHERE m.starred = 1 AND m.superseded_by IS NULL
+             ORDER BY m.timestamp DESC
+             LIMIT ?1"
         )?;
-        Ok(())
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+        let starred = stmt.query_map(params![limit], |row| {
+            Ok(StarredMessage { message: row_to_message(row)?, conversation_title: row.get(8)? })
+        })?;
+        starred.collect()
     })
 }
 
-pub fn set_default_persona_profile(profile_id: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-    with_connection(|conn| {
-        // Remove default from all profiles
-        conn.execute("UPDATE persona_profiles SET is_default = 0", [])?;
-        // Set the selected profile as default
-        conn.execute(
-            "UPDATE persona_profiles SET is_default = 1, updated_at = ?1 WHERE id = ?2",
-            params![now, profile_id]
+pub fn get_recent_messages(conversation_id: &str, limit: usize) -> Result<Vec<Message>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp, COALESCE(starred, 0) 
+             FROM messages 
+             WHERE conversation_id = ?1 AND superseded_by IS NULL
+             ORDER BY seq DESC, timestamp DESC 
+             LIMIT ?2"
         )?;
-        Ok(())
+        
+        let messages = stmt.query_map(params![conversation_id, limit], |row| {
+            Ok(Message {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                role: row.get(2)?,
+                content: row.get(3)?,
+                response_type: row.get(4)?,
+                references_message_id: row.get(5)?,
+                timestamp: row.get(6)?,
+                attachments: Vec::new(),
+                starred: row.get::<_, i64>(7)? != 0,
+            })
+        })?;
+        
+        let mut result: Vec<Message> = messages.collect::<Result<Vec<_>>>()?;
+        result.reverse();
+        load_attachments(conn, &mut result)?;
+        Ok(result)
     })
 }
 
-pub fn update_persona_profile_name(profile_id: &str, new_name: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
+/// Delete every message in a conversation; returns the attachments that went with them, whose
+/// stored files are the caller's to delete (uploads not sent yet stay)
+pub fn clear_conversation_messages(conversation_id: &str) -> Result<Vec<Attachment>> {
     with_connection(|conn| {
-        conn.execute(
-            "UPDATE persona_profiles SET name = ?1, updated_at = ?2 WHERE id = ?3",
-            params![new_name, now, profile_id]
+        record_message_tombstones(conn, "conversation_id = ?1", params![conversation_id])?;
+        let removed = delete_message_attachments(conn, "conversation_id = ?1", params![conversation_id])?;
+        conn.execute("DELETE FROM messages WHERE conversation_id = ?1", params![conversation_id])?;
+        Ok(removed)
+    })
+}
+
+/// Up to `limit` live messages saved before the given one in its conversation, oldest first
+pub fn get_messages_before(message_id: &str, limit: usize) -> Result<Vec<Message>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.conversation_id, m.role, m.content, m.response_type, m.references_message_id, m.timestamp, COALESCE(m.starred, 0)
+             FROM messages m JOIN messages target ON target.id = ?1
+             WHERE m.conversation_id = target.conversation_id AND m.seq < target.seq AND m.superseded_by IS NULL
+             ORDER BY m.seq DESC
+             LIMIT ?2"
         )?;
-        Ok(())
+        let mut result = stmt.query_map(params![message_id, limit], row_to_message)?
+            .collect::<Result<Vec<_>>>()?;
+        result.reverse();
+        load_attachments(conn, &mut result)?;
+        Ok(result)
     })
 }
 
-/// Update the dominant trait for the active persona profile
-pub fn update_dominant_trait(dominant_trait: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-    
-    // Derive secondary trait from dominant
-    let secondary = match dominant_trait {
-        "logic" => "instinct",
-        "instinct" => "psyche",
-        "psyche" => "logic",
-        _ => "logic",
-    };
-    
+/// Delete every message saved after a sequence mark (rolling back a cancelled turn); returns how many,
+/// and the attachments that went with them (their stored files are the caller's to delete)
+pub fn delete_messages_after(conversation_id: &str, after_seq: i64) -> Result<(usize, Vec<Attachment>)> {
     with_connection(|conn| {
-        conn.execute(
-            "UPDATE persona_profiles SET dominant_trait = ?1, secondary_trait = ?2, updated_at = ?3 WHERE is_active = 1",
-            params![dominant_trait, secondary, now]
+        record_message_tombstones(conn, "conversation_id = ?1 AND seq > ?2", params![conversation_id, after_seq])?;
+        let removed = delete_message_attachments(conn, "conversation_id = ?1 AND seq > ?2", params![conversation_id, after_seq])?;
+        let deleted = conn.execute(
+            "DELETE FROM messages WHERE conversation_id = ?1 AND seq > ?2",
+            params![conversation_id, after_seq]
         )?;
-        Ok(())
+        Ok((deleted, removed))
     })
 }
 
-pub fn delete_persona_profile(profile_id: &str) -> Result<()> {
+/// What deleting one message changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedMessage {
+    pub conversation_id: String,
+    pub relinked: usize,                  // Messages that replied to it, now pointing at what it replied to
+    pub exchange_since: String,           // When its exchange began (the user message that opened it)
+    pub exchange_until: Option<String>,   // When the next exchange began (None = it was the last one)
+}
+
+/// Delete one message and the rows hanging off it. Replies to it are re-pointed at the message it
+/// referenced, so threads stay connected. Returns None if there's no such message.
+pub fn delete_message(id: &str) -> Result<Option<DeletedMessage>> {
     with_connection(|conn| {
-        // Don't allow deleting the last profile
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM persona_profiles", [], |row| row.get(0))?;
-        if count <= 1 {
-            return Err(rusqlite::Error::QueryReturnedNoRows); // Using this as a simple error
+        let deleted = delete_message_in(conn, id)?;
+        if deleted.is_some() {
+            record_tombstone(conn, "message", id)?;
         }
-        
-        // Check if this is the active profile
-        let is_active: bool = conn.query_row(
-            "SELECT is_active FROM persona_profiles WHERE id = ?1",
-            params![profile_id],
-            |row| Ok(row.get::<_, i64>(0)? != 0)
-        ).unwrap_or(false);
-        
-        // Delete the profile
-        conn.execute("DELETE FROM persona_profiles WHERE id = ?1", params![profile_id])?;
-        
-        // If we deleted the active profile, activate the default or first remaining
-        if is_active {
-            // Try to activate the default profile
-            let activated = conn.execute(
-                "UPDATE persona_profiles SET is_active = 1 WHERE is_default = 1",
-                []
-            )?;
-            
-            // If no default, activate the first one
-            if activated == 0 {
-                conn.execute(
-                    "UPDATE persona_profiles SET is_active = 1 WHERE id = (SELECT id FROM persona_profiles ORDER BY created_at ASC LIMIT 1)",
-                    []
-                )?;
-            }
-        }
-        
-        Ok(())
+        Ok(deleted)
     })
 }
 
-// ============ AGENT INTERACTION TRACKING ============
+/// Body of `delete_message`, inside the caller's transaction
+fn delete_message_in(tx: &Connection, id: &str) -> Result<Option<DeletedMessage>> {
+    let found: Option<(String, String, Option<String>, String, i64)> = tx.query_row(
+        "SELECT conversation_id, role, references_message_id, timestamp, seq FROM messages WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+    ).optional()?;
+    let Some((conversation_id, role, references, timestamp, seq)) = found else {
+        return Ok(None);
+    };
+    
+    // The exchange is bounded by the user message that opened it and the next user message
+    let (exchange_since, anchor_seq) = if role == "user" {
+        (timestamp, seq)
+    } else {
+        tx.query_row(
+            "SELECT timestamp, seq FROM messages WHERE conversation_id = ?1 AND role = 'user' AND seq < ?2
+             ORDER BY seq DESC LIMIT 1",
+            params![conversation_id, seq],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?.unwrap_or((timestamp, seq))
+    };
+    let exchange_until: Option<String> = tx.query_row(
+        "SELECT timestamp FROM messages WHERE conversation_id = ?1 AND role = 'user' AND seq > ?2
+         ORDER BY seq ASC LIMIT 1",
+        params![conversation_id, anchor_seq],
+        |row| row.get(0)
+    ).optional()?;
+    
+    let relinked = tx.execute(
+        "UPDATE messages SET references_message_id = ?1 WHERE references_message_id = ?2",
+        params![references, id]
+    )?;
+    tx.execute("DELETE FROM message_versions WHERE message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM turn_versions WHERE user_message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM attachments WHERE message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM message_feedback WHERE message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM mood_log WHERE message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM analytics_engagement WHERE message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM analytics_intrinsic_signals WHERE message_id = ?1", params![id])?;
+    tx.execute("DELETE FROM embeddings WHERE source_type = 'message' AND source_id = ?1", params![id])?;
+    tx.execute("DELETE FROM messages WHERE id = ?1", params![id])?;
+    
+    Ok(Some(DeletedMessage { conversation_id, relinked, exchange_since, exchange_until }))
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct AgentInteraction {
-    pub profile_id: String,
-    pub agent_type: String,
-    pub total_interactions: i64,
-    pub positive_engagements: i64,
-    pub negative_engagements: i64,
-    pub last_interaction: Option<String>,
+/// What deleting a conversation removes beyond the conversation, its messages and derived rows
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DeleteConversationOptions {
+    #[serde(default)]
+    pub remove_facts: bool,  // Facts first learned in this conversation
+    #[serde(default)]
+    pub remove_themes: bool, // Unlink it from recurring themes; themes left with no conversations go too
+    #[serde(default)]
+    pub keep_summary: bool,  // Keep its summary so the gist still informs later conversations
 }
 
-/// Get or create agent interaction record for a profile/agent pair
-pub fn get_agent_interaction(profile_id: &str, agent_type: &str) -> Result<AgentInteraction> {
-    let now = Utc::now().to_rfc3339();
-    
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeletedCounts {
+    pub messages: usize,
+    pub facts: usize,
+    pub summaries: usize,
+    pub themes_updated: usize,
+    pub themes_removed: usize,
+    pub embeddings: usize,
+}
+
+/// Delete a conversation along with its facts and summary
+pub fn delete_conversation(conversation_id: &str) -> Result<()> {
+    delete_conversation_with(conversation_id, DeleteConversationOptions {
+        remove_facts: true,
+        remove_themes: false,
+        keep_summary: false,
+    })?;
+    Ok(())
+}
+
+/// Delete a conversation in one transaction, with memory cleanup chosen by `options`
+pub fn delete_conversation_with(conversation_id: &str, options: DeleteConversationOptions) -> Result<DeletedCounts> {
     with_connection(|conn| {
-        // Try to get existing record
-        let result: Result<AgentInteraction> = conn.query_row(
-            "SELECT profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, last_interaction 
-             FROM agent_interactions WHERE profile_id = ?1 AND agent_type = ?2",
-            params![profile_id, agent_type],
-            |row| Ok(AgentInteraction {
-                profile_id: row.get(0)?,
-                agent_type: row.get(1)?,
-                total_interactions: row.get(2)?,
-                positive_engagements: row.get(3)?,
-                negative_engagements: row.get(4)?,
-                last_interaction: row.get(5)?,
-            })
-        );
-        
-        match result {
-            Ok(interaction) => Ok(interaction),
-            Err(_) => {
-                // Create new record
-                conn.execute(
-                    "INSERT INTO agent_interactions (profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, created_at, updated_at)
-                     VALUES (?1, ?2, 0, 0, 0, ?3, ?3)",
-                    params![profile_id, agent_type, now]
-                )?;
-                
-                Ok(AgentInteraction {
-                    profile_id: profile_id.to_string(),
-                    agent_type: agent_type.to_string(),
-                    total_interactions: 0,
-                    positive_engagements: 0,
-                    negative_engagements: 0,
-                    last_interaction: None,
-                })
-            }
+        if options.remove_facts {
+            record_fact_tombstones(conn, "source_conversation_id = ?1", params![conversation_id])?;
         }
+        let counts = delete_conversation_in(conn, conversation_id, options)?;
+        record_tombstone(conn, "conversation", conversation_id)?;
+        Ok(counts)
     })
 }
 
-/// Record an interaction with an agent (positive or negative)
-pub fn record_agent_interaction(profile_id: &str, agent_type: &str, is_positive: bool) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
+/// Body of `delete_conversation_with`, inside the caller's transaction
+fn delete_conversation_in(tx: &Connection, conversation_id: &str, options: DeleteConversationOptions) -> Result<DeletedCounts> {
+    let mut counts = DeletedCounts::default();
     
-    with_connection(|conn| {
-        // Ensure record exists
-        let _ = conn.execute(
-            "INSERT OR IGNORE INTO agent_interactions (profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, created_at, updated_at)
-             VALUES (?1, ?2, 0, 0, 0, ?3, ?3)",
-            params![profile_id, agent_type, now]
-        );
-        
-        // Update counts
-        if is_positive {
-            conn.execute(
-                "UPDATE agent_interactions SET total_interactions = total_interactions + 1, positive_engagements = positive_engagements + 1, last_interaction = ?1, updated_at = ?1 
-                 WHERE profile_id = ?2 AND agent_type = ?3",
-                params![now, profile_id, agent_type]
-            )?;
-        } else {
-            conn.execute(
-                "UPDATE agent_interactions SET total_interactions = total_interactions + 1, negative_engagements = negative_engagements + 1, last_interaction = ?1, updated_at = ?1 
-                 WHERE profile_id = ?2 AND agent_type = ?3",
-                params![now, profile_id, agent_type]
+    // Delete related data first (foreign key constraints)
+    tx.execute(
+        "DELETE FROM message_versions WHERE message_id IN (SELECT id FROM messages WHERE conversation_id = ?1)",
+        params![conversation_id]
+    )?;
+    tx.execute(
+        "DELETE FROM turn_versions WHERE user_message_id IN (SELECT id FROM messages WHERE conversation_id = ?1)",
+        params![conversation_id]
+    )?;
+    counts.messages = tx.execute("DELETE FROM messages WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM limbo_entries WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM imported_conversations WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM pending_messages WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM agent_mutes WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM conversation_tags WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM attachments WHERE conversation_id = ?1", params![conversation_id])?;
+    if !options.keep_summary {
+        counts.summaries = tx.execute("DELETE FROM conversation_summaries WHERE conversation_id = ?1", params![conversation_id])?;
+    }
+    counts.embeddings = tx.execute("DELETE FROM embeddings WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM analytics_engagement WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM analytics_intrinsic_signals WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM analytics_turn_metrics WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM mood_log WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM message_feedback WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM reminders WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM decisions WHERE conversation_id = ?1", params![conversation_id])?;
+    tx.execute("DELETE FROM journey_sessions WHERE conversation_id = ?1", params![conversation_id])?;
+    if options.remove_facts {
+        tx.execute(
+            "DELETE FROM fact_reviews WHERE fact_id IN (SELECT id FROM user_facts WHERE source_conversation_id = ?1)",
+            params![conversation_id]
+        )?;
+        counts.facts = tx.execute("DELETE FROM user_facts WHERE source_conversation_id = ?1", params![conversation_id])?;
+    }
+    
+    if options.remove_themes {
+        let linked: Vec<(i64, String, i64)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, related_conversations, frequency FROM recurring_themes WHERE related_conversations LIKE ?1"
             )?;
+            let rows = stmt.query_map(params![format!("%\"{}\"%", conversation_id)], |row| {
+                Ok((row.get(0)?, row.get::<_, Option<String>>(1)?.unwrap_or_default(), row.get(2)?))
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for (id, related, frequency) in linked {
+            let mut convs: Vec<String> = serde_json::from_str(&related).unwrap_or_default();
+            convs.retain(|c| c != conversation_id);
+            if convs.is_empty() {
+                tx.execute("DELETE FROM recurring_themes WHERE id = ?1", params![id])?;
+                counts.themes_removed += 1;
+            } else {
+                tx.execute(
+                    "UPDATE recurring_themes SET related_conversations = ?1, frequency = ?2 WHERE id = ?3",
+                    params![serde_json::to_string(&convs).unwrap_or_default(), (frequency - 1).max(1), id]
+                )?;
+                counts.themes_updated += 1;
+            }
         }
-        
-        Ok(())
-    })
+    }
+    
+    // Delete the conversation itself
+    tx.execute("DELETE FROM conversations WHERE id = ?1", params![conversation_id])?;
+    Ok(counts)
 }
 
-/// Get all agent interactions for a profile
-pub fn get_all_agent_interactions(profile_id: &str) -> Result<Vec<AgentInteraction>> {
-    with_connection(|conn| {
+// ============ User Context ============
+
+pub fn get_all_user_context() -> Result<Vec<UserContext>> {
+    with_read_connection(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, last_interaction 
-             FROM agent_interactions WHERE profile_id = ?1"
+            "SELECT id, key, value, confidence, source_agent, updated_at FROM user_context ORDER BY confidence DESC"
         )?;
         
-        let interactions = stmt.query_map(params![profile_id], |row| {
-            Ok(AgentInteraction {
-                profile_id: row.get(0)?,
-                agent_type: row.get(1)?,
-                total_interactions: row.get(2)?,
-                positive_engagements: row.get(3)?,
-                negative_engagements: row.get(4)?,
-                last_interaction: row.get(5)?,
+        let contexts = stmt.query_map([], |row| {
+            Ok(UserContext {
+                id: row.get(0)?,
+                key: row.get(1)?,
+                value: row.get(2)?,
+                confidence: row.get(3)?,
+                source_agent: row.get(4)?,
+                updated_at: row.get(5)?,
             })
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
+        })?;
         
-        Ok(interactions)
+        contexts.collect()
     })
 }
-// ============ RESET PERSONALIZATION ============
 
-/// Reset personalization for a profile - keeps API keys, points, dominant trait, and name
-pub fn reset_personalization(profile_id: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-    let log_path = std::path::Path::new("/Users/briggskellogg/Developer/intersect/.cursor/debug.log");
-    
-    // #region agent log
-    if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
-        use std::io::Write;
-        let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:entry","message":"Starting reset","data":{{"profile_id":"{}"}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H1,H2,H4"}}"#, profile_id, chrono::Utc::now().timestamp_millis());
-    }
-    // #endregion
-    
+pub fn clear_user_context() -> Result<()> {
     with_connection(|conn| {
-        // Reset persona_profiles weights, points, message_count, and created_at to defaults
-        let persona_rows = conn.execute(
-            "UPDATE persona_profiles SET 
-                instinct_weight = 0.333, 
-                logic_weight = 0.333, 
-                psyche_weight = 0.334, 
-                instinct_points = 4,
-                logic_points = 4,
-                psyche_points = 4,
-                message_count = 0, 
-                created_at = ?1,
-                updated_at = ?1 
-             WHERE id = ?2",
-            params![now, profile_id]
-        )?;
-        
-        // #region agent log
-        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
-            use std::io::Write;
-            let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:persona_update","message":"Updated persona_profiles","data":{{"rows_affected":{}}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H1,H5"}}"#, persona_rows, chrono::Utc::now().timestamp_millis());
-        }
-        // #endregion
-        
-        // Also reset user_profile weights (fallback table)
-        let user_rows = conn.execute(
-            "UPDATE user_profile SET 
-                instinct_weight = 0.333, 
-                logic_weight = 0.333, 
-                psyche_weight = 0.334, 
-                total_messages = 0, 
-                updated_at = ?1",
-            params![now]
-        )?;
-        
-        // #region agent log
-        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
-            use std::io::Write;
-            let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:user_update","message":"Updated user_profile","data":{{"rows_affected":{}}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H2"}}"#, user_rows, chrono::Utc::now().timestamp_millis());
-        }
-        // #endregion
-        
-        // Clear memory tables for this profile
-        let facts = conn.execute("DELETE FROM user_facts WHERE 1=1", [])?;
-        let patterns = conn.execute("DELETE FROM user_patterns WHERE 1=1", [])?;
-        let themes = conn.execute("DELETE FROM recurring_themes WHERE 1=1", [])?;
-        let summaries = conn.execute("DELETE FROM conversation_summaries WHERE 1=1", [])?;
-        let interactions = conn.execute("DELETE FROM agent_interactions WHERE profile_id = ?1", params![profile_id])?;
-        
-        // #region agent log
-        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
-            use std::io::Write;
-            let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:memory_clear","message":"Cleared memory tables","data":{{"facts":{},"patterns":{},"themes":{},"summaries":{},"interactions":{}}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H4"}}"#, facts, patterns, themes, summaries, interactions, chrono::Utc::now().timestamp_millis());
-        }
-        // #endregion
-        
+        conn.execute("DELETE FROM user_context", [])?;
         Ok(())
     })
 }
 
-/// Full reset including conversation history
-pub fn reset_personalization_full(profile_id: &str) -> Result<()> {
-    // First do the standard reset
-    reset_personalization(profile_id)?;
-    
-    // Then delete all conversations and messages
+// ============ User Facts ============
+
+/// Insert a fact, or re-confirm the one already stored under its category and key.
+/// Returns the stored fact's id and whether it was newly inserted.
+pub fn save_user_fact(fact: &UserFact) -> Result<(i64, bool)> {
     with_connection(|conn| {
-        conn.execute("DELETE FROM messages WHERE 1=1", [])?;
-        conn.execute("DELETE FROM conversations WHERE 1=1", [])?;
-        Ok(())
+        let existing: Option<i64> = conn.query_row(
+            "SELECT id FROM user_facts WHERE category = ?1 AND key = ?2",
+            params![fact.category, fact.key],
+            |row| row.get(0)
+        ).optional()?;
+        conn.execute(
+            "INSERT INTO user_facts (category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(category, key) DO UPDATE SET
+                value = ?3,
+                confidence = MAX(confidence, ?4),
+                last_confirmed = ?8,
+                decayed_at = NULL,
+                mention_count = mention_count + 1",
+            params![
+                fact.category,
+                fact.key,
+                fact.value,
+                fact.confidence,
+                fact.source_type,
+                fact.source_conversation_id,
+                fact.first_mentioned,
+                fact.last_confirmed,
+                fact.mention_count
+            ]
+        )?;
+        Ok(match existing {
+            Some(id) => (id, false),
+            None => (conn.last_insert_rowid(), true),
+        })
     })
 }
 
-// ============ JOURNEY SESSION FUNCTIONS ============
-
-/// Create a new journey session for Game Mode
-pub fn create_journey_session(profile_id: &str, conversation_id: &str) -> Result<JourneySession> {
+pub fn get_all_user_facts() -> Result<Vec<UserFact>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count
+             FROM user_facts ORDER BY confidence DESC, last_confirmed DESC, mention_count DESC"
+        )?;
+        
+        let facts = stmt.query_map([], |row| {
+            Ok(UserFact {
+                id: row.get(0)?,
+                category: row.get(1)?,
+                key: row.get(2)?,
+                value: row.get(3)?,
+                confidence: row.get(4)?,
+                source_type: row.get(5)?,
+                source_conversation_id: row.get(6)?,
+                first_mentioned: row.get(7)?,
+                last_confirmed: row.get(8)?,
+                mention_count: row.get(9)?,
+            })
+        })?;
+        
+        facts.collect()
+    })
+}
+
+pub fn get_user_fact(id: i64) -> Result<Option<UserFact>> {
+    with_read_connection(|conn| {
+        let result = conn.query_row(
+            "SELECT id, category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count
+             FROM user_facts WHERE id = ?1",
+            params![id],
+            |row| Ok(UserFact {
+                id: row.get(0)?,
+                category: row.get(1)?,
+                key: row.get(2)?,
+                value: row.get(3)?,
+                confidence: row.get(4)?,
+                source_type: row.get(5)?,
+                source_conversation_id: row.get(6)?,
+                first_mentioned: row.get(7)?,
+                last_confirmed: row.get(8)?,
+                mention_count: row.get(9)?,
+            })
+        );
+        
+        match result {
+            Ok(fact) => Ok(Some(fact)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    })
+}
+
+/// Correct a fact by hand. A user edit counts as an explicit confirmation, so it becomes
+/// explicit at full confidence unless `confidence` says otherwise. Returns false if there's no such fact.
+pub fn update_user_fact(id: i64, value: &str, confidence: Option<f64>) -> Result<bool> {
     let now = Utc::now().to_rfc3339();
-    let id = uuid::Uuid::new_v4().to_string();
-    
     with_connection(|conn| {
+        let updated = conn.execute(
+            "UPDATE user_facts SET value = ?1, confidence = ?2, source_type = 'explicit', last_confirmed = ?3, decayed_at = NULL WHERE id = ?4",
+            params![value, confidence.unwrap_or(1.0).clamp(0.0, 1.0), now, id]
+        )?;
+        // Editing a fact is reviewing it
+        conn.execute("DELETE FROM fact_reviews WHERE fact_id = ?1", params![id])?;
+        Ok(updated > 0)
+    })
+}
+
+/// Delete a fact and its search embedding; returns false if there's no such fact
+pub fn delete_user_fact(id: i64) -> Result<bool> {
+    with_connection(|conn| {
+        record_fact_tombstones(conn, "id = ?1", params![id])?;
+        let removed = conn.execute("DELETE FROM user_facts WHERE id = ?1", params![id])?;
         conn.execute(
-            "INSERT INTO journey_sessions (id, profile_id, conversation_id, phase, phase_confirmed, completed, started_at)
-             VALUES (?1, ?2, ?3, 'exploration', 0, 0, ?4)",
-            params![id, profile_id, conversation_id, now]
+            "DELETE FROM embeddings WHERE source_type = 'fact' AND source_id = ?1",
+            params![id.to_string()]
         )?;
-        
-        Ok(JourneySession {
-            id,
-            profile_id: profile_id.to_string(),
-            conversation_id: conversation_id.to_string(),
-            phase: "exploration".to_string(),
-            phase_confirmed: false,
-            problem_summary: None,
-            resolution_summary: None,
-            acceptance_summary: None,
-            completed: false,
-            started_at: now,
-            completed_at: None,
-        })
+        conn.execute("DELETE FROM fact_reviews WHERE fact_id = ?1", params![id])?;
+        Ok(removed > 0)
     })
 }
 
-/// Get journey session by conversation ID
-pub fn get_journey_session_by_conversation(conversation_id: &str) -> Result<Option<JourneySession>> {
+/// A fact waiting for the user to confirm, correct or delete it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FactReview {
+    #[serde(flatten)]
+    pub fact: UserFact,
+    pub reason: String,
+    pub flagged_at: String,
+}
+
+/// Flag the facts a conversation produced in [since, until) for review; returns how many
+pub fn flag_facts_for_review(conversation_id: &str, since: &str, until: Option<&str>, reason: &str) -> Result<usize> {
+    let now = Utc::now().to_rfc3339();
     with_connection(|conn| {
-        let result = conn.query_row(
-            "SELECT id, profile_id, conversation_id, phase, phase_confirmed, problem_summary, 
-                    resolution_summary, acceptance_summary, completed, started_at, completed_at
-             FROM journey_sessions WHERE conversation_id = ?1",
-            params![conversation_id],
-            |row| {
-                Ok(JourneySession {
+        conn.execute(
+            "INSERT OR REPLACE INTO fact_reviews (fact_id, reason, flagged_at)
+             SELECT id, ?4, ?5 FROM user_facts
+             WHERE source_conversation_id = ?1
+               AND ((first_mentioned >= ?2 AND (?3 IS NULL OR first_mentioned < ?3))
+                 OR (last_confirmed >= ?2 AND (?3 IS NULL OR last_confirmed < ?3)))",
+            params![conversation_id, since, until, reason, now]
+        )
+    })
+}
+
+/// Facts flagged for review, newest flag first
+pub fn get_facts_for_review() -> Result<Vec<FactReview>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT f.id, f.category, f.key, f.value, f.confidence, f.source_type, f.source_conversation_id, f.first_mentioned, f.last_confirmed, f.mention_count,
+                    r.reason, r.flagged_at
+             FROM fact_reviews r JOIN user_facts f ON f.id = r.fact_id
+             ORDER BY r.flagged_at DESC"
+        )?;
+        let reviews = stmt.query_map([], |row| {
+            Ok(FactReview {
+                fact: UserFact {
                     id: row.get(0)?,
-                    profile_id: row.get(1)?,
-                    conversation_id: row.get(2)?,
-                    phase: row.get(3)?,
-                    phase_confirmed: row.get::<_, i64>(4)? != 0,
-                    problem_summary: row.get(5)?,
-                    resolution_summary: row.get(6)?,
-                    acceptance_summary: row.get(7)?,
-                    completed: row.get::<_, i64>(8)? != 0,
-                    started_at: row.get(9)?,
-                    completed_at: row.get(10)?,
-                })
-            }
-        ).optional()?;
-        
-        Ok(result)
+                    category: row.get(1)?,
+                    key: row.get(2)?,
+                    value: row.get(3)?,
+                    confidence: row.get(4)?,
+                    source_type: row.get(5)?,
+                    source_conversation_id: row.get(6)?,
+                    first_mentioned: row.get(7)?,
+                    last_confirmed: row.get(8)?,
+                    mention_count: row.get(9)?,
+                },
+                reason: row.get(10)?,
+                flagged_at: row.get(11)?,
+            })
+        })?;
+        reviews.collect()
     })
 }
 
-/// Update journey phase (Governor suggests transition)
-pub fn update_journey_phase(session_id: &str, new_phase: &str, summary: Option<&str>) -> Result<()> {
+/// Keep a flagged fact as it is; returns false if it wasn't flagged
+pub fn clear_fact_review(fact_id: i64) -> Result<bool> {
+    with_connection(|conn| {
+        let removed = conn.execute("DELETE FROM fact_reviews WHERE fact_id = ?1", params![fact_id])?;
+        Ok(removed > 0)
+    })
+}
+
+/// Fact keys are matched case-insensitively ("Sister_Name" is "sister_name")
+fn normalize_fact_key(key: &str) -> String {
+    key.trim().to_lowercase()
+}
+
+/// Forget every fact stored under `key` (in any category) and keep extraction from storing it
+/// again for `block_days`; returns how many facts were removed
+pub fn forget_fact(key: &str, block_days: i64) -> Result<usize> {
+    let key = normalize_fact_key(key);
+    let now = Utc::now();
     with_connection(|conn| {
-        // Update phase and reset confirmation flag
         conn.execute(
-            "UPDATE journey_sessions SET phase = ?1, phase_confirmed = 0 WHERE id = ?2",
-            params![new_phase, session_id]
+            "DELETE FROM embeddings WHERE source_type = 'fact'
+             AND source_id IN (SELECT CAST(id AS TEXT) FROM user_facts WHERE lower(key) = ?1)",
+            params![key]
         )?;
-        
-        // Store summary for the previous phase based on which phase we're entering
-        if let Some(summary_text) = summary {
-            match new_phase {
-                "resolution" => {
-                    conn.execute(
-                        "UPDATE journey_sessions SET problem_summary = ?1 WHERE id = ?2",
-                        params![summary_text, session_id]
-                    )?;
-                }
-                "acceptance" => {
-                    conn.execute(
-                        "UPDATE journey_sessions SET resolution_summary = ?1 WHERE id = ?2",
-                        params![summary_text, session_id]
-                    )?;
-                }
-                _ => {}
-            }
-        }
-        
-        Ok(())
+        record_fact_tombstones(conn, "lower(key) = ?1", params![key])?;
+        conn.execute(
+            "DELETE FROM fact_reviews WHERE fact_id IN (SELECT id FROM user_facts WHERE lower(key) = ?1)",
+            params![key]
+        )?;
+        let removed = conn.execute("DELETE FROM user_facts WHERE lower(key) = ?1", params![key])?;
+        conn.execute(
+            "INSERT OR REPLACE INTO forgotten_facts (key, forgotten_at, blocked_until) VALUES (?1, ?2, ?3)",
+            params![key, now.to_rfc3339(), (now + chrono::Duration::days(block_days)).to_rfc3339()]
+        )?;
+        Ok(removed)
     })
 }
 
-/// Confirm phase transition (user accepts Governor's suggestion)
-pub fn confirm_journey_phase(session_id: &str) -> Result<()> {
+/// Whether extraction is currently kept from storing a fact under `key`
+pub fn is_fact_key_blocked(key: &str) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT COUNT(*) FROM forgotten_facts WHERE key = ?1 AND blocked_until > ?2",
+            params![normalize_fact_key(key), now],
+            |row| Ok(row.get::<_, i64>(0)? > 0)
+        )
+    })
+}
+
+/// Store a fact the user explicitly asked to be remembered: explicit, full confidence, and
+/// clears any forget block on the key. Returns the fact's id.
+pub fn remember_user_fact(category: &str, key: &str, value: &str, source_conversation_id: Option<&str>) -> Result<i64> {
+    let now = Utc::now().to_rfc3339();
     with_connection(|conn| {
         conn.execute(
-            "UPDATE journey_sessions SET phase_confirmed = 1 WHERE id = ?1",
-            params![session_id]
+            "INSERT INTO user_facts (category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count)
+             VALUES (?1, ?2, ?3, 1.0, 'explicit', ?4, ?5, ?5, 1)
+             ON CONFLICT(category, key) DO UPDATE SET
+                value = ?3,
+                confidence = 1.0,
+                source_type = 'explicit',
+                last_confirmed = ?5,
+                decayed_at = NULL,
+                mention_count = mention_count + 1",
+            params![category, key, value, source_conversation_id, now]
         )?;
-        Ok(())
+        let id = conn.query_row(
+            "SELECT id FROM user_facts WHERE category = ?1 AND key = ?2",
+            params![category, key],
+            |row| row.get(0)
+        )?;
+        conn.execute("DELETE FROM forgotten_facts WHERE key = ?1", params![normalize_fact_key(key)])?;
+        Ok(id)
     })
 }
 
-/// Complete a journey session
-pub fn complete_journey_session(session_id: &str, acceptance_summary: Option<&str>) -> Result<()> {
+// ============ User Patterns ============
+
+pub fn save_user_pattern(pattern: &UserPattern) -> Result<()> {
     let now = Utc::now().to_rfc3339();
-    
     with_connection(|conn| {
-        // Get the profile ID for this session to increment counter
-        let profile_id: String = conn.query_row(
-            "SELECT profile_id FROM journey_sessions WHERE id = ?1",
-            params![session_id],
+        // Check if pattern with same type and similar description exists
+        let existing: Option<i64> = conn.query_row(
+            "SELECT id FROM user_patterns WHERE pattern_type = ?1 AND description = ?2",
+            params![pattern.pattern_type, pattern.description],
             |row| row.get(0)
-        )?;
+        ).ok();
         
-        // Mark session as complete
-        conn.execute(
-            "UPDATE journey_sessions SET completed = 1, completed_at = ?1, acceptance_summary = ?2, phase = 'acceptance', phase_confirmed = 1 WHERE id = ?3",
-            params![now, acceptance_summary, session_id]
+        if let Some(id) = existing {
+            // Update existing pattern
+            conn.execute(
+                "UPDATE user_patterns SET confidence = MIN(1.0, confidence + 0.1), observation_count = observation_count + 1, last_updated = ?1, decayed_at = NULL, evidence = ?2 WHERE id = ?3",
+                params![now, pattern.evidence, id]
+            )?;
+        } else {
+            // Insert new pattern
+            conn.execute(
+                "INSERT INTO user_patterns (pattern_type, description, confidence, evidence, first_observed, last_updated, observation_count)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    pattern.pattern_type,
+                    pattern.description,
+                    pattern.confidence,
+                    pattern.evidence,
+                    pattern.first_observed,
+                    pattern.last_updated,
+                    pattern.observation_count
+                ]
+            )?;
+        }
+        Ok(())
+    })
+}
+
+pub fn get_all_user_patterns() -> Result<Vec<UserPattern>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, pattern_type, description, confidence, evidence, first_observed, last_updated, observation_count
+             FROM user_patterns ORDER BY confidence DESC, last_updated DESC, observation_count DESC"
         )?;
         
-        // Increment the journey sessions counter for this profile
-        conn.execute(
-            "UPDATE persona_profiles SET journey_sessions_completed = COALESCE(journey_sessions_completed, 0) + 1 WHERE id = ?1",
-            params![profile_id]
-        )?;
+        let patterns = stmt.query_map([], |row| {
+            Ok(UserPattern {
+                id: row.get(0)?,
+                pattern_type: row.get(1)?,
+                description: row.get(2)?,
+                confidence: row.get(3)?,
+                evidence: row.get(4)?,
+                first_observed: row.get(5)?,
+                last_updated: row.get(6)?,
+                observation_count: row.get(7)?,
+            })
+        })?;
         
-        Ok(())
+        patterns.collect()
     })
 }
 
-/// Get journey sessions completed count for a profile
-pub fn get_journey_sessions_completed(profile_id: &str) -> Result<i64> {
+/// Returns false if there's no such pattern
+pub fn delete_user_pattern(id: i64) -> Result<bool> {
     with_connection(|conn| {
-        let count: i64 = conn.query_row(
-            "SELECT COALESCE(journey_sessions_completed, 0) FROM persona_profiles WHERE id = ?1",
-            params![profile_id],
-            |row| row.get(0)
-        ).unwrap_or(0);
+        let removed = conn.execute("DELETE FROM user_patterns WHERE id = ?1", params![id])?;
+        Ok(removed > 0)
+    })
+}
+
+// ============ Consolidation ============
+// Near-duplicate facts and patterns are merged into one row: counts add up, the earliest and
+// latest timestamps are kept, and confidence is combined as independent evidence (noisy-OR).
+
+/// Confidence from several independent observations: 1 - Π(1 - c)
+pub fn combine_confidences(confidences: &[f64]) -> f64 {
+    let doubt: f64 = confidences.iter().map(|c| 1.0 - c.clamp(0.0, 1.0)).product();
+    1.0 - doubt
+}
+
+fn id_list(ids: &[i64]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// Merge the facts in `merge_ids` into `keep_id` under the given category/key/value.
+/// Returns false (changing nothing) if any of the facts no longer exists.
+pub fn merge_user_facts(keep_id: i64, merge_ids: &[i64], category: &str, key: &str, value: &str) -> Result<bool> {
+    let mut all_ids = vec![keep_id];
+    all_ids.extend(merge_ids.iter().filter(|id| **id != keep_id));
+    with_connection(|conn| {
+        let rows: Vec<(f64, String, String, String, i64)> = {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT confidence, source_type, first_mentioned, last_confirmed, mention_count FROM user_facts WHERE id IN ({})",
+                id_list(&all_ids)
+            ))?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        if rows.len() != all_ids.len() || all_ids.len() < 2 {
+            return Ok(false);
+        }
         
-        Ok(count)
+        let confidences: Vec<f64> = rows.iter().map(|r| r.0).collect();
+        let source_type = if rows.iter().any(|r| r.1 == "explicit") { "explicit" } else { "inferred" };
+        let first_mentioned = rows.iter().map(|r| r.2.clone()).min().unwrap_or_default();
+        let last_confirmed = rows.iter().map(|r| r.3.clone()).max().unwrap_or_default();
+        let mention_count: i64 = rows.iter().map(|r| r.4).sum();
+        
+        let merged = id_list(&all_ids[1..]);
+        conn.execute(
+            &format!("DELETE FROM embeddings WHERE source_type = 'fact' AND source_id IN ({})",
+                all_ids[1..].iter().map(|id| format!("'{}'", id)).collect::<Vec<_>>().join(",")),
+            []
+        )?;
+        record_fact_tombstones(conn, &format!("id IN ({})", merged), [])?;
+        conn.execute(&format!("DELETE FROM fact_reviews WHERE fact_id IN ({})", merged), [])?;
+        conn.execute(&format!("DELETE FROM user_facts WHERE id IN ({})", merged), [])?;
+        conn.execute(
+            "UPDATE user_facts SET category = ?1, key = ?2, value = ?3, confidence = ?4, source_type = ?5,
+                first_mentioned = ?6, last_confirmed = ?7, mention_count = ?8, decayed_at = NULL
+             WHERE id = ?9",
+            params![category, key, value, combine_confidences(&confidences), source_type,
+                first_mentioned, last_confirmed, mention_count, keep_id]
+        )?;
+        // The merged fact may have taken over one of the deleted keys
+        conn.execute(
+            "DELETE FROM sync_tombstones WHERE kind = 'fact' AND key = ?1",
+            params![fact_tombstone_key(category, key)]
+        )?;
+        Ok(true)
     })
 }
+
+/// Evidence is usually a JSON array of observations, but older rows hold a plain string
+fn evidence_items(evidence: &str) -> Vec<String> {
+    serde_json::from_str::<Vec<String>>(evidence)
+        .unwrap_or_else(|_| if evidence.trim().is_empty() { Vec::new() } else { vec![evidence.to_string()] })
+}
+
+/// Merge the patterns in `merge_ids` into `keep_id` with the given description, combining their evidence.
+/// Returns false (changing nothing) if any of the patterns no longer exists.
+pub fn merge_user_patterns(keep_id: i64, merge_ids: &[i64], description: &str) -> Result<bool> {
+    let mut all_ids = vec![keep_id];
+    all_ids.extend(merge_ids.iter().filter(|id| **id != keep_id));
+    with_connection(|conn| {
+        let rows: Vec<(f64, String, String, String, i64)> = {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT confidence, evidence, first_observed, last_updated, observation_count FROM user_patterns WHERE id IN ({})",
+                id_list(&all_ids)
+            ))?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        if rows.len() != all_ids.len() || all_ids.len() < 2 {
+            return Ok(false);
+        }
+        
+        let confidences: Vec<f64> = rows.iter().map(|r| r.0).collect();
+        let mut evidence: Vec<String> = Vec::new();
+        for item in rows.iter().flat_map(|r| evidence_items(&r.1)) {
+            if !evidence.contains(&item) {
+                evidence.push(item);
+            }
+        }
+        let first_observed = rows.iter().map(|r| r.2.clone()).min().unwrap_or_default();
+        let last_updated = rows.iter().map(|r| r.3.clone()).max().unwrap_or_default();
+        let observation_count: i64 = rows.iter().map(|r| r.4).sum();
+        
+        conn.execute(&format!("DELETE FROM user_patterns WHERE id IN ({})", id_list(&all_ids[1..])), [])?;
+        conn.execute(
+            "UPDATE user_patterns SET description = ?1, confidence = ?2, evidence = ?3,
+                first_observed = ?4, last_updated = ?5, observation_count = ?6, decayed_at = NULL
+             WHERE id = ?7",
+            params![description, combine_confidences(&confidences), serde_json::to_string(&evidence).unwrap_or_default(),
+                first_observed, last_updated, observation_count, keep_id]
+        )?;
+        Ok(true)
+    })
+}
+
+// ============ Confidence Decay ============
+// Facts and patterns lose confidence the longer they go unconfirmed, halving every half-life.
+// Each pass decays from the later of the last confirmation and the last pass, so running it
+// often compounds to the same result as running it once. Confirming again resets the clock.
+
+/// Half-life in days per fact category; stable facts (names, relationships) fade slowest
+const FACT_HALF_LIVES: [(&str, f64); 5] = [
+    ("personal", 730.0),
+    ("relationships", 730.0),
+    ("values", 540.0),
+    ("work", 365.0),
+    ("preferences", 180.0),
+];
+const DEFAULT_FACT_HALF_LIFE: f64 = 365.0;
+
+/// Half-life in days per pattern type; moods shift faster than communication style
+const PATTERN_HALF_LIVES: [(&str, f64); 4] = [
+    ("communication_style", 365.0),
+    ("thinking_mode", 365.0),
+    ("emotional_tendency", 180.0),
+    ("recurring_theme", 120.0),
+];
+const DEFAULT_PATTERN_HALF_LIFE: f64 = 270.0;
+
+/// Decay never takes confidence below this; stale memories are de-emphasized, not forgotten
+const MIN_DECAYED_CONFIDENCE: f64 = 0.1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DecaySummary {
+    pub facts: usize,    // Facts whose confidence dropped
+    pub patterns: usize, // Patterns whose confidence dropped
+}
+
+fn half_life(table: &[(&str, f64)], kind: &str, default: f64) -> f64 {
+    table.iter().find(|(k, _)| *k == kind).map(|(_, days)| *days).unwrap_or(default)
+}
+
+/// Confidence after `days` unconfirmed, floored at MIN_DECAYED_CONFIDENCE (never raised)
+pub fn decayed_confidence(confidence: f64, days: f64, half_life_days: f64) -> f64 {
+    if days <= 0.0 || confidence <= MIN_DECAYED_CONFIDENCE {
+        return confidence;
+    }
+    (confidence * 0.5f64.powf(days / half_life_days)).max(MIN_DECAYED_CONFIDENCE)
+}
+
+fn decay_table(
+    conn: &Connection,
+    now: DateTime<Utc>,
+    select: &str,
+    update: &str,
+    half_lives: &[(&str, f64)],
+    default_half_life: f64,
+) -> Result<usize> {
+    let rows: Vec<(i64, String, f64, String)> = {
+        let mut stmt = conn.prepare(select)?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect::<Result<Vec<_>>>()?
+    };
+    
+    let now_str = now.to_rfc3339();
+    let mut decayed = 0;
+    for (id, kind, confidence, since) in rows {
+        let Ok(since) = DateTime::parse_from_rfc3339(&since) else { continue };
+        let days = (now - since.with_timezone(&Utc)).num_seconds() as f64 / 86_400.0;
+        let updated = decayed_confidence(confidence, days, half_life(half_lives, &kind, default_half_life));
+        if updated < confidence {
+            conn.execute(update, params![updated, now_str, id])?;
+            decayed += 1;
+        }
+    }
+    Ok(decayed)
+}
+
+/// Apply confidence decay to every fact and pattern as of `now`
+pub fn decay_confidences(now: DateTime<Utc>) -> Result<DecaySummary> {
+    with_connection(|conn| {
+        let facts = decay_table(
+            conn,
+            now,
+            "SELECT id, category, confidence, COALESCE(decayed_at, last_confirmed) FROM user_facts",
+            "UPDATE user_facts SET confidence = ?1, decayed_at = ?2 WHERE id = ?3",
+            &FACT_HALF_LIVES,
+            DEFAULT_FACT_HALF_LIFE,
+        )?;
+        let patterns = decay_table(
+            conn,
+            now,
+            "SELECT id, pattern_type, confidence, COALESCE(decayed_at, last_updated) FROM user_patterns",
+            "UPDATE user_patterns SET confidence = ?1, decayed_at = ?2 WHERE id = ?3",
+            &PATTERN_HALF_LIVES,
+            DEFAULT_PATTERN_HALF_LIFE,
+        )?;
+        Ok(DecaySummary { facts, patterns })
+    })
+}
+
+// ============ API Usage ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsageTotal {
+    pub provider: String,
+    pub model: String,
+    pub calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+pub fn record_api_usage(provider: &str, model: &str, input_tokens: u64, output_tokens: u64, cost_usd: f64) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO api_usage (provider, model, input_tokens, output_tokens, cost_usd, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![provider, model, input_tokens as i64, output_tokens as i64, cost_usd, now]
+        )?;
+        Ok(())
+    })
+}
+
+/// Estimated spend with a provider since an RFC 3339 instant
+pub fn get_spend_since(provider: &str, since: &str) -> Result<f64> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT COALESCE(SUM(cost_usd), 0) FROM api_usage WHERE provider = ?1 AND created_at >= ?2",
+            params![provider, since],
+            |row| row.get(0)
+        )
+    })
+}
+
+/// Calls, tokens and spend per provider and model since an RFC 3339 instant, most expensive first
+pub fn get_usage_totals(since: &str) -> Result<Vec<UsageTotal>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT provider, model, COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+             FROM api_usage WHERE created_at >= ?1
+             GROUP BY provider, model
+             ORDER BY SUM(cost_usd) DESC"
+        )?;
+        let totals = stmt.query_map(params![since], |row| {
+            Ok(UsageTotal {
+                provider: row.get(0)?,
+                model: row.get(1)?,
+                calls: row.get(2)?,
+                input_tokens: row.get(3)?,
+                output_tokens: row.get(4)?,
+                cost_usd: row.get(5)?,
+            })
+        })?;
+        totals.collect()
+    })
+}
+
+// ============ Imported Conversations ============
+
+/// Write a conversation from a ChatGPT / Claude export in one transaction, keeping its original times.
+/// It's stored processed (it was never live, so crash recovery must not finalize it); with `extract_memory`
+/// it's queued for background memory extraction instead. Returns None if this export was imported before.
+pub fn save_imported_conversation(
+    source: &str,
+    external_id: &str,
+    title: &str,
+    created_at: &str,
+    updated_at: &str,
+    messages: &[(String, String, String)], // (role, content, timestamp)
+    extract_memory: bool,
+) -> Result<Option<String>> {
+    with_connection(|conn| {
+        let already_imported: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM imported_conversations WHERE source = ?1 AND external_id = ?2",
+            params![source, external_id],
+            |row| row.get(0)
+        )?;
+        if already_imported > 0 {
+            return Ok(None);
+        }
+        
+        let id = uuid::Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO conversations (id, title, summary, processed, is_disco, persona_profile_id, created_at, updated_at, last_seq)
+             VALUES (?1, ?2, NULL, 1, 0, (SELECT id FROM persona_profiles WHERE is_active = 1), ?3, ?4, ?5)",
+            params![id, title, created_at, updated_at, messages.len() as i64]
+        )?;
+        for (seq, (role, content, timestamp)) in messages.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO messages (id, conversation_id, role, content, timestamp, seq)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![uuid::Uuid::new_v4().to_string(), id, role, content, timestamp, seq as i64 + 1]
+            )?;
+        }
+        conn.execute(
+            "INSERT INTO imported_conversations (conversation_id, source, external_id, extraction_pending, imported_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, source, external_id, extract_memory as i64, Utc::now().to_rfc3339()]
+        )?;
+        Ok(Some(id))
+    })
+}
+
+/// Imported conversations still waiting for memory extraction, oldest first
+pub fn get_pending_import_extractions(limit: usize) -> Result<Vec<String>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT i.conversation_id FROM imported_conversations i
+             JOIN conversations c ON c.id = i.conversation_id
+             WHERE i.extraction_pending = 1
+             ORDER BY c.created_at ASC
+             LIMIT ?1"
+        )?;
+        let ids = stmt.query_map(params![limit as i64], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        Ok(ids)
+    })
+}
+
+pub fn mark_import_extracted(conversation_id: &str) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE imported_conversations SET extraction_pending = 0 WHERE conversation_id = ?1",
+            params![conversation_id]
+        )?;
+        Ok(())
+    })
+}
+
+// ============ Pending Messages (offline queue) ============
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMessage {
+    pub id: String,
+    pub conversation_id: String,
+    pub content: String,
+    pub active_agents: Vec<String>,
+    pub disco_agents: Vec<String>,
+    pub attachment_ids: Vec<String>,
+    pub reply_to: Option<String>,
+    pub voice: bool,
+    pub queued_at: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+}
+
+pub fn queue_pending_message(
+    conversation_id: &str,
+    content: &str,
+    active_agents: &[String],
+    disco_agents: &[String],
+    attachment_ids: &[String],
+    reply_to: Option<&str>,
+    voice: bool,
+) -> Result<PendingMessage> {
+    let pending = PendingMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.to_string(),
+        content: content.to_string(),
+        active_agents: active_agents.to_vec(),
+        disco_agents: disco_agents.to_vec(),
+        attachment_ids: attachment_ids.to_vec(),
+        reply_to: reply_to.map(str::to_string),
+        voice,
+        queued_at: Utc::now().to_rfc3339(),
+        attempts: 0,
+        last_error: None,
+    };
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO pending_messages (id, conversation_id, content, active_agents, disco_agents, attachment_ids, queued_at, reply_to, voice)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                pending.id,
+                pending.conversation_id,
+                pending.content,
+                serde_json::to_string(&pending.active_agents).unwrap_or_else(|_| "[]".to_string()),
+                serde_json::to_string(&pending.disco_agents).unwrap_or_else(|_| "[]".to_string()),
+                serde_json::to_string(&pending.attachment_ids).unwrap_or_else(|_| "[]".to_string()),
+                pending.queued_at,
+                pending.reply_to,
+                pending.voice,
+            ]
+        )?;
+        Ok(())
+    })?;
+    Ok(pending)
+}
+
+/// Queued messages in the order they were written (optionally for one conversation)
+pub fn get_pending_messages(conversation_id: Option<&str>) -> Result<Vec<PendingMessage>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, content, active_agents, disco_agents, queued_at, attempts, last_error, attachment_ids, reply_to, COALESCE(voice, 0)
+             FROM pending_messages
+             WHERE ?1 IS NULL OR conversation_id = ?1
+             ORDER BY queued_at ASC"
+        )?;
+        let pending = stmt.query_map(params![conversation_id], |row| {
+            let active: String = row.get(3)?;
+            let disco: String = row.get(4)?;
+            let attachments: Option<String> = row.get(8)?;
+            Ok(PendingMessage {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                content: row.get(2)?,
+                active_agents: serde_json::from_str(&active).unwrap_or_default(),
+                disco_agents: serde_json::from_str(&disco).unwrap_or_default(),
+                attachment_ids: attachments.and_then(|a| serde_json::from_str(&a).ok()).unwrap_or_default(),
+                reply_to: row.get(9)?,
+                voice: row.get::<_, i64>(10)? != 0,
+                queued_at: row.get(5)?,
+                attempts: row.get(6)?,
+                last_error: row.get(7)?,
+            })
+        })?;
+        pending.collect()
+    })
+}
+
+pub fn record_pending_attempt(id: &str, error: &str) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE pending_messages SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+            params![id, error]
+        )?;
+        Ok(())
+    })
+}
+
+/// Drop a queued message (sent, or discarded by the user); returns false if it wasn't queued
+pub fn delete_pending_message(id: &str) -> Result<bool> {
+    with_connection(|conn| {
+        let removed = conn.execute("DELETE FROM pending_messages WHERE id = ?1", params![id])?;
+        Ok(removed > 0)
+    })
+}
+
+// ============ Attachments ============
+
+const ATTACHMENT_COLUMNS: &str = "id, conversation_id, message_id, kind, mime_type, file_name, path, size_bytes, description, created_at";
+
+fn row_to_attachment(row: &rusqlite::Row) -> Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        message_id: row.get(2)?,
+        kind: row.get(3)?,
+        mime_type: row.get(4)?,
+        file_name: row.get(5)?,
+        path: row.get(6)?,
+        size_bytes: row.get(7)?,
+        description: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+/// Fill in each message's attachments (one query per message that has any)
+fn load_attachments(conn: &Connection, messages: &mut [Message]) -> Result<()> {
+    let has_any: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM attachments WHERE message_id IS NOT NULL)", [], |row| row.get(0))?;
+    if !has_any {
+        return Ok(());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM attachments WHERE message_id = ?1 ORDER BY created_at ASC", ATTACHMENT_COLUMNS
+    ))?;
+    for message in messages.iter_mut() {
+        message.attachments = stmt.query_map(params![message.id], row_to_attachment)?
+            .collect::<Result<Vec<_>>>()?;
+    }
+    Ok(())
+}
+
+pub fn save_attachment(attachment: &Attachment) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            &format!("INSERT INTO attachments ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)", ATTACHMENT_COLUMNS),
+            params![
+                attachment.id, attachment.conversation_id, attachment.message_id, attachment.kind,
+                attachment.mime_type, attachment.file_name, attachment.path, attachment.size_bytes,
+                attachment.description, attachment.created_at
+            ]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_attachment(id: &str) -> Result<Option<Attachment>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            &format!("SELECT {} FROM attachments WHERE id = ?1", ATTACHMENT_COLUMNS),
+            params![id],
+            row_to_attachment
+        ).optional()
+    })
+}
+
+/// Attach pending uploads to a message; only unlinked attachments from the same conversation move
+pub fn link_attachments(message_id: &str, conversation_id: &str, attachment_ids: &[String]) -> Result<usize> {
+    with_connection(|conn| {
+        let mut linked = 0;
+        for id in attachment_ids {
+            linked += conn.execute(
+                "UPDATE attachments SET message_id = ?1 WHERE id = ?2 AND conversation_id = ?3 AND message_id IS NULL",
+                params![message_id, id, conversation_id]
+            )?;
+        }
+        Ok(linked)
+    })
+}
+
+pub fn get_message_attachments(message_id: &str) -> Result<Vec<Attachment>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attachments WHERE message_id = ?1 ORDER BY created_at ASC", ATTACHMENT_COLUMNS
+        ))?;
+        let attachments = stmt.query_map(params![message_id], row_to_attachment)?;
+        attachments.collect()
+    })
+}
+
+/// Every attachment in a conversation, sent or pending (for cleaning up files)
+pub fn get_conversation_attachments(conversation_id: &str) -> Result<Vec<Attachment>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attachments WHERE conversation_id = ?1", ATTACHMENT_COLUMNS
+        ))?;
+        let attachments = stmt.query_map(params![conversation_id], row_to_attachment)?;
+        attachments.collect()
+    })
+}
+
+/// Delete the attachment rows of the messages matching `filter` (a WHERE clause on messages); returns them
+fn delete_message_attachments<P: rusqlite::Params + Copy>(conn: &Connection, filter: &str, params: P) -> Result<Vec<Attachment>> {
+    let scope = format!("message_id IN (SELECT id FROM messages WHERE {})", filter);
+    let removed = conn.prepare(&format!("SELECT {} FROM attachments WHERE {}", ATTACHMENT_COLUMNS, scope))?
+        .query_map(params, row_to_attachment)?
+        .collect::<Result<Vec<_>>>()?;
+    conn.execute(&format!("DELETE FROM attachments WHERE {}", scope), params)?;
+    Ok(removed)
+}
+
+/// Return a message's attachments to pending (its send is being undone and queued for later); returns their ids
+pub fn unlink_attachments(message_id: &str) -> Result<Vec<String>> {
+    with_connection(|conn| {
+        let ids = conn.prepare("SELECT id FROM attachments WHERE message_id = ?1 ORDER BY created_at ASC")?
+            .query_map(params![message_id], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        conn.execute("UPDATE attachments SET message_id = NULL WHERE message_id = ?1", params![message_id])?;
+        Ok(ids)
+    })
+}
+
+pub fn set_attachment_description(id: &str, description: &str) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE attachments SET description = ?1 WHERE id = ?2",
+            params![description, id]
+        )?;
+        Ok(())
+    })
+}
+
+/// Remove an attachment row; returns it so the caller can delete the stored file
+pub fn delete_attachment(id: &str) -> Result<Option<Attachment>> {
+    let attachment = get_attachment(id)?;
+    if attachment.is_some() {
+        with_connection(|conn| conn.execute("DELETE FROM attachments WHERE id = ?1", params![id]))?;
+    }
+    Ok(attachment)
+}
+
+// ============ Documents ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Document {
+    pub id: String,
+    pub title: String,
+    pub source_path: String,
+    pub kind: String,               // "pdf" | "markdown" | "text"
+    pub summary: Option<String>,
+    pub char_count: i64,
+    pub chunk_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentChunk {
+    pub id: i64,
+    pub document_id: String,
+    pub document_title: String,
+    pub chunk_index: i64,
+    pub content: String,
+}
+
+/// Save a document and its chunks together
+pub fn save_document(document: &Document, chunks: &[String]) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO documents (id, title, source_path, kind, summary, char_count, chunk_count, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                document.id, document.title, document.source_path, document.kind, document.summary,
+                document.char_count, document.chunk_count, document.created_at
+            ]
+        )?;
+        for (index, chunk) in chunks.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO document_chunks (document_id, chunk_index, content) VALUES (?1, ?2, ?3)",
+                params![document.id, index as i64, chunk]
+            )?;
+        }
+        Ok(())
+    })
+}
+
+pub fn get_documents() -> Result<Vec<Document>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, title, source_path, kind, summary, char_count, chunk_count, created_at
+             FROM documents ORDER BY created_at DESC"
+        )?;
+        let documents = stmt.query_map([], |row| {
+            Ok(Document {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                source_path: row.get(2)?,
+                kind: row.get(3)?,
+                summary: row.get(4)?,
+                char_count: row.get(5)?,
+                chunk_count: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+        documents.collect()
+    })
+}
+
+pub fn has_documents() -> Result<bool> {
+    with_read_connection(|conn| {
+        conn.query_row("SELECT EXISTS(SELECT 1 FROM documents)", [], |row| row.get(0))
+    })
+}
+
+/// A chunk with its document's title (for citing it in prompts)
+pub fn get_document_chunk(chunk_id: i64) -> Result<Option<DocumentChunk>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT c.id, c.document_id, d.title, c.chunk_index, c.content
+             FROM document_chunks c JOIN documents d ON d.id = c.document_id
+             WHERE c.id = ?1",
+            params![chunk_id],
+            |row| Ok(DocumentChunk {
+                id: row.get(0)?,
+                document_id: row.get(1)?,
+                document_title: row.get(2)?,
+                chunk_index: row.get(3)?,
+                content: row.get(4)?,
+            })
+        ).optional()
+    })
+}
+
+/// Delete a document with its chunks and their embeddings; returns false if it didn't exist
+pub fn delete_document(id: &str) -> Result<bool> {
+    with_connection(|conn| {
+        conn.execute(
+            "DELETE FROM embeddings WHERE source_type = 'document'
+             AND source_id IN (SELECT CAST(id AS TEXT) FROM document_chunks WHERE document_id = ?1)",
+            params![id]
+        )?;
+        conn.execute("DELETE FROM document_chunks WHERE document_id = ?1", params![id])?;
+        let removed = conn.execute("DELETE FROM documents WHERE id = ?1", params![id])?;
+        Ok(removed > 0)
+    })
+}
+
+// ============ Conversation Tags ============
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationTag {
+    pub tag: String,
+    pub source: String, // "user" | "auto"
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub conversations: i64,
+}
+
+/// Tags are case-insensitive and whitespace-collapsed so "Work " and "work" are one group
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Tag a conversation; returns false if it already had the tag (a user tag is never downgraded to auto)
+pub fn add_conversation_tag(conversation_id: &str, tag: &str, source: &str) -> Result<bool> {
+    let tag = normalize_tag(tag);
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let added = conn.execute(
+            "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag, source, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![conversation_id, tag, source, now]
+        )?;
+        if added == 0 && source == "user" {
+            conn.execute(
+                "UPDATE conversation_tags SET source = 'user' WHERE conversation_id = ?1 AND tag = ?2",
+                params![conversation_id, tag]
+            )?;
+        }
+        Ok(added > 0)
+    })
+}
+
+/// Returns false if the conversation didn't have the tag
+pub fn remove_conversation_tag(conversation_id: &str, tag: &str) -> Result<bool> {
+    with_connection(|conn| {
+        let removed = conn.execute(
+            "DELETE FROM conversation_tags WHERE conversation_id = ?1 AND tag = ?2",
+            params![conversation_id, normalize_tag(tag)]
+        )?;
+        Ok(removed > 0)
+    })
+}
+
+pub fn get_conversation_tags(conversation_id: &str) -> Result<Vec<ConversationTag>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT tag, source, created_at FROM conversation_tags WHERE conversation_id = ?1 ORDER BY tag ASC"
+        )?;
+        let tags = stmt.query_map(params![conversation_id], |row| {
+            Ok(ConversationTag {
+                tag: row.get(0)?,
+                source: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+        tags.collect()
+    })
+}
+
+/// Every tag in use with how many conversations carry it, most used first
+pub fn get_all_tags() -> Result<Vec<TagCount>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT tag, COUNT(*) FROM conversation_tags GROUP BY tag ORDER BY COUNT(*) DESC, tag ASC"
+        )?;
+        let tags = stmt.query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                conversations: row.get(1)?,
+            })
+        })?;
+        tags.collect()
+    })
+}
+
+/// Non-empty conversations tagged `tag`, most recently updated first
+pub fn get_conversations_by_tag(tag: &str) -> Result<Vec<Conversation>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.title, c.summary, c.processed, c.is_disco, c.created_at, c.updated_at, c.model_override, c.parent_conversation_id, c.branch_point_message_id
+             FROM conversations c
+             JOIN conversation_tags t ON t.conversation_id = c.id
+             WHERE t.tag = ?1 AND EXISTS (SELECT 1 FROM messages WHERE conversation_id = c.id)
+             ORDER BY c.updated_at DESC"
+        )?;
+        
+        let convs = stmt.query_map(params![normalize_tag(tag)], |row| {
+            Ok(Conversation {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                summary: row.get(2)?,
+                processed: row.get::<_, i64>(3)? != 0,
+                is_disco: row.get::<_, i64>(4).unwrap_or(0) != 0,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+                model_override: row.get(7)?,
+                parent_conversation_id: row.get(8)?,
+                branch_point_message_id: row.get(9)?,
+            })
+        })?;
+        
+        convs.collect()
+    })
+}
+
+// ============ Data Archive ============
+// Whole-profile export/import for moving machines: every listed table dumped as JSON rows.
+// Columns are matched by name on import, so archives from older schemas load into newer ones.
+
+pub const ARCHIVE_FORMAT: &str = "intersect-archive";
+pub const ARCHIVE_VERSION: i64 = 1;
+
+/// Tables carried in an archive. Derived data (embeddings, search index, raw analytics) is rebuilt instead.
+const ARCHIVE_TABLES: [&str; 38] = [
+    "user_profile", "persona_profiles", "persona_weight_history", "persona_comparisons", "personality_snapshots", "app_settings",
+    "agent_prompts", "agent_prompt_versions",
+    "conversations", "messages", "turn_versions", "message_versions", "message_feedback", "limbo_entries", "agent_mutes",
+    "imported_conversations", "conversation_tags", "attachments",
+    "conversation_summaries", "user_context", "user_facts", "forgotten_facts", "fact_reviews", "user_patterns", "recurring_themes",
+    "agent_interactions", "agent_style_preferences", "decisions", "check_ins", "reminders", "mood_log", "habits", "habit_logs",
+    "journey_sessions", "notifications", "reports", "documents", "document_chunks",
+];
+
+/// API keys stay on the machine they were entered on
+const ARCHIVE_SECRET_COLUMNS: [&str; 3] = ["api_key", "anthropic_key", "gemini_key"];
+
+/// Settings that belong to this machine too: sync setup and its key (see sync.rs), the web search
+/// provider (holds its API key), MCP servers (their env holds tokens) and the local API (its bearer token)
+const ARCHIVE_LOCAL_SETTINGS: [&str; 5] = ["cloud_sync", "cloud_sync_key", "web_search", "mcp_servers", "local_api"];
+
+/// Settings archived with a credential blanked: (setting key, field name blanked wherever it appears).
+/// A field holding an object (like a header map) keeps its keys with every value emptied.
+const ARCHIVE_SCRUBBED_SETTINGS: [(&str, &str); 2] = [("webhooks", "secret"), ("openai_endpoint", "extra_headers")];
+
+fn blank_field(value: &mut serde_json::Value, field: &str) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                if name != field {
+                    blank_field(value, field);
+                } else if let serde_json::Value::Object(entries) = value {
+                    entries.values_mut().for_each(|v| *v = serde_json::Value::String(String::new()));
+                } else {
+                    *value = serde_json::Value::Null;
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| blank_field(item, field)),
+        _ => {}
+    }
+}
+
+/// An app_settings row with the credentials inside its JSON value blanked
+fn scrub_setting_row(mut row: serde_json::Value) -> serde_json::Value {
+    let Some(key) = row.get("key").and_then(|k| k.as_str()).map(str::to_string) else { return row };
+    let fields: Vec<&str> = ARCHIVE_SCRUBBED_SETTINGS.iter()
+        .filter(|(setting, _)| *setting == key)
+        .map(|(_, field)| *field)
+        .collect();
+    let parsed = row.get("value").and_then(|v| v.as_str()).and_then(|v| serde_json::from_str(v).ok());
+    if let (false, Some(mut value)) = (fields.is_empty(), parsed) {
+        fields.iter().for_each(|field| blank_field(&mut value, field));
+        row["value"] = serde_json::Value::String(value.to_string());
+    }
+    row
+}
+
+/// Attachment files are archived by their path inside the attachments dir, so the rows still
+/// point at them once that folder is copied to another machine
+fn relative_attachment_path(mut row: serde_json::Value, attachments_dir: &Path) -> serde_json::Value {
+    let relative = row.get("path").and_then(|p| p.as_str())
+        .and_then(|path| Path::new(path).strip_prefix(attachments_dir).ok())
+        .map(|path| path.to_string_lossy().to_string());
+    if let Some(relative) = relative {
+        row["path"] = serde_json::Value::String(relative);
+    }
+    row
+}
+
+fn absolute_attachment_path(mut row: serde_json::Value, attachments_dir: &Path) -> serde_json::Value {
+    let absolute = row.get("path").and_then(|p| p.as_str())
+        .filter(|path| Path::new(path).is_relative())
+        .map(|path| attachments_dir.join(path).to_string_lossy().to_string());
+    if let Some(absolute) = absolute {
+        row["path"] = serde_json::Value::String(absolute);
+    }
+    row
+}
+
+fn is_local_setting(table: &str, row: &serde_json::Value) -> bool {
+    table == "app_settings"
+        && row.get("key").and_then(|k| k.as_str()).is_some_and(|k| ARCHIVE_LOCAL_SETTINGS.contains(&k))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchiveTableCount {
+    pub table: String,
+    pub rows: usize,
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+    let columns = stmt.query_map([], |row| row.get(0))?;
+    columns.collect()
+}
+
+fn sql_to_json(value: rusqlite::types::ValueRef) -> serde_json::Value {
+    use base64::Engine;
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => serde_json::json!(i),
+        ValueRef::Real(f) => serde_json::json!(f),
+        ValueRef::Text(t) => serde_json::Value::String(String::from_utf8_lossy(t).to_string()),
+        ValueRef::Blob(b) => serde_json::json!({ "$blob": base64::engine::general_purpose::STANDARD.encode(b) }),
+    }
+}
+
+fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use base64::Engine;
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => n.as_i64().map(Value::Integer)
+            .unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or(0.0))),
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        serde_json::Value::Object(o) => o.get("$blob").and_then(|b| b.as_str())
+            .and_then(|b| base64::engine::general_purpose::STANDARD.decode(b).ok())
+            .map(Value::Blob)
+            .unwrap_or_else(|| Value::Text(value.to_string())),
+        serde_json::Value::Array(_) => Value::Text(value.to_string()),
+    }
+}
+
+/// Dump every archive table as { "format", "version", "exported_at", "tables": { name: [row objects] } }
+pub fn export_archive(attachments_dir: &Path) -> Result<serde_json::Value> {
+    with_read_connection(|conn| {
+        let mut tables = serde_json::Map::new();
+        for table in ARCHIVE_TABLES {
+            let columns = table_columns(conn, table)?;
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+            let rows = stmt.query_map([], |row| {
+                let mut object = serde_json::Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    let value = if ARCHIVE_SECRET_COLUMNS.contains(&column.as_str()) {
+                        serde_json::Value::Null
+                    } else {
+                        sql_to_json(row.get_ref(i)?)
+                    };
+                    object.insert(column.clone(), value);
+                }
+                Ok(serde_json::Value::Object(object))
+            })?.collect::<Result<Vec<_>>>()?;
+            let rows: Vec<serde_json::Value> = rows.into_iter()
+                .filter(|row| !is_local_setting(table, row))
+                .map(|row| match table {
+                    "app_settings" => scrub_setting_row(row),
+                    "attachments" => relative_attachment_path(row, attachments_dir),
+                    _ => row,
+                })
+                .collect();
+            tables.insert(table.to_string(), serde_json::Value::Array(rows));
+        }
+        
+        Ok(serde_json::json!({
+            "format": ARCHIVE_FORMAT,
+            "version": ARCHIVE_VERSION,
+            "exported_at": Utc::now().to_rfc3339(),
+            "tables": tables,
+        }))
+    })
+}
+
+/// Replace the archive tables with an archive's contents in one transaction.
+/// The current API keys and machine-local settings are kept. Returns how many rows were loaded per table.
+pub fn import_archive(archive: &serde_json::Value, attachments_dir: &Path) -> std::result::Result<Vec<ArchiveTableCount>, String> {
+    if archive.get("format").and_then(|f| f.as_str()) != Some(ARCHIVE_FORMAT) {
+        return Err("Not an Intersect archive".to_string());
+    }
+    let version = archive.get("version").and_then(|v| v.as_i64()).unwrap_or(0);
+    if version < 1 || version > ARCHIVE_VERSION {
+        return Err(format!("Unsupported archive version {} (this app reads up to {})", version, ARCHIVE_VERSION));
+    }
+    let tables = archive.get("tables").and_then(|t| t.as_object()).ok_or("Archive has no tables")?;
+    
+    with_connection(|conn| {
+        let keys: Option<(Option<String>, Option<String>, Option<String>)> = conn.query_row(
+            "SELECT api_key, anthropic_key, gemini_key FROM user_profile LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        ).optional()?;
+        
+        let mut counts = Vec::new();
+        for table in ARCHIVE_TABLES {
+            if table == "app_settings" {
+                conn.execute(
+                    &format!("DELETE FROM app_settings WHERE key NOT IN ('{}')", ARCHIVE_LOCAL_SETTINGS.join("', '")),
+                    []
+                )?;
+            } else {
+                conn.execute(&format!("DELETE FROM {}", table), [])?;
+            }
+            let Some(rows) = tables.get(table).and_then(|r| r.as_array()) else { continue };
+            let rows: Vec<serde_json::Value> = rows.iter()
+                .filter(|row| !is_local_setting(table, row))
+                .map(|row| if table == "attachments" { absolute_attachment_path(row.clone(), attachments_dir) } else { row.clone() })
+                .collect();
+            
+            let columns = table_columns(conn, table)?;
+            for row in &rows {
+                let Some(object) = row.as_object() else { continue };
+                let present: Vec<&String> = columns.iter().filter(|c| object.contains_key(c.as_str())).collect();
+                if present.is_empty() {
+                    continue;
+                }
+                let sql = format!(
+                    "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                    table,
+                    present.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", "),
+                    (1..=present.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+                );
+                let values: Vec<rusqlite::types::Value> = present.iter().map(|c| json_to_sql(&object[c.as_str()])).collect();
+                conn.execute(&sql, rusqlite::params_from_iter(values))?;
+            }
+            counts.push(ArchiveTableCount { table: table.to_string(), rows: rows.len() });
+        }
+        
+        if let Some((api_key, anthropic_key, gemini_key)) = keys {
+            conn.execute(
+                "UPDATE user_profile SET api_key = ?1, anthropic_key = ?2, gemini_key = ?3",
+                params![api_key, anthropic_key, gemini_key]
+            )?;
+        }
+        Ok(counts)
+    }).map_err(|e| e.to_string())
+}
+
+// ============ Diagnostics ============
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DatabaseStats {
+    pub size_bytes: u64, // Database file plus its write-ahead log
+    pub schema_version: i64,
+    pub tables: Vec<ArchiveTableCount>,
+}
+
+/// Size, schema version and row counts of every table, for bug reports
+pub fn database_stats() -> Result<DatabaseStats> {
+    with_read_connection(|conn| {
+        let names: Vec<String> = {
+            let mut stmt = conn.prepare(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let tables = names.into_iter()
+            .map(|table| {
+                let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?;
+                Ok(ArchiveTableCount { table, rows: rows as usize })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let size_bytes = conn.path()
+            .filter(|p| !p.is_empty())
+            .map(|p| [p.to_string(), format!("{}-wal", p)].iter()
+                .filter_map(|f| std::fs::metadata(f).ok())
+                .map(|m| m.len())
+                .sum())
+            .unwrap_or(0);
+        Ok(DatabaseStats { size_bytes, schema_version: schema_version(conn)?, tables })
+    })
+}
+
+// ============ Sync ============
+// Cross-device sync (see sync.rs) trades snapshots of conversations (with their messages and
+// summaries), facts and persona profiles. The newer copy of a row wins: a conversation by
+// updated_at, a fact by last_confirmed, a profile by updated_at. Messages are merged rather than
+// replaced, so two devices writing to one conversation both keep their turns. Deletions travel as
+// tombstones so the other device doesn't bring the row back.
+
+/// How long a deletion is remembered for devices that haven't synced since
+const TOMBSTONE_RETENTION_DAYS: i64 = 90;
+
+/// A table row as column -> value (the archive's JSON encoding)
+pub type SyncRow = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncConversation {
+    pub conversation: SyncRow,
+    pub messages: Vec<SyncRow>,
+    pub summaries: Vec<SyncRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SyncTombstone {
+    pub kind: String,       // "conversation", "message" or "fact"
+    pub key: String,        // Row id, or "category/key" for facts
+    pub deleted_at: String,
+}
+
+/// One device's side of a sync
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncData {
+    pub conversations: Vec<SyncConversation>,
+    pub facts: Vec<SyncRow>,
+    pub profiles: Vec<SyncRow>,
+    pub tombstones: Vec<SyncTombstone>,
+}
+
+/// What merging another device's data changed here
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct SyncApplied {
+    pub conversations: usize,
+    pub messages: usize, // Messages new to this device
+    pub facts: usize,
+    pub profiles: usize,
+    pub deleted: usize,
+}
+
+impl SyncApplied {
+    pub fn add(&mut self, other: SyncApplied) {
+        self.conversations += other.conversations;
+        self.messages += other.messages;
+        self.facts += other.facts;
+        self.profiles += other.profiles;
+        self.deleted += other.deleted;
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        *self == SyncApplied::default()
+    }
+}
+
+fn fact_tombstone_key(category: &str, key: &str) -> String {
+    format!("{}/{}", category, key)
+}
+
+fn record_tombstone(conn: &Connection, kind: &str, key: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO sync_tombstones (kind, key, deleted_at) VALUES (?1, ?2, ?3)",
+        params![kind, key, Utc::now().to_rfc3339()]
+    )?;
+    Ok(())
+}
+
+/// Tombstone the messages matching `filter` (a WHERE clause) before they're deleted
+fn record_message_tombstones<P: rusqlite::Params>(conn: &Connection, filter: &str, params: P) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO sync_tombstones (kind, key, deleted_at)
+             SELECT 'message', id, '{}' FROM messages WHERE {}",
+            Utc::now().to_rfc3339(), filter
+        ),
+        params
+    )?;
+    Ok(())
+}
+
+/// Tombstone the facts matching `filter` (a WHERE clause) before they're deleted
+fn record_fact_tombstones<P: rusqlite::Params>(conn: &Connection, filter: &str, params: P) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO sync_tombstones (kind, key, deleted_at)
+             SELECT 'fact', category || '/' || key, '{}' FROM user_facts WHERE {}",
+            Utc::now().to_rfc3339(), filter
+        ),
+        params
+    )?;
+    Ok(())
+}
+
+/// Tombstone everything synced that a reset is about to delete, so other devices delete it too
+/// instead of sending it back on the next sync
+fn record_reset_tombstones(conn: &Connection, conversations: bool, facts: bool) -> Result<()> {
+    if conversations {
+        record_message_tombstones(conn, "1 = 1", [])?;
+        conn.execute(
+            "INSERT OR REPLACE INTO sync_tombstones (kind, key, deleted_at) SELECT 'conversation', id, ?1 FROM conversations",
+            params![Utc::now().to_rfc3339()]
+        )?;
+    }
+    if facts {
+        record_fact_tombstones(conn, "1 = 1", [])?;
+    }
+    Ok(())
+}
+
+/// Rows of a table matching `filter` (a WHERE clause, optionally with ORDER BY)
+fn sync_rows<P: rusqlite::Params>(conn: &Connection, table: &str, filter: &str, params: P) -> Result<Vec<SyncRow>> {
+    let columns = table_columns(conn, table)?;
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE {}", table, filter))?;
+    let rows = stmt.query_map(params, |row| {
+        let mut object = SyncRow::new();
+        for (i, column) in columns.iter().enumerate() {
+            object.insert(column.clone(), sql_to_json(row.get_ref(i)?));
+        }
+        Ok(object)
+    })?;
+    rows.collect()
+}
+
+/// Write a row, updating the existing one on a `conflict` column clash (plain insert if None).
+/// Columns this schema doesn't have, and those in `skip`, are left out.
+fn upsert_sync_row(conn: &Connection, table: &str, columns: &[String], row: &SyncRow, conflict: Option<&str>, skip: &[&str]) -> Result<()> {
+    let present: Vec<&str> = columns.iter()
+        .map(|c| c.as_str())
+        .filter(|c| row.contains_key(*c) && !skip.contains(c))
+        .collect();
+    if present.is_empty() {
+        return Ok(());
+    }
+    let mut sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table,
+        present.join(", "),
+        (1..=present.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+    );
+    if let Some(conflict) = conflict {
+        let keys: Vec<&str> = conflict.split(',').map(str::trim).collect();
+        let updates: Vec<String> = present.iter()
+            .filter(|c| !keys.contains(c))
+            .map(|c| format!("{0} = excluded.{0}", c))
+            .collect();
+        if updates.is_empty() {
+            sql.push_str(&format!(" ON CONFLICT({}) DO NOTHING", conflict));
+        } else {
+            sql.push_str(&format!(" ON CONFLICT({}) DO UPDATE SET {}", conflict, updates.join(", ")));
+        }
+    }
+    let values: Vec<rusqlite::types::Value> = present.iter().map(|c| json_to_sql(&row[*c])).collect();
+    conn.execute(&sql, rusqlite::params_from_iter(values))?;
+    Ok(())
+}
+
+fn row_str<'a>(row: &'a SyncRow, column: &str) -> Option<&'a str> {
+    row.get(column).and_then(|v| v.as_str())
+}
+
+/// Whether timestamp `a` is later than `b` (a missing `b` counts as older than anything)
+fn is_newer(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => match (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+            (Ok(a), Ok(b)) => a > b,
+            _ => a > b,
+        },
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// This device's side of a sync: every conversation, fact and persona profile, plus recent deletions
+pub fn export_sync_data() -> Result<SyncData> {
+    let cutoff = (Utc::now() - chrono::Duration::days(TOMBSTONE_RETENTION_DAYS)).to_rfc3339();
+    with_read_connection(|conn| {
+        let mut conversations = Vec::new();
+        for conversation in sync_rows(conn, "conversations", "1 = 1 ORDER BY id", [])? {
+            let id = row_str(&conversation, "id").unwrap_or_default().to_string();
+            conversations.push(SyncConversation {
+                messages: sync_rows(conn, "messages", "conversation_id = ?1 ORDER BY seq", params![id])?,
+                summaries: sync_rows(conn, "conversation_summaries", "conversation_id = ?1 ORDER BY created_at", params![id])?,
+                conversation,
+            });
+        }
+        
+        let mut stmt = conn.prepare(
+            "SELECT kind, key, deleted_at FROM sync_tombstones WHERE deleted_at >= ?1 ORDER BY kind, key"
+        )?;
+        let tombstones = stmt.query_map(params![cutoff], |row| {
+            Ok(SyncTombstone { kind: row.get(0)?, key: row.get(1)?, deleted_at: row.get(2)? })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(SyncData {
+            conversations,
+            facts: sync_rows(conn, "user_facts", "1 = 1 ORDER BY category, key", [])?,
+            profiles: sync_rows(conn, "persona_profiles", "1 = 1 ORDER BY dominant_trait", [])?,
+            tombstones,
+        })
+    })
+}
+
+/// Apply another device's deletion unless the row changed here since; returns whether anything was deleted
+fn apply_tombstone(tx: &Connection, tombstone: &SyncTombstone) -> Result<bool> {
+    let deleted = match tombstone.kind.as_str() {
+        "conversation" => {
+            let updated_at: Option<String> = tx.query_row(
+                "SELECT updated_at FROM conversations WHERE id = ?1",
+                params![tombstone.key],
+                |row| row.get(0)
+            ).optional()?;
+            match updated_at {
+                Some(updated_at) if !is_newer(Some(&updated_at), Some(&tombstone.deleted_at)) => {
+                    delete_conversation_in(tx, &tombstone.key, DeleteConversationOptions::default())?;
+                    true
+                }
+                _ => false,
+            }
+        }
+        "message" => {
+            // Messages aren't edited in place across devices, so their timestamp is their last change
+            let timestamp: Option<String> = tx.query_row(
+                "SELECT timestamp FROM messages WHERE id = ?1",
+                params![tombstone.key],
+                |row| row.get(0)
+            ).optional()?;
+            match timestamp {
+                Some(timestamp) if !is_newer(Some(&timestamp), Some(&tombstone.deleted_at)) => {
+                    delete_message_in(tx, &tombstone.key)?.is_some()
+                }
+                _ => false,
+            }
+        }
+        "fact" => {
+            let (category, key) = tombstone.key.split_once('/').unwrap_or_default();
+            let fact: Option<(i64, String)> = tx.query_row(
+                "SELECT id, last_confirmed FROM user_facts WHERE category = ?1 AND key = ?2",
+                params![category, key],
+                |row| Ok((row.get(0)?, row.get(1)?))
+            ).optional()?;
+            match fact {
+                Some((id, confirmed)) if !is_newer(Some(&confirmed), Some(&tombstone.deleted_at)) => {
+                    tx.execute("DELETE FROM embeddings WHERE source_type = 'fact' AND source_id = ?1", params![id.to_string()])?;
+                    tx.execute("DELETE FROM fact_reviews WHERE fact_id = ?1", params![id])?;
+                    tx.execute("DELETE FROM user_facts WHERE id = ?1", params![id])?;
+                    true
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+    
+    // Remember it too, so it reaches devices that sync with this one
+    tx.execute(
+        "INSERT INTO sync_tombstones (kind, key, deleted_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(kind, key) DO UPDATE SET deleted_at = max(deleted_at, excluded.deleted_at)",
+        params![tombstone.kind, tombstone.key, tombstone.deleted_at]
+    )?;
+    Ok(deleted)
+}
+
+/// Merge another device's data into this database in one transaction
+/// Merge another device's data. Messages for conversations in `busy` (a turn in flight, which
+/// rolls back by seq if cancelled) are left for the next sync.
+pub fn apply_sync_data(remote: &SyncData, busy: &HashSet<String>) -> Result<SyncApplied> {
+    let cutoff = (Utc::now() - chrono::Duration::days(TOMBSTONE_RETENTION_DAYS)).to_rfc3339();
+    with_connection(|conn| {
+        let mut applied = SyncApplied::default();
+        
+        for tombstone in &remote.tombstones {
+            if apply_tombstone(conn, tombstone)? {
+                applied.deleted += 1;
+            }
+        }
+        
+        // A row deleted here stays deleted unless the other copy changed after the deletion
+        let tombstones: HashMap<(String, String), String> = {
+            let mut stmt = conn.prepare("SELECT kind, key, deleted_at FROM sync_tombstones")?;
+            let rows = stmt.query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?;
+            rows.collect::<Result<HashMap<_, _>>>()?
+        };
+        let deleted_here = |kind: &str, key: &str, stamp: Option<&str>| {
+            tombstones.get(&(kind.to_string(), key.to_string()))
+                .is_some_and(|deleted_at| !is_newer(stamp, Some(deleted_at)))
+        };
+        
+        // Persona profiles: the same three on every device, matched by trait (ids differ)
+        let profile_columns = table_columns(conn, "persona_profiles")?;
+        let local_profiles: HashMap<String, (String, String)> = {
+            let mut stmt = conn.prepare("SELECT dominant_trait, id, updated_at FROM persona_profiles")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?;
+            rows.collect::<Result<HashMap<_, _>>>()?
+        };
+        let mut profile_ids: HashMap<String, String> = HashMap::new();
+        for profile in &remote.profiles {
+            let (Some(remote_id), Some(dominant)) = (row_str(profile, "id"), row_str(profile, "dominant_trait")) else { continue };
+            let Some((local_id, local_updated)) = local_profiles.get(dominant) else { continue };
+            profile_ids.insert(remote_id.to_string(), local_id.clone());
+            if is_newer(row_str(profile, "updated_at"), Some(local_updated)) {
+                let mut profile = profile.clone();
+                profile.insert("id".to_string(), serde_json::json!(local_id));
+                // Which profile is active is up to each device
+                upsert_sync_row(conn, "persona_profiles", &profile_columns, &profile, Some("id"), &["is_active", "is_default"])?;
+                applied.profiles += 1;
+            }
+        }
+        
+        // Facts: matched by category and key
+        let fact_columns = table_columns(conn, "user_facts")?;
+        for fact in &remote.facts {
+            let (Some(category), Some(key)) = (row_str(fact, "category"), row_str(fact, "key")) else { continue };
+            let confirmed = row_str(fact, "last_confirmed");
+            if deleted_here("fact", &fact_tombstone_key(category, key), confirmed) {
+                continue;
+            }
+            let local: Option<String> = conn.query_row(
+                "SELECT last_confirmed FROM user_facts WHERE category = ?1 AND key = ?2",
+                params![category, key],
+                |row| row.get(0)
+            ).optional()?;
+            if is_newer(confirmed, local.as_deref()) {
+                upsert_sync_row(conn, "user_facts", &fact_columns, fact, Some("category, key"), &["id"])?;
+                applied.facts += 1;
+            }
+        }
+        
+        // Conversations: the newer row and summaries win, messages from both sides are kept
+        let conversation_columns = table_columns(conn, "conversations")?;
+        let message_columns = table_columns(conn, "messages")?;
+        let summary_columns = table_columns(conn, "conversation_summaries")?;
+        let mut touched = Vec::new();
+        for remote_conversation in &remote.conversations {
+            let row = &remote_conversation.conversation;
+            let Some(id) = row_str(row, "id") else { continue };
+            let updated_at = row_str(row, "updated_at");
+            if deleted_here("conversation", id, updated_at) {
+                continue;
+            }
+            let local: Option<String> = conn.query_row(
+                "SELECT updated_at FROM conversations WHERE id = ?1",
+                params![id],
+                |row| row.get(0)
+            ).optional()?;
+            let theirs_newer = is_newer(updated_at, local.as_deref());
+            
+            if theirs_newer {
+                let mut row = row.clone();
+                if let Some(persona) = row_str(&row, "persona_profile_id").map(str::to_string) {
+                    let local_persona = profile_ids.get(&persona).map(|id| serde_json::json!(id));
+                    row.insert("persona_profile_id".to_string(), local_persona.unwrap_or(serde_json::Value::Null));
+                }
+                upsert_sync_row(conn, "conversations", &conversation_columns, &row, Some("id"), &["last_seq"])?;
+                conn.execute("DELETE FROM conversation_summaries WHERE conversation_id = ?1", params![id])?;
+                for summary in &remote_conversation.summaries {
+                    upsert_sync_row(conn, "conversation_summaries", &summary_columns, summary, None, &["id"])?;
+                }
+            }
+            
+            if busy.contains(id) {
+                if theirs_newer {
+                    applied.conversations += 1;
+                    touched.push(id.to_string());
+                }
+                continue;
+            }
+            
+            let local_messages: HashSet<String> = {
+                let mut stmt = conn.prepare("SELECT id FROM messages WHERE conversation_id = ?1")?;
+                let rows = stmt.query_map(params![id], |row| row.get(0))?;
+                rows.collect::<Result<HashSet<_>>>()?
+            };
+            let mut inserted = 0;
+            for message in &remote_conversation.messages {
+                let Some(message_id) = row_str(message, "id") else { continue };
+                let exists = local_messages.contains(message_id);
+                if deleted_here("message", message_id, None) || (exists && !theirs_newer) {
+                    continue;
+                }
+                // Local sequence numbers never change: extraction watermarks, mute bookmarks and
+                // turn rollback are keyed on them. Messages new to this device go after the last one.
+                if exists {
+                    upsert_sync_row(conn, "messages", &message_columns, message, Some("id"), &["seq"])?;
+                } else {
+                    conn.execute("UPDATE conversations SET last_seq = last_seq + 1 WHERE id = ?1", params![id])?;
+                    let seq: i64 = conn.query_row("SELECT last_seq FROM conversations WHERE id = ?1", params![id], |row| row.get(0))?;
+                    let mut message = message.clone();
+                    message.insert("seq".to_string(), serde_json::json!(seq));
+                    upsert_sync_row(conn, "messages", &message_columns, &message, Some("id"), &[])?;
+                    inserted += 1;
+                }
+            }
+            applied.messages += inserted;
+            if theirs_newer || inserted > 0 {
+                applied.conversations += 1;
+                touched.push(id.to_string());
+            }
+        }
+        
+        conn.execute("DELETE FROM sync_tombstones WHERE deleted_at < ?1", params![cutoff])?;
+        
+        // The other device may still be in these; don't finalize them here as abandoned
+        WRITTEN_THIS_SESSION.lock().unwrap().extend(touched);
+        Ok(applied)
+    })
+}
+
+// ============ Full-Text Search ============
+
+/// A message matching a search, with enough conversation context to show and open it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub conversation_created_at: String,
+    pub role: String,
+    pub snippet: String,            // Matched terms wrapped in <mark>...</mark>
+    pub timestamp: String,
+}
+
+/// Turn free text into an FTS5 query: every word must appear, the last one may be a prefix.
+/// Quoting each word keeps punctuation from being read as query syntax.
+fn fts_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text.split_whitespace()
+        .map(|w| w.replace('"', ""))
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("\"{}\"", w))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", words.join(" ")))
+}
+
+/// Search live user and agent messages across every conversation, best matches first
+pub fn search_messages(query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+    let Some(fts) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.conversation_id, c.title, c.created_at, m.role,
+                    snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16), m.timestamp
+             FROM messages_fts
+             JOIN messages m ON m.rowid = messages_fts.rowid
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE messages_fts MATCH ?1 AND m.role != 'system' AND m.superseded_by IS NULL
+             ORDER BY bm25(messages_fts)
+             LIMIT ?2"
+        )?;
+        let hits = stmt.query_map(params![fts, limit], |row| {
+            Ok(SearchHit {
+                message_id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                conversation_title: row.get(2)?,
+                conversation_created_at: row.get(3)?,
+                role: row.get(4)?,
+                snippet: row.get(5)?,
+                timestamp: row.get(6)?,
+            })
+        })?;
+        hits.collect()
+    })
+}
+
+// ============ Conversation Summaries ============
+
+pub fn save_conversation_summary(summary: &ConversationSummary) -> Result<()> {
+    with_connection(|conn| {
+        // Replace existing summary for this conversation
+        conn.execute(
+            "INSERT OR REPLACE INTO conversation_summaries 
+             (conversation_id, summary, key_topics, emotional_tone, user_state, agents_involved, message_count, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                summary.conversation_id,
+                summary.summary,
+                summary.key_topics,
+                summary.emotional_tone,
+                summary.user_state,
+                summary.agents_involved,
+                summary.message_count,
+                summary.created_at
+            ]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_conversation_summary(conversation_id: &str) -> Result<Option<ConversationSummary>> {
+    with_read_connection(|conn| {
+        let result = conn.query_row(
+            "SELECT id, conversation_id, summary, key_topics, emotional_tone, user_state, agents_involved, message_count, created_at
+             FROM conversation_summaries WHERE conversation_id = ?1",
+            params![conversation_id],
+            |row| {
+                Ok(ConversationSummary {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    summary: row.get(2)?,
+                    key_topics: row.get(3)?,
+                    emotional_tone: row.get(4)?,
+                    user_state: row.get(5)?,
+                    agents_involved: row.get(6)?,
+                    message_count: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            }
+        );
+        match result {
+            Ok(s) => Ok(Some(s)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    })
+}
+
+/// Most recent summary from any conversation other than the given one
+pub fn get_previous_conversation_summary(conversation_id: &str) -> Result<Option<ConversationSummary>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT id, conversation_id, summary, key_topics, emotional_tone, user_state, agents_involved, message_count, created_at
+             FROM conversation_summaries WHERE conversation_id != ?1
+             ORDER BY created_at DESC LIMIT 1",
+            params![conversation_id],
+            |row| {
+                Ok(ConversationSummary {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    summary: row.get(2)?,
+                    key_topics: row.get(3)?,
+                    emotional_tone: row.get(4)?,
+                    user_state: row.get(5)?,
+                    agents_involved: row.get(6)?,
+                    message_count: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            }
+        ).optional()
+    })
+}
+
+// ============ Session Reflections ============
+// A short question generated from a finished conversation's summary, shown once on the next open
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionReflection {
+    pub conversation_id: String,
+    pub question: String,
+    pub created_at: String,
+}
+
+pub fn set_summary_reflection(conversation_id: &str, question: &str) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE conversation_summaries SET reflection_question = ?1, reflection_surfaced_at = NULL
+             WHERE conversation_id = ?2",
+            params![question, conversation_id]
+        )?;
+        Ok(())
+    })
+}
+
+/// Most recent reflection that hasn't been shown to the user yet
+pub fn get_pending_reflection() -> Result<Option<SessionReflection>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT conversation_id, reflection_question, created_at FROM conversation_summaries
+             WHERE reflection_question IS NOT NULL AND reflection_surfaced_at IS NULL
+             ORDER BY created_at DESC LIMIT 1",
+            [],
+            |row| {
+                Ok(SessionReflection {
+                    conversation_id: row.get(0)?,
+                    question: row.get(1)?,
+                    created_at: row.get(2)?,
+                })
+            }
+        ).optional()
+    })
+}
+
+/// Mark every pending reflection as surfaced - only the newest is shown, older ones are stale
+pub fn mark_reflections_surfaced() -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE conversation_summaries SET reflection_surfaced_at = ?1
+             WHERE reflection_question IS NOT NULL AND reflection_surfaced_at IS NULL",
+            params![now]
+        )?;
+        Ok(())
+    })
+}
+
+// ============ Fact Category Sensitivity ============
+// Sensitive categories are kept out of disco rebuttals/debates unless the user raises them
+
+pub const DEFAULT_SENSITIVE_CATEGORIES: [&str; 2] = ["relationships", "health"];
+
+pub fn get_sensitive_fact_categories() -> Result<Vec<String>> {
+    let stored = get_setting("sensitive_fact_categories")?;
+    Ok(stored
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_else(|| DEFAULT_SENSITIVE_CATEGORIES.iter().map(|c| c.to_string()).collect()))
+}
+
+pub fn set_fact_category_sensitive(category: &str, sensitive: bool) -> Result<()> {
+    let category = category.trim().to_lowercase();
+    let mut categories = get_sensitive_fact_categories()?;
+    categories.retain(|c| *c != category);
+    if sensitive {
+        categories.push(category);
+    }
+    let json = serde_json::to_string(&categories).unwrap_or_else(|_| "[]".to_string());
+    set_setting("sensitive_fact_categories", &json)
+}
+
+// ============ Recurring Themes ============
+
+pub fn save_recurring_theme(theme: &str, conversation_id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        // Try to get existing theme
+        let existing: Option<(i64, String)> = conn.query_row(
+            "SELECT id, related_conversations FROM recurring_themes WHERE theme = ?1",
+            params![theme],
+            |row| Ok((row.get(0)?, row.get::<_, Option<String>>(1)?.unwrap_or_default()))
+        ).ok();
+        
+        if let Some((id, existing_convs)) = existing {
+            // Update existing theme
+            let mut convs: Vec<String> = if existing_convs.is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&existing_convs).unwrap_or_default()
+            };
+            if !convs.contains(&conversation_id.to_string()) {
+                convs.push(conversation_id.to_string());
+            }
+            let convs_json = serde_json::to_string(&convs).unwrap_or_default();
+            
+            conn.execute(
+                "UPDATE recurring_themes SET frequency = frequency + 1, last_mentioned = ?1, related_conversations = ?2 WHERE id = ?3",
+                params![now, convs_json, id]
+            )?;
+        } else {
+            // Insert new theme
+            let convs_json = serde_json::to_string(&vec![conversation_id]).unwrap_or_default();
+            conn.execute(
+                "INSERT INTO recurring_themes (theme, frequency, last_mentioned, related_conversations) VALUES (?1, 1, ?2, ?3)",
+                params![theme, now, convs_json]
+            )?;
+        }
+        Ok(())
+    })
+}
+
+fn row_to_theme(row: &rusqlite::Row) -> Result<RecurringTheme> {
+    Ok(RecurringTheme {
+        id: row.get(0)?,
+        theme: row.get(1)?,
+        frequency: row.get(2)?,
+        last_mentioned: row.get(3)?,
+        related_conversations: row.get(4)?,
+        muted: row.get::<_, Option<i64>>(5)?.unwrap_or(0) != 0,
+    })
+}
+
+/// Get all unmuted themes (muted themes are excluded from anything user-facing)
+pub fn get_all_recurring_themes() -> Result<Vec<RecurringTheme>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, theme, frequency, last_mentioned, related_conversations, muted
+             FROM recurring_themes WHERE COALESCE(muted, 0) = 0 ORDER BY frequency DESC"
+        )?;
+        
+        let themes = stmt.query_map([], row_to_theme)?;
+        
+        themes.collect()
+    })
+}
+
+pub fn get_top_themes(limit: usize) -> Result<Vec<RecurringTheme>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, theme, frequency, last_mentioned, related_conversations, muted
+             FROM recurring_themes WHERE COALESCE(muted, 0) = 0 ORDER BY frequency DESC LIMIT ?1"
+        )?;
+        
+        let themes = stmt.query_map([limit], row_to_theme)?;
+        
+        themes.collect()
+    })
+}
+
+/// Get muted themes (still tracked in the background so they can be unmuted later)
+pub fn get_muted_themes() -> Result<Vec<RecurringTheme>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, theme, frequency, last_mentioned, related_conversations, muted
+             FROM recurring_themes WHERE muted = 1 ORDER BY frequency DESC"
+        )?;
+        
+        let themes = stmt.query_map([], row_to_theme)?;
+        
+        themes.collect()
+    })
+}
+
+/// Forget a theme entirely (muting keeps tracking it); returns false if there's no such theme
+pub fn delete_recurring_theme(theme: &str) -> Result<bool> {
+    with_connection(|conn| {
+        let removed = conn.execute("DELETE FROM recurring_themes WHERE theme = ?1", params![theme])?;
+        Ok(removed > 0)
+    })
+}
+
+/// Mute or unmute a theme. Muting a theme that hasn't been seen yet creates it,
+/// so it stays hidden once extraction starts picking it up.
+pub fn set_theme_muted(theme: &str, muted: bool) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let updated = conn.execute(
+            "UPDATE recurring_themes SET muted = ?1 WHERE theme = ?2",
+            params![muted, theme]
+        )?;
+        
+        if updated == 0 && muted {
+            conn.execute(
+                "INSERT INTO recurring_themes (theme, frequency, last_mentioned, related_conversations, muted) VALUES (?1, 0, ?2, '[]', 1)",
+                params![theme, now]
+            )?;
+        }
+        Ok(())
+    })
+}
+
+// ============ Reset ============
+
+/// Wipe everything but the API keys; returns the attachments whose stored files the caller should delete
+pub fn reset_all_data() -> Result<Vec<Attachment>> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let removed = all_attachments(conn)?;
+        // Clear all conversation and memory data
+        conn.execute("DELETE FROM message_versions", [])?;
+        conn.execute("DELETE FROM embeddings", [])?;
+        record_reset_tombstones(conn, true, true)?;
+        conn.execute("DELETE FROM messages", [])?;
+        conn.execute("DELETE FROM limbo_entries", [])?;
+        conn.execute("DELETE FROM agent_mutes", [])?;
+        conn.execute("DELETE FROM imported_conversations", [])?;
+        conn.execute("DELETE FROM pending_messages", [])?;
+        conn.execute("DELETE FROM conversation_tags", [])?;
+        conn.execute("DELETE FROM attachments", [])?;
+        conn.execute("DELETE FROM conversations", [])?;
+        conn.execute("DELETE FROM user_context", [])?;
+        conn.execute("DELETE FROM user_facts", [])?;
+        conn.execute("DELETE FROM forgotten_facts", [])?;
+        conn.execute("DELETE FROM fact_reviews", [])?;
+        conn.execute("DELETE FROM user_patterns", [])?;
+        conn.execute("DELETE FROM conversation_summaries", [])?;
+        conn.execute("DELETE FROM recurring_themes", [])?;
+        conn.execute("DELETE FROM agent_style_preferences", [])?;
+        conn.execute("DELETE FROM analytics_engagement", [])?;
+        conn.execute("DELETE FROM analytics_intrinsic_signals", [])?;
+        conn.execute("DELETE FROM analytics_turn_metrics", [])?;
+        conn.execute("DELETE FROM api_usage", [])?;
+        conn.execute("DELETE FROM reports", [])?;
+        conn.execute("DELETE FROM document_chunks", [])?;
+        conn.execute("DELETE FROM documents", [])?;
+        conn.execute("DELETE FROM reminders", [])?;
+        conn.execute("DELETE FROM mood_log", [])?;
+        conn.execute("DELETE FROM message_feedback", [])?;
+        
+        conn.execute("DELETE FROM personality_snapshots", [])?;
+        // Delete all persona profiles (will be recreated on next init)
+        conn.execute("DELETE FROM persona_profiles", [])?;
+        
+        // Reset user_profile weights and message count, but KEEP API keys
+        let (instinct_w, logic_w, psyche_w) = RESET_USER_WEIGHTS;
+        conn.execute(
+            "UPDATE user_profile SET instinct_weight = ?1, logic_weight = ?2, psyche_weight = ?3, total_messages = 0, updated_at = ?4",
+            params![instinct_w, logic_w, psyche_w, now]
+        )?;
+        
+        // Recreate the 3 fixed persona profiles with default names, weights and points (Logic default and active)
+        for DefaultPersona { name, dominant_trait: dominant, weights: (instinct_w, logic_w, psyche_w), points: (instinct_p, logic_p, psyche_p) } in DEFAULT_PERSONAS {
+            let id = uuid::Uuid::new_v4().to_string();
+            let is_logic = dominant == "logic";
+            conn.execute(
+                "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, ?11, ?11)",
+                params![id, name, is_logic, dominant, instinct_w, logic_w, psyche_w, instinct_p, logic_p, psyche_p, now]
+            )?;
+        }
+        
+        Ok(removed)
+    })
+}
+
+fn all_attachments(conn: &Connection) -> Result<Vec<Attachment>> {
+    conn.prepare(&format!("SELECT {} FROM attachments", ATTACHMENT_COLUMNS))?
+        .query_map([], row_to_attachment)?
+        .collect()
+}
+
+// ============ Scoped Resets ============
+// Start fresh on one axis without losing everything else
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetScope {
+    Memory,         // Learned facts, patterns, themes, summaries, decisions
+    Weights,        // Persona weights and the engagement history behind them
+    Conversations,  // Transcripts (learned memory is kept)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResetItem {
+    pub table: String,
+    pub action: String,     // "delete" | "reset"
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResetPreview {
+    pub scope: ResetScope,
+    pub dry_run: bool,
+    pub items: Vec<ResetItem>,
+}
+
+/// The persona_profiles update restoring each profile's default weights and points, by its dominant trait
+fn persona_defaults_sql() -> String {
+    let column = |pick: fn(&DefaultPersona) -> String| {
+        let cases: String = DEFAULT_PERSONAS.iter()
+            .map(|persona| format!(" WHEN '{}' THEN {}", persona.dominant_trait, pick(persona)))
+            .collect();
+        format!("CASE dominant_trait{} END", cases)
+    };
+    format!(
+        "UPDATE persona_profiles SET instinct_weight = {}, logic_weight = {}, psyche_weight = {},
+            instinct_points = {}, logic_points = {}, psyche_points = {}",
+        column(|p| p.weights.0.to_string()), column(|p| p.weights.1.to_string()), column(|p| p.weights.2.to_string()),
+        column(|p| p.points.0.to_string()), column(|p| p.points.1.to_string()), column(|p| p.points.2.to_string()),
+    )
+}
+
+/// (table, action, count query, apply statement) for each scope
+fn reset_plan(scope: ResetScope) -> Vec<(&'static str, &'static str, &'static str, String)> {
+    // Weights go back to the defaults fresh profiles get
+    let mut plan = match scope {
+        ResetScope::Weights => vec![
+            ("persona_profiles", "reset", "SELECT COUNT(*) FROM persona_profiles", persona_defaults_sql()),
+            ("user_profile", "reset", "SELECT COUNT(*) FROM user_profile", format!(
+                "UPDATE user_profile SET instinct_weight = {}, logic_weight = {}, psyche_weight = {}",
+                RESET_USER_WEIGHTS.0, RESET_USER_WEIGHTS.1, RESET_USER_WEIGHTS.2
+            )),
+        ],
+        _ => Vec::new(),
+    };
+    let fixed: Vec<(&'static str, &'static str, &'static str, &'static str)> = match scope {
+        ResetScope::Memory => vec![
+            ("user_context", "delete", "SELECT COUNT(*) FROM user_context", "DELETE FROM user_context"),
+            ("user_facts", "delete", "SELECT COUNT(*) FROM user_facts", "DELETE FROM user_facts"),
+            // Fact ids are reused, so stale flags would land on new facts
+            ("fact_reviews", "delete", "SELECT COUNT(*) FROM fact_reviews", "DELETE FROM fact_reviews"),
+            ("user_patterns", "delete", "SELECT COUNT(*) FROM user_patterns", "DELETE FROM user_patterns"),
+            // Muted themes are a preference, not memory - keep them so they stay hidden
+            ("recurring_themes", "delete",
+                "SELECT COUNT(*) FROM recurring_themes WHERE COALESCE(muted, 0) = 0",
+                "DELETE FROM recurring_themes WHERE COALESCE(muted, 0) = 0"),
+            ("conversation_summaries", "delete", "SELECT COUNT(*) FROM conversation_summaries", "DELETE FROM conversation_summaries"),
+            ("decisions", "delete", "SELECT COUNT(*) FROM decisions", "DELETE FROM decisions"),
+            ("reports", "delete", "SELECT COUNT(*) FROM reports", "DELETE FROM reports"),
+            ("document_chunks", "delete", "SELECT COUNT(*) FROM document_chunks", "DELETE FROM document_chunks"),
+            ("documents", "delete", "SELECT COUNT(*) FROM documents", "DELETE FROM documents"),
+            ("embeddings", "delete",
+                "SELECT COUNT(*) FROM embeddings WHERE source_type != 'message'",
+                "DELETE FROM embeddings WHERE source_type != 'message'"),
+        ],
+        ResetScope::Weights => vec![
+            ("persona_weight_history", "delete", "SELECT COUNT(*) FROM persona_weight_history", "DELETE FROM persona_weight_history"),
+            ("personality_snapshots", "delete", "SELECT COUNT(*) FROM personality_snapshots", "DELETE FROM personality_snapshots"),
+            ("agent_interactions", "delete", "SELECT COUNT(*) FROM agent_interactions", "DELETE FROM agent_interactions"),
+            ("agent_style_preferences", "delete", "SELECT COUNT(*) FROM agent_style_preferences", "DELETE FROM agent_style_preferences"),
+            ("analytics_engagement", "delete", "SELECT COUNT(*) FROM analytics_engagement", "DELETE FROM analytics_engagement"),
+            ("analytics_intrinsic_signals", "delete", "SELECT COUNT(*) FROM analytics_intrinsic_signals", "DELETE FROM analytics_intrinsic_signals"),
+        ],
+        ResetScope::Conversations => vec![
+            ("conversations", "delete", "SELECT COUNT(*) FROM conversations", "DELETE FROM conversations"),
+            ("messages", "delete", "SELECT COUNT(*) FROM messages", "DELETE FROM messages"),
+            ("limbo_entries", "delete", "SELECT COUNT(*) FROM limbo_entries", "DELETE FROM limbo_entries"),
+            ("agent_mutes", "delete", "SELECT COUNT(*) FROM agent_mutes", "DELETE FROM agent_mutes"),
+            ("imported_conversations", "delete", "SELECT COUNT(*) FROM imported_conversations", "DELETE FROM imported_conversations"),
+            ("pending_messages", "delete", "SELECT COUNT(*) FROM pending_messages", "DELETE FROM pending_messages"),
+            ("conversation_tags", "delete", "SELECT COUNT(*) FROM conversation_tags", "DELETE FROM conversation_tags"),
+            ("attachments", "delete", "SELECT COUNT(*) FROM attachments", "DELETE FROM attachments"),
+            ("turn_versions", "delete", "SELECT COUNT(*) FROM turn_versions", "DELETE FROM turn_versions"),
+            ("message_versions", "delete", "SELECT COUNT(*) FROM message_versions", "DELETE FROM message_versions"),
+            ("message_feedback", "delete", "SELECT COUNT(*) FROM message_feedback", "DELETE FROM message_feedback"),
+            ("embeddings", "delete", "SELECT COUNT(*) FROM embeddings", "DELETE FROM embeddings"),
+            ("journey_sessions", "delete", "SELECT COUNT(*) FROM journey_sessions", "DELETE FROM journey_sessions"),
+            ("decisions", "delete", "SELECT COUNT(*) FROM decisions", "DELETE FROM decisions"),
+            ("analytics_turn_metrics", "delete", "SELECT COUNT(*) FROM analytics_turn_metrics", "DELETE FROM analytics_turn_metrics"),
+            ("mood_log", "delete", "SELECT COUNT(*) FROM mood_log", "DELETE FROM mood_log"),
+            ("check_ins", "delete", "SELECT COUNT(*) FROM check_ins", "DELETE FROM check_ins"),
+            // Reminders set outside a conversation aren't conversation data
+            ("reminders", "delete",
+                "SELECT COUNT(*) FROM reminders WHERE conversation_id IS NOT NULL",
+                "DELETE FROM reminders WHERE conversation_id IS NOT NULL"),
+        ],
+    };
+    plan.extend(fixed.into_iter().map(|(table, action, count_sql, apply_sql)| (table, action, count_sql, apply_sql.to_string())));
+    plan
+}
+
+/// Count what a scoped reset touches and, unless this is a dry run, apply it in one transaction.
+/// Also returns the attachments whose stored files the caller should delete.
+pub fn reset_scope(scope: ResetScope, dry_run: bool) -> Result<(ResetPreview, Vec<Attachment>)> {
+    with_connection(|conn| {
+        let plan = reset_plan(scope);
+        let mut items = Vec::with_capacity(plan.len());
+        for (table, action, count_sql, _) in &plan {
+            let rows: i64 = conn.query_row(count_sql, [], |row| row.get(0))?;
+            items.push(ResetItem { table: table.to_string(), action: action.to_string(), rows });
+        }
+        
+        let mut removed = Vec::new();
+        if !dry_run {
+            if plan.iter().any(|(table, ..)| *table == "attachments") {
+                removed = all_attachments(conn)?;
+            }
+            record_reset_tombstones(conn, scope == ResetScope::Conversations, scope == ResetScope::Memory)?;
+            for (_, _, _, apply_sql) in &plan {
+                conn.execute(apply_sql, [])?;
+            }
+        }
+        
+        Ok((ResetPreview { scope, dry_run, items }, removed))
+    })
+}
+
+// ============ Persona Profiles (Multi-Profile System) ============
+
+/// One of the three fixed persona profiles as created fresh
+struct DefaultPersona {
+    name: &'static str,
+    dominant_trait: &'static str,
+    weights: (f64, f64, f64),   // Instinct, logic, psyche: the dominant trait leads at 40%, the others at 30%
+    points: (i64, i64, i64),
+}
+
+const DEFAULT_PERSONAS: [DefaultPersona; 3] = [
+    DefaultPersona { name: "Logic", dominant_trait: "logic", weights: (0.30, 0.40, 0.30), points: (3, 4, 4) },
+    DefaultPersona { name: "Instinct", dominant_trait: "instinct", weights: (0.40, 0.30, 0.30), points: (4, 3, 4) },
+    DefaultPersona { name: "Psyche", dominant_trait: "psyche", weights: (0.30, 0.30, 0.40), points: (3, 3, 5) },
+];
+/// user_profile weights after a reset (an even split)
+const RESET_USER_WEIGHTS: (f64, f64, f64) = (0.333, 0.333, 0.334);
+
+pub fn create_persona_profile(
+    name: &str,
+    dominant_trait: &str,
+    secondary_trait: &str,
+    is_default: bool,
+) -> Result<PersonaProfile> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    
+    // Calculate weights based on trait selection: dominant 50%, secondary 30%, third 20%
+    let (instinct_weight, logic_weight, psyche_weight) = calculate_trait_weights(dominant_trait, secondary_trait);
+    
+    with_connection(|conn| {
+        // If this is the first profile or marked as default, ensure only one is default
+        if is_default {
+            conn.execute("UPDATE persona_profiles SET is_default = 0", [])?;
+        }
+        
+        // Check if this is the first profile (make it active)
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM persona_profiles", [], |row| row.get(0))?;
+        let is_active = count == 0; // First profile is automatically active
+        
+        // Default points: 4, 4, 3 (total 11) - will be adjusted by user
+        conn.execute(
+            "INSERT INTO persona_profiles (id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 4, 4, 3, 0, ?10, ?11)",
+            params![id, name, is_default || is_active, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, now, now]
+        )?;
+        
+        Ok(PersonaProfile {
+            id,
+            name: name.to_string(),
+            is_default: is_default || is_active,
+            is_active,
+            dominant_trait: dominant_trait.to_string(),
+            secondary_trait: secondary_trait.to_string(),
+            instinct_weight,
+            logic_weight,
+            psyche_weight,
+            instinct_points: 4,
+            logic_points: 4,
+            psyche_points: 3,
+            message_count: 0,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    })
+}
+
+fn calculate_trait_weights(dominant: &str, secondary: &str) -> (f64, f64, f64) {
+    // dominant = 50%, secondary = 30%, third = 20%
+    let mut instinct = 0.2;
+    let mut logic = 0.2;
+    let mut psyche = 0.2;
+    
+    match dominant {
+        "instinct" => instinct = 0.5,
+        "logic" => logic = 0.5,
+        "psyche" => psyche = 0.5,
+        _ => {}
+    }
+    
+    match secondary {
+        "instinct" => instinct = 0.3,
+        "logic" => logic = 0.3,
+        "psyche" => psyche = 0.3,
+        _ => {}
+    }
+    
+    (instinct, logic, psyche)
+}
+
+pub fn get_all_persona_profiles() -> Result<Vec<PersonaProfile>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at
+             FROM persona_profiles ORDER BY is_default DESC, message_count DESC"
+        )?;
+        
+        let profiles = stmt.query_map([], |row| {
+            Ok(PersonaProfile {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                is_default: row.get::<_, i64>(2)? != 0,
+                is_active: row.get::<_, i64>(3)? != 0,
+                dominant_trait: row.get(4)?,
+                secondary_trait: row.get(5)?,
+                instinct_weight: row.get(6)?,
+                logic_weight: row.get(7)?,
+                psyche_weight: row.get(8)?,
+                instinct_points: row.get(9)?,
+                logic_points: row.get(10)?,
+                psyche_points: row.get(11)?,
+                message_count: row.get(12)?,
+                created_at: row.get(13)?,
+                updated_at: row.get(14)?,
+            })
+        })?;
+        
+        profiles.collect()
+    })
+}
+
+pub fn get_active_persona_profile() -> Result<Option<PersonaProfile>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT id, name, is_default, is_active, dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, instinct_points, logic_points, psyche_points, message_count, created_at, updated_at
+             FROM persona_profiles WHERE is_active = 1",
+            [],
+            |row| Ok(PersonaProfile {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                is_default: row.get::<_, i64>(2)? != 0,
+                is_active: row.get::<_, i64>(3)? != 0,
+                dominant_trait: row.get(4)?,
+                secondary_trait: row.get(5)?,
+                instinct_weight: row.get(6)?,
+                logic_weight: row.get(7)?,
+                psyche_weight: row.get(8)?,
+                instinct_points: row.get(9)?,
+                logic_points: row.get(10)?,
+                psyche_points: row.get(11)?,
+                message_count: row.get(12)?,
+                created_at: row.get(13)?,
+                updated_at: row.get(14)?,
+            })
+        ).optional()
+    })
+}
+
+pub fn get_persona_profile_count() -> Result<i64> {
+    with_read_connection(|conn| {
+        conn.query_row("SELECT COUNT(*) FROM persona_profiles", [], |row| row.get(0))
+    })
+}
+
+pub fn set_active_persona_profile(profile_id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        // Deactivate all profiles
+        conn.execute("UPDATE persona_profiles SET is_active = 0", [])?;
+        // Activate the selected profile
+        conn.execute(
+            "UPDATE persona_profiles SET is_active = 1, updated_at = ?1 WHERE id = ?2",
+            params![now, profile_id]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn set_default_persona_profile(profile_id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        // Remove default from all profiles
+        conn.execute("UPDATE persona_profiles SET is_default = 0", [])?;
+        // Set the selected profile as default
+        conn.execute(
+            "UPDATE persona_profiles SET is_default = 1, updated_at = ?1 WHERE id = ?2",
+            params![now, profile_id]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn update_persona_profile_name(profile_id: &str, new_name: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE persona_profiles SET name = ?1, updated_at = ?2 WHERE id = ?3",
+            params![new_name, now, profile_id]
+        )?;
+        Ok(())
+    })
+}
+
+/// Update the dominant trait for the active persona profile
+pub fn update_dominant_trait(dominant_trait: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    
+    // Derive secondary trait from dominant
+    let secondary = match dominant_trait {
+        "logic" => "instinct",
+        "instinct" => "psyche",
+        "psyche" => "logic",
+        _ => "logic",
+    };
+    
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE persona_profiles SET dominant_trait = ?1, secondary_trait = ?2, updated_at = ?3 WHERE is_active = 1",
+            params![dominant_trait, secondary, now]
+        )?;
+        Ok(())
+    })
+}
+
+/// Set both traits on the active persona profile, recalculating its weights
+pub fn update_active_persona_traits(dominant_trait: &str, secondary_trait: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let (instinct_weight, logic_weight, psyche_weight) = calculate_trait_weights(dominant_trait, secondary_trait);
+
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE persona_profiles SET dominant_trait = ?1, secondary_trait = ?2, instinct_weight = ?3, logic_weight = ?4, psyche_weight = ?5, updated_at = ?6 WHERE is_active = 1",
+            params![dominant_trait, secondary_trait, instinct_weight, logic_weight, psyche_weight, now]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn delete_persona_profile(profile_id: &str) -> Result<()> {
+    with_connection(|conn| {
+        // Don't allow deleting the last profile
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM persona_profiles", [], |row| row.get(0))?;
+        if count <= 1 {
+            return Err(rusqlite::Error::QueryReturnedNoRows); // Using this as a simple error
+        }
+        
+        // Check if this is the active profile
+        let is_active: bool = conn.query_row(
+            "SELECT is_active FROM persona_profiles WHERE id = ?1",
+            params![profile_id],
+            |row| Ok(row.get::<_, i64>(0)? != 0)
+        ).unwrap_or(false);
+        
+        // Delete the profile
+        conn.execute("DELETE FROM persona_profiles WHERE id = ?1", params![profile_id])?;
+        
+        // If we deleted the active profile, activate the default or first remaining
+        if is_active {
+            // Try to activate the default profile
+            let activated = conn.execute(
+                "UPDATE persona_profiles SET is_active = 1 WHERE is_default = 1",
+                []
+            )?;
+            
+            // If no default, activate the first one
+            if activated == 0 {
+                conn.execute(
+                    "UPDATE persona_profiles SET is_active = 1 WHERE id = (SELECT id FROM persona_profiles ORDER BY created_at ASC LIMIT 1)",
+                    []
+                )?;
+            }
+        }
+        
+        Ok(())
+    })
+}
+
+// ============ AGENT INTERACTION TRACKING ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentInteraction {
+    pub profile_id: String,
+    pub agent_type: String,
+    pub total_interactions: i64,
+    pub positive_engagements: i64,
+    pub negative_engagements: i64,
+    pub last_interaction: Option<String>,
+}
+
+/// Get or create agent interaction record for a profile/agent pair
+pub fn get_agent_interaction(profile_id: &str, agent_type: &str) -> Result<AgentInteraction> {
+    let now = Utc::now().to_rfc3339();
+    
+    with_connection(|conn| {
+        // Try to get existing record
+        let result: Result<AgentInteraction> = conn.query_row(
+            "SELECT profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, last_interaction 
+             FROM agent_interactions WHERE profile_id = ?1 AND agent_type = ?2",
+            params![profile_id, agent_type],
+            |row| Ok(AgentInteraction {
+                profile_id: row.get(0)?,
+                agent_type: row.get(1)?,
+                total_interactions: row.get(2)?,
+                positive_engagements: row.get(3)?,
+                negative_engagements: row.get(4)?,
+                last_interaction: row.get(5)?,
+            })
+        );
+        
+        match result {
+            Ok(interaction) => Ok(interaction),
+            Err(_) => {
+                // Create new record
+                conn.execute(
+                    "INSERT INTO agent_interactions (profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, created_at, updated_at)
+                     VALUES (?1, ?2, 0, 0, 0, ?3, ?3)",
+                    params![profile_id, agent_type, now]
+                )?;
+                
+                Ok(AgentInteraction {
+                    profile_id: profile_id.to_string(),
+                    agent_type: agent_type.to_string(),
+                    total_interactions: 0,
+                    positive_engagements: 0,
+                    negative_engagements: 0,
+                    last_interaction: None,
+                })
+            }
+        }
+    })
+}
+
+/// Record an interaction with an agent (positive or negative)
+pub fn record_agent_interaction(profile_id: &str, agent_type: &str, is_positive: bool) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    
+    with_connection(|conn| {
+        // Ensure record exists
+        let _ = conn.execute(
+            "INSERT OR IGNORE INTO agent_interactions (profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, created_at, updated_at)
+             VALUES (?1, ?2, 0, 0, 0, ?3, ?3)",
+            params![profile_id, agent_type, now]
+        );
+        
+        // Update counts
+        if is_positive {
+            conn.execute(
+                "UPDATE agent_interactions SET total_interactions = total_interactions + 1, positive_engagements = positive_engagements + 1, last_interaction = ?1, updated_at = ?1 
+                 WHERE profile_id = ?2 AND agent_type = ?3",
+                params![now, profile_id, agent_type]
+            )?;
+        } else {
+            conn.execute(
+                "UPDATE agent_interactions SET total_interactions = total_interactions + 1, negative_engagements = negative_engagements + 1, last_interaction = ?1, updated_at = ?1 
+                 WHERE profile_id = ?2 AND agent_type = ?3",
+                params![now, profile_id, agent_type]
+            )?;
+        }
+        
+        Ok(())
+    })
+}
+
+/// Get all agent interactions for a profile
+pub fn get_all_agent_interactions(profile_id: &str) -> Result<Vec<AgentInteraction>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT profile_id, agent_type, total_interactions, positive_engagements, negative_engagements, last_interaction 
+             FROM agent_interactions WHERE profile_id = ?1"
+        )?;
+        
+        let interactions = stmt.query_map(params![profile_id], |row| {
+            Ok(AgentInteraction {
+                profile_id: row.get(0)?,
+                agent_type: row.get(1)?,
+                total_interactions: row.get(2)?,
+                positive_engagements: row.get(3)?,
+                negative_engagements: row.get(4)?,
+                last_interaction: row.get(5)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+        
+        Ok(interactions)
+    })
+}
+
+// ============ Agent Style Preferences ============
+
+/// Running engagement average for one response style of one agent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentStylePreference {
+    pub agent: String,
+    pub dimension: String,  // "format" | "ending"
+    pub style: String,      // "list" | "prose" | "question" | "statement"
+    pub score: f64,         // -1.0 to 1.0, mean engagement when the agent used this style
+    pub samples: i64,
+}
+
+/// Older samples fade once a style has this many, so preferences can drift
+const STYLE_SCORE_WINDOW: i64 = 20;
+
+/// Fold one engagement score into the running average for an agent's response style
+pub fn record_style_engagement(profile_id: &str, agent: &str, dimension: &str, style: &str, engagement: f64) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO agent_style_preferences (profile_id, agent, dimension, style, score, samples, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)
+             ON CONFLICT(profile_id, agent, dimension, style) DO UPDATE SET
+                score = score + (?5 - score) / MIN(samples + 1, ?7),
+                samples = samples + 1,
+                updated_at = ?6",
+            params![profile_id, agent, dimension, style, engagement, now, STYLE_SCORE_WINDOW]
+        )?;
+        Ok(())
+    })
+}
+
+/// Style preferences learned for one agent under a profile
+pub fn get_agent_style_preferences(profile_id: &str, agent: &str) -> Result<Vec<AgentStylePreference>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT agent, dimension, style, score, samples FROM agent_style_preferences
+             WHERE profile_id = ?1 AND agent = ?2
+             ORDER BY dimension, style"
+        )?;
+        
+        let prefs = stmt.query_map(params![profile_id, agent], |row| {
+            Ok(AgentStylePreference {
+                agent: row.get(0)?,
+                dimension: row.get(1)?,
+                style: row.get(2)?,
+                score: row.get(3)?,
+                samples: row.get(4)?,
+            })
+        })?;
+        
+        prefs.collect()
+    })
+}
+// ============ RESET PERSONALIZATION ============
+
+/// Reset personalization for a profile - keeps API keys, points, dominant trait, and name
+pub fn reset_personalization(profile_id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let log_path = std::path::Path::new("/Users/briggskellogg/Developer/intersect/.cursor/debug.log");
+    
+    // #region agent log
+    if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
+        use std::io::Write;
+        let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:entry","message":"Starting reset","data":{{"profile_id":"{}"}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H1,H2,H4"}}"#, profile_id, chrono::Utc::now().timestamp_millis());
+    }
+    // #endregion
+    
+    with_connection(|conn| {
+        // Reset persona_profiles weights, points, message_count, and created_at to defaults
+        let persona_rows = conn.execute(
+            "UPDATE persona_profiles SET 
+                instinct_weight = 0.333, 
+                logic_weight = 0.333, 
+                psyche_weight = 0.334, 
+                instinct_points = 4,
+                logic_points = 4,
+                psyche_points = 4,
+                message_count = 0, 
+                created_at = ?1,
+                updated_at = ?1 
+             WHERE id = ?2",
+            params![now, profile_id]
+        )?;
+        
+        // #region agent log
+        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
+            use std::io::Write;
+            let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:persona_update","message":"Updated persona_profiles","data":{{"rows_affected":{}}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H1,H5"}}"#, persona_rows, chrono::Utc::now().timestamp_millis());
+        }
+        // #endregion
+        
+        // Also reset user_profile weights (fallback table)
+        let user_rows = conn.execute(
+            "UPDATE user_profile SET 
+                instinct_weight = 0.333, 
+                logic_weight = 0.333, 
+                psyche_weight = 0.334, 
+                total_messages = 0, 
+                updated_at = ?1",
+            params![now]
+        )?;
+        
+        // #region agent log
+        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
+            use std::io::Write;
+            let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:user_update","message":"Updated user_profile","data":{{"rows_affected":{}}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H2"}}"#, user_rows, chrono::Utc::now().timestamp_millis());
+        }
+        // #endregion
+        
+        // Clear memory tables for this profile
+        let facts = conn.execute("DELETE FROM user_facts WHERE 1=1", [])?;
+        conn.execute("DELETE FROM fact_reviews WHERE 1=1", [])?;
+        let patterns = conn.execute("DELETE FROM user_patterns WHERE 1=1", [])?;
+        let themes = conn.execute("DELETE FROM recurring_themes WHERE 1=1", [])?;
+        let summaries = conn.execute("DELETE FROM conversation_summaries WHERE 1=1", [])?;
+        let interactions = conn.execute("DELETE FROM agent_interactions WHERE profile_id = ?1", params![profile_id])?;
+        conn.execute("DELETE FROM agent_style_preferences WHERE profile_id = ?1", params![profile_id])?;
+        conn.execute("DELETE FROM embeddings WHERE source_type != 'message'", [])?;
+        conn.execute("DELETE FROM analytics_engagement WHERE 1=1", [])?;
+        conn.execute("DELETE FROM analytics_intrinsic_signals WHERE 1=1", [])?;
+        
+        // #region agent log
+        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(log_path) {
+            use std::io::Write;
+            let _ = writeln!(f, r#"{{"location":"db.rs:reset_personalization:memory_clear","message":"Cleared memory tables","data":{{"facts":{},"patterns":{},"themes":{},"summaries":{},"interactions":{}}},"timestamp":{},"sessionId":"debug-session","hypothesisId":"H4"}}"#, facts, patterns, themes, summaries, interactions, chrono::Utc::now().timestamp_millis());
+        }
+        // #endregion
+        
+        Ok(())
+    })
+}
+
+/// Full reset including conversation history
+pub fn reset_personalization_full(profile_id: &str) -> Result<()> {
+    // First do the standard reset
+    reset_personalization(profile_id)?;
+    
+    // Then delete all conversations and messages
+    with_connection(|conn| {
+        conn.execute("DELETE FROM embeddings WHERE 1=1", [])?;
+        conn.execute("DELETE FROM messages WHERE 1=1", [])?;
+        conn.execute("DELETE FROM limbo_entries WHERE 1=1", [])?;
+        conn.execute("DELETE FROM agent_mutes WHERE 1=1", [])?;
+        conn.execute("DELETE FROM conversations WHERE 1=1", [])?;
+        Ok(())
+    })
+}
+
+// ============ JOURNEY SESSION FUNCTIONS ============
+
+/// Create a new journey session for Game Mode
+pub fn create_journey_session(profile_id: &str, conversation_id: &str) -> Result<JourneySession> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO journey_sessions (id, profile_id, conversation_id, phase, phase_confirmed, completed, started_at)
+             VALUES (?1, ?2, ?3, 'exploration', 0, 0, ?4)",
+            params![id, profile_id, conversation_id, now]
+        )?;
+        
+        Ok(JourneySession {
+            id,
+            profile_id: profile_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            phase: "exploration".to_string(),
+            phase_confirmed: false,
+            problem_summary: None,
+            resolution_summary: None,
+            acceptance_summary: None,
+            completed: false,
+            started_at: now,
+            completed_at: None,
+        })
+    })
+}
+
+/// Get journey session by conversation ID
+pub fn get_journey_session_by_conversation(conversation_id: &str) -> Result<Option<JourneySession>> {
+    with_read_connection(|conn| {
+        let result = conn.query_row(
+            "SELECT id, profile_id, conversation_id, phase, phase_confirmed, problem_summary, 
+                    resolution_summary, acceptance_summary, completed, started_at, completed_at
+             FROM journey_sessions WHERE conversation_id = ?1",
+            params![conversation_id],
+            |row| {
+                Ok(JourneySession {
+                    id: row.get(0)?,
+                    profile_id: row.get(1)?,
+                    conversation_id: row.get(2)?,
+                    phase: row.get(3)?,
+                    phase_confirmed: row.get::<_, i64>(4)? != 0,
+                    problem_summary: row.get(5)?,
+                    resolution_summary: row.get(6)?,
+                    acceptance_summary: row.get(7)?,
+                    completed: row.get::<_, i64>(8)? != 0,
+                    started_at: row.get(9)?,
+                    completed_at: row.get(10)?,
+                })
+            }
+        ).optional()?;
+        
+        Ok(result)
+    })
+}
+
+/// Update journey phase (Governor suggests transition)
+pub fn update_journey_phase(session_id: &str, new_phase: &str, summary: Option<&str>) -> Result<()> {
+    with_connection(|conn| {
+        // Update phase and reset confirmation flag
+        conn.execute(
+            "UPDATE journey_sessions SET phase = ?1, phase_confirmed = 0 WHERE id = ?2",
+            params![new_phase, session_id]
+        )?;
+        
+        // Store summary for the previous phase based on which phase we're entering
+        if let Some(summary_text) = summary {
+            match new_phase {
+                "resolution" => {
+                    conn.execute(
+                        "UPDATE journey_sessions SET problem_summary = ?1 WHERE id = ?2",
+                        params![summary_text, session_id]
+                    )?;
+                }
+                "acceptance" => {
+                    conn.execute(
+                        "UPDATE journey_sessions SET resolution_summary = ?1 WHERE id = ?2",
+                        params![summary_text, session_id]
+                    )?;
+                }
+                _ => {}
+            }
+        }
+        
+        Ok(())
+    })
+}
+
+/// Confirm phase transition (user accepts Governor's suggestion)
+pub fn confirm_journey_phase(session_id: &str) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE journey_sessions SET phase_confirmed = 1 WHERE id = ?1",
+            params![session_id]
+        )?;
+        Ok(())
+    })
+}
+
+/// Complete a journey session
+pub fn complete_journey_session(session_id: &str, acceptance_summary: Option<&str>) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    
+    with_connection(|conn| {
+        // Get the profile ID for this session to increment counter
+        let profile_id: String = conn.query_row(
+            "SELECT profile_id FROM journey_sessions WHERE id = ?1",
+            params![session_id],
+            |row| row.get(0)
+        )?;
+        
+        // Mark session as complete
+        conn.execute(
+            "UPDATE journey_sessions SET completed = 1, completed_at = ?1, acceptance_summary = ?2, phase = 'acceptance', phase_confirmed = 1 WHERE id = ?3",
+            params![now, acceptance_summary, session_id]
+        )?;
+        
+        // Increment the journey sessions counter for this profile
+        conn.execute(
+            "UPDATE persona_profiles SET journey_sessions_completed = COALESCE(journey_sessions_completed, 0) + 1 WHERE id = ?1",
+            params![profile_id]
+        )?;
+        
+        Ok(())
+    })
+}
+
+/// Get journey sessions completed count for a profile
+pub fn get_journey_sessions_completed(profile_id: &str) -> Result<i64> {
+    with_read_connection(|conn| {
+        let count: i64 = conn.query_row(
+            "SELECT COALESCE(journey_sessions_completed, 0) FROM persona_profiles WHERE id = ?1",
+            params![profile_id],
+            |row| row.get(0)
+        ).unwrap_or(0);
+        
+        Ok(count)
+    })
+}
+
+// ============ Persona Comparison ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeightSnapshot {
+    pub instinct_weight: f64,
+    pub logic_weight: f64,
+    pub psyche_weight: f64,
+    pub recorded_at: String,
+}
+
+/// Aggregated activity for one persona, used to ground comparisons
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonaActivity {
+    pub profile_id: String,
+    pub conversation_count: i64,
+    pub user_message_count: i64,
+    pub weight_history: Vec<WeightSnapshot>,
+    pub top_topics: Vec<(String, i64)>,   // (topic, conversations mentioning it)
+    pub emotional_tones: Vec<(String, i64)>,
+}
+
+pub fn get_persona_activity(profile_id: &str) -> Result<PersonaActivity> {
+    with_read_connection(|conn| {
+        let conversation_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM conversations WHERE persona_profile_id = ?1",
+            params![profile_id],
+            |row| row.get(0)
+        )?;
+        
+        let user_message_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM messages m JOIN conversations c ON m.conversation_id = c.id
+             WHERE c.persona_profile_id = ?1 AND m.role = 'user'",
+            params![profile_id],
+            |row| row.get(0)
+        )?;
+        
+        let mut stmt = conn.prepare(
+            "SELECT instinct_weight, logic_weight, psyche_weight, recorded_at
+             FROM persona_weight_history WHERE profile_id = ?1 ORDER BY recorded_at ASC"
+        )?;
+        let weight_history = stmt.query_map(params![profile_id], |row| {
+            Ok(WeightSnapshot {
+                instinct_weight: row.get(0)?,
+                logic_weight: row.get(1)?,
+                psyche_weight: row.get(2)?,
+                recorded_at: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        // Latest summary per conversation for this persona
+        let mut stmt = conn.prepare(
+            "SELECT s.key_topics, s.emotional_tone FROM conversation_summaries s
+             JOIN conversations c ON s.conversation_id = c.id
+             WHERE c.persona_profile_id = ?1
+               AND s.id IN (SELECT MAX(id) FROM conversation_summaries GROUP BY conversation_id)"
+        )?;
+        let rows = stmt.query_map(params![profile_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut topics: HashMap<String, i64> = HashMap::new();
+        let mut tones: HashMap<String, i64> = HashMap::new();
+        for (key_topics, tone) in rows {
+            let parsed: Vec<String> = serde_json::from_str(&key_topics).unwrap_or_default();
+            for topic in parsed {
+                *topics.entry(topic.to_lowercase()).or_insert(0) += 1;
+            }
+            if let Some(tone) = tone.filter(|t| !t.is_empty()) {
+                *tones.entry(tone.to_lowercase()).or_insert(0) += 1;
+            }
+        }
+        
+        let sorted = |map: HashMap<String, i64>, limit: usize| {
+            let mut v: Vec<(String, i64)> = map.into_iter().collect();
+            v.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            v.truncate(limit);
+            v
+        };
+        
+        Ok(PersonaActivity {
+            profile_id: profile_id.to_string(),
+            conversation_count,
+            user_message_count,
+            weight_history,
+            top_topics: sorted(topics, 8),
+            emotional_tones: sorted(tones, 5),
+        })
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonaComparison {
+    pub id: String,
+    pub profile_a: String,
+    pub profile_b: String,
+    pub stats: String,      // JSON: [PersonaActivity, PersonaActivity]
+    pub narrative: String,
+    pub created_at: String,
+}
+
+pub fn save_persona_comparison(profile_a: &str, profile_b: &str, stats: &str, narrative: &str) -> Result<PersonaComparison> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO persona_comparisons (id, profile_a, profile_b, stats, narrative, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, profile_a, profile_b, stats, narrative, now]
+        )?;
+        Ok(PersonaComparison {
+            id,
+            profile_a: profile_a.to_string(),
+            profile_b: profile_b.to_string(),
+            stats: stats.to_string(),
+            narrative: narrative.to_string(),
+            created_at: now,
+        })
+    })
+}
+
+pub fn get_persona_comparisons() -> Result<Vec<PersonaComparison>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_a, profile_b, stats, narrative, created_at
+             FROM persona_comparisons ORDER BY created_at DESC"
+        )?;
+        let comparisons = stmt.query_map([], |row| {
+            Ok(PersonaComparison {
+                id: row.get(0)?,
+                profile_a: row.get(1)?,
+                profile_b: row.get(2)?,
+                stats: row.get(3)?,
+                narrative: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        Ok(comparisons)
+    })
+}
+
+// ============ Personality Snapshots ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonalitySnapshot {
+    pub id: i64,
+    pub profile_id: String,
+    pub code: String,
+    pub name: String,
+    pub group: String,
+    pub confidence: f64,
+    pub message_count: i64,
+    pub recorded_at: String,
+}
+
+/// Confidence has to move at least this much for an unchanged type to be recorded again
+const SNAPSHOT_MIN_CONFIDENCE_CHANGE: f64 = 0.02;
+
+/// Record an assessment unless it matches the profile's latest snapshot; returns whether it was recorded
+pub fn save_personality_snapshot(
+    profile_id: &str,
+    code: &str,
+    name: &str,
+    group: &str,
+    confidence: f64,
+    message_count: i64,
+) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let latest: Option<(String, f64)> = conn.query_row(
+            "SELECT code, confidence FROM personality_snapshots WHERE profile_id = ?1 ORDER BY id DESC LIMIT 1",
+            params![profile_id],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).ok();
+        if latest.is_some_and(|(c, conf)| c == code && (conf - confidence).abs() < SNAPSHOT_MIN_CONFIDENCE_CHANGE) {
+            return Ok(false);
+        }
+        conn.execute(
+            "INSERT INTO personality_snapshots (profile_id, code, name, type_group, confidence, message_count, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![profile_id, code, name, group, confidence, message_count, now]
+        )?;
+        Ok(true)
+    })
+}
+
+/// A profile's snapshots, newest first
+pub fn get_personality_snapshots(profile_id: &str, limit: usize) -> Result<Vec<PersonalitySnapshot>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, code, name, type_group, confidence, message_count, recorded_at
+             FROM personality_snapshots WHERE profile_id = ?1 ORDER BY id DESC LIMIT ?2"
+        )?;
+        let snapshots = stmt.query_map(params![profile_id, limit], |row| {
+            Ok(PersonalitySnapshot {
+                id: row.get(0)?,
+                profile_id: row.get(1)?,
+                code: row.get(2)?,
+                name: row.get(3)?,
+                group: row.get(4)?,
+                confidence: row.get(5)?,
+                message_count: row.get(6)?,
+                recorded_at: row.get(7)?,
+            })
+        })?;
+        snapshots.collect()
+    })
+}
+
+// ============ Turn Versions (Re-run Turns) ============
+
+/// A previous set of responses to a user message, kept when the turn is re-run
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TurnVersion {
+    pub id: String,
+    pub user_message_id: String,
+    pub version: i64,
+    pub previous_content: String,   // User message text at the time of this version
+    pub responses: Vec<Message>,
+    pub created_at: String,
+}
+
+/// Archive the current responses to a user message under a new turn version.
+/// Optionally replaces the user message text. Returns the new version id.
+pub fn supersede_turn(user_message_id: &str, new_content: Option<&str>) -> Result<String> {
+    let now = Utc::now().to_rfc3339();
+    let version_id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        let (conversation_id, content, timestamp): (String, String, String) = conn.query_row(
+            "SELECT conversation_id, content, timestamp FROM messages WHERE id = ?1",
+            params![user_message_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        )?;
+        
+        let version: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) + 1 FROM turn_versions WHERE user_message_id = ?1",
+            params![user_message_id],
+            |row| row.get(0)
+        )?;
+        
+        conn.execute(
+            "INSERT INTO turn_versions (id, user_message_id, version, previous_content, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![version_id, user_message_id, version, content, now]
+        )?;
+        
+        // Everything after the user message in this conversation belongs to its turn
+        conn.execute(
+            "UPDATE messages SET superseded_by = ?1
+             WHERE conversation_id = ?2 AND timestamp > ?3 AND role != 'user' AND superseded_by IS NULL",
+            params![version_id, conversation_id, timestamp]
+        )?;
+        
+        if let Some(text) = new_content {
+            conn.execute(
+                "UPDATE messages SET content = ?1 WHERE id = ?2",
+                params![text, user_message_id]
+            )?;
+        }
+        
+        Ok(version_id)
+    })
+}
+
+/// Undo `supersede_turn` when the re-run didn't produce a new turn: the archived
+/// responses become visible again and the user message gets its old text back.
+pub fn restore_superseded_turn(version_id: &str) -> Result<()> {
+    with_connection(|conn| {
+        let version: Option<(String, String)> = conn.query_row(
+            "SELECT user_message_id, previous_content FROM turn_versions WHERE id = ?1",
+            params![version_id],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?;
+        let Some((user_message_id, previous_content)) = version else {
+            return Ok(());
+        };
+        
+        conn.execute(
+            "UPDATE messages SET superseded_by = NULL WHERE superseded_by = ?1",
+            params![version_id]
+        )?;
+        conn.execute(
+            "UPDATE messages SET content = ?1 WHERE id = ?2",
+            params![previous_content, user_message_id]
+        )?;
+        conn.execute("DELETE FROM turn_versions WHERE id = ?1", params![version_id])?;
+        Ok(())
+    })
+}
+
+pub fn get_turn_versions(user_message_id: &str) -> Result<Vec<TurnVersion>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, user_message_id, version, previous_content, created_at
+             FROM turn_versions WHERE user_message_id = ?1 ORDER BY version ASC"
+        )?;
+        let mut versions = stmt.query_map(params![user_message_id], |row| {
+            Ok(TurnVersion {
+                id: row.get(0)?,
+                user_message_id: row.get(1)?,
+                version: row.get(2)?,
+                previous_content: row.get(3)?,
+                responses: Vec::new(),
+                created_at: row.get(4)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, role, content, response_type, references_message_id, timestamp, COALESCE(starred, 0)
+             FROM messages WHERE superseded_by = ?1 ORDER BY seq ASC, timestamp ASC"
+        )?;
+        for version in &mut versions {
+            version.responses = stmt.query_map(params![version.id], row_to_message)?
+                .collect::<Result<Vec<_>>>()?;
+        }
+        
+        Ok(versions)
+    })
+}
+
+// ============ Message Versions (Regenerated Responses) ============
+
+/// An earlier content of an agent response, kept when the response is regenerated
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageVersion {
+    pub id: String,
+    pub message_id: String,
+    pub version: i64,
+    pub content: String,
+    pub created_at: String,
+}
+
+/// Archive a message's current content as a new version, then replace it in place.
+/// The message keeps its id and position, so the UI can swap the text.
+pub fn replace_message_content(message_id: &str, new_content: &str) -> Result<MessageVersion> {
+    let now = Utc::now().to_rfc3339();
+    let version_id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        let content: String = conn.query_row(
+            "SELECT content FROM messages WHERE id = ?1",
+            params![message_id],
+            |row| row.get(0)
+        )?;
+        let version: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) + 1 FROM message_versions WHERE message_id = ?1",
+            params![message_id],
+            |row| row.get(0)
+        )?;
+        
+        conn.execute(
+            "INSERT INTO message_versions (id, message_id, version, content, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![version_id, message_id, version, content, now]
+        )?;
+        conn.execute(
+            "UPDATE messages SET content = ?1 WHERE id = ?2",
+            params![new_content, message_id]
+        )?;
+        
+        Ok(MessageVersion {
+            id: version_id,
+            message_id: message_id.to_string(),
+            version,
+            content,
+            created_at: now,
+        })
+    })
+}
+
+pub fn get_message_versions(message_id: &str) -> Result<Vec<MessageVersion>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, message_id, version, content, created_at
+             FROM message_versions WHERE message_id = ?1 ORDER BY version ASC"
+        )?;
+        let versions = stmt.query_map(params![message_id], |row| {
+            Ok(MessageVersion {
+                id: row.get(0)?,
+                message_id: row.get(1)?,
+                version: row.get(2)?,
+                content: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        versions.collect()
+    })
+}
+
+// ============ Drafts ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Draft {
+    pub id: String,
+    pub kind: String,       // "email" | "text" | "tough_reply"
+    pub context: String,
+    pub agent: String,
+    pub content: String,
+    pub rationale: Option<String>,
+    pub revision: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn row_to_draft(row: &rusqlite::Row) -> Result<Draft> {
+    Ok(Draft {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        context: row.get(2)?,
+        agent: row.get(3)?,
+        content: row.get(4)?,
+        rationale: row.get(5)?,
+        revision: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+pub fn create_draft(kind: &str, context: &str, agent: &str, content: &str, rationale: &str) -> Result<Draft> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO drafts (id, kind, context, agent, content, rationale, revision, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8)",
+            params![id, kind, context, agent, content, rationale, now, now]
+        )?;
+        Ok(Draft {
+            id,
+            kind: kind.to_string(),
+            context: context.to_string(),
+            agent: agent.to_string(),
+            content: content.to_string(),
+            rationale: Some(rationale.to_string()),
+            revision: 1,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    })
+}
+
+pub fn get_draft(id: &str) -> Result<Option<Draft>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT id, kind, context, agent, content, rationale, revision, created_at, updated_at
+             FROM drafts WHERE id = ?1",
+            params![id],
+            row_to_draft
+        ).optional()
+    })
+}
+
+pub fn update_draft(id: &str, content: &str, rationale: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE drafts SET content = ?1, rationale = ?2, revision = revision + 1, updated_at = ?3 WHERE id = ?4",
+            params![content, rationale, now, id]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_recent_drafts(limit: usize) -> Result<Vec<Draft>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, context, agent, content, rationale, revision, created_at, updated_at
+             FROM drafts ORDER BY updated_at DESC LIMIT ?1"
+        )?;
+        let drafts = stmt.query_map(params![limit], row_to_draft)?.collect::<Result<Vec<_>>>()?;
+        Ok(drafts)
+    })
+}
+
+pub fn delete_draft(id: &str) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute("DELETE FROM drafts WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+}
+
+// ============ Decisions ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Decision {
+    pub id: String,
+    pub conversation_id: String,
+    pub decision: String,
+    pub options: String,                // JSON array
+    pub followed_agent: Option<String>,
+    pub created_at: String,
+    pub followed_up_at: Option<String>,
+}
+
+fn row_to_decision(row: &rusqlite::Row) -> Result<Decision> {
+    Ok(Decision {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        decision: row.get(2)?,
+        options: row.get(3)?,
+        followed_agent: row.get(4)?,
+        created_at: row.get(5)?,
+        followed_up_at: row.get(6)?,
+    })
+}
+
+pub fn save_decision(conversation_id: &str, decision: &str, options: &[String], followed_agent: Option<&str>) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    let options_json = serde_json::to_string(options).unwrap_or_else(|_| "[]".to_string());
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO decisions (id, conversation_id, decision, options, followed_agent, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, conversation_id, decision, options_json, followed_agent, now]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_decisions() -> Result<Vec<Decision>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, decision, options, followed_agent, created_at, followed_up_at
+             FROM decisions ORDER BY created_at DESC"
+        )?;
+        let decisions = stmt.query_map([], row_to_decision)?.collect::<Result<Vec<_>>>()?;
+        Ok(decisions)
+    })
+}
+
+/// Oldest decision made before `cutoff` that the Governor hasn't followed up on yet
+pub fn get_decision_due_for_follow_up(cutoff: &str) -> Result<Option<Decision>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT id, conversation_id, decision, options, followed_agent, created_at, followed_up_at
+             FROM decisions WHERE followed_up_at IS NULL AND created_at <= ?1
+             ORDER BY created_at ASC LIMIT 1",
+            params![cutoff],
+            row_to_decision
+        ).optional()
+    })
+}
+
+pub fn mark_decision_followed_up(id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE decisions SET followed_up_at = ?1 WHERE id = ?2",
+            params![now, id]
+        )?;
+        Ok(())
+    })
+}
+
+// ============ Check-ins ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckIn {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub topic: String,
+    pub due_at: String,
+    pub status: String,     // "proposed" | "scheduled" | "delivered" | "done" | "dismissed"
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+fn row_to_check_in(row: &rusqlite::Row) -> Result<CheckIn> {
+    Ok(CheckIn {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        topic: row.get(2)?,
+        due_at: row.get(3)?,
+        status: row.get(4)?,
+        created_at: row.get(5)?,
+        delivered_at: row.get(6)?,
+    })
+}
+
+pub fn create_check_in(conversation_id: Option<&str>, topic: &str, due_at: &str, status: &str) -> Result<CheckIn> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO check_ins (id, conversation_id, topic, due_at, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, conversation_id, topic, due_at, status, now]
+        )?;
+        Ok(CheckIn {
+            id,
+            conversation_id: conversation_id.map(|c| c.to_string()),
+            topic: topic.to_string(),
+            due_at: due_at.to_string(),
+            status: status.to_string(),
+            created_at: now,
+            delivered_at: None,
+        })
+    })
+}
+
+pub fn get_check_in(id: &str) -> Result<Option<CheckIn>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT id, conversation_id, topic, due_at, status, created_at, delivered_at
+             FROM check_ins WHERE id = ?1",
+            params![id],
+            row_to_check_in
+        ).optional()
+    })
+}
+
+pub fn get_check_ins(status: Option<&str>) -> Result<Vec<CheckIn>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, topic, due_at, status, created_at, delivered_at
+             FROM check_ins WHERE (?1 IS NULL OR status = ?1) ORDER BY due_at ASC"
+        )?;
+        let check_ins = stmt.query_map(params![status], row_to_check_in)?.collect::<Result<Vec<_>>>()?;
+        Ok(check_ins)
+    })
+}
+
+/// Scheduled check-ins due at or before `now`
+pub fn get_due_check_ins(now: &str) -> Result<Vec<CheckIn>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, topic, due_at, status, created_at, delivered_at
+             FROM check_ins WHERE status = 'scheduled' AND due_at <= ?1 ORDER BY due_at ASC"
+        )?;
+        let check_ins = stmt.query_map(params![now], row_to_check_in)?.collect::<Result<Vec<_>>>()?;
+        Ok(check_ins)
+    })
+}
+
+pub fn update_check_in_status(id: &str, status: &str, due_at: Option<&str>) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE check_ins SET status = ?1, due_at = COALESCE(?2, due_at) WHERE id = ?3",
+            params![status, due_at, id]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn mark_check_in_delivered(id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE check_ins SET status = 'delivered', delivered_at = ?1 WHERE id = ?2",
+            params![now, id]
+        )?;
+        Ok(())
+    })
+}
+
+// ============ Reminders ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reminder {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub agent: Option<String>, // Agent that set it (None = set by the user)
+    pub text: String,
+    pub due_at: String,
+    pub status: String,        // "pending" | "fired" | "done" | "dismissed"
+    pub created_at: String,
+    pub fired_at: Option<String>,
+}
+
+fn row_to_reminder(row: &rusqlite::Row) -> Result<Reminder> {
+    Ok(Reminder {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        agent: row.get(2)?,
+        text: row.get(3)?,
+        due_at: row.get(4)?,
+        status: row.get(5)?,
+        created_at: row.get(6)?,
+        fired_at: row.get(7)?,
+    })
+}
+
+pub fn create_reminder(conversation_id: Option<&str>, agent: Option<&str>, text: &str, due_at: &str) -> Result<Reminder> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO reminders (id, conversation_id, agent, text, due_at, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6)",
+            params![id, conversation_id, agent, text, due_at, now]
+        )?;
+        Ok(Reminder {
+            id,
+            conversation_id: conversation_id.map(|c| c.to_string()),
+            agent: agent.map(|a| a.to_string()),
+            text: text.to_string(),
+            due_at: due_at.to_string(),
+            status: "pending".to_string(),
+            created_at: now,
+            fired_at: None,
+        })
+    })
+}
+
+/// Drop the pending reminders set in a conversation from `since` (until `until`, if given),
+/// when the turn that set them is rolled back or replaced; returns how many
+pub fn delete_pending_reminders(conversation_id: &str, since: &str, until: Option<&str>) -> Result<usize> {
+    with_connection(|conn| {
+        conn.execute(
+            "DELETE FROM reminders
+             WHERE conversation_id = ?1 AND status = 'pending' AND created_at >= ?2 AND (?3 IS NULL OR created_at < ?3)",
+            params![conversation_id, since, until]
+        )
+    })
+}
+
+pub fn get_reminders(status: Option<&str>) -> Result<Vec<Reminder>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, agent, text, due_at, status, created_at, fired_at
+             FROM reminders WHERE (?1 IS NULL OR status = ?1) ORDER BY due_at ASC"
+        )?;
+        let reminders = stmt.query_map(params![status], row_to_reminder)?.collect::<Result<Vec<_>>>()?;
+        Ok(reminders)
+    })
+}
+
+/// Pending reminders due at or before `now`
+pub fn get_due_reminders(now: &str) -> Result<Vec<Reminder>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, agent, text, due_at, status, created_at, fired_at
+             FROM reminders WHERE status = 'pending' AND due_at <= ?1 ORDER BY due_at ASC"
+        )?;
+        let reminders = stmt.query_map(params![now], row_to_reminder)?.collect::<Result<Vec<_>>>()?;
+        Ok(reminders)
+    })
+}
+
+/// Set a reminder's status; returns false if there's no such reminder
+pub fn update_reminder_status(id: &str, status: &str) -> Result<bool> {
+    with_connection(|conn| {
+        let updated = conn.execute("UPDATE reminders SET status = ?1 WHERE id = ?2", params![status, id])?;
+        Ok(updated > 0)
+    })
+}
+
+pub fn mark_reminder_fired(id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE reminders SET status = 'fired', fired_at = ?1 WHERE id = ?2",
+            params![now, id]
+        )?;
+        Ok(())
+    })
+}
+
+// ============ Message Feedback ============
+
+/// Set (1 = up, -1 = down) or clear (0) the rating on an agent message; returns the previous rating
+pub fn set_message_feedback(message_id: &str, conversation_id: &str, agent: &str, rating: i64) -> Result<Option<i64>> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let previous: Option<i64> = conn.query_row(
+            "SELECT rating FROM message_feedback WHERE message_id = ?1",
+            params![message_id],
+            |row| row.get(0)
+        ).optional()?;
+        if rating == 0 {
+            conn.execute("DELETE FROM message_feedback WHERE message_id = ?1", params![message_id])?;
+        } else {
+            conn.execute(
+                "INSERT INTO message_feedback (message_id, conversation_id, agent, rating, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                 ON CONFLICT(message_id) DO UPDATE SET rating = ?4, updated_at = ?5",
+                params![message_id, conversation_id, agent, rating, now]
+            )?;
+        }
+        Ok(previous)
+    })
+}
+
+/// (agent, thumbs up, thumbs down) for every agent that has been rated
+pub fn get_feedback_totals() -> Result<Vec<(String, i64, i64)>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT agent, SUM(rating > 0), SUM(rating < 0) FROM message_feedback GROUP BY agent ORDER BY agent"
+        )?;
+        let totals = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        totals.collect()
+    })
+}
+
+// ============ Mood Log ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MoodEntry {
+    pub id: i64,
+    pub conversation_id: Option<String>,
+    pub message_id: Option<String>,
+    pub source: String,                 // "message" | "summary"
+    pub sentiment: Option<f64>,         // -1.0 (low) to 1.0 (high)
+    pub emotional_tone: Option<String>, // Summary entries only
+    pub user_state: Option<String>,     // Summary entries only
+    pub created_at: String,
+}
+
+/// Add a mood entry; a message's entry replaces the one from before it was edited
+pub fn add_mood_entry(entry: &MoodEntry) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO mood_log (conversation_id, message_id, source, sentiment, emotional_tone, user_state, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                entry.conversation_id,
+                entry.message_id,
+                entry.source,
+                entry.sentiment,
+                entry.emotional_tone,
+                entry.user_state,
+                entry.created_at
+            ]
+        )?;
+        Ok(())
+    })
+}
+
+/// Mood entries in [since, until) (RFC 3339; None = unbounded), oldest first
+pub fn get_mood_entries(since: Option<&str>, until: Option<&str>) -> Result<Vec<MoodEntry>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, message_id, source, sentiment, emotional_tone, user_state, created_at
+             FROM mood_log WHERE (?1 IS NULL OR created_at >= ?1) AND (?2 IS NULL OR created_at < ?2)
+             ORDER BY created_at ASC"
+        )?;
+        let entries = stmt.query_map(params![since, until], |row| {
+            Ok(MoodEntry {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                message_id: row.get(2)?,
+                source: row.get(3)?,
+                sentiment: row.get(4)?,
+                emotional_tone: row.get(5)?,
+                user_state: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+        entries.collect()
+    })
+}
+
+// ============ Habits ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HabitProgress {
+    pub id: String,
+    pub name: String,
+    pub target_per_week: Option<i64>,
+    pub source: String,             // "explicit" | "inferred"
+    pub this_week: i64,             // Logs in the last 7 days
+    pub week_streak: i64,           // Consecutive 7-day windows meeting the target (or with any log)
+    pub last_logged: Option<String>,
+    pub total_logs: i64,
+}
+
+impl HabitProgress {
+    /// One-line description for agent grounding
+    pub fn describe(&self) -> String {
+        let target = self.target_per_week
+            .map(|t| format!(" (goal {}x/week)", t))
+            .unwrap_or_default();
+        let last = match &self.last_logged {
+            Some(ts) => format!("last logged {}", &ts[..ts.len().min(10)]),
+            None => "never logged".to_string(),
+        };
+        format!(
+            "{}{}: {} in the last 7 days, {}-week streak, {}",
+            self.name, target, self.this_week, self.week_streak, last
+        )
+    }
+}
+
+/// Create a habit if new; fill in the weekly target if one is learned later. Returns the habit id.
+pub fn upsert_habit(name: &str, target_per_week: Option<i64>, source: &str) -> Result<String> {
+    let name = name.trim().to_lowercase();
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO habits (id, name, target_per_week, source, active, created_at)
+             VALUES (?1, ?2, ?3, ?4, 1, ?5)
+             ON CONFLICT(name) DO UPDATE SET
+                target_per_week = COALESCE(?3, target_per_week),
+                active = 1",
+            params![id, name, target_per_week, source, now]
+        )?;
+        conn.query_row("SELECT id FROM habits WHERE name = ?1", params![name], |row| row.get(0))
+    })
+}
+
+pub fn log_habit(habit_id: &str, note: Option<&str>, conversation_id: Option<&str>) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO habit_logs (habit_id, logged_at, note, source_conversation_id) VALUES (?1, ?2, ?3, ?4)",
+            params![habit_id, now, note, conversation_id]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn set_habit_active(habit_id: &str, active: bool) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE habits SET active = ?1 WHERE id = ?2",
+            params![active as i64, habit_id]
+        )?;
+        Ok(())
+    })
+}
+
+/// Progress for every active habit, computed from the logs
+pub fn get_habit_progress() -> Result<Vec<HabitProgress>> {
+    let now = Utc::now();
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, target_per_week, source FROM habits WHERE active = 1 ORDER BY created_at ASC"
+        )?;
+        let habits = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?, row.get::<_, String>(3)?))
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut log_stmt = conn.prepare(
+            "SELECT logged_at FROM habit_logs WHERE habit_id = ?1 ORDER BY logged_at DESC"
+        )?;
+        
+        let mut progress = Vec::new();
+        for (id, name, target_per_week, source) in habits {
+            let logs: Vec<chrono::DateTime<Utc>> = log_stmt.query_map(params![id], |row| row.get::<_, String>(0))?
+                .filter_map(|r| r.ok())
+                .filter_map(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
+                .map(|ts| ts.with_timezone(&Utc))
+                .collect();
+            
+            // Count logs per trailing 7-day window: window 0 = last 7 days, 1 = the 7 before, ...
+            let window_count = |w: i64| {
+                let end = now - chrono::Duration::days(7 * w);
+                let start = end - chrono::Duration::days(7);
+                logs.iter().filter(|t| **t > start && **t <= end).count() as i64
+            };
+            let needed = target_per_week.unwrap_or(1).max(1);
+            
+            let this_week = window_count(0);
+            // The current window still counts as in progress, so it can't break a streak
+            let mut week_streak = if this_week >= needed { 1 } else { 0 };
+            let mut w = 1;
+            while w <= 52 && window_count(w) >= needed {
+                week_streak += 1;
+                w += 1;
+            }
+            
+            progress.push(HabitProgress {
+                id,
+                name,
+                target_per_week,
+                source,
+                this_week,
+                week_streak,
+                last_logged: logs.first().map(|t| t.to_rfc3339()),
+                total_logs: logs.len() as i64,
+            });
+        }
+        
+        Ok(progress)
+    })
+}
+
+// ============ Embeddings ============
+
+/// Everything semantic search can find: (source_type, source_id, conversation_id, content)
+const EMBEDDING_SOURCES_SQL: &str =
+    "SELECT 'message' AS source_type, m.id AS source_id, m.conversation_id, m.content
+     FROM messages m WHERE m.role != 'system' AND m.superseded_by IS NULL AND length(m.content) >= 10
+     UNION ALL
+     SELECT 'fact', CAST(f.id AS TEXT), f.source_conversation_id, f.key || ': ' || f.value
+     FROM user_facts f
+     UNION ALL
+     SELECT 'summary', CAST(s.id AS TEXT), s.conversation_id, s.summary
+     FROM conversation_summaries s WHERE s.summary != ''
+     UNION ALL
+     SELECT 'document', CAST(c.id AS TEXT), NULL, c.content
+     FROM document_chunks c";
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingSource {
+    pub source_type: String,        // "message" | "fact" | "summary" | "document" (a chunk)
+    pub source_id: String,
+    pub conversation_id: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredEmbedding {
+    pub source: EmbeddingSource,
+    pub vector: Vec<f32>,
+}
+
+fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Sources with no embedding for `model` yet, or whose text changed since they were embedded
+pub fn get_unembedded_sources(model: &str, limit: usize) -> Result<Vec<EmbeddingSource>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT src.source_type, src.source_id, src.conversation_id, src.content
+             FROM ({}) src
+             LEFT JOIN embeddings e ON e.source_type = src.source_type AND e.source_id = src.source_id AND e.model = ?1
+             WHERE e.source_id IS NULL OR e.content != src.content
+             LIMIT ?2",
+            EMBEDDING_SOURCES_SQL
+        ))?;
+        let sources = stmt.query_map(params![model, limit], |row| {
+            Ok(EmbeddingSource {
+                source_type: row.get(0)?,
+                source_id: row.get(1)?,
+                conversation_id: row.get(2)?,
+                content: row.get(3)?,
+            })
+        })?;
+        sources.collect()
+    })
+}
+
+pub fn save_embedding(source: &EmbeddingSource, model: &str, vector: &[f32]) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO embeddings (source_type, source_id, conversation_id, content, model, vector, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                source.source_type,
+                source.source_id,
+                source.conversation_id,
+                source.content,
+                model,
+                vector_to_blob(vector),
+                now
+            ]
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_embeddings(model: &str, source_type: Option<&str>) -> Result<Vec<StoredEmbedding>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT source_type, source_id, conversation_id, content, vector FROM embeddings
+             WHERE model = ?1 AND (?2 IS NULL OR source_type = ?2)"
+        )?;
+        let embeddings = stmt.query_map(params![model, source_type], |row| {
+            Ok(StoredEmbedding {
+                source: EmbeddingSource {
+                    source_type: row.get(0)?,
+                    source_id: row.get(1)?,
+                    conversation_id: row.get(2)?,
+                    content: row.get(3)?,
+                },
+                vector: blob_to_vector(&row.get::<_, Vec<u8>>(4)?),
+            })
+        })?;
+        embeddings.collect()
+    })
+}
+
+/// Drop embeddings whose source was deleted, superseded, or embedded with another model; returns how many
+pub fn prune_embeddings(model: &str) -> Result<usize> {
+    with_connection(|conn| {
+        conn.execute(
+            &format!(
+                "DELETE FROM embeddings
+                 WHERE model != ?1 OR (source_type, source_id) NOT IN (SELECT source_type, source_id FROM ({}))",
+                EMBEDDING_SOURCES_SQL
+            ),
+            params![model]
+        )
+    })
+}
+
+// ============ Analytics (raw trait analysis) ============
+// Kept apart from the prompt-facing memory (facts, patterns, themes): these rows are only
+// read by the analytics commands and age out after their retention window.
+
+/// Days each analytics table keeps rows (prompt-facing memory has no age limit)
+const ANALYTICS_RETENTION_DAYS: [(&str, i64); 3] = [
+    ("analytics_engagement", 180),
+    ("analytics_intrinsic_signals", 90),
+    ("analytics_turn_metrics", 365),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EngagementRecord {
+    pub id: i64,
+    pub conversation_id: String,
+    pub message_id: Option<String>,  // The user message that was analyzed
+    pub logic_score: f64,
+    pub instinct_score: f64,
+    pub psyche_score: f64,
+    pub reasoning: Option<String>,
+    pub recorded_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntrinsicSignalRecord {
+    pub id: i64,
+    pub conversation_id: String,
+    pub message_id: Option<String>,
+    pub logic_signal: f64,
+    pub instinct_signal: f64,
+    pub psyche_signal: f64,
+    pub reasoning: Option<String>,
+    pub recorded_at: String,
+}
+
+/// Scores in (logic, instinct, psyche) order
+pub fn record_engagement(conversation_id: &str, message_id: &str, scores: (f64, f64, f64), reasoning: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO analytics_engagement (conversation_id, message_id, logic_score, instinct_score, psyche_score, reasoning, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![conversation_id, message_id, scores.0, scores.1, scores.2, reasoning, now]
+        )?;
+        Ok(())
+    })
+}
+
+/// Signals in (logic, instinct, psyche) order
+pub fn record_intrinsic_signals(conversation_id: &str, message_id: &str, signals: (f64, f64, f64), reasoning: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO analytics_intrinsic_signals (conversation_id, message_id, logic_signal, instinct_signal, psyche_signal, reasoning, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![conversation_id, message_id, signals.0, signals.1, signals.2, reasoning, now]
+        )?;
+        Ok(())
+    })
+}
+
+/// Newest first, optionally for one conversation
+pub fn get_engagement_history(conversation_id: Option<&str>, limit: usize) -> Result<Vec<EngagementRecord>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, message_id, logic_score, instinct_score, psyche_score, reasoning, recorded_at
+             FROM analytics_engagement
+             WHERE ?1 IS NULL OR conversation_id = ?1
+             ORDER BY recorded_at DESC, id DESC
+             LIMIT ?2"
+        )?;
+        
+        let records = stmt.query_map(params![conversation_id, limit], |row| {
+            Ok(EngagementRecord {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                message_id: row.get(2)?,
+                logic_score: row.get(3)?,
+                instinct_score: row.get(4)?,
+                psyche_score: row.get(5)?,
+                reasoning: row.get(6)?,
+                recorded_at: row.get(7)?,
+            })
+        })?;
+        
+        records.collect()
+    })
+}
+
+/// Newest first, optionally for one conversation
+pub fn get_intrinsic_signal_history(conversation_id: Option<&str>, limit: usize) -> Result<Vec<IntrinsicSignalRecord>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, message_id, logic_signal, instinct_signal, psyche_signal, reasoning, recorded_at
+             FROM analytics_intrinsic_signals
+             WHERE ?1 IS NULL OR conversation_id = ?1
+             ORDER BY recorded_at DESC, id DESC
+             LIMIT ?2"
+        )?;
+        
+        let records = stmt.query_map(params![conversation_id, limit], |row| {
+            Ok(IntrinsicSignalRecord {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                message_id: row.get(2)?,
+                logic_signal: row.get(3)?,
+                instinct_signal: row.get(4)?,
+                psyche_signal: row.get(5)?,
+                reasoning: row.get(6)?,
+                recorded_at: row.get(7)?,
+            })
+        })?;
+        
+        records.collect()
+    })
+}
+
+/// One completed turn, for the usage dashboard
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TurnMetric {
+    pub conversation_id: String,
+    pub message_id: Option<String>,  // The user message that started the turn
+    pub mode: String,                // "standard" | "disco" | "game" | "composite"
+    pub debate_mode: Option<String>, // "mild" | "intense" when agents pushed back on each other
+    pub response_count: i64,
+    pub latency_ms: i64,             // From the user message to the last response
+    pub recorded_at: String,
+}
+
+pub fn record_turn_metric(metric: &TurnMetric) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO analytics_turn_metrics (conversation_id, message_id, mode, debate_mode, response_count, latency_ms, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                metric.conversation_id,
+                metric.message_id,
+                metric.mode,
+                metric.debate_mode,
+                metric.response_count,
+                metric.latency_ms,
+                metric.recorded_at
+            ]
+        )?;
+        Ok(())
+    })
+}
+
+/// Turn metrics in [since, until) (RFC 3339; None = unbounded), oldest first
+pub fn get_turn_metrics(since: Option<&str>, until: Option<&str>) -> Result<Vec<TurnMetric>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT conversation_id, message_id, mode, debate_mode, response_count, latency_ms, recorded_at
+             FROM analytics_turn_metrics
+             WHERE (?1 IS NULL OR recorded_at >= ?1) AND (?2 IS NULL OR recorded_at < ?2)
+             ORDER BY recorded_at ASC"
+        )?;
+        let metrics = stmt.query_map(params![since, until], |row| {
+            Ok(TurnMetric {
+                conversation_id: row.get(0)?,
+                message_id: row.get(1)?,
+                mode: row.get(2)?,
+                debate_mode: row.get(3)?,
+                response_count: row.get(4)?,
+                latency_ms: row.get(5)?,
+                recorded_at: row.get(6)?,
+            })
+        })?;
+        metrics.collect()
+    })
+}
+
+/// Current (non-superseded) message counts per role in [since, until), most first
+pub fn get_message_counts_by_role(since: Option<&str>, until: Option<&str>) -> Result<Vec<(String, i64)>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT role, COUNT(*) FROM messages
+             WHERE superseded_by IS NULL AND role != 'system'
+               AND (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp < ?2)
+             GROUP BY role ORDER BY COUNT(*) DESC"
+        )?;
+        let counts = stmt.query_map(params![since, until], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        counts.collect()
+    })
+}
+
+/// Creation times of conversations started in [since, until)
+pub fn get_conversation_start_times(since: Option<&str>, until: Option<&str>) -> Result<Vec<String>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT created_at FROM conversations
+             WHERE (?1 IS NULL OR created_at >= ?1) AND (?2 IS NULL OR created_at < ?2)"
+        )?;
+        let times = stmt.query_map(params![since, until], |row| row.get(0))?;
+        times.collect()
+    })
+}
+
+/// When each fact first learned in [since, until) was first mentioned
+pub fn get_fact_learned_times(since: Option<&str>, until: Option<&str>) -> Result<Vec<String>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT first_mentioned FROM user_facts
+             WHERE (?1 IS NULL OR first_mentioned >= ?1) AND (?2 IS NULL OR first_mentioned < ?2)"
+        )?;
+        let times = stmt.query_map(params![since, until], |row| row.get(0))?;
+        times.collect()
+    })
+}
+
+/// Drop analytics rows older than each table's retention window; returns rows removed
+pub fn prune_analytics() -> Result<usize> {
+    let now = Utc::now();
+    with_connection(|conn| {
+        let mut removed = 0;
+        for (table, days) in ANALYTICS_RETENTION_DAYS {
+            let cutoff = (now - chrono::Duration::days(days)).to_rfc3339();
+            removed += conn.execute(
+                &format!("DELETE FROM {} WHERE recorded_at < ?1", table),
+                params![cutoff]
+            )?;
+        }
+        Ok(removed)
+    })
+}
+
+// ============ Notifications ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notification {
+    pub id: String,
+    pub kind: String,               // e.g. "theme_spike"
+    pub subject: Option<String>,    // What the notification is about (used for cooldowns)
+    pub title: String,
+    pub body: String,
+    pub created_at: String,
+    pub read_at: Option<String>,
+}
+
+fn row_to_notification(row: &rusqlite::Row) -> Result<Notification> {
+    Ok(Notification {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        subject: row.get(2)?,
+        title: row.get(3)?,
+        body: row.get(4)?,
+        created_at: row.get(5)?,
+        read_at: row.get(6)?,
+    })
+}
+
+pub fn create_notification(kind: &str, subject: Option<&str>, title: &str, body: &str) -> Result<Notification> {
+    let now = Utc::now().to_rfc3339();
+    let id = uuid::Uuid::new_v4().to_string();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO notifications (id, kind, subject, title, body, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, kind, subject, title, body, now]
+        )?;
+        Ok(Notification {
+            id,
+            kind: kind.to_string(),
+            subject: subject.map(|s| s.to_string()),
+            title: title.to_string(),
+            body: body.to_string(),
+            created_at: now,
+            read_at: None,
+        })
+    })
+}
+
+pub fn get_notifications(unread_only: bool, limit: usize) -> Result<Vec<Notification>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, subject, title, body, created_at, read_at
+             FROM notifications WHERE (?1 = 0 OR read_at IS NULL)
+             ORDER BY created_at DESC LIMIT ?2"
+        )?;
+        let notifications = stmt.query_map(params![unread_only, limit as i64], row_to_notification)?;
+        notifications.collect()
+    })
+}
+
+pub fn mark_notification_read(id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "UPDATE notifications SET read_at = ?1 WHERE id = ?2 AND read_at IS NULL",
+            params![now, id]
+        )?;
+        Ok(())
+    })
+}
+
+/// When a notification of this kind was last raised about this subject
+pub fn get_last_notification_at(kind: &str, subject: &str) -> Result<Option<String>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT MAX(created_at) FROM notifications WHERE kind = ?1 AND subject = ?2",
+            params![kind, subject],
+            |row| row.get(0)
+        )
+    })
+}
+
+// ============ Agent Prompt Overrides ============
+
+/// The user's prompt for an agent in a mode, if they've replaced the built-in one
+pub fn get_agent_prompt_override(agent: &str, mode: &str) -> Result<Option<String>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT prompt FROM agent_prompts WHERE agent = ?1 AND mode = ?2",
+            params![agent, mode],
+            |row| row.get(0)
+        ).optional()
+    })
+}
+
+/// Versions kept per agent and mode; older ones are dropped as new ones are saved
+const MAX_PROMPT_VERSIONS: i64 = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentPromptVersion {
+    pub id: i64,
+    pub agent: String,
+    pub mode: String,
+    pub prompt: String,
+    pub created_at: String,
+}
+
+/// Save an override and record it in the version history
+pub fn set_agent_prompt_override(agent: &str, mode: &str, prompt: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO agent_prompts (agent, mode, prompt, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(agent, mode) DO UPDATE SET prompt = ?3, updated_at = ?4",
+            params![agent, mode, prompt, now]
+        )?;
+        conn.execute(
+            "INSERT INTO agent_prompt_versions (agent, mode, prompt, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![agent, mode, prompt, now]
+        )?;
+        conn.execute(
+            "DELETE FROM agent_prompt_versions WHERE agent = ?1 AND mode = ?2 AND id NOT IN (
+                SELECT id FROM agent_prompt_versions WHERE agent = ?1 AND mode = ?2 ORDER BY id DESC LIMIT ?3
+            )",
+            params![agent, mode, MAX_PROMPT_VERSIONS]
+        )?;
+        Ok(())
+    })
+}
+
+fn row_to_agent_prompt_version(row: &rusqlite::Row) -> rusqlite::Result<AgentPromptVersion> {
+    Ok(AgentPromptVersion {
+        id: row.get(0)?,
+        agent: row.get(1)?,
+        mode: row.get(2)?,
+        prompt: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// Saved versions of an agent's prompt in a mode, newest first
+pub fn get_agent_prompt_versions(agent: &str, mode: &str) -> Result<Vec<AgentPromptVersion>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, agent, mode, prompt, created_at FROM agent_prompt_versions
+             WHERE agent = ?1 AND mode = ?2 ORDER BY id DESC"
+        )?;
+        let versions = stmt.query_map(params![agent, mode], row_to_agent_prompt_version)?;
+        versions.collect()
+    })
+}
+
+pub fn get_agent_prompt_version(id: i64) -> Result<Option<AgentPromptVersion>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT id, agent, mode, prompt, created_at FROM agent_prompt_versions WHERE id = ?1",
+            params![id],
+            row_to_agent_prompt_version
+        ).optional()
+    })
+}
+
+/// Go back to the built-in prompt; returns false if there was no override
+pub fn delete_agent_prompt_override(agent: &str, mode: &str) -> Result<bool> {
+    with_connection(|conn| {
+        let removed = conn.execute(
+            "DELETE FROM agent_prompts WHERE agent = ?1 AND mode = ?2",
+            params![agent, mode]
+        )?;
+        Ok(removed > 0)
+    })
+}
+
+// ============ Reports ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Report {
+    pub id: String,
+    pub kind: String,               // "weekly_digest"
+    pub title: String,
+    pub body: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub created_at: String,
+}
+
+pub fn save_report(kind: &str, title: &str, body: &str, period_start: &str, period_end: &str) -> Result<Report> {
+    let report = Report {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind: kind.to_string(),
+        title: title.to_string(),
+        body: body.to_string(),
+        period_start: period_start.to_string(),
+        period_end: period_end.to_string(),
+        created_at: clock::now().to_rfc3339(),
+    };
+    with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO reports (id, kind, title, body, period_start, period_end, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![report.id, report.kind, report.title, report.body, report.period_start, report.period_end, report.created_at]
+        )?;
+        Ok(())
+    })?;
+    Ok(report)
+}
+
+/// Reports newest first, optionally of one kind
+pub fn get_reports(kind: Option<&str>, limit: usize) -> Result<Vec<Report>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, title, body, period_start, period_end, created_at
+             FROM reports WHERE ?1 IS NULL OR kind = ?1
+             ORDER BY created_at DESC LIMIT ?2"
+        )?;
+        let reports = stmt.query_map(params![kind, limit as i64], |row| {
+            Ok(Report {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                title: row.get(2)?,
+                body: row.get(3)?,
+                period_start: row.get(4)?,
+                period_end: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        reports.collect()
+    })
+}
+
+/// End of the period covered by the latest report of this kind
+pub fn get_last_report_period_end(kind: &str) -> Result<Option<String>> {
+    with_read_connection(|conn| {
+        conn.query_row(
+            "SELECT MAX(period_end) FROM reports WHERE kind = ?1",
+            params![kind],
+            |row| row.get(0)
+        )
+    })
+}
+
+/// Latest summary of each conversation summarized in [since, until)
+pub fn get_summaries_between(since: &str, until: &str) -> Result<Vec<ConversationSummary>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, summary, key_topics, emotional_tone, user_state, agents_involved, message_count, created_at
+             FROM conversation_summaries
+             WHERE created_at >= ?1 AND created_at < ?2
+               AND id IN (SELECT MAX(id) FROM conversation_summaries GROUP BY conversation_id)
+             ORDER BY created_at ASC"
+        )?;
+        let summaries = stmt.query_map(params![since, until], |row| {
+            Ok(ConversationSummary {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                summary: row.get(2)?,
+                key_topics: row.get(3)?,
+                emotional_tone: row.get(4)?,
+                user_state: row.get(5)?,
+                agents_involved: row.get(6)?,
+                message_count: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?;
+        summaries.collect()
+    })
+}
+
+/// Facts first learned in [since, until)
+pub fn get_facts_learned_between(since: &str, until: &str) -> Result<Vec<UserFact>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, category, key, value, confidence, source_type, source_conversation_id, first_mentioned, last_confirmed, mention_count
+             FROM user_facts WHERE first_mentioned >= ?1 AND first_mentioned < ?2
+             ORDER BY first_mentioned ASC"
+        )?;
+        let facts = stmt.query_map(params![since, until], |row| {
+            Ok(UserFact {
+                id: row.get(0)?,
+                category: row.get(1)?,
+                key: row.get(2)?,
+                value: row.get(3)?,
+                confidence: row.get(4)?,
+                source_type: row.get(5)?,
+                source_conversation_id: row.get(6)?,
+                first_mentioned: row.get(7)?,
+                last_confirmed: row.get(8)?,
+                mention_count: row.get(9)?,
+            })
+        })?;
+        facts.collect()
+    })
+}
+
+// ============ Agent Mutes ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentMute {
+    pub id: i64,
+    pub conversation_id: String,
+    pub agent: String,
+    pub reason: Option<String>,
+    pub muted_at: String,
+    pub unmuted_at: Option<String>,
+    pub missed_exchanges: i64,      // User messages sent while muted
+}
+
+fn row_to_agent_mute(row: &rusqlite::Row) -> Result<AgentMute> {
+    Ok(AgentMute {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        agent: row.get(2)?,
+        reason: row.get(3)?,
+        muted_at: row.get(4)?,
+        unmuted_at: row.get(5)?,
+        missed_exchanges: row.get(6)?,
+    })
+}
+
+// Missed exchanges are counted up to the unmute point (or the latest message while still muted)
+const AGENT_MUTE_COLUMNS: &str = "m.id, m.conversation_id, m.agent, m.reason, m.muted_at, m.unmuted_at,
+    (SELECT COUNT(*) FROM messages msg
+     WHERE msg.conversation_id = m.conversation_id AND msg.role = 'user'
+       AND msg.seq > m.muted_seq AND msg.seq <= COALESCE(m.unmuted_seq, msg.seq))";
+
+/// Mute an agent in a conversation (updates the reason if it's already muted)
+pub fn mute_agent(conversation_id: &str, agent: &str, reason: Option<&str>) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let updated = conn.execute(
+            "UPDATE agent_mutes SET reason = ?1 WHERE conversation_id = ?2 AND agent = ?3 AND unmuted_at IS NULL",
+            params![reason, conversation_id, agent]
+        )?;
+        if updated == 0 {
+            conn.execute(
+                "INSERT INTO agent_mutes (conversation_id, agent, reason, muted_at, muted_seq)
+                 VALUES (?1, ?2, ?3, ?4, COALESCE((SELECT last_seq FROM conversations WHERE id = ?1), 0))",
+                params![conversation_id, agent, reason, now]
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Unmute an agent; its next response in the conversation acknowledges the gap
+pub fn unmute_agent(conversation_id: &str, agent: &str) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+    with_connection(|conn| {
+        let updated = conn.execute(
+            "UPDATE agent_mutes SET unmuted_at = ?1, unmuted_seq = COALESCE((SELECT last_seq FROM conversations WHERE id = ?2), 0)
+             WHERE conversation_id = ?2 AND agent = ?3 AND unmuted_at IS NULL",
+            params![now, conversation_id, agent]
+        )?;
+        Ok(updated > 0)
+    })
+}
+
+/// Agents currently muted in a conversation
+pub fn get_muted_agents(conversation_id: &str) -> Result<Vec<AgentMute>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM agent_mutes m WHERE m.conversation_id = ?1 AND m.unmuted_at IS NULL ORDER BY m.id",
+            AGENT_MUTE_COLUMNS
+        ))?;
+        let mutes = stmt.query_map(params![conversation_id], row_to_agent_mute)?;
+        mutes.collect()
+    })
+}
+
+/// Unmuted agents that haven't spoken since coming back
+pub fn get_unacknowledged_returns(conversation_id: &str) -> Result<Vec<AgentMute>> {
+    with_read_connection(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM agent_mutes m
+             WHERE m.conversation_id = ?1 AND m.unmuted_at IS NOT NULL AND m.acknowledged = 0
+             ORDER BY m.id",
+            AGENT_MUTE_COLUMNS
+        ))?;
+        let mutes = stmt.query_map(params![conversation_id], row_to_agent_mute)?;
+        mutes.collect()
+    })
+}
+
+pub fn mark_return_acknowledged(id: i64) -> Result<()> {
+    with_connection(|conn| {
+        conn.execute("UPDATE agent_mutes SET acknowledged = 1 WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    
+    // The connection is a process-wide singleton, so tests take turns with it (other modules'
+    // database tests included)
+    static TEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+    
+    pub(crate) fn fresh_db() -> std::sync::MutexGuard<'static, ()> {
+        let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        init_database_at(Path::new(":memory:")).expect("in-memory database");
+        WRITTEN_THIS_SESSION.lock().unwrap().clear();
+        guard
+    }
+    
+    pub(crate) fn message(conversation_id: &str, role: &str, content: &str, timestamp: &str) -> Message {
+        Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            conversation_id: conversation_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            response_type: None,
+            references_message_id: None,
+            timestamp: timestamp.to_string(),
+            attachments: Vec::new(),
+            starred: false,
+        }
+    }
+    
+    fn column_exists(table: &str, column: &str) -> bool {
+        with_connection(|conn| {
+            conn.query_row(
+                &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+                params![column],
+                |row| row.get::<_, i64>(0)
+            )
+        }).unwrap() > 0
+    }
+    
+    /// Simulate a conversation written by a previous app session (bypasses save_message)
+    fn insert_previous_session_conversation(id: &str, message_count: i64, processed: bool) {
+        with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO conversations (id, processed, is_disco, created_at, updated_at, last_seq)
+                 VALUES (?1, ?2, 0, '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00', ?3)",
+                params![id, processed, message_count]
+            )?;
+            for seq in 1..=message_count {
+                conn.execute(
+                    "INSERT INTO messages (id, conversation_id, role, content, timestamp, seq)
+                     VALUES (?1, ?2, 'user', 'hi', '2024-01-01T00:00:00+00:00', ?3)",
+                    params![format!("{}-{}", id, seq), id, seq]
+                )?;
+            }
+            Ok(())
+        }).unwrap();
+    }
+    
+    // ============ Migrations ============
+    
+    #[test]
+    fn schema_setup_is_idempotent() {
+        let _guard = fresh_db();
+        with_connection(init_schema).expect("second run of schema setup");
+        
+        assert!(column_exists("messages", "seq"));
+        assert!(column_exists("conversations", "last_seq"));
+        assert!(column_exists("conversations", "extraction_watermark"));
+        assert!(column_exists("recurring_themes", "muted"));
+        assert_eq!(get_all_persona_profiles().unwrap().len(), 3);
+        
+        // Each migration is recorded once, however often setup runs
+        let versions: i64 = with_connection(|conn| {
+            conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0))
+        }).unwrap();
+        assert_eq!(versions, MIGRATIONS.len() as i64);
+        assert_eq!(with_connection(schema_version).unwrap(), MIGRATIONS.last().unwrap().version);
+    }
+    
+    #[test]
+    fn databases_at_an_older_version_get_later_columns() {
+        let _guard = fresh_db();
+        // As a database that shipped with migration 3 left it
+        with_connection(|conn| {
+            conn.execute("DELETE FROM schema_version WHERE version > 3", [])?;
+            conn.execute("ALTER TABLE pending_messages DROP COLUMN reply_to", [])?;
+            conn.execute("ALTER TABLE pending_messages DROP COLUMN voice", [])?;
+            Ok(())
+        }).unwrap();
+        assert!(!column_exists("pending_messages", "reply_to"));
+        
+        with_connection(init_schema).unwrap();
+        assert!(column_exists("pending_messages", "reply_to"));
+        assert!(column_exists("pending_messages", "voice"));
+        assert_eq!(with_connection(schema_version).unwrap(), MIGRATIONS.last().unwrap().version);
+    }
+    
+    #[test]
+    fn legacy_database_is_migrated() {
+        let _guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir().join(format!("intersect-legacy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("intersect.db");
+        
+        {
+            let legacy = Connection::open(&path).unwrap();
+            legacy.execute_batch(
+                "
+                CREATE TABLE conversations (
+                    id TEXT PRIMARY KEY,
+                    title TEXT,
+                    summary TEXT,
+                    limbo_summary TEXT,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL
+                );
+                CREATE TABLE messages (
+                    id TEXT PRIMARY KEY,
+                    conversation_id TEXT NOT NULL,
+                    role TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    response_type TEXT,
+                    references_message_id TEXT,
+                    timestamp TEXT NOT NULL
+                );
+                INSERT INTO conversations VALUES ('c1', NULL, NULL, 'User: hello', '2024-01-01T00:00:00+00:00', '2024-01-01T00:05:00+00:00');
+                INSERT INTO messages VALUES ('m2', 'c1', 'logic', 'second', NULL, NULL, '2024-01-01T00:02:00+00:00');
+                INSERT INTO messages VALUES ('m1', 'c1', 'user', 'first', NULL, NULL, '2024-01-01T00:01:00+00:00');
+                INSERT INTO messages VALUES ('m3', 'c1', 'user', 'third', NULL, NULL, '2024-01-01T00:03:00+00:00');
+                "
+            ).unwrap();
+        }
+        
+        init_database_at(&path).expect("migrate legacy database");
+        
+        // Sequence numbers are backfilled in timestamp order
+        let contents: Vec<String> = get_conversation_messages("c1").unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["first", "second", "third"]);
+        assert_eq!(get_last_seq("c1").unwrap(), 3);
+        
+        // The old limbo blob becomes a single entry
+        assert_eq!(get_limbo_summary("c1").unwrap().as_deref(), Some("User: hello"));
+        
+        // The pre-migration state was snapshotted next to it, before anything was rewritten
+        let snapshots: Vec<_> = std::fs::read_dir(dir.join("backups")).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = Connection::open(&snapshots[0]).unwrap();
+        let limbo: Option<String> = snapshot.query_row("SELECT limbo_summary FROM conversations", [], |row| row.get(0)).unwrap();
+        assert_eq!(limbo.as_deref(), Some("User: hello"));
+        let tables: i64 = snapshot.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 3); // conversations, messages, and the empty schema_version
+        
+        init_database_at(Path::new(":memory:")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    // ============ Persona Invariants ============
+    
+    #[test]
+    fn fresh_database_has_one_profile_per_trait() {
+        let _guard = fresh_db();
+        let profiles = get_all_persona_profiles().unwrap();
+        
+        let mut traits: Vec<&str> = profiles.iter().map(|p| p.dominant_trait.as_str()).collect();
+        traits.sort();
+        assert_eq!(traits, vec!["instinct", "logic", "psyche"]);
+        assert_eq!(profiles.iter().filter(|p| p.is_active).count(), 1);
+        assert_eq!(profiles.iter().filter(|p| p.is_default).count(), 1);
+        assert_eq!(get_active_persona_profile().unwrap().unwrap().dominant_trait, "logic");
+    }
+    
+    #[test]
+    fn switching_profiles_keeps_a_single_active_one() {
+        let _guard = fresh_db();
+        let psyche = get_all_persona_profiles().unwrap()
+            .into_iter()
+            .find(|p| p.dominant_trait == "psyche")
+            .unwrap();
+        
+        set_active_persona_profile(&psyche.id).unwrap();
+        
+        let profiles = get_all_persona_profiles().unwrap();
+        assert_eq!(profiles.iter().filter(|p| p.is_active).count(), 1);
+        assert_eq!(get_active_persona_profile().unwrap().unwrap().id, psyche.id);
+    }
+    
+    // ============ Recovery Queries ============
+    
+    #[test]
+    fn recovery_picks_up_unprocessed_conversations_from_previous_sessions() {
+        let _guard = fresh_db();
+        insert_previous_session_conversation("crashed", 2, false);
+        insert_previous_session_conversation("too-short", 1, false);
+        insert_previous_session_conversation("finished", 4, true);
+        
+        let ids: Vec<String> = get_conversations_needing_recovery().unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, vec!["crashed"]);
+    }
+    
+    #[test]
+    fn recovery_skips_conversations_written_this_session() {
+        let _guard = fresh_db();
+        create_conversation("live", false).unwrap();
+        save_message(&message("live", "user", "hello", "2024-01-01T00:00:00+00:00")).unwrap();
+        save_message(&message("live", "logic", "hi", "2024-01-01T00:00:00+00:00")).unwrap();
+        
+        assert!(get_conversations_needing_recovery().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn system_notices_are_not_conversation_activity() {
+        let _guard = fresh_db();
+        insert_previous_session_conversation("short", 1, false);
+        save_system_message("short", "mode_change", "Switched to composite mode", &serde_json::json!({})).unwrap();
+        
+        // One user message plus a notice is still too short to recover, and still from a previous session
+        assert!(get_conversations_needing_recovery().unwrap().is_empty());
+        let conversation = get_conversation("short").unwrap().unwrap();
+        assert_eq!(conversation.updated_at, "2024-01-01T00:00:00+00:00");
+        assert!(get_open_session_conversations().unwrap().is_empty());
+        
+        insert_previous_session_conversation("empty", 0, false);
+        save_system_message("empty", "mode_change", "Switched to composite mode", &serde_json::json!({})).unwrap();
+        assert!(get_recent_conversations(10).unwrap().iter().all(|c| c.id != "empty"));
+    }
+    
+    #[test]
+    fn messages_keep_insertion_order_when_timestamps_tie() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let same_instant = "2024-01-01T00:00:00+00:00";
+        for content in ["one", "two", "three"] {
+            save_message(&message("c", "user", content, same_instant)).unwrap();
+        }
+        
+        let contents: Vec<String> = get_conversation_messages("c").unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["one", "two", "three"]);
+        assert_eq!(get_last_seq("c").unwrap(), 3);
+    }
+    
+    #[test]
+    fn deleting_a_message_relinks_replies_to_it() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let question = message("c", "user", "pasted the wrong thing", "2024-01-01T00:00:00+00:00");
+        let primary = Message { references_message_id: Some(question.id.clone()), ..message("c", "logic", "odd", "2024-01-01T00:00:01+00:00") };
+        let secondary = Message { references_message_id: Some(primary.id.clone()), ..message("c", "psyche", "agreed", "2024-01-01T00:00:02+00:00") };
+        let next = message("c", "user", "anyway", "2024-01-01T00:05:00+00:00");
+        for msg in [&question, &primary, &secondary, &next] {
+            save_message(msg).unwrap();
+        }
+        
+        let deleted = delete_message(&primary.id).unwrap().unwrap();
+        assert_eq!(deleted.relinked, 1);
+        assert_eq!(deleted.exchange_since, question.timestamp);
+        assert_eq!(deleted.exchange_until.as_deref(), Some(next.timestamp.as_str()));
+        
+        let messages = get_conversation_messages("c").unwrap();
+        assert_eq!(messages.len(), 3);
+        let reply = messages.iter().find(|m| m.id == secondary.id).unwrap();
+        assert_eq!(reply.references_message_id.as_deref(), Some(question.id.as_str()));
+        assert!(delete_message(&primary.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn transcript_reads_carry_the_star() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let insight = message("c", "psyche", "Rest is not a reward", "2024-01-01T00:00:00+00:00");
+        save_message(&insight).unwrap();
+        save_message(&message("c", "user", "Say more", "2024-01-01T00:01:00+00:00")).unwrap();
+        assert_eq!(toggle_message_star(&insight.id).unwrap(), Some(true));
+
+        let starred = |messages: Vec<Message>| -> Vec<String> {
+            messages.into_iter().filter(|m| m.starred).map(|m| m.id).collect()
+        };
+        assert_eq!(starred(get_conversation_messages("c").unwrap()), vec![insight.id.clone()]);
+        assert_eq!(starred(get_recent_messages("c", 10).unwrap()), vec![insight.id.clone()]);
+        assert!(get_message(&insight.id).unwrap().unwrap().starred);
+        assert_eq!(get_starred_messages(None).unwrap()[0].message.id, insight.id);
+
+        assert_eq!(toggle_message_star(&insight.id).unwrap(), Some(false));
+        assert!(starred(get_conversation_messages("c").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn rerun_keeps_message_state_and_can_restore_the_archived_turn() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let question = message("c", "user", "Should I move?", "2024-01-01T00:00:00+00:00");
+        let reply = message("c", "logic", "List the costs first", "2024-01-01T00:00:01+00:00");
+        save_message(&question).unwrap();
+        save_message(&reply).unwrap();
+        toggle_message_star(&question.id).unwrap();
+        set_message_metadata(&question.id, r#"{"voice":true}"#).unwrap();
+        
+        // Re-running saves the user message again; that must not reset what save_message doesn't own
+        let version_id = supersede_turn(&question.id, Some("Should I move abroad?")).unwrap();
+        save_message(&Message { content: "Should I move abroad?".to_string(), ..question.clone() }).unwrap();
+        let resaved = get_message(&question.id).unwrap().unwrap();
+        assert!(resaved.starred);
+        assert_eq!(get_message_metadata(&question.id).unwrap().as_deref(), Some(r#"{"voice":true}"#));
+        assert_eq!(get_conversation_messages("c").unwrap().len(), 1);
+        
+        restore_superseded_turn(&version_id).unwrap();
+        let messages = get_conversation_messages("c").unwrap();
+        assert_eq!(messages.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec![question.id.as_str(), reply.id.as_str()]);
+        assert_eq!(messages[0].content, "Should I move?");
+        assert!(get_turn_versions(&question.id).unwrap().is_empty());
+    }
+
+    // ============ Search ============
+    
+    #[test]
+    fn search_follows_message_edits_and_deletes() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let mut msg = message("c", "psyche", "Burnout creeps in when rest feels earned", "2024-01-01T00:00:00+00:00");
+        save_message(&msg).unwrap();
+        save_message(&message("c", "user", "What about weekends?", "2024-01-01T00:01:00+00:00")).unwrap();
+        
+        let hits = search_messages("burnout", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, msg.id);
+        assert!(hits[0].snippet.contains("<mark>Burnout</mark>"));
+        
+        // Re-saving replaces the row; the old text must not linger in the index
+        msg.content = "Rest is not a reward".to_string();
+        save_message(&msg).unwrap();
+        assert!(search_messages("burnout", 10).unwrap().is_empty());
+        assert_eq!(search_messages("rest", 10).unwrap().len(), 1);
+        
+        // Prefix on the last word, punctuation is not query syntax
+        assert_eq!(search_messages("week", 10).unwrap().len(), 1);
+        assert_eq!(search_messages("\"weekends?\"", 10).unwrap().len(), 1);
+        
+        delete_conversation("c").unwrap();
+        assert!(search_messages("rest", 10).unwrap().is_empty());
+    }
+    
+    // ============ Data Archive ============
+    
+    #[test]
+    fn archive_round_trip_keeps_data_but_not_keys() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        save_message(&message("c", "user", "Moving to a new laptop", "2024-01-01T00:00:00+00:00")).unwrap();
+        set_setting("greeting_style", "minimal").unwrap();
+        update_api_key("sk-old-machine").unwrap();
+        set_setting("web_search", r#"{"provider":"brave","api_key":"brave-old-machine"}"#).unwrap();
+        set_setting("mcp_servers", r#"[{"name":"gh","command":"gh-mcp","env":{"GITHUB_TOKEN":"ghp-old-machine"}}]"#).unwrap();
+        set_setting("local_api", r#"{"enabled":true,"port":7777,"token":"bearer-old-machine"}"#).unwrap();
+        set_setting("webhooks", r#"[{"id":"w","url":"https://example.com/hook","secret":"hmac-old-machine"}]"#).unwrap();
+        set_setting("openai_endpoint", r#"{"base_url":"https://example.openai.azure.com","extra_headers":{"api-key":"azure-old-machine"}}"#).unwrap();
+        
+        save_attachment(&Attachment {
+            id: "a".to_string(),
+            conversation_id: "c".to_string(),
+            message_id: None,
+            kind: "image".to_string(),
+            mime_type: "image/png".to_string(),
+            file_name: "whiteboard.png".to_string(),
+            path: "/old-mac/attachments/a.png".to_string(),
+            size_bytes: 3,
+            description: None,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+        }).unwrap();
+        
+        let archive = export_archive(Path::new("/old-mac/attachments")).unwrap();
+        assert_eq!(archive["tables"]["attachments"][0]["path"], "a.png");
+        assert!(!archive.to_string().contains("sk-old-machine"));
+        assert!(!archive.to_string().contains("brave-old-machine"));
+        assert!(!archive.to_string().contains("ghp-old-machine"));
+        assert!(!archive.to_string().contains("bearer-old-machine"));
+        assert!(!archive.to_string().contains("hmac-old-machine"));
+        assert!(!archive.to_string().contains("azure-old-machine"));
+        
+        let (_, removed) = reset_scope(ResetScope::Conversations, false).unwrap();
+        assert_eq!(removed.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), vec!["a"]);
+        set_setting("greeting_style", "contextual").unwrap();
+        update_api_key("sk-new-machine").unwrap();
+        
+        let counts = import_archive(&archive, Path::new("/new-mac/attachments")).unwrap();
+        assert_eq!(get_attachment("a").unwrap().unwrap().path, "/new-mac/attachments/a.png");
+        assert_eq!(counts.iter().find(|c| c.table == "messages").unwrap().rows, 1);
+        assert_eq!(get_conversation_messages("c").unwrap()[0].content, "Moving to a new laptop");
+        assert_eq!(get_setting("greeting_style").unwrap().as_deref(), Some("minimal"));
+        // Webhooks come back without their signing secrets
+        assert!(get_setting("webhooks").unwrap().unwrap().contains("https://example.com/hook"));
+        assert_eq!(get_user_profile().unwrap().api_key.as_deref(), Some("sk-new-machine"));
+        // Imported messages are searchable again
+        assert_eq!(search_messages("laptop", 5).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn archive_from_a_newer_version_is_rejected() {
+        let _guard = fresh_db();
+        let mut archive = export_archive(Path::new("/attachments")).unwrap();
+        archive["version"] = serde_json::json!(ARCHIVE_VERSION + 1);
+        assert!(import_archive(&archive, Path::new("/attachments")).is_err());
+    }
+    
+    #[test]
+    fn reset_dry_run_changes_nothing() {
+        let _guard = fresh_db();
+        insert_previous_session_conversation("c", 3, false);
+        
+        let (preview, _) = reset_scope(ResetScope::Conversations, true).unwrap();
+        let messages = preview.items.iter().find(|i| i.table == "messages").unwrap();
+        assert_eq!(messages.rows, 3);
+        assert_eq!(get_conversation_messages("c").unwrap().len(), 3);
+        
+        reset_scope(ResetScope::Conversations, false).unwrap();
+        assert!(get_conversation_messages("c").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn conversation_reset_clears_mood_history_and_check_ins() {
+        let _guard = fresh_db();
+        insert_previous_session_conversation("c", 1, false);
+        add_mood_entry(&MoodEntry {
+            id: 0,
+            conversation_id: Some("c".to_string()),
+            message_id: Some("c-1".to_string()),
+            source: "message".to_string(),
+            sentiment: Some(-0.4),
+            emotional_tone: None,
+            user_state: None,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+        }).unwrap();
+        create_check_in(Some("c"), "the interview", "2099-01-01T09:00:00+00:00", "scheduled").unwrap();
+        
+        reset_scope(ResetScope::Conversations, false).unwrap();
+        assert!(get_mood_entries(None, None).unwrap().is_empty());
+        assert!(get_check_ins(None).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn deleting_a_conversation_takes_its_decisions_and_journey_with_it() {
+        let _guard = fresh_db();
+        for id in ["c", "other"] {
+            create_conversation(id, false).unwrap();
+            save_decision(id, "take the job", &["take it".to_string(), "stay".to_string()], None).unwrap();
+            create_journey_session("p", id).unwrap();
+        }
+        
+        delete_conversation("c").unwrap();
+        assert!(get_decisions().unwrap().iter().all(|d| d.conversation_id == "other"));
+        assert!(get_journey_session_by_conversation("c").unwrap().is_none());
+        assert!(get_journey_session_by_conversation("other").unwrap().is_some());
+        
+        reset_scope(ResetScope::Conversations, false).unwrap();
+        assert!(get_decisions().unwrap().is_empty());
+        assert!(get_journey_session_by_conversation("other").unwrap().is_none());
+    }
+    
+    #[test]
+    fn weights_reset_to_the_profile_defaults() {
+        let _guard = fresh_db();
+        with_connection(|conn| conn.execute(
+            "UPDATE persona_profiles SET instinct_weight = 0.9, logic_weight = 0.05, psyche_weight = 0.05, instinct_points = 6",
+            []
+        )).unwrap();
+        
+        reset_scope(ResetScope::Weights, false).unwrap();
+        for profile in get_all_persona_profiles().unwrap() {
+            let defaults = DEFAULT_PERSONAS.iter().find(|p| p.dominant_trait == profile.dominant_trait).unwrap();
+            assert_eq!((profile.instinct_weight, profile.logic_weight, profile.psyche_weight), defaults.weights);
+            assert_eq!((profile.instinct_points, profile.logic_points, profile.psyche_points), defaults.points);
+        }
+        let user = get_user_profile().unwrap();
+        assert_eq!((user.instinct_weight, user.logic_weight, user.psyche_weight), RESET_USER_WEIGHTS);
+    }
+    
+    #[test]
+    fn imported_conversations_are_deduplicated_and_skip_recovery() {
+        let _guard = fresh_db();
+        let messages = vec![
+            ("user".to_string(), "hi".to_string(), "2023-03-01T10:00:00+00:00".to_string()),
+            ("logic".to_string(), "hello".to_string(), "2023-03-01T10:00:05+00:00".to_string()),
+        ];
+        let first = save_imported_conversation("chatgpt", "abc", "Old chat", "2023-03-01T10:00:00+00:00",
+            "2023-03-01T10:00:05+00:00", &messages, true).unwrap();
+        let again = save_imported_conversation("chatgpt", "abc", "Old chat", "2023-03-01T10:00:00+00:00",
+            "2023-03-01T10:00:05+00:00", &messages, true).unwrap();
+        
+        let id = first.expect("first import is saved");
+        assert!(again.is_none());
+        assert_eq!(get_conversation_messages(&id).unwrap().len(), 2);
+        assert_eq!(get_conversation(&id).unwrap().unwrap().created_at, "2023-03-01T10:00:00+00:00");
+        assert!(get_conversations_needing_recovery().unwrap().is_empty());
+        assert_eq!(get_pending_import_extractions(10).unwrap(), vec![id.clone()]);
+        
+        mark_import_extracted(&id).unwrap();
+        assert!(get_pending_import_extractions(10).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn pending_messages_keep_order_and_go_with_their_conversation() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let agents = vec!["logic".to_string()];
+        let first = queue_pending_message("c", "first", &agents, &[], &[], None, true).unwrap();
+        let second = queue_pending_message("c", "second", &agents, &[], &["a".to_string()], Some("m1"), false).unwrap();
+        
+        let queued = get_pending_messages(Some("c")).unwrap();
+        assert_eq!(queued.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), vec![first.id.as_str(), second.id.as_str()]);
+        assert_eq!(queued[0].active_agents, agents);
+        assert_eq!(queued[1].attachment_ids, vec!["a".to_string()]);
+        assert_eq!((queued[0].reply_to.as_deref(), queued[1].reply_to.as_deref()), (None, Some("m1")));
+        assert!(queued[0].voice && !queued[1].voice);
+        
+        record_pending_attempt(&first.id, "error sending request").unwrap();
+        assert_eq!(get_pending_messages(None).unwrap()[0].attempts, 1);
+        assert!(delete_pending_message(&first.id).unwrap());
+        assert!(!delete_pending_message(&first.id).unwrap());
+        
+        delete_conversation("c").unwrap();
+        assert!(get_pending_messages(None).unwrap().is_empty());
+    }    
+    #[test]
+    fn reminders_go_with_their_turn_and_conversation() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        create_conversation("other", false).unwrap();
+        let before = Utc::now().to_rfc3339();
+        create_reminder(Some("c"), Some("logic"), "Send the draft", "2099-01-01T09:00:00+00:00").unwrap();
+        create_reminder(Some("other"), Some("logic"), "Call back", "2099-01-01T09:00:00+00:00").unwrap();
+        
+        // A window that ended before the reminder was set leaves it alone
+        assert_eq!(delete_pending_reminders("c", "2000-01-01T00:00:00+00:00", Some(&before)).unwrap(), 0);
+        assert_eq!(delete_pending_reminders("c", &before, None).unwrap(), 1);
+        assert_eq!(get_reminders(None).unwrap().len(), 1);
+        
+        delete_conversation("other").unwrap();
+        assert!(get_reminders(None).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn deleting_a_conversation_can_keep_its_summary_and_unlink_themes() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        create_conversation("other", false).unwrap();
+        save_message(&message("c", "user", "hi", "2025-01-01T00:00:00+00:00")).unwrap();
+        save_recurring_theme("work", "c").unwrap();
+        save_recurring_theme("work", "other").unwrap();
+        save_recurring_theme("health", "c").unwrap();
+        with_connection(|conn| conn.execute(
+            "INSERT INTO conversation_summaries (conversation_id, summary, key_topics, agents_involved, created_at)
+             VALUES ('c', 'gist', '[]', '[]', '2025-01-01T00:00:00+00:00')", []
+        )).unwrap();
+        
+        let counts = delete_conversation_with("c", DeleteConversationOptions {
+            remove_facts: true,
+            remove_themes: true,
+            keep_summary: true,
+        }).unwrap();
+        
+        assert_eq!(counts.messages, 1);
+        assert_eq!(counts.summaries, 0);
+        assert_eq!((counts.themes_updated, counts.themes_removed), (1, 1));
+        assert!(get_conversation("c").unwrap().is_none());
+        assert!(get_conversation_summary("c").unwrap().is_some());
+        let themes = get_all_recurring_themes().unwrap();
+        assert_eq!(themes.len(), 1);
+        assert_eq!(themes[0].related_conversations.as_deref(), Some("[\"other\"]"));
+    }
+    
+    #[test]
+    fn edited_facts_become_explicit_and_deleted_facts_lose_their_embedding() {
+        let _guard = fresh_db();
+        let city = UserFact {
+            id: 0,
+            category: "personal".to_string(),
+            key: "city".to_string(),
+            value: "Boston".to_string(),
+            confidence: 0.6,
+            source_type: "inferred".to_string(),
+            source_conversation_id: None,
+            first_mentioned: "2025-01-01T00:00:00+00:00".to_string(),
+            last_confirmed: "2025-01-01T00:00:00+00:00".to_string(),
+            mention_count: 1,
+        };
+        let (id, inserted) = save_user_fact(&city).unwrap();
+        assert!(inserted);
+        assert_eq!(id, get_all_user_facts().unwrap()[0].id);
+        // Saying it again re-confirms the stored fact
+        assert_eq!(save_user_fact(&city).unwrap(), (id, false));
+        save_embedding(&EmbeddingSource {
+            source_type: "fact".to_string(),
+            source_id: id.to_string(),
+            conversation_id: None,
+            content: "city: Boston".to_string(),
+        }, "m", &[0.5]).unwrap();
+        
+        assert!(update_user_fact(id, "Denver", None).unwrap());
+        let fact = get_user_fact(id).unwrap().unwrap();
+        assert_eq!((fact.value.as_str(), fact.source_type.as_str(), fact.confidence), ("Denver", "explicit", 1.0));
+        
+        assert!(delete_user_fact(id).unwrap());
+        assert!(!delete_user_fact(id).unwrap());
+        assert!(get_embeddings("m", None).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn confidence_decays_by_half_life_and_compounds_across_passes() {
+        assert_eq!(decayed_confidence(1.0, 365.0, 365.0), 0.5);
+        assert_eq!(decayed_confidence(1.0, 10_000.0, 365.0), MIN_DECAYED_CONFIDENCE);
+        assert_eq!(decayed_confidence(0.05, 365.0, 365.0), 0.05);
+        
+        let _guard = fresh_db();
+        save_user_fact(&UserFact {
+            id: 0,
+            category: "work".to_string(),
+            key: "job".to_string(),
+            value: "Engineer".to_string(),
+            confidence: 1.0,
+            source_type: "explicit".to_string(),
+            source_conversation_id: None,
+            first_mentioned: "2024-01-01T00:00:00+00:00".to_string(),
+            last_confirmed: "2024-01-01T00:00:00+00:00".to_string(),
+            mention_count: 1,
+        }).unwrap();
+        
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        decay_confidences(at("2024-07-01T12:00:00+00:00")).unwrap();
+        let summary = decay_confidences(at("2024-12-31T00:00:00+00:00")).unwrap();
+        assert_eq!(summary.facts, 1);
+        let confidence = get_all_user_facts().unwrap()[0].confidence;
+        assert!((confidence - 0.5).abs() < 1e-9, "two passes over one half-life: {}", confidence);
+    }
+    
+    #[test]
+    fn forgotten_fact_keys_are_removed_and_blocked_until_remembered() {
+        let _guard = fresh_db();
+        for category in ["personal", "relationships"] {
+            save_user_fact(&UserFact {
+                id: 0,
+                category: category.to_string(),
+                key: "Sister_Name".to_string(),
+                value: "Ana".to_string(),
+                confidence: 0.9,
+                source_type: "explicit".to_string(),
+                source_conversation_id: None,
+                first_mentioned: "2025-01-01T00:00:00+00:00".to_string(),
+                last_confirmed: "2025-01-01T00:00:00+00:00".to_string(),
+                mention_count: 1,
+            }).unwrap();
+        }
+        
+        assert_eq!(forget_fact("sister_name", 30).unwrap(), 2);
+        assert!(get_all_user_facts().unwrap().is_empty());
+        assert!(is_fact_key_blocked("SISTER_NAME").unwrap());
+        
+        let id = remember_user_fact("relationships", "sister_name", "Ana", None).unwrap();
+        assert!(!is_fact_key_blocked("sister_name").unwrap());
+        let fact = get_user_fact(id).unwrap().unwrap();
+        assert_eq!((fact.confidence, fact.source_type.as_str()), (1.0, "explicit"));
+    }
+    
+    #[test]
+    fn removed_facts_take_their_review_flags_with_them() {
+        let _guard = fresh_db();
+        let fact = |key: &str| UserFact {
+            id: 0,
+            category: "personal".to_string(),
+            key: key.to_string(),
+            value: "something".to_string(),
+            confidence: 0.6,
+            source_type: "inferred".to_string(),
+            source_conversation_id: Some("c".to_string()),
+            first_mentioned: "2024-01-01T00:00:00+00:00".to_string(),
+            last_confirmed: "2024-01-01T00:00:00+00:00".to_string(),
+            mention_count: 1,
+        };
+        let flag_all = || flag_facts_for_review("c", "2024-01-01T00:00:00+00:00", None, "message deleted").unwrap();
+        let review_rows = || with_connection(|conn| conn.query_row("SELECT COUNT(*) FROM fact_reviews", [], |row| row.get::<_, i64>(0))).unwrap();
+        
+        save_user_fact(&fact("pet")).unwrap();
+        flag_all();
+        forget_fact("pet", 30).unwrap();
+        assert_eq!(review_rows(), 0);
+        
+        save_user_fact(&fact("job")).unwrap();
+        save_user_fact(&fact("occupation")).unwrap();
+        flag_all();
+        let facts = get_all_user_facts().unwrap();
+        let keep = facts.iter().find(|f| f.key == "occupation").unwrap().id;
+        let merge = facts.iter().find(|f| f.key == "job").unwrap().id;
+        merge_user_facts(keep, &[merge], "personal", "occupation", "something").unwrap();
+        assert_eq!(review_rows(), 1);
+        
+        reset_scope(ResetScope::Memory, false).unwrap();
+        assert_eq!(review_rows(), 0);
+        
+        // A new fact that reuses an old id starts out unflagged
+        save_user_fact(&fact("city")).unwrap();
+        assert!(get_facts_for_review().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn merging_facts_combines_counts_and_confidence() {
+        let _guard = fresh_db();
+        for (key, value, confidence, when) in [
+            ("job", "engineer", 0.5, "2024-01-01T00:00:00+00:00"),
+            ("occupation", "software engineer", 0.6, "2025-01-01T00:00:00+00:00"),
+        ] {
+            save_user_fact(&UserFact {
+                id: 0,
+                category: "work".to_string(),
+                key: key.to_string(),
+                value: value.to_string(),
+                confidence,
+                source_type: "inferred".to_string(),
+                source_conversation_id: None,
+                first_mentioned: when.to_string(),
+                last_confirmed: when.to_string(),
+                mention_count: 2,
+            }).unwrap();
+        }
+        let facts = get_all_user_facts().unwrap();
+        let keep = facts.iter().find(|f| f.key == "occupation").unwrap().id;
+        let merge = facts.iter().find(|f| f.key == "job").unwrap().id;
+        
+        assert!(merge_user_facts(keep, &[merge], "work", "occupation", "software engineer").unwrap());
+        assert!(!merge_user_facts(keep, &[merge], "work", "occupation", "software engineer").unwrap());
+        
+        let facts = get_all_user_facts().unwrap();
+        assert_eq!(facts.len(), 1);
+        assert_eq!(facts[0].mention_count, 4);
+        assert_eq!(facts[0].first_mentioned, "2024-01-01T00:00:00+00:00");
+        assert!((facts[0].confidence - 0.8).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn tags_are_normalized_and_filter_conversations() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        create_conversation("empty", false).unwrap();
+        save_message(&message("c", "user", "hi", "2025-01-01T00:00:00+00:00")).unwrap();
+        
+        assert!(add_conversation_tag("c", "  Work ", "auto").unwrap());
+        assert!(!add_conversation_tag("c", "work", "user").unwrap());
+        add_conversation_tag("empty", "work", "user").unwrap();
+        
+        let tags = get_conversation_tags("c").unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!((tags[0].tag.as_str(), tags[0].source.as_str()), ("work", "user"));
+        let tagged: Vec<String> = get_conversations_by_tag("WORK").unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(tagged, vec!["c"]);
+        
+        assert!(remove_conversation_tag("c", "Work").unwrap());
+        assert!(get_conversations_by_tag("work").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn attachments_link_to_their_message_and_load_with_the_transcript() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        let attachment = Attachment {
+            id: "a".to_string(),
+            conversation_id: "c".to_string(),
+            message_id: None,
+            kind: "image".to_string(),
+            mime_type: "image/png".to_string(),
+            file_name: "screenshot.png".to_string(),
+            path: "/tmp/a.png".to_string(),
+            size_bytes: 10,
+            description: None,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+        };
+        save_attachment(&attachment).unwrap();
+        save_message(&message("c", "user", "look at this", "2024-01-01T00:00:01+00:00")).unwrap();
+        let message_id = get_conversation_messages("c").unwrap()[0].id.clone();
+        
+        assert_eq!(link_attachments(&message_id, "other", &["a".to_string()]).unwrap(), 0);
+        assert_eq!(link_attachments(&message_id, "c", &["a".to_string()]).unwrap(), 1);
+        assert_eq!(link_attachments(&message_id, "c", &["a".to_string()]).unwrap(), 0);
+        
+        let messages = get_conversation_messages("c").unwrap();
+        assert_eq!(messages[0].attachments.len(), 1);
+        assert_eq!(messages[0].attachments[0].file_name, "screenshot.png");
+        
+        // Undoing a send returns the upload to pending, so it can go with the message again
+        assert_eq!(unlink_attachments(&message_id).unwrap(), vec!["a".to_string()]);
+        assert!(get_message_attachments(&message_id).unwrap().is_empty());
+        assert_eq!(link_attachments(&message_id, "c", &["a".to_string()]).unwrap(), 1);
+        
+        // Clearing the messages takes their attachments with them
+        let removed = clear_conversation_messages("c").unwrap();
+        assert_eq!(removed.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), vec!["a"]);
+        assert!(get_attachment("a").unwrap().is_none());
+        
+        save_attachment(&attachment).unwrap();
+        delete_conversation("c").unwrap();
+        assert!(get_attachment("a").unwrap().is_none());
+    }
+    
+    #[test]
+    fn conversation_agent_presets_are_saved_and_cleared() {
+        let _guard = fresh_db();
+        create_conversation("c", false).unwrap();
+        assert!(get_conversation_agents("c").unwrap().is_none());
+        
+        let dot_only = vec!["logic".to_string()];
+        assert!(set_conversation_agents("c", Some(&dot_only)).unwrap());
+        assert_eq!(get_conversation_agents("c").unwrap(), Some(dot_only));
+        
+        assert!(set_conversation_agents("c", None).unwrap());
+        assert!(get_conversation_agents("c").unwrap().is_none());
+        assert!(!set_conversation_agents("missing", None).unwrap());
+    }
+    
+    #[test]
+    fn prompt_overrides_keep_a_version_history() {
+        let _guard = fresh_db();
+        set_agent_prompt_override("instinct", "disco", "v1").unwrap();
+        set_agent_prompt_override("instinct", "disco", "v2").unwrap();
+        set_agent_prompt_override("instinct", "normal", "calm").unwrap();
+        
+        let versions = get_agent_prompt_versions("instinct", "disco").unwrap();
+        assert_eq!(versions.iter().map(|v| v.prompt.as_str()).collect::<Vec<_>>(), vec!["v2", "v1"]);
+        assert_eq!(get_agent_prompt_override("instinct", "disco").unwrap().as_deref(), Some("v2"));
+        
+        // Rolling back saves the old text as the newest version
+        let v1 = get_agent_prompt_version(versions[1].id).unwrap().unwrap();
+        set_agent_prompt_override(&v1.agent, &v1.mode, &v1.prompt).unwrap();
+        assert_eq!(get_agent_prompt_override("instinct", "disco").unwrap().as_deref(), Some("v1"));
+        assert_eq!(get_agent_prompt_versions("instinct", "disco").unwrap().len(), 3);
+        
+        assert!(delete_agent_prompt_override("instinct", "disco").unwrap());
+        assert!(get_agent_prompt_override("instinct", "disco").unwrap().is_none());
+    }
+    
+    #[test]
+    fn file_databases_use_wal_and_pooled_readers() {
+        let _guard = fresh_db();
+        let dir = std::env::temp_dir().join(format!("intersect-wal-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        init_database_at(&dir.join("intersect.db")).unwrap();
+        
+        let mode: String = with_connection(|conn| conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))).unwrap();
+        assert_eq!(mode, "wal");
+        assert!(READERS.read().unwrap().is_some());
+        
+        // A write on the writer is visible to the next pooled read
+        create_conversation("c", false).unwrap();
+        assert!(get_conversation("c").unwrap().is_some());
+        
+        // Writers on separate connections still take sequence numbers one at a time
+        let writers: Vec<_> = (0..4).map(|w| std::thread::spawn(move || {
+            for i in 0..10 {
+                save_message(&message("c", "user", &format!("{} {}", w, i), "2025-01-31T09:00:00+00:00")).unwrap();
+            }
+        })).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let mut seqs: Vec<i64> = with_read_connection(|conn| {
+            conn.prepare("SELECT seq FROM messages WHERE conversation_id = 'c'")?
+                .query_map([], |row| row.get(0))?
+                .collect()
+        }).unwrap();
+        seqs.sort();
+        assert_eq!(seqs, (1..=40).collect::<Vec<i64>>());
+        
+        // A failed write leaves nothing behind
+        let failed: Result<()> = with_connection(|conn| {
+            conn.execute("INSERT INTO app_settings (key, value, updated_at) VALUES ('half_done', 'x', '')", [])?;
+            conn.execute("INSERT INTO no_such_table VALUES (1)", [])?;
+            Ok(())
+        });
+        assert!(failed.is_err());
+        assert!(get_setting("half_done").unwrap().is_none());
+        
+        init_database_at(Path::new(":memory:")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn sync_merges_newer_rows_messages_and_deletions() {
+        let _guard = fresh_db();
+        create_conversation("c1", false).unwrap();
+        save_message(&message("c1", "user", "from this Mac", "2026-03-10T12:00:00+00:00")).unwrap();
+        let fact = |key: &str, value: &str| UserFact {
+            id: 0,
+            category: "personal".to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+            confidence: 0.9,
+            source_type: "explicit".to_string(),
+            source_conversation_id: None,
+            first_mentioned: "2026-03-01T00:00:00+00:00".to_string(),
+            last_confirmed: "2026-03-01T00:00:00+00:00".to_string(),
+            mention_count: 1,
+        };
+        save_user_fact(&fact("job", "designer")).unwrap();
+        save_user_fact(&fact("city", "Lisbon")).unwrap();
+        
+        // The other Mac added a turn and renamed the conversation later, and re-confirmed a fact
+        let mut theirs = export_sync_data().unwrap();
+        let conversation = &mut theirs.conversations[0];
+        conversation.conversation.insert("title".to_string(), serde_json::json!("Renamed there"));
+        conversation.conversation.insert("updated_at".to_string(), serde_json::json!("2099-01-01T00:00:00+00:00"));
+        let mut reply = conversation.messages[0].clone();
+        reply.insert("id".to_string(), serde_json::json!("from-the-other-mac"));
+        reply.insert("content".to_string(), serde_json::json!("from the other Mac"));
+        reply.insert("timestamp".to_string(), serde_json::json!("2026-03-10T12:01:00+00:00"));
+        conversation.messages.push(reply);
+        let job = theirs.facts.iter_mut().find(|f| f["key"] == "job").unwrap();
+        job.insert("value".to_string(), serde_json::json!("architect"));
+        job.insert("last_confirmed".to_string(), serde_json::json!("2099-01-01T00:00:00+00:00"));
+        
+        // Meanwhile this Mac deleted the city; their stale copy mustn't bring it back
+        let city = get_all_user_facts().unwrap().into_iter().find(|f| f.key == "city").unwrap();
+        assert!(delete_user_fact(city.id).unwrap());
+        
+        let applied = apply_sync_data(&theirs, &HashSet::new()).unwrap();
+        assert_eq!((applied.conversations, applied.messages, applied.facts), (1, 1, 1));
+        assert_eq!(get_conversation("c1").unwrap().unwrap().title.as_deref(), Some("Renamed there"));
+        let contents: Vec<String> = get_conversation_messages("c1").unwrap().into_iter().map(|m| m.content).collect();
+        assert_eq!(contents, vec!["from this Mac", "from the other Mac"]);
+        assert_eq!(get_last_seq("c1").unwrap(), 2);
+        let facts = get_all_user_facts().unwrap();
+        assert_eq!(facts.len(), 1);
+        assert_eq!(facts[0].value, "architect");
+        
+        // Applying the same data again changes nothing
+        assert!(apply_sync_data(&theirs, &HashSet::new()).unwrap().is_empty());
+        
+        // Their deletion of the conversation reaches this Mac
+        let deletion = SyncData {
+            tombstones: vec![SyncTombstone {
+                kind: "conversation".to_string(),
+                key: "c1".to_string(),
+                deleted_at: "2099-02-01T00:00:00+00:00".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(apply_sync_data(&deletion, &HashSet::new()).unwrap().deleted, 1);
+        assert!(get_conversation("c1").unwrap().is_none());
+    }
+    
+    #[test]
+    fn sync_keeps_local_seqs_and_waits_out_a_turn_in_flight() {
+        let _guard = fresh_db();
+        create_conversation("c1", false).unwrap();
+        let question = message("c1", "user", "first", "2026-03-10T12:00:00+00:00");
+        save_message(&question).unwrap();
+        save_message(&message("c1", "logic", "reply", "2026-03-10T12:00:01+00:00")).unwrap();
+        advance_extraction_watermark("c1", 2).unwrap();
+        
+        // The other Mac has an earlier message this one hasn't seen, and a newer copy of the conversation
+        let mut theirs = export_sync_data().unwrap();
+        let conversation = &mut theirs.conversations[0];
+        conversation.conversation.insert("updated_at".to_string(), serde_json::json!("2099-01-01T00:00:00+00:00"));
+        conversation.messages[0].insert("seq".to_string(), serde_json::json!(7));
+        let mut earlier = conversation.messages[0].clone();
+        earlier.insert("id".to_string(), serde_json::json!("from-the-other-mac"));
+        earlier.insert("content".to_string(), serde_json::json!("from the other Mac"));
+        earlier.insert("timestamp".to_string(), serde_json::json!("2026-03-10T11:59:00+00:00"));
+        conversation.messages.push(earlier);
+        
+        // A turn starts here, then the sync lands before it's cancelled
+        let start_seq = get_last_seq("c1").unwrap();
+        save_message(&message("c1", "user", "in flight", "2026-03-10T12:05:00+00:00")).unwrap();
+        let busy: HashSet<String> = ["c1".to_string()].into();
+        assert_eq!(apply_sync_data(&theirs, &busy).unwrap().messages, 0);
+        delete_messages_after("c1", start_seq).unwrap();
+        let contents: Vec<String> = get_conversation_messages("c1").unwrap().into_iter().map(|m| m.content).collect();
+        assert_eq!(contents, vec!["first", "reply"]);
+        
+        // Once the turn is over, their message goes after the last one and nothing local moves
+        assert_eq!(apply_sync_data(&theirs, &HashSet::new()).unwrap().messages, 1);
+        let seqs: Vec<(String, i64)> = with_connection(|conn| {
+            let mut stmt = conn.prepare("SELECT content, seq FROM messages WHERE conversation_id = 'c1' ORDER BY seq")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        }).unwrap();
+        assert_eq!(seqs, vec![("first".to_string(), 1), ("reply".to_string(), 2), ("from the other Mac".to_string(), 4)]);
+        assert_eq!(get_extraction_watermark("c1").unwrap(), Some(2));
+        
+        // A deletion from before the message was written doesn't remove it
+        let stale_deletion = SyncData {
+            tombstones: vec![SyncTombstone {
+                kind: "message".to_string(),
+                key: question.id.clone(),
+                deleted_at: "2026-03-10T11:00:00+00:00".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(apply_sync_data(&stale_deletion, &HashSet::new()).unwrap().deleted, 0);
+        assert!(get_message(&question.id).unwrap().is_some());
+    }
+    
+    #[test]
+    fn reset_tombstones_what_it_wipes() {
+        let _guard = fresh_db();
+        create_conversation("c1", false).unwrap();
+        save_message(&message("c1", "user", "before the reset", "2026-03-10T12:00:00+00:00")).unwrap();
+        let stale = export_sync_data().unwrap();
+        
+        reset_all_data().unwrap();
+        let kinds: Vec<String> = export_sync_data().unwrap().tombstones.into_iter().map(|t| t.kind).collect();
+        assert!(kinds.contains(&"conversation".to_string()) && kinds.contains(&"message".to_string()));
+        
+        // Another device's copy from before the reset doesn't bring the conversation back
+        apply_sync_data(&stale, &HashSet::new()).unwrap();
+        assert!(get_conversation("c1").unwrap().is_none());
+    }
+    
+    #[test]
+    fn branch_starts_extraction_after_the_copied_history() {
+        let _guard = fresh_db();
+        create_conversation("parent", false).unwrap();
+        save_message(&message("parent", "user", "first", "2026-03-10T12:00:00+00:00")).unwrap();
+        save_message(&message("parent", "logic", "reply", "2026-03-10T12:01:00+00:00")).unwrap();
+        let branch_point = message("parent", "user", "second", "2026-03-10T12:02:00+00:00");
+        save_message(&branch_point).unwrap();
+        
+        create_branch("branch", "parent", &branch_point.id).unwrap();
+        assert_eq!(get_extraction_watermark("branch").unwrap(), Some(2));
+        assert!(get_conversation_messages_after("branch", 2).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+//! Debate settings for Intersect
+//!
+//! After the primary and secondary responses, the Governor can let the voices keep going
+//! (`Orchestrator::should_continue_debate`). These settings bound that loop:
+//! - `max_responses`: total agent responses in one exchange, primary and secondary included
+//! - `continue_bias`: leans the Governor's continue/stop call; negative ends debates sooner,
+//!   positive lets them run, and below zero a "continue" is also dropped with that probability
+//! - `allow_outside_disco`: whether rebuttals can turn into a debate when no agent is in disco
+//!
+//! Stored in app_settings as `debate_settings`.
+
+use crate::db;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "debate_settings";
+/// Most agent responses one exchange can have, primary and secondary included
+pub const MAX_RESPONSES_LIMIT: usize = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DebateSettings {
+    pub max_responses: usize,      // 2-6; 2 turns debates off
+    pub continue_bias: f64,        // -1 (stop early) to 1 (keep going); 0 leaves it to the Governor
+    pub allow_outside_disco: bool, // Debates in normal mode, not just disco
+}
+
+impl Default for DebateSettings {
+    fn default() -> Self {
+        Self { max_responses: 4, continue_bias: 0.0, allow_outside_disco: true }
+    }
+}
+
+impl DebateSettings {
+    /// Guidance line for the Governor's continue prompt
+    pub fn bias_guidance(&self) -> &'static str {
+        if self.continue_bias <= -0.5 {
+            "The user prefers short exchanges: only continue for a sharp, genuinely new disagreement."
+        } else if self.continue_bias < 0.0 {
+            "Lean toward stopping unless the next point clearly adds something."
+        } else if self.continue_bias >= 0.5 {
+            "The user enjoys the back-and-forth: continue whenever another voice has a real reaction."
+        } else if self.continue_bias > 0.0 {
+            "Lean toward continuing when there's live disagreement."
+        } else {
+            "Prefer STOPPING if the exchange feels complete or would just belabor the point."
+        }
+    }
+
+    /// Whether a negative bias drops a "continue" the Governor called (probability -continue_bias)
+    pub fn vetoes_continue(&self, rng: &mut impl Rng) -> bool {
+        self.continue_bias < 0.0 && rng.random::<f64>() < -self.continue_bias
+    }
+
+    /// Reject settings outside the ranges the UI offers
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=MAX_RESPONSES_LIMIT).contains(&self.max_responses) {
+            return Err(format!("Max responses must be between 2 and {}", MAX_RESPONSES_LIMIT));
+        }
+        if !(-1.0..=1.0).contains(&self.continue_bias) {
+            return Err("Continue bias must be between -1 and 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a debate can start in this turn
+    pub fn allows_debate(&self, has_any_disco: bool) -> bool {
+        self.max_responses > 2 && (has_any_disco || self.allow_outside_disco)
+    }
+}
+
+pub fn get_settings() -> DebateSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_settings(settings: &DebateSettings) -> Result<(), String> {
+    settings.validate()?;
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn with(max_responses: usize, continue_bias: f64) -> DebateSettings {
+        DebateSettings { max_responses, continue_bias, ..DebateSettings::default() }
+    }
+
+    #[test]
+    fn validation_bounds_the_total_and_the_bias() {
+        assert!(DebateSettings::default().validate().is_ok());
+        assert!(with(2, -1.0).validate().is_ok());
+        assert!(with(MAX_RESPONSES_LIMIT, 1.0).validate().is_ok());
+        assert!(with(1, 0.0).validate().is_err());
+        assert!(with(MAX_RESPONSES_LIMIT + 1, 0.0).validate().is_err());
+        assert!(with(4, -1.5).validate().is_err());
+        assert!(with(4, f64::NAN).validate().is_err());
+    }
+
+    #[test]
+    fn bias_guidance_follows_the_bias() {
+        assert!(with(4, -1.0).bias_guidance().starts_with("The user prefers short exchanges"));
+        assert!(with(4, -0.2).bias_guidance().starts_with("Lean toward stopping"));
+        assert!(with(4, 0.0).bias_guidance().starts_with("Prefer STOPPING"));
+        assert!(with(4, 0.2).bias_guidance().starts_with("Lean toward continuing"));
+        assert!(with(4, 0.5).bias_guidance().starts_with("The user enjoys the back-and-forth"));
+    }
+
+    #[test]
+    fn only_a_negative_bias_vetoes() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!((0..100).all(|_| !with(4, 0.5).vetoes_continue(&mut rng)));
+        assert!((0..100).all(|_| with(4, -1.0).vetoes_continue(&mut rng)));
+        let vetoed = (0..1000).filter(|_| with(4, -0.3).vetoes_continue(&mut rng)).count();
+        assert!((200..400).contains(&vetoed));
+    }
+
+    #[test]
+    fn debates_need_room_and_permission() {
+        assert!(!with(2, 0.0).allows_debate(true));
+        let normal_only = DebateSettings { allow_outside_disco: false, ..DebateSettings::default() };
+        assert!(normal_only.allows_debate(true));
+        assert!(!normal_only.allows_debate(false));
+    }
+}
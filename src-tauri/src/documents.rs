@@ -0,0 +1,201 @@
+//! Document ingestion for Intersect
+//!
+//! PDFs, markdown and plain text files become part of the knowledge agents can draw on:
+//! - The text is extracted (pdf-extract for PDFs), split into overlapping chunks on paragraph
+//!   boundaries, and summarized once with Haiku
+//! - Chunks are stored in document_chunks and embedded with everything else (see `semantic`)
+//! - Each turn, the chunks closest to the user's message are quoted into agent prompts
+//!   (`grounding_note`), so agents can use a document when the conversation touches it
+
+use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
+use crate::db::{self, Document};
+use crate::logging;
+use crate::semantic;
+use std::path::Path;
+
+/// Target chunk size in characters (a few paragraphs)
+const CHUNK_CHARS: usize = 1200;
+/// Characters carried over from the end of one chunk into the next, so no passage is cut in half
+const CHUNK_OVERLAP_CHARS: usize = 150;
+/// Larger files are refused rather than half-ingested
+const MAX_DOCUMENT_BYTES: u64 = 20 * 1024 * 1024;
+/// How much of the document the summarizer reads
+const SUMMARY_INPUT_CHARS: usize = 12000;
+/// Chunks quoted to agents per turn
+const SNIPPETS_PER_TURN: usize = 3;
+
+/// "pdf" | "markdown" | "text", by extension
+pub fn document_kind(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "pdf" => Some("pdf"),
+        "md" | "markdown" => Some("markdown"),
+        "txt" | "text" => Some("text"),
+        _ => None,
+    }
+}
+
+fn extract_text(path: &Path, kind: &str) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let text = match kind {
+        "pdf" => pdf_extract::extract_text_from_mem(&bytes).map_err(|e| format!("Couldn't read the PDF: {}", e))?,
+        _ => String::from_utf8_lossy(&bytes).to_string(),
+    };
+    Ok(text.replace("\r\n", "\n"))
+}
+
+/// Split text into chunks of about CHUNK_CHARS on paragraph boundaries (hard-splitting paragraphs
+/// that are longer), each starting with the tail of the previous one
+pub fn chunk_text(text: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let chars: Vec<char> = paragraph.chars().collect();
+        for piece in chars.chunks(CHUNK_CHARS) {
+            pieces.push(piece.iter().collect());
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut has_new = false; // current holds more than the tail carried over from the last chunk
+    for piece in pieces {
+        if has_new && current.chars().count() + piece.chars().count() > CHUNK_CHARS {
+            let tail: String = {
+                let chars: Vec<char> = current.chars().collect();
+                chars[chars.len().saturating_sub(CHUNK_OVERLAP_CHARS)..].iter().collect()
+            };
+            chunks.push(std::mem::take(&mut current));
+            current = tail;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&piece);
+        has_new = true;
+    }
+    if has_new {
+        chunks.push(current);
+    }
+    chunks
+}
+
+async fn summarize(anthropic_key: &str, title: &str, text: &str) -> Result<String, String> {
+    let excerpt: String = text.chars().take(SUMMARY_INPUT_CHARS).collect();
+    let system_prompt = "You summarize documents a user has added to their personal knowledge base. \
+In 3-5 sentences, say what the document is and the main points someone would want to recall later. No preamble.";
+    AnthropicClient::new(anthropic_key)
+        .chat_completion_advanced(
+            CLAUDE_HAIKU,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content: format!("Title: {}\n\n{}", title, excerpt) }],
+            0.3,
+            Some(300),
+            ThinkingBudget::None,
+        )
+        .await
+        .map(|s| s.trim().to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Extract, chunk, summarize and store a document. Embedding happens on the next indexing pass
+/// (immediately when an OpenAI key is given).
+pub async fn ingest(path: &Path, anthropic_key: Option<&str>, openai_key: Option<&str>) -> Result<Document, String> {
+    let kind = document_kind(path).ok_or("Only PDF, markdown and text files can be ingested")?;
+    let size = std::fs::metadata(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?.len();
+    if size > MAX_DOCUMENT_BYTES {
+        return Err(format!("Documents can be at most {} MB", MAX_DOCUMENT_BYTES / 1024 / 1024));
+    }
+
+    // PDF parsing is CPU-bound, so it stays off the async runtime
+    let source = path.to_path_buf();
+    let text = tokio::task::spawn_blocking(move || extract_text(&source, kind))
+        .await
+        .map_err(|e| e.to_string())??;
+    let chunks = chunk_text(&text);
+    if chunks.is_empty() {
+        return Err("No text found in the document".to_string());
+    }
+    let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "Untitled".to_string());
+
+    // A missing summary doesn't block ingestion; the chunks are what grounding uses
+    let summary = match anthropic_key {
+        Some(key) => match summarize(key, &title, &text).await {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                logging::log_error(None, &format!("Document summary failed for {}: {}", title, e));
+                None
+            }
+        },
+        None => None,
+    };
+
+    let document = Document {
+        id: uuid::Uuid::new_v4().to_string(),
+        title,
+        source_path: path.to_string_lossy().to_string(),
+        kind: kind.to_string(),
+        summary,
+        char_count: text.chars().count() as i64,
+        chunk_count: chunks.len() as i64,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    db::save_document(&document, &chunks).map_err(|e| e.to_string())?;
+    logging::log_memory(None, &format!(
+        "Ingested document \"{}\" ({}, {} chunks)", document.title, document.kind, document.chunk_count
+    ));
+
+    if let Some(key) = openai_key {
+        if let Err(e) = semantic::index_pending(key).await {
+            logging::log_error(None, &format!("Indexing document chunks failed: {}", e));
+        }
+    }
+    Ok(document)
+}
+
+/// Prompt note quoting the document passages closest to the user's message, if any are close
+pub async fn grounding_note(openai_key: &str, user_message: &str) -> Option<String> {
+    if !db::has_documents().unwrap_or(false) {
+        return None;
+    }
+    let snippets = semantic::document_snippets(openai_key, user_message, SNIPPETS_PER_TURN).await;
+    if snippets.is_empty() {
+        return None;
+    }
+    logging::log_memory(None, &format!(
+        "Grounding with {} document passage(s): {}",
+        snippets.len(),
+        snippets.iter().map(|(c, score)| format!("{} #{} ({:.2})", c.document_title, c.chunk_index, score)).collect::<Vec<_>>().join(", ")
+    ));
+
+    let passages = snippets.iter()
+        .map(|(chunk, _)| format!("From \"{}\":\n{}", chunk.document_title, chunk.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(format!(
+        "FROM THE USER'S DOCUMENTS (passages they added that relate to this message -- use them if they help, \
+mention which document when you do, and don't recite them):\n{}",
+        passages
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_follow_paragraphs_and_overlap() {
+        let paragraph = "word ".repeat(100); // 500 chars
+        let text = [paragraph.trim(); 5].join("\n\n");
+        let chunks = chunk_text(&text);
+
+        assert!(chunks.len() >= 2);
+        assert!(chunks.iter().all(|c| c.chars().count() <= CHUNK_CHARS + CHUNK_OVERLAP_CHARS + 2));
+        // Each later chunk starts with the end of the one before it
+        let tail: String = chunks[0].chars().rev().take(CHUNK_OVERLAP_CHARS).collect::<Vec<_>>().into_iter().rev().collect();
+        assert!(chunks[1].starts_with(&tail));
+
+        // A single huge paragraph is still split
+        let long = "x".repeat(CHUNK_CHARS * 3);
+        assert!(chunk_text(&long).len() >= 3);
+        assert!(chunk_text("  \n\n ").is_empty());
+    }
+}
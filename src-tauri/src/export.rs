@@ -0,0 +1,437 @@
+//! Long-running exports for Intersect
+//!
+//! Exports can walk every conversation in the database, so they run as background jobs
+//! instead of blocking the IPC thread:
+//! - `start` registers a job and returns its ID immediately
+//! - Progress streams to the frontend as "export-progress" events
+//! - `get_result` returns the job's state and, once finished, the output path
+//!
+//! Single conversations export synchronously (see `render_conversation`) as Markdown or JSON.
+
+use crate::db::{self, Conversation, Message};
+use crate::logging;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportKind {
+    MarkdownArchive,    // One Markdown file per conversation in a timestamped folder
+    ObsidianSync,       // Markdown with frontmatter, written (and overwritten) inside a vault
+    DataReport,         // A single JSON report of everything Intersect knows about the user
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportJobSpec {
+    pub kind: ExportKind,
+    /// Output directory. Required for Obsidian (the vault path); defaults to app data/exports otherwise.
+    pub destination: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    pub id: String,
+    pub kind: ExportKind,
+    pub status: String,     // "running" | "completed" | "failed"
+    pub processed: usize,
+    pub total: usize,
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+static JOBS: Lazy<Mutex<HashMap<String, ExportJob>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Register an export job and run it in the background
+pub fn start(app_handle: tauri::AppHandle, spec: ExportJobSpec) -> Result<String, String> {
+    let output_dir = resolve_output_dir(&app_handle, &spec)?;
+    let id = uuid::Uuid::new_v4().to_string();
+
+    JOBS.lock().unwrap().insert(id.clone(), ExportJob {
+        id: id.clone(),
+        kind: spec.kind,
+        status: "running".to_string(),
+        processed: 0,
+        total: 0,
+        output_path: None,
+        error: None,
+        started_at: Utc::now().to_rfc3339(),
+        finished_at: None,
+    });
+
+    let job_id = id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = run_export(&app_handle, &job_id, spec.kind, &output_dir);
+        finish(&app_handle, &job_id, result);
+    });
+
+    logging::log_conversation(None, &format!("Export job {} started ({:?})", id, spec.kind));
+    Ok(id)
+}
+
+/// Current state of a job (output_path is set once it completes)
+pub fn get_result(job_id: &str) -> Option<ExportJob> {
+    JOBS.lock().unwrap().get(job_id).cloned()
+}
+
+fn resolve_output_dir(app_handle: &tauri::AppHandle, spec: &ExportJobSpec) -> Result<PathBuf, String> {
+    match (&spec.destination, spec.kind) {
+        (Some(dir), _) => Ok(PathBuf::from(dir)),
+        (None, ExportKind::ObsidianSync) => Err("Obsidian sync needs a vault path".to_string()),
+        (None, _) => {
+            let app_data_dir = app_handle.path().app_data_dir()
+                .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+            Ok(app_data_dir.join("exports"))
+        }
+    }
+}
+
+fn update_progress(app_handle: &tauri::AppHandle, job_id: &str, processed: usize, total: usize) {
+    let snapshot = {
+        let mut jobs = JOBS.lock().unwrap();
+        let Some(job) = jobs.get_mut(job_id) else { return };
+        job.processed = processed;
+        job.total = total;
+        job.clone()
+    };
+    let _ = app_handle.emit("export-progress", &snapshot);
+}
+
+fn finish(app_handle: &tauri::AppHandle, job_id: &str, result: Result<PathBuf, String>) {
+    let snapshot = {
+        let mut jobs = JOBS.lock().unwrap();
+        let Some(job) = jobs.get_mut(job_id) else { return };
+        match &result {
+            Ok(path) => {
+                job.status = "completed".to_string();
+                job.output_path = Some(path.to_string_lossy().to_string());
+            }
+            Err(e) => {
+                job.status = "failed".to_string();
+                job.error = Some(e.clone());
+            }
+        }
+        job.finished_at = Some(Utc::now().to_rfc3339());
+        job.clone()
+    };
+
+    match &result {
+        Ok(path) => logging::log_conversation(None, &format!("Export job {} written to {}", job_id, path.display())),
+        Err(e) => logging::log_error(None, &format!("Export job {} failed: {}", job_id, e)),
+    }
+    let _ = app_handle.emit("export-progress", &snapshot);
+}
+
+fn run_export(app_handle: &tauri::AppHandle, job_id: &str, kind: ExportKind, output_dir: &Path) -> Result<PathBuf, String> {
+    match kind {
+        ExportKind::MarkdownArchive => {
+            let folder = output_dir.join(format!("intersect-export-{}", Utc::now().format("%Y%m%d-%H%M%S")));
+            export_conversations(app_handle, job_id, &folder, false)
+        }
+        // Stable file names under a fixed folder, so re-running updates the vault in place
+        ExportKind::ObsidianSync => export_conversations(app_handle, job_id, &output_dir.join("Intersect"), true),
+        ExportKind::DataReport => export_data_report(app_handle, job_id, output_dir),
+    }
+}
+
+// ============ Conversation Exports ============
+
+fn export_conversations(app_handle: &tauri::AppHandle, job_id: &str, folder: &Path, obsidian: bool) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+
+    let conversations = db::get_all_conversations().map_err(|e| e.to_string())?;
+    let total = conversations.len();
+    update_progress(app_handle, job_id, 0, total);
+
+    for (i, conversation) in conversations.iter().enumerate() {
+        let messages = db::get_conversation_messages(&conversation.id).map_err(|e| e.to_string())?;
+        let summary = db::get_conversation_summary(&conversation.id).map_err(|e| e.to_string())?;
+        let markdown = conversation_to_markdown(conversation, &messages, summary.as_ref(), obsidian);
+        std::fs::write(folder.join(file_name_for(conversation)), markdown).map_err(|e| e.to_string())?;
+        update_progress(app_handle, job_id, i + 1, total);
+    }
+
+    Ok(folder.to_path_buf())
+}
+
+/// "2025-01-31 Title words abcd1234.md" - date-sorted, readable, unique per conversation
+fn file_name_for(conversation: &Conversation) -> String {
+    let date = conversation.created_at.get(..10).unwrap_or("undated");
+    let title: String = conversation.title.as_deref().unwrap_or("Untitled")
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+        .take(60)
+        .collect();
+    let short_id = conversation.id.get(..8).unwrap_or(&conversation.id);
+    format!("{} {} {}.md", date, title.trim(), short_id)
+}
+
+fn speaker_name(role: &str) -> String {
+    match role {
+        "user" => "You".to_string(),
+        "system" => "Governor".to_string(),
+        other => {
+            let mut chars = other.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+    }
+}
+
+/// A conversation as Markdown: title, the stored summary (with its topics and tone) if there is one,
+/// then the transcript. `obsidian` adds frontmatter for vaults.
+fn conversation_to_markdown(
+    conversation: &Conversation,
+    messages: &[Message],
+    summary: Option<&db::ConversationSummary>,
+    obsidian: bool,
+) -> String {
+    let title = conversation.title.as_deref().unwrap_or("Untitled conversation");
+    let mut out = String::new();
+
+    if obsidian {
+        let agents: Vec<String> = messages.iter()
+            .filter(|m| m.role != "user" && m.role != "system")
+            .map(|m| m.role.clone())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        out.push_str("---\n");
+        out.push_str(&format!("intersect_id: {}\n", conversation.id));
+        out.push_str(&format!("created: {}\n", conversation.created_at));
+        out.push_str(&format!("updated: {}\n", conversation.updated_at));
+        out.push_str(&format!("agents: [{}]\n", agents.join(", ")));
+        out.push_str("tags: [intersect]\n");
+        out.push_str("---\n\n");
+    }
+
+    out.push_str(&format!("# {}\n\n", title));
+    out.push_str(&format!("*{}*\n\n", conversation.created_at));
+    if let Some(summary) = summary {
+        out.push_str(&format!("> {}\n\n", summary.summary.replace('\n', "\n> ")));
+        let topics: Vec<String> = serde_json::from_str(&summary.key_topics).unwrap_or_default();
+        if !topics.is_empty() {
+            out.push_str(&format!("**Topics:** {}\n\n", topics.join(", ")));
+        }
+        if let Some(tone) = &summary.emotional_tone {
+            out.push_str(&format!("**Tone:** {}\n\n", tone));
+        }
+    } else if let Some(summary) = &conversation.summary {
+        out.push_str(&format!("> {}\n\n", summary.replace('\n', "\n> ")));
+    }
+
+    for message in messages {
+        let response_type = message.response_type.as_deref()
+            .filter(|t| *t != "primary")
+            .map(|t| format!(" ({})", t))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "**{}**{} -- {}\n\n{}\n\n",
+            speaker_name(&message.role), response_type, message.timestamp, message.content
+        ));
+    }
+
+    out
+}
+
+// ============ Single Conversation Export ============
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConversationFormat {
+    Markdown,
+    Json,
+}
+
+impl ConversationFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Bump when the JSON shape changes so importers can tell versions apart
+const CONVERSATION_EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct ConversationExport<'a> {
+    format_version: u32,
+    exported_at: String,
+    conversation: &'a Conversation,
+    summary: Option<&'a db::ConversationSummary>,
+    messages: Vec<ExportedMessage<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExportedMessage<'a> {
+    id: &'a str,
+    role: &'a str,
+    speaker: String,
+    response_type: Option<&'a str>,
+    references_message_id: Option<&'a str>,
+    timestamp: &'a str,
+    content: &'a str,
+}
+
+/// Render one conversation; returns (suggested file name, file contents)
+pub fn render_conversation(conversation_id: &str, format: ConversationFormat) -> Result<(String, String), String> {
+    let conversation = db::get_conversation(conversation_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Conversation not found")?;
+    let messages = db::get_conversation_messages(conversation_id).map_err(|e| e.to_string())?;
+    let summary = db::get_conversation_summary(conversation_id).map_err(|e| e.to_string())?;
+
+    let contents = match format {
+        ConversationFormat::Markdown => conversation_to_markdown(&conversation, &messages, summary.as_ref(), false),
+        ConversationFormat::Json => {
+            let export = ConversationExport {
+                format_version: CONVERSATION_EXPORT_VERSION,
+                exported_at: Utc::now().to_rfc3339(),
+                conversation: &conversation,
+                summary: summary.as_ref(),
+                messages: messages.iter().map(|m| ExportedMessage {
+                    id: &m.id,
+                    role: &m.role,
+                    speaker: speaker_name(&m.role),
+                    response_type: m.response_type.as_deref(),
+                    references_message_id: m.references_message_id.as_deref(),
+                    timestamp: &m.timestamp,
+                    content: &m.content,
+                }).collect(),
+            };
+            serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?
+        }
+    };
+
+    let file_name = file_name_for(&conversation).trim_end_matches(".md").to_string();
+    Ok((format!("{}.{}", file_name, format.extension()), contents))
+}
+
+// ============ Data Report ============
+
+#[derive(Serialize)]
+struct DataReport {
+    generated_at: String,
+    conversation_count: usize,
+    message_count: usize,
+    facts: Vec<db::UserFact>,
+    patterns: Vec<db::UserPattern>,
+    themes: Vec<db::RecurringTheme>,
+    decisions: Vec<db::Decision>,
+    habits: Vec<db::HabitProgress>,
+}
+
+fn export_data_report(app_handle: &tauri::AppHandle, job_id: &str, output_dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+    let conversations = db::get_all_conversations().map_err(|e| e.to_string())?;
+    let total = conversations.len() + 1;
+    let mut message_count = 0;
+    for (i, conversation) in conversations.iter().enumerate() {
+        message_count += db::get_conversation_messages(&conversation.id).map_err(|e| e.to_string())?.len();
+        update_progress(app_handle, job_id, i + 1, total);
+    }
+
+    let report = DataReport {
+        generated_at: Utc::now().to_rfc3339(),
+        conversation_count: conversations.len(),
+        message_count,
+        facts: db::get_all_user_facts().map_err(|e| e.to_string())?,
+        patterns: db::get_all_user_patterns().map_err(|e| e.to_string())?,
+        themes: db::get_all_recurring_themes().map_err(|e| e.to_string())?,
+        decisions: db::get_decisions().map_err(|e| e.to_string())?,
+        habits: db::get_habit_progress().map_err(|e| e.to_string())?,
+    };
+
+    let path = output_dir.join(format!("intersect-report-{}.json", Utc::now().format("%Y%m%d-%H%M%S")));
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())?;
+    update_progress(app_handle, job_id, total, total);
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Conversation {
+        Conversation {
+            id: "abcd1234-0000".to_string(),
+            title: Some("Job offer".to_string()),
+            summary: Some("Weighing the offer".to_string()),
+            processed: true,
+            is_disco: false,
+            created_at: "2025-01-31T09:00:00+00:00".to_string(),
+            updated_at: "2025-01-31T09:05:00+00:00".to_string(),
+            model_override: None,
+            parent_conversation_id: None,
+            branch_point_message_id: None,
+        }
+    }
+
+    fn message(role: &str, response_type: Option<&str>, content: &str) -> Message {
+        Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            conversation_id: "abcd1234-0000".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            response_type: response_type.map(str::to_string),
+            references_message_id: None,
+            timestamp: "2025-01-31T09:01:00+00:00".to_string(),
+            attachments: Vec::new(),
+            starred: false,
+        }
+    }
+
+    #[test]
+    fn markdown_has_the_summary_and_transcript() {
+        let messages = vec![
+            message("user", None, "Should I take it?"),
+            message("logic", Some("primary"), "Compare the total comp."),
+            message("psyche", Some("addition"), "What does your gut say?"),
+        ];
+        let summary = db::ConversationSummary {
+            id: 1,
+            conversation_id: "abcd1234-0000".to_string(),
+            summary: "Deciding on a job offer\nLeaning yes".to_string(),
+            key_topics: r#"["career","money"]"#.to_string(),
+            emotional_tone: Some("hopeful".to_string()),
+            user_state: None,
+            agents_involved: "[]".to_string(),
+            message_count: 3,
+            created_at: "2025-01-31T09:05:00+00:00".to_string(),
+        };
+
+        let markdown = conversation_to_markdown(&conversation(), &messages, Some(&summary), false);
+        assert!(markdown.starts_with("# Job offer\n\n"));
+        assert!(markdown.contains("> Deciding on a job offer\n> Leaning yes\n\n**Topics:** career, money\n\n**Tone:** hopeful"));
+        assert!(markdown.contains("**You** -- 2025-01-31T09:01:00+00:00\n\nShould I take it?"));
+        assert!(markdown.contains("**Logic** -- "));
+        assert!(markdown.contains("**Psyche** (addition) -- "));
+        assert!(!markdown.contains("---\nintersect_id"));
+
+        // Without a stored summary the conversation's own one is used; Obsidian gets frontmatter
+        let obsidian = conversation_to_markdown(&conversation(), &messages, None, true);
+        assert!(obsidian.starts_with("---\nintersect_id: abcd1234-0000\n"));
+        assert!(obsidian.contains("agents: [logic, psyche]"));
+        assert!(obsidian.contains("> Weighing the offer\n\n"));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::openai::ChatMessage;
+use crate::retry;
+use crate::usage;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const REQUEST_TIMEOUT_SECS: u64 = 60; // 60 second timeout for API requests
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    contents: Vec<Content>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    temperature: f32,
+    max_output_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    content: Option<Content>,
+}
+
+pub struct GeminiClient {
+    client: Client,
+    api_key: String,
+}
+
+impl GeminiClient {
+    pub fn new(api_key: &str) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
+            api_key: api_key.to_string(),
+        }
+    }
+
+    /// Validate the API key by listing models (costs nothing)
+    pub async fn validate_api_key(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let response = self.client
+            .get(GEMINI_API_URL)
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(true)
+        } else {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+
+            // Gemini reports a bad key as 400 INVALID_ARGUMENT or 403
+            if matches!(status.as_u16(), 400 | 401 | 403) {
+                return Err("Invalid API key".into());
+            } else if status.as_u16() == 429 {
+                return Err("Rate limited - too many requests".into());
+            }
+
+            Err(format!("API error ({}): {}", status, error_text).into())
+        }
+    }
+
+    /// Chat completion; `messages` use OpenAI roles ("system" / "user" / "assistant")
+    pub async fn chat_completion(
+        &self,
+        model: &str,
+        messages: Vec<ChatMessage>,
+        temperature: f32,
+        max_tokens: Option<u32>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let model = usage::degraded_model("gemini", model).unwrap_or(model);
+
+        // Gemini takes the system prompt separately and calls the assistant "model"
+        let system = messages.iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let contents = messages.into_iter()
+            .filter(|m| m.role != "system")
+            .map(|m| Content {
+                role: Some(if m.role == "assistant" { "model".to_string() } else { "user".to_string() }),
+                parts: vec![Part { text: Some(m.content) }],
+            })
+            .collect();
+
+        let request = GenerateContentRequest {
+            system_instruction: (!system.is_empty()).then(|| Content {
+                role: None,
+                parts: vec![Part { text: Some(system) }],
+            }),
+            contents,
+            generation_config: GenerationConfig {
+                temperature,
+                max_output_tokens: max_tokens.unwrap_or(2048),
+            },
+        };
+
+        let response = retry::send("Gemini chat", self.client
+            .post(format!("{}/{}:generateContent", GEMINI_API_URL, model))
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(&request)
+        ).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("Gemini API error ({}): {}", status, error_text).into());
+        }
+
+        let completion: GenerateContentResponse = response.json().await?;
+        if let Some(usage) = &completion.usage_metadata {
+            usage::record("gemini", model, usage.prompt_token_count, usage.candidates_token_count);
+        }
+        let text: String = completion.candidates
+            .into_iter()
+            .next()
+            .and_then(|c| c.content)
+            .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+            .unwrap_or_default();
+
+        if text.is_empty() {
+            return Err("No response from Gemini".into());
+        }
+        Ok(text)
+    }
+}
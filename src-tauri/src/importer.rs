@@ -0,0 +1,312 @@
+//! Bringing outside conversations into Intersect
+//!
+//! Parses plain-text / Markdown chat transcripts in the simple "Name: text" format:
+//! - `role_mapping` says which speaker names map to which role ("user", "instinct", "logic", "psyche")
+//! - A line starting with a mapped name (optionally bolded, e.g. "**Sam:**") starts a new message
+//! - Any other line continues the previous message, so multi-paragraph replies survive
+//! - A Markdown heading before the first message becomes the conversation title
+//!
+//! Also reads the `conversations.json` found in ChatGPT and Claude data exports (see `parse_chat_export`).
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const IMPORTABLE_ROLES: [&str; 4] = ["user", "instinct", "logic", "psyche"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTranscript {
+    pub title: Option<String>,
+    pub messages: Vec<(String, String)>, // (role, content)
+}
+
+/// Parse a transcript; fails if the mapping names an unknown role or no line matches a mapped speaker
+pub fn parse_transcript(text: &str, role_mapping: &HashMap<String, String>) -> Result<ParsedTranscript, String> {
+    let mut speakers: HashMap<String, &'static str> = HashMap::new();
+    for (name, role) in role_mapping {
+        let role = IMPORTABLE_ROLES.iter()
+            .find(|r| r.eq_ignore_ascii_case(role.trim()))
+            .ok_or_else(|| format!("Unknown role \"{}\" for {} (expected one of: {})", role, name, IMPORTABLE_ROLES.join(", ")))?;
+        speakers.insert(name.trim().to_lowercase(), role);
+    }
+
+    let mut title = None;
+    let mut messages: Vec<(String, String)> = Vec::new();
+
+    for line in text.lines() {
+        if let Some((role, first_line)) = speaker_line(line, &speakers) {
+            messages.push((role.to_string(), first_line.to_string()));
+            continue;
+        }
+
+        match messages.last_mut() {
+            Some((_, content)) => {
+                content.push('\n');
+                content.push_str(line.trim_end());
+            }
+            None => {
+                // Preamble before the first message: keep the first heading as the title
+                let heading = line.trim_start_matches('#');
+                if title.is_none() && heading.len() < line.len() && !heading.trim().is_empty() {
+                    title = Some(heading.trim().to_string());
+                }
+            }
+        }
+    }
+
+    let messages: Vec<(String, String)> = messages.into_iter()
+        .map(|(role, content)| (role, content.trim().to_string()))
+        .filter(|(_, content)| !content.is_empty())
+        .collect();
+
+    if messages.is_empty() {
+        return Err("No lines matched the speaker names in the role mapping".to_string());
+    }
+
+    Ok(ParsedTranscript { title, messages })
+}
+
+/// "Name: text", "**Name:** text" or "**Name**: text" where Name is a mapped speaker
+fn speaker_line<'a>(line: &'a str, speakers: &HashMap<String, &'static str>) -> Option<(&'static str, &'a str)> {
+    let (name, rest) = line.split_once(':')?;
+    let name = name.trim().trim_matches(['*', '_']).trim();
+    if name.is_empty() || name.len() > 60 {
+        return None;
+    }
+    let role = *speakers.get(&name.to_lowercase())?;
+    let rest = rest.trim_start_matches(['*', '_']).trim();
+    Some((role, rest))
+}
+
+// ============ Chat Exports ============
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportSource {
+    ChatGpt,
+    Claude,
+}
+
+impl ExportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportSource::ChatGpt => "chatgpt",
+            ExportSource::Claude => "claude",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedMessage {
+    pub from_user: bool,
+    pub content: String,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedConversation {
+    pub external_id: String,
+    pub title: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub messages: Vec<ExportedMessage>,
+}
+
+/// Parse a ChatGPT or Claude `conversations.json`; the format is detected from the first conversation.
+/// Conversations with no user or assistant text are dropped.
+pub fn parse_chat_export(text: &str) -> Result<(ExportSource, Vec<ExportedConversation>), String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("Not a JSON export: {}", e))?;
+    let items = match value {
+        Value::Array(items) => items,
+        single @ Value::Object(_) => vec![single],
+        _ => return Err("Expected a list of conversations".to_string()),
+    };
+
+    let source = match items.first() {
+        Some(first) if first.get("mapping").is_some() => ExportSource::ChatGpt,
+        Some(first) if first.get("chat_messages").is_some() => ExportSource::Claude,
+        Some(_) => return Err("Unrecognized export (expected ChatGPT or Claude conversations.json)".to_string()),
+        None => return Err("The export contains no conversations".to_string()),
+    };
+
+    let conversations = items.iter()
+        .filter_map(|item| match source {
+            ExportSource::ChatGpt => chatgpt_conversation(item),
+            ExportSource::Claude => claude_conversation(item),
+        })
+        .filter(|c| !c.messages.is_empty())
+        .collect();
+    Ok((source, conversations))
+}
+
+/// ChatGPT stores each conversation as a tree (edits and regenerations branch); follow the branch
+/// that ends at `current_node`, which is the one the user last saw
+fn chatgpt_conversation(item: &Value) -> Option<ExportedConversation> {
+    let external_id = item.get("conversation_id").or_else(|| item.get("id"))?.as_str()?.to_string();
+    let mapping = item.get("mapping")?.as_object()?;
+
+    let mut path = Vec::new();
+    let mut node_id = item.get("current_node").and_then(|n| n.as_str());
+    while let Some(node) = node_id.and_then(|id| mapping.get(id)) {
+        path.push(node);
+        node_id = node.get("parent").and_then(|p| p.as_str());
+        if path.len() > mapping.len() {
+            break; // Malformed export with a cycle
+        }
+    }
+    path.reverse();
+
+    let messages = path.iter()
+        .filter_map(|node| {
+            let message = node.get("message")?;
+            let from_user = match message.pointer("/author/role")?.as_str()? {
+                "user" => true,
+                "assistant" => false,
+                _ => return None, // system prompts and tool calls
+            };
+            if message.pointer("/metadata/is_visually_hidden_from_conversation").and_then(|h| h.as_bool()) == Some(true) {
+                return None;
+            }
+            let content = message.pointer("/content/parts")?
+                .as_array()?
+                .iter()
+                .filter_map(|part| part.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let content = content.trim();
+            if content.is_empty() {
+                return None;
+            }
+            Some(ExportedMessage {
+                from_user,
+                content: content.to_string(),
+                timestamp: message.get("create_time").and_then(unix_time),
+            })
+        })
+        .collect();
+
+    Some(ExportedConversation {
+        external_id,
+        title: non_empty(item.get("title")),
+        created_at: item.get("create_time").and_then(unix_time),
+        updated_at: item.get("update_time").and_then(unix_time),
+        messages,
+    })
+}
+
+fn claude_conversation(item: &Value) -> Option<ExportedConversation> {
+    let external_id = item.get("uuid")?.as_str()?.to_string();
+
+    let messages = item.get("chat_messages")?
+        .as_array()?
+        .iter()
+        .filter_map(|message| {
+            let from_user = match message.get("sender")?.as_str()? {
+                "human" => true,
+                "assistant" => false,
+                _ => return None,
+            };
+            // Newer exports leave `text` empty and put the reply in typed content blocks
+            let content = match non_empty(message.get("text")) {
+                Some(text) => text,
+                None => message.get("content")?
+                    .as_array()?
+                    .iter()
+                    .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+                    .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .trim()
+                    .to_string(),
+            };
+            if content.is_empty() {
+                return None;
+            }
+            Some(ExportedMessage {
+                from_user,
+                content,
+                timestamp: message.get("created_at").and_then(iso_time),
+            })
+        })
+        .collect();
+
+    Some(ExportedConversation {
+        external_id,
+        title: non_empty(item.get("name")),
+        created_at: item.get("created_at").and_then(iso_time),
+        updated_at: item.get("updated_at").and_then(iso_time),
+        messages,
+    })
+}
+
+fn non_empty(value: Option<&Value>) -> Option<String> {
+    value?.as_str().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+/// ChatGPT timestamps are fractional Unix seconds
+fn unix_time(value: &Value) -> Option<String> {
+    let secs = value.as_f64()?;
+    DateTime::<Utc>::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32).map(|t| t.to_rfc3339())
+}
+
+/// Claude timestamps are ISO 8601; normalize them to the RFC 3339 form the database uses
+fn iso_time(value: &Value) -> Option<String> {
+    DateTime::parse_from_rfc3339(value.as_str()?).ok().map(|t| t.with_timezone(&Utc).to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, role)| (name.to_string(), role.to_string())).collect()
+    }
+
+    #[test]
+    fn reads_plain_and_bolded_speaker_lines() {
+        let text = "\
+# Talking through the move
+
+Some notes before it started.
+Sam: Should I take the job in Lisbon?
+**Dot:** Compare the salary against the cost of living first.
+Here's a second paragraph.
+
+Note: rent is about 40% higher.
+**puff**: How do you feel about leaving?
+";
+        let parsed = parse_transcript(text, &mapping(&[("sam", "user"), ("Dot", "Logic"), (" Puff ", "psyche")])).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Talking through the move"));
+        assert_eq!(parsed.messages, vec![
+            ("user".to_string(), "Should I take the job in Lisbon?".to_string()),
+            ("logic".to_string(), "Compare the salary against the cost of living first.\nHere's a second paragraph.\n\nNote: rent is about 40% higher.".to_string()),
+            ("psyche".to_string(), "How do you feel about leaving?".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn headings_after_the_first_message_stay_in_it() {
+        let parsed = parse_transcript("Sam: First\n# Not a title\nSam: Second", &mapping(&[("Sam", "user")])).unwrap();
+        assert_eq!(parsed.title, None);
+        assert_eq!(parsed.messages[0].1, "First\n# Not a title");
+        assert_eq!(parsed.messages.len(), 2);
+    }
+
+    #[test]
+    fn empty_messages_are_dropped() {
+        let parsed = parse_transcript("Sam:\nDot:   \nSam: Still here", &mapping(&[("Sam", "user"), ("Dot", "logic")])).unwrap();
+        assert_eq!(parsed.messages, vec![("user".to_string(), "Still here".to_string())]);
+    }
+
+    #[test]
+    fn rejects_unknown_roles_and_unmatched_transcripts() {
+        let err = parse_transcript("Sam: hi", &mapping(&[("Sam", "governor")])).unwrap_err();
+        assert!(err.contains("Unknown role \"governor\""));
+
+        let no_match = "Alex: hi\nJordan: hello";
+        assert!(parse_transcript(no_match, &mapping(&[("Sam", "user")])).is_err());
+        assert!(parse_transcript("", &mapping(&[("Sam", "user")])).is_err());
+        assert!(parse_transcript("Sam: hi", &HashMap::new()).is_err());
+        // Only-empty messages count as no messages
+        assert!(parse_transcript("Sam:   \n", &mapping(&[("Sam", "user")])).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Daily journaling for Intersect
+//!
+//! A journal is a conversation flagged `is_journal` with a single agent (the one configured here).
+//! Each evening, at `prompt_time`, the scheduler starts the day's journal: the agent opens with a
+//! prompt and a notification goes out. The user's replies are the entries; extraction on journal
+//! conversations uses a journaling-specific prompt (see `memory`), and `entries` groups them for
+//! the timeline view.
+//!
+//! Settings are stored in app_settings as `journaling`.
+
+use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
+use crate::clock;
+use crate::db::{self, Message};
+use crate::logging;
+use crate::orchestrator::Agent;
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "journaling";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct JournalSettings {
+    pub enabled: bool,
+    pub agent: String,                   // The agent that prompts and answers
+    pub prompt_time: String,             // Local "HH:MM" for the evening prompt
+    pub last_prompted_on: Option<String>, // Local date of the last evening prompt
+}
+
+impl Default for JournalSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            agent: Agent::Psyche.as_str().to_string(),
+            prompt_time: "20:30".to_string(),
+            last_prompted_on: None,
+        }
+    }
+}
+
+impl JournalSettings {
+    fn prompt_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(&self.prompt_time, "%H:%M").ok()
+    }
+}
+
+/// One day's journal for the timeline
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry {
+    pub conversation_id: String,
+    pub date: String,           // Local YYYY-MM-DD
+    pub agent: Option<String>,
+    pub prompt: Option<String>, // The agent's opening question
+    pub entries: Vec<Message>,  // The user's messages
+    pub word_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalStart {
+    pub conversation_id: String,
+    pub agent: String,
+    pub prompt: String,
+}
+
+pub fn get_settings() -> JournalSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &JournalSettings) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// Change the journal agent, prompt time or on/off (the last prompt date is kept)
+pub fn set_settings(settings: &JournalSettings) -> Result<(), String> {
+    if Agent::from_str(&settings.agent).is_none() {
+        return Err(format!("Unknown agent: {}", settings.agent));
+    }
+    if settings.prompt_time().is_none() {
+        return Err("Prompt time must be HH:MM".to_string());
+    }
+    save_settings(&JournalSettings {
+        last_prompted_on: get_settings().last_prompted_on,
+        ..settings.clone()
+    })
+}
+
+fn fallback_prompt(agent: Agent) -> &'static str {
+    match agent {
+        Agent::Instinct => "Day's done. What's the one thing from today that's still buzzing?",
+        Agent::Logic => "Let's close out the day. What happened, and what would you do differently?",
+        Agent::Psyche => "How are you feeling as the day winds down? Start anywhere.",
+    }
+}
+
+/// A short opening question in the agent's voice (falls back to a fixed one if the call fails)
+async fn generate_prompt(anthropic_key: Option<&str>, agent: Agent) -> String {
+    let Some(key) = anthropic_key else {
+        return fallback_prompt(agent).to_string();
+    };
+    let voice = match agent {
+        Agent::Instinct => "Snap: gut-level, direct, energetic, short sentences",
+        Agent::Logic => "Dot: analytical, calm, structured, curious about cause and effect",
+        Agent::Psyche => "Puff: warm, emotionally attuned, gently introspective",
+    };
+    let recent_themes: Vec<String> = db::get_all_recurring_themes().unwrap_or_default()
+        .into_iter()
+        .take(3)
+        .map(|t| t.theme)
+        .collect();
+    let system_prompt = format!(
+        "You open the user's evening journal in Intersect, speaking as {}.\n\
+Write ONE inviting journaling question for tonight, 1-2 sentences, no preamble or quotes. \
+It's {}. Keep it open-ended; you may gently nod to one of their recurring themes if it fits ({}). \
+When using dashes: ALWAYS \" -- \" (double dashes with spaces).",
+        voice,
+        clock::now_local().format("%A"),
+        if recent_themes.is_empty() { "none yet".to_string() } else { recent_themes.join(", ") }
+    );
+    let client = AnthropicClient::new(key);
+    match client.chat_completion_advanced(
+        CLAUDE_HAIKU,
+        Some(&system_prompt),
+        vec![AnthropicMessage { role: "user".to_string(), content: "Write tonight's journaling question.".to_string() }],
+        0.8,
+        Some(120),
+        ThinkingBudget::None,
+    ).await {
+        Ok(text) if !text.trim().is_empty() => text.trim().to_string(),
+        Ok(_) => fallback_prompt(agent).to_string(),
+        Err(e) => {
+            logging::log_error(None, &format!("Journal prompt generation failed: {}", e));
+            fallback_prompt(agent).to_string()
+        }
+    }
+}
+
+/// Start a journal conversation with the configured agent's opening prompt
+pub async fn start_entry() -> Result<JournalStart, String> {
+    let settings = get_settings();
+    let agent = Agent::from_str(&settings.agent).unwrap_or(Agent::Psyche);
+    let anthropic_key = db::get_user_profile().ok().and_then(|p| p.anthropic_key);
+
+    let conversation = crate::create_conversation(false)?;
+    db::set_conversation_journal(&conversation.id).map_err(|e| e.to_string())?;
+    db::set_conversation_agents(&conversation.id, Some(&[agent.as_str().to_string()])).map_err(|e| e.to_string())?;
+    let title = format!("Journal -- {}", clock::now_local().format("%b %-d"));
+    db::set_conversation_title(&conversation.id, &title).map_err(|e| e.to_string())?;
+
+    let prompt = generate_prompt(anthropic_key.as_deref(), agent).await;
+    db::save_message(&Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation.id.clone(),
+        role: agent.as_str().to_string(),
+        content: prompt.clone(),
+        response_type: Some("primary".to_string()),
+        references_message_id: None,
+        timestamp: Utc::now().to_rfc3339(),
+        attachments: Vec::new(),
+        starred: false,
+    }).map_err(|e| e.to_string())?;
+    logging::log_conversation(Some(&conversation.id), &format!("Journal started with {}", agent.as_str()));
+
+    Ok(JournalStart { conversation_id: conversation.id, agent: agent.as_str().to_string(), prompt })
+}
+
+/// Whether the evening prompt is due: on, past the prompt time, and not yet sent today
+fn prompt_due(settings: &JournalSettings, now: DateTime<Local>) -> bool {
+    let today = now.date_naive().format("%Y-%m-%d").to_string();
+    settings.enabled
+        && settings.prompt_time().is_some_and(|at| now.time() >= at)
+        && settings.last_prompted_on.as_deref() != Some(today.as_str())
+}
+
+/// Start tonight's journal if it's time (called from the scheduler tick)
+pub async fn maybe_prompt_evening() -> Option<JournalStart> {
+    let mut settings = get_settings();
+    let now = clock::now_local();
+    if !prompt_due(&settings, now) {
+        return None;
+    }
+    // Recorded first so a slow or failed start doesn't prompt twice
+    settings.last_prompted_on = Some(now.date_naive().format("%Y-%m-%d").to_string());
+    if let Err(e) = save_settings(&settings) {
+        logging::log_error(None, &format!("Couldn't record the journal prompt: {}", e));
+        return None;
+    }
+    match start_entry().await {
+        Ok(start) => Some(start),
+        Err(e) => {
+            logging::log_error(None, &format!("Evening journal failed to start: {}", e));
+            None
+        }
+    }
+}
+
+/// Journal days in a range, oldest first
+pub fn entries(range: &str) -> Result<Vec<JournalEntry>, String> {
+    let (since, until) = clock::parse_range(range, clock::now_local().date_naive())?;
+    let messages = db::get_journal_messages(
+        since.map(|t| t.to_rfc3339()).as_deref(),
+        until.map(|t| t.to_rfc3339()).as_deref(),
+    ).map_err(|e| e.to_string())?;
+
+    let mut days: Vec<JournalEntry> = Vec::new();
+    for message in messages {
+        if days.last().is_none_or(|day| day.conversation_id != message.conversation_id) {
+            let date = DateTime::parse_from_rfc3339(&message.timestamp)
+                .map(|t| t.with_timezone(&Local).format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            days.push(JournalEntry {
+                conversation_id: message.conversation_id.clone(),
+                date,
+                agent: None,
+                prompt: None,
+                entries: Vec::new(),
+                word_count: 0,
+            });
+        }
+        let Some(day) = days.last_mut() else { continue };
+        if message.role == "user" {
+            day.word_count += message.content.split_whitespace().count();
+            day.entries.push(message);
+        } else if day.prompt.is_none() && day.entries.is_empty() {
+            day.agent = Some(message.role.clone());
+            day.prompt = Some(message.content);
+        }
+    }
+    Ok(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike};
+
+    #[test]
+    fn evening_prompt_fires_once_after_prompt_time() {
+        let evening = Local.with_ymd_and_hms(2026, 3, 10, 21, 0, 0).unwrap();
+        let mut settings = JournalSettings { enabled: true, ..JournalSettings::default() };
+        assert!(prompt_due(&settings, evening));
+        assert!(!prompt_due(&settings, evening.with_hour(19).unwrap()));
+        settings.last_prompted_on = Some("2026-03-10".to_string());
+        assert!(!prompt_due(&settings, evening));
+        settings.enabled = false;
+        settings.last_prompted_on = None;
+        assert!(!prompt_due(&settings, evening));
+    }
+}
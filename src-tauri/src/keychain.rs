@@ -0,0 +1,106 @@
+//! Secrets kept in the macOS login Keychain (service "Intersect"), unlocked with the user's login /
+//! Touch ID rather than stored next to the database or in its backups
+//!
+//! - `database-key`: the passphrase for encryption at rest (`encryption` feature). A random 256-bit
+//!   passphrase is generated the first time and never shown to the user; elsewhere (or for scripted
+//!   installs) INTERSECT_DB_PASSPHRASE supplies it instead
+//! - `sync-key`: the key cloud sync derived from the user's passphrase (see sync.rs)
+//! - `sync-credentials`: the sync backend's S3 secret key or WebDAV password, hex-encoded
+
+#[cfg(feature = "encryption")]
+use rand::RngCore;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[cfg(feature = "encryption")]
+pub const PASSPHRASE_ENV: &str = "INTERSECT_DB_PASSPHRASE";
+const KEYCHAIN_SERVICE: &str = "Intersect";
+#[cfg(feature = "encryption")]
+const DB_KEY_ACCOUNT: &str = "database-key";
+pub const SYNC_KEY_ACCOUNT: &str = "sync-key";
+pub const SYNC_CREDENTIALS_ACCOUNT: &str = "sync-credentials";
+
+/// The database passphrase, creating and storing one on first use
+#[cfg(feature = "encryption")]
+pub fn get_or_create_db_key() -> Result<String, String> {
+    if let Some(key) = std::env::var(PASSPHRASE_ENV).ok().filter(|k| !k.trim().is_empty()) {
+        return Ok(key);
+    }
+    if !cfg!(target_os = "macos") {
+        return Err(format!("Database encryption needs {} on this platform", PASSPHRASE_ENV));
+    }
+
+    if let Some(key) = read_secret(DB_KEY_ACCOUNT)? {
+        return Ok(key);
+    }
+
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    let key: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    write_secret(DB_KEY_ACCOUNT, &key)?;
+    Ok(key)
+}
+
+fn ensure_available() -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        Ok(())
+    } else {
+        Err("The Keychain is only available on macOS".to_string())
+    }
+}
+
+/// The secret stored for an account, if any
+pub fn read_secret(account: &str) -> Result<Option<String>, String> {
+    ensure_available()?;
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a", account, "-w"])
+        .output()
+        .map_err(|e| format!("Keychain unavailable: {}", e))?;
+    // Exit code 44 = item not found
+    if output.status.code() == Some(44) {
+        return Ok(None);
+    }
+    if !output.status.success() {
+        return Err(format!("Keychain read failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let secret = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(Some(secret).filter(|s| !s.is_empty()))
+}
+
+/// Store a secret (hex or other shell-safe text), replacing any existing one.
+/// `security -i` reads the command from stdin, so the secret never shows up in the process list.
+/// Interactive mode doesn't report failures in its exit code, so the write is checked by reading back.
+pub fn write_secret(account: &str, secret: &str) -> Result<(), String> {
+    ensure_available()?;
+    let mut child = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Keychain unavailable: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "add-generic-password -s {} -a {} -w {} -U", KEYCHAIN_SERVICE, account, secret)
+            .map_err(|e| format!("Keychain write failed: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("Keychain write failed: {}", e))?;
+    if read_secret(account)?.as_deref() != Some(secret) {
+        return Err(format!("Keychain write failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Remove an account's secret (no-op if there is none, or off macOS where nothing could be stored)
+pub fn delete_secret(account: &str) -> Result<(), String> {
+    if ensure_available().is_err() {
+        return Ok(());
+    }
+    let output = Command::new("security")
+        .args(["delete-generic-password", "-s", KEYCHAIN_SERVICE, "-a", account])
+        .output()
+        .map_err(|e| format!("Keychain unavailable: {}", e))?;
+    if !output.status.success() && output.status.code() != Some(44) {
+        return Err(format!("Keychain delete failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
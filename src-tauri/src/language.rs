@@ -0,0 +1,172 @@
+//! Conversation language for Intersect
+//!
+//! The agents, the summarizer and the memory extractor follow the user's language. It's either
+//! fixed in settings or, with "auto", detected per message from common function words (the
+//! user's recent messages break ties when a message is too short to tell). English needs no
+//! instruction, so prompts only change for the other supported languages.
+//!
+//! Settings are stored in app_settings as `language`.
+
+use crate::db;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "language";
+/// Function-word hits a message needs before it's attributed to a language
+const MIN_HITS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    pub code: &'static str,
+    pub name: &'static str,
+    stopwords: &'static [&'static str],
+}
+
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        code: "en",
+        name: "English",
+        stopwords: &["the", "and", "is", "are", "was", "i", "you", "it", "to", "of", "that", "what", "this", "with", "my", "have", "but", "not", "for", "just", "about", "don't", "i'm"],
+    },
+    Language {
+        code: "es",
+        name: "Spanish",
+        stopwords: &["el", "la", "los", "las", "que", "de", "y", "es", "en", "un", "una", "por", "para", "pero", "con", "mi", "yo", "no", "muy", "está", "estoy", "qué", "como", "cómo", "porque", "también", "tengo", "eso", "hoy"],
+    },
+    Language {
+        code: "fr",
+        name: "French",
+        stopwords: &["le", "la", "les", "et", "est", "je", "tu", "vous", "que", "qui", "des", "une", "un", "pas", "pour", "avec", "mais", "dans", "ce", "c'est", "j'ai", "mon", "très", "suis", "aujourd'hui"],
+    },
+    Language {
+        code: "de",
+        name: "German",
+        stopwords: &["der", "die", "das", "und", "ist", "ich", "nicht", "ein", "eine", "zu", "mit", "aber", "auch", "wie", "was", "mein", "bin", "habe", "sehr", "heute", "dass", "für"],
+    },
+    Language {
+        code: "pt",
+        name: "Portuguese",
+        stopwords: &["o", "os", "as", "e", "é", "que", "não", "um", "uma", "com", "para", "mas", "meu", "eu", "muito", "estou", "você", "isso", "hoje", "também", "tenho", "porque"],
+    },
+    Language {
+        code: "it",
+        name: "Italian",
+        stopwords: &["il", "lo", "gli", "e", "è", "che", "non", "un", "una", "per", "con", "ma", "mio", "io", "sono", "molto", "oggi", "anche", "perché", "ho", "questo", "della"],
+    },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LanguageSettings {
+    pub language: String, // "auto" or a code from LANGUAGES
+}
+
+impl Default for LanguageSettings {
+    fn default() -> Self {
+        Self { language: "auto".to_string() }
+    }
+}
+
+pub fn get_settings() -> LanguageSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_settings(settings: &LanguageSettings) -> Result<(), String> {
+    let language = settings.language.trim().to_lowercase();
+    if language != "auto" && by_code(&language).is_none() {
+        return Err(format!("Unsupported language: {}", settings.language));
+    }
+    let json = serde_json::to_string(&LanguageSettings { language }).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+pub fn by_code(code: &str) -> Option<Language> {
+    LANGUAGES.iter().find(|l| l.code == code).copied()
+}
+
+/// The language a text is most likely written in, if it has enough function words to tell
+pub fn detect(text: &str) -> Option<Language> {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphabetic() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    let mut scores: Vec<(usize, Language)> = LANGUAGES.iter()
+        .map(|lang| (words.iter().filter(|w| lang.stopwords.contains(&w.as_str())).count(), *lang))
+        .collect();
+    scores.sort_by_key(|(hits, _)| std::cmp::Reverse(*hits));
+    match scores.as_slice() {
+        [(best, lang), (second, _), ..] if *best >= MIN_HITS && best > second => Some(*lang),
+        _ => None,
+    }
+}
+
+/// The language to use given the setting and samples of the user's writing, newest first.
+/// Each sample is tried alone, then all of them together.
+fn resolve(settings: &LanguageSettings, samples: &[&str]) -> Option<Language> {
+    if settings.language != "auto" {
+        return by_code(&settings.language);
+    }
+    samples.iter()
+        .find_map(|sample| detect(sample))
+        .or_else(|| detect(&samples.join("\n")))
+}
+
+/// The language the user is writing in, or None for English or when it can't be told
+fn current(samples: &[&str]) -> Option<Language> {
+    resolve(&get_settings(), samples).filter(|lang| lang.code != "en")
+}
+
+/// Prompt line asking an agent to answer in the user's language
+pub fn reply_instruction(samples: &[&str]) -> Option<String> {
+    current(samples).map(|lang| format!(
+        "LANGUAGE: The user is writing in {0}. Reply in {0}, keeping your voice and every rule above.",
+        lang.name
+    ))
+}
+
+/// Prompt line for the summarizer; tone and state stay in English since mood tracking scores them
+pub fn summary_instruction(samples: &[&str]) -> Option<String> {
+    current(samples).map(|lang| format!(
+        "LANGUAGE: The conversation is in {0}. Write the summary and key topics in {0}. \
+Keep emotional_tone and user_state as single English words like the examples above.",
+        lang.name
+    ))
+}
+
+/// Prompt line for the extractor; keys and categories stay in English so facts keep matching
+pub fn extraction_instruction(samples: &[&str]) -> Option<String> {
+    current(samples).map(|lang| format!(
+        "LANGUAGE: The user writes in {0}. Write fact values, pattern descriptions and evidence, themes and \
+habit names in {0}. Keep JSON keys, categories, pattern types and fact keys in English so they match existing entries.",
+        lang.name
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_common_languages() {
+        let code = |text: &str| detect(text).map(|l| l.code);
+        assert_eq!(code("Hoy estoy muy cansado pero tengo que terminar el proyecto"), Some("es"));
+        assert_eq!(code("I just don't know what to do about the job offer"), Some("en"));
+        assert_eq!(code("Je suis très fatigué aujourd'hui, mais c'est normal"), Some("fr"));
+        assert_eq!(code("Ich bin heute sehr müde und habe keine Lust"), Some("de"));
+        assert_eq!(code("ok"), None);
+    }
+
+    #[test]
+    fn short_messages_fall_back_to_recent_ones() {
+        let auto = LanguageSettings::default();
+        let recent = ["vale", "No sé qué hacer con mi trabajo, estoy muy perdido"];
+        assert_eq!(resolve(&auto, &recent).map(|l| l.code), Some("es"));
+
+        let fixed = LanguageSettings { language: "fr".to_string() };
+        assert_eq!(resolve(&fixed, &recent).map(|l| l.code), Some("fr"));
+    }
+}
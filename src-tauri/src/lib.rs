@@ -1,22 +1,60 @@
+mod analytics;
 mod anthropic;
+mod api;
+mod attachments;
+mod backup;
+mod calendar;
+mod capture;
+mod clock;
+mod context;
 mod db;
+mod debate;
+mod diagnostics;
 mod disco_prompts;
+mod documents;
+mod export;
+mod gemini;
+mod importer;
+mod journal;
+mod keychain;
 mod knowledge;
+mod language;
+mod links;
 mod logging;
+mod mcp;
 mod memory;
+mod mood;
+mod offline;
+mod ollama;
 mod openai;
 mod orchestrator;
+mod personality;
+mod providers;
+mod redact;
+mod retry;
+mod sampling;
+mod scheduler;
+mod search;
+mod semantic;
+mod shortcuts;
+mod sync;
+mod tokenizer;
+mod tools;
+mod usage;
+mod voice;
+mod webhooks;
 
 use db::{Message, UserProfile, UserContext};
 use memory::{MemoryExtractor, ConversationSummarizer, UserProfileSummary};
-use orchestrator::{Orchestrator, Agent, ResponseType, AgentResponse, EngagementAnalyzer, IntrinsicTraitAnalyzer, combine_trait_analyses, decide_response_heuristic, decide_grounding_heuristic};
+use orchestrator::{Orchestrator, Agent, ResponseType, AgentResponse, RoutingTrace, EngagementAnalyzer, IntrinsicTraitAnalyzer, combine_trait_analyses, evolve_weights, InteractionType, decide_response_heuristic, force_primary, decide_grounding_heuristic, decide_search_heuristic, is_schedule_or_stress_topic, classify_response_style, format_style_note, compute_agent_silence, AgentSilence, CancelToken, TURN_CANCELLED};
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
 use once_cell::sync::Lazy;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 // ============ Session Weight Storage ============
 // Session weights track short-term boosts that decay over conversation
@@ -58,12 +96,33 @@ fn clear_session_weights(conversation_id: &str) {
     weights.remove(conversation_id);
 }
 
+// In-flight turns by conversation, so cancel_turn can reach them
+static ACTIVE_TURNS: Lazy<Mutex<HashMap<String, CancelToken>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Conversations with a turn in flight right now
+pub(crate) fn busy_conversations() -> HashSet<String> {
+    ACTIVE_TURNS.lock().unwrap().keys().cloned().collect()
+}
+
+// Conversations already checked for a better-fitting persona (one suggestion per conversation)
+static PERSONA_SUGGESTION_CHECKED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SendMessageResult {
     pub responses: Vec<AgentResponse>,
     pub debate_mode: Option<String>, // "mild" | "intense" | null
     pub weight_change: Option<WeightChangeNotification>,
     pub governor_response: Option<String>, // Governor's synthesized response after reading agent thoughts
+    #[serde(default)]
+    pub queued: Option<db::PendingMessage>, // Set when the app was offline and the message was queued instead
+    #[serde(default)]
+    pub routing: Option<RoutingTrace>, // Why the primary agent was picked (also stored on its message)
+}
+
+/// Routing internals the UI can show to explain who gets picked next
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoutingExplanation {
+    pub silence: Vec<AgentSilence>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -82,6 +141,9 @@ pub struct ConversationInfo {
     pub is_disco: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub model_override: Option<String>,
+    pub parent_conversation_id: Option<String>,
+    pub branch_point_message_id: Option<String>,
 }
 
 // ============ App Initialization ============
@@ -94,9 +156,15 @@ pub struct InitResult {
 
 #[tauri::command]
 fn init_app(app_handle: tauri::AppHandle) -> Result<InitResult, String> {
+    // Deterministic clock/RNG for fixture runs (no-op unless the fixture env vars are set)
+    let fixture_mode = clock::init_from_env();
+    
     // Initialize database
     db::init_database(&app_handle).map_err(|e| e.to_string())?;
     
+    // Names from the user's facts are masked in logs from the first line on
+    redact::refresh_names();
+    
     // Initialize logging
     if let Err(e) = logging::init_logging() {
         eprintln!("Failed to initialize logging: {}", e);
@@ -105,6 +173,59 @@ fn init_app(app_handle: tauri::AppHandle) -> Result<InitResult, String> {
     // Clean up old log files (keep last 7 days)
     let _ = logging::cleanup_old_logs();
     
+    // Raw trait analytics age out; prompt-facing memory is kept
+    if let Ok(pruned) = db::prune_analytics() {
+        if pruned > 0 {
+            logging::log_memory(None, &format!("Pruned {} expired analytics rows", pruned));
+        }
+    }
+    
+    // Facts and patterns that haven't been confirmed in a while carry less weight in grounding
+    match db::decay_confidences(clock::now()) {
+        Ok(decayed) if decayed.facts + decayed.patterns > 0 => {
+            logging::log_memory(None, &format!(
+                "Confidence decay: {} facts, {} patterns", decayed.facts, decayed.patterns
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => logging::log_error(None, &format!("Confidence decay failed: {}", e)),
+    }
+    
+    if fixture_mode {
+        logging::log_conversation(None, &format!("Fixture mode active (clock: {})", clock::now().to_rfc3339()));
+    }
+    
+    // Make env-var key overrides visible in the log so the active source is obvious
+    if let Ok(sources) = db::get_key_sources() {
+        if sources.openai == "env" || sources.anthropic == "env" || sources.gemini == "env" {
+            logging::log_conversation(None, &format!(
+                "API key override active (openai: {}, anthropic: {}, gemini: {})",
+                sources.openai, sources.anthropic, sources.gemini
+            ));
+        }
+    }
+    
+    // Start the proactive scheduler (check-ins)
+    scheduler::start(app_handle.clone());
+    
+    // Start rotating local backups of the database
+    backup::start(&app_handle);
+    
+    // Merge with other devices through the user's sync backend, if set up
+    sync::start(&app_handle);
+    
+    // Watch connectivity and send messages queued while offline once it's back
+    offline::start(app_handle.clone(), spawn_pending_flush);
+    
+    // Serve the local automation API if the user turned it on
+    api::start(app_handle.clone());
+    
+    // Run intersect:// links that opened the app before it was ready
+    shortcuts::start(app_handle.clone());
+    
+    // Global hotkey for the quick capture window
+    capture::start(&app_handle);
+    
     // Check for orphaned conversations from crash/force-quit
     let unprocessed = db::get_conversations_needing_recovery().unwrap_or_default();
     
@@ -130,19 +251,41 @@ fn init_app(app_handle: tauri::AppHandle) -> Result<InitResult, String> {
 
 /// Recover and finalize all unprocessed conversations from crashes/force-quits
 #[tauri::command]
-async fn recover_conversations() -> Result<usize, String> {
-    let unprocessed = db::get_conversations_needing_recovery()
+async fn recover_conversations(app_handle: tauri::AppHandle) -> Result<usize, String> {
+    let unprocessed = db::run_blocking(db::get_conversations_needing_recovery)
+        .await?
         .map_err(|e| e.to_string())?;
     
     let count = unprocessed.len();
+    // Over the Anthropic budget finalization is deferred; they stay queued for a later launch
+    if count > 0 && usage::skip_optional_calls("anthropic") {
+        logging::log_conversation(None, &format!("Recovery of {} conversations deferred (Anthropic budget exceeded)", count));
+        return Ok(0);
+    }
     logging::log_conversation(None, &format!("Starting recovery of {} conversations", count));
     
     for conv in unprocessed {
         logging::log_conversation(Some(&conv.id), "Recovering conversation");
         
         // Use the existing finalize_conversation logic
-        if let Err(e) = finalize_conversation_internal(&conv.id).await {
-            logging::log_error(Some(&conv.id), &format!("Recovery failed: {}", e));
+        match finalize_conversation_internal(&conv.id).await {
+            Ok(()) => record_system_notice(
+                &app_handle,
+                &conv.id,
+                "recovery",
+                "This conversation was interrupted -- I've recovered it and folded it into memory.",
+                serde_json::json!({ "recovered_at": Utc::now().to_rfc3339(), "status": "recovered" }),
+            ),
+            Err(e) => {
+                logging::log_error(Some(&conv.id), &format!("Recovery failed: {}", e));
+                record_system_notice(
+                    &app_handle,
+                    &conv.id,
+                    "recovery",
+                    "This conversation was interrupted and I couldn't fully recover it.",
+                    serde_json::json!({ "recovered_at": Utc::now().to_rfc3339(), "status": "failed", "error": e }),
+                );
+            }
         }
     }
     
@@ -156,31 +299,43 @@ async fn finalize_conversation_internal(conversation_id: &str) -> Result<(), Str
     // Clear session weights when conversation ends
     clear_session_weights(conversation_id);
     
-    let profile = db::get_user_profile().map_err(|e| e.to_string())?;
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let mark_processed = |summary: Option<String>| {
+        let id = conversation_id.to_string();
+        db::run_blocking(move || db::mark_conversation_processed(&id, summary.as_deref()))
+    };
     let anthropic_key = match profile.anthropic_key {
         Some(key) => key,
         None => {
             // No API key - just mark as processed without extraction
-            db::mark_conversation_processed(conversation_id, None)
-                .map_err(|e| e.to_string())?;
+            mark_processed(None).await?.map_err(|e| e.to_string())?;
             return Ok(());
         }
     };
     
-    let conversation = db::get_conversation(conversation_id)
-        .map_err(|e| e.to_string())?
-        .ok_or("Conversation not found")?;
+    let id = conversation_id.to_string();
+    let (conversation, messages) = db::run_blocking(move || -> rusqlite::Result<_> {
+        let Some(conversation) = db::get_conversation(&id)? else {
+            return Ok((None, Vec::new()));
+        };
+        let messages = if conversation.processed { Vec::new() } else { db::get_conversation_messages(&id)? };
+        Ok((Some(conversation), messages))
+    }).await?.map_err(|e| e.to_string())?;
+    let conversation = conversation.ok_or("Conversation not found")?;
     
     if conversation.processed {
         return Ok(());
     }
     
-    let messages = db::get_conversation_messages(conversation_id)
-        .map_err(|e| e.to_string())?;
-    
     if messages.len() < 2 {
-        db::mark_conversation_processed(conversation_id, None)
-            .map_err(|e| e.to_string())?;
+        mark_processed(None).await?.map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+    
+    // Over the Anthropic budget with optional calls paused: skip the model calls but leave the
+    // conversation unprocessed, so recovery finalizes it once there's budget again
+    if usage::skip_optional_calls("anthropic") {
+        logging::log_memory(Some(conversation_id), "Finalization deferred (Anthropic budget exceeded)");
         return Ok(());
     }
     
@@ -199,46 +354,110 @@ async fn finalize_conversation_internal(conversation_id: &str) -> Result<(), Str
     
     let final_summary = match summarizer.summarize(&messages, None).await {
         Ok(result) => {
-            let _ = ConversationSummarizer::save_summary(
-                conversation_id,
-                &result,
-                messages.len() as i64,
-                &agents_involved,
-            );
+            let (id, saved, message_count) = (conversation_id.to_string(), result.clone(), messages.len() as i64);
+            let _ = db::run_blocking(move || {
+                ConversationSummarizer::save_summary(&id, &saved, message_count, &agents_involved)
+            }).await;
             logging::log_memory(Some(conversation_id), &format!(
                 "Generated summary: {} topics", result.key_topics.len()
             ));
+            
+            if auto_tagging_enabled() {
+                auto_tag_conversation(conversation_id, &result.key_topics);
+            }
+            
+            if reflection_prompts_enabled() {
+                match summarizer.generate_reflection(&result).await {
+                    Ok(Some(question)) => {
+                        let id = conversation_id.to_string();
+                        let _ = db::run_blocking(move || db::set_summary_reflection(&id, &question)).await;
+                        logging::log_memory(Some(conversation_id), "Generated reflection question");
+                    }
+                    Ok(None) => {}
+                    Err(e) => logging::log_error(Some(conversation_id), &format!("Reflection failed: {}", e)),
+                }
+            }
             Some(result.summary)
         }
         Err(e) => {
             logging::log_error(Some(conversation_id), &format!("Summary failed: {}", e));
-            conversation.limbo_summary.clone()
+            let id = conversation_id.to_string();
+            db::run_blocking(move || db::get_limbo_summary(&id).ok().flatten()).await.ok().flatten()
         }
     };
     
-    // Extract patterns
-    let extractor = MemoryExtractor::new(&anthropic_key);
-    let existing_facts = db::get_all_user_facts().unwrap_or_default();
+    // Record a decision if the user committed to one
+    match summarizer.detect_decision(&messages).await {
+        Ok(Some(decision)) => {
+            if let Some(text) = decision.decision {
+                let (id, recorded) = (conversation_id.to_string(), text.clone());
+                let _ = db::run_blocking(move || {
+                    db::save_decision(&id, &recorded, &decision.options, decision.followed_agent.as_deref())
+                }).await;
+                logging::log_memory(Some(conversation_id), &format!("Recorded decision: {}", text));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => logging::log_error(Some(conversation_id), &format!("Decision detection failed: {}", e)),
+    }
     
-    let full_conversation: String = messages.iter()
-        .map(|m| format!("{}: {}", m.role.to_uppercase(), m.content))
-        .collect::<Vec<_>>()
-        .join("\n\n");
+    // Extract patterns - only from messages past the watermark, so exchanges already
+    // handled by per-exchange extraction don't duplicate facts or inflate mention counts
+    let id = conversation_id.to_string();
+    let (last_seq, unextracted) = db::run_blocking(move || -> rusqlite::Result<_> {
+        let watermark = db::get_extraction_watermark(&id).ok().flatten().unwrap_or(0);
+        let last_seq = db::get_last_seq(&id).unwrap_or(0);
+        Ok((last_seq, db::get_conversation_messages_after(&id, watermark)?))
+    }).await?.map_err(|e| e.to_string())?;
+    let unextracted: Vec<Message> = unextracted
+        .into_iter()
+        .filter(|m| m.role != "system")
+        .collect();
     
-    if let Ok(result) = extractor.extract_from_exchange(
-        &full_conversation,
-        &[],
-        &existing_facts,
-        conversation_id,
-    ).await {
-        logging::log_memory(Some(conversation_id), &format!(
-            "Extracted {} facts, {} patterns",
-            result.new_facts.len(), result.new_patterns.len()
-        ));
+    if !memory_extraction_enabled() {
+        logging::log_memory(Some(conversation_id), "Extraction skipped (disabled in privacy settings)");
+    } else if unextracted.is_empty() {
+        logging::log_memory(Some(conversation_id), "Extraction skipped (already up to date)");
+    } else {
+        let extractor = MemoryExtractor::new(&anthropic_key);
+        let existing_facts = db::run_blocking(|| db::get_all_user_facts().unwrap_or_default()).await?;
+        
+        let remaining_conversation: Vec<(String, String)> = unextracted.iter()
+            .map(|m| (m.role.clone(), m.content.clone()))
+            .collect();
+        
+        if let Ok(result) = extractor.extract_from_transcript(
+            &remaining_conversation,
+            &existing_facts,
+            conversation_id,
+        ).await {
+            logging::log_memory(Some(conversation_id), &format!(
+                "Extracted {} facts, {} patterns from {} unextracted messages",
+                result.new_facts.len(), result.new_patterns.len(), unextracted.len()
+            ));
+            let id = conversation_id.to_string();
+            let _ = db::run_blocking(move || db::advance_extraction_watermark(&id, last_seq)).await;
+        }
     }
     
-    db::mark_conversation_processed(conversation_id, final_summary.as_deref())
-        .map_err(|e| e.to_string())?;
+    mark_processed(final_summary).await?.map_err(|e| e.to_string())?;
+    
+    // Keep the semantic index current with this conversation's messages, summary and facts
+    if let Some(openai_key) = profile.api_key.as_deref() {
+        if let Err(e) = semantic::index_pending(openai_key).await {
+            logging::log_error(Some(conversation_id), &format!("Semantic indexing failed: {}", e));
+        }
+    }
+    
+    // Themes were just refreshed - surface any that are spiking across recent conversations
+    match scheduler::check_theme_spikes(&anthropic_key).await {
+        Ok(0) => {}
+        Ok(n) => logging::log_memory(Some(conversation_id), &format!("Raised {} theme report(s)", n)),
+        Err(e) => logging::log_error(Some(conversation_id), &format!("Theme spike check failed: {}", e)),
+    }
+    
+    // Memory just grew - merge near-duplicates if it's been a while
+    maybe_consolidate_memory(&anthropic_key);
     
     logging::log_conversation(Some(conversation_id), "Finalization complete");
     
@@ -259,7 +478,22 @@ async fn validate_and_save_api_key(api_key: String) -> Result<bool, String> {
     match client.validate_api_key().await {
         Ok(valid) => {
             if valid {
-                db::update_api_key(&api_key).map_err(|e| e.to_string())?;
+                db::run_blocking(move || db::update_api_key(&api_key)).await?.map_err(|e| e.to_string())?;
+            }
+            Ok(valid)
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+async fn validate_and_save_anthropic_key(api_key: String) -> Result<bool, String> {
+    let client = anthropic::AnthropicClient::new(&api_key);
+    
+    match client.validate_api_key().await {
+        Ok(valid) => {
+            if valid {
+                db::run_blocking(move || db::update_anthropic_key(&api_key)).await?.map_err(|e| e.to_string())?;
             }
             Ok(valid)
         }
@@ -267,6 +501,61 @@ async fn validate_and_save_api_key(api_key: String) -> Result<bool, String> {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProviderKeyStatus {
+    pub configured: bool,
+    pub valid: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyHealth {
+    pub openai: ProviderKeyStatus,
+    pub anthropic: ProviderKeyStatus,
+    pub gemini: ProviderKeyStatus,
+}
+
+impl ProviderKeyStatus {
+    fn from_check(check: Option<Result<bool, Box<dyn std::error::Error + Send + Sync>>>) -> Self {
+        match check {
+            None => ProviderKeyStatus { configured: false, valid: false, error: None },
+            Some(Ok(valid)) => ProviderKeyStatus { configured: true, valid, error: None },
+            Some(Err(e)) => ProviderKeyStatus { configured: true, valid: false, error: Some(e.to_string()) },
+        }
+    }
+}
+
+/// Check every saved provider key against its API
+#[tauri::command]
+async fn validate_all_keys() -> Result<KeyHealth, String> {
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    
+    let openai_check = match &profile.api_key {
+        Some(key) => Some(openai::OpenAIClient::new(key).validate_api_key().await),
+        None => None,
+    };
+    let anthropic_check = match &profile.anthropic_key {
+        Some(key) => Some(anthropic::AnthropicClient::new(key).validate_api_key().await),
+        None => None,
+    };
+    let gemini_check = match &profile.gemini_key {
+        Some(key) => Some(gemini::GeminiClient::new(key).validate_api_key().await),
+        None => None,
+    };
+    
+    Ok(KeyHealth {
+        openai: ProviderKeyStatus::from_check(openai_check),
+        anthropic: ProviderKeyStatus::from_check(anthropic_check),
+        gemini: ProviderKeyStatus::from_check(gemini_check),
+    })
+}
+
+/// Report whether each key comes from an env override, the database, or nowhere
+#[tauri::command]
+fn get_key_sources() -> Result<db::KeySources, String> {
+    db::get_key_sources().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn save_api_key(api_key: String) -> Result<(), String> {
     db::update_api_key(&api_key).map_err(|e| e.to_string())
@@ -287,6 +576,31 @@ fn remove_anthropic_key() -> Result<(), String> {
     db::clear_anthropic_key().map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn validate_and_save_gemini_key(api_key: String) -> Result<bool, String> {
+    let client = gemini::GeminiClient::new(&api_key);
+    
+    match client.validate_api_key().await {
+        Ok(valid) => {
+            if valid {
+                db::run_blocking(move || db::update_gemini_key(&api_key)).await?.map_err(|e| e.to_string())?;
+            }
+            Ok(valid)
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+fn save_gemini_key(api_key: String) -> Result<(), String> {
+    db::update_gemini_key(&api_key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_gemini_key() -> Result<(), String> {
+    db::clear_gemini_key().map_err(|e| e.to_string())
+}
+
 // ============ Persona Profiles ============
 
 #[tauri::command]
@@ -348,6 +662,145 @@ fn reset_personalization(profile_id: String, options: ResetOptions) -> Result<()
     }
 }
 
+// ============ First-Run Setup ============
+
+/// Ordered onboarding steps; setup resumes at the first one not yet completed
+const SETUP_STEPS: [&str; 4] = ["keys", "provider_test", "persona", "privacy"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetupState {
+    pub completed_steps: Vec<String>,
+    pub next_step: Option<String>, // None once every step is done
+    pub is_complete: bool,
+    pub has_openai_key: bool,
+    pub has_anthropic_key: bool,
+    pub memory_extraction_enabled: bool,
+}
+
+fn load_completed_setup_steps() -> Vec<String> {
+    db::get_setting("setup_completed_steps")
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Privacy choice from setup: whether exchanges are mined for facts/patterns
+fn memory_extraction_enabled() -> bool {
+    db::get_setting("privacy_memory_extraction")
+        .ok()
+        .flatten()
+        .map(|v| v != "false")
+        .unwrap_or(true)
+}
+
+#[tauri::command]
+fn get_setup_state() -> Result<SetupState, String> {
+    let profile = db::get_user_profile().map_err(|e| e.to_string())?;
+    let completed_steps = load_completed_setup_steps();
+    let next_step = SETUP_STEPS.iter()
+        .find(|step| !completed_steps.iter().any(|c| c == *step))
+        .map(|s| s.to_string());
+    
+    Ok(SetupState {
+        is_complete: next_step.is_none(),
+        completed_steps,
+        next_step,
+        has_openai_key: profile.api_key.is_some(),
+        has_anthropic_key: profile.anthropic_key.is_some(),
+        memory_extraction_enabled: memory_extraction_enabled(),
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SetupPayload {
+    pub openai_key: Option<String>,
+    pub anthropic_key: Option<String>,
+    pub name: Option<String>,
+    pub dominant_trait: Option<String>,
+    pub secondary_trait: Option<String>,
+    pub memory_extraction: Option<bool>,
+}
+
+#[tauri::command]
+async fn complete_setup_step(step: String, payload: Option<SetupPayload>) -> Result<SetupState, String> {
+    let payload = payload.unwrap_or_default();
+    
+    match step.as_str() {
+        "keys" => {
+            let openai_key = payload.openai_key.filter(|k| !k.trim().is_empty());
+            let anthropic_key = payload.anthropic_key.filter(|k| !k.trim().is_empty());
+            let profile = db::run_blocking(move || {
+                if let Some(key) = openai_key {
+                    db::update_api_key(key.trim())?;
+                }
+                if let Some(key) = anthropic_key {
+                    db::update_anthropic_key(key.trim())?;
+                }
+                db::get_user_profile()
+            }).await?.map_err(|e| e.to_string())?;
+            // OpenAI is optional: without it agents run on Claude (Anthropic-only mode)
+            if profile.anthropic_key.is_none() {
+                return Err("An Anthropic key is required".to_string());
+            }
+        }
+        "provider_test" => {
+            let health = validate_all_keys().await?;
+            for (provider, status, required) in [("OpenAI", &health.openai, false), ("Anthropic", &health.anthropic, true)] {
+                if !status.configured {
+                    if !required {
+                        continue;
+                    }
+                    return Err(format!("{} API key not set", provider));
+                }
+                if !status.valid {
+                    return Err(status.error.clone()
+                        .unwrap_or_else(|| format!("{} API key was rejected", provider)));
+                }
+            }
+        }
+        "persona" => {
+            let dominant = payload.dominant_trait.unwrap_or_else(|| "logic".to_string());
+            let secondary = payload.secondary_trait.unwrap_or_else(|| "psyche".to_string());
+            if Agent::from_str(&dominant).is_none() || Agent::from_str(&secondary).is_none() || dominant == secondary {
+                return Err("Choose two different traits".to_string());
+            }
+            
+            let name = payload.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+            db::run_blocking(move || -> rusqlite::Result<()> {
+                // Resuming after a crash shouldn't create a duplicate first profile
+                if db::get_persona_profile_count()? == 0 {
+                    let name = name.unwrap_or_else(|| "Default".to_string());
+                    db::create_persona_profile(&name, &dominant, &secondary, true)?;
+                } else {
+                    db::update_active_persona_traits(&dominant, &secondary)?;
+                    if let (Some(name), Some(active)) = (name, db::get_active_persona_profile()?) {
+                        db::update_persona_profile_name(&active.id, &name)?;
+                    }
+                }
+                Ok(())
+            }).await?.map_err(|e| e.to_string())?;
+        }
+        "privacy" => {
+            let enabled = payload.memory_extraction.unwrap_or(true);
+            db::run_blocking(move || db::set_setting("privacy_memory_extraction", if enabled { "true" } else { "false" }))
+                .await?
+                .map_err(|e| e.to_string())?;
+        }
+        _ => return Err(format!("Unknown setup step: {}", step)),
+    }
+    
+    let mut completed = load_completed_setup_steps();
+    if !completed.contains(&step) {
+        completed.push(step.clone());
+        let json = serde_json::to_string(&completed).map_err(|e| e.to_string())?;
+        db::run_blocking(move || db::set_setting("setup_completed_steps", &json)).await?.map_err(|e| e.to_string())?;
+    }
+    logging::log_conversation(None, &format!("Setup step completed: {}", step));
+    
+    get_setup_state()
+}
+
 // ============ Conversations ============
 
 #[tauri::command]
@@ -361,6 +814,9 @@ fn create_conversation(is_disco: bool) -> Result<ConversationInfo, String> {
         is_disco: conv.is_disco,
         created_at: conv.created_at,
         updated_at: conv.updated_at,
+        model_override: conv.model_override,
+        parent_conversation_id: conv.parent_conversation_id,
+        branch_point_message_id: conv.branch_point_message_id,
     })
 }
 
@@ -374,6 +830,9 @@ fn get_recent_conversations(limit: usize) -> Result<Vec<ConversationInfo>, Strin
         is_disco: c.is_disco,
         created_at: c.created_at,
         updated_at: c.updated_at,
+        model_override: c.model_override,
+        parent_conversation_id: c.parent_conversation_id,
+        branch_point_message_id: c.branch_point_message_id,
     }).collect())
 }
 
@@ -382,9 +841,92 @@ fn get_conversation_messages(conversation_id: String) -> Result<Vec<Message>, St
     db::get_conversation_messages(&conversation_id).map_err(|e| e.to_string())
 }
 
+/// Full-text search across every conversation, best matches first (snippets highlight terms with <mark>)
+#[tauri::command]
+fn search_messages(query: String, limit: Option<usize>) -> Result<Vec<db::SearchHit>, String> {
+    db::search_messages(&query, limit.unwrap_or(20).min(100)).map_err(|e| e.to_string())
+}
+
+/// Search messages, facts and summaries by meaning rather than exact words
+#[tauri::command]
+async fn semantic_search(query: String, limit: Option<usize>) -> Result<Vec<semantic::SemanticHit>, String> {
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let api_key = profile.api_key.ok_or("OpenAI API key not set")?;
+    semantic::search(&api_key, &query, limit.unwrap_or(10).min(50))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pin the model agents use in this conversation ("gpt-4o-mini", "opus", a full Claude model ID),
+/// or pass None to go back to the default
+#[tauri::command]
+fn set_conversation_model(conversation_id: String, model: Option<String>) -> Result<Option<String>, String> {
+    let resolved = match model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        Some(name) => Some(orchestrator::resolve_agent_model(name)
+            .ok_or_else(|| format!("Unsupported model: {}", name))?),
+        None => None,
+    };
+    db::set_conversation_model(&conversation_id, resolved.as_deref()).map_err(|e| e.to_string())?;
+    logging::log_conversation(Some(&conversation_id), &format!(
+        "Agent model set to {}", resolved.as_deref().unwrap_or("default")
+    ));
+    Ok(resolved)
+}
+
+/// Save which agents this conversation uses by default ("Dot-only" = ["logic"]),
+/// or pass None to go back to all three
+#[tauri::command]
+fn set_conversation_agents(conversation_id: String, agents: Option<Vec<String>>) -> Result<Option<Vec<String>>, String> {
+    let agents = match agents {
+        Some(agents) => {
+            let mut preset: Vec<String> = Vec::new();
+            for agent in &agents {
+                let agent = Agent::from_str(agent).ok_or_else(|| format!("Unknown agent: {}", agent))?;
+                if !preset.iter().any(|a| a == agent.as_str()) {
+                    preset.push(agent.as_str().to_string());
+                }
+            }
+            if preset.is_empty() {
+                return Err("A preset needs at least one agent".to_string());
+            }
+            Some(preset)
+        }
+        None => None,
+    };
+    if !db::set_conversation_agents(&conversation_id, agents.as_deref()).map_err(|e| e.to_string())? {
+        return Err(format!("Conversation not found: {}", conversation_id));
+    }
+    logging::log_conversation(Some(&conversation_id), &format!(
+        "Agent preset set to {}", agents.as_ref().map(|a| a.join(", ")).unwrap_or_else(|| "all agents".to_string())
+    ));
+    Ok(agents)
+}
+
+/// The conversation's saved agent preset (None = all agents)
+#[tauri::command]
+fn get_conversation_agents(conversation_id: String) -> Result<Option<Vec<String>>, String> {
+    db::get_conversation_agents(&conversation_id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn clear_conversation(conversation_id: String) -> Result<(), String> {
-    db::clear_conversation_messages(&conversation_id).map_err(|e| e.to_string())
+    let removed = db::clear_conversation_messages(&conversation_id).map_err(|e| e.to_string())?;
+    attachments::remove_files(&removed);
+    Ok(())
+}
+
+/// Delete a conversation. By default only its own data goes; `options` can also remove the facts
+/// and theme links it produced, or keep its summary so the gist still informs later conversations.
+#[tauri::command]
+fn delete_conversation(conversation_id: String, options: Option<db::DeleteConversationOptions>) -> Result<db::DeletedCounts, String> {
+    let stored_files = db::get_conversation_attachments(&conversation_id).unwrap_or_default();
+    let counts = db::delete_conversation_with(&conversation_id, options.unwrap_or_default()).map_err(|e| e.to_string())?;
+    attachments::remove_files(&stored_files);
+    logging::log_conversation(Some(&conversation_id), &format!(
+        "Deleted conversation ({} messages, {} facts, {} summaries, {} themes updated, {} themes removed)",
+        counts.messages, counts.facts, counts.summaries, counts.themes_updated, counts.themes_removed
+    ));
+    Ok(counts)
 }
 
 /// Finalize a conversation: run holistic extraction, consolidate facts, generate final summary
@@ -393,57 +935,291 @@ async fn finalize_conversation(conversation_id: String) -> Result<(), String> {
     finalize_conversation_internal(&conversation_id).await
 }
 
-// ============ Conversation Opener ============
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ConversationOpenerResult {
-    pub agent: String,
-    pub content: String,
-}
-
+/// "Previously" recap for a reopened conversation; cached until new messages arrive
 #[tauri::command]
-async fn get_conversation_opener(is_voice_mode: Option<bool>) -> Result<ConversationOpenerResult, String> {
-    let profile = db::get_user_profile().map_err(|e| e.to_string())?;
-    let anthropic_key = profile.anthropic_key.ok_or("Anthropic API key not set")?;
+async fn get_conversation_recap(conversation_id: String) -> Result<Option<String>, String> {
+    use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
     
-    // Get active persona profile to inform the greeting
-    let active_profile = db::get_active_persona_profile().map_err(|e| e.to_string())?;
-    let active_trait = active_profile.map(|p| p.dominant_trait).unwrap_or_else(|| "logic".to_string());
+    let id = conversation_id.clone();
+    let (messages, cached) = db::run_blocking(move || -> rusqlite::Result<_> {
+        Ok((db::get_conversation_messages(&id)?, db::get_conversation_recap(&id)?))
+    }).await?.map_err(|e| e.to_string())?;
+    let messages: Vec<Message> = messages.into_iter()
+        .filter(|m| m.role != "system")
+        .collect();
+    if messages.len() < 2 {
+        return Ok(None);
+    }
+    let message_count = messages.len() as i64;
     
-    // The dominant agent greets the user (using Anthropic/Claude)
-    // No past conversation context - each new conversation starts fresh
-    let content = generate_governor_greeting(&anthropic_key, &active_trait, is_voice_mode.unwrap_or(false))
-        .await
-        .map_err(|e| e.to_string())?;
+    if let Some((recap, cached_count)) = cached {
+        if cached_count == message_count {
+            return Ok(Some(recap));
+        }
+    }
     
-    // Return the dominant agent as the speaker, not "system"
-    Ok(ConversationOpenerResult { agent: active_trait.clone(), content })
-}
+    let id = conversation_id.clone();
+    let (profile, conversation, summary) = db::run_blocking(move || -> rusqlite::Result<_> {
+        let profile = db::get_user_profile()?;
+        let conversation = db::get_conversation(&id)?;
+        // Prefer the final summary, then the rolling summary, then the crash-safe limbo notes
+        let summary = conversation.as_ref().and_then(|c| c.summary.clone())
+            .or_else(|| db::get_conversation_summary(&id).ok().flatten().map(|s| s.summary))
+            .or_else(|| db::get_limbo_summary(&id).ok().flatten())
+            .unwrap_or_else(|| "No summary stored.".to_string());
+        Ok((profile, conversation, summary))
+    }).await?.map_err(|e| e.to_string())?;
+    let anthropic_key = profile.anthropic_key.ok_or("Anthropic API key not set")?;
+    conversation.ok_or("Conversation not found")?;
+    
+    // The tail of the conversation is where open loops live
+    let tail: String = messages.iter()
+        .rev()
+        .take(6)
+        .rev()
+        .map(|m| format!("{}: {}", m.role.to_uppercase(), truncate_for_summary(&m.content, 300)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let system_prompt = r#"You are the Governor of Intersect. The user is reopening an earlier conversation.
 
-/// Generate a brief Governor greeting for a new conversation using knowledge base
-/// Each new conversation starts with a fresh context window - no past conversation references
-/// In voice mode, the greeting is more atmospheric and evocative to set the mood
-async fn generate_governor_greeting(anthropic_key: &str, active_trait: &str, is_voice_mode: bool) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
-    use chrono::{Local, Timelike};
+Write a "previously" recap in 2-3 sentences:
+- What the conversation was about
+- Where it left off, including any open question or unresolved thread
+- Address the user as "you"; no headers, no bullet points
+- When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")"#;
     
-    // ===== CURRENT TIME OF DAY (not relative to past conversations) =====
-    let now = Local::now();
-    let hour = now.hour();
-    let time_of_day = match hour {
-        5..=8 => "early_morning",
-        9..=11 => "morning",
-        12..=16 => "afternoon",
-        17..=20 => "evening",
-        _ => "late_night", // 21-4
-    };
+    let user_prompt = format!(
+        "SUMMARY:\n{}\n\nHOW IT ENDED:\n{}\n\nWrite the recap:",
+        summary, tail
+    );
     
-    // ===== GATHER USER CONTEXT (learned knowledge, not conversation-specific) =====
-    let user_facts = db::get_all_user_facts().unwrap_or_default();
-    let user_patterns = db::get_all_user_patterns().unwrap_or_default();
+    let client = AnthropicClient::new(&anthropic_key);
+    let recap = client.chat_completion_advanced(
+        CLAUDE_HAIKU,
+        Some(system_prompt),
+        vec![AnthropicMessage { role: "user".to_string(), content: user_prompt }],
+        0.5,
+        Some(200),
+        ThinkingBudget::None
+    ).await.map_err(|e| e.to_string())?;
     
-    // Build context for greeting
-    let mut context_parts = Vec::new();
+    let recap = recap.trim().to_string();
+    let saved = recap.clone();
+    db::run_blocking(move || db::save_conversation_recap(&conversation_id, &saved, message_count))
+        .await?
+        .map_err(|e| e.to_string())?;
+    
+    Ok(Some(recap))
+}
+
+// ============ Conversation Tags ============
+
+/// Summary topics become tags only if they're short enough to read as a label
+const AUTO_TAG_LIMIT: usize = 3;
+const AUTO_TAG_MAX_CHARS: usize = 24;
+
+fn auto_tagging_enabled() -> bool {
+    db::get_setting("auto_tag_conversations")
+        .ok()
+        .flatten()
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+/// Tag a finished conversation with its leading summary topics
+fn auto_tag_conversation(conversation_id: &str, key_topics: &[String]) {
+    let tags: Vec<String> = key_topics.iter()
+        .map(|t| db::normalize_tag(t))
+        .filter(|t| !t.is_empty() && t.chars().count() <= AUTO_TAG_MAX_CHARS)
+        .take(AUTO_TAG_LIMIT)
+        .collect();
+    for tag in &tags {
+        if let Err(e) = db::add_conversation_tag(conversation_id, tag, "auto") {
+            logging::log_error(Some(conversation_id), &format!("Auto-tagging failed: {}", e));
+            return;
+        }
+    }
+    if !tags.is_empty() {
+        logging::log_memory(Some(conversation_id), &format!("Auto-tagged: {}", tags.join(", ")));
+    }
+}
+
+#[tauri::command]
+fn add_conversation_tag(conversation_id: String, tag: String) -> Result<Vec<db::ConversationTag>, String> {
+    if db::normalize_tag(&tag).is_empty() {
+        return Err("Tag can't be empty".to_string());
+    }
+    db::add_conversation_tag(&conversation_id, &tag, "user").map_err(|e| e.to_string())?;
+    db::get_conversation_tags(&conversation_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_conversation_tag(conversation_id: String, tag: String) -> Result<Vec<db::ConversationTag>, String> {
+    db::remove_conversation_tag(&conversation_id, &tag).map_err(|e| e.to_string())?;
+    db::get_conversation_tags(&conversation_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_conversation_tags(conversation_id: String) -> Result<Vec<db::ConversationTag>, String> {
+    db::get_conversation_tags(&conversation_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_all_tags() -> Result<Vec<db::TagCount>, String> {
+    db::get_all_tags().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_conversations_by_tag(tag: String) -> Result<Vec<ConversationInfo>, String> {
+    let convs = db::get_conversations_by_tag(&tag).map_err(|e| e.to_string())?;
+    Ok(convs.into_iter().map(|c| ConversationInfo {
+        id: c.id,
+        title: c.title,
+        summary: c.summary,
+        is_disco: c.is_disco,
+        created_at: c.created_at,
+        updated_at: c.updated_at,
+        model_override: c.model_override,
+        parent_conversation_id: c.parent_conversation_id,
+        branch_point_message_id: c.branch_point_message_id,
+    }).collect())
+}
+
+#[tauri::command]
+fn get_auto_tagging_enabled() -> Result<bool, String> {
+    Ok(auto_tagging_enabled())
+}
+
+#[tauri::command]
+fn set_auto_tagging_enabled(enabled: bool) -> Result<(), String> {
+    db::set_setting("auto_tag_conversations", if enabled { "true" } else { "false" })
+        .map_err(|e| e.to_string())
+}
+
+// ============ Conversation Opener ============
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationOpenerResult {
+    pub agent: String,
+    pub content: String,
+}
+
+/// How the conversation opener greets the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GreetingStyle {
+    Contextual, // LLM greeting with time-of-day and profile color (default)
+    Minimal,    // Short static opener, no LLM call
+    None,       // No opener at all
+}
+
+impl GreetingStyle {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GreetingStyle::Contextual => "contextual",
+            GreetingStyle::Minimal => "minimal",
+            GreetingStyle::None => "none",
+        }
+    }
+    
+    fn from_str(s: &str) -> Option<GreetingStyle> {
+        match s.to_lowercase().as_str() {
+            "contextual" => Some(GreetingStyle::Contextual),
+            "minimal" => Some(GreetingStyle::Minimal),
+            "none" => Some(GreetingStyle::None),
+            _ => None,
+        }
+    }
+    
+    /// Load the saved greeting style, defaulting to contextual
+    fn load() -> GreetingStyle {
+        db::get_setting("greeting_style")
+            .ok()
+            .flatten()
+            .and_then(|s| GreetingStyle::from_str(&s))
+            .unwrap_or(GreetingStyle::Contextual)
+    }
+}
+
+#[tauri::command]
+fn get_greeting_style() -> Result<GreetingStyle, String> {
+    Ok(GreetingStyle::load())
+}
+
+#[tauri::command]
+fn set_greeting_style(style: String) -> Result<(), String> {
+    let parsed = GreetingStyle::from_str(&style)
+        .ok_or_else(|| format!("Invalid greeting style: {}", style))?;
+    db::set_setting("greeting_style", parsed.as_str()).map_err(|e| e.to_string())
+}
+
+/// Static opener used by the "minimal" greeting style (no API call)
+fn minimal_opener(active_trait: &str) -> &'static str {
+    match active_trait {
+        "instinct" => "What's up?",
+        "psyche" => "How are you doing?",
+        _ => "What's on your mind?",
+    }
+}
+
+#[tauri::command]
+async fn get_conversation_opener(is_voice_mode: Option<bool>) -> Result<ConversationOpenerResult, String> {
+    // Get active persona profile to inform the greeting
+    let active_profile = db::run_blocking(db::get_active_persona_profile).await?.map_err(|e| e.to_string())?;
+    let active_trait = active_profile.map(|p| p.dominant_trait).unwrap_or_else(|| "logic".to_string());
+    
+    // Respect the user's greeting style before touching the API
+    // "none" returns empty content so the frontend can skip rendering an opener
+    match GreetingStyle::load() {
+        GreetingStyle::None => {
+            return Ok(ConversationOpenerResult { agent: active_trait, content: String::new() });
+        }
+        GreetingStyle::Minimal => {
+            let content = minimal_opener(&active_trait).to_string();
+            return Ok(ConversationOpenerResult { agent: active_trait, content });
+        }
+        GreetingStyle::Contextual => {}
+    }
+    
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let anthropic_key = profile.anthropic_key.ok_or("Anthropic API key not set")?;
+    
+    // The dominant agent greets the user (using Anthropic/Claude)
+    // No past conversation context - each new conversation starts fresh
+    let content = generate_governor_greeting(&anthropic_key, &active_trait, is_voice_mode.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    // Return the dominant agent as the speaker, not "system"
+    Ok(ConversationOpenerResult { agent: active_trait.clone(), content })
+}
+
+/// Generate a brief Governor greeting for a new conversation using knowledge base
+/// Each new conversation starts with a fresh context window - no past conversation references
+/// In voice mode, the greeting is more atmospheric and evocative to set the mood
+async fn generate_governor_greeting(anthropic_key: &str, active_trait: &str, is_voice_mode: bool) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
+    use chrono::Timelike;
+    
+    // ===== CURRENT TIME OF DAY (not relative to past conversations) =====
+    let now = clock::now_local();
+    let hour = now.hour();
+    let time_of_day = match hour {
+        5..=8 => "early_morning",
+        9..=11 => "morning",
+        12..=16 => "afternoon",
+        17..=20 => "evening",
+        _ => "late_night", // 21-4
+    };
+    
+    // ===== GATHER USER CONTEXT (learned knowledge, not conversation-specific) =====
+    let (user_facts, user_patterns) = db::run_blocking(|| {
+        (db::get_all_user_facts().unwrap_or_default(), db::get_all_user_patterns().unwrap_or_default())
+    }).await.unwrap_or_default();
+    
+    // Build context for greeting
+    let mut context_parts = Vec::new();
     
     // 1. TIME OF DAY (current time only)
     let time_of_day_desc = match time_of_day {
@@ -485,6 +1261,26 @@ async fn generate_governor_greeting(anthropic_key: &str, active_trait: &str, is_
         context_parts.push(format!("BEHAVIORAL PATTERNS:\n{}", themes.join("\n")));
     }
     
+    // 5. DECISION FOLLOW-UP (a choice made at least two weeks ago, asked about once)
+    let follow_up_cutoff = (clock::now() - chrono::Duration::days(14)).to_rfc3339();
+    let due_decision = if is_voice_mode {
+        None
+    } else {
+        db::run_blocking(move || db::get_decision_due_for_follow_up(&follow_up_cutoff))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .flatten()
+    };
+    if let Some(decision) = &due_decision {
+        context_parts.push(format!("DECISION FOLLOW-UP: A while ago they decided: {}", decision.decision));
+    }
+    
+    // 6. TODAY'S CALENDAR (only when the user turned calendar context on)
+    if let Some(day) = calendar::day_context().await {
+        context_parts.push(day);
+    }
+    
     let full_context = context_parts.join("\n\n");
     
     // ===== SYSTEM PROMPT - Different for text vs voice mode =====
@@ -561,7 +1357,8 @@ Only mention if relevant.
 - When using dashes: ALWAYS " -- " (double dashes with spaces)
 - NO roleplay asterisks like *leans in* or *pauses* -- just speak naturally
 - NO meta-commentary, explanations, or quotation marks around your output
-- This is a fresh conversation - don't reference past conversations"#, active_trait)
+- This is a fresh conversation - don't reference past conversations
+- EXCEPTION: if a DECISION FOLLOW-UP is given, ask briefly how that choice is working out (e.g. "You went with the smaller apartment -- how's that going?")"#, active_trait)
     };
 
     let client = AnthropicClient::new(anthropic_key);
@@ -574,14 +1371,21 @@ Only mention if relevant.
     
     let max_tokens = if is_voice_mode { 100 } else { 50 }; // Voice mode greetings are longer
     
-    client.chat_completion_advanced(
+    let greeting = client.chat_completion_advanced(
         CLAUDE_HAIKU,
         Some(&system_prompt),
         messages,
         0.8,
         Some(max_tokens),
         ThinkingBudget::None
-    ).await
+    ).await?;
+    
+    // Only follow up on a decision once
+    if let Some(decision) = due_decision {
+        let _ = db::run_blocking(move || db::mark_decision_followed_up(&decision.id)).await;
+    }
+    
+    Ok(greeting)
 }
 
 /// Check if a disco session needs a cooldown warning
@@ -625,10 +1429,9 @@ async fn generate_governor_response(
     journey_phase: Option<&str>, // Game Mode journey phase: "exploration", "resolution", "acceptance"
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
-    use chrono::Local;
     
     // Get current date/time for context
-    let now = Local::now();
+    let now = clock::now_local();
     let current_datetime = now.format("%A, %B %d, %Y at %I:%M %p").to_string();
     
     // Format internal processing (Governor sees this, but doesn't reveal it)
@@ -828,259 +1631,1474 @@ fn get_dominant_agent(weights: (f64, f64, f64)) -> &'static str {
     }
 }
 
-// Helper to generate weight change notification
-fn generate_weight_notification(
-    old_weights: (f64, f64, f64),
-    new_weights: (f64, f64, f64),
-    primary_agent: &str,
-    had_secondary: bool,
-) -> Option<WeightChangeNotification> {
-    let old_dominant = get_dominant_agent(old_weights);
-    let new_dominant = get_dominant_agent(new_weights);
-    
-    // Calculate total weight shift
-    let total_shift = (new_weights.0 - old_weights.0).abs() 
-        + (new_weights.1 - old_weights.1).abs() 
-        + (new_weights.2 - old_weights.2).abs();
-    
-    // Only notify on significant changes
-    if total_shift < 0.01 {
-        return None;
+// ============ Agent Providers ============
+
+/// Put each agent on its configured provider (agents left on the default keep the orchestrator's default:
+/// OpenAI, or Claude in Anthropic-only mode). An agent whose provider has no key uses that default
+/// rather than failing the turn.
+fn apply_agent_providers(orchestrator: &mut Orchestrator, profile: &UserProfile) {
+    for agent in [Agent::Instinct, Agent::Logic, Agent::Psyche] {
+        let config = providers::get_agent_provider(agent.as_str());
+        if config == providers::AgentProvider::default() {
+            continue;
+        }
+        match providers::build(&config, profile) {
+            Ok(provider) => orchestrator.set_agent_provider(agent, provider, config.model_or_default()),
+            Err(e) => logging::log_error(None, &format!("{} uses the default provider: {}", agent.as_str(), e)),
+        }
     }
     
-    let agent_name = match primary_agent {
-        "instinct" => "Snap",
-        "logic" => "Dot", 
-        "psyche" => "Puff",
-        _ => primary_agent,
-    };
-    
-    let (change_type, message) = if old_dominant != new_dominant {
-        // Major shift - dominant agent changed
-        let old_name = match old_dominant {
-            "instinct" => "Instinct",
-            "logic" => "Logic",
-            "psyche" => "Psyche",
-            _ => old_dominant,
-        };
-        let new_name = match new_dominant {
-            "instinct" => "Instinct",
-            "logic" => "Logic", 
-            "psyche" => "Psyche",
-            _ => new_dominant,
-        };
-        (
-            "major_shift".to_string(),
-            format!("Your dominant trait has shifted from {} to {}. This conversation resonated more with {}.", old_name, new_name, agent_name)
-        )
-    } else if total_shift > 0.03 {
-        // Notable shift within same dominant
-        let direction = if new_weights.0 > old_weights.0 {
-            "Instinct"
-        } else if new_weights.1 > old_weights.1 {
-            "Logic"
-        } else {
-            "Psyche"
-        };
-        (
-            "shift".to_string(),
-            format!("Your {} weight increased slightly. {} guided this exchange.", direction, agent_name)
-        )
-    } else {
-        // Minor adjustment
-        (
-            "minor".to_string(),
-            format!("Weights adjusted based on {} taking the lead{}.", agent_name, if had_secondary { " with support" } else { "" })
-        )
+    // Fallbacks without a key are left out of the chain
+    let fallbacks = providers::get_fallback_chain().iter()
+        .filter_map(|entry| {
+            let config = providers::parse_fallback(entry);
+            let provider = providers::build(&config, profile).ok()?;
+            Some((provider, config.model_or_default()))
+        })
+        .collect();
+    orchestrator.set_fallback_chain(fallbacks);
+}
+
+/// Note on a saved agent message which model actually produced it (metadata "served_by")
+fn annotate_served_model(orchestrator: &Orchestrator, agent: Agent, message_id: &str) {
+    let Some(served) = orchestrator.served_model(agent) else { return };
+    annotate_message(message_id, "served_by", serde_json::json!(served));
+}
+
+/// Set one key in a message's metadata JSON, keeping the others
+fn annotate_message(message_id: &str, key: &str, value: serde_json::Value) {
+    let mut metadata = db::get_message_metadata(message_id).ok().flatten()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .filter(|m| m.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    metadata[key] = value;
+    let _ = db::set_message_metadata(message_id, &metadata.to_string());
+}
+
+#[tauri::command]
+fn get_model_fallback_chain() -> Vec<String> {
+    providers::get_fallback_chain()
+}
+
+/// Models agents fall back to, in order, on rate limits and provider outages
+/// ("gpt-4o-mini", "claude-sonnet-4-20250514", "ollama:llama3.1", ...); empty disables fallback
+#[tauri::command]
+fn set_model_fallback_chain(chain: Vec<String>) -> Result<(), String> {
+    providers::set_fallback_chain(&chain)?;
+    logging::log_routing(None, &format!("Model fallback chain set to [{}]", chain.join(", ")));
+    Ok(())
+}
+
+#[tauri::command]
+fn get_retry_policy() -> retry::RetryPolicy {
+    retry::get_policy()
+}
+
+/// How often and how patiently model calls retry rate limits and transient errors
+/// before giving up (or moving down the fallback chain)
+#[tauri::command]
+fn set_retry_policy(policy: retry::RetryPolicy) -> Result<(), String> {
+    retry::set_policy(&policy)
+}
+
+#[tauri::command]
+fn get_agent_providers() -> HashMap<String, providers::AgentProvider> {
+    [Agent::Instinct, Agent::Logic, Agent::Psyche].iter()
+        .map(|agent| (agent.as_str().to_string(), providers::get_agent_provider(agent.as_str())))
+        .collect()
+}
+
+/// Run an agent on "openai", "anthropic", "ollama" or "gemini"; model None uses the provider's default
+#[tauri::command]
+fn set_agent_provider(agent: String, provider: String, model: Option<String>) -> Result<(), String> {
+    let agent = Agent::from_str(&agent).ok_or_else(|| format!("Unknown agent: {}", agent))?;
+    let config = providers::AgentProvider {
+        provider: provider.trim().to_lowercase(),
+        model: model.map(|m| m.trim().to_string()).filter(|m| !m.is_empty()),
     };
-    
-    Some(WeightChangeNotification {
-        message,
-        old_dominant: old_dominant.to_string(),
-        new_dominant: new_dominant.to_string(),
-        change_type,
-    })
+    providers::set_agent_provider(agent.as_str(), &config)?;
+    logging::log_routing(None, &format!(
+        "{} now runs on {} ({})", agent.as_str(), config.provider, config.model_or_default()
+    ));
+    Ok(())
 }
 
-// ============ Send Message (Core Turn-Taking with Memory) ============
+#[tauri::command]
+fn get_agent_sampling() -> HashMap<String, sampling::AgentSampling> {
+    [Agent::Instinct, Agent::Logic, Agent::Psyche].iter()
+        .map(|agent| (agent.as_str().to_string(), sampling::get(*agent)))
+        .collect()
+}
 
+/// Set an agent's reply temperature (0-1), with an optional different one while it's in disco mode
 #[tauri::command]
-async fn send_message(
-    conversation_id: String,
-    user_message: String,
-    active_agents: Vec<String>,
-    disco_agents: Vec<String>,
-) -> Result<SendMessageResult, String> {
-    // Get profile for API keys and weights
-    let profile = db::get_user_profile().map_err(|e| e.to_string())?;
-    let api_key = profile.api_key.clone().ok_or("OpenAI API key not set")?;
-    let anthropic_key = profile.anthropic_key.clone().ok_or("Anthropic API key not set")?;
-    
-    // Get active persona profile for points and dominant trait
-    let active_persona = db::get_active_persona_profile().map_err(|e| e.to_string())?
-        .ok_or("No active persona profile")?;
-    let points = (active_persona.instinct_points, active_persona.logic_points, active_persona.psyche_points);
-    let dominant_trait = Some(active_persona.dominant_trait.as_str());
-    
-    // ===== SESSION WEIGHTS: Separate base (persistent) from session (decaying) =====
-    let base_weights = (profile.instinct_weight, profile.logic_weight, profile.psyche_weight);
-    
-    // Decay session weights by 10% per exchange
-    decay_session_weights(&conversation_id);
-    
-    // Get current session weights
-    let session_weights = get_or_init_session_weights(&conversation_id);
-    
-    // Combine base + session for routing
-    let routing_weights = (
-        base_weights.0 + session_weights.0,
-        base_weights.1 + session_weights.1,
-        base_weights.2 + session_weights.2,
-    );
+fn set_agent_sampling(agent: String, temperature: f32, disco_temperature: Option<f32>) -> Result<(), String> {
+    let agent = Agent::from_str(&agent).ok_or_else(|| format!("Unknown agent: {}", agent))?;
+    sampling::set(agent, &sampling::AgentSampling { temperature, disco_temperature })?;
+    logging::log_routing(None, &format!(
+        "{} temperature set to {}{}", agent.as_str(), temperature,
+        disco_temperature.map(|t| format!(" ({} in disco)", t)).unwrap_or_default()
+    ));
+    Ok(())
+}
+
+/// Put an agent back on its built-in temperature
+#[tauri::command]
+fn reset_agent_sampling(agent: String) -> Result<bool, String> {
+    let agent = Agent::from_str(&agent).ok_or_else(|| format!("Unknown agent: {}", agent))?;
+    sampling::reset(agent)
+}
+
+/// Provider for agents without their own setting (stored on the user profile)
+#[tauri::command]
+fn set_default_agent_provider(provider: String) -> Result<(), String> {
+    let provider = provider.trim().to_lowercase();
+    providers::set_default_provider(&provider)?;
+    logging::log_routing(None, &format!("Default agent provider set to {}", provider));
+    Ok(())
+}
+
+#[tauri::command]
+fn get_ollama_url() -> String {
+    providers::ollama_base_url()
+}
+
+/// Point Ollama agents at another server (empty resets to localhost)
+#[tauri::command]
+fn set_ollama_url(url: String) -> Result<(), String> {
+    providers::set_ollama_base_url(&url)
+}
+
+#[tauri::command]
+fn get_openai_endpoint() -> openai::OpenAIEndpoint {
+    openai::OpenAIEndpoint::configured()
+}
+
+/// Point the OpenAI client at OpenRouter or another OpenAI-compatible server.
+/// Omitted fields fall back to api.openai.com, no extra headers and gpt-4o-mini.
+#[tauri::command]
+fn set_openai_endpoint(
+    base_url: Option<String>,
+    extra_headers: Option<HashMap<String, String>>,
+    default_model: Option<String>,
+) -> Result<openai::OpenAIEndpoint, String> {
+    let defaults = openai::OpenAIEndpoint::default();
+    let endpoint = openai::OpenAIEndpoint {
+        base_url: base_url.filter(|u| !u.trim().is_empty()).unwrap_or(defaults.base_url),
+        extra_headers: extra_headers.unwrap_or_default(),
+        default_model: default_model.filter(|m| !m.trim().is_empty()).unwrap_or(defaults.default_model),
+    };
+    endpoint.save()?;
     
-    if active_agents.is_empty() {
-        return Ok(SendMessageResult { responses: Vec::new(), debate_mode: None, weight_change: None, governor_response: None });
+    let saved = openai::OpenAIEndpoint::configured();
+    logging::log_routing(None, &format!(
+        "OpenAI endpoint set to {} (default model {})", saved.base_url, saved.default_model
+    ));
+    Ok(saved)
+}
+
+/// Models available on the configured Ollama server (also a reachability check)
+#[tauri::command]
+async fn list_ollama_models() -> Result<Vec<String>, String> {
+    ollama::OllamaClient::new(&providers::ollama_base_url())
+        .list_models()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// ============ Agent Prompts ============
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentPromptInfo {
+    pub agent: String,
+    pub mode: String,            // "normal" | "disco"
+    pub prompt: String,          // The persona prompt in use
+    pub is_custom: bool,
+    pub default_prompt: String,  // Built-in prompt, for diffing and "reset"
+}
+
+/// Parse an agent and an optional prompt mode ("normal" when omitted, or "disco")
+fn parse_prompt_target(agent: &str, mode: Option<&str>) -> Result<(Agent, bool), String> {
+    let agent = Agent::from_str(agent).ok_or_else(|| format!("Unknown agent: {}", agent))?;
+    match mode.map(|m| m.trim().to_lowercase()).as_deref() {
+        None | Some("normal") => Ok((agent, false)),
+        Some("disco") => Ok((agent, true)),
+        Some(other) => Err(format!("Unknown prompt mode: {}", other)),
+    }
+}
+
+/// An agent's persona prompt. The formatting and safety guard rails are appended at
+/// call time whether or not the prompt is custom, so they aren't included here.
+#[tauri::command]
+fn get_agent_prompt(agent: String, mode: Option<String>) -> Result<AgentPromptInfo, String> {
+    let (agent, is_disco) = parse_prompt_target(&agent, mode.as_deref())?;
+    let mode = orchestrator::prompt_mode(is_disco);
+    let custom = db::get_agent_prompt_override(agent.as_str(), mode).map_err(|e| e.to_string())?;
+    let default_prompt = orchestrator::default_agent_prompt(agent, is_disco).to_string();
+    Ok(AgentPromptInfo {
+        agent: agent.as_str().to_string(),
+        mode: mode.to_string(),
+        is_custom: custom.is_some(),
+        prompt: custom.unwrap_or_else(|| default_prompt.clone()),
+        default_prompt,
+    })
+}
+
+#[tauri::command]
+fn set_agent_prompt(agent: String, prompt: String, mode: Option<String>) -> Result<(), String> {
+    let (agent, is_disco) = parse_prompt_target(&agent, mode.as_deref())?;
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Err("Prompt cannot be empty".to_string());
+    }
+    if prompt.chars().count() > orchestrator::MAX_AGENT_PROMPT_CHARS {
+        return Err(format!("Prompt is too long (max {} characters)", orchestrator::MAX_AGENT_PROMPT_CHARS));
+    }
+    let mode = orchestrator::prompt_mode(is_disco);
+    db::set_agent_prompt_override(agent.as_str(), mode, prompt).map_err(|e| e.to_string())?;
+    logging::log_agent(None, &format!("Custom {} prompt saved for {} ({} chars)", mode, agent.as_str(), prompt.chars().count()));
+    Ok(())
+}
+
+/// Drop an agent's custom prompt and go back to the built-in one (history is kept)
+#[tauri::command]
+fn reset_agent_prompt(agent: String, mode: Option<String>) -> Result<bool, String> {
+    let (agent, is_disco) = parse_prompt_target(&agent, mode.as_deref())?;
+    let mode = orchestrator::prompt_mode(is_disco);
+    let removed = db::delete_agent_prompt_override(agent.as_str(), mode).map_err(|e| e.to_string())?;
+    if removed {
+        logging::log_agent(None, &format!("{} {} prompt reset to default", agent.as_str(), mode));
+    }
+    Ok(removed)
+}
+
+/// Every saved version of an agent's prompt in a mode, newest first
+#[tauri::command]
+fn get_agent_prompt_history(agent: String, mode: Option<String>) -> Result<Vec<db::AgentPromptVersion>, String> {
+    let (agent, is_disco) = parse_prompt_target(&agent, mode.as_deref())?;
+    db::get_agent_prompt_versions(agent.as_str(), orchestrator::prompt_mode(is_disco)).map_err(|e| e.to_string())
+}
+
+/// Make an earlier version the active prompt again. The rollback is itself saved as a
+/// new version, so it can be undone the same way.
+#[tauri::command]
+fn rollback_agent_prompt(version_id: i64) -> Result<db::AgentPromptVersion, String> {
+    let version = db::get_agent_prompt_version(version_id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Prompt version {} not found", version_id))?;
+    db::set_agent_prompt_override(&version.agent, &version.mode, &version.prompt).map_err(|e| e.to_string())?;
+    logging::log_agent(None, &format!(
+        "{} {} prompt rolled back to version {} ({})", version.agent, version.mode, version.id, version.created_at
+    ));
+    Ok(version)
+}
+
+// ============ Usage & Budgets ============
+
+/// Spend this month and budget state for every provider
+#[tauri::command]
+fn get_budget_status() -> Vec<usage::BudgetStatus> {
+    providers::PROVIDERS.iter()
+        .map(|provider| usage::budget_status(provider))
+        .collect()
+}
+
+/// Set a provider's monthly budget in USD; a limit of None removes it.
+/// Past the limit, `degrade` switches to the provider's cheaper model and `skip_optional`
+/// pauses memory extraction and summaries until the next month.
+#[tauri::command]
+fn set_spend_budget(provider: String, monthly_limit_usd: Option<f64>, degrade: bool, skip_optional: bool) -> Result<(), String> {
+    if !providers::PROVIDERS.contains(&provider.as_str()) {
+        return Err(format!("Unknown provider: {}", provider));
+    }
+    let budget = monthly_limit_usd.map(|monthly_limit_usd| usage::SpendBudget { monthly_limit_usd, degrade, skip_optional });
+    usage::set_budget(&provider, budget)?;
+    logging::log_routing(None, &match monthly_limit_usd {
+        Some(limit) => format!("{} budget set to ${:.2}/month", provider, limit),
+        None => format!("{} budget removed", provider),
+    });
+    Ok(())
+}
+
+/// Calls, tokens and estimated cost per model since `since` (RFC 3339; default: start of this month)
+#[tauri::command]
+fn get_api_usage(since: Option<String>) -> Result<Vec<db::UsageTotal>, String> {
+    let since = since.unwrap_or_else(usage::month_start);
+    db::get_usage_totals(&since).map_err(|e| e.to_string())
+}
+
+// ============ Session Reflections ============
+
+fn reflection_prompts_enabled() -> bool {
+    db::get_setting("reflection_prompts")
+        .ok()
+        .flatten()
+        .map(|v| v != "false")
+        .unwrap_or(true)
+}
+
+#[tauri::command]
+fn get_reflection_prompts_enabled() -> Result<bool, String> {
+    Ok(reflection_prompts_enabled())
+}
+
+#[tauri::command]
+fn set_reflection_prompts_enabled(enabled: bool) -> Result<(), String> {
+    db::set_setting("reflection_prompts", if enabled { "true" } else { "false" })
+        .map_err(|e| e.to_string())
+}
+
+/// The reflection left by the last finished conversation, if it hasn't been shown yet.
+/// Returned once - fetching it marks it surfaced.
+#[tauri::command]
+fn get_pending_reflection() -> Result<Option<db::SessionReflection>, String> {
+    if !reflection_prompts_enabled() {
+        return Ok(None);
+    }
+    let reflection = db::get_pending_reflection().map_err(|e| e.to_string())?;
+    if reflection.is_some() {
+        db::mark_reflections_surfaced().map_err(|e| e.to_string())?;
+    }
+    Ok(reflection)
+}
+
+// ============ System Notices ============
+
+/// Persist a Governor notice in the transcript and push it to the open window
+fn record_system_notice(app_handle: &tauri::AppHandle, conversation_id: &str, kind: &str, content: &str, payload: serde_json::Value) {
+    match db::save_system_message(conversation_id, kind, content, &payload) {
+        Ok(message) => {
+            let _ = app_handle.emit("system-notice", &message);
+        }
+        Err(e) => logging::log_error(Some(conversation_id), &format!("Failed to record {} notice: {}", kind, e)),
+    }
+}
+
+/// Record a mode_change notice when this turn runs in a different mode than the last one
+fn record_mode_change(app_handle: &tauri::AppHandle, conversation_id: &str, mode: &str) {
+    let previous_mode = db::get_system_notices(conversation_id, Some("mode_change")).ok()
+        .and_then(|notices| notices.last().and_then(|n| n.payload["mode"].as_str().map(|m| m.to_string())))
+        .unwrap_or_else(|| "standard".to_string());
+    if previous_mode == mode {
+        return;
     }
     
-    // ===== MEMORY SYSTEM: Build User Profile =====
-    let user_profile = MemoryExtractor::build_profile_summary().ok();
-    
-    // Get existing facts for extraction context
-    let existing_facts = db::get_all_user_facts().unwrap_or_default();
-    
-    // Save user message
-    let user_msg = Message {
-        id: Uuid::new_v4().to_string(),
-        conversation_id: conversation_id.clone(),
-        role: "user".to_string(),
-        content: user_message.clone(),
-        response_type: None,
-        references_message_id: None,
-        timestamp: Utc::now().to_rfc3339(),
+    let content = match mode {
+        "game" => "Game mode -- every voice is in disco.",
+        "composite" => "Composite mode -- the agents draft together and I answer once.",
+        "disco" => "Disco mode -- some voices are running hotter.",
+        _ => "Back to standard mode.",
     };
-    db::save_message(&user_msg).map_err(|e| e.to_string())?;
+    record_system_notice(app_handle, conversation_id, "mode_change", content, serde_json::json!({
+        "mode": mode,
+        "previous_mode": previous_mode,
+    }));
+}
+
+#[tauri::command]
+fn get_system_notices(conversation_id: String, kind: Option<String>) -> Result<Vec<db::SystemNotice>, String> {
+    db::get_system_notices(&conversation_id, kind.as_deref()).map_err(|e| e.to_string())
+}
+
+// Helper to generate weight change notification
+fn generate_weight_notification(
+    old_weights: (f64, f64, f64),
+    new_weights: (f64, f64, f64),
+    primary_agent: &str,
+    had_secondary: bool,
+) -> Option<WeightChangeNotification> {
+    let old_dominant = get_dominant_agent(old_weights);
+    let new_dominant = get_dominant_agent(new_weights);
     
-    // Get recent messages for context
-    let recent_messages = db::get_recent_messages(&conversation_id, 20).map_err(|e| e.to_string())?;
+    // Calculate total weight shift
+    let total_shift = (new_weights.0 - old_weights.0).abs() 
+        + (new_weights.1 - old_weights.1).abs() 
+        + (new_weights.2 - old_weights.2).abs();
     
-    // Create orchestrator (OpenAI for agents only - routing is now heuristic-based)
-    let orchestrator = Orchestrator::new(&api_key, &anthropic_key);
+    // Only notify on significant changes
+    if total_shift < 0.01 {
+        return None;
+    }
     
-    // Helper to check if an agent is in disco mode
-    let is_agent_disco = |agent: &str| -> bool {
-        disco_agents.iter().any(|a| a == agent)
+    let agent_name = match primary_agent {
+        "instinct" => "Snap",
+        "logic" => "Dot", 
+        "psyche" => "Puff",
+        _ => primary_agent,
     };
-    let has_any_disco = !disco_agents.is_empty();
-    let is_game_mode = disco_agents.len() == active_agents.len() && disco_agents.len() >= 3; // All 3 agents in disco = game mode
     
-    // ===== GAME MODE: Use dynamic multi-turn thoughts =====
-    if is_game_mode {
-        logging::log_routing(Some(&conversation_id), "Game mode - using dynamic multi-turn thoughts");
-        
-        // Generate dynamic thoughts (1-4 turns, weighted selection, no same agent twice in a row)
-        let thought_responses = orchestrator
-            .generate_dynamic_thoughts(
-                &user_message,
-                &recent_messages,
-                routing_weights,
-                user_profile.as_ref(),
-                true, // is_disco = true for game mode
-            )
-            .await
-            .map_err(|e| e.to_string())?;
-        
-        let mut responses = Vec::new();
-        
-        // Save and format responses
-        for (idx, (agent_str, content)) in thought_responses.iter().enumerate() {
-            let msg = Message {
-                id: Uuid::new_v4().to_string(),
-                conversation_id: conversation_id.clone(),
-                role: agent_str.clone(),
-                content: content.clone(),
-                response_type: Some(if idx == 0 { "primary" } else { "addition" }.to_string()),
-                references_message_id: None,
-                timestamp: Utc::now().to_rfc3339(),
-            };
-            db::save_message(&msg).map_err(|e| e.to_string())?;
-            
-            responses.push(AgentResponse {
-                agent: agent_str.clone(),
-                content: content.clone(),
-                response_type: if idx == 0 { "primary" } else { "addition" }.to_string(),
-                references_message_id: None,
-            });
-            
-            // Boost session weight for agents who responded
-            if let Some(agent) = Agent::from_str(agent_str) {
-                boost_session_weight(&conversation_id, agent, 0.015);
-            }
-        }
-        
-        // Get journey session phase for Game Mode
-        let journey_phase = db::get_journey_session_by_conversation(&conversation_id)
-            .ok()
-            .flatten()
-            .map(|s| s.phase);
-        
-        // Generate Governor response based on thoughts
-        let governor_text = generate_governor_response(
-            &anthropic_key,
-            &user_message,
-            &thought_responses,
-            &recent_messages,
-            true, // is_disco
-            user_profile.as_ref(),
-            Some(active_persona.dominant_trait.as_str()),
-            journey_phase.as_deref(), // Pass journey phase for Game Mode
-        ).await.map_err(|e| e.to_string())?;
-        
-        // Save Governor message
-        let gov_msg = Message {
-            id: Uuid::new_v4().to_string(),
-            conversation_id: conversation_id.clone(),
-            role: "governor".to_string(),
-            content: governor_text.clone(),
-            response_type: Some("governor".to_string()),
-            references_message_id: None,
-            timestamp: Utc::now().to_rfc3339(),
+    let (change_type, message) = if old_dominant != new_dominant {
+        // Major shift - dominant agent changed
+        let old_name = match old_dominant {
+            "instinct" => "Instinct",
+            "logic" => "Logic",
+            "psyche" => "Psyche",
+            _ => old_dominant,
         };
-        db::save_message(&gov_msg).map_err(|e| e.to_string())?;
-        
-        return Ok(SendMessageResult {
-            responses,
-            debate_mode: Some("game".to_string()),
-            weight_change: None,
-            governor_response: Some(governor_text),
-        });
-    }
-    
-    // ===== TEXT MODE: Standard routing =====
-    // Use heuristic grounding (instant, no API call)
-    let grounding = user_profile.as_ref().map(|profile| {
-        decide_grounding_heuristic(&user_message, &recent_messages, Some(profile))
-    });
-    
-    // Use heuristic routing with combined base + session weights, points, and dominant trait
-    let decision = decide_response_heuristic(
-        &user_message, 
-        routing_weights, 
-        &active_agents,
-        &recent_messages,
-        has_any_disco,
-        Some(points),
-        dominant_trait,
-    );
-    
-    let mut responses = Vec::new();
-    let mut debate_mode: Option<String> = None;
-    let mut agents_involved = Vec::new();
-    
-    // Get primary agent response with grounding
-    let primary_agent = Agent::from_str(&decision.primary_agent)
-        .ok_or_else(|| format!("Invalid agent: {}", decision.primary_agent))?;
-    agents_involved.push(primary_agent.as_str().to_string());
-    
+        let new_name = match new_dominant {
+            "instinct" => "Instinct",
+            "logic" => "Logic", 
+            "psyche" => "Psyche",
+            _ => new_dominant,
+        };
+        (
+            "major_shift".to_string(),
+            format!("Your dominant trait has shifted from {} to {}. This conversation resonated more with {}.", old_name, new_name, agent_name)
+        )
+    } else if total_shift > 0.03 {
+        // Notable shift within same dominant
+        let direction = if new_weights.0 > old_weights.0 {
+            "Instinct"
+        } else if new_weights.1 > old_weights.1 {
+            "Logic"
+        } else {
+            "Psyche"
+        };
+        (
+            "shift".to_string(),
+            format!("Your {} weight increased slightly. {} guided this exchange.", direction, agent_name)
+        )
+    } else {
+        // Minor adjustment
+        (
+            "minor".to_string(),
+            format!("Weights adjusted based on {} taking the lead{}.", agent_name, if had_secondary { " with support" } else { "" })
+        )
+    };
+    
+    Some(WeightChangeNotification {
+        message,
+        old_dominant: old_dominant.to_string(),
+        new_dominant: new_dominant.to_string(),
+        change_type,
+    })
+}
+
+// ============ Knowledge Base ============
+
+/// Add a PDF, markdown or text file to the knowledge base agents draw on
+#[tauri::command]
+async fn ingest_document(path: String) -> Result<db::Document, String> {
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    documents::ingest(std::path::Path::new(&path), profile.anthropic_key.as_deref(), profile.api_key.as_deref()).await
+}
+
+#[tauri::command]
+fn get_documents() -> Result<Vec<db::Document>, String> {
+    db::get_documents().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_document(document_id: String) -> Result<bool, String> {
+    let removed = db::delete_document(&document_id).map_err(|e| e.to_string())?;
+    if removed {
+        logging::log_memory(None, &format!("Deleted document {}", document_id));
+    }
+    Ok(removed)
+}
+
+// ============ Attachments ============
+
+/// Attach a file (path or raw bytes) to the message being composed. It stays pending until
+/// send_message is called with its id in `attachment_ids`.
+#[tauri::command]
+async fn add_attachment(
+    app_handle: tauri::AppHandle,
+    conversation_id: String,
+    path: Option<String>,
+    bytes: Option<Vec<u8>>,
+    file_name: Option<String>,
+) -> Result<db::Attachment, String> {
+    let (data, name) = match (path, bytes) {
+        (Some(path), _) => {
+            let data = tokio::fs::read(&path).await.map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+            let name = std::path::Path::new(&path).file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (data, file_name.unwrap_or(name))
+        }
+        (None, Some(bytes)) => (bytes, file_name.ok_or("A file name is needed to tell the format")?),
+        (None, None) => return Err("Pass a path or the file's bytes".to_string()),
+    };
+    let attachment = attachments::store(&app_handle, &conversation_id, &name, &data)?;
+    logging::log_conversation(Some(&conversation_id), &format!(
+        "Attached {} ({}, {} bytes)", attachment.file_name, attachment.mime_type, attachment.size_bytes
+    ));
+    Ok(attachment)
+}
+
+/// Remove an attachment and its stored file
+#[tauri::command]
+fn remove_attachment(attachment_id: String) -> Result<bool, String> {
+    let removed = db::delete_attachment(&attachment_id).map_err(|e| e.to_string())?;
+    if let Some(attachment) = &removed {
+        attachments::remove_files(std::slice::from_ref(attachment));
+    }
+    Ok(removed.is_some())
+}
+
+#[tauri::command]
+fn get_message_attachments(message_id: String) -> Result<Vec<db::Attachment>, String> {
+    db::get_message_attachments(&message_id).map_err(|e| e.to_string())
+}
+
+// ============ Agent Tools ============
+
+/// Tools agents can call while replying, with whether each is on
+#[tauri::command]
+async fn get_agent_tools() -> Vec<tools::ToolInfo> {
+    tools::list_tools().await
+}
+
+#[tauri::command]
+fn set_agent_tool_enabled(name: String, enabled: bool) -> Result<(), String> {
+    tools::set_tool_enabled(&name, enabled)?;
+    logging::log_agent(None, &format!("Tool {} {}", name, if enabled { "enabled" } else { "disabled" }));
+    Ok(())
+}
+
+// ============ Local API ============
+
+#[tauri::command]
+fn get_local_api_settings() -> api::ApiSettings {
+    api::get_settings()
+}
+
+/// Turn the localhost automation API on or off (a token is generated the first time)
+#[tauri::command]
+async fn set_local_api_enabled(app_handle: tauri::AppHandle, enabled: bool, port: Option<u16>) -> Result<api::ApiSettings, String> {
+    api::configure(app_handle, enabled, port).await
+}
+
+#[tauri::command]
+async fn regenerate_local_api_token() -> Result<api::ApiSettings, String> {
+    api::regenerate_token()
+}
+
+// ============ Calendar Context ============
+
+#[tauri::command]
+fn get_calendar_settings() -> calendar::CalendarSettings {
+    calendar::get_settings()
+}
+
+#[tauri::command]
+fn set_calendar_settings(settings: calendar::CalendarSettings) -> Result<(), String> {
+    calendar::set_settings(&settings)?;
+    logging::log_conversation(None, &format!(
+        "Calendar context {} ({})", if settings.enabled { "on" } else { "off" }, settings.source
+    ));
+    Ok(())
+}
+
+/// Today's events as the agents would see them (also how the user previews and grants access)
+#[tauri::command]
+async fn get_today_events() -> Result<Vec<calendar::CalendarEvent>, String> {
+    calendar::today_events().await
+}
+
+// ============ Quick Capture ============
+
+#[tauri::command]
+fn get_capture_settings() -> capture::CaptureSettings {
+    capture::get_settings()
+}
+
+/// Change the capture hotkey or mode (the hotkey is re-registered right away)
+#[tauri::command]
+fn set_capture_settings(app_handle: tauri::AppHandle, settings: capture::CaptureSettings) -> Result<capture::CaptureSettings, String> {
+    capture::set_settings(&app_handle, &settings)
+}
+
+/// Append text from the capture window to the Inbox conversation without opening the main window
+#[tauri::command]
+fn quick_capture(app_handle: tauri::AppHandle, text: String) -> Result<capture::CaptureResult, String> {
+    capture::capture(&app_handle, &text)
+}
+
+// ============ Webhooks ============
+
+#[tauri::command]
+fn get_webhooks() -> Vec<webhooks::Webhook> {
+    webhooks::get_webhooks()
+}
+
+/// Register a URL for memory/report events; no events means all of them
+#[tauri::command]
+fn add_webhook(url: String, events: Option<Vec<String>>, secret: Option<String>) -> Result<webhooks::Webhook, String> {
+    let webhook = webhooks::add_webhook(&url, events.unwrap_or_default(), secret)?;
+    logging::log_conversation(None, &format!("Added webhook {}", webhook.url));
+    Ok(webhook)
+}
+
+#[tauri::command]
+fn remove_webhook(id: String) -> Result<(), String> {
+    if !webhooks::remove_webhook(&id)? {
+        return Err("Webhook not found".to_string());
+    }
+    Ok(())
+}
+
+/// Send a ping to a webhook and return the HTTP status it answered with
+#[tauri::command]
+async fn test_webhook(id: String) -> Result<u16, String> {
+    webhooks::test(&id).await
+}
+
+// ============ MCP Servers ============
+
+#[tauri::command]
+fn get_mcp_servers() -> Vec<mcp::McpServerConfig> {
+    mcp::get_servers()
+}
+
+/// Add or update an MCP server (e.g. {"name": "notes", "command": "npx",
+/// "args": ["-y", "@modelcontextprotocol/server-filesystem", "/Users/me/Notes"]})
+#[tauri::command]
+async fn save_mcp_server(config: mcp::McpServerConfig) -> Result<(), String> {
+    let name = config.name.clone();
+    mcp::save_server(config).await?;
+    logging::log_agent(None, &format!("Saved MCP server {}", name));
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_mcp_server(name: String) -> Result<bool, String> {
+    mcp::remove_server(&name).await
+}
+
+/// Connect to a server and list its tools and resources
+#[tauri::command]
+async fn get_mcp_server_info(name: String) -> Result<mcp::McpServerInfo, String> {
+    mcp::server_info(&name).await
+}
+
+// ============ Web Search ============
+
+#[tauri::command]
+fn get_search_settings() -> search::SearchSettings {
+    search::get_settings()
+}
+
+/// Set the search provider ("brave" | "serpapi", or none to turn web search off) and its key
+#[tauri::command]
+fn set_search_settings(settings: search::SearchSettings) -> Result<(), String> {
+    search::set_settings(&settings)?;
+    logging::log_routing(None, &format!(
+        "Web search {}", settings.provider.as_deref().map(|p| format!("set to {}", p)).unwrap_or_else(|| "turned off".to_string())
+    ));
+    Ok(())
+}
+
+// ============ Voice Input ============
+
+/// Transcribe a dictated message from a file path or raw bytes. The text is returned for the
+/// user to review; send it with send_message(voice: true) to mark it as voice-origin.
+#[tauri::command]
+async fn transcribe_audio(path: Option<String>, bytes: Option<Vec<u8>>, file_name: Option<String>) -> Result<voice::Transcription, String> {
+    let (audio, name) = match (path, bytes) {
+        (Some(path), _) => {
+            let audio = tokio::fs::read(&path).await.map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+            let name = std::path::Path::new(&path).file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "audio.wav".to_string());
+            (audio, file_name.unwrap_or(name))
+        }
+        (None, Some(bytes)) => (bytes, file_name.unwrap_or_else(|| "audio.webm".to_string())),
+        (None, None) => return Err("Pass a path or the recorded bytes".to_string()),
+    };
+    
+    let openai_key = db::run_blocking(db::get_user_profile).await.ok().and_then(Result::ok).and_then(|p| p.api_key);
+    let transcription = voice::transcribe(audio, &name, openai_key.as_deref()).await?;
+    logging::log_conversation(None, &format!(
+        "Transcribed {} ({} chars, {})", name, transcription.text.chars().count(), transcription.engine
+    ));
+    Ok(transcription)
+}
+
+#[tauri::command]
+fn get_voice_settings() -> voice::VoiceSettings {
+    voice::get_settings()
+}
+
+/// Point transcription at a local whisper.cpp binary and model (None for either = API only)
+#[tauri::command]
+fn set_voice_settings(settings: voice::VoiceSettings) -> Result<(), String> {
+    voice::set_settings(&settings)
+}
+
+// ============ Context Window ============
+
+#[tauri::command]
+fn get_context_settings() -> context::ContextSettings {
+    context::get_settings()
+}
+
+/// How many tokens of history each agent call carries, and whether the rolling summary covers the rest
+#[tauri::command]
+fn set_context_settings(settings: context::ContextSettings) -> Result<(), String> {
+    context::set_settings(&settings)
+}
+
+/// Tokens the next turn would carry, with the draft being typed, so the UI can warn about cost or truncation
+#[tauri::command]
+fn estimate_tokens(conversation_id: String, draft: Option<String>) -> Result<context::TokenEstimate, String> {
+    context::estimate(&conversation_id, draft.as_deref())
+}
+
+// ============ Language ============
+
+#[tauri::command]
+fn get_language_settings() -> language::LanguageSettings {
+    language::get_settings()
+}
+
+/// "auto" follows the language of each message; a code ("es", "fr", ...) pins one
+#[tauri::command]
+fn set_language_settings(settings: language::LanguageSettings) -> Result<(), String> {
+    language::set_settings(&settings)?;
+    logging::log_conversation(None, &format!("Language set to {}", settings.language));
+    Ok(())
+}
+
+// ============ Debate Settings ============
+
+#[tauri::command]
+fn get_debate_settings() -> debate::DebateSettings {
+    debate::get_settings()
+}
+
+/// How long the voices may keep going after the secondary response, and whether they
+/// debate outside disco mode
+#[tauri::command]
+fn set_debate_settings(settings: debate::DebateSettings) -> Result<(), String> {
+    debate::set_settings(&settings)?;
+    logging::log_agent(None, &format!(
+        "Debate settings: max {} responses, bias {:+.2}, outside disco {}",
+        settings.max_responses, settings.continue_bias, settings.allow_outside_disco
+    ));
+    Ok(())
+}
+
+// ============ Composite Mode ============
+// All active agents draft internally; only one Governor answer is shown and stored
+
+/// "chat" (default, every agent visible) | "composite" (single synthesized answer)
+fn load_response_mode() -> String {
+    db::get_setting("response_mode")
+        .ok()
+        .flatten()
+        .filter(|m| m == "chat" || m == "composite")
+        .unwrap_or_else(|| "chat".to_string())
+}
+
+#[tauri::command]
+fn get_response_mode() -> Result<String, String> {
+    Ok(load_response_mode())
+}
+
+#[tauri::command]
+fn set_response_mode(mode: String) -> Result<(), String> {
+    if mode != "chat" && mode != "composite" {
+        return Err(format!("Invalid response mode: {}", mode));
+    }
+    db::set_setting("response_mode", &mode).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeDraft {
+    pub agent: String,
+    pub content: String,
+}
+
+/// Hidden drafts behind a composite answer
+#[tauri::command]
+fn get_composite_drafts(message_id: String) -> Result<Vec<CompositeDraft>, String> {
+    let metadata = db::get_message_metadata(&message_id).map_err(|e| e.to_string())?;
+    Ok(metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .and_then(|v| serde_json::from_value(v["drafts"].clone()).ok())
+        .unwrap_or_default())
+}
+
+/// Synthesize one answer from the agents' drafts, with a brief attributed viewpoint line each
+async fn generate_composite_answer(
+    anthropic_key: &str,
+    user_message: &str,
+    drafts: &[CompositeDraft],
+    conversation_history: &[Message],
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
+    
+    let drafts_text = drafts.iter()
+        .map(|d| format!("[{}] {}", d.agent.to_uppercase(), d.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    
+    let recent_context = conversation_history.iter()
+        .rev()
+        .take(6)
+        .rev()
+        .filter(|m| m.role == "user" || m.role == "governor")
+        .map(|m| format!("{}: {}", if m.role == "user" { "User" } else { "You" }, m.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let mut system_prompt = r#"You are the Governor of Intersect. Three internal perspectives -- Instinct (gut/action), Logic (analysis), Psyche (emotion/meaning) -- have each drafted a reply. The user only sees what you write.
+
+Write ONE answer:
+- 2-4 sentences that merge the strongest points into a single, direct reply
+- Then a blank line and a short "Viewpoints" line: one brief clause per perspective that drafted, attributed by name (e.g. "Instinct: ... / Logic: ... / Psyche: ...")
+- Don't mention drafts, agents, or this process
+- When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")"#.to_string();
+    
+    // Answer in the user's language, like the drafts do
+    let language_samples: Vec<&str> = std::iter::once(user_message)
+        .chain(conversation_history.iter().rev().filter(|m| m.role == "user").take(3).map(|m| m.content.as_str()))
+        .collect();
+    if let Some(instruction) = language::reply_instruction(&language_samples) {
+        system_prompt = format!("{}\n\n{}", system_prompt, instruction);
+    }
+    
+    let user_prompt = format!(
+        "RECENT CONVERSATION:\n{}\n\nUSER: {}\n\nDRAFTS:\n{}\n\nWrite the answer:",
+        if recent_context.is_empty() { "New conversation.".to_string() } else { recent_context },
+        user_message,
+        drafts_text
+    );
+    
+    let client = AnthropicClient::new(anthropic_key);
+    client.chat_completion_advanced(
+        CLAUDE_HAIKU,
+        Some(&system_prompt),
+        vec![AnthropicMessage { role: "user".to_string(), content: user_prompt }],
+        0.6,
+        Some(350),
+        ThinkingBudget::None
+    ).await
+}
+
+// ============ Send Message (Core Turn-Taking with Memory) ============
+
+/// Longest stretch of a quoted message carried into the turn (routing and agent prompts alike)
+const MAX_QUOTE_CHARS: usize = 500;
+
+/// The quoted message, cut at MAX_QUOTE_CHARS so a long reply doesn't swamp the user's own words
+fn quote_excerpt(content: &str) -> String {
+    if content.chars().count() > MAX_QUOTE_CHARS {
+        format!("{}…", content.chars().take(MAX_QUOTE_CHARS).collect::<String>())
+    } else {
+        content.to_string()
+    }
+}
+
+/// reply_to_message_id quotes an earlier agent message: that agent answers first, with the quote in view
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn send_message(
+    app_handle: tauri::AppHandle,
+    conversation_id: String,
+    user_message: String,
+    active_agents: Option<Vec<String>>,
+    disco_agents: Vec<String>,
+    voice: Option<bool>,
+    attachment_ids: Option<Vec<String>>,
+    reply_to_message_id: Option<String>,
+) -> Result<SendMessageResult, String> {
+    let attachment_ids = attachment_ids.unwrap_or_default();
+    let voice = voice.unwrap_or(false);
+    let id = conversation_id.clone();
+    let reply_to = reply_to_message_id.clone();
+    let (is_journal, preset, quoted) = db::run_blocking(move || -> rusqlite::Result<_> {
+        let quoted = match &reply_to {
+            Some(reply_to) => db::get_message(reply_to)?,
+            None => None,
+        };
+        Ok((
+            db::is_journal_conversation(&id).unwrap_or(false),
+            db::get_conversation_agents(&id).ok().flatten(),
+            quoted,
+        ))
+    }).await?.map_err(|e| e.to_string())?;
+    // A journal always talks to its one agent; otherwise no agents named means the preset, or everyone
+    let active_agents = if is_journal {
+        preset.clone().or(active_agents)
+    } else {
+        active_agents
+    };
+    let mut active_agents = active_agents
+        .or(preset)
+        .unwrap_or_else(|| [Agent::Instinct, Agent::Logic, Agent::Psyche].iter().map(|a| a.as_str().to_string()).collect());
+    
+    // The agent being replied to is in the turn even if the preset leaves it out
+    if reply_to_message_id.is_some() {
+        let quoted = quoted
+            .filter(|m| m.conversation_id == conversation_id)
+            .ok_or("The message being replied to isn't in this conversation")?;
+        let agent = Agent::from_str(&quoted.role).ok_or("Only agent messages can be replied to")?;
+        if !active_agents.iter().any(|a| a == agent.as_str()) {
+            active_agents.push(agent.as_str().to_string());
+        }
+    }
+    
+    // Known to be offline (and still are): queue instead of failing
+    if !offline::is_online() && !offline::refresh(&app_handle).await {
+        return queue_offline_message(
+            &conversation_id, &user_message, &active_agents, &disco_agents, &attachment_ids, reply_to_message_id.as_deref(), voice,
+        ).await;
+    }
+    
+    // Messages queued earlier in this conversation go out first; if any are still waiting
+    // (another flush has them, or they failed again) this one queues behind them
+    if has_sendable_pending_messages(&conversation_id).await {
+        flush_pending_messages_internal(&app_handle, false, Some(&conversation_id)).await;
+        if has_sendable_pending_messages(&conversation_id).await {
+            return queue_offline_message(
+                &conversation_id, &user_message, &active_agents, &disco_agents, &attachment_ids, reply_to_message_id.as_deref(), voice,
+            ).await;
+        }
+    }
+    
+    let id = conversation_id.clone();
+    let start_seq = db::run_blocking(move || db::get_last_seq(&id).unwrap_or(0)).await?;
+    let started_at = Utc::now().to_rfc3339();
+    let user_msg = Message {
+        id: Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.clone(),
+        role: "user".to_string(),
+        content: user_message.clone(),
+        response_type: None,
+        references_message_id: reply_to_message_id.clone(),
+        timestamp: Utc::now().to_rfc3339(),
+        attachments: Vec::new(),
+        starred: false,
+    };
+    let user_msg_id = user_msg.id.clone();
+    // Pending uploads join the message before the turn runs, so the turn can describe them
+    if !attachment_ids.is_empty() {
+        let (message_id, id, ids) = (user_msg_id.clone(), conversation_id.clone(), attachment_ids.clone());
+        db::run_blocking(move || db::link_attachments(&message_id, &id, &ids)).await?.map_err(|e| e.to_string())?;
+    }
+    match run_turn(app_handle.clone(), user_msg, active_agents.clone(), disco_agents.clone()).await {
+        // The connection dropped mid-turn: undo what the turn saved and queue the message instead
+        Err(e) if offline::is_connectivity_error(&e) && !offline::refresh(&app_handle).await => {
+            let message_id = user_msg_id.clone();
+            let attachment_ids = db::run_blocking(move || db::unlink_attachments(&message_id).unwrap_or_default())
+                .await
+                .unwrap_or_default();
+            let _ = roll_back_turn(&conversation_id, start_seq, &started_at).await;
+            queue_offline_message(
+                &conversation_id, &user_message, &active_agents, &disco_agents, &attachment_ids, reply_to_message_id.as_deref(), voice,
+            ).await
+        }
+        result => {
+            // Dictated messages are marked so the transcript can show (and later analysis can weigh) them
+            if result.is_ok() && voice {
+                annotate_message(&user_msg_id, "origin", serde_json::json!("voice"));
+            }
+            result
+        }
+    }
+}
+
+/// Core turn: save the user message, route, generate agent responses, and kick off memory work
+/// Shared by send_message and rerun_turn (which passes an existing user message).
+/// The turn can be aborted with cancel_turn; whatever it saved is then rolled back.
+async fn run_turn(
+    app_handle: tauri::AppHandle,
+    user_msg: Message,
+    active_agents: Vec<String>,
+    disco_agents: Vec<String>,
+) -> Result<SendMessageResult, String> {
+    let conversation_id = user_msg.conversation_id.clone();
+    let user_msg_id = user_msg.id.clone();
+    let disco = !disco_agents.is_empty();
+    let cancel = CancelToken::new();
+    ACTIVE_TURNS.lock().unwrap().insert(conversation_id.clone(), cancel.clone());
+    let started = std::time::Instant::now();
+    
+    // Everything this turn saves gets a seq above this mark, and any reminder a created_at from here
+    let id = conversation_id.clone();
+    let start_seq = db::run_blocking(move || db::get_last_seq(&id).unwrap_or(0)).await?;
+    let started_at = Utc::now().to_rfc3339();
+    
+    let result = run_turn_inner(app_handle, user_msg, active_agents, disco_agents, cancel.clone()).await;
+    
+    if let Ok(turn) = &result {
+        let response_count = turn.responses.len() + usize::from(turn.governor_response.is_some());
+        if response_count > 0 {
+            analytics::record_turn(&conversation_id, &user_msg_id, turn.debate_mode.as_deref(), disco, response_count, started.elapsed());
+        }
+    }
+    
+    {
+        let mut turns = ACTIVE_TURNS.lock().unwrap();
+        if turns.get(&conversation_id).is_some_and(|t| t.same_as(&cancel)) {
+            turns.remove(&conversation_id);
+        }
+    }
+    
+    if cancel.is_cancelled() && result.is_err() {
+        match roll_back_turn(&conversation_id, start_seq, &started_at).await {
+            Ok(removed) => logging::log_conversation(Some(&conversation_id), &format!(
+                "Turn cancelled, rolled back {} saved messages", removed
+            )),
+            Err(e) => logging::log_error(Some(&conversation_id), &format!("Failed to roll back cancelled turn: {}", e)),
+        }
+        return Err(TURN_CANCELLED.to_string());
+    }
+    result
+}
+
+/// Undo what a turn saved: messages after its sequence mark (with their attachments' stored files)
+/// and reminders agents set since it started; returns how many messages were removed
+async fn roll_back_turn(conversation_id: &str, after_seq: i64, started_at: &str) -> Result<usize, String> {
+    let (id, started_at) = (conversation_id.to_string(), started_at.to_string());
+    db::run_blocking(move || -> rusqlite::Result<usize> {
+        let (deleted, removed) = db::delete_messages_after(&id, after_seq)?;
+        attachments::remove_files(&removed);
+        db::delete_pending_reminders(&id, &started_at, None)?;
+        Ok(deleted)
+    }).await?.map_err(|e| e.to_string())
+}
+
+/// Save a message from async code without holding a runtime thread for the write
+async fn save_message_off_runtime(message: &Message) -> Result<(), String> {
+    let message = message.clone();
+    db::run_blocking(move || db::save_message(&message)).await?.map_err(|e| e.to_string())
+}
+
+/// Abort the turn in flight for a conversation; returns false if nothing was running
+#[tauri::command]
+fn cancel_turn(conversation_id: String) -> bool {
+    match ACTIVE_TURNS.lock().unwrap().get(&conversation_id) {
+        Some(token) => {
+            token.cancel();
+            logging::log_conversation(Some(&conversation_id), "Cancel requested for in-flight turn");
+            true
+        }
+        None => false,
+    }
+}
+
+async fn run_turn_inner(
+    app_handle: tauri::AppHandle,
+    user_msg: Message,
+    active_agents: Vec<String>,
+    disco_agents: Vec<String>,
+    cancel: CancelToken,
+) -> Result<SendMessageResult, String> {
+    let conversation_id = user_msg.conversation_id.clone();
+    let user_message = user_msg.content.clone();
+    
+    // Get profile for API keys and weights
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let api_key = profile.api_key.clone(); // None = Anthropic-only mode
+    let anthropic_key = profile.anthropic_key.clone().ok_or("Anthropic API key not set")?;
+    
+    // Get active persona profile for points and dominant trait, and the agents muted in this conversation
+    let id = conversation_id.clone();
+    let (active_persona, muted_agents) = db::run_blocking(move || -> rusqlite::Result<_> {
+        Ok((db::get_active_persona_profile()?, db::get_muted_agents(&id).unwrap_or_default()))
+    }).await?.map_err(|e| e.to_string())?;
+    let active_persona = active_persona.ok_or("No active persona profile")?;
+    let points = (active_persona.instinct_points, active_persona.logic_points, active_persona.psyche_points);
+    let dominant_trait = Some(active_persona.dominant_trait.as_str());
+    
+    // ===== SESSION WEIGHTS: Separate base (persistent) from session (decaying) =====
+    let base_weights = (profile.instinct_weight, profile.logic_weight, profile.psyche_weight);
+    
+    // Decay session weights by 10% per exchange
+    decay_session_weights(&conversation_id);
+    
+    // Get current session weights
+    let session_weights = get_or_init_session_weights(&conversation_id);
+    
+    // Combine base + session for routing
+    let routing_weights = (
+        base_weights.0 + session_weights.0,
+        base_weights.1 + session_weights.1,
+        base_weights.2 + session_weights.2,
+    );
+    
+    // Agents muted in this conversation sit out routing entirely
+    let active_agents: Vec<String> = active_agents.into_iter()
+        .filter(|a| !muted_agents.iter().any(|m| &m.agent == a))
+        .collect();
+    
+    if active_agents.is_empty() {
+        return Ok(SendMessageResult { responses: Vec::new(), debate_mode: None, weight_change: None, governor_response: None, queued: None, routing: None });
+    }
+    
+    // ===== EXPLICIT MEMORY: "remember that ..." is stored before the profile is built, so agents use it right away =====
+    if memory::mentions_remember(&user_message) {
+        match MemoryExtractor::new(&anthropic_key).detect_remember_request(&user_message).await {
+            Ok(Some(fact)) => match remember_fact_internal(&fact.category, &fact.key, &fact.value, Some(&conversation_id)) {
+                Ok(saved) => {
+                    let _ = app_handle.emit("fact-remembered", &saved);
+                }
+                Err(e) => logging::log_error(Some(&conversation_id), &format!("Failed to remember fact: {}", e)),
+            },
+            Ok(None) => {}
+            Err(e) => logging::log_error(Some(&conversation_id), &format!("Remember detection failed: {}", e)),
+        }
+    }
+    
+    // ===== MEMORY SYSTEM: Build User Profile =====
+    let user_profile = MemoryExtractor::build_profile_summary().ok();
+    
+    // Get existing facts for extraction context
+    let existing_facts = db::run_blocking(|| db::get_all_user_facts().unwrap_or_default()).await?;
+    
+    // Note mode switches in the transcript (before the message they apply to)
+    let turn_mode = if disco_agents.len() == active_agents.len() && disco_agents.len() >= 3 {
+        "game"
+    } else if load_response_mode() == "composite" && active_agents.len() >= 2 {
+        "composite"
+    } else if !disco_agents.is_empty() {
+        "disco"
+    } else {
+        "standard"
+    };
+    record_mode_change(&app_handle, &conversation_id, turn_mode);
+    
+    // Save user message
+    save_message_off_runtime(&user_msg).await?;
+    mood::record_message(&user_msg);
+    
+    // What the user wrote, for memory, analysis and affinity; the forms below are for agent and routing prompts
+    let raw_user_message = user_message.clone();
+    
+    // ===== QUOTE-REPLY: A reply to an earlier agent message goes to that agent, with the quote in view =====
+    let quoted = match user_msg.references_message_id.clone() {
+        Some(id) => db::run_blocking(move || db::get_message(&id).ok().flatten()).await?,
+        None => None,
+    };
+    let quoted = quoted
+        .filter(|m| m.conversation_id == conversation_id && Agent::from_str(&m.role).is_some());
+    let user_message = match &quoted {
+        Some(quoted) => format!(
+            "[Replying to {}'s earlier message: \"{}\"]\n\n{}",
+            quoted.role.to_uppercase(), quote_excerpt(&quoted.content), user_message
+        ),
+        None => user_message,
+    };
+    
+    // ===== ATTACHMENTS: A vision model describes attached images once, so every agent can react to them =====
+    let user_message = match attachments::image_context(&user_msg.id, &user_message, &anthropic_key, api_key.as_deref()).await {
+        Some(context) => format!("{}\n\n{}", user_message, context),
+        None => user_message,
+    };
+    
+    // ===== LINKS: Pages the user linked are fetched and summarized, so routing and agents see what they say =====
+    let link_summaries = links::summarize_links(&conversation_id, &user_msg.content, &anthropic_key).await;
+    let user_message = match links::link_context(&link_summaries) {
+        Some(context) => {
+            annotate_message(&user_msg.id, "links", serde_json::json!(link_summaries));
+            logging::log_routing(Some(&conversation_id), &format!("Summarized {} linked page(s)", link_summaries.len()));
+            format!("{}\n\n{}", user_message, context)
+        }
+        None => user_message,
+    };
+    
+    // Get recent messages for context: as many as the history budget can use
+    let history_budget = context::get_settings().history_token_budget;
+    let id = conversation_id.clone();
+    let mut recent_messages = db::run_blocking(move || {
+        context::fetch_history(history_budget, |limit| db::get_recent_messages(&id, limit))
+    }).await?.map_err(|e| e.to_string())?;
+    attachments::describe_history(&mut recent_messages, Some(&user_msg.id));
+    
+    // Create orchestrator (OpenAI for agents only - routing is now heuristic-based)
+    let mut orchestrator = Orchestrator::new(api_key.as_deref(), &anthropic_key);
+    orchestrator.set_cancel_token(cancel.clone());
+    apply_agent_providers(&mut orchestrator, &profile);
+    orchestrator.set_tools(tools::enabled_tools().await, &conversation_id);
+    
+    // A model pinned on the conversation overrides the default agent model; agents back from a mute
+    // acknowledge the gap instead of pretending they were there
+    let id = conversation_id.clone();
+    let (model_override, returning_agents) = db::run_blocking(move || (
+        db::get_conversation(&id).ok().flatten().and_then(|c| c.model_override),
+        db::get_unacknowledged_returns(&id).unwrap_or_default(),
+    )).await?;
+    orchestrator.set_model_override(model_override);
+    
+    for returning in &returning_agents {
+        if let Some(agent) = Agent::from_str(&returning.agent) {
+            orchestrator.add_agent_note(agent, return_from_mute_note(returning));
+        }
+    }
+    
+    // Passages from ingested documents that relate to this message (needs OpenAI embeddings)
+    if let Some(key) = api_key.as_deref() {
+        if let Some(note) = documents::grounding_note(key, &user_message).await {
+            for agent_name in &active_agents {
+                if let Some(agent) = Agent::from_str(agent_name) {
+                    orchestrator.add_agent_note(agent, note.clone());
+                }
+            }
+        }
+    }
+    
+    // Learned response-style preferences shape each agent's form, not just its content
+    let (persona_id, agents) = (active_persona.id.clone(), active_agents.clone());
+    let style_preferences = db::run_blocking(move || {
+        agents.iter()
+            .filter_map(|name| Agent::from_str(name))
+            .map(|agent| (agent, db::get_agent_style_preferences(&persona_id, agent.as_str()).unwrap_or_default()))
+            .collect::<Vec<_>>()
+    }).await?;
+    for (agent, prefs) in style_preferences {
+        if let Some(note) = format_style_note(&prefs) {
+            orchestrator.add_agent_note(agent, note);
+        }
+    }
+    
+    // Helper to check if an agent is in disco mode
+    let is_agent_disco = |agent: &str| -> bool {
+        disco_agents.iter().any(|a| a == agent)
+    };
+    let has_any_disco = !disco_agents.is_empty();
+    let is_game_mode = disco_agents.len() == active_agents.len() && disco_agents.len() >= 3; // All 3 agents in disco = game mode
+    
+    // ===== GAME MODE: Use dynamic multi-turn thoughts =====
+    if is_game_mode {
+        logging::log_routing(Some(&conversation_id), "Game mode - using dynamic multi-turn thoughts");
+        
+        // Generate dynamic thoughts (1-4 turns, weighted selection, no same agent twice in a row)
+        let thought_responses = orchestrator
+            .generate_dynamic_thoughts(
+                &user_message,
+                &recent_messages,
+                routing_weights,
+                user_profile.as_ref(),
+                true, // is_disco = true for game mode
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        
+        let mut responses = Vec::new();
+        
+        // Save and format responses
+        for (idx, (agent_str, content)) in thought_responses.iter().enumerate() {
+            let msg = Message {
+                id: Uuid::new_v4().to_string(),
+                conversation_id: conversation_id.clone(),
+                role: agent_str.clone(),
+                content: content.clone(),
+                response_type: Some(if idx == 0 { "primary" } else { "addition" }.to_string()),
+                references_message_id: None,
+                timestamp: Utc::now().to_rfc3339(),
+                attachments: Vec::new(),
+                starred: false,
+            };
+            save_message_off_runtime(&msg).await?;
+            
+            responses.push(AgentResponse {
+                agent: agent_str.clone(),
+                content: content.clone(),
+                response_type: if idx == 0 { "primary" } else { "addition" }.to_string(),
+                references_message_id: None,
+            });
+            
+            // Boost session weight for agents who responded
+            if let Some(agent) = Agent::from_str(agent_str) {
+                boost_session_weight(&conversation_id, agent, 0.015);
+            }
+        }
+        
+        // Get journey session phase for Game Mode
+        let id = conversation_id.clone();
+        let journey_phase = db::run_blocking(move || db::get_journey_session_by_conversation(&id).ok().flatten())
+            .await?
+            .map(|s| s.phase);
+        
+        // Generate Governor response based on thoughts
+        let governor_text = generate_governor_response(
+            &anthropic_key,
+            &user_message,
+            &thought_responses,
+            &recent_messages,
+            true, // is_disco
+            user_profile.as_ref(),
+            Some(active_persona.dominant_trait.as_str()),
+            journey_phase.as_deref(), // Pass journey phase for Game Mode
+        ).await.map_err(|e| e.to_string())?;
+        
+        // Save Governor message
+        let gov_msg = Message {
+            id: Uuid::new_v4().to_string(),
+            conversation_id: conversation_id.clone(),
+            role: "governor".to_string(),
+            content: governor_text.clone(),
+            response_type: Some("governor".to_string()),
+            references_message_id: None,
+            timestamp: Utc::now().to_rfc3339(),
+            attachments: Vec::new(),
+            starred: false,
+        };
+        save_message_off_runtime(&gov_msg).await?;
+        
+        return Ok(SendMessageResult {
+            responses,
+            debate_mode: Some("game".to_string()),
+            weight_change: None,
+            governor_response: Some(governor_text),
+            queued: None,
+            routing: None,
+        });
+    }
+    
+    // ===== TEXT MODE: Standard routing =====
+    // Use heuristic grounding (instant, no API call)
+    let grounding = user_profile.as_ref().map(|profile| {
+        decide_grounding_heuristic(&user_message, &recent_messages, Some(profile))
+    });
+    
+    // ===== COMPOSITE MODE: Every agent drafts, one synthesized answer is shown =====
+    if load_response_mode() == "composite" && active_agents.len() >= 2 {
+        logging::log_routing(Some(&conversation_id), "Composite mode - drafting with all active agents");
+        
+        let mut drafts = Vec::new();
+        for agent_str in &active_agents {
+            let Some(agent) = Agent::from_str(agent_str) else { continue };
+            match orchestrator.get_agent_response_with_grounding(
+                agent,
+                &user_message,
+                &recent_messages,
+                ResponseType::Primary,
+                None,
+                None,
+                grounding.as_ref(),
+                user_profile.as_ref(),
+                is_agent_disco(agent_str),
+                false,
+            ).await {
+                Ok(content) => drafts.push(CompositeDraft { agent: agent_str.clone(), content }),
+                Err(e) => logging::log_error(Some(&conversation_id), &format!(
+                    "Composite draft from {} failed: {}", agent_str, e
+                )),
+            }
+        }
+        if cancel.is_cancelled() {
+            return Err(TURN_CANCELLED.to_string());
+        }
+        if drafts.is_empty() {
+            return Err("No agent drafts were produced".to_string());
+        }
+        
+        let answer = generate_composite_answer(&anthropic_key, &user_message, &drafts, &recent_messages);
+        let answer = tokio::select! {
+            result = answer => result.map_err(|e| e.to_string())?,
+            _ = cancel.cancelled() => return Err(TURN_CANCELLED.to_string()),
+        };
+        
+        let composite_msg = Message {
+            id: Uuid::new_v4().to_string(),
+            conversation_id: conversation_id.clone(),
+            role: "governor".to_string(),
+            content: answer.clone(),
+            response_type: Some("composite".to_string()),
+            references_message_id: None,
+            timestamp: Utc::now().to_rfc3339(),
+            attachments: Vec::new(),
+            starred: false,
+        };
+        save_message_off_runtime(&composite_msg).await?;
+        let metadata = serde_json::json!({ "drafts": drafts }).to_string();
+        let message_id = composite_msg.id.clone();
+        db::run_blocking(move || -> rusqlite::Result<()> {
+            db::set_message_metadata(&message_id, &metadata)?;
+            db::increment_message_count()
+        }).await?.map_err(|e| e.to_string())?;
+        
+        // Keep the crash-safe limbo summary and extraction going; drafts stand in for agent replies
+        let exchange_note = format!(
+            "User: {}\nGovernor: {}",
+            truncate_for_summary(&raw_user_message, 100),
+            truncate_for_summary(&answer, 100)
+        );
+        let id = conversation_id.clone();
+        let _ = db::run_blocking(move || db::append_limbo_summary(&id, &exchange_note)).await;
+        
+        if memory_extraction_enabled() && !usage::skip_optional_calls("anthropic") {
+            let anthropic_key_for_extraction = anthropic_key.clone();
+            let conversation_id_for_extraction = conversation_id.clone();
+            let user_message_for_extraction = raw_user_message.clone();
+            let drafts_for_extraction: Vec<(String, String)> = drafts.iter()
+                .map(|d| (d.agent.clone(), d.content.clone()))
+                .collect();
+            let id = conversation_id.clone();
+            let watermark = db::run_blocking(move || db::get_last_seq(&id).unwrap_or(0)).await?;
+            spawn_tracked(async move {
+                let extractor = MemoryExtractor::new(&anthropic_key_for_extraction);
+                match extractor.extract_from_exchange(
+                    &user_message_for_extraction,
+                    &drafts_for_extraction,
+                    &existing_facts,
+                    &conversation_id_for_extraction,
+                ).await {
+                    Ok(_) => {
+                        let id = conversation_id_for_extraction.clone();
+                        let _ = db::run_blocking(move || db::advance_extraction_watermark(&id, watermark)).await;
+                    }
+                    Err(e) => logging::log_error(Some(&conversation_id_for_extraction), &format!("Extraction failed: {}", e)),
+                }
+            });
+        }
+        
+        return Ok(SendMessageResult {
+            responses: Vec::new(),
+            debate_mode: Some("composite".to_string()),
+            weight_change: None,
+            governor_response: Some(answer),
+            queued: None,
+            routing: None,
+        });
+    }
+    
+    // First exchange of a new session: let the last conversation's mood nudge routing
+    let is_first_exchange = recent_messages.iter().filter(|m| m.role == "user").count() <= 1;
+    let prior_mood = if is_first_exchange {
+        let id = conversation_id.clone();
+        db::run_blocking(move || db::get_previous_conversation_summary(&id).ok().flatten()).await?
+            .map(|s| [s.emotional_tone, s.user_state].into_iter().flatten().collect::<Vec<_>>().join("; "))
+            .filter(|m| !m.is_empty())
+    } else {
+        None
+    };
+    
+    // Use heuristic routing with combined base + session weights, points, and dominant trait
+    let silence = compute_agent_silence(&recent_messages);
+    let embedding_affinity = match api_key.as_deref().filter(|k| !k.trim().is_empty()) {
+        Some(key) => semantic::agent_affinity(key, &raw_user_message).await,
+        None => None, // Embeddings need OpenAI; keyword routing only
+    };
+    let mut decision = decide_response_heuristic(
+        &user_message, 
+        routing_weights, 
+        &active_agents,
+        &silence,
+        has_any_disco,
+        Some(points),
+        dominant_trait,
+        prior_mood.as_deref(),
+        embedding_affinity.as_deref(),
+    );
+    if let Some(quoted_agent) = quoted.as_ref().map(|m| m.role.clone()).filter(|a| active_agents.contains(a)) {
+        force_primary(&mut decision, &quoted_agent);
+    }
+    let routing = decision.routing.clone();
+    
+    let mut responses = Vec::new();
+    let mut debate_mode: Option<String> = None;
+    let mut agents_involved = Vec::new();
+    
+    // Get primary agent response with grounding
+    let primary_agent = Agent::from_str(&decision.primary_agent)
+        .ok_or_else(|| format!("Invalid agent: {}", decision.primary_agent))?;
+    agents_involved.push(primary_agent.as_str().to_string());
+    
     // Check if this agent is in disco mode
     let primary_is_disco = is_agent_disco(primary_agent.as_str());
     if primary_is_disco {
@@ -1089,496 +3107,2164 @@ async fn send_message(
         ));
     }
     
-    let primary_response = orchestrator
+    // Factual questions get web search results (when a search provider is set up), for the agent answering
+    if let Some(query) = decide_search_heuristic(&user_msg.content) {
+        if let Some(note) = search::grounding_note(&conversation_id, &query).await {
+            orchestrator.add_agent_note(primary_agent, note);
+        }
+    }
+    
+    // Deep grounding on scheduling or stress gets today's calendar (when the user shared it)
+    let deep_grounding = grounding.as_ref().is_some_and(|g| g.grounding_level == "deep");
+    if deep_grounding && is_schedule_or_stress_topic(&user_msg.content) {
+        if let Some(note) = calendar::day_context().await {
+            orchestrator.add_agent_note(primary_agent, note);
+        }
+    }
+    
+    let primary_response = orchestrator
+        .get_agent_response_with_grounding(
+            primary_agent,
+            &user_message,
+            &recent_messages,
+            ResponseType::Primary,
+            None,
+            None,
+            grounding.as_ref(),
+            user_profile.as_ref(),
+            primary_is_disco,
+            false, // primary_is_disco for pushback (N/A for primary response)
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    // Save primary response
+    let primary_msg_id = Uuid::new_v4().to_string();
+    let primary_msg = Message {
+        id: primary_msg_id.clone(),
+        conversation_id: conversation_id.clone(),
+        role: primary_agent.as_str().to_string(),
+        content: primary_response.clone(),
+        response_type: Some("primary".to_string()),
+        references_message_id: None,
+        timestamp: Utc::now().to_rfc3339(),
+        attachments: Vec::new(),
+        starred: false,
+    };
+    save_message_off_runtime(&primary_msg).await?;
+    annotate_served_model(&orchestrator, primary_agent, &primary_msg_id);
+    if let Some(routing) = &routing {
+        annotate_message(&primary_msg_id, "routing", serde_json::json!(routing));
+    }
+    
+    responses.push(AgentResponse {
+        agent: primary_agent.as_str().to_string(),
+        content: primary_response.clone(),
+        response_type: "primary".to_string(),
+        references_message_id: None,
+    });
+    
+    // Boost session weight for primary agent (immediate, decays over conversation)
+    boost_session_weight(&conversation_id, primary_agent, 0.02);
+    
+    // Get secondary agent response if needed
+    if decision.add_secondary {
+        if let Some(secondary_agent_str) = decision.secondary_agent {
+            // Handle "all_agents" request - get responses from all remaining active agents
+            if secondary_agent_str == "all" {
+                logging::log_routing(Some(&conversation_id), &format!(
+                    "All-agent request - getting responses from all {} agents", active_agents.len()
+                ));
+                
+                // Get the remaining agents (everyone except primary)
+                let remaining_agents: Vec<String> = active_agents.iter()
+                    .filter(|a| **a != decision.primary_agent)
+                    .cloned()
+                    .collect();
+                
+                let remaining_agents: Vec<Agent> = remaining_agents.iter()
+                    .filter_map(|a| Agent::from_str(a))
+                    .collect();
+                
+                // The additions all answer the same primary response, so they're requested
+                // concurrently and then saved in active-agent order
+                let additions = futures::future::join_all(remaining_agents.iter().map(|&agent| {
+                    orchestrator.get_agent_response_with_grounding(
+                        agent,
+                        &user_message,
+                        &recent_messages,
+                        ResponseType::Addition,
+                        Some(&primary_response),
+                        Some(primary_agent.as_str()),
+                        grounding.as_ref(),
+                        user_profile.as_ref(),
+                        is_agent_disco(agent.as_str()), // Per-agent disco
+                        primary_is_disco, // Whether primary agent was in disco
+                    )
+                })).await;
+                
+                for (agent, agent_response) in remaining_agents.into_iter().zip(additions) {
+                    let agent_response = agent_response.map_err(|e| e.to_string())?;
+                    agents_involved.push(agent.as_str().to_string());
+                    
+                    // Save response
+                    let msg = Message {
+                        id: Uuid::new_v4().to_string(),
+                        conversation_id: conversation_id.clone(),
+                        role: agent.as_str().to_string(),
+                        content: agent_response.clone(),
+                        response_type: Some(ResponseType::Addition.as_str().to_string()),
+                        references_message_id: Some(primary_msg_id.clone()),
+                        timestamp: Utc::now().to_rfc3339(),
+                        attachments: Vec::new(),
+                        starred: false,
+                    };
+                    save_message_off_runtime(&msg).await?;
+                    annotate_served_model(&orchestrator, agent, &msg.id);
+                    
+                    responses.push(AgentResponse {
+                        agent: agent.as_str().to_string(),
+                        content: agent_response,
+                        response_type: ResponseType::Addition.as_str().to_string(),
+                        references_message_id: Some(primary_msg_id.clone()),
+                    });
+                }
+            } else if let Some(secondary_agent) = Agent::from_str(&secondary_agent_str) {
+                agents_involved.push(secondary_agent.as_str().to_string());
+                
+                let response_type = decision.secondary_type
+                    .as_ref()
+                    .and_then(|t| ResponseType::from_str(t))
+                    .unwrap_or(ResponseType::Addition);
+                
+                // Set debate mode based on response type
+                debate_mode = match response_type {
+                    ResponseType::Addition => None,
+                    ResponseType::Rebuttal => Some("mild".to_string()),
+                    ResponseType::Debate => Some("intense".to_string()),
+                    _ => None,
+                };
+                
+                // Check if secondary agent is in disco mode
+                let secondary_is_disco = is_agent_disco(secondary_agent.as_str());
+                if secondary_is_disco {
+                    logging::log_agent(Some(&conversation_id), &format!(
+                        "{} in DISCO MODE - using extreme prompts", secondary_agent.as_str()
+                    ));
+                }
+                
+                let secondary_response = orchestrator
+                    .get_agent_response_with_grounding(
+                        secondary_agent,
+                        &user_message,
+                        &recent_messages,
+                        response_type,
+                        Some(&primary_response),
+                        Some(primary_agent.as_str()),
+                        grounding.as_ref(),
+                        user_profile.as_ref(),
+                        secondary_is_disco, // Per-agent disco
+                        primary_is_disco, // Whether primary agent was in disco
+                    )
+                    .await
+                    .map_err(|e| e.to_string())?;
+                
+                // Save secondary response
+                let secondary_msg = Message {
+                    id: Uuid::new_v4().to_string(),
+                    conversation_id: conversation_id.clone(),
+                    role: secondary_agent.as_str().to_string(),
+                    content: secondary_response.clone(),
+                    response_type: Some(response_type.as_str().to_string()),
+                    references_message_id: Some(primary_msg_id.clone()),
+                    timestamp: Utc::now().to_rfc3339(),
+                    attachments: Vec::new(),
+                    starred: false,
+                };
+                save_message_off_runtime(&secondary_msg).await?;
+                annotate_served_model(&orchestrator, secondary_agent, &secondary_msg.id);
+                
+                responses.push(AgentResponse {
+                    agent: secondary_agent.as_str().to_string(),
+                    content: secondary_response.clone(),
+                    response_type: response_type.as_str().to_string(),
+                    references_message_id: Some(primary_msg_id.clone()),
+                });
+                
+                // Boost session weight for secondary agent (immediate, decays over conversation)
+                boost_session_weight(&conversation_id, secondary_agent, 0.015);
+                
+                // ===== MULTI-TURN DEBATE LOOP =====
+                // Allow debates when there's genuine disagreement (rebuttal/debate), not just additions
+                // Disco mode makes debates more likely/intense; the debate settings decide whether they
+                // can happen in normal mode too, and how long they run
+                let debate_settings = debate::get_settings();
+                if response_type != ResponseType::Addition && debate_settings.allows_debate(has_any_disco) {
+                    let mut responses_so_far: Vec<(String, String)> = vec![
+                        (primary_agent.as_str().to_string(), primary_response.clone()),
+                        (secondary_agent.as_str().to_string(), secondary_response.clone()),
+                    ];
+                    
+                    let mut last_response = secondary_response.clone();
+                    let mut last_agent = secondary_agent.as_str().to_string();
+                    let mut last_agent_disco = secondary_is_disco;
+                    let mut last_msg_id = secondary_msg.id.clone();
+                    
+                    // Try to continue debate (up to max_responses total, primary and secondary included)
+                    for turn in 0..debate_settings.max_responses.saturating_sub(2) {
+                        if cancel.is_cancelled() {
+                            return Err(TURN_CANCELLED.to_string());
+                        }
+                        let response_count = responses_so_far.len();
+                        
+                        let (should_continue, next_agent_str, next_type) = orchestrator
+                            .should_continue_debate(
+                                &user_message,
+                                &responses_so_far,
+                                &active_agents,
+                                has_any_disco,
+                                response_count,
+                                &debate_settings,
+                            )
+                            .await
+                            .unwrap_or((false, None, None));
+                        
+                        if !should_continue {
+                            logging::log_agent(Some(&conversation_id), &format!(
+                                "Debate ending after {} responses (turn {})", response_count, turn
+                            ));
+                            break;
+                        }
+                        
+                        if let Some(next_agent_name) = next_agent_str {
+                            if let Some(next_agent) = Agent::from_str(&next_agent_name) {
+                                agents_involved.push(next_agent.as_str().to_string());
+                                
+                                let next_response_type = next_type
+                                    .as_ref()
+                                    .and_then(|t| ResponseType::from_str(t))
+                                    .unwrap_or(ResponseType::Rebuttal);
+                                
+                                let next_agent_disco = is_agent_disco(next_agent.as_str());
+                                logging::log_agent(Some(&conversation_id), &format!(
+                                    "Debate turn {}: {} responding (disco: {})", turn + 1, next_agent.as_str(), next_agent_disco
+                                ));
+                                
+                                let next_response = orchestrator
+                                    .get_agent_response_with_grounding(
+                                        next_agent,
+                                        &user_message,
+                                        &recent_messages,
+                                        next_response_type,
+                                        Some(&last_response),
+                                        Some(&last_agent),
+                                        grounding.as_ref(),
+                                        user_profile.as_ref(),
+                                        next_agent_disco, // Per-agent disco
+                                        last_agent_disco, // Whether last agent was in disco
+                                    )
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+                                
+                                // Save debate response
+                                let next_msg_id = Uuid::new_v4().to_string();
+                                let next_msg = Message {
+                                    id: next_msg_id.clone(),
+                                    conversation_id: conversation_id.clone(),
+                                    role: next_agent.as_str().to_string(),
+                                    content: next_response.clone(),
+                                    response_type: Some(next_response_type.as_str().to_string()),
+                                    references_message_id: Some(last_msg_id.clone()),
+                                    timestamp: Utc::now().to_rfc3339(),
+                                    attachments: Vec::new(),
+                                    starred: false,
+                                };
+                                save_message_off_runtime(&next_msg).await?;
+                                annotate_served_model(&orchestrator, next_agent, &next_msg_id);
+                                
+                                responses.push(AgentResponse {
+                                    agent: next_agent.as_str().to_string(),
+                                    content: next_response.clone(),
+                                    response_type: next_response_type.as_str().to_string(),
+                                    references_message_id: Some(last_msg_id.clone()),
+                                });
+                                
+                                // Boost session weight for debate agent (immediate, decays over conversation)
+                                boost_session_weight(&conversation_id, next_agent, 0.015);
+                                
+                                // Update for next iteration
+                                responses_so_far.push((next_agent.as_str().to_string(), next_response.clone()));
+                                last_response = next_response;
+                                last_agent = next_agent.as_str().to_string();
+                                last_agent_disco = next_agent_disco;
+                                last_msg_id = next_msg_id;
+                                
+                                // Intensify debate mode if we're continuing
+                                if response_count >= 4 {
+                                    debate_mode = Some("intense".to_string());
+                                }
+                            }
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    if cancel.is_cancelled() {
+        return Err(TURN_CANCELLED.to_string());
+    }
+    
+    // ===== GOVERNOR SYNTHESIS: Generate synthesized response after reading agent thoughts =====
+    let governor_response = if !responses.is_empty() {
+        // Collect agent responses as tuples of (agent_name, content)
+        let agent_responses: Vec<(String, String)> = responses
+            .iter()
+            .map(|r| (r.agent.clone(), r.content.clone()))
+            .collect();
+        
+        // Generate Governor's synthesized response
+        let synthesis = generate_governor_response(
+            &anthropic_key,
+            &user_message,
+            &agent_responses,
+            &recent_messages,
+            has_any_disco,
+            user_profile.as_ref(),
+            Some(active_persona.dominant_trait.as_str()),
+            None, // No journey phase in Text Mode
+        );
+        let synthesis = tokio::select! {
+            result = synthesis => result,
+            _ = cancel.cancelled() => return Err(TURN_CANCELLED.to_string()),
+        };
+        match synthesis {
+            Ok(response) => {
+                // Save Governor response to database
+                let governor_msg = Message {
+                    id: Uuid::new_v4().to_string(),
+                    conversation_id: conversation_id.clone(),
+                    role: "governor".to_string(),
+                    content: response.clone(),
+                    response_type: None,
+                    references_message_id: None,
+                    timestamp: Utc::now().to_rfc3339(),
+                    attachments: Vec::new(),
+                    starred: false,
+                };
+                if let Err(e) = save_message_off_runtime(&governor_msg).await {
+                    logging::log_error(Some(&conversation_id), &format!(
+                        "Failed to save Governor response: {}", e
+                    ));
+                }
+                Some(response)
+            }
+            Err(e) => {
+                logging::log_error(Some(&conversation_id), &format!(
+                    "Failed to generate Governor response: {}", e
+                ));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    
+    // Increment message count
+    db::run_blocking(db::increment_message_count).await?.map_err(|e| e.to_string())?;
+    
+    // ===== TRAIT ANALYSIS: Run in background AFTER response (non-blocking) =====
+    // This was moved from before routing to improve response speed
+    if !usage::skip_optional_calls("anthropic") {
+        let anthropic_key_for_traits = anthropic_key.clone();
+        let user_message_for_traits = raw_user_message.clone();
+        let conversation_id_for_traits = conversation_id.clone();
+        let has_any_disco_for_traits = has_any_disco;
+        let total_messages_for_traits = profile.total_messages;
+        let primary_agent_for_traits = responses.first().map(|r| r.agent.clone()).unwrap_or_default();
+        let had_secondary_for_traits = responses.len() > 1;
+        let app_handle_for_traits = app_handle.clone();
+        let profile_id_for_traits = active_persona.id.clone();
+        let message_id_for_traits = user_msg.id.clone();
+        
+        // Collect previous agent responses for engagement analysis
+        // (recent_messages already ends with this turn's user message, so skip past it)
+        let previous_responses_for_traits: Vec<(String, String)> = recent_messages
+            .iter()
+            .rev()
+            .skip_while(|m| m.id == user_msg.id)
+            .take_while(|m| m.role != "user")
+            .filter(|m| m.role != "system")
+            .map(|m| (m.role.clone(), m.content.clone()))
+            .collect();
+        
+        spawn_tracked(async move {
+            logging::log_routing(Some(&conversation_id_for_traits), "[BACKGROUND] Starting trait analysis...");
+            
+            // 1. Intrinsic Trait Analysis
+            let intrinsic_analyzer = IntrinsicTraitAnalyzer::new(&anthropic_key_for_traits);
+            let intrinsic_analysis = intrinsic_analyzer.analyze(&user_message_for_traits).await.ok();
+            
+            if let Some(ref intrinsic) = intrinsic_analysis {
+                logging::log_routing(Some(&conversation_id_for_traits), &format!(
+                    "[BACKGROUND] Intrinsic signals - L:{:.2} I:{:.2} P:{:.2}",
+                    intrinsic.logic_signal, intrinsic.instinct_signal, intrinsic.psyche_signal
+                ));
+                let (id, message_id, intrinsic) = (conversation_id_for_traits.clone(), message_id_for_traits.clone(), intrinsic.clone());
+                let _ = db::run_blocking(move || db::record_intrinsic_signals(
+                    &id,
+                    &message_id,
+                    (intrinsic.logic_signal, intrinsic.instinct_signal, intrinsic.psyche_signal),
+                    &intrinsic.reasoning,
+                )).await;
+            }
+            
+            // 2. Engagement Analysis (if there were previous agent responses)
+            let engagement_analysis = if !previous_responses_for_traits.is_empty() {
+                let previous_with_agents: Vec<(Agent, String)> = previous_responses_for_traits
+                    .iter()
+                    .filter_map(|(role, content)| {
+                        Agent::from_str(role).map(|agent| (agent, content.clone()))
+                    })
+                    .collect();
+                
+                if !previous_with_agents.is_empty() {
+                    let engagement_analyzer = EngagementAnalyzer::new(&anthropic_key_for_traits);
+                    engagement_analyzer.analyze_engagement(&user_message_for_traits, &previous_with_agents).await.ok()
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+            
+            if let Some(ref engagement) = engagement_analysis {
+                logging::log_routing(Some(&conversation_id_for_traits), &format!(
+                    "[BACKGROUND] Engagement scores - L:{:.2} I:{:.2} P:{:.2}",
+                    engagement.logic_score, engagement.instinct_score, engagement.psyche_score
+                ));
+                let (id, message_id, engagement) = (conversation_id_for_traits.clone(), message_id_for_traits.clone(), engagement.clone());
+                let (profile_id, previous_responses) = (profile_id_for_traits.clone(), previous_responses_for_traits.clone());
+                let _ = db::run_blocking(move || {
+                    let _ = db::record_engagement(
+                        &id,
+                        &message_id,
+                        (engagement.logic_score, engagement.instinct_score, engagement.psyche_score),
+                        &engagement.reasoning,
+                    );
+                    
+                    // Credit the engagement to the *form* each agent used, not just the agent
+                    for (role, content) in &previous_responses {
+                        let Some(agent) = Agent::from_str(role) else { continue };
+                        for (dimension, style) in classify_response_style(content) {
+                            let _ = db::record_style_engagement(
+                                &profile_id,
+                                agent.as_str(),
+                                dimension,
+                                style,
+                                engagement.score_for(agent),
+                            );
+                        }
+                    }
+                }).await;
+            }
+            
+            // 3. Update weights if we have analysis
+            if intrinsic_analysis.is_some() || engagement_analysis.is_some() {
+                let updated = db::run_blocking(move || {
+                    let mut previous = (0.0, 0.0, 0.0);
+                    db::update_weights_atomic(|current| {
+                        previous = current;
+                        combine_trait_analyses(
+                            current,
+                            engagement_analysis.as_ref(),
+                            intrinsic_analysis.as_ref(),
+                            has_any_disco_for_traits,
+                            total_messages_for_traits,
+                        )
+                    }).map(|new_weights| (previous, new_weights))
+                }).await.and_then(|r| r.map_err(|e| e.to_string()));
+                
+                match updated {
+                    Err(e) => logging::log_error(Some(&conversation_id_for_traits), &format!(
+                        "[BACKGROUND] Failed to update weights: {}", e
+                    )),
+                    Ok((current_weights, new_weights)) => {
+                        logging::log_routing(Some(&conversation_id_for_traits), &format!(
+                            "[BACKGROUND] Updated weights - I:{:.3} L:{:.3} P:{:.3}",
+                            new_weights.0, new_weights.1, new_weights.2
+                        ));
+                        
+                        // Minor adjustments happen every exchange; only real shifts go in the transcript
+                        if let Some(notice) = generate_weight_notification(
+                            current_weights,
+                            new_weights,
+                            &primary_agent_for_traits,
+                            had_secondary_for_traits,
+                        ) {
+                            if notice.change_type != "minor" {
+                                let payload = serde_json::json!({
+                                    "change_type": notice.change_type,
+                                    "old_dominant": notice.old_dominant,
+                                    "new_dominant": notice.new_dominant,
+                                    "old_weights": [current_weights.0, current_weights.1, current_weights.2],
+                                    "new_weights": [new_weights.0, new_weights.1, new_weights.2],
+                                });
+                                if notice.change_type == "major_shift" {
+                                    webhooks::emit("trait.shifted", serde_json::json!({
+                                        "conversation_id": conversation_id_for_traits,
+                                        "message": notice.message,
+                                        "details": payload,
+                                    }));
+                                }
+                                record_system_notice(&app_handle_for_traits, &conversation_id_for_traits, "weight_shift", &notice.message, payload);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+    
+    // A returning agent only needs to acknowledge the gap once
+    let acknowledged: Vec<_> = returning_agents.iter()
+        .filter(|returning| responses.iter().any(|r| r.agent == returning.agent))
+        .map(|returning| returning.id)
+        .collect();
+    if !acknowledged.is_empty() {
+        let _ = db::run_blocking(move || {
+            for id in acknowledged {
+                let _ = db::mark_return_acknowledged(id);
+            }
+        }).await;
+    }
+    
+    // ===== MEMORY SYSTEM: Extract Facts & Patterns (async, non-blocking) =====
+    let anthropic_key_clone = anthropic_key.clone();
+    let user_message_clone = raw_user_message.clone();
+    let conversation_id_clone = conversation_id.clone();
+    let responses_for_extraction: Vec<(String, String)> = responses
+        .iter()
+        .map(|r| (r.agent.clone(), r.content.clone()))
+        .collect();
+    let existing_facts_clone = existing_facts;
+    // Every message in this exchange is saved by now; a successful extraction covers up to here
+    let id = conversation_id.clone();
+    let extraction_watermark = db::run_blocking(move || db::get_last_seq(&id).unwrap_or(0)).await?;
+    
+    // Respect the privacy choice made during setup
+    if !memory_extraction_enabled() {
+        logging::log_memory(Some(&conversation_id), "Extraction skipped (disabled in privacy settings)");
+    } else if usage::skip_optional_calls("anthropic") {
+        logging::log_memory(Some(&conversation_id), "Extraction skipped (Anthropic budget exceeded)");
+    } else {
+        logging::log_memory(Some(&conversation_id), "Spawning extraction task...");
+    
+        // Spawn memory extraction as a background task (uses Anthropic Opus)
+        spawn_tracked(async move {
+            logging::log_memory(Some(&conversation_id_clone), "Extraction task started");
+            let extractor = MemoryExtractor::new(&anthropic_key_clone);
+            match extractor.extract_from_exchange(
+                &user_message_clone,
+                &responses_for_extraction,
+                &existing_facts_clone,
+                &conversation_id_clone,
+            ).await {
+                Ok(result) => {
+                    let id = conversation_id_clone.clone();
+                    let _ = db::run_blocking(move || db::advance_extraction_watermark(&id, extraction_watermark)).await;
+                    logging::log_memory(Some(&conversation_id_clone), &format!(
+                        "Extraction completed: {} facts, {} patterns",
+                        result.new_facts.len(), result.new_patterns.len()
+                    ));
+                }
+                Err(e) => logging::log_error(Some(&conversation_id_clone), &format!(
+                    "Extraction failed: {}", e
+                )),
+            }
+        });
+    }
+    
+    // ===== MEMORY SYSTEM: Append to Limbo Summary (crash-safe incremental summary) =====
+    // This happens every exchange so the conversation is always recoverable
+    {
+        let agents_summary: Vec<String> = responses.iter()
+            .map(|r| format!("{}: {}", r.agent, truncate_for_summary(&r.content, 100)))
+            .collect();
+        let exchange_note = format!(
+            "User: {}\n{}",
+            truncate_for_summary(&raw_user_message, 100),
+            agents_summary.join("\n")
+        );
+        let id = conversation_id.clone();
+        let _ = db::run_blocking(move || db::append_limbo_summary(&id, &exchange_note)).await;
+        logging::log_memory(Some(&conversation_id), "Appended exchange to limbo summary");
+    }
+    
+    // ===== MEMORY SYSTEM: Summarize Conversation Periodically =====
+    let message_count = profile.total_messages + 1;
+    if message_count % 10 == 0 && !usage::skip_optional_calls("anthropic") {
+        // Every 10 messages, update conversation summary (uses Anthropic Opus)
+        let anthropic_key_for_summary = anthropic_key.clone();
+        let conversation_id_for_summary = conversation_id.clone();
+        let agents_for_summary = agents_involved.clone();
+        
+        spawn_tracked(async move {
+            let summarizer = ConversationSummarizer::new(&anthropic_key_for_summary);
+            // All messages plus the existing summary
+            let id = conversation_id_for_summary.clone();
+            let (all_messages, existing) = db::run_blocking(move || (
+                db::get_conversation_messages(&id).unwrap_or_default(),
+                db::get_conversation_summary(&id).ok().flatten(),
+            )).await.unwrap_or_default();
+            let existing_text = existing.as_ref().map(|s| s.summary.as_str());
+            
+            // Only summarize messages not in the existing summary
+            let messages_to_summarize = if existing.is_some() {
+                // Get the last 15 messages to create a rolling summary
+                all_messages.into_iter().rev().take(15).rev().collect::<Vec<_>>()
+            } else {
+                all_messages
+            };
+            
+            if let Ok(result) = summarizer.summarize(&messages_to_summarize, existing_text).await {
+                let _ = db::run_blocking(move || ConversationSummarizer::save_summary(
+                    &conversation_id_for_summary,
+                    &result,
+                    message_count,
+                    &agents_for_summary,
+                )).await;
+            }
+        });
+    }
+    
+    // ===== FOLLOW-UPS: Turn a proposed check-in into a pending proposal the user can confirm =====
+    if !usage::skip_optional_calls("anthropic") {
+        let anthropic_key_for_follow_up = anthropic_key.clone();
+        let conversation_id_for_follow_up = conversation_id.clone();
+        let user_message_for_follow_up = raw_user_message.clone();
+        let responses_for_follow_up: Vec<(String, String)> = responses
+            .iter()
+            .map(|r| (r.agent.clone(), r.content.clone()))
+            .collect();
+        let app_handle_for_follow_up = app_handle.clone();
+        spawn_tracked(async move {
+            match scheduler::propose_follow_up(
+                &anthropic_key_for_follow_up,
+                &conversation_id_for_follow_up,
+                &user_message_for_follow_up,
+                &responses_for_follow_up,
+            ).await {
+                Ok(Some(proposal)) => {
+                    logging::log_conversation(Some(&conversation_id_for_follow_up), &format!(
+                        "Follow-up proposed: {} at {}", proposal.topic, proposal.due_at
+                    ));
+                    let _ = app_handle_for_follow_up.emit("follow-up-proposed", &proposal);
+                }
+                Ok(None) => {}
+                Err(e) => logging::log_error(Some(&conversation_id_for_follow_up), &format!(
+                    "Follow-up detection failed: {}", e
+                )),
+            }
+        });
+    }
+    
+    // ===== PERSONA SUGGESTION: Check once per conversation whether another persona fits better =====
+    {
+        let conversation_id_for_suggestion = conversation_id.clone();
+        let active_persona_id = active_persona.id.clone();
+        tokio::spawn(async move {
+            if let Some(suggestion) = check_persona_suggestion(&conversation_id_for_suggestion, &active_persona_id) {
+                logging::log_routing(Some(&conversation_id_for_suggestion), &format!(
+                    "Suggesting persona switch to {}", suggestion.profile_name
+                ));
+                let _ = app_handle.emit("persona-suggestion", &suggestion);
+            }
+        });
+    }
+    
+    // Weight changes are handled by background analysis only (base weights)
+    // Session weights decay automatically and don't generate notifications
+    Ok(SendMessageResult { responses, debate_mode, weight_change: None, governor_response, queued: None, routing })
+}
+
+// ============ Re-run Turn ============
+
+/// Regenerate the responses to the user's last message, optionally with edited text.
+/// The old responses are kept as a turn version instead of being deleted.
+#[tauri::command]
+async fn rerun_turn(
+    app_handle: tauri::AppHandle,
+    message_id: String,
+    new_content: Option<String>,
+    active_agents: Vec<String>,
+    disco_agents: Vec<String>,
+) -> Result<SendMessageResult, String> {
+    let (message, recent) = db::run_blocking(move || -> rusqlite::Result<_> {
+        let Some(message) = db::get_message(&message_id)? else {
+            return Ok((None, Vec::new()));
+        };
+        let recent = db::get_recent_messages(&message.conversation_id, 50)?;
+        Ok((Some(message), recent))
+    }).await?.map_err(|e| e.to_string())?;
+    let message = message.ok_or("Message not found")?;
+    if message.role != "user" {
+        return Err("Only user messages can be re-run".to_string());
+    }
+    
+    let last_user_id = recent.iter().rev().find(|m| m.role == "user").map(|m| m.id.as_str());
+    if last_user_id != Some(message.id.as_str()) {
+        return Err("Only the last message in a conversation can be re-run".to_string());
+    }
+    
+    let new_content = new_content.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    let (id, content) = (message.id.clone(), new_content.clone());
+    let version_id = db::run_blocking(move || db::supersede_turn(&id, content.as_deref()))
+        .await?
+        .map_err(|e| e.to_string())?;
+    logging::log_conversation(Some(&message.conversation_id), &format!(
+        "Re-running turn {} (previous responses archived as {})", message.id, version_id
+    ));
+    
+    let mut user_msg = message;
+    if let Some(content) = new_content {
+        user_msg.content = content;
+    }
+    let conversation_id = user_msg.conversation_id.clone();
+    let asked_at = user_msg.timestamp.clone();
+    let rerun_at = Utc::now().to_rfc3339();
+    let result = run_turn(app_handle, user_msg, active_agents, disco_agents).await;
+    
+    // The replaced turn's reminders go with it, once there's a new turn to replace it
+    if result.is_ok() {
+        let id = conversation_id.clone();
+        let deleted = db::run_blocking(move || db::delete_pending_reminders(&id, &asked_at, Some(&rerun_at)))
+            .await
+            .and_then(|r| r.map_err(|e| e.to_string()));
+        if let Err(e) = deleted {
+            logging::log_error(Some(&conversation_id), &format!("Failed to drop the replaced turn's reminders: {}", e));
+        }
+    }
+    // A cancelled or failed re-run must not leave the turn with no visible replies
+    if result.is_err() {
+        let id = version_id.clone();
+        let restored = db::run_blocking(move || db::restore_superseded_turn(&id))
+            .await
+            .and_then(|r| r.map_err(|e| e.to_string()));
+        match restored {
+            Ok(()) => logging::log_conversation(Some(&conversation_id), &format!(
+                "Re-run didn't complete, restored archived responses {}", version_id
+            )),
+            Err(e) => logging::log_error(Some(&conversation_id), &format!(
+                "Failed to restore archived responses {}: {}", version_id, e
+            )),
+        }
+    }
+    result
+}
+
+#[tauri::command]
+fn get_turn_versions(message_id: String) -> Result<Vec<db::TurnVersion>, String> {
+    db::get_turn_versions(&message_id).map_err(|e| e.to_string())
+}
+
+// ============ Conversation Branches ============
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BranchResult {
+    pub conversation: ConversationInfo,
+    pub result: SendMessageResult,
+}
+
+/// Edit an earlier user message and replay from there in a new branch.
+/// The original conversation is left untouched; the branch copies everything before the edited message.
+#[tauri::command]
+async fn branch_from_message(
+    app_handle: tauri::AppHandle,
+    message_id: String,
+    new_content: String,
+    active_agents: Vec<String>,
+    disco_agents: Vec<String>,
+) -> Result<BranchResult, String> {
+    let message = db::run_blocking(move || db::get_message(&message_id))
+        .await?
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+    if message.role != "user" {
+        return Err("Only user messages can be edited".to_string());
+    }
+    let new_content = new_content.trim().to_string();
+    if new_content.is_empty() {
+        return Err("Message cannot be empty".to_string());
+    }
+    
+    let branch_id = Uuid::new_v4().to_string();
+    let (source_id, at_id) = (message.conversation_id.clone(), message.id.clone());
+    let branch = db::run_blocking(move || db::create_branch(&branch_id, &source_id, &at_id))
+        .await?
+        .map_err(|e| e.to_string())?;
+    logging::log_conversation(Some(&branch.id), &format!(
+        "Branched from conversation {} at message {}", message.conversation_id, message.id
+    ));
+    
+    let user_msg = Message {
+        id: Uuid::new_v4().to_string(),
+        conversation_id: branch.id.clone(),
+        role: "user".to_string(),
+        content: new_content,
+        response_type: None,
+        references_message_id: None,
+        timestamp: Utc::now().to_rfc3339(),
+        attachments: Vec::new(),
+        starred: false,
+    };
+    let result = match run_turn(app_handle, user_msg, active_agents, disco_agents).await {
+        Ok(result) => result,
+        Err(e) => {
+            // A branch without its replayed turn is just a truncated copy - don't leave it behind
+            let id = branch.id.clone();
+            let _ = db::run_blocking(move || db::delete_conversation(&id)).await;
+            return Err(e);
+        }
+    };
+    
+    let conv = db::run_blocking(move || db::get_conversation(&branch.id))
+        .await?
+        .map_err(|e| e.to_string())?
+        .ok_or("Conversation not found")?;
+    Ok(BranchResult {
+        conversation: ConversationInfo {
+            id: conv.id,
+            title: conv.title,
+            summary: conv.summary,
+            is_disco: conv.is_disco,
+            created_at: conv.created_at,
+            updated_at: conv.updated_at,
+            model_override: conv.model_override,
+            parent_conversation_id: conv.parent_conversation_id,
+            branch_point_message_id: conv.branch_point_message_id,
+        },
+        result,
+    })
+}
+
+#[tauri::command]
+fn get_conversation_branches(conversation_id: String) -> Result<Vec<ConversationInfo>, String> {
+    let convs = db::get_conversation_branches(&conversation_id).map_err(|e| e.to_string())?;
+    Ok(convs.into_iter().map(|c| ConversationInfo {
+        id: c.id,
+        title: c.title,
+        summary: c.summary,
+        is_disco: c.is_disco,
+        created_at: c.created_at,
+        updated_at: c.updated_at,
+        model_override: c.model_override,
+        parent_conversation_id: c.parent_conversation_id,
+        branch_point_message_id: c.branch_point_message_id,
+    }).collect())
+}
+
+// ============ Regenerate Response ============
+
+/// Regenerate a single saved agent response with the same agent, response type and grounding.
+/// The message is updated in place; its previous content is kept as a message version.
+#[tauri::command]
+async fn regenerate_response(message_id: String, disco_agents: Vec<String>) -> Result<AgentResponse, String> {
+    let message = db::run_blocking(move || db::get_message(&message_id))
+        .await?
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+    let agent = Agent::from_str(&message.role).ok_or("Only agent responses can be regenerated")?;
+    let response_type = message.response_type.as_deref()
+        .and_then(ResponseType::from_str)
+        .unwrap_or(ResponseType::Primary);
+    
+    // Rebuild the context the response was generated with: history up to the user message it answers,
+    // plus the primary response that additions, rebuttals and debates answer
+    let history_budget = context::get_settings().history_token_budget;
+    let (id, references) = (message.id.clone(), message.references_message_id.clone());
+    let (mut recent_messages, primary) = db::run_blocking(move || -> rusqlite::Result<_> {
+        let history = context::fetch_history(history_budget, |limit| db::get_messages_before(&id, limit))?;
+        let primary = match &references {
+            Some(id) => db::get_message(id)?,
+            None => None,
+        };
+        Ok((history, primary))
+    }).await?.map_err(|e| e.to_string())?;
+    attachments::describe_history(&mut recent_messages, None);
+    let user_idx = recent_messages.iter().rposition(|m| m.role == "user")
+        .ok_or("No user message precedes this response")?;
+    recent_messages.truncate(user_idx + 1);
+    let user_message = recent_messages[recent_messages.len() - 1].content.clone();
+    
+    let is_agent_disco = |agent: &str| disco_agents.iter().any(|a| a == agent);
+    
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let api_key = profile.api_key.clone(); // None = Anthropic-only mode
+    let anthropic_key = profile.anthropic_key.clone().ok_or("Anthropic API key not set")?;
+    
+    let mut orchestrator = Orchestrator::new(api_key.as_deref(), &anthropic_key);
+    apply_agent_providers(&mut orchestrator, &profile);
+    orchestrator.set_tools(tools::enabled_tools().await, &message.conversation_id);
+    let id = message.conversation_id.clone();
+    let model_override = db::run_blocking(move || db::get_conversation(&id).ok().flatten().and_then(|c| c.model_override))
+        .await?;
+    orchestrator.set_model_override(model_override);
+    
+    let user_profile = MemoryExtractor::build_profile_summary().ok();
+    let grounding = user_profile.as_ref().map(|profile| {
+        decide_grounding_heuristic(&user_message, &recent_messages, Some(profile))
+    });
+    
+    let content = orchestrator
         .get_agent_response_with_grounding(
-            primary_agent,
+            agent,
             &user_message,
             &recent_messages,
-            ResponseType::Primary,
-            None,
-            None,
+            response_type,
+            primary.as_ref().map(|p| p.content.as_str()),
+            primary.as_ref().map(|p| p.role.as_str()),
             grounding.as_ref(),
             user_profile.as_ref(),
-            primary_is_disco,
-            false, // primary_is_disco for pushback (N/A for primary response)
+            is_agent_disco(agent.as_str()),
+            primary.as_ref().is_some_and(|p| is_agent_disco(&p.role)),
         )
         .await
         .map_err(|e| e.to_string())?;
     
-    // Save primary response
-    let primary_msg_id = Uuid::new_v4().to_string();
-    let primary_msg = Message {
-        id: primary_msg_id.clone(),
-        conversation_id: conversation_id.clone(),
-        role: primary_agent.as_str().to_string(),
-        content: primary_response.clone(),
-        response_type: Some("primary".to_string()),
-        references_message_id: None,
-        timestamp: Utc::now().to_rfc3339(),
+    let (id, new_content) = (message.id.clone(), content.clone());
+    let version = db::run_blocking(move || db::replace_message_content(&id, &new_content))
+        .await?
+        .map_err(|e| e.to_string())?;
+    annotate_served_model(&orchestrator, agent, &message.id);
+    logging::log_agent(Some(&message.conversation_id), &format!(
+        "Regenerated {} response {} (previous content kept as version {})", agent.as_str(), message.id, version.version
+    ));
+    
+    Ok(AgentResponse {
+        agent: agent.as_str().to_string(),
+        content,
+        response_type: response_type.as_str().to_string(),
+        references_message_id: message.references_message_id,
+    })
+}
+
+#[tauri::command]
+fn get_message_versions(message_id: String) -> Result<Vec<db::MessageVersion>, String> {
+    db::get_message_versions(&message_id).map_err(|e| e.to_string())
+}
+
+// ============ Delete Message ============
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteMessageResult {
+    pub relinked: usize,      // Replies re-pointed at what the deleted message replied to
+    pub facts_flagged: usize, // Facts from its exchange flagged for review
+}
+
+/// Delete one message (a mis-paste, say). Replies to it keep their place in the thread; with
+/// flag_facts, facts learned from its exchange are flagged for review in the memory browser.
+#[tauri::command]
+fn delete_message(message_id: String, flag_facts: Option<bool>) -> Result<DeleteMessageResult, String> {
+    let stored_files = db::get_message_attachments(&message_id).unwrap_or_default();
+    let deleted = db::delete_message(&message_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+    attachments::remove_files(&stored_files);
+    
+    let facts_flagged = if flag_facts.unwrap_or(false) {
+        db::flag_facts_for_review(
+            &deleted.conversation_id,
+            &deleted.exchange_since,
+            deleted.exchange_until.as_deref(),
+            "Learned from a message that was deleted",
+        ).map_err(|e| e.to_string())?
+    } else {
+        0
+    };
+    logging::log_conversation(Some(&deleted.conversation_id), &format!(
+        "Deleted message {} ({} replies relinked, {} facts flagged for review)",
+        message_id, deleted.relinked, facts_flagged
+    ));
+    Ok(DeleteMessageResult { relinked: deleted.relinked, facts_flagged })
+}
+
+// ============ Message Feedback ============
+
+/// Thumbs up/down on an agent message: "up", "down", or "none" to clear.
+/// The active profile's weights move by the change from the previous rating, so flipping or
+/// clearing a rating takes back the nudge the earlier one gave.
+#[tauri::command]
+fn rate_message(message_id: String, rating: String) -> Result<(), String> {
+    let value = match rating.as_str() {
+        "up" => 1,
+        "down" => -1,
+        "none" => 0,
+        other => return Err(format!("Unknown rating: {}", other)),
     };
-    db::save_message(&primary_msg).map_err(|e| e.to_string())?;
+    let message = db::get_message(&message_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+    let agent = Agent::from_str(&message.role).ok_or("Only agent responses can be rated")?;
     
-    responses.push(AgentResponse {
-        agent: primary_agent.as_str().to_string(),
-        content: primary_response.clone(),
-        response_type: "primary".to_string(),
-        references_message_id: None,
+    let previous = db::set_message_feedback(&message_id, &message.conversation_id, agent.as_str(), value)
+        .map_err(|e| e.to_string())?;
+    annotate_message(&message_id, "rating", if value == 0 { serde_json::Value::Null } else { serde_json::json!(rating) });
+    let delta = value - previous.unwrap_or(0);
+    if delta == 0 {
+        return Ok(());
+    }
+    
+    let total_messages = db::get_user_profile().map(|p| p.total_messages).unwrap_or(0);
+    let new_weights = db::update_weights_atomic(|current| {
+        evolve_weights(current, agent, InteractionType::ExplicitFeedback { delta }, total_messages)
+    }).map_err(|e| e.to_string())?;
+    if value != 0 {
+        if let Ok(Some(persona)) = db::get_active_persona_profile() {
+            let _ = db::record_agent_interaction(&persona.id, agent.as_str(), value > 0);
+        }
+    }
+    logging::log_routing(Some(&message.conversation_id), &format!(
+        "Thumbs {} on {} -- weights I:{:.3} L:{:.3} P:{:.3}",
+        rating, agent.as_str(), new_weights.0, new_weights.1, new_weights.2
+    ));
+    Ok(())
+}
+
+/// (agent, thumbs up, thumbs down) across all rated messages
+#[tauri::command]
+fn get_feedback_totals() -> Result<Vec<(String, i64, i64)>, String> {
+    db::get_feedback_totals().map_err(|e| e.to_string())
+}
+
+// ============ Starred Messages ============
+
+/// Star or unstar a message; returns whether it's now starred
+#[tauri::command]
+fn toggle_star(message_id: String) -> Result<bool, String> {
+    let starred = db::toggle_message_star(&message_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+    logging::log_memory(None, &format!("Message {} {}", message_id, if starred { "starred" } else { "unstarred" }));
+    Ok(starred)
+}
+
+/// Saved insights: starred messages, newest first
+#[tauri::command]
+fn get_starred_messages(limit: Option<usize>) -> Result<Vec<db::StarredMessage>, String> {
+    db::get_starred_messages(limit).map_err(|e| e.to_string())
+}
+
+// ============ Routing Explanation ============
+
+/// Routing state for the conversation's next turn (same history window run_turn routes with)
+#[tauri::command]
+fn get_routing_explanation(conversation_id: String) -> Result<RoutingExplanation, String> {
+    let history_budget = context::get_settings().history_token_budget;
+    let recent_messages = context::fetch_history(history_budget, |limit| db::get_recent_messages(&conversation_id, limit))
+        .map_err(|e| e.to_string())?;
+    Ok(RoutingExplanation {
+        silence: compute_agent_silence(&recent_messages),
+    })
+}
+
+/// Routing internals stored on an agent message when it was picked as primary
+#[tauri::command]
+fn get_message_routing(message_id: String) -> Result<Option<RoutingTrace>, String> {
+    let metadata = db::get_message_metadata(&message_id).map_err(|e| e.to_string())?;
+    Ok(metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .and_then(|v| serde_json::from_value(v["routing"].clone()).ok()))
+}
+
+// ============ Decisions ============
+
+#[tauri::command]
+fn get_decisions() -> Result<Vec<db::Decision>, String> {
+    db::get_decisions().map_err(|e| e.to_string())
+}
+
+// ============ Check-ins ============
+
+#[tauri::command]
+fn get_check_ins(status: Option<String>) -> Result<Vec<db::CheckIn>, String> {
+    db::get_check_ins(status.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Confirm a proposed follow-up so the scheduler acts on it (optionally at a different time)
+#[tauri::command]
+fn confirm_check_in(id: String, due_at: Option<String>) -> Result<db::CheckIn, String> {
+    let check_in = db::get_check_in(&id).map_err(|e| e.to_string())?.ok_or("Check-in not found")?;
+    if check_in.status != "proposed" {
+        return Err(format!("Check-in is already {}", check_in.status));
+    }
+    
+    let due_at = match due_at {
+        Some(d) => Some(chrono::DateTime::parse_from_rfc3339(&d)
+            .map_err(|e| format!("Invalid due_at: {}", e))?
+            .with_timezone(&Utc)
+            .to_rfc3339()),
+        None => None,
+    };
+    db::update_check_in_status(&id, "scheduled", due_at.as_deref()).map_err(|e| e.to_string())?;
+    db::get_check_in(&id).map_err(|e| e.to_string())?.ok_or_else(|| "Check-in not found".to_string())
+}
+
+#[tauri::command]
+fn dismiss_check_in(id: String) -> Result<(), String> {
+    db::update_check_in_status(&id, "dismissed", None).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn complete_check_in(id: String) -> Result<(), String> {
+    db::update_check_in_status(&id, "done", None).map_err(|e| e.to_string())
+}
+
+// ============ Journaling ============
+
+#[tauri::command]
+fn get_journal_settings() -> journal::JournalSettings {
+    journal::get_settings()
+}
+
+#[tauri::command]
+fn set_journal_settings(settings: journal::JournalSettings) -> Result<(), String> {
+    journal::set_settings(&settings)?;
+    logging::log_conversation(None, &format!(
+        "Journaling {} ({} at {})", if settings.enabled { "on" } else { "off" }, settings.agent, settings.prompt_time
+    ));
+    Ok(())
+}
+
+/// Start a journal entry now instead of waiting for the evening prompt
+#[tauri::command]
+async fn start_journal_entry() -> Result<journal::JournalStart, String> {
+    journal::start_entry().await
+}
+
+/// Journal days for the timeline: "week", "month", "year", "all" or "YYYY-MM-DD..YYYY-MM-DD"
+#[tauri::command]
+fn get_journal_entries(range: Option<String>) -> Result<Vec<journal::JournalEntry>, String> {
+    journal::entries(range.as_deref().unwrap_or("all"))
+}
+
+// ============ Mood ============
+
+/// Mood over a range ("week", "month", "year", "all" or "YYYY-MM-DD..YYYY-MM-DD"), by day or by week
+#[tauri::command]
+fn get_mood_trend(range: Option<String>) -> Result<mood::MoodTrend, String> {
+    mood::trend(range.as_deref().unwrap_or("month"))
+}
+
+// ============ Reminders ============
+
+#[tauri::command]
+fn get_reminders(status: Option<String>) -> Result<Vec<db::Reminder>, String> {
+    db::get_reminders(status.as_deref()).map_err(|e| e.to_string())
+}
+
+fn set_reminder_status(id: &str, status: &str) -> Result<(), String> {
+    if !db::update_reminder_status(id, status).map_err(|e| e.to_string())? {
+        return Err("Reminder not found".to_string());
+    }
+    logging::log_conversation(None, &format!("Reminder {} marked {}", id, status));
+    Ok(())
+}
+
+#[tauri::command]
+fn complete_reminder(id: String) -> Result<(), String> {
+    set_reminder_status(&id, "done")
+}
+
+/// Drop a reminder (before or after it fires)
+#[tauri::command]
+fn dismiss_reminder(id: String) -> Result<(), String> {
+    set_reminder_status(&id, "dismissed")
+}
+
+// ============ Export Jobs ============
+
+/// Start a background export; progress arrives as "export-progress" events
+#[tauri::command]
+fn start_export(app_handle: tauri::AppHandle, job_spec: export::ExportJobSpec) -> Result<String, String> {
+    export::start(app_handle, job_spec)
+}
+
+#[tauri::command]
+fn get_export_result(job_id: String) -> Result<export::ExportJob, String> {
+    export::get_result(&job_id).ok_or_else(|| "Export job not found".to_string())
+}
+
+/// Export one conversation as Markdown or JSON to a file the user picks.
+/// Returns the written path, or None if the save dialog was cancelled.
+#[tauri::command]
+async fn export_conversation(app_handle: tauri::AppHandle, conversation_id: String, format: String) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+    
+    let format = export::ConversationFormat::from_str(&format)
+        .ok_or_else(|| format!("Unknown export format: {}", format))?;
+    let (file_name, contents) = export::render_conversation(&conversation_id, format)?;
+    
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app_handle.dialog()
+        .file()
+        .set_file_name(&file_name)
+        .add_filter(if format == export::ConversationFormat::Json { "JSON" } else { "Markdown" }, &[format.extension()])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let Some(path) = rx.await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| e.to_string())?;
+    
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+    logging::log_conversation(Some(&conversation_id), &format!("Exported conversation to {}", path.display()));
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+// ============ Backups ============
+
+#[tauri::command]
+fn list_backups() -> Result<Vec<backup::Backup>, String> {
+    backup::list_backups()
+}
+
+/// Replace the database with a backup; returns the safety snapshot of the database it replaced
+#[tauri::command]
+fn restore_backup(id: String) -> Result<backup::Backup, String> {
+    let safety = backup::restore_backup(&id)?;
+    // Session state belongs to the database that was just replaced
+    SESSION_WEIGHTS.lock().unwrap().clear();
+    Ok(safety)
+}
+
+#[tauri::command]
+fn get_backup_settings() -> backup::BackupSettings {
+    backup::get_settings()
+}
+
+/// How many daily, weekly and safety (pre-restore/import/reset/migration) backups to keep
+#[tauri::command]
+fn set_backup_settings(settings: backup::BackupSettings) -> Result<(), String> {
+    backup::set_settings(&settings)?;
+    logging::log_conversation(None, &format!(
+        "Keeping {} daily, {} weekly and {} safety backups", settings.keep_daily, settings.keep_weekly, settings.keep_safety
+    ));
+    Ok(())
+}
+
+// ============ Cloud Sync ============
+
+#[tauri::command]
+fn get_sync_settings() -> sync::SyncSettings {
+    sync::get_status()
+}
+
+/// Turn on encrypted sync with a backend (folder, S3 or WebDAV). Joining a backend other devices
+/// already sync to needs the passphrase they were set up with.
+#[tauri::command]
+async fn configure_sync(backend: sync::SyncBackend, passphrase: String, device_name: Option<String>) -> Result<sync::SyncSettings, String> {
+    sync::configure(backend, &passphrase, device_name).await
+}
+
+#[tauri::command]
+fn set_sync_interval(minutes: u64) -> Result<(), String> {
+    sync::set_interval(minutes)
+}
+
+/// Stop syncing this device; what's already on the backend stays there
+#[tauri::command]
+fn disable_sync() -> Result<(), String> {
+    sync::disable()?;
+    logging::log_conversation(None, "Cloud sync turned off");
+    Ok(())
+}
+
+#[tauri::command]
+async fn sync_now() -> Result<sync::SyncReport, String> {
+    sync::sync_now().await
+}
+
+// ============ Full Data Archive ============
+
+/// Write everything Intersect knows (conversations, memory, personas, weights) to one archive file.
+/// API keys are left out.
+#[tauri::command]
+fn export_all_data(app_handle: tauri::AppHandle, path: String) -> Result<Vec<db::ArchiveTableCount>, String> {
+    let archive = db::export_archive(&attachments::attachments_dir(&app_handle)?).map_err(|e| e.to_string())?;
+    let counts = archive["tables"].as_object()
+        .map(|tables| tables.iter()
+            .map(|(table, rows)| db::ArchiveTableCount { table: table.clone(), rows: rows.as_array().map_or(0, |r| r.len()) })
+            .collect())
+        .unwrap_or_default();
+    let json = serde_json::to_string(&archive).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write archive: {}", e))?;
+    logging::log_conversation(None, &format!("Exported all data to {}", path));
+    Ok(counts)
+}
+
+/// Replace all data with an archive from export_all_data. The current database is snapshotted first
+/// (as a "pre-import" backup) and the API keys on this machine are kept.
+#[tauri::command]
+fn import_all_data(app_handle: tauri::AppHandle, path: String) -> Result<Vec<db::ArchiveTableCount>, String> {
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read archive: {}", e))?;
+    let archive: serde_json::Value = serde_json::from_str(&json).map_err(|e| format!("Invalid archive: {}", e))?;
+    
+    let safety = backup::create_safety_backup("pre-import")?;
+    let counts = db::import_archive(&archive, &attachments::attachments_dir(&app_handle)?)?;
+    // Session state belongs to the data that was just replaced
+    SESSION_WEIGHTS.lock().unwrap().clear();
+    
+    logging::log_conversation(None, &format!("Imported all data from {} (previous data saved as {})", path, safety.id));
+    Ok(counts)
+}
+
+// ============ Transcript Import ============
+
+/// Import a "Name: text" transcript (plain text or Markdown) as a new conversation.
+/// role_mapping maps each speaker name to "user", "instinct", "logic" or "psyche".
+/// The conversation is finalized in the background so memory can learn from it.
+#[tauri::command]
+async fn import_transcript(path: String, role_mapping: HashMap<String, String>) -> Result<ConversationInfo, String> {
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let parsed = importer::parse_transcript(&text, &role_mapping)?;
+    
+    let id = Uuid::new_v4().to_string();
+    let title = parsed.title.clone().unwrap_or_else(|| {
+        std::path::Path::new(&path).file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Imported conversation".to_string())
+    });
+    let message_count = parsed.messages.len();
+    
+    let conversation_id = id.clone();
+    db::run_blocking(move || -> rusqlite::Result<()> {
+        db::create_conversation(&conversation_id, false)?;
+        db::set_conversation_title(&conversation_id, &title)?;
+        for (role, content) in parsed.messages {
+            db::save_message(&Message {
+                id: Uuid::new_v4().to_string(),
+                conversation_id: conversation_id.clone(),
+                role,
+                content,
+                response_type: None,
+                references_message_id: None,
+                timestamp: Utc::now().to_rfc3339(),
+                attachments: Vec::new(),
+                starred: false,
+            })?;
+        }
+        Ok(())
+    }).await?.map_err(|e| e.to_string())?;
+    
+    logging::log_conversation(Some(&id), &format!(
+        "Imported {} messages from {}", message_count, path
+    ));
+    
+    let conversation_id = id.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = finalize_conversation_internal(&conversation_id).await {
+            logging::log_error(Some(&conversation_id), &format!("Finalizing imported conversation failed: {}", e));
+        }
+    });
+    
+    let conv = db::run_blocking(move || db::get_conversation(&id))
+        .await?
+        .map_err(|e| e.to_string())?
+        .ok_or("Conversation not found")?;
+    Ok(ConversationInfo {
+        id: conv.id,
+        title: conv.title,
+        summary: conv.summary,
+        is_disco: conv.is_disco,
+        created_at: conv.created_at,
+        updated_at: conv.updated_at,
+        model_override: conv.model_override,
+        parent_conversation_id: conv.parent_conversation_id,
+        branch_point_message_id: conv.branch_point_message_id,
+    })
+}
+
+// ============ Graceful Shutdown ============
+
+/// How long quitting waits for running extraction/summary tasks to finish writing
+const SHUTDOWN_TASK_GRACE_SECS: u64 = 5;
+/// Upper bound for finalizing open conversations on quit; anything unfinished is recovered next launch
+const SHUTDOWN_FINALIZE_TIMEOUT_SECS: u64 = 20;
+
+// Memory tasks spawned after a turn that haven't finished yet
+static BACKGROUND_TASKS: AtomicUsize = AtomicUsize::new(0);
+// Set once shutdown work is done, so the exit it triggers goes through
+static SHUTDOWN_COMPLETE: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN_STARTED: AtomicBool = AtomicBool::new(false);
+
+struct BackgroundTask;
+
+impl Drop for BackgroundTask {
+    fn drop(&mut self) {
+        BACKGROUND_TASKS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Spawn a background task that shutdown waits for (briefly) before finalizing
+fn spawn_tracked<F>(task: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    BACKGROUND_TASKS.fetch_add(1, Ordering::SeqCst);
+    tokio::spawn(async move {
+        let _task = BackgroundTask;
+        task.await;
+    });
+}
+
+/// Run on quit: let in-flight memory tasks land, finalize the conversations still open this
+/// session, and checkpoint the database, so the crash-recovery path is rarely needed
+async fn graceful_shutdown() {
+    logging::log_conversation(None, "Shutting down");
+    
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(SHUTDOWN_TASK_GRACE_SECS);
+    while BACKGROUND_TASKS.load(Ordering::SeqCst) > 0 && std::time::Instant::now() < deadline {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let unfinished = BACKGROUND_TASKS.load(Ordering::SeqCst);
+    if unfinished > 0 {
+        logging::log_error(None, &format!("Quitting with {} background tasks still running", unfinished));
+    }
+    
+    let open = db::run_blocking(db::get_open_session_conversations)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let finalize_all = async {
+        for conversation_id in &open {
+            if let Err(e) = finalize_conversation_internal(conversation_id).await {
+                logging::log_error(Some(conversation_id), &format!("Finalize on quit failed: {}", e));
+            }
+        }
+    };
+    let timeout = std::time::Duration::from_secs(SHUTDOWN_FINALIZE_TIMEOUT_SECS);
+    if tokio::time::timeout(timeout, finalize_all).await.is_err() {
+        logging::log_error(None, "Finalizing on quit timed out; the rest will be recovered on next launch");
+    }
+    
+    if let Err(e) = db::run_blocking(db::checkpoint).await.and_then(|r| r.map_err(|e| e.to_string())) {
+        logging::log_error(None, &format!("Database checkpoint on quit failed: {}", e));
+    }
+}
+
+/// Hold the first exit request until shutdown work is done, then exit for real
+fn handle_run_event(app_handle: &tauri::AppHandle, event: tauri::RunEvent) {
+    match event {
+        tauri::RunEvent::ExitRequested { code, api, .. } => {
+            if SHUTDOWN_COMPLETE.load(Ordering::SeqCst) {
+                return;
+            }
+            api.prevent_exit();
+            if SHUTDOWN_STARTED.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                graceful_shutdown().await;
+                SHUTDOWN_COMPLETE.store(true, Ordering::SeqCst);
+                app_handle.exit(code.unwrap_or(0));
+            });
+        }
+        // intersect:// links from Shortcuts, Raycast, etc.
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        tauri::RunEvent::Opened { urls } => {
+            shortcuts::open_urls(app_handle, urls.iter().map(|url| url.to_string()).collect());
+        }
+        _ => {}
+    }
+}
+
+// ============ Offline Queue ============
+
+/// Background flushes skip a queued message after this many failed sends (a manual flush still tries it)
+const MAX_PENDING_ATTEMPTS: i64 = 3;
+
+// Set while queued messages are being sent
+static PENDING_FLUSH_RUNNING: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Progress of a queued message going out, emitted as "pending-message-progress"
+#[derive(Debug, Serialize)]
+pub struct PendingMessageProgress {
+    pub pending_id: String,
+    pub conversation_id: String,
+    pub status: String, // "sending" | "sent" | "failed"
+    pub remaining: usize,
+    pub result: Option<SendMessageResult>,
+    pub error: Option<String>,
+}
+
+async fn queue_offline_message(
+    conversation_id: &str,
+    user_message: &str,
+    active_agents: &[String],
+    disco_agents: &[String],
+    attachment_ids: &[String],
+    reply_to: Option<&str>,
+    voice: bool,
+) -> Result<SendMessageResult, String> {
+    let (id, content, reply_to) = (conversation_id.to_string(), user_message.to_string(), reply_to.map(str::to_string));
+    let (active_agents, disco_agents, attachment_ids) = (active_agents.to_vec(), disco_agents.to_vec(), attachment_ids.to_vec());
+    let pending = db::run_blocking(move || {
+        db::queue_pending_message(&id, &content, &active_agents, &disco_agents, &attachment_ids, reply_to.as_deref(), voice)
+    }).await?.map_err(|e| e.to_string())?;
+    logging::log_conversation(Some(conversation_id), "Message queued");
+    Ok(SendMessageResult {
+        responses: Vec::new(),
+        debate_mode: None,
+        weight_change: None,
+        governor_response: None,
+        queued: Some(pending),
+        routing: None,
+    })
+}
+
+fn spawn_pending_flush(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        flush_pending_messages_internal(&app_handle, false, None).await;
     });
+}
+
+/// Whether a conversation has queued messages that will still be sent automatically
+async fn has_sendable_pending_messages(conversation_id: &str) -> bool {
+    let id = conversation_id.to_string();
+    db::run_blocking(move || db::get_pending_messages(Some(&id)))
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .is_some_and(|pending| pending.iter().any(|p| p.attempts < MAX_PENDING_ATTEMPTS))
+}
+
+/// Send queued messages (every conversation's, or just one's) in the order they were written,
+/// emitting progress for each. Stops at the first connectivity failure; a message that fails
+/// otherwise stays queued with its error, and later messages in that conversation wait so the
+/// order is kept. Returns how many went out.
+async fn flush_pending_messages_internal(app_handle: &tauri::AppHandle, include_failed: bool, conversation_id: Option<&str>) -> usize {
+    {
+        let mut running = PENDING_FLUSH_RUNNING.lock().unwrap();
+        if *running {
+            return 0;
+        }
+        *running = true;
+    }
     
-    // Boost session weight for primary agent (immediate, decays over conversation)
-    boost_session_weight(&conversation_id, primary_agent, 0.02);
+    let id = conversation_id.map(str::to_string);
+    let pending: Vec<db::PendingMessage> = db::run_blocking(move || db::get_pending_messages(id.as_deref()).unwrap_or_default())
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| include_failed || p.attempts < MAX_PENDING_ATTEMPTS)
+        .collect();
+    let mut remaining = pending.len();
+    let mut held_back: HashSet<String> = HashSet::new();
+    let mut sent = 0;
     
-    // Get secondary agent response if needed
-    if decision.add_secondary {
-        if let Some(secondary_agent_str) = decision.secondary_agent {
-            // Handle "all_agents" request - get responses from all remaining active agents
-            if secondary_agent_str == "all" {
-                logging::log_routing(Some(&conversation_id), &format!(
-                    "All-agent request - getting responses from all {} agents", active_agents.len()
-                ));
-                
-                // Get the remaining agents (everyone except primary)
-                let remaining_agents: Vec<String> = active_agents.iter()
-                    .filter(|a| **a != decision.primary_agent)
-                    .cloned()
-                    .collect();
-                
-                for (idx, agent_str) in remaining_agents.iter().enumerate() {
-                    if let Some(agent) = Agent::from_str(agent_str) {
-                        agents_involved.push(agent.as_str().to_string());
-                        
-                        let response_type = if idx == 0 { ResponseType::Addition } else { ResponseType::Addition };
-                        
-                        let agent_response = orchestrator
-                            .get_agent_response_with_grounding(
-                                agent,
-                                &user_message,
-                                &recent_messages,
-                                response_type,
-                                Some(&primary_response),
-                                Some(primary_agent.as_str()),
-                                grounding.as_ref(),
-                                user_profile.as_ref(),
-                                is_agent_disco(agent.as_str()), // Per-agent disco
-                                primary_is_disco, // Whether primary agent was in disco
-                            )
-                            .await
-                            .map_err(|e| e.to_string())?;
-                        
-                        // Save response
-                        let msg = Message {
-                            id: Uuid::new_v4().to_string(),
-                            conversation_id: conversation_id.clone(),
-                            role: agent.as_str().to_string(),
-                            content: agent_response.clone(),
-                            response_type: Some(response_type.as_str().to_string()),
-                            references_message_id: Some(primary_msg_id.clone()),
-                            timestamp: Utc::now().to_rfc3339(),
-                        };
-                        db::save_message(&msg).map_err(|e| e.to_string())?;
-                        
-                        responses.push(AgentResponse {
-                            agent: agent.as_str().to_string(),
-                            content: agent_response,
-                            response_type: response_type.as_str().to_string(),
-                            references_message_id: Some(primary_msg_id.clone()),
-                        });
-                    }
-                }
-            } else if let Some(secondary_agent) = Agent::from_str(&secondary_agent_str) {
-                agents_involved.push(secondary_agent.as_str().to_string());
-                
-                let response_type = decision.secondary_type
-                    .as_ref()
-                    .and_then(|t| ResponseType::from_str(t))
-                    .unwrap_or(ResponseType::Addition);
-                
-                // Set debate mode based on response type
-                debate_mode = match response_type {
-                    ResponseType::Addition => None,
-                    ResponseType::Rebuttal => Some("mild".to_string()),
-                    ResponseType::Debate => Some("intense".to_string()),
-                    _ => None,
-                };
-                
-                // Check if secondary agent is in disco mode
-                let secondary_is_disco = is_agent_disco(secondary_agent.as_str());
-                if secondary_is_disco {
-                    logging::log_agent(Some(&conversation_id), &format!(
-                        "{} in DISCO MODE - using extreme prompts", secondary_agent.as_str()
-                    ));
+    for queued in pending {
+        if held_back.contains(&queued.conversation_id) {
+            continue;
+        }
+        let progress = |status: &str, remaining: usize, result: Option<SendMessageResult>, error: Option<String>| PendingMessageProgress {
+            pending_id: queued.id.clone(),
+            conversation_id: queued.conversation_id.clone(),
+            status: status.to_string(),
+            remaining,
+            result,
+            error,
+        };
+        let _ = app_handle.emit("pending-message-progress", &progress("sending", remaining, None, None));
+        
+        let id = queued.conversation_id.clone();
+        let start_seq = db::run_blocking(move || db::get_last_seq(&id).unwrap_or(0)).await.unwrap_or(0);
+        let started_at = Utc::now().to_rfc3339();
+        let user_msg = Message {
+            id: Uuid::new_v4().to_string(),
+            conversation_id: queued.conversation_id.clone(),
+            role: "user".to_string(),
+            content: queued.content.clone(),
+            response_type: None,
+            references_message_id: queued.reply_to.clone(),
+            timestamp: queued.queued_at.clone(),
+            attachments: Vec::new(),
+            starred: false,
+        };
+        let user_msg_id = user_msg.id.clone();
+        if !queued.attachment_ids.is_empty() {
+            let (message_id, id, ids) = (user_msg_id.clone(), queued.conversation_id.clone(), queued.attachment_ids.clone());
+            let _ = db::run_blocking(move || db::link_attachments(&message_id, &id, &ids)).await;
+        }
+        match run_turn(app_handle.clone(), user_msg, queued.active_agents.clone(), queued.disco_agents.clone()).await {
+            Ok(result) => {
+                if queued.voice {
+                    annotate_message(&user_msg_id, "origin", serde_json::json!("voice"));
                 }
-                
-                let secondary_response = orchestrator
-                    .get_agent_response_with_grounding(
-                        secondary_agent,
-                        &user_message,
-                        &recent_messages,
-                        response_type,
-                        Some(&primary_response),
-                        Some(primary_agent.as_str()),
-                        grounding.as_ref(),
-                        user_profile.as_ref(),
-                        secondary_is_disco, // Per-agent disco
-                        primary_is_disco, // Whether primary agent was in disco
-                    )
-                    .await
-                    .map_err(|e| e.to_string())?;
-                
-                // Save secondary response
-                let secondary_msg = Message {
-                    id: Uuid::new_v4().to_string(),
-                    conversation_id: conversation_id.clone(),
-                    role: secondary_agent.as_str().to_string(),
-                    content: secondary_response.clone(),
-                    response_type: Some(response_type.as_str().to_string()),
-                    references_message_id: Some(primary_msg_id.clone()),
-                    timestamp: Utc::now().to_rfc3339(),
-                };
-                db::save_message(&secondary_msg).map_err(|e| e.to_string())?;
-                
-                responses.push(AgentResponse {
-                    agent: secondary_agent.as_str().to_string(),
-                    content: secondary_response.clone(),
-                    response_type: response_type.as_str().to_string(),
-                    references_message_id: Some(primary_msg_id.clone()),
-                });
-                
-                // Boost session weight for secondary agent (immediate, decays over conversation)
-                boost_session_weight(&conversation_id, secondary_agent, 0.015);
-                
-                // ===== MULTI-TURN DEBATE LOOP =====
-                // Allow debates when there's genuine disagreement (rebuttal/debate), not just additions
-                // Disco mode makes debates more likely/intense, but they can happen in normal mode too
-                if response_type != ResponseType::Addition {
-                    let mut responses_so_far: Vec<(String, String)> = vec![
-                        (primary_agent.as_str().to_string(), primary_response.clone()),
-                        (secondary_agent.as_str().to_string(), secondary_response.clone()),
-                    ];
-                    
-                    let mut last_response = secondary_response.clone();
-                    let mut last_agent = secondary_agent.as_str().to_string();
-                    let mut last_agent_disco = secondary_is_disco;
-                    let mut last_msg_id = secondary_msg.id.clone();
-                    
-                    // Try to continue debate (up to 2 more responses, max 4 total)
-                    for turn in 0..2 {
-                        let response_count = responses_so_far.len();
-                        
-                        let (should_continue, next_agent_str, next_type) = orchestrator
-                            .should_continue_debate(
-                                &user_message,
-                                &responses_so_far,
-                                &active_agents,
-                                has_any_disco,
-                                response_count,
-                            )
-                            .await
-                            .unwrap_or((false, None, None));
-                        
-                        if !should_continue {
-                            logging::log_agent(Some(&conversation_id), &format!(
-                                "Debate ending after {} responses (turn {})", response_count, turn
-                            ));
-                            break;
-                        }
-                        
-                        if let Some(next_agent_name) = next_agent_str {
-                            if let Some(next_agent) = Agent::from_str(&next_agent_name) {
-                                agents_involved.push(next_agent.as_str().to_string());
-                                
-                                let next_response_type = next_type
-                                    .as_ref()
-                                    .and_then(|t| ResponseType::from_str(t))
-                                    .unwrap_or(ResponseType::Rebuttal);
-                                
-                                let next_agent_disco = is_agent_disco(next_agent.as_str());
-                                logging::log_agent(Some(&conversation_id), &format!(
-                                    "Debate turn {}: {} responding (disco: {})", turn + 1, next_agent.as_str(), next_agent_disco
-                                ));
-                                
-                                let next_response = orchestrator
-                                    .get_agent_response_with_grounding(
-                                        next_agent,
-                                        &user_message,
-                                        &recent_messages,
-                                        next_response_type,
-                                        Some(&last_response),
-                                        Some(&last_agent),
-                                        grounding.as_ref(),
-                                        user_profile.as_ref(),
-                                        next_agent_disco, // Per-agent disco
-                                        last_agent_disco, // Whether last agent was in disco
-                                    )
-                                    .await
-                                    .map_err(|e| e.to_string())?;
-                                
-                                // Save debate response
-                                let next_msg_id = Uuid::new_v4().to_string();
-                                let next_msg = Message {
-                                    id: next_msg_id.clone(),
-                                    conversation_id: conversation_id.clone(),
-                                    role: next_agent.as_str().to_string(),
-                                    content: next_response.clone(),
-                                    response_type: Some(next_response_type.as_str().to_string()),
-                                    references_message_id: Some(last_msg_id.clone()),
-                                    timestamp: Utc::now().to_rfc3339(),
-                                };
-                                db::save_message(&next_msg).map_err(|e| e.to_string())?;
-                                
-                                responses.push(AgentResponse {
-                                    agent: next_agent.as_str().to_string(),
-                                    content: next_response.clone(),
-                                    response_type: next_response_type.as_str().to_string(),
-                                    references_message_id: Some(last_msg_id.clone()),
-                                });
-                                
-                                // Boost session weight for debate agent (immediate, decays over conversation)
-                                boost_session_weight(&conversation_id, next_agent, 0.015);
-                                
-                                // Update for next iteration
-                                responses_so_far.push((next_agent.as_str().to_string(), next_response.clone()));
-                                last_response = next_response;
-                                last_agent = next_agent.as_str().to_string();
-                                last_agent_disco = next_agent_disco;
-                                last_msg_id = next_msg_id;
-                                
-                                // Intensify debate mode if we're continuing
-                                if response_count >= 4 {
-                                    debate_mode = Some("intense".to_string());
-                                }
-                            }
-                        } else {
-                            break;
-                        }
-                    }
+                let id = queued.id.clone();
+                let _ = db::run_blocking(move || db::delete_pending_message(&id)).await;
+                sent += 1;
+                remaining -= 1;
+                let _ = app_handle.emit("pending-message-progress", &progress("sent", remaining, Some(result), None));
+            }
+            Err(e) => {
+                let message_id = user_msg_id.clone();
+                let _ = db::run_blocking(move || db::unlink_attachments(&message_id)).await;
+                let _ = roll_back_turn(&queued.conversation_id, start_seq, &started_at).await;
+                let (id, error) = (queued.id.clone(), e.clone());
+                let _ = db::run_blocking(move || db::record_pending_attempt(&id, &error)).await;
+                let _ = app_handle.emit("pending-message-progress", &progress("failed", remaining, None, Some(e.clone())));
+                logging::log_error(Some(&queued.conversation_id), &format!("Queued message failed to send: {}", e));
+                if offline::is_connectivity_error(&e) {
+                    break;
                 }
+                held_back.insert(queued.conversation_id.clone());
             }
         }
     }
     
-    // ===== GOVERNOR SYNTHESIS: Generate synthesized response after reading agent thoughts =====
-    let governor_response = if !responses.is_empty() {
-        // Collect agent responses as tuples of (agent_name, content)
-        let agent_responses: Vec<(String, String)> = responses
-            .iter()
-            .map(|r| (r.agent.clone(), r.content.clone()))
-            .collect();
-        
-        // Generate Governor's synthesized response
-        match generate_governor_response(
-            &anthropic_key,
-            &user_message,
-            &agent_responses,
-            &recent_messages,
-            has_any_disco,
-            user_profile.as_ref(),
-            Some(active_persona.dominant_trait.as_str()),
-            None, // No journey phase in Text Mode
-        ).await {
-            Ok(response) => {
-                // Save Governor response to database
-                let governor_msg = Message {
-                    id: Uuid::new_v4().to_string(),
-                    conversation_id: conversation_id.clone(),
-                    role: "governor".to_string(),
-                    content: response.clone(),
-                    response_type: None,
-                    references_message_id: None,
-                    timestamp: Utc::now().to_rfc3339(),
-                };
-                if let Err(e) = db::save_message(&governor_msg) {
-                    logging::log_error(Some(&conversation_id), &format!(
-                        "Failed to save Governor response: {}", e
-                    ));
+    if sent > 0 {
+        logging::log_conversation(None, &format!("Sent {} queued messages", sent));
+    }
+    *PENDING_FLUSH_RUNNING.lock().unwrap() = false;
+    sent
+}
+
+#[tauri::command]
+fn get_pending_messages(conversation_id: Option<String>) -> Result<Vec<db::PendingMessage>, String> {
+    db::get_pending_messages(conversation_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn discard_pending_message(id: String) -> Result<bool, String> {
+    db::delete_pending_message(&id).map_err(|e| e.to_string())
+}
+
+/// Try sending the queue now (including messages that failed before); returns how many went out
+#[tauri::command]
+async fn flush_pending_messages(app_handle: tauri::AppHandle) -> Result<usize, String> {
+    if !offline::refresh(&app_handle).await {
+        return Err("Still offline".to_string());
+    }
+    Ok(flush_pending_messages_internal(&app_handle, true, None).await)
+}
+
+#[tauri::command]
+async fn check_connectivity(app_handle: tauri::AppHandle) -> bool {
+    offline::refresh(&app_handle).await
+}
+
+// ============ Chat Export Import ============
+
+/// Imported conversations fetched per extraction pass
+const IMPORT_EXTRACTION_BATCH: usize = 10;
+/// Messages per extractor call; long imported conversations are split so nothing is truncated
+const IMPORT_EXTRACTION_CHUNK: usize = 40;
+
+// Set while the background extraction over imported conversations is running
+static IMPORT_EXTRACTION_RUNNING: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatImportResult {
+    pub source: String,      // "chatgpt" | "claude"
+    pub imported: usize,
+    pub skipped: usize,      // Already imported earlier
+    pub messages: usize,
+    pub extracting: bool,    // Memory extraction continues in the background
+}
+
+/// Import a ChatGPT or Claude data export (`conversations.json`). Assistant replies are attributed
+/// to `assistant_role` (default "logic"). Conversations imported before are skipped, so re-running
+/// on a newer export only adds what's new. With extract_memory, facts, patterns and themes are
+/// extracted from the imported conversations in the background.
+#[tauri::command]
+async fn import_chat_export(path: String, assistant_role: Option<String>, extract_memory: bool) -> Result<ChatImportResult, String> {
+    let assistant_role = assistant_role.unwrap_or_else(|| "logic".to_string());
+    if Agent::from_str(&assistant_role).is_none() {
+        return Err(format!("Unknown agent: {}", assistant_role));
+    }
+    
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let (source, conversations) = importer::parse_chat_export(&text)?;
+    
+    let now = Utc::now().to_rfc3339();
+    let mut result = db::run_blocking(move || -> Result<ChatImportResult, String> {
+        let mut result = ChatImportResult {
+            source: source.as_str().to_string(),
+            imported: 0,
+            skipped: 0,
+            messages: 0,
+            extracting: false,
+        };
+        for conversation in &conversations {
+            let created_at = conversation.created_at.clone()
+                .or_else(|| conversation.messages.first().and_then(|m| m.timestamp.clone()))
+                .unwrap_or_else(|| now.clone());
+            let updated_at = conversation.updated_at.clone()
+                .or_else(|| conversation.messages.last().and_then(|m| m.timestamp.clone()))
+                .unwrap_or_else(|| created_at.clone());
+            let messages: Vec<(String, String, String)> = conversation.messages.iter()
+                .map(|m| {
+                    let role = if m.from_user { "user".to_string() } else { assistant_role.clone() };
+                    (role, m.content.clone(), m.timestamp.clone().unwrap_or_else(|| created_at.clone()))
+                })
+                .collect();
+            
+            let saved = db::save_imported_conversation(
+                source.as_str(),
+                &conversation.external_id,
+                conversation.title.as_deref().unwrap_or("Imported conversation"),
+                &created_at,
+                &updated_at,
+                &messages,
+                extract_memory,
+            ).map_err(|e| e.to_string())?;
+            match saved {
+                Some(_) => {
+                    result.imported += 1;
+                    result.messages += messages.len();
                 }
-                Some(response)
-            }
-            Err(e) => {
-                logging::log_error(Some(&conversation_id), &format!(
-                    "Failed to generate Governor response: {}", e
-                ));
-                None
+                None => result.skipped += 1,
             }
         }
-    } else {
-        None
-    };
+        Ok(result)
+    }).await??;
     
-    // Increment message count
-    db::increment_message_count().map_err(|e| e.to_string())?;
+    logging::log_conversation(None, &format!(
+        "Imported {} {} conversations ({} messages, {} already imported) from {}",
+        result.imported, result.source, result.messages, result.skipped, path
+    ));
     
-    // ===== TRAIT ANALYSIS: Run in background AFTER response (non-blocking) =====
-    // This was moved from before routing to improve response speed
+    // Queued extractions stay pending while the budget is exceeded and run on a later import
+    if extract_memory && result.imported > 0 && memory_extraction_enabled() && !usage::skip_optional_calls("anthropic") {
+        let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+        if let Some(anthropic_key) = profile.anthropic_key {
+            result.extracting = true;
+            tauri::async_runtime::spawn(async move {
+                extract_imported_memory(&anthropic_key).await;
+            });
+        }
+    }
+    
+    Ok(result)
+}
+
+/// Run the memory extractor over imported conversations still queued for it, a batch at a time.
+/// Progress is kept per conversation, so an interrupted run picks up where it left off next import.
+async fn extract_imported_memory(anthropic_key: &str) {
     {
-        let anthropic_key_for_traits = anthropic_key.clone();
-        let user_message_for_traits = user_message.clone();
-        let conversation_id_for_traits = conversation_id.clone();
-        let has_any_disco_for_traits = has_any_disco;
-        let total_messages_for_traits = profile.total_messages;
-        
-        // Collect previous agent responses for engagement analysis
-        let previous_responses_for_traits: Vec<(String, String)> = recent_messages
-            .iter()
-            .rev()
-            .take_while(|m| m.role != "user")
-            .filter(|m| m.role != "system")
-            .map(|m| (m.role.clone(), m.content.clone()))
-            .collect();
-        
-        tokio::spawn(async move {
-            logging::log_routing(Some(&conversation_id_for_traits), "[BACKGROUND] Starting trait analysis...");
-            
-            // 1. Intrinsic Trait Analysis
-            let intrinsic_analyzer = IntrinsicTraitAnalyzer::new(&anthropic_key_for_traits);
-            let intrinsic_analysis = intrinsic_analyzer.analyze(&user_message_for_traits).await.ok();
-            
-            if let Some(ref intrinsic) = intrinsic_analysis {
-                logging::log_routing(Some(&conversation_id_for_traits), &format!(
-                    "[BACKGROUND] Intrinsic signals - L:{:.2} I:{:.2} P:{:.2}",
-                    intrinsic.logic_signal, intrinsic.instinct_signal, intrinsic.psyche_signal
-                ));
+        let mut running = IMPORT_EXTRACTION_RUNNING.lock().unwrap();
+        if *running {
+            return;
+        }
+        *running = true;
+    }
+    
+    let extractor = MemoryExtractor::new(anthropic_key);
+    let mut extracted = 0;
+    // Conversations where every chunk failed stay queued for the next import, skipped for the rest of this run
+    let mut failed: HashSet<String> = HashSet::new();
+    loop {
+        let limit = IMPORT_EXTRACTION_BATCH + failed.len();
+        let batch = db::run_blocking(move || db::get_pending_import_extractions(limit))
+            .await
+            .and_then(|r| r.map_err(|e| e.to_string()));
+        let batch: Vec<String> = match batch {
+            Ok(batch) => batch.into_iter().filter(|id| !failed.contains(id)).collect(),
+            Err(e) => {
+                logging::log_error(None, &format!("Import extraction stopped: {}", e));
+                break;
             }
-            
-            // 2. Engagement Analysis (if there were previous agent responses)
-            let engagement_analysis = if !previous_responses_for_traits.is_empty() {
-                let previous_with_agents: Vec<(Agent, String)> = previous_responses_for_traits
-                    .iter()
-                    .filter_map(|(role, content)| {
-                        Agent::from_str(role).map(|agent| (agent, content.clone()))
-                    })
-                    .collect();
-                
-                if !previous_with_agents.is_empty() {
-                    let engagement_analyzer = EngagementAnalyzer::new(&anthropic_key_for_traits);
-                    engagement_analyzer.analyze_engagement(&user_message_for_traits, &previous_with_agents).await.ok()
-                } else {
-                    None
+        };
+        if batch.is_empty() {
+            break;
+        }
+        
+        for conversation_id in batch {
+            let id = conversation_id.clone();
+            let messages: Vec<(String, String)> = db::run_blocking(move || db::get_conversation_messages(&id).unwrap_or_default())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|m| m.role != "system")
+                .map(|m| (m.role, m.content))
+                .collect();
+            let mut succeeded = false;
+            for chunk in messages.chunks(IMPORT_EXTRACTION_CHUNK) {
+                // Re-read facts each chunk so later chunks confirm rather than duplicate earlier ones
+                let existing_facts = db::run_blocking(|| db::get_all_user_facts().unwrap_or_default())
+                    .await
+                    .unwrap_or_default();
+                match extractor.extract_from_transcript(chunk, &existing_facts, &conversation_id).await {
+                    Ok(_) => succeeded = true,
+                    Err(e) => logging::log_error(Some(&conversation_id), &format!("Import extraction failed: {}", e)),
                 }
-            } else {
-                None
-            };
-            
-            if let Some(ref engagement) = engagement_analysis {
-                logging::log_routing(Some(&conversation_id_for_traits), &format!(
-                    "[BACKGROUND] Engagement scores - L:{:.2} I:{:.2} P:{:.2}",
-                    engagement.logic_score, engagement.instinct_score, engagement.psyche_score
-                ));
             }
-            
-            // 3. Update weights if we have analysis
-            if intrinsic_analysis.is_some() || engagement_analysis.is_some() {
-                if let Ok(current_profile) = db::get_user_profile() {
-                    let current_weights = (current_profile.instinct_weight, current_profile.logic_weight, current_profile.psyche_weight);
-                    
-                    let new_weights = combine_trait_analyses(
-                        current_weights,
-                        engagement_analysis.as_ref(),
-                        intrinsic_analysis.as_ref(),
-                        has_any_disco_for_traits,
-                        total_messages_for_traits,
-                    );
-                    
-                    if let Err(e) = db::update_weights(new_weights.0, new_weights.1, new_weights.2) {
-                        logging::log_error(Some(&conversation_id_for_traits), &format!(
-                            "[BACKGROUND] Failed to update weights: {}", e
-                        ));
-                    } else {
-                        logging::log_routing(Some(&conversation_id_for_traits), &format!(
-                            "[BACKGROUND] Updated weights - I:{:.3} L:{:.3} P:{:.3}",
-                            new_weights.0, new_weights.1, new_weights.2
-                        ));
-                    }
-                }
+            if !succeeded && !messages.is_empty() {
+                failed.insert(conversation_id);
+                continue;
             }
-        });
+            let id = conversation_id.clone();
+            let _ = db::run_blocking(move || {
+                let _ = db::advance_extraction_watermark(&id, db::get_last_seq(&id).unwrap_or(0));
+                db::mark_import_extracted(&id)
+            }).await;
+            extracted += 1;
+        }
     }
     
-    // ===== MEMORY SYSTEM: Extract Facts & Patterns (async, non-blocking) =====
-    let anthropic_key_clone = anthropic_key.clone();
-    let user_message_clone = user_message.clone();
-    let conversation_id_clone = conversation_id.clone();
-    let responses_for_extraction: Vec<(String, String)> = responses
-        .iter()
-        .map(|r| (r.agent.clone(), r.content.clone()))
-        .collect();
-    let existing_facts_clone = existing_facts;
+    *IMPORT_EXTRACTION_RUNNING.lock().unwrap() = false;
+    logging::log_memory(None, &format!(
+        "Extracted memory from {} imported conversations ({} left queued after failing)", extracted, failed.len()
+    ));
+}
+
+// ============ Agent Mutes ============
+
+#[tauri::command]
+fn mute_agent(conversation_id: String, agent: String, reason: Option<String>) -> Result<(), String> {
+    if Agent::from_str(&agent).is_none() {
+        return Err(format!("Unknown agent: {}", agent));
+    }
+    db::mute_agent(&conversation_id, &agent, reason.as_deref()).map_err(|e| e.to_string())?;
+    logging::log_routing(Some(&conversation_id), &format!("Muted {} ({})", agent, reason.as_deref().unwrap_or("no reason")));
+    Ok(())
+}
+
+#[tauri::command]
+fn unmute_agent(conversation_id: String, agent: String) -> Result<bool, String> {
+    let unmuted = db::unmute_agent(&conversation_id, &agent).map_err(|e| e.to_string())?;
+    if unmuted {
+        logging::log_routing(Some(&conversation_id), &format!("Unmuted {}", agent));
+    }
+    Ok(unmuted)
+}
+
+#[tauri::command]
+fn get_muted_agents(conversation_id: String) -> Result<Vec<db::AgentMute>, String> {
+    db::get_muted_agents(&conversation_id).map_err(|e| e.to_string())
+}
+
+/// Prompt note for an agent's first response after being unmuted
+fn return_from_mute_note(mute: &db::AgentMute) -> String {
+    let reason = mute.reason.as_deref()
+        .map(|r| format!(" The user's reason for muting you: \"{}\".", r))
+        .unwrap_or_default();
+    format!(
+        "RETURNING FROM MUTE: The user muted you in this conversation and has just brought you back. You missed {} of their messages.{} \
+Briefly and naturally acknowledge you've been away (a few words, not an apology speech) -- don't pretend you followed everything you missed, and respect the reason if one was given.",
+        mute.missed_exchanges, reason
+    )
+}
+
+/// Response styles the user engages with for an agent, under the active profile
+#[tauri::command]
+fn get_agent_style_preferences(agent: String) -> Result<Vec<db::AgentStylePreference>, String> {
+    let active = db::get_active_persona_profile().map_err(|e| e.to_string())?
+        .ok_or("No active persona profile")?;
+    db::get_agent_style_preferences(&active.id, &agent).map_err(|e| e.to_string())
+}
+
+// ============ Notifications ============
+
+#[tauri::command]
+fn get_notifications(unread_only: Option<bool>, limit: Option<usize>) -> Result<Vec<db::Notification>, String> {
+    db::get_notifications(unread_only.unwrap_or(false), limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn mark_notification_read(id: String) -> Result<(), String> {
+    db::mark_notification_read(&id).map_err(|e| e.to_string())
+}
+
+// ============ Reports & Digest ============
+
+#[tauri::command]
+fn get_reports(kind: Option<String>, limit: Option<usize>) -> Result<Vec<db::Report>, String> {
+    db::get_reports(kind.as_deref(), limit.unwrap_or(20).min(100)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_digest_settings() -> Result<scheduler::DigestSettings, String> {
+    Ok(scheduler::get_digest_settings())
+}
+
+#[tauri::command]
+fn set_digest_settings(settings: scheduler::DigestSettings) -> Result<(), String> {
+    scheduler::set_digest_settings(&settings)
+}
+
+/// Write a digest of the last interval now instead of waiting for the schedule.
+/// Returns None when there were no conversations to digest.
+#[tauri::command]
+async fn generate_digest_now() -> Result<Option<db::Report>, String> {
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let anthropic_key = profile.anthropic_key.ok_or("Anthropic API key not set")?;
+    let now = clock::now();
+    let since = now - chrono::Duration::days(scheduler::get_digest_settings().interval_days);
+    scheduler::generate_digest(&anthropic_key, &since.to_rfc3339(), &now.to_rfc3339())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// ============ Trait Analytics ============
+
+/// Raw engagement scores per analyzed user message, newest first
+#[tauri::command]
+fn get_engagement_history(conversation_id: Option<String>, limit: Option<usize>) -> Result<Vec<db::EngagementRecord>, String> {
+    db::get_engagement_history(conversation_id.as_deref(), limit.unwrap_or(100)).map_err(|e| e.to_string())
+}
+
+/// Raw intrinsic trait signals per analyzed user message, newest first
+#[tauri::command]
+fn get_intrinsic_signal_history(conversation_id: Option<String>, limit: Option<usize>) -> Result<Vec<db::IntrinsicSignalRecord>, String> {
+    db::get_intrinsic_signal_history(conversation_id.as_deref(), limit.unwrap_or(100)).map_err(|e| e.to_string())
+}
+
+// ============ Habits ============
+
+#[tauri::command]
+fn get_habits() -> Result<Vec<db::HabitProgress>, String> {
+    db::get_habit_progress().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn declare_habit(name: String, target_per_week: Option<i64>) -> Result<String, String> {
+    if name.trim().is_empty() {
+        return Err("Habit name is required".to_string());
+    }
+    db::upsert_habit(&name, target_per_week, "explicit").map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn log_habit(habit_id: String, note: Option<String>) -> Result<(), String> {
+    db::log_habit(&habit_id, note.as_deref(), None).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn archive_habit(habit_id: String) -> Result<(), String> {
+    db::set_habit_active(&habit_id, false).map_err(|e| e.to_string())
+}
+
+// ============ Drafts ============
+
+/// Draft an email, text, or tough reply with whichever agent routing picks for the context
+#[tauri::command]
+async fn draft_message(kind: String, context: String) -> Result<db::Draft, String> {
+    if !["email", "text", "tough_reply"].contains(&kind.as_str()) {
+        return Err(format!("Invalid draft kind: {}", kind));
+    }
     
-    logging::log_memory(Some(&conversation_id), "Spawning extraction task...");
+    let (profile, active_persona) = db::run_blocking(|| -> rusqlite::Result<_> {
+        Ok((db::get_user_profile()?, db::get_active_persona_profile()?))
+    }).await?.map_err(|e| e.to_string())?;
+    let api_key = profile.api_key.clone(); // None = Anthropic-only mode
+    let anthropic_key = profile.anthropic_key.clone().ok_or("Anthropic API key not set")?;
     
-    // Spawn memory extraction as a background task (uses Anthropic Opus)
-    tokio::spawn(async move {
-        logging::log_memory(Some(&conversation_id_clone), "Extraction task started");
-        let extractor = MemoryExtractor::new(&anthropic_key_clone);
-        match extractor.extract_from_exchange(
-            &user_message_clone,
-            &responses_for_extraction,
-            &existing_facts_clone,
-            &conversation_id_clone,
-        ).await {
-            Ok(result) => logging::log_memory(Some(&conversation_id_clone), &format!(
-                "Extraction completed: {} facts, {} patterns",
-                result.new_facts.len(), result.new_patterns.len()
-            )),
-            Err(e) => logging::log_error(Some(&conversation_id_clone), &format!(
-                "Extraction failed: {}", e
-            )),
-        }
-    });
+    let all_agents = vec!["instinct".to_string(), "logic".to_string(), "psyche".to_string()];
+    let decision = decide_response_heuristic(
+        &context,
+        (profile.instinct_weight, profile.logic_weight, profile.psyche_weight),
+        &all_agents,
+        &[],
+        false,
+        active_persona.as_ref().map(|p| (p.instinct_points, p.logic_points, p.psyche_points)),
+        active_persona.as_ref().map(|p| p.dominant_trait.as_str()),
+        None,
+        None,
+    );
+    let agent = Agent::from_str(&decision.primary_agent).unwrap_or(Agent::Logic);
     
-    // ===== MEMORY SYSTEM: Append to Limbo Summary (crash-safe incremental summary) =====
-    // This happens every exchange so the conversation is always recoverable
-    {
-        let agents_summary: Vec<String> = responses.iter()
-            .map(|r| format!("{}: {}", r.agent, truncate_for_summary(&r.content, 100)))
-            .collect();
-        let exchange_note = format!(
-            "User: {}\n{}",
-            truncate_for_summary(&user_message, 100),
-            agents_summary.join("\n")
-        );
-        let _ = db::append_limbo_summary(&conversation_id, &exchange_note);
-        logging::log_memory(Some(&conversation_id), "Appended exchange to limbo summary");
+    let user_profile = MemoryExtractor::build_profile_summary().ok();
+    let orchestrator = Orchestrator::new(api_key.as_deref(), &anthropic_key);
+    let (content, rationale) = orchestrator
+        .draft_message(agent, &kind, &context, None, user_profile.as_ref())
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    logging::log_agent(None, &format!("{} drafted a {}", agent.as_str(), kind));
+    db::run_blocking(move || db::create_draft(&kind, &context, agent.as_str(), &content, &rationale))
+        .await?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn refine_draft(id: String, instruction: String) -> Result<db::Draft, String> {
+    let draft_id = id.clone();
+    let (draft, profile) = db::run_blocking(move || -> rusqlite::Result<_> {
+        Ok((db::get_draft(&draft_id)?, db::get_user_profile()?))
+    }).await?.map_err(|e| e.to_string())?;
+    let draft = draft.ok_or("Draft not found")?;
+    let api_key = profile.api_key.clone(); // None = Anthropic-only mode
+    let anthropic_key = profile.anthropic_key.clone().ok_or("Anthropic API key not set")?;
+    let agent = Agent::from_str(&draft.agent).unwrap_or(Agent::Logic);
+    
+    let user_profile = MemoryExtractor::build_profile_summary().ok();
+    let orchestrator = Orchestrator::new(api_key.as_deref(), &anthropic_key);
+    let (content, rationale) = orchestrator
+        .draft_message(agent, &draft.kind, &draft.context, Some((&draft.content, &instruction)), user_profile.as_ref())
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    db::run_blocking(move || -> rusqlite::Result<_> {
+        db::update_draft(&id, &content, &rationale)?;
+        db::get_draft(&id)
+    }).await?.map_err(|e| e.to_string())?.ok_or_else(|| "Draft not found".to_string())
+}
+
+#[tauri::command]
+fn get_recent_drafts(limit: usize) -> Result<Vec<db::Draft>, String> {
+    db::get_recent_drafts(limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_draft(id: String) -> Result<(), String> {
+    db::delete_draft(&id).map_err(|e| e.to_string())
+}
+
+// ============ Persona Suggestion ============
+
+/// User messages a conversation needs before we judge which persona it belongs to
+const PERSONA_SUGGESTION_MIN_MESSAGES: usize = 6;
+/// How much better another persona must fit than the active one to be suggested
+const PERSONA_SUGGESTION_MARGIN: f64 = 0.15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaSuggestion {
+    pub conversation_id: String,
+    pub profile_id: String,
+    pub profile_name: String,
+    pub message: String,
+    pub created_at: String,
+}
+
+/// Score how well a conversation matches a persona's baseline (0.0 - 1.0)
+/// Combines agent-mix similarity with topic overlap when topics are known
+fn persona_fit_score(
+    agent_share: (f64, f64, f64),
+    topics: &HashSet<String>,
+    persona: &db::PersonaProfile,
+    activity: &db::PersonaActivity,
+) -> f64 {
+    let distance = (agent_share.0 - persona.instinct_weight).abs()
+        + (agent_share.1 - persona.logic_weight).abs()
+        + (agent_share.2 - persona.psyche_weight).abs();
+    let agent_similarity = 1.0 - (distance / 2.0).min(1.0);
+    
+    let persona_topics: HashSet<String> = activity.top_topics.iter().map(|(t, _)| t.clone()).collect();
+    if topics.is_empty() || persona_topics.is_empty() {
+        return agent_similarity;
     }
+    let overlap = topics.intersection(&persona_topics).count() as f64;
+    let union = topics.union(&persona_topics).count() as f64;
     
-    // ===== MEMORY SYSTEM: Summarize Conversation Periodically =====
-    let message_count = profile.total_messages + 1;
-    if message_count % 10 == 0 {
-        // Every 10 messages, update conversation summary (uses Anthropic Opus)
-        let anthropic_key_for_summary = anthropic_key.clone();
-        let conversation_id_for_summary = conversation_id.clone();
-        let agents_for_summary = agents_involved.clone();
-        
-        tokio::spawn(async move {
-            let summarizer = ConversationSummarizer::new(&anthropic_key_for_summary);
-            let all_messages = db::get_conversation_messages(&conversation_id_for_summary).unwrap_or_default();
-            
-            // Get existing summary
-            let existing = db::get_conversation_summary(&conversation_id_for_summary).ok().flatten();
-            let existing_text = existing.as_ref().map(|s| s.summary.as_str());
-            
-            // Only summarize messages not in the existing summary
-            let messages_to_summarize = if existing.is_some() {
-                // Get the last 15 messages to create a rolling summary
-                all_messages.into_iter().rev().take(15).rev().collect::<Vec<_>>()
-            } else {
-                all_messages
-            };
-            
-            if let Ok(result) = summarizer.summarize(&messages_to_summarize, existing_text).await {
-                let _ = ConversationSummarizer::save_summary(
-                    &conversation_id_for_summary,
-                    &result,
-                    message_count,
-                    &agents_for_summary,
-                );
-            }
-        });
+    0.5 * agent_similarity + 0.5 * (overlap / union)
+}
+
+/// Compare a conversation against every persona baseline; stores and returns a suggestion if one fits clearly better
+fn check_persona_suggestion(conversation_id: &str, active_persona_id: &str) -> Option<PersonaSuggestion> {
+    let messages = db::get_conversation_messages(conversation_id).ok()?;
+    let user_messages = messages.iter().filter(|m| m.role == "user").count();
+    if user_messages < PERSONA_SUGGESTION_MIN_MESSAGES {
+        return None;
+    }
+    if !PERSONA_SUGGESTION_CHECKED.lock().unwrap().insert(conversation_id.to_string()) {
+        return None;
     }
     
-    // Weight changes are handled by background analysis only (base weights)
-    // Session weights decay automatically and don't generate notifications
-    Ok(SendMessageResult { responses, debate_mode, weight_change: None, governor_response })
+    // Which agents actually carried this conversation
+    let (mut instinct, mut logic, mut psyche) = (0.0, 0.0, 0.0);
+    for m in &messages {
+        match m.role.as_str() {
+            "instinct" => instinct += 1.0,
+            "logic" => logic += 1.0,
+            "psyche" => psyche += 1.0,
+            _ => {}
+        }
+    }
+    let total = instinct + logic + psyche;
+    if total == 0.0 {
+        return None;
+    }
+    let agent_share = (instinct / total, logic / total, psyche / total);
+    
+    let topics: HashSet<String> = db::get_conversation_summary(conversation_id).ok().flatten()
+        .and_then(|s| serde_json::from_str::<Vec<String>>(&s.key_topics).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|t| t.to_lowercase())
+        .collect();
+    
+    let profiles = db::get_all_persona_profiles().ok()?;
+    let mut active_score = 0.0;
+    let mut best: Option<(f64, &db::PersonaProfile)> = None;
+    for persona in &profiles {
+        let activity = db::get_persona_activity(&persona.id).ok()?;
+        let score = persona_fit_score(agent_share, &topics, persona, &activity);
+        if persona.id == active_persona_id {
+            active_score = score;
+        } else if best.is_none_or(|(b, _)| score > b) {
+            best = Some((score, persona));
+        }
+    }
+    
+    let (best_score, best_persona) = best?;
+    if best_score - active_score < PERSONA_SUGGESTION_MARGIN {
+        return None;
+    }
+    
+    let suggestion = PersonaSuggestion {
+        conversation_id: conversation_id.to_string(),
+        profile_id: best_persona.id.clone(),
+        profile_name: best_persona.name.clone(),
+        message: format!("This looks like a {} conversation -- switch?", best_persona.name),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let json = serde_json::to_string(&suggestion).ok()?;
+    db::set_setting("pending_persona_suggestion", &json).ok()?;
+    
+    Some(suggestion)
+}
+
+fn load_pending_persona_suggestion() -> Option<PersonaSuggestion> {
+    db::get_setting("pending_persona_suggestion")
+        .ok()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+#[tauri::command]
+fn get_persona_suggestion() -> Result<Option<PersonaSuggestion>, String> {
+    Ok(load_pending_persona_suggestion())
+}
+
+/// Switch to the suggested persona and move the conversation over to it
+#[tauri::command]
+fn accept_persona_suggestion() -> Result<db::PersonaProfile, String> {
+    let suggestion = load_pending_persona_suggestion().ok_or("No pending persona suggestion")?;
+    
+    db::set_active_persona_profile(&suggestion.profile_id).map_err(|e| e.to_string())?;
+    db::set_conversation_persona(&suggestion.conversation_id, &suggestion.profile_id).map_err(|e| e.to_string())?;
+    db::set_setting("pending_persona_suggestion", "").map_err(|e| e.to_string())?;
+    
+    logging::log_routing(Some(&suggestion.conversation_id), &format!(
+        "Accepted persona suggestion: {}", suggestion.profile_name
+    ));
+    
+    db::get_active_persona_profile()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Suggested persona no longer exists".to_string())
+}
+
+#[tauri::command]
+fn dismiss_persona_suggestion() -> Result<(), String> {
+    db::set_setting("pending_persona_suggestion", "").map_err(|e| e.to_string())
 }
 
 // ============ User Context (Legacy) ============
@@ -1659,6 +5345,337 @@ fn get_memory_stats() -> Result<MemoryStats, String> {
     })
 }
 
+// ============ Usage Analytics ============
+
+/// Dashboard numbers ("week", "month", "year", "all" or "YYYY-MM-DD..YYYY-MM-DD")
+#[tauri::command]
+fn get_analytics(range: Option<String>) -> Result<analytics::Analytics, String> {
+    analytics::get(range.as_deref().unwrap_or("month"))
+}
+
+// ============ Usage Heatmap ============
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageHeatmap {
+    pub range: String,
+    pub counts: Vec<Vec<i64>>, // [day_of_week][hour], Monday = 0, local time
+    pub total: i64,
+    pub peak_day: Option<u32>,
+    pub peak_hour: Option<u32>,
+}
+
+/// User message counts bucketed by local day-of-week and hour
+/// range: "week" | "month" | "year" | "all"
+#[tauri::command]
+fn get_usage_heatmap(range: String) -> Result<UsageHeatmap, String> {
+    use chrono::{Datelike, Duration, Local, Timelike};
+    
+    let since = match range.as_str() {
+        "week" => Some(clock::now() - Duration::days(7)),
+        "month" => Some(clock::now() - Duration::days(30)),
+        "year" => Some(clock::now() - Duration::days(365)),
+        "all" => None,
+        _ => return Err(format!("Invalid range: {}", range)),
+    };
+    let since = since.map(|t| t.to_rfc3339());
+    
+    let timestamps = db::get_user_message_timestamps(since.as_deref()).map_err(|e| e.to_string())?;
+    
+    let mut counts = vec![vec![0i64; 24]; 7];
+    let mut total = 0;
+    for ts in timestamps {
+        if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(&ts) {
+            let local = parsed.with_timezone(&Local);
+            counts[local.weekday().num_days_from_monday() as usize][local.hour() as usize] += 1;
+            total += 1;
+        }
+    }
+    
+    let peak = |sums: Vec<i64>| {
+        sums.iter().enumerate()
+            .filter(|(_, c)| **c > 0)
+            .max_by_key(|(_, c)| **c)
+            .map(|(i, _)| i as u32)
+    };
+    let peak_day = peak(counts.iter().map(|day| day.iter().sum()).collect());
+    let peak_hour = peak((0..24).map(|h| counts.iter().map(|day| day[h]).sum()).collect());
+    
+    Ok(UsageHeatmap { range, counts, total, peak_day, peak_hour })
+}
+
+// ============ Sensitive Fact Categories ============
+
+#[tauri::command]
+fn get_sensitive_fact_categories() -> Result<Vec<String>, String> {
+    db::get_sensitive_fact_categories().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_fact_category_sensitive(category: String, sensitive: bool) -> Result<(), String> {
+    db::set_fact_category_sensitive(&category, sensitive).map_err(|e| e.to_string())
+}
+
+// ============ Theme Muting ============
+
+#[tauri::command]
+fn mute_theme(theme: String) -> Result<(), String> {
+    db::set_theme_muted(&theme, true).map_err(|e| e.to_string())?;
+    logging::log_memory(None, &format!("Muted theme: {}", theme));
+    Ok(())
+}
+
+#[tauri::command]
+fn unmute_theme(theme: String) -> Result<(), String> {
+    db::set_theme_muted(&theme, false).map_err(|e| e.to_string())?;
+    logging::log_memory(None, &format!("Unmuted theme: {}", theme));
+    Ok(())
+}
+
+#[tauri::command]
+fn get_muted_themes() -> Result<Vec<String>, String> {
+    let themes = db::get_muted_themes().map_err(|e| e.to_string())?;
+    Ok(themes.into_iter().map(|t| t.theme).collect())
+}
+
+// ============ Memory Consolidation ============
+
+/// Finalization starts a consolidation pass when the last one is older than this
+const CONSOLIDATION_INTERVAL_DAYS: i64 = 7;
+static CONSOLIDATION_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsolidationReport {
+    pub ran_at: String,
+    pub facts_merged: usize,    // Facts folded into another fact
+    pub patterns_merged: usize, // Patterns folded into another pattern
+    pub merges: Vec<String>,    // One readable line per merge
+}
+
+fn last_consolidation() -> Option<ConsolidationReport> {
+    db::get_setting("memory_consolidation_report")
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// Merge near-duplicate facts and patterns, log each merge, and store the report
+async fn consolidate_memory_internal(anthropic_key: &str) -> Result<ConsolidationReport, String> {
+    if CONSOLIDATION_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("Consolidation is already running".to_string());
+    }
+    let result = run_consolidation(anthropic_key).await;
+    CONSOLIDATION_RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn run_consolidation(anthropic_key: &str) -> Result<ConsolidationReport, String> {
+    let (facts, patterns) = db::run_blocking(|| -> rusqlite::Result<_> {
+        Ok((db::get_all_user_facts()?, db::get_all_user_patterns()?))
+    }).await?.map_err(|e| e.to_string())?;
+    let plan = MemoryExtractor::new(anthropic_key)
+        .plan_consolidation(&facts, &patterns)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    db::run_blocking(move || apply_consolidation(&plan, &facts, &patterns)).await?
+}
+
+/// Apply a consolidation plan's merges and store the report
+fn apply_consolidation(
+    plan: &memory::ConsolidationPlan,
+    facts: &[db::UserFact],
+    patterns: &[db::UserPattern],
+) -> Result<ConsolidationReport, String> {
+    let mut report = ConsolidationReport {
+        ran_at: Utc::now().to_rfc3339(),
+        facts_merged: 0,
+        patterns_merged: 0,
+        merges: Vec::new(),
+    };
+    
+    for merge in &plan.fact_merges {
+        let describe = |id: &i64| facts.iter()
+            .find(|f| f.id == *id)
+            .map(|f| format!("{}/{}: {}", f.category, f.key, f.value))
+            .unwrap_or_default();
+        let sources: Vec<String> = std::iter::once(&merge.keep_id).chain(&merge.merge_ids).map(describe).collect();
+        match db::merge_user_facts(merge.keep_id, &merge.merge_ids, &merge.category, &merge.key, &merge.value) {
+            Ok(true) => {
+                let line = format!("{} -> {}/{}: {}", sources.join(" + "), merge.category, merge.key, merge.value);
+                logging::log_memory(None, &format!("Consolidated facts: {}", line));
+                report.facts_merged += merge.merge_ids.len();
+                report.merges.push(line);
+            }
+            Ok(false) => {}
+            Err(e) => logging::log_error(None, &format!("Fact merge into {} failed: {}", merge.keep_id, e)),
+        }
+    }
+    
+    for merge in &plan.pattern_merges {
+        let describe = |id: &i64| patterns.iter()
+            .find(|p| p.id == *id)
+            .map(|p| p.description.clone())
+            .unwrap_or_default();
+        let sources: Vec<String> = std::iter::once(&merge.keep_id).chain(&merge.merge_ids).map(describe).collect();
+        match db::merge_user_patterns(merge.keep_id, &merge.merge_ids, &merge.description) {
+            Ok(true) => {
+                let line = format!("{} -> {}", sources.join(" + "), merge.description);
+                logging::log_memory(None, &format!("Consolidated patterns: {}", line));
+                report.patterns_merged += merge.merge_ids.len();
+                report.merges.push(line);
+            }
+            Ok(false) => {}
+            Err(e) => logging::log_error(None, &format!("Pattern merge into {} failed: {}", merge.keep_id, e)),
+        }
+    }
+    
+    logging::log_memory(None, &format!(
+        "Consolidation complete: {} facts and {} patterns merged", report.facts_merged, report.patterns_merged
+    ));
+    let json = serde_json::to_string(&report).map_err(|e| e.to_string())?;
+    db::set_setting("memory_consolidation_report", &json).map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Start a background consolidation pass if the last one is older than CONSOLIDATION_INTERVAL_DAYS
+fn maybe_consolidate_memory(anthropic_key: &str) {
+    if !memory_extraction_enabled() || usage::skip_optional_calls("anthropic") {
+        return;
+    }
+    let due = last_consolidation()
+        .and_then(|r| chrono::DateTime::parse_from_rfc3339(&r.ran_at).ok())
+        .is_none_or(|ran_at| Utc::now().signed_duration_since(ran_at) >= chrono::Duration::days(CONSOLIDATION_INTERVAL_DAYS));
+    if !due {
+        return;
+    }
+    let anthropic_key = anthropic_key.to_string();
+    spawn_tracked(async move {
+        if let Err(e) = consolidate_memory_internal(&anthropic_key).await {
+            logging::log_error(None, &format!("Memory consolidation failed: {}", e));
+        }
+    });
+}
+
+/// Merge near-duplicate facts and patterns now
+#[tauri::command]
+async fn consolidate_memory() -> Result<ConsolidationReport, String> {
+    let profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let anthropic_key = profile.anthropic_key.ok_or("Anthropic API key not set")?;
+    consolidate_memory_internal(&anthropic_key).await
+}
+
+/// What the most recent consolidation pass merged, if one has run
+#[tauri::command]
+fn get_consolidation_summary() -> Result<Option<ConsolidationReport>, String> {
+    Ok(last_consolidation())
+}
+
+// ============ Memory Browser ============
+
+#[tauri::command]
+fn get_all_facts() -> Result<Vec<db::UserFact>, String> {
+    db::get_all_user_facts().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_all_patterns() -> Result<Vec<db::UserPattern>, String> {
+    db::get_all_user_patterns().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_all_themes() -> Result<Vec<db::RecurringTheme>, String> {
+    db::get_all_recurring_themes().map_err(|e| e.to_string())
+}
+
+/// Correct a fact's value ("I don't live in Boston anymore"); the edit is kept as explicit
+#[tauri::command]
+fn update_fact(id: i64, value: String, confidence: Option<f64>) -> Result<db::UserFact, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("A fact needs a value; delete it instead".to_string());
+    }
+    if !db::update_user_fact(id, value, confidence).map_err(|e| e.to_string())? {
+        return Err("Fact not found".to_string());
+    }
+    let fact = db::get_user_fact(id).map_err(|e| e.to_string())?.ok_or("Fact not found")?;
+    redact::refresh_names();
+    logging::log_memory(None, &format!("Edited fact {}: {} = {}", fact.category, fact.key, fact.value));
+    Ok(fact)
+}
+
+/// How long a forgotten fact key is kept from being re-extracted
+const FORGET_BLOCK_DAYS: i64 = 90;
+
+fn remember_fact_internal(category: &str, key: &str, value: &str, conversation_id: Option<&str>) -> Result<db::UserFact, String> {
+    let (category, key, value) = (category.trim().to_lowercase(), key.trim(), value.trim());
+    if category.is_empty() || key.is_empty() || value.is_empty() {
+        return Err("A fact needs a category, key and value".to_string());
+    }
+    let id = db::remember_user_fact(&category, key, value, conversation_id).map_err(|e| e.to_string())?;
+    redact::refresh_names();
+    logging::log_memory(conversation_id, &format!("Remembered {}: {} = {}", category, key, value));
+    let fact = db::get_user_fact(id).map_err(|e| e.to_string())?.ok_or_else(|| "Fact not found".to_string())?;
+    webhooks::emit("fact.learned", serde_json::json!(fact));
+    Ok(fact)
+}
+
+/// Store a fact exactly as given, at full confidence
+#[tauri::command]
+fn remember_fact(category: String, key: String, value: String) -> Result<db::UserFact, String> {
+    remember_fact_internal(&category, &key, &value, None)
+}
+
+/// Remove every fact under `key` and keep extraction from learning it again for FORGET_BLOCK_DAYS;
+/// returns how many facts were removed
+#[tauri::command]
+fn forget_fact(key: String) -> Result<usize, String> {
+    let removed = db::forget_fact(&key, FORGET_BLOCK_DAYS).map_err(|e| e.to_string())?;
+    logging::log_memory(None, &format!("Forgot {} ({} facts removed)", key.trim(), removed));
+    Ok(removed)
+}
+
+/// Facts flagged for a second look (e.g. learned from a deleted message)
+#[tauri::command]
+fn get_facts_for_review() -> Result<Vec<db::FactReview>, String> {
+    db::get_facts_for_review().map_err(|e| e.to_string())
+}
+
+/// Keep a flagged fact as it is (editing or deleting it also clears the flag)
+#[tauri::command]
+fn keep_reviewed_fact(id: i64) -> Result<(), String> {
+    if !db::clear_fact_review(id).map_err(|e| e.to_string())? {
+        return Err("Fact isn't flagged for review".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn delete_fact(id: i64) -> Result<(), String> {
+    if !db::delete_user_fact(id).map_err(|e| e.to_string())? {
+        return Err("Fact not found".to_string());
+    }
+    logging::log_memory(None, &format!("Deleted fact {}", id));
+    Ok(())
+}
+
+#[tauri::command]
+fn delete_pattern(id: i64) -> Result<(), String> {
+    if !db::delete_user_pattern(id).map_err(|e| e.to_string())? {
+        return Err("Pattern not found".to_string());
+    }
+    logging::log_memory(None, &format!("Deleted pattern {}", id));
+    Ok(())
+}
+
+#[tauri::command]
+fn delete_theme(theme: String) -> Result<(), String> {
+    if !db::delete_recurring_theme(&theme).map_err(|e| e.to_string())? {
+        return Err("Theme not found".to_string());
+    }
+    logging::log_memory(None, &format!("Deleted theme: {}", theme));
+    Ok(())
+}
+
 #[tauri::command]
 fn update_weights(instinct: f64, logic: f64, psyche: f64) -> Result<(), String> {
     db::update_weights(instinct, logic, psyche).map_err(|e| e.to_string())
@@ -1703,23 +5720,75 @@ fn get_user_profile_summary() -> Result<String, String> {
     }
 }
 
+// ============ Personality Type ============
+
+/// Assess a persona's personality type (the active one by default) and record a snapshot if it moved
+fn compute_personality(profile_id: Option<&str>) -> Result<(db::PersonaProfile, personality::PersonalityAssessment), String> {
+    let profile = match profile_id {
+        Some(id) => db::get_all_persona_profiles().map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or("Profile not found")?,
+        None => db::get_active_persona_profile().map_err(|e| e.to_string())?
+            .ok_or("No active persona profile")?,
+    };
+    let patterns = db::get_all_user_patterns().unwrap_or_default();
+    let assessment = personality::assess(&profile, &patterns);
+    
+    if db::save_personality_snapshot(
+        &profile.id,
+        &assessment.code,
+        &assessment.name,
+        &assessment.group,
+        assessment.confidence,
+        assessment.message_count,
+    ).map_err(|e| e.to_string())? {
+        logging::log_memory(None, &format!("Personality ({}): {}", profile.name, assessment.describe()));
+    }
+    Ok((profile, assessment))
+}
+
+#[tauri::command]
+fn compute_personality_type(profile_id: Option<String>) -> Result<personality::PersonalityAssessment, String> {
+    compute_personality(profile_id.as_deref()).map(|(_, assessment)| assessment)
+}
+
+/// How a persona's type and confidence have moved, newest first
+#[tauri::command]
+fn get_personality_history(profile_id: Option<String>, limit: Option<usize>) -> Result<Vec<db::PersonalitySnapshot>, String> {
+    let profile_id = match profile_id {
+        Some(id) => id,
+        None => db::get_active_persona_profile().map_err(|e| e.to_string())?
+            .ok_or("No active persona profile")?
+            .id,
+    };
+    db::get_personality_snapshots(&profile_id, limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
 // ============ Governor Report Generation ============
 
+/// Persona profiles plus everything learned about the user, for the reports built from them
+type KnowledgeBase = (Vec<db::PersonaProfile>, Vec<db::UserFact>, Vec<db::UserPattern>, Vec<db::RecurringTheme>);
+
+fn load_knowledge_base() -> rusqlite::Result<KnowledgeBase> {
+    Ok((
+        db::get_all_persona_profiles()?,
+        db::get_all_user_facts().unwrap_or_default(),
+        db::get_all_user_patterns().unwrap_or_default(),
+        db::get_all_recurring_themes().unwrap_or_default(),
+    ))
+}
+
 #[tauri::command]
 async fn generate_governor_report(profile_id: Option<String>) -> Result<String, String> {
     use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_SONNET};
     
     // Get Anthropic API key
-    let user_profile = db::get_user_profile().map_err(|e| e.to_string())?;
+    let user_profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
     let anthropic_key = user_profile.anthropic_key.ok_or("Anthropic API key not set")?;
     
-    // Get all persona profiles
-    let profiles = db::get_all_persona_profiles().map_err(|e| e.to_string())?;
-    
-    // Get knowledge base data
-    let facts = db::get_all_user_facts().unwrap_or_default();
-    let patterns = db::get_all_user_patterns().unwrap_or_default();
-    let themes = db::get_all_recurring_themes().unwrap_or_default();
+    // Get all persona profiles and the knowledge base data
+    let (profiles, facts, patterns, themes) = db::run_blocking(load_knowledge_base).await?.map_err(|e| e.to_string())?;
     
     // Build context for the LLM
     let facts_text = if facts.is_empty() {
@@ -1764,6 +5833,12 @@ async fn generate_governor_report(profile_id: Option<String>) -> Result<String,
     
     let total_messages: i64 = profiles.iter().map(|p| p.message_count).sum();
     
+    // Personality type of the profile in scope (the active one for overviews)
+    let personality_text = match compute_personality(profile_id.as_deref()) {
+        Ok((p, assessment)) => format!("{}: {}", p.name, assessment.describe()),
+        Err(_) => "Not assessed yet.".to_string(),
+    };
+    
     // Determine if generating for a specific profile or all
     let scope = if let Some(ref pid) = profile_id {
         let target = profiles.iter().find(|p| p.id == *pid);
@@ -1799,14 +5874,15 @@ FOCUS ON:
 - Cognitive tendencies (how they think)
 - Communication patterns (how they express themselves)
 - Notable themes or interests
+- Their personality type, only when its confidence is meaningful (mention it by name, not as a label dump)
 
 STYLE:
 - When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")
 - Example: "They think in systems -- always mapping things out.""#;
 
     let user_prompt = format!(
-        "SCOPE: {}\n\nPROFILES:\n{}\n\nTOTAL MESSAGES: {}\n\nLEARNED FACTS:\n{}\n\nBEHAVIORAL PATTERNS:\n{}\n\nRECURRING THEMES:\n{}\n\nGenerate the Governor's report:",
-        scope, profiles_text, total_messages, facts_text, patterns_text, themes_text
+        "SCOPE: {}\n\nPROFILES:\n{}\n\nTOTAL MESSAGES: {}\n\nPERSONALITY TYPE:\n{}\n\nLEARNED FACTS:\n{}\n\nBEHAVIORAL PATTERNS:\n{}\n\nRECURRING THEMES:\n{}\n\nGenerate the Governor's report:",
+        scope, profiles_text, total_messages, personality_text, facts_text, patterns_text, themes_text
     );
     
     // Use Sonnet (non-thinking) for fast report generation
@@ -1830,19 +5906,110 @@ STYLE:
     Ok(response)
 }
 
+// ============ Persona Comparison ============
+
+fn describe_persona_activity(profile: &db::PersonaProfile, activity: &db::PersonaActivity) -> String {
+    let trajectory = match (activity.weight_history.first(), activity.weight_history.last()) {
+        (Some(first), Some(last)) if activity.weight_history.len() > 1 => format!(
+            "Logic {:.0}% -> {:.0}%, Instinct {:.0}% -> {:.0}%, Psyche {:.0}% -> {:.0}% ({} snapshots)",
+            first.logic_weight * 100.0, last.logic_weight * 100.0,
+            first.instinct_weight * 100.0, last.instinct_weight * 100.0,
+            first.psyche_weight * 100.0, last.psyche_weight * 100.0,
+            activity.weight_history.len()
+        ),
+        _ => "No weight history yet".to_string(),
+    };
+    let topics = if activity.top_topics.is_empty() {
+        "none yet".to_string()
+    } else {
+        activity.top_topics.iter().map(|(t, n)| format!("{} ({})", t, n)).collect::<Vec<_>>().join(", ")
+    };
+    let tones = if activity.emotional_tones.is_empty() {
+        "none yet".to_string()
+    } else {
+        activity.emotional_tones.iter().map(|(t, n)| format!("{} ({})", t, n)).collect::<Vec<_>>().join(", ")
+    };
+    
+    format!(
+        "{} ({} dominant)\n- Conversations: {}\n- User messages: {}\n- Current weights: Logic {:.0}%, Instinct {:.0}%, Psyche {:.0}%\n- Trajectory: {}\n- Top topics: {}\n- Emotional tones: {}",
+        profile.name, profile.dominant_trait,
+        activity.conversation_count, activity.user_message_count,
+        profile.logic_weight * 100.0, profile.instinct_weight * 100.0, profile.psyche_weight * 100.0,
+        trajectory, topics, tones
+    )
+}
+
+/// Compare how the user thinks across two personas; the result is saved as a report
+#[tauri::command]
+async fn compare_personas(profile_a: String, profile_b: String) -> Result<db::PersonaComparison, String> {
+    use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_SONNET};
+    
+    if profile_a == profile_b {
+        return Err("Choose two different personas to compare".to_string());
+    }
+    
+    let user_profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
+    let anthropic_key = user_profile.anthropic_key.ok_or("Anthropic API key not set")?;
+    
+    let profiles = db::run_blocking(db::get_all_persona_profiles).await?.map_err(|e| e.to_string())?;
+    let a = profiles.iter().find(|p| p.id == profile_a).ok_or("Persona A not found")?;
+    let b = profiles.iter().find(|p| p.id == profile_b).ok_or("Persona B not found")?;
+    
+    let (id_a, id_b) = (a.id.clone(), b.id.clone());
+    let (activity_a, activity_b) = db::run_blocking(move || -> rusqlite::Result<_> {
+        Ok((db::get_persona_activity(&id_a)?, db::get_persona_activity(&id_b)?))
+    }).await?.map_err(|e| e.to_string())?;
+    let stats = serde_json::to_string(&(&activity_a, &activity_b)).map_err(|e| e.to_string())?;
+    
+    let narrative = if activity_a.user_message_count < 3 || activity_b.user_message_count < 3 {
+        "There isn't enough in both personas yet to compare them -- spend a little more time in each and ask again.".to_string()
+    } else {
+        let system_prompt = r#"You are the Governor of Intersect. You compare how the same user thinks and talks across two of their persona profiles.
+
+RULES:
+- Write 3-4 sentences, no headers or bullet points
+- Only use the data provided; never invent topics or numbers
+- Cover volume, how the weights moved, what each persona tends to be about, and tone differences
+- When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")"#;
+        
+        let user_prompt = format!(
+            "PERSONA A:\n{}\n\nPERSONA B:\n{}\n\nWrite the comparison:",
+            describe_persona_activity(a, &activity_a),
+            describe_persona_activity(b, &activity_b)
+        );
+        
+        let client = AnthropicClient::new(&anthropic_key);
+        client.chat_completion_advanced(
+            CLAUDE_SONNET,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content: user_prompt }],
+            0.5,
+            Some(300),
+            ThinkingBudget::None
+        ).await.map_err(|e| e.to_string())?
+    };
+    
+    let (id_a, id_b) = (a.id.clone(), b.id.clone());
+    db::run_blocking(move || db::save_persona_comparison(&id_a, &id_b, &stats, &narrative))
+        .await?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_persona_comparisons() -> Result<Vec<db::PersonaComparison>, String> {
+    db::get_persona_comparisons().map_err(|e| e.to_string())
+}
+
 // ============ 3-Sentence Summary ============
 
 #[tauri::command]
 async fn generate_user_summary() -> Result<String, String> {
     use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_SONNET};
     
-    let user_profile = db::get_user_profile().map_err(|e| e.to_string())?;
+    let user_profile = db::run_blocking(db::get_user_profile).await?.map_err(|e| e.to_string())?;
     let anthropic_key = user_profile.anthropic_key.ok_or("Anthropic API key not set")?;
     
-    let profiles = db::get_all_persona_profiles().map_err(|e| e.to_string())?;
-    let facts = db::get_all_user_facts().unwrap_or_default();
-    let patterns = db::get_all_user_patterns().unwrap_or_default();
-    let themes = db::get_all_recurring_themes().unwrap_or_default();
+    let (profiles, facts, patterns, themes) = db::run_blocking(load_knowledge_base).await?.map_err(|e| e.to_string())?;
     
     let total_messages: i64 = profiles.iter().map(|p| p.message_count).sum();
     
@@ -1888,11 +6055,120 @@ Style:
     ).await.map_err(|e| e.to_string())
 }
 
+// ============ Logging ============
+
+#[tauri::command]
+fn get_log_redaction() -> bool {
+    redact::is_enabled()
+}
+
+/// Turn PII masking in logs off for debugging (back on at the next launch)
+#[tauri::command]
+fn set_log_redaction(enabled: bool) {
+    redact::set_enabled(enabled);
+    logging::log_conversation(None, &format!("Log redaction {}", if enabled { "on" } else { "off until restart" }));
+}
+
+#[tauri::command]
+fn get_log_settings() -> logging::LogSettings {
+    logging::get_settings()
+}
+
+/// Plaintext or JSON lines, from the next line on
+#[tauri::command]
+fn set_log_format(format: logging::LogFormat) -> Result<(), String> {
+    let mut settings = logging::get_settings();
+    settings.format = format;
+    logging::set_settings(&settings)
+}
+
+/// Log lines for the debug panel, filtered by category, conversation and start time (RFC 3339);
+/// the newest `limit` (default 200) in chronological order
+#[tauri::command]
+async fn query_logs(
+    category: Option<String>,
+    conversation_id: Option<String>,
+    since: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<logging::LogEntry>, String> {
+    let since = since
+        .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|t| t.with_timezone(&chrono::Utc)))
+        .transpose()
+        .map_err(|e| format!("Invalid since: {}", e))?;
+    let limit = limit.unwrap_or(200).clamp(1, logging::MAX_QUERY_LIMIT);
+    // Up to a week of log files: read them off the main thread
+    tokio::task::spawn_blocking(move || {
+        logging::query(category.as_deref(), conversation_id.as_deref(), since, limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Set a category's level ("off" or "info"); category "all" sets every one
+#[tauri::command]
+fn set_log_level(category: String, level: logging::LogLevel) -> Result<(), String> {
+    logging::set_level(&category, level)?;
+    logging::log_conversation(None, &format!("Log level for {} set to {:?}", category.trim(), level));
+    Ok(())
+}
+
+// ============ Diagnostics ============
+
+/// Zip recent logs, database stats, the app version and redacted settings for a bug report;
+/// returns the bundle's path
+#[tauri::command]
+async fn export_diagnostics(app_handle: tauri::AppHandle) -> Result<String, String> {
+    // Counts rows in every table and zips the logs: keep it off the main thread
+    tokio::task::spawn_blocking(move || diagnostics::export(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 // ============ Reset ============
 
+/// Resets snapshot the database first (see list_backups / restore_backup to undo one). The snapshot
+/// still holds the wiped data until it expires after backup::PRE_RESET_RETENTION_DAYS.
 #[tauri::command]
 fn reset_all_data() -> Result<(), String> {
-    db::reset_all_data().map_err(|e| e.to_string())
+    backup::create_safety_backup("pre-reset")?;
+    let removed = db::reset_all_data().map_err(|e| e.to_string())?;
+    attachments::remove_files(&removed);
+    Ok(())
+}
+
+/// Scoped resets: pass dry_run = true to preview what would be removed
+#[tauri::command]
+fn reset_memory_only(dry_run: bool) -> Result<db::ResetPreview, String> {
+    if !dry_run {
+        backup::create_safety_backup("pre-reset")?;
+    }
+    let (preview, _) = db::reset_scope(db::ResetScope::Memory, dry_run).map_err(|e| e.to_string())?;
+    Ok(preview)
+}
+
+#[tauri::command]
+fn reset_weights_only(dry_run: bool) -> Result<db::ResetPreview, String> {
+    if !dry_run {
+        backup::create_safety_backup("pre-reset")?;
+    }
+    let (preview, _) = db::reset_scope(db::ResetScope::Weights, dry_run).map_err(|e| e.to_string())?;
+    if !dry_run {
+        SESSION_WEIGHTS.lock().unwrap().clear();
+    }
+    Ok(preview)
+}
+
+#[tauri::command]
+fn reset_conversations_only(dry_run: bool) -> Result<db::ResetPreview, String> {
+    if !dry_run {
+        backup::create_safety_backup("pre-reset")?;
+    }
+    let (preview, removed) = db::reset_scope(db::ResetScope::Conversations, dry_run).map_err(|e| e.to_string())?;
+    attachments::remove_files(&removed);
+    if !dry_run {
+        SESSION_WEIGHTS.lock().unwrap().clear();
+    }
+    Ok(preview)
 }
 
 // ============ Window Controls ============
@@ -2189,14 +6465,22 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
             init_app,
             get_user_profile,
             validate_and_save_api_key,
+            validate_and_save_anthropic_key,
+            validate_all_keys,
+            get_key_sources,
             save_api_key,
             remove_api_key,
             save_anthropic_key,
             remove_anthropic_key,
+            validate_and_save_gemini_key,
+            save_gemini_key,
+            remove_gemini_key,
             create_persona_profile,
             get_all_persona_profiles,
             get_active_persona_profile,
@@ -2207,21 +6491,210 @@ pub fn run() {
             update_dominant_trait,
             delete_persona_profile,
             reset_personalization,
+            get_setup_state,
+            complete_setup_step,
             create_conversation,
             get_recent_conversations,
             get_conversation_messages,
+            search_messages,
+            semantic_search,
+            set_conversation_model,
+            set_conversation_agents,
+            get_conversation_agents,
+            get_agent_providers,
+            get_agent_sampling,
+            set_agent_sampling,
+            reset_agent_sampling,
+            set_agent_provider,
+            set_default_agent_provider,
+            get_ollama_url,
+            set_ollama_url,
+            list_ollama_models,
+            get_agent_prompt,
+            set_agent_prompt,
+            reset_agent_prompt,
+            get_agent_prompt_history,
+            rollback_agent_prompt,
+            get_openai_endpoint,
+            set_openai_endpoint,
+            get_model_fallback_chain,
+            set_model_fallback_chain,
+            get_retry_policy,
+            set_retry_policy,
+            get_budget_status,
+            set_spend_budget,
+            get_api_usage,
             clear_conversation,
+            delete_conversation,
+            add_conversation_tag,
+            remove_conversation_tag,
+            get_conversation_tags,
+            get_all_tags,
+            get_conversations_by_tag,
+            get_auto_tagging_enabled,
+            set_auto_tagging_enabled,
             finalize_conversation,
+            get_conversation_recap,
             recover_conversations,
             get_conversation_opener,
+            get_greeting_style,
+            set_greeting_style,
+            get_reflection_prompts_enabled,
+            set_reflection_prompts_enabled,
+            get_pending_reflection,
             send_message,
+            get_pending_messages,
+            discard_pending_message,
+            flush_pending_messages,
+            check_connectivity,
+            rerun_turn,
+            cancel_turn,
+            get_routing_explanation,
+            get_message_routing,
+            get_turn_versions,
+            branch_from_message,
+            get_conversation_branches,
+            regenerate_response,
+            delete_message,
+            rate_message,
+            get_feedback_totals,
+            toggle_star,
+            get_starred_messages,
+            get_message_versions,
+            get_response_mode,
+            set_response_mode,
+            get_debate_settings,
+            set_debate_settings,
+            ingest_document,
+            get_documents,
+            delete_document,
+            add_attachment,
+            remove_attachment,
+            get_message_attachments,
+            get_agent_tools,
+            set_agent_tool_enabled,
+            get_local_api_settings,
+            set_local_api_enabled,
+            regenerate_local_api_token,
+            get_calendar_settings,
+            set_calendar_settings,
+            get_today_events,
+            get_capture_settings,
+            set_capture_settings,
+            quick_capture,
+            get_webhooks,
+            add_webhook,
+            remove_webhook,
+            test_webhook,
+            get_mcp_servers,
+            save_mcp_server,
+            remove_mcp_server,
+            get_mcp_server_info,
+            get_search_settings,
+            set_search_settings,
+            transcribe_audio,
+            get_voice_settings,
+            set_voice_settings,
+            get_context_settings,
+            set_context_settings,
+            estimate_tokens,
+            get_language_settings,
+            set_language_settings,
+            get_composite_drafts,
+            draft_message,
+            refine_draft,
+            get_recent_drafts,
+            delete_draft,
+            get_decisions,
+            get_check_ins,
+            confirm_check_in,
+            dismiss_check_in,
+            complete_check_in,
+            get_journal_settings,
+            set_journal_settings,
+            start_journal_entry,
+            get_journal_entries,
+            get_mood_trend,
+            get_reminders,
+            complete_reminder,
+            dismiss_reminder,
+            start_export,
+            get_export_result,
+            export_conversation,
+            import_transcript,
+            import_chat_export,
+            list_backups,
+            restore_backup,
+            get_backup_settings,
+            set_backup_settings,
+            get_sync_settings,
+            configure_sync,
+            set_sync_interval,
+            disable_sync,
+            sync_now,
+            get_log_redaction,
+            set_log_redaction,
+            get_log_settings,
+            set_log_format,
+            set_log_level,
+            query_logs,
+            export_diagnostics,
+            export_all_data,
+            import_all_data,
+            mute_agent,
+            unmute_agent,
+            get_muted_agents,
+            get_agent_style_preferences,
+            get_system_notices,
+            get_notifications,
+            mark_notification_read,
+            get_reports,
+            get_digest_settings,
+            set_digest_settings,
+            generate_digest_now,
+            get_engagement_history,
+            get_intrinsic_signal_history,
+            get_habits,
+            declare_habit,
+            log_habit,
+            archive_habit,
+            get_persona_suggestion,
+            accept_persona_suggestion,
+            dismiss_persona_suggestion,
             get_user_context,
             clear_user_context,
             get_memory_stats,
+            get_analytics,
+            get_usage_heatmap,
+            mute_theme,
+            unmute_theme,
+            get_muted_themes,
+            get_all_facts,
+            get_all_patterns,
+            get_all_themes,
+            update_fact,
+            get_facts_for_review,
+            keep_reviewed_fact,
+            delete_fact,
+            remember_fact,
+            forget_fact,
+            consolidate_memory,
+            get_consolidation_summary,
+            delete_pattern,
+            delete_theme,
+            get_sensitive_fact_categories,
+            set_fact_category_sensitive,
             get_user_profile_summary,
             generate_governor_report,
+            compute_personality_type,
+            get_personality_history,
+            compare_personas,
+            get_persona_comparisons,
             generate_user_summary,
             reset_all_data,
+            reset_memory_only,
+            reset_weights_only,
+            reset_conversations_only,
             set_always_on_top,
             get_governor_disco_image,
             get_governor_swirling_video,
@@ -2238,6 +6711,7 @@ pub fn run() {
             complete_journey_session,
             get_journey_sessions_completed,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(handle_run_event);
 }
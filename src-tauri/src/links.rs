@@ -0,0 +1,458 @@
+//! Link summaries for Intersect
+//!
+//! When the user pastes a link, agents would otherwise react to a bare URL. Before routing, the
+//! first few http(s) links in the message are fetched and summarized with Haiku, and the summaries
+//! travel with the turn's message (like image descriptions) so routing and every agent see them.
+//! - robots.txt is checked first; a disallowed page is skipped, not fetched
+//! - Only HTML and plain text pages are read, and at most MAX_PAGE_BYTES of each
+//! - Only public addresses are fetched: hosts (redirects included) that are or resolve to loopback,
+//!   private or link-local addresses are refused, so a link can't reach the user's network
+//! - Failures are logged and skipped -- a dead link never blocks the turn, and the whole pass gives
+//!   up after LINKS_DEADLINE_SECS
+
+use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU};
+use crate::logging;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Links summarized per message; the rest are left as-is
+const LINKS_PER_MESSAGE: usize = 2;
+/// Pages are truncated at this size rather than refused
+const MAX_PAGE_BYTES: usize = 1024 * 1024;
+const MAX_ROBOTS_BYTES: usize = 256 * 1024;
+const FETCH_TIMEOUT_SECS: u64 = 10;
+/// Link summaries for one message (fetching and summarizing every link) give up after this long
+const LINKS_DEADLINE_SECS: u64 = 15;
+const MAX_REDIRECTS: usize = 5;
+/// How much page text the summarizer reads
+const SUMMARY_INPUT_CHARS: usize = 12000;
+/// Product token matched against robots.txt user-agent groups
+const ROBOTS_AGENT: &str = "intersect";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkSummary {
+    pub url: String,
+    pub title: Option<String>,
+    pub summary: String,
+}
+
+/// http(s) links in a message, in order, without trailing punctuation or duplicates
+pub fn find_links(text: &str) -> Vec<Url> {
+    let mut links: Vec<Url> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(start) = word.find("http://").or_else(|| word.find("https://")) else {
+            continue;
+        };
+        let candidate = word[start..].trim_end_matches(|c: char| ".,;:!?)]}>'\"".contains(c));
+        if let Ok(url) = Url::parse(candidate) {
+            if url.host_str().is_some() && !links.contains(&url) {
+                links.push(url);
+            }
+        }
+    }
+    links
+}
+
+/// Whether `path` matches a robots.txt rule pattern (`*` wildcards, `$` anchors the end)
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// Whether robots.txt lets us fetch `path`. A group naming us wins over `*`; within it the
+/// longest matching rule decides, Allow winning ties.
+pub fn robots_allows(robots: &str, path: &str) -> bool {
+    let mut ours: Vec<(bool, String)> = Vec::new();
+    let mut anyone: Vec<(bool, String)> = Vec::new();
+    let mut group_agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+    let mut named = false;
+
+    for line in robots.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim());
+        match key.as_str() {
+            "user-agent" => {
+                if in_rules {
+                    group_agents.clear();
+                    in_rules = false;
+                }
+                let agent = value.to_lowercase();
+                named |= agent.contains(ROBOTS_AGENT);
+                group_agents.push(agent);
+            }
+            "allow" | "disallow" => {
+                in_rules = true;
+                if value.is_empty() {
+                    continue; // "Disallow:" with no path allows everything
+                }
+                let rule = (key == "allow", value.to_string());
+                if group_agents.iter().any(|a| a.contains(ROBOTS_AGENT)) {
+                    ours.push(rule.clone());
+                }
+                if group_agents.iter().any(|a| a == "*") {
+                    anyone.push(rule);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let rules = if named { ours } else { anyone };
+    rules.iter()
+        .filter(|(_, pattern)| robots_pattern_matches(pattern, path))
+        .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+        .map(|(allow, _)| *allow)
+        .unwrap_or(true)
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Page title and readable text from HTML (scripts, styles and tags dropped, whitespace collapsed)
+pub fn html_to_text(html: &str) -> (Option<String>, String) {
+    // ASCII lowercasing keeps byte offsets, so positions found in `lower` index into `html`
+    let lower = html.to_ascii_lowercase();
+    let title = lower.find("<title").and_then(|start| {
+        let open_end = start + lower[start..].find('>')? + 1;
+        let close = open_end + lower[open_end..].find("</title")?;
+        let title = decode_entities(html[open_end..close].trim());
+        (!title.is_empty()).then_some(title)
+    });
+
+    let mut text = String::new();
+    let mut pos = 0;
+    while pos < html.len() {
+        let Some(offset) = lower[pos..].find('<') else {
+            text.push_str(&html[pos..]);
+            break;
+        };
+        let tag_start = pos + offset;
+        text.push_str(&html[pos..tag_start]);
+        text.push(' ');
+        // Skip the contents of non-content elements along with the tag
+        let skipped = ["script", "style", "noscript", "svg", "head"].iter().find_map(|name| {
+            let rest = &lower[tag_start + 1..];
+            let is_tag = rest.starts_with(name)
+                && rest[name.len()..].starts_with(|c: char| c == '>' || c.is_whitespace());
+            if !is_tag {
+                return None;
+            }
+            let close = format!("</{}", name);
+            let close_at = lower[tag_start..].find(&close).map(|i| tag_start + i)?;
+            Some(close_at + lower[close_at..].find('>').map(|i| i + 1).unwrap_or(close.len()))
+        });
+        pos = match skipped {
+            Some(end) => end,
+            None => match lower[tag_start..].find('>') {
+                Some(i) => tag_start + i + 1,
+                None => html.len(),
+            },
+        };
+    }
+
+    let text = decode_entities(&text).split_whitespace().collect::<Vec<_>>().join(" ");
+    (title, text)
+}
+
+/// Whether an address is on the public internet (not loopback, private, link-local, shared,
+/// documentation, multicast or otherwise reserved)
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+                || ip.is_broadcast() || ip.is_documentation() || ip.is_multicast()
+                || a == 0                                   // "this network"
+                || (a == 100 && (64..128).contains(&b))     // shared address space (CGNAT)
+                || (a == 198 && (18..20).contains(&b))      // benchmarking
+                || a >= 240)                                // reserved
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00               // unique local
+                || (first & 0xffc0) == 0xfe80)              // link-local
+        }
+    }
+}
+
+/// Whether a URL's host may be fetched; IP literals are checked here, names when they resolve
+fn host_allowed(url: &Url) -> bool {
+    let Some(host) = url.host_str() else { return false };
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => is_public_ip(ip),
+        Err(_) => true,
+    }
+}
+
+/// DNS resolution that drops non-public addresses, so no hostname (or rebinding) reaches them
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn http_client() -> Result<Client, String> {
+    let redirects = reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if !host_allowed(attempt.url()) {
+            attempt.error("redirected to a private address")
+        } else {
+            attempt.follow()
+        }
+    });
+    Client::builder()
+        .timeout(Duration::from_secs(FETCH_TIMEOUT_SECS))
+        .connect_timeout(Duration::from_secs(5))
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(redirects)
+        .user_agent(format!("Intersect/{} (link preview)", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// GET a URL, reading at most `cap` bytes of the body
+async fn fetch_capped(client: &Client, url: Url, cap: usize) -> Result<(reqwest::StatusCode, Option<String>, Vec<u8>), String> {
+    let mut response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let content_type = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_lowercase());
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= cap {
+            body.truncate(cap);
+            break;
+        }
+    }
+    Ok((status, content_type, body))
+}
+
+/// Check robots.txt for the link's site. Missing robots.txt (4xx) allows; an unreachable or
+/// failing one doesn't.
+async fn robots_permit(client: &Client, url: &Url) -> Result<bool, String> {
+    let mut robots_url = url.clone();
+    robots_url.set_path("/robots.txt");
+    robots_url.set_query(None);
+    robots_url.set_fragment(None);
+    let (status, _, body) = fetch_capped(client, robots_url, MAX_ROBOTS_BYTES).await?;
+    if status.is_client_error() {
+        return Ok(true);
+    }
+    if !status.is_success() {
+        return Ok(false);
+    }
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    Ok(robots_allows(&String::from_utf8_lossy(&body), &path))
+}
+
+/// Fetch a page's title and text, respecting robots.txt
+async fn fetch_page(client: &Client, url: &Url) -> Result<(Option<String>, String), String> {
+    if !host_allowed(url) {
+        return Err("private addresses can't be fetched".to_string());
+    }
+    if !robots_permit(client, url).await? {
+        return Err("disallowed by robots.txt".to_string());
+    }
+    let (status, content_type, body) = fetch_capped(client, url.clone(), MAX_PAGE_BYTES).await?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+    let content_type = content_type.unwrap_or_default();
+    let body = String::from_utf8_lossy(&body);
+    if content_type.starts_with("text/html") || content_type.starts_with("application/xhtml") {
+        Ok(html_to_text(&body))
+    } else if content_type.starts_with("text/plain") {
+        Ok((None, body.split_whitespace().collect::<Vec<_>>().join(" ")))
+    } else {
+        Err(format!("unsupported content type {}", content_type))
+    }
+}
+
+/// Title and text of any http(s) page, robots.txt respected (used by the agents' fetch_url tool)
+pub async fn fetch_text(url: &str) -> Result<(Option<String>, String), String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Only http(s) links can be fetched".to_string());
+    }
+    fetch_page(&http_client()?, &url).await
+}
+
+async fn summarize(anthropic_key: &str, url: &Url, title: Option<&str>, text: &str) -> Result<String, String> {
+    let excerpt: String = text.chars().take(SUMMARY_INPUT_CHARS).collect();
+    let system_prompt = "You summarize web pages a user has linked in a conversation, for assistants who can't open them. \
+In 2-4 sentences, say what the page is and its main points or claims, concretely. No preamble.";
+    let content = format!("URL: {}\nTitle: {}\n\n{}", url, title.unwrap_or("(none)"), excerpt);
+    AnthropicClient::new(anthropic_key)
+        .chat_completion_advanced(
+            CLAUDE_HAIKU,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content }],
+            0.3,
+            Some(250),
+            ThinkingBudget::None,
+        )
+        .await
+        .map(|s| s.trim().to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Fetch and summarize the links in a message. Links that can't be read are skipped.
+pub async fn summarize_links(conversation_id: &str, message: &str, anthropic_key: &str) -> Vec<LinkSummary> {
+    let links: Vec<Url> = find_links(message).into_iter().take(LINKS_PER_MESSAGE).collect();
+    if links.is_empty() {
+        return Vec::new();
+    }
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            logging::log_error(Some(conversation_id), &format!("Link client failed: {}", e));
+            return Vec::new();
+        }
+    };
+
+    // One deadline for the whole pass: links still going when it passes are skipped
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(LINKS_DEADLINE_SECS);
+    let results = futures::future::join_all(links.iter().map(|url| {
+        let client = &client;
+        let summarize_link = async move {
+            let (title, text) = fetch_page(client, url).await?;
+            if text.is_empty() {
+                return Err("no readable text".to_string());
+            }
+            let summary = summarize(anthropic_key, url, title.as_deref(), &text).await?;
+            Ok::<LinkSummary, String>(LinkSummary { url: url.to_string(), title, summary })
+        };
+        async move {
+            tokio::time::timeout_at(deadline, summarize_link).await
+                .unwrap_or_else(|_| Err("timed out".to_string()))
+        }
+    })).await;
+
+    links.iter().zip(results)
+        .filter_map(|(url, result)| match result {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                logging::log_error(Some(conversation_id), &format!("Skipped link {}: {}", url, e));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Context for agents about the linked pages; None when nothing was summarized
+pub fn link_context(summaries: &[LinkSummary]) -> Option<String> {
+    if summaries.is_empty() {
+        return None;
+    }
+    let lines = summaries.iter()
+        .map(|s| match &s.title {
+            Some(title) => format!("[Linked page {} (\"{}\"): {}]", s.url, title, s.summary),
+            None => format!("[Linked page {}: {}]", s.url, s.summary),
+        })
+        .collect::<Vec<_>>();
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_links_and_reads_html() {
+        let links = find_links("see https://example.com/a?b=1, and (http://foo.org/x). again https://example.com/a?b=1");
+        let links: Vec<String> = links.iter().map(|u| u.to_string()).collect();
+        assert_eq!(links, vec!["https://example.com/a?b=1", "http://foo.org/x"]);
+        assert!(find_links("ftp://example.com and plain text").is_empty());
+
+        let (title, text) = html_to_text(
+            "<html><head><title>Hi &amp; bye</title><style>p{}</style></head>\
+<body><script>var x = '<p>';</script><p>Hello   <b>world</b></p></body></html>"
+        );
+        assert_eq!(title.as_deref(), Some("Hi & bye"));
+        assert_eq!(text, "Hello world");
+    }
+
+    #[test]
+    fn robots_rules() {
+        let robots = "User-agent: *\nDisallow: /private\nAllow: /private/ok\n\n\
+User-agent: BadBot\nDisallow: /\n";
+        assert!(robots_allows(robots, "/public"));
+        assert!(!robots_allows(robots, "/private/page"));
+        assert!(robots_allows(robots, "/private/ok/page"));
+
+        // A group naming us replaces the * rules
+        let robots = "User-agent: *\nDisallow: /\n\nUser-agent: Intersect\nDisallow: /*.pdf$\n";
+        assert!(robots_allows(robots, "/article"));
+        assert!(!robots_allows(robots, "/files/report.pdf"));
+        assert!(robots_allows(robots, "/files/report.pdf.html"));
+        assert!(robots_allows("", "/anything"));
+    }
+
+    #[test]
+    fn only_public_addresses_are_fetched() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1",
+                   "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(!host_allowed(&Url::parse("http://127.0.0.1:8080/admin").unwrap()));
+        assert!(!host_allowed(&Url::parse("http://[::1]/").unwrap()));
+        assert!(host_allowed(&Url::parse("https://example.com/a").unwrap()));
+    }
+}
@@ -6,14 +6,29 @@
 //! - AGENT: Agent response generation
 //! - CONVERSATION: Session lifecycle
 //! - ERROR: Errors and crashes
+//!
+//! Messages are PII-redacted before they're printed or written (see redact.rs).
+//!
+//! Lines are plaintext or JSON (one object per line), and each category can be switched on or off,
+//! both changeable at runtime. Settings are stored in app_settings as `log_settings` and cached here,
+//! since logging can't query the database while a caller holds the connection.
+//!
+//! `query` reads the files back (either format) for the in-app log viewer.
 
-use chrono::{Local, Utc};
+use crate::db;
+use crate::redact;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
 use once_cell::sync::Lazy;
 
+const SETTINGS_KEY: &str = "log_settings";
+pub const MAX_QUERY_LIMIT: usize = 2000;
+
 /// Log categories for structured logging
 #[derive(Debug, Clone, Copy)]
 pub enum LogCategory {
@@ -25,7 +40,15 @@ pub enum LogCategory {
 }
 
 impl LogCategory {
-    fn as_str(&self) -> &'static str {
+    pub const ALL: [LogCategory; 5] = [
+        LogCategory::Memory,
+        LogCategory::Routing,
+        LogCategory::Agent,
+        LogCategory::Conversation,
+        LogCategory::Error,
+    ];
+    
+    pub fn as_str(&self) -> &'static str {
         match self {
             LogCategory::Memory => "MEMORY",
             LogCategory::Routing => "ROUTING",
@@ -34,6 +57,100 @@ impl LogCategory {
             LogCategory::Error => "ERROR",
         }
     }
+    
+    /// Parse a category name, case-insensitively ("routing" or "ROUTING")
+    pub fn parse(name: &str) -> Option<LogCategory> {
+        LogCategory::ALL.into_iter().find(|c| c.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+    
+    /// Severity of the lines in this category, as written to JSON logs
+    fn severity(&self) -> &'static str {
+        match self {
+            LogCategory::Error => "error",
+            _ => "info",
+        }
+    }
+}
+
+/// Errors have their own category, so a category's lines are either all written or none are
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,  // Nothing from the category
+    Info, // Everything
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text, // [timestamp] [CATEGORY] conversation=abcd1234 | message
+    Json, // {"timestamp","level","category","conversation_id","message"}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LogSettings {
+    pub format: LogFormat,
+    #[serde(deserialize_with = "deserialize_levels")]
+    pub levels: HashMap<String, LogLevel>, // Category name -> level; missing = info
+}
+
+/// Saved levels, including the "error" level of earlier versions: it let through only the ERROR
+/// category's lines, so it reads as "info" there and "off" everywhere else
+fn deserialize_levels<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, LogLevel>, D::Error> {
+    let saved: HashMap<String, String> = HashMap::deserialize(deserializer)?;
+    Ok(saved.into_iter()
+        .filter_map(|(category, level)| {
+            let level = match level.as_str() {
+                "off" => LogLevel::Off,
+                "info" => LogLevel::Info,
+                "error" if category == LogCategory::Error.as_str() => LogLevel::Info,
+                "error" => LogLevel::Off,
+                _ => return None,
+            };
+            Some((category, level))
+        })
+        .collect())
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self { format: LogFormat::Text, levels: HashMap::new() }
+    }
+}
+
+impl LogSettings {
+    pub fn level(&self, category: LogCategory) -> LogLevel {
+        self.levels.get(category.as_str()).copied().unwrap_or(LogLevel::Info)
+    }
+}
+
+static SETTINGS: Lazy<RwLock<LogSettings>> = Lazy::new(|| RwLock::new(LogSettings::default()));
+
+pub fn get_settings() -> LogSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// Save and apply log settings
+pub fn set_settings(settings: &LogSettings) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())?;
+    *SETTINGS.write().unwrap() = settings.clone();
+    Ok(())
+}
+
+/// Set one category's level, or every category's with "all"
+pub fn set_level(category: &str, level: LogLevel) -> Result<(), String> {
+    let categories: Vec<LogCategory> = if category.trim().eq_ignore_ascii_case("all") {
+        LogCategory::ALL.to_vec()
+    } else {
+        vec![LogCategory::parse(category).ok_or_else(|| format!("Unknown log category: {}", category))?]
+    };
+    let mut settings = get_settings();
+    for category in categories {
+        settings.levels.insert(category.as_str().to_string(), level);
+    }
+    set_settings(&settings)
 }
 
 /// Global log file handle
@@ -64,6 +181,13 @@ pub fn init_logging() -> Result<(), Box<dyn std::error::Error>> {
     let log_path = get_log_file_path();
     *LOG_FILE.lock().unwrap() = Some(log_path.clone());
     
+    // Format and levels saved by an earlier session
+    if let Some(settings) = db::get_setting(SETTINGS_KEY).ok().flatten()
+        .and_then(|json| serde_json::from_str::<LogSettings>(&json).ok())
+    {
+        *SETTINGS.write().unwrap() = settings;
+    }
+    
     // Log startup
     log(LogCategory::Conversation, None, "Intersect logging initialized");
     
@@ -72,28 +196,46 @@ pub fn init_logging() -> Result<(), Box<dyn std::error::Error>> {
 
 /// Log a message with category and optional conversation context
 pub fn log(category: LogCategory, conversation_id: Option<&str>, message: &str) {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let settings = SETTINGS.read().unwrap().clone();
+    log_to(&settings, &get_log_file_path(), category, conversation_id, message);
+}
+
+fn log_to(settings: &LogSettings, log_path: &Path, category: LogCategory, conversation_id: Option<&str>, message: &str) {
+    if settings.level(category) == LogLevel::Off {
+        return;
+    }
+    let now = Local::now();
+    let message = redact::redact(message);
     let conv_context = conversation_id
         .map(|id| format!("conversation={} | ", &id[..8.min(id.len())]))
         .unwrap_or_default();
     
-    let log_line = format!(
+    let text_line = format!(
         "[{}] [{}] {}{}\n",
-        timestamp,
+        now.format("%Y-%m-%d %H:%M:%S"),
         category.as_str(),
         conv_context,
         message
     );
+    let log_line = match settings.format {
+        LogFormat::Text => text_line.clone(),
+        LogFormat::Json => format!("{}\n", serde_json::json!({
+            "timestamp": now.to_rfc3339(),
+            "level": category.severity(),
+            "category": category.as_str(),
+            "conversation_id": conversation_id,
+            "message": message,
+        })),
+    };
     
     // Always print to console (for dev)
-    print!("{}", log_line);
+    print!("{}", text_line);
     
     // Write to file
-    let log_path = get_log_file_path();
     if let Ok(mut file) = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_path)
+        .open(log_path)
     {
         let _ = file.write_all(log_line.as_bytes());
     }
@@ -124,6 +266,125 @@ pub fn log_error(conversation_id: Option<&str>, message: &str) {
     log(LogCategory::Error, conversation_id, message);
 }
 
+/// Daily log files by date, newest first
+fn log_files(log_dir: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let Ok(dir) = fs::read_dir(log_dir) else { return Vec::new() };
+    let mut files: Vec<(NaiveDate, PathBuf)> = dir
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let date = name.strip_prefix("intersect-")?.strip_suffix(".log")?;
+            Some((NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?, path))
+        })
+        .collect();
+    files.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+    files
+}
+
+/// The `count` most recent log files, newest first
+pub fn recent_log_files(count: usize) -> Vec<PathBuf> {
+    log_files(&get_log_dir()).into_iter().take(count).map(|(_, path)| path).collect()
+}
+
+/// A log line read back for the log viewer
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LogEntry {
+    pub timestamp: String,               // RFC 3339
+    pub category: String,
+    pub conversation_id: Option<String>, // Only the first 8 characters in plaintext logs
+    pub message: String,
+}
+
+/// Parse one line in either format; None for lines that continue a multi-line message
+fn parse_line(line: &str) -> Option<LogEntry> {
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        return Some(LogEntry {
+            timestamp: value["timestamp"].as_str()?.to_string(),
+            category: value["category"].as_str()?.to_string(),
+            conversation_id: value["conversation_id"].as_str().map(str::to_string),
+            message: value["message"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    
+    // [2025-01-31 09:30:00] [MEMORY] conversation=abcd1234 | message
+    let (timestamp, rest) = line.strip_prefix('[')?.split_once("] [")?;
+    let (category, rest) = rest.split_once("] ")?;
+    LogCategory::parse(category)?;
+    let naive = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+    let timestamp = Local.from_local_datetime(&naive).earliest()?.to_rfc3339();
+    let (conversation_id, message) = match rest.strip_prefix("conversation=").and_then(|r| r.split_once(" | ")) {
+        Some((id, message)) => (Some(id.to_string()), message),
+        None => (None, rest),
+    };
+    Some(LogEntry {
+        timestamp,
+        category: category.to_string(),
+        conversation_id,
+        message: message.to_string(),
+    })
+}
+
+/// Log entries matching every given filter, the newest `limit` of them in chronological order.
+/// A conversation id matches plaintext lines by its 8-character prefix.
+pub fn query(
+    category: Option<&str>,
+    conversation_id: Option<&str>,
+    since: Option<DateTime<Utc>>,
+    limit: usize,
+) -> Result<Vec<LogEntry>, String> {
+    query_in(&get_log_dir(), category, conversation_id, since, limit)
+}
+
+fn query_in(
+    log_dir: &Path,
+    category: Option<&str>,
+    conversation_id: Option<&str>,
+    since: Option<DateTime<Utc>>,
+    limit: usize,
+) -> Result<Vec<LogEntry>, String> {
+    let category = category
+        .map(|c| LogCategory::parse(c).ok_or_else(|| format!("Unknown log category: {}", c)))
+        .transpose()?;
+    let matches = |entry: &LogEntry| {
+        category.is_none_or(|c| entry.category == c.as_str())
+            && conversation_id.is_none_or(|wanted| entry.conversation_id.as_deref().is_some_and(|id| {
+                id == wanted || (id.len() == 8 && wanted.starts_with(id))
+            }))
+            && since.is_none_or(|since| {
+                DateTime::parse_from_rfc3339(&entry.timestamp).is_ok_and(|t| t >= since)
+            })
+    };
+    
+    // Newest file first, newest line first, until the limit is reached
+    let mut entries = Vec::new();
+    for (date, path) in log_files(log_dir) {
+        // Files are named by local day, so older days can't hold lines after `since`
+        if since.is_some_and(|since| date < since.with_timezone(&Local).date_naive()) {
+            break;
+        }
+        let Ok(contents) = fs::read_to_string(&path) else { continue };
+        let mut file_entries: Vec<LogEntry> = Vec::new();
+        for line in contents.lines() {
+            match parse_line(line) {
+                Some(entry) => file_entries.push(entry),
+                None => {
+                    if let Some(last) = file_entries.last_mut() {
+                        last.message.push('\n');
+                        last.message.push_str(line);
+                    }
+                }
+            }
+        }
+        entries.extend(file_entries.into_iter().rev().filter(|e| matches(e)).take(limit - entries.len()));
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    entries.reverse();
+    Ok(entries)
+}
+
 /// Clean up old log files (keep last 7 days)
 pub fn cleanup_old_logs() -> Result<usize, Box<dyn std::error::Error>> {
     let log_dir = get_log_dir();
@@ -154,16 +415,95 @@ pub fn cleanup_old_logs() -> Result<usize, Box<dyn std::error::Error>> {
     Ok(deleted)
 }
 
-
-
-
-
-
-
-
-
-
-
-
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn reads_back_both_line_formats() {
+        let text = parse_line("[2025-01-31 09:30:00] [MEMORY] conversation=abcd1234 | Learned 2 facts").unwrap();
+        assert_eq!(text.category, "MEMORY");
+        assert_eq!(text.conversation_id.as_deref(), Some("abcd1234"));
+        assert_eq!(text.message, "Learned 2 facts");
+        assert!(text.timestamp.starts_with("2025-01-31T09:30:00"));
+        
+        let json = parse_line(r#"{"timestamp":"2025-01-31T09:30:00+00:00","level":"error","category":"ERROR","conversation_id":null,"message":"boom"}"#).unwrap();
+        assert_eq!((json.category.as_str(), json.conversation_id, json.message.as_str()), ("ERROR", None, "boom"));
+        
+        // Continuation lines of a multi-line message aren't entries
+        assert!(parse_line("    at line 2").is_none());
+        assert!(parse_line("[not] [A CATEGORY] text").is_none());
+    }
+    
+    #[test]
+    fn query_applies_every_filter() {
+        let dir = std::env::temp_dir().join(format!("intersect-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let stamp = |day: &str, time: &str| {
+            Local.from_local_datetime(&NaiveDateTime::parse_from_str(&format!("{} {}", day, time), "%Y-%m-%d %H:%M:%S").unwrap())
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        fs::write(dir.join("intersect-2025-01-30.log"), "\
+[2025-01-30 23:00:00] [MEMORY] conversation=abcd1234 | Old fact
+").unwrap();
+        fs::write(dir.join("intersect-2025-01-31.log"), "\
+[2025-01-31 09:00:00] [ROUTING] conversation=abcd1234 | Picked logic
+[2025-01-31 09:01:00] [MEMORY] conversation=abcd1234 | Learned 2 facts
+  second line of the same entry
+[2025-01-31 09:02:00] [MEMORY] conversation=ffff0000 | Other conversation
+[2025-01-31 09:03:00] [MEMORY] conversation=abcd1234 | Learned 1 fact
+").unwrap();
+        fs::write(dir.join("notes.txt"), "[2025-01-31 09:00:00] [ERROR] not a log file").unwrap();
+        let messages = |entries: Vec<LogEntry>| entries.into_iter().map(|e| e.message).collect::<Vec<_>>();
+        
+        // Full ids match plaintext lines by prefix; results come back oldest first
+        let memory = query_in(&dir, Some("memory"), Some("abcd1234-5678-90ab-cdef-000000000000"), None, 10).unwrap();
+        assert_eq!(messages(memory), vec!["Old fact", "Learned 2 facts\n  second line of the same entry", "Learned 1 fact"]);
+        
+        // `since` drops earlier lines, including whole earlier days
+        let since = query_in(&dir, None, None, Some(stamp("2025-01-31", "09:01:00")), 10).unwrap();
+        assert_eq!(since.len(), 3);
+        assert!(since.iter().all(|e| e.category == "MEMORY"));
+        
+        // `limit` keeps the newest
+        let newest = query_in(&dir, None, None, None, 2).unwrap();
+        assert_eq!(messages(newest), vec!["Other conversation", "Learned 1 fact"]);
+        
+        assert!(query_in(&dir, Some("nope"), None, None, 10).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn silenced_categories_write_nothing() {
+        let dir = std::env::temp_dir().join(format!("intersect-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("intersect-2025-01-31.log");
+        let mut settings = LogSettings::default();
+        for category in [LogCategory::Routing, LogCategory::Memory, LogCategory::Agent] {
+            settings.levels.insert(category.as_str().to_string(), LogLevel::Off);
+        }
+        
+        log_to(&settings, &path, LogCategory::Routing, None, "Picked logic");
+        log_to(&settings, &path, LogCategory::Memory, None, "Learned 2 facts");
+        log_to(&settings, &path, LogCategory::Error, None, "Extraction failed");
+        log_to(&settings, &path, LogCategory::Conversation, None, "Started");
+        
+        let entries = query_in(&dir, None, None, None, 10).unwrap();
+        let categories: Vec<&str> = entries.iter().map(|e| e.category.as_str()).collect();
+        assert_eq!(categories, vec!["ERROR", "CONVERSATION"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn saved_error_levels_keep_their_old_meaning() {
+        let settings: LogSettings = serde_json::from_str(
+            r#"{"format":"text","levels":{"ROUTING":"error","ERROR":"error","MEMORY":"info","AGENT":"off"}}"#
+        ).unwrap();
+        assert_eq!(settings.level(LogCategory::Routing), LogLevel::Off);
+        assert_eq!(settings.level(LogCategory::Error), LogLevel::Info);
+        assert_eq!(settings.level(LogCategory::Memory), LogLevel::Info);
+        assert_eq!(settings.level(LogCategory::Agent), LogLevel::Off);
+        assert_eq!(settings.level(LogCategory::Conversation), LogLevel::Info);
+    }
+}
@@ -0,0 +1,404 @@
+//! Model Context Protocol client for Intersect
+//!
+//! Users can add MCP servers (e.g. the reference filesystem server pointed at a notes folder, or a
+//! calendar server); their tools and resources join the agents' tool-use loop (see `tools`):
+//! - Servers run as child processes speaking JSON-RPC over stdio, started on first use and kept
+//!   running (one connection per server, requests on it are serialized)
+//! - Each server tool is offered as `mcp__<server>__<tool>`; servers with resources also get
+//!   `mcp__<server>__read_resource`
+//! - Server tools are off until the user turns each one on (see `tools::set_tool_enabled`)
+//! - A server that fails to start is left alone for RETRY_AFTER_SECS instead of stalling every turn
+//!
+//! Server configs are stored in app_settings as `mcp_servers`.
+
+use crate::db;
+use crate::logging;
+use crate::tools::ToolSpec;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+const SETTINGS_KEY: &str = "mcp_servers";
+const PROTOCOL_VERSION: &str = "2024-11-05";
+/// Prefix of tool names that route to an MCP server
+pub const TOOL_PREFIX: &str = "mcp__";
+const CONNECT_TIMEOUT_SECS: u64 = 15;
+const CALL_TIMEOUT_SECS: u64 = 30;
+const RETRY_AFTER_SECS: u64 = 300;
+/// Resources listed in the read_resource tool's description
+const LISTED_RESOURCES: usize = 25;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerConfig {
+    pub name: String,    // Letters, digits, - and _ (it becomes part of tool names)
+    pub command: String, // e.g. "npx"
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// What a server offers (returned to the settings UI)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerInfo {
+    pub name: String,
+    pub server_version: Option<String>,
+    pub tools: Vec<McpTool>,
+    pub resources: Vec<McpResource>,
+}
+
+struct Connection {
+    _child: Child, // Killed when the connection is dropped
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+struct McpClient {
+    config: McpServerConfig,
+    info: McpServerInfo,
+    connection: Mutex<Connection>,
+}
+
+static CLIENTS: Lazy<Mutex<HashMap<String, Arc<McpClient>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static FAILED: Lazy<std::sync::Mutex<HashMap<String, Instant>>> = Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+// ============ Settings ============
+
+pub fn get_servers() -> Vec<McpServerConfig> {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_servers(servers: &[McpServerConfig]) -> Result<(), String> {
+    let json = serde_json::to_string(servers).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// Add a server or replace the one with the same name (its running process is restarted on next use)
+pub async fn save_server(config: McpServerConfig) -> Result<(), String> {
+    let name = config.name.trim().to_string();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("Server names can only use letters, digits, - and _".to_string());
+    }
+    if config.command.trim().is_empty() {
+        return Err("A command is needed to start the server".to_string());
+    }
+    let config = McpServerConfig { name: name.clone(), command: config.command.trim().to_string(), ..config };
+
+    let mut servers = get_servers();
+    servers.retain(|s| s.name != name);
+    servers.push(config);
+    save_servers(&servers)?;
+    disconnect(&name).await;
+    Ok(())
+}
+
+pub async fn remove_server(name: &str) -> Result<bool, String> {
+    let mut servers = get_servers();
+    let before = servers.len();
+    servers.retain(|s| s.name != name);
+    if servers.len() == before {
+        return Ok(false);
+    }
+    save_servers(&servers)?;
+    disconnect(name).await;
+    Ok(true)
+}
+
+async fn disconnect(name: &str) {
+    CLIENTS.lock().await.remove(name);
+    FAILED.lock().unwrap().remove(name);
+}
+
+// ============ JSON-RPC over stdio ============
+
+impl Connection {
+    async fn send(&mut self, message: &Value) -> Result<(), String> {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await.map_err(|e| format!("MCP write failed: {}", e))?;
+        self.stdin.flush().await.map_err(|e| format!("MCP write failed: {}", e))
+    }
+
+    /// Send a request and wait for its response, skipping notifications and server requests
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+
+        loop {
+            let mut line = String::new();
+            let read = self.stdout.read_line(&mut line).await.map_err(|e| format!("MCP read failed: {}", e))?;
+            if read == 0 {
+                return Err("MCP server closed the connection".to_string());
+            }
+            let Ok(message) = serde_json::from_str::<Value>(line.trim()) else {
+                continue; // Servers sometimes log to stdout
+            };
+            if message["id"].as_u64() != Some(id) || message.get("method").is_some() {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(format!("MCP error: {}", error["message"].as_str().unwrap_or("unknown")));
+            }
+            return Ok(message["result"].clone());
+        }
+    }
+}
+
+/// Start a server, do the initialize handshake and list what it offers
+async fn connect(config: &McpServerConfig) -> Result<McpClient, String> {
+    let mut child = Command::new(&config.command)
+        .args(&config.args)
+        .envs(&config.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Couldn't start {}: {}", config.command, e))?;
+    let stdin = child.stdin.take().ok_or("No stdin for MCP server")?;
+    let stdout = BufReader::new(child.stdout.take().ok_or("No stdout for MCP server")?);
+    let mut connection = Connection { _child: child, stdin, stdout, next_id: 0 };
+
+    let initialized = connection.request("initialize", json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "intersect", "version": env!("CARGO_PKG_VERSION") }
+    })).await?;
+    connection.send(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await?;
+
+    let capabilities = &initialized["capabilities"];
+    let tools = if capabilities.get("tools").is_some() {
+        let listed = connection.request("tools/list", json!({})).await?;
+        listed["tools"].as_array().cloned().unwrap_or_default().iter()
+            .filter_map(|tool| Some(McpTool {
+                name: tool["name"].as_str()?.to_string(),
+                description: tool["description"].as_str().unwrap_or("").to_string(),
+                input_schema: tool.get("inputSchema").cloned().unwrap_or_else(|| json!({ "type": "object" })),
+            }))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let resources = if capabilities.get("resources").is_some() {
+        // Listing resources is optional for servers that only serve templates
+        match connection.request("resources/list", json!({})).await {
+            Ok(listed) => listed["resources"].as_array().cloned().unwrap_or_default().iter()
+                .filter_map(|resource| Some(McpResource {
+                    uri: resource["uri"].as_str()?.to_string(),
+                    name: resource["name"].as_str().unwrap_or("").to_string(),
+                    description: resource["description"].as_str().map(str::to_string),
+                }))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    } else {
+        Vec::new()
+    };
+
+    Ok(McpClient {
+        config: config.clone(),
+        info: McpServerInfo {
+            name: config.name.clone(),
+            server_version: initialized["serverInfo"]["version"].as_str().map(str::to_string),
+            tools,
+            resources,
+        },
+        connection: Mutex::new(connection),
+    })
+}
+
+/// The running client for a server, starting it if needed
+async fn client(config: &McpServerConfig) -> Result<Arc<McpClient>, String> {
+    let mut clients = CLIENTS.lock().await;
+    if let Some(client) = clients.get(&config.name) {
+        if client.config == *config {
+            return Ok(client.clone());
+        }
+    }
+    if let Some(failed_at) = FAILED.lock().unwrap().get(&config.name) {
+        if failed_at.elapsed() < Duration::from_secs(RETRY_AFTER_SECS) {
+            return Err(format!("MCP server {} failed to start recently", config.name));
+        }
+    }
+
+    match tokio::time::timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS), connect(config)).await {
+        Ok(Ok(client)) => {
+            logging::log_agent(None, &format!(
+                "Connected to MCP server {} ({} tools, {} resources)",
+                config.name, client.info.tools.len(), client.info.resources.len()
+            ));
+            let client = Arc::new(client);
+            clients.insert(config.name.clone(), client.clone());
+            FAILED.lock().unwrap().remove(&config.name);
+            Ok(client)
+        }
+        result => {
+            let error = match result {
+                Ok(Err(e)) => e,
+                _ => "timed out connecting".to_string(),
+            };
+            FAILED.lock().unwrap().insert(config.name.clone(), Instant::now());
+            Err(format!("MCP server {}: {}", config.name, error))
+        }
+    }
+}
+
+/// Connect to a configured server (retrying even if it failed recently) and describe it
+pub async fn server_info(name: &str) -> Result<McpServerInfo, String> {
+    let config = get_servers().into_iter().find(|s| s.name == name).ok_or_else(|| format!("No MCP server named {}", name))?;
+    FAILED.lock().unwrap().remove(name);
+    Ok(client(&config).await?.info.clone())
+}
+
+// ============ Agent Tools ============
+
+fn tool_name(server: &str, tool: &str) -> String {
+    // Function names are limited to 64 characters of [a-zA-Z0-9_-]
+    let tool: String = tool.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+    format!("{}{}__{}", TOOL_PREFIX, server, tool).chars().take(64).collect()
+}
+
+/// Tools from every enabled server that's reachable
+pub async fn tool_specs() -> Vec<ToolSpec> {
+    let mut specs = Vec::new();
+    for config in get_servers().iter().filter(|s| s.enabled) {
+        let client = match client(config).await {
+            Ok(client) => client,
+            Err(e) => {
+                logging::log_error(None, &e);
+                continue;
+            }
+        };
+        for tool in &client.info.tools {
+            specs.push(ToolSpec {
+                name: tool_name(&config.name, &tool.name),
+                description: format!("[{} via MCP] {}", config.name, tool.description),
+                parameters: tool.input_schema.clone(),
+            });
+        }
+        if !client.info.resources.is_empty() {
+            let listed = client.info.resources.iter()
+                .take(LISTED_RESOURCES)
+                .map(|r| format!("{} ({})", r.uri, r.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            specs.push(ToolSpec {
+                name: tool_name(&config.name, "read_resource"),
+                description: format!("[{} via MCP] Read a resource by URI. Available: {}", config.name, listed),
+                parameters: json!({
+                    "type": "object",
+                    "properties": { "uri": { "type": "string" } },
+                    "required": ["uri"]
+                }),
+            });
+        }
+    }
+    specs
+}
+
+/// Text parts of an MCP content list
+fn content_text(content: &Value) -> String {
+    content.as_array().cloned().unwrap_or_default().iter()
+        .filter_map(|part| match part["type"].as_str() {
+            Some("text") => part["text"].as_str().map(str::to_string),
+            Some("resource") => part["resource"]["text"].as_str().map(str::to_string),
+            Some(other) => Some(format!("[{} content]", other)),
+            None => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Run an `mcp__<server>__<tool>` call
+pub async fn call_tool(name: &str, arguments: &Value) -> Result<String, String> {
+    let rest = name.strip_prefix(TOOL_PREFIX).ok_or("Not an MCP tool")?;
+    let config = get_servers().into_iter()
+        .filter(|s| s.enabled)
+        .find(|s| rest.starts_with(&format!("{}__", s.name)))
+        .ok_or_else(|| format!("No MCP server for {}", name))?;
+    let client = client(&config).await?;
+
+    let (method, params) = if name == tool_name(&config.name, "read_resource")
+        && !client.info.tools.iter().any(|t| t.name == "read_resource")
+    {
+        ("resources/read", json!({ "uri": arguments["uri"] }))
+    } else {
+        let tool = client.info.tools.iter()
+            .find(|t| tool_name(&config.name, &t.name) == name)
+            .ok_or_else(|| format!("{} has no tool {}", config.name, name))?;
+        ("tools/call", json!({ "name": tool.name, "arguments": arguments }))
+    };
+
+    let request = async { client.connection.lock().await.request(method, params).await };
+    let result = match tokio::time::timeout(Duration::from_secs(CALL_TIMEOUT_SECS), request).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => {
+            // A dead server is restarted on the next call
+            CLIENTS.lock().await.remove(&config.name);
+            return Err(e);
+        }
+        Err(_) => {
+            CLIENTS.lock().await.remove(&config.name);
+            return Err(format!("{} timed out", name));
+        }
+    };
+
+    if method == "resources/read" {
+        return Ok(result["contents"].as_array().cloned().unwrap_or_default().iter()
+            .map(|c| c["text"].as_str().map(str::to_string).unwrap_or_else(|| format!("[binary content: {}]", c["uri"])))
+            .collect::<Vec<_>>()
+            .join("\n"));
+    }
+    let text = content_text(&result["content"]);
+    if result["isError"].as_bool().unwrap_or(false) {
+        return Err(text);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_names_are_valid_function_names() {
+        assert_eq!(tool_name("notes", "read_file"), "mcp__notes__read_file");
+        assert_eq!(tool_name("cal", "events.list"), "mcp__cal__events_list");
+        let long = tool_name("server", &"x".repeat(100));
+        assert_eq!(long.len(), 64);
+        assert!(long.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+    }
+}
@@ -7,8 +7,10 @@
 //! - Building a comprehensive user profile
 
 use crate::db::{self, UserFact, UserPattern, ConversationSummary, Message};
-use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_OPUS};
+use crate::anthropic::{cacheable, AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU, CLAUDE_OPUS, CLAUDE_SONNET};
 use crate::logging;
+use crate::redact;
+use crate::webhooks;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -21,6 +23,16 @@ pub struct ExtractionResult {
     pub updated_facts: Vec<FactUpdate>,
     pub new_patterns: Vec<ExtractedPattern>,
     pub themes: Vec<String>,
+    #[serde(default)]
+    pub habits: Vec<ExtractedHabit>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExtractedHabit {
+    pub name: String,
+    pub target_per_week: Option<i64>,
+    #[serde(default)]
+    pub done: bool, // User reported doing it in this exchange
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,6 +70,10 @@ pub struct UserProfileSummary {
     pub communication_style: Option<String>,
     pub thinking_preference: Option<String>,
     pub emotional_tendency: Option<String>,
+    #[serde(default)]
+    pub habits: Vec<String>, // Pre-formatted habit progress lines from logged data
+    #[serde(default)]
+    pub starred: Vec<String>, // Messages the user starred, newest first ("ROLE: content")
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -76,6 +92,18 @@ pub struct PatternSummary {
 
 // ============ Memory Extractor ============
 
+/// Most starred messages carried in the profile (newest first)
+const MAX_STARRED_IN_PROFILE: usize = 10;
+
+/// Appended to the extraction prompt for journal conversations
+const JOURNAL_EXTRACTION_GUIDANCE: &str = r#"THIS EXCHANGE IS A JOURNAL ENTRY (the user reflecting on their day, prompted by an agent):
+- Facts: lasting things the entry reveals (people in their life, ongoing projects, health, commitments), not one-off
+  events of the day. "Had a rough meeting with my manager Sam" -> relationships/manager: "Sam", not the meeting itself
+- Patterns: journaling shows how they process their days -- what they dwell on, how they talk about setbacks and wins,
+  what restores them. Patterns confirmed across entries deserve more confidence than in chat (up to 0.7)
+- Themes: the day's main concerns and moods (e.g. "work stress", "gratitude", "sleep")
+- Habits: journaling often reports completions ("ran this morning", "skipped the gym again") -- log them"#;
+
 pub struct MemoryExtractor {
     client: AnthropicClient,
 }
@@ -96,8 +124,46 @@ impl MemoryExtractor {
         conversation_id: &str,
     ) -> Result<ExtractionResult, Box<dyn Error + Send + Sync>> {
         logging::log_memory(Some(conversation_id), &format!(
-            "Starting extraction. User message: {}", &user_message[..user_message.len().min(100)]
+            "Starting extraction. User message: {}", user_message.chars().take(100).collect::<String>()
         ));
+        // Format agent responses
+        let responses_text = agent_responses
+            .iter()
+            .map(|(agent, content)| format!("{}: {}", agent.to_uppercase(), content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let exchange = format!("USER: {}\n{}", user_message, responses_text);
+        self.extract(&exchange, &[user_message], existing_facts, conversation_id).await
+    }
+    
+    /// Extract facts and patterns from a run of (role, content) messages -- an imported conversation,
+    /// or what's left unextracted at finalize -- each labeled with its speaker
+    pub async fn extract_from_transcript(
+        &self,
+        messages: &[(String, String)],
+        existing_facts: &[UserFact],
+        conversation_id: &str,
+    ) -> Result<ExtractionResult, Box<dyn Error + Send + Sync>> {
+        logging::log_memory(Some(conversation_id), &format!("Starting extraction over {} messages", messages.len()));
+        let transcript = messages.iter()
+            .map(|(role, content)| format!("{}: {}", role.to_uppercase(), content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let user_text: Vec<&str> = messages.iter()
+            .filter(|(role, _)| role == "user")
+            .map(|(_, content)| content.as_str())
+            .collect();
+        self.extract(&transcript, &user_text, existing_facts, conversation_id).await
+    }
+    
+    /// Run extraction over labeled conversation text; `user_text` is what the user wrote (for language)
+    async fn extract(
+        &self,
+        exchange: &str,
+        user_text: &[&str],
+        existing_facts: &[UserFact],
+        conversation_id: &str,
+    ) -> Result<ExtractionResult, Box<dyn Error + Send + Sync>> {
         // Build context of existing facts for the LLM
         let existing_facts_context = if existing_facts.is_empty() {
             "No existing facts about the user.".to_string()
@@ -110,19 +176,12 @@ impl MemoryExtractor {
                 .join("\n")
         };
         
-        // Format agent responses
-        let responses_text = agent_responses
-            .iter()
-            .map(|(agent, content)| format!("{}: {}", agent.to_uppercase(), content))
-            .collect::<Vec<_>>()
-            .join("\n");
-        
         let system_prompt = r#"You are a memory extraction system for Intersect, a multi-agent AI assistant. Your job is to extract learnable information from conversations.
 
 EXTRACT TWO TYPES OF INFORMATION:
 
 1. FACTS (explicit statements by the user about themselves):
-   Categories: "personal", "preferences", "work", "relationships", "values", "interests", "background"
+   Categories: "personal", "preferences", "work", "relationships", "health", "values", "interests", "background"
    - Only extract what the USER explicitly states
    - High confidence (0.8-1.0) for direct statements
    - Lower confidence (0.5-0.7) for implied information
@@ -137,6 +196,12 @@ EXTRACT TWO TYPES OF INFORMATION:
    - Extract 1-3 main themes/topics from this exchange
    - These help track what the user cares about over time
 
+4. HABITS (recurring behaviors the user is trying to keep):
+   - Declared habits: "trying to run three times a week" -> {"name": "run", "target_per_week": 3, "done": false}
+   - Reported completions: "went for a run this morning" -> {"name": "run", "target_per_week": null, "done": true}
+   - Short lowercase names; reuse the name of an existing habit when it's the same thing
+   - Leave empty unless the user clearly talks about a habit
+
 IMPORTANT:
 - Be conservative - only extract clear, meaningful information
 - Don't repeat existing facts unless you're confirming/updating them
@@ -147,14 +212,31 @@ Respond with ONLY valid JSON in this exact format:
   "new_facts": [{"category": "...", "key": "...", "value": "...", "confidence": 0.9, "source_type": "explicit"}],
   "updated_facts": [{"category": "...", "key": "...", "new_value": "..." or null, "confirmed": true}],
   "new_patterns": [{"pattern_type": "...", "description": "...", "confidence": 0.5, "evidence": "..."}],
-  "themes": ["theme1", "theme2"]
+  "themes": ["theme1", "theme2"],
+  "habits": [{"name": "...", "target_per_week": 3, "done": false}]
 }"#;
 
+        // Journal entries are first-person reflections on the day, so they get their own guidance
+        let is_journal = db::is_journal_conversation(conversation_id).unwrap_or(false);
+        let system_prompt = if is_journal {
+            format!("{}\n\n{}", system_prompt, JOURNAL_EXTRACTION_GUIDANCE)
+        } else {
+            system_prompt.to_string()
+        };
+        let system_prompt = match crate::language::extraction_instruction(user_text) {
+            Some(instruction) => format!("{}\n\n{}", system_prompt, instruction),
+            None => system_prompt,
+        };
+        
+        // Instructions and known facts rarely change between exchanges, so they form the cached
+        // system prompt; only the exchange itself goes in the user turn
+        let system_prompt = cacheable(
+            &format!("{}\n\nEXISTING FACTS ABOUT USER:\n{}", system_prompt, existing_facts_context),
+            "",
+        );
         let user_prompt = format!(
-            "EXISTING FACTS ABOUT USER:\n{}\n\nCONVERSATION EXCHANGE:\nUSER: {}\n{}\n\nExtract any new learnable information:",
-            existing_facts_context,
-            user_message,
-            responses_text
+            "CONVERSATION EXCHANGE:\n{}\n\nExtract any new learnable information:",
+            exchange
         );
 
         // Use Anthropic client for memory extraction (Opus, thinking high)
@@ -167,7 +249,7 @@ Respond with ONLY valid JSON in this exact format:
 
         let response = self.client.chat_completion_advanced(
             CLAUDE_OPUS,
-            Some(system_prompt),
+            Some(&system_prompt),
             messages,
             0.2,
             Some(800),
@@ -196,6 +278,7 @@ Respond with ONLY valid JSON in this exact format:
                     updated_facts: Vec::new(),
                     new_patterns: Vec::new(),
                     themes: Vec::new(),
+                    habits: Vec::new(),
                 }
             }
         };
@@ -205,19 +288,26 @@ Respond with ONLY valid JSON in this exact format:
             result.new_facts.len(), result.new_patterns.len(), result.themes.len()
         ));
         
-        // Save extracted data to database
-        self.save_extraction_result(&result, conversation_id)?;
+        // Save extracted data to database (a burst of small writes, kept off the async runtime)
+        let to_save = result.clone();
+        let source_conversation = conversation_id.to_string();
+        db::run_blocking(move || Self::save_extraction_result(&to_save, &source_conversation)).await??;
+        redact::refresh_names();
         logging::log_memory(Some(conversation_id), "Saved extraction result to database");
         
         Ok(result)
     }
     
     /// Save extraction results to the database
-    fn save_extraction_result(&self, result: &ExtractionResult, conversation_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    fn save_extraction_result(result: &ExtractionResult, conversation_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
         let now = Utc::now().to_rfc3339();
         
-        // Save new facts
+        // Save new facts (except keys the user asked to forget)
         for fact in &result.new_facts {
+            if db::is_fact_key_blocked(&fact.key).unwrap_or(false) {
+                logging::log_memory(Some(conversation_id), &format!("Skipped forgotten fact: {}", fact.key));
+                continue;
+            }
             let user_fact = UserFact {
                 id: 0, // Will be assigned by DB
                 category: fact.category.clone(),
@@ -230,7 +320,14 @@ Respond with ONLY valid JSON in this exact format:
                 last_confirmed: now.clone(),
                 mention_count: 1,
             };
-            let _ = db::save_user_fact(&user_fact);
+            // Only newly learned facts are announced, not re-confirmations
+            if let Ok((id, true)) = db::save_user_fact(&user_fact) {
+                if fact.confidence >= webhooks::HIGH_CONFIDENCE {
+                    if let Ok(Some(stored)) = db::get_user_fact(id) {
+                        webhooks::emit("fact.learned", serde_json::json!(stored));
+                    }
+                }
+            }
         }
         
         // Save new patterns
@@ -253,6 +350,15 @@ Respond with ONLY valid JSON in this exact format:
             let _ = db::save_recurring_theme(theme, conversation_id);
         }
         
+        // Save habits (inferred declarations and reported completions)
+        for habit in &result.habits {
+            if let Ok(habit_id) = db::upsert_habit(&habit.name, habit.target_per_week, "inferred") {
+                if habit.done {
+                    let _ = db::log_habit(&habit_id, None, Some(conversation_id));
+                }
+            }
+        }
+        
         Ok(())
     }
     
@@ -299,6 +405,21 @@ Respond with ONLY valid JSON in this exact format:
             });
         }
         
+        // Habit progress straight from the logs, so agents cite real streaks and lapses
+        let habits = db::get_habit_progress().unwrap_or_default()
+            .iter()
+            .map(|h| h.describe())
+            .collect();
+        
+        // Starred messages are things the user explicitly found valuable
+        let starred = db::get_starred_messages(Some(MAX_STARRED_IN_PROFILE)).unwrap_or_default()
+            .into_iter()
+            .map(|s| {
+                let content: String = s.message.content.chars().take(300).collect();
+                format!("{}: {}", s.message.role.to_uppercase(), content)
+            })
+            .collect();
+        
         Ok(UserProfileSummary {
             facts_by_category,
             top_patterns,
@@ -306,6 +427,8 @@ Respond with ONLY valid JSON in this exact format:
             communication_style,
             thinking_preference,
             emotional_tendency,
+            habits,
+            starred,
         })
     }
     
@@ -341,13 +464,23 @@ Respond with ONLY valid JSON in this exact format:
                 if let Some(thinking) = &profile.thinking_preference {
                     parts.push(format!("Thinking: {}", thinking));
                 }
+                if !profile.habits.is_empty() {
+                    parts.push(format!("HABITS (from logged data):\n  {}", profile.habits.join("\n  ")));
+                }
                 
                 parts.join("\n")
             }
             GroundingLevel::Deep => {
-                // Full profile
+                // Full profile, led by what the user starred
                 let mut parts = Vec::new();
                 
+                if !profile.starred.is_empty() {
+                    parts.push(format!(
+                        "SAVED INSIGHTS (the user starred these as valuable -- weigh them first):\n  {}",
+                        profile.starred.join("\n  ")
+                    ));
+                }
+                
                 for (category, facts) in &profile.facts_by_category {
                     if !facts.is_empty() {
                         let items: Vec<String> = facts.iter().map(|f| {
@@ -368,12 +501,240 @@ Respond with ONLY valid JSON in this exact format:
                     parts.push(format!("RECURRING THEMES: {}", profile.recurring_themes.join(", ")));
                 }
                 
+                if !profile.habits.is_empty() {
+                    parts.push(format!("HABITS (from logged data):\n  {}", profile.habits.join("\n  ")));
+                }
+                
                 parts.join("\n")
             }
         }
     }
 }
 
+// ============ Sensitive Category Guardrail ============
+
+/// Words that indicate the user raised a sensitive category themselves
+fn sensitive_category_keywords(category: &str) -> &'static [&'static str] {
+    match category {
+        "relationships" => &["relationship", "partner", "wife", "husband", "girlfriend", "boyfriend",
+            "dating", "marriage", "married", "divorce", "breakup", "family", "mom", "dad",
+            "parent", "friend", "kids", "children"],
+        "health" => &["health", "doctor", "sick", "illness", "diagnos", "therapy", "therapist",
+            "medication", "meds", "pain", "injury", "surgery", "anxiety", "depress", "sleep", "hospital"],
+        _ => &[],
+    }
+}
+
+impl MemoryExtractor {
+    /// Strip sensitive fact categories (and patterns that touch them) from a profile,
+    /// unless the user brought the topic up in this session's messages
+    pub fn guard_sensitive_categories(profile: &UserProfileSummary, session_user_text: &str) -> UserProfileSummary {
+        let sensitive = db::get_sensitive_fact_categories().unwrap_or_default();
+        if sensitive.is_empty() {
+            return profile.clone();
+        }
+        let session_lower = session_user_text.to_lowercase();
+        
+        let mut guarded = profile.clone();
+        for category in &sensitive {
+            let facts = profile.facts_by_category.get(category);
+            
+            // Raised this session: a category keyword, the category name, or a stored fact value
+            let raised = session_lower.contains(category.as_str())
+                || sensitive_category_keywords(category).iter().any(|k| session_lower.contains(k))
+                || facts.is_some_and(|fs| fs.iter().any(|f| {
+                    f.value.len() > 3 && session_lower.contains(&f.value.to_lowercase())
+                }));
+            if raised {
+                continue;
+            }
+            
+            guarded.facts_by_category.remove(category);
+            let keywords = sensitive_category_keywords(category);
+            guarded.top_patterns.retain(|p| {
+                let desc = p.description.to_lowercase();
+                !keywords.iter().any(|k| desc.contains(k))
+            });
+            logging::log_memory(None, &format!("Guardrail: withholding '{}' from adversarial grounding", category));
+        }
+        
+        guarded
+    }
+}
+
+// ============ Explicit Memory ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RememberRequest {
+    pub remember: bool,
+    pub category: Option<String>,
+    pub key: Option<String>,
+    pub value: Option<String>,
+}
+
+/// Cheap pre-filter so we only ask the model when the user may be asking to be remembered
+pub fn mentions_remember(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    ["remember that", "remember this", "remember my", "don't forget", "do not forget", "keep in mind", "make a note"]
+        .iter()
+        .any(|k| lower.contains(k))
+}
+
+impl MemoryExtractor {
+    /// If the user explicitly asks to have something about them remembered, the fact to store
+    pub async fn detect_remember_request(&self, user_message: &str) -> Result<Option<ExtractedFact>, Box<dyn Error + Send + Sync>> {
+        let system_prompt = r#"You detect explicit requests to remember a fact about the user ("remember that my sister is named Ana").
+
+Only count a direct request to remember, note or keep in mind something about the user or their life.
+Reminiscing ("I remember when...") or asking the assistant to recall something is NOT a request.
+
+If it is a request, respond with:
+{"remember": true, "category": "personal" | "preferences" | "work" | "relationships" | "values" | "health", "key": "short_snake_case_key", "value": "the fact"}
+Otherwise:
+{"remember": false, "category": null, "key": null, "value": null}
+
+Respond with ONLY valid JSON."#;
+        
+        let response = self.client.chat_completion_advanced(
+            CLAUDE_HAIKU,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content: user_message.to_string() }],
+            0.0,
+            Some(150),
+            ThinkingBudget::None
+        ).await?;
+        
+        let cleaned = response
+            .trim()
+            .trim_start_matches("```json")
+            .trim_end_matches("```")
+            .trim();
+        
+        let fact = serde_json::from_str::<RememberRequest>(cleaned).ok()
+            .filter(|r| r.remember)
+            .and_then(|r| match (r.category, r.key, r.value) {
+                (Some(category), Some(key), Some(value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+                    Some(ExtractedFact {
+                        category,
+                        key: key.trim().to_string(),
+                        value: value.trim().to_string(),
+                        confidence: 1.0,
+                        source_type: "explicit".to_string(),
+                    })
+                }
+                _ => None,
+            });
+        Ok(fact)
+    }
+}
+
+// ============ Consolidation ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FactMerge {
+    pub keep_id: i64,
+    pub merge_ids: Vec<i64>,
+    pub category: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternMerge {
+    pub keep_id: i64,
+    pub merge_ids: Vec<i64>,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConsolidationPlan {
+    #[serde(default)]
+    pub fact_merges: Vec<FactMerge>,
+    #[serde(default)]
+    pub pattern_merges: Vec<PatternMerge>,
+}
+
+/// Most facts/patterns sent for one consolidation pass (highest confidence first)
+const CONSOLIDATION_MAX_ITEMS: usize = 150;
+
+impl MemoryExtractor {
+    /// Ask Claude which facts and patterns say the same thing and what the merged entry should read
+    pub async fn plan_consolidation(
+        &self,
+        facts: &[UserFact],
+        patterns: &[UserPattern],
+    ) -> Result<ConsolidationPlan, Box<dyn Error + Send + Sync>> {
+        if facts.len() < 2 && patterns.len() < 2 {
+            return Ok(ConsolidationPlan::default());
+        }
+        
+        let facts_text = facts.iter()
+            .take(CONSOLIDATION_MAX_ITEMS)
+            .map(|f| format!("[{}] {}/{}: {}", f.id, f.category, f.key, f.value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let patterns_text = patterns.iter()
+            .take(CONSOLIDATION_MAX_ITEMS)
+            .map(|p| format!("[{}] {}: {}", p.id, p.pattern_type, p.description))
+            .collect::<Vec<_>>()
+            .join("\n");
+        
+        let system_prompt = r#"You tidy the long-term memory of Intersect, a personal AI. Find entries that say the same thing about the user.
+
+Merge ONLY true duplicates or near-duplicates ("job: engineer" and "occupation: software engineer").
+Never merge entries that are merely related, or that contradict each other (an old and a new city are not duplicates).
+For each merge, pick the best entry as keep_id, list the others in merge_ids, and write the merged entry
+(most specific and current wording; keep the category and a short snake_case key for facts).
+Patterns merge only within the same pattern type.
+
+Respond with ONLY valid JSON:
+{
+  "fact_merges": [{"keep_id": 1, "merge_ids": [2], "category": "work", "key": "occupation", "value": "software engineer"}],
+  "pattern_merges": [{"keep_id": 3, "merge_ids": [4], "description": "..."}]
+}
+Use empty arrays when nothing should be merged."#;
+        
+        let user_prompt = format!(
+            "FACTS:\n{}\n\nPATTERNS:\n{}",
+            if facts_text.is_empty() { "(none)" } else { &facts_text },
+            if patterns_text.is_empty() { "(none)" } else { &patterns_text }
+        );
+        
+        let response = self.client.chat_completion_advanced(
+            CLAUDE_SONNET,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content: user_prompt }],
+            0.1,
+            Some(1500),
+            ThinkingBudget::None
+        ).await?;
+        
+        let cleaned = response
+            .trim()
+            .trim_start_matches("```json")
+            .trim_end_matches("```")
+            .trim();
+        
+        let mut plan: ConsolidationPlan = serde_json::from_str(cleaned)?;
+        
+        // Only ids we actually sent, and every id in at most one merge
+        let mut seen = std::collections::HashSet::new();
+        plan.fact_merges.retain(|m| {
+            let ids: Vec<i64> = std::iter::once(m.keep_id).chain(m.merge_ids.iter().copied()).collect();
+            !m.merge_ids.is_empty()
+                && ids.iter().all(|id| facts.iter().any(|f| f.id == *id))
+                && ids.iter().all(|id| seen.insert(("fact", *id)))
+        });
+        plan.pattern_merges.retain(|m| {
+            let ids: Vec<i64> = std::iter::once(m.keep_id).chain(m.merge_ids.iter().copied()).collect();
+            !m.merge_ids.is_empty()
+                && ids.iter().all(|id| patterns.iter().any(|p| p.id == *id))
+                && ids.iter().all(|id| seen.insert(("pattern", *id)))
+        });
+        Ok(plan)
+    }
+}
+
 // ============ Conversation Summarizer ============
 
 pub struct ConversationSummarizer {
@@ -431,6 +792,16 @@ Respond with ONLY valid JSON:
   "emotional_tone": "...",
   "user_state": "..." or null
 }"#;
+        let user_samples: Vec<&str> = messages.iter()
+            .rev()
+            .filter(|m| m.role == "user")
+            .take(5)
+            .map(|m| m.content.as_str())
+            .collect();
+        let system_prompt = match crate::language::summary_instruction(&user_samples) {
+            Some(instruction) => format!("{}\n\n{}", system_prompt, instruction),
+            None => system_prompt.to_string(),
+        };
 
         // Use Anthropic client for summarization (Opus, thinking high)
         let api_messages = vec![
@@ -442,7 +813,7 @@ Respond with ONLY valid JSON:
 
         let response = self.client.chat_completion_advanced(
             CLAUDE_OPUS,
-            Some(system_prompt),
+            Some(&system_prompt),
             api_messages,
             0.3,
             Some(400),
@@ -487,10 +858,100 @@ Respond with ONLY valid JSON:
         };
         
         db::save_conversation_summary(&summary)?;
+        crate::mood::record_summary(conversation_id, result);
         Ok(())
     }
 }
 
+// ============ Decision Detection ============
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DecisionResult {
+    pub decision_made: bool,
+    pub decision: Option<String>,
+    #[serde(default)]
+    pub options: Vec<String>,
+    pub followed_agent: Option<String>, // "instinct" | "logic" | "psyche" | null
+}
+
+impl ConversationSummarizer {
+    /// Detect whether the user reached a decision in this conversation
+    pub async fn detect_decision(&self, messages: &[Message]) -> Result<Option<DecisionResult>, Box<dyn Error + Send + Sync>> {
+        let messages_text: String = messages
+            .iter()
+            .map(|m| format!("{}: {}", m.role.to_uppercase(), m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        
+        let system_prompt = r#"You review Intersect conversations for decisions. Agents are INSTINCT, LOGIC and PSYCHE; GOVERNOR synthesizes.
+
+A decision counts ONLY if the USER clearly commits to a choice (e.g. "I'm going with the smaller apartment"). Exploring, leaning, or asking is not a decision.
+
+Respond with ONLY valid JSON:
+{
+  "decision_made": true/false,
+  "decision": "short statement of what they chose" or null,
+  "options": ["option considered", "..."],
+  "followed_agent": "instinct" | "logic" | "psyche" | null
+}
+followed_agent is the agent whose advice the choice matches most closely, or null if none clearly."#;
+        
+        let response = self.client.chat_completion_advanced(
+            CLAUDE_HAIKU,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content: messages_text }],
+            0.1,
+            Some(300),
+            ThinkingBudget::None
+        ).await?;
+        
+        let cleaned = response
+            .trim()
+            .trim_start_matches("```json")
+            .trim_end_matches("```")
+            .trim();
+        
+        let result = serde_json::from_str::<DecisionResult>(cleaned).ok()
+            .filter(|d| d.decision_made && d.decision.as_ref().is_some_and(|t| !t.trim().is_empty()));
+        Ok(result)
+    }
+    
+    /// One short question for the user to sit with after the session, drawn from the summary
+    pub async fn generate_reflection(&self, summary: &SummaryResult) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        if summary.summary.trim().is_empty() {
+            return Ok(None);
+        }
+        
+        let system_prompt = r#"You are the Governor, leaving the user one question to reflect on after a conversation.
+
+- Exactly one question, under 25 words, addressed to the user as "you"
+- Open-ended and specific to what they talked about -- not generic self-help
+- Gentle, never prescriptive; don't summarize the conversation back to them
+- Output ONLY the question, no quotes or preamble
+- When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")"#;
+        
+        let mut context = format!("Summary: {}", summary.summary);
+        if !summary.key_topics.is_empty() {
+            context.push_str(&format!("\nTopics: {}", summary.key_topics.join(", ")));
+        }
+        if let Some(state) = &summary.user_state {
+            context.push_str(&format!("\nHow they seemed: {}", state));
+        }
+        
+        let response = self.client.chat_completion_advanced(
+            CLAUDE_HAIKU,
+            Some(system_prompt),
+            vec![AnthropicMessage { role: "user".to_string(), content: context }],
+            0.7,
+            Some(80),
+            ThinkingBudget::None
+        ).await?;
+        
+        let question = response.trim().trim_matches('"').trim().to_string();
+        Ok(Some(question).filter(|q| !q.is_empty()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SummaryResult {
     pub summary: String,
@@ -0,0 +1,279 @@
+//! Mood and sentiment over time for Intersect
+//!
+//! Two sources feed the mood log:
+//! - every user message gets a lightweight lexicon score (no model call), from -1.0 to 1.0;
+//!   messages with no emotional words at all are left out rather than logged as neutral
+//! - every conversation summary records its `emotional_tone` and `user_state`, scored the same way
+//!
+//! `trend` buckets the log by day (ranges up to a month) or by week, for the mood view.
+
+use crate::clock;
+use crate::db::{self, Message, MoodEntry};
+use crate::logging;
+use crate::memory::SummaryResult;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Word weights, each with its inflections spelled out: only whole words match, so "happen",
+// "function" or "crystal" never count as feelings
+const LEXICON: &[(&[&str], f64)] = &[
+    // Positive
+    (&["happy", "happier", "happiest", "happiness", "happily"], 2.0),
+    (&["joy", "joyful", "joyous"], 2.0),
+    (&["love", "loved", "loving", "lovely"], 2.0),
+    (&["excited", "exciting", "excitement", "thrilled", "thrilling", "wonderful", "amazing", "awesome"], 2.0),
+    (&["great", "proud", "grateful", "thankful", "relieved", "hopeful", "optimistic", "enthusiastic"], 1.5),
+    (&["confident", "energized", "motivated", "glad", "accomplished", "accomplishment"], 1.5),
+    (&["fun", "enjoy", "enjoyed", "enjoying", "good", "nice", "better", "calm", "calmer", "peaceful"], 1.0),
+    (&["positive", "playful", "win", "won", "winning"], 1.0),
+    (&["curious", "progress"], 0.5),
+    // Negative
+    (&["overwhelm", "overwhelmed", "overwhelming", "depressed", "depressing", "depression", "hopeless", "miserable"], -2.0),
+    (&["panic", "panicked", "panicking", "awful", "terrible", "horrible", "hate", "hated", "hating"], -2.0),
+    (&["stress", "stressed", "stressful", "anxious", "anxiety", "worry", "worried", "worrying", "exhausted", "exhausting"], -1.5),
+    (&["sad", "sadder", "sadness", "lonely", "loneliness", "frustrated", "frustrating", "frustration", "angry"], -1.5),
+    (&["upset", "hurt", "hurting", "disappointed", "disappointing", "afraid", "scared", "fear", "guilt", "guilty"], -1.5),
+    (&["ashamed", "cry", "cried", "crying", "drained", "draining"], -1.5),
+    (&["tired", "annoyed", "annoying", "tense", "burnout", "burned", "burnt", "stuck", "bad", "worse", "lost", "negative"], -1.0),
+    (&["bored", "confused", "confusing", "uncertain"], -0.5),
+];
+
+// Words that flip the next few words ("not happy"), and ones that strengthen the next word
+const NEGATORS: &[&str] = &["not", "no", "never", "hardly", "barely", "without", "isn't", "wasn't", "don't", "didn't", "can't", "won't"];
+const INTENSIFIERS: &[&str] = &["very", "really", "so", "extremely", "super", "incredibly", "totally", "completely"];
+
+/// Ranges longer than this many days are bucketed by week
+const DAILY_BUCKET_MAX_DAYS: i64 = 31;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodPoint {
+    pub period: String,                 // Local YYYY-MM-DD: the day, or the Monday starting the week
+    pub average_sentiment: Option<f64>, // None when only summaries without a scorable tone landed here
+    pub message_count: usize,           // Scored user messages
+    pub tones: Vec<String>,             // Most common summary tones, up to 3
+    pub user_states: Vec<String>,       // Most common summary user states, up to 3
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodTrend {
+    pub range: String,
+    pub granularity: String,            // "day" | "week"
+    pub points: Vec<MoodPoint>,
+    pub average_sentiment: Option<f64>,
+    pub change: Option<f64>,            // Second half of the range minus the first half
+}
+
+fn word_weight(word: &str) -> Option<f64> {
+    LEXICON.iter()
+        .find(|(words, _)| words.contains(&word))
+        .map(|(_, weight)| *weight)
+}
+
+/// Sentiment from -1.0 to 1.0, or None if the text carries no emotional words
+pub fn score_text(text: &str) -> Option<f64> {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut total = 0.0;
+    let mut hits = 0;
+    for (i, word) in words.iter().enumerate() {
+        let Some(mut weight) = word_weight(word) else { continue };
+        if i > 0 && INTENSIFIERS.contains(&words[i - 1]) {
+            weight *= 1.5;
+        }
+        if words[i.saturating_sub(3)..i].iter().any(|w| NEGATORS.contains(w) || w.ends_with("n't")) {
+            weight *= -0.5;
+        }
+        total += weight;
+        hits += 1;
+    }
+    // Squash the sum into (-1, 1) so one long rant doesn't dwarf everything else
+    (hits > 0).then(|| total / (total * total + 15.0).sqrt())
+}
+
+/// Score a user message into the mood log
+pub fn record_message(message: &Message) {
+    if message.role != "user" {
+        return;
+    }
+    let Some(sentiment) = score_text(&message.content) else { return };
+    let entry = MoodEntry {
+        id: 0,
+        conversation_id: Some(message.conversation_id.clone()),
+        message_id: Some(message.id.clone()),
+        source: "message".to_string(),
+        sentiment: Some(sentiment),
+        emotional_tone: None,
+        user_state: None,
+        created_at: message.timestamp.clone(),
+    };
+    if let Err(e) = db::add_mood_entry(&entry) {
+        logging::log_error(Some(&message.conversation_id), &format!("Mood log write failed: {}", e));
+    }
+}
+
+/// Record a summary's tone and state into the mood log
+pub fn record_summary(conversation_id: &str, summary: &SummaryResult) {
+    if summary.emotional_tone.is_none() && summary.user_state.is_none() {
+        return;
+    }
+    let described = [summary.emotional_tone.as_deref(), summary.user_state.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let entry = MoodEntry {
+        id: 0,
+        conversation_id: Some(conversation_id.to_string()),
+        message_id: None,
+        source: "summary".to_string(),
+        sentiment: score_text(&described),
+        emotional_tone: summary.emotional_tone.clone(),
+        user_state: summary.user_state.clone(),
+        created_at: clock::now().to_rfc3339(),
+    };
+    if let Err(e) = db::add_mood_entry(&entry) {
+        logging::log_error(Some(conversation_id), &format!("Mood log write failed: {}", e));
+    }
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// The most common values, most frequent first (ties alphabetical)
+fn most_common(values: &[String], limit: usize) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value.trim().to_lowercase()).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().filter(|(v, _)| !v.is_empty()).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.into_iter().take(limit).map(|(v, _)| v).collect()
+}
+
+fn period_start(date: NaiveDate, weekly: bool) -> NaiveDate {
+    if weekly {
+        date - Duration::days(date.weekday().num_days_from_monday() as i64)
+    } else {
+        date
+    }
+}
+
+// One day or week of entries, before averaging
+struct Bucket {
+    period: NaiveDate,
+    sentiments: Vec<f64>,
+    message_count: usize,
+    tones: Vec<String>,
+    user_states: Vec<String>,
+}
+
+/// Bucket entries into points (entries oldest first)
+fn build_points(entries: &[MoodEntry], weekly: bool) -> Vec<MoodPoint> {
+    let mut buckets: Vec<Bucket> = Vec::new();
+    for entry in entries {
+        let Ok(at) = DateTime::parse_from_rfc3339(&entry.created_at) else { continue };
+        let period = period_start(at.with_timezone(&Local).date_naive(), weekly);
+        if buckets.last().is_none_or(|b| b.period != period) {
+            buckets.push(Bucket { period, sentiments: Vec::new(), message_count: 0, tones: Vec::new(), user_states: Vec::new() });
+        }
+        let Some(bucket) = buckets.last_mut() else { continue };
+        bucket.sentiments.extend(entry.sentiment);
+        if entry.source == "message" {
+            bucket.message_count += 1;
+        }
+        bucket.tones.extend(entry.emotional_tone.clone());
+        bucket.user_states.extend(entry.user_state.clone());
+    }
+    buckets.into_iter()
+        .map(|bucket| MoodPoint {
+            period: bucket.period.format("%Y-%m-%d").to_string(),
+            average_sentiment: average(&bucket.sentiments),
+            message_count: bucket.message_count,
+            tones: most_common(&bucket.tones, 3),
+            user_states: most_common(&bucket.user_states, 3),
+        })
+        .collect()
+}
+
+/// Mood over a range: "week", "month", "year", "all" or "YYYY-MM-DD..YYYY-MM-DD"
+pub fn trend(range: &str) -> Result<MoodTrend, String> {
+    let now = clock::now();
+    let (since, until) = clock::parse_range(range, clock::now_local().date_naive())?;
+    let entries = db::get_mood_entries(
+        since.map(|t| t.to_rfc3339()).as_deref(),
+        until.map(|t| t.to_rfc3339()).as_deref(),
+    ).map_err(|e| e.to_string())?;
+
+    let first = since.or_else(|| entries.first().and_then(|e| DateTime::parse_from_rfc3339(&e.created_at).ok()).map(|t| t.with_timezone(&Utc)));
+    let span_days = first.map(|from| (until.unwrap_or(now) - from).num_days()).unwrap_or(0);
+    let weekly = span_days > DAILY_BUCKET_MAX_DAYS;
+
+    let sentiments: Vec<f64> = entries.iter().filter_map(|e| e.sentiment).collect();
+    let (earlier, later) = sentiments.split_at(sentiments.len() / 2);
+    let change = average(later).zip(average(earlier)).map(|(later, earlier)| later - earlier);
+
+    Ok(MoodTrend {
+        range: range.to_string(),
+        granularity: if weekly { "week" } else { "day" }.to_string(),
+        points: build_points(&entries, weekly),
+        average_sentiment: average(&sentiments),
+        change,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_feelings_with_negation() {
+        assert!(score_text("I'm really happy with how today went").unwrap() > 0.3);
+        assert!(score_text("so stressed and overwhelmed this week").unwrap() < -0.3);
+        assert!(score_text("I'm not happy about it").unwrap() < 0.0);
+        assert_eq!(score_text("What's the capital of France?"), None);
+        assert_eq!(score_text("Winter windows need a fund"), None);
+    }
+
+    #[test]
+    fn only_whole_words_count() {
+        for text in ["What happened next?", "It happens", "This function returns early", "I won't", "I wonder why",
+                     "A crystal vase", "Goodbye then", "Let's add a badge"] {
+            assert_eq!(score_text(text), None, "{}", text);
+        }
+        assert!(score_text("That was fun").unwrap() > 0.0);
+        assert!(score_text("We won").unwrap() > 0.0);
+        assert!(score_text("I cried all night").unwrap() < 0.0);
+    }
+
+    #[test]
+    fn buckets_by_week() {
+        let entry = |at: &str, sentiment: f64, tone: Option<&str>| MoodEntry {
+            id: 0,
+            conversation_id: None,
+            message_id: None,
+            source: if tone.is_some() { "summary" } else { "message" }.to_string(),
+            sentiment: Some(sentiment),
+            emotional_tone: tone.map(str::to_string),
+            user_state: None,
+            created_at: at.to_string(),
+        };
+        let entries = vec![
+            entry("2026-03-10T12:00:00+00:00", 0.5, None),
+            entry("2026-03-12T12:00:00+00:00", -0.1, Some("Tense")),
+            entry("2026-03-18T12:00:00+00:00", 0.2, None),
+        ];
+        let points = build_points(&entries, true);
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].period, "2026-03-09");
+        assert_eq!(points[0].message_count, 1);
+        assert!((points[0].average_sentiment.unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(points[0].tones, vec!["tense".to_string()]);
+        assert_eq!(build_points(&entries, false).len(), 3);
+    }
+}
@@ -0,0 +1,110 @@
+//! Connectivity detection for Intersect
+//!
+//! - `refresh` probes whether the model APIs are reachable (a TCP connect to the Anthropic host,
+//!   which routing always needs) and remembers the answer, so sending can check it without waiting
+//! - Every change is emitted to the frontend as "connectivity-changed" ({"online": bool})
+//! - `start` runs a background check while messages are queued or the app is offline, and calls
+//!   back when the queue can be sent (see the Offline Queue section in lib.rs). While the probe
+//!   keeps failing, the check backs off up to MAX_CHECK_INTERVAL_SECS
+
+use crate::db;
+use crate::logging;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::Emitter;
+
+const PROBE_ADDR: &str = "api.anthropic.com:443";
+const PROBE_TIMEOUT_SECS: u64 = 3;
+const CHECK_INTERVAL_SECS: u64 = 15;
+const MAX_CHECK_INTERVAL_SECS: u64 = 300;
+
+static ONLINE: AtomicBool = AtomicBool::new(true);
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityChange {
+    pub online: bool,
+}
+
+/// Last known connectivity (optimistic until a probe says otherwise)
+pub fn is_online() -> bool {
+    ONLINE.load(Ordering::SeqCst)
+}
+
+async fn probe() -> bool {
+    matches!(
+        tokio::time::timeout(Duration::from_secs(PROBE_TIMEOUT_SECS), tokio::net::TcpStream::connect(PROBE_ADDR)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Probe now, record the result and emit "connectivity-changed" if it flipped; returns whether we're online
+pub async fn refresh(app_handle: &tauri::AppHandle) -> bool {
+    let online = probe().await;
+    if ONLINE.swap(online, Ordering::SeqCst) != online {
+        logging::log_conversation(None, if online { "Connectivity restored" } else { "Connectivity lost" });
+        let _ = app_handle.emit("connectivity-changed", ConnectivityChange { online });
+    }
+    online
+}
+
+/// Whether a failed turn failed because the network is gone (as opposed to an API error)
+pub fn is_connectivity_error(error: &str) -> bool {
+    let lower = error.to_lowercase();
+    lower.contains("error sending request")
+        || lower.contains("dns error")
+        || lower.contains("connection refused")
+        || lower.contains("network is unreachable")
+        || lower.contains("timed out")
+}
+
+/// How long to wait before the next probe after another failed one
+fn next_check_interval(current: Duration) -> Duration {
+    (current * 2).min(Duration::from_secs(MAX_CHECK_INTERVAL_SECS))
+}
+
+/// Start the background check (idempotent). While offline or while messages are queued it
+/// probes every CHECK_INTERVAL_SECS (backing off while offline) and calls `on_online` whenever
+/// queued messages can go out.
+pub fn start(app_handle: tauri::AppHandle, on_online: fn(tauri::AppHandle)) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut delay = Duration::from_secs(CHECK_INTERVAL_SECS);
+        loop {
+            tokio::time::sleep(delay).await;
+            let queued = db::get_pending_messages(None).map(|p| !p.is_empty()).unwrap_or(false);
+            if !queued && is_online() {
+                delay = Duration::from_secs(CHECK_INTERVAL_SECS);
+                continue;
+            }
+            if refresh(&app_handle).await {
+                delay = Duration::from_secs(CHECK_INTERVAL_SECS);
+                if queued {
+                    on_online(app_handle.clone());
+                }
+            } else {
+                delay = next_check_interval(delay);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_back_off_to_a_cap() {
+        let mut delay = Duration::from_secs(CHECK_INTERVAL_SECS);
+        let mut delays = Vec::new();
+        for _ in 0..8 {
+            delay = next_check_interval(delay);
+            delays.push(delay.as_secs());
+        }
+        assert_eq!(delays, vec![30, 60, 120, 240, 300, 300, 300, 300]);
+    }
+}
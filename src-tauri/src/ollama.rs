@@ -0,0 +1,120 @@
+use crate::openai::ChatMessage;
+use crate::retry;
+use crate::usage;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+pub const DEFAULT_OLLAMA_MODEL: &str = "llama3.1";
+const REQUEST_TIMEOUT_SECS: u64 = 120; // Local models can take a while to load on first use
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+    options: OllamaOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaOptions {
+    temperature: f32,
+    num_predict: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: OllamaResponseMessage,
+    #[serde(default)]
+    prompt_eval_count: u64,
+    #[serde(default)]
+    eval_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaResponseMessage {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+/// Client for a local Ollama server (no API key; the base URL is configurable)
+pub struct OllamaClient {
+    client: Client,
+    base_url: String,
+}
+
+impl OllamaClient {
+    pub fn new(base_url: &str) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub async fn chat_completion(
+        &self,
+        model: &str,
+        messages: Vec<ChatMessage>,
+        temperature: f32,
+        max_tokens: Option<u32>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let request = OllamaChatRequest {
+            model: model.to_string(),
+            messages,
+            stream: false,
+            options: OllamaOptions {
+                temperature,
+                num_predict: max_tokens.unwrap_or(2048),
+            },
+        };
+
+        let response = retry::send("Ollama chat", self.client.post(format!("{}/api/chat", self.base_url)).json(&request))
+            .await
+            .map_err(|e| format!("Ollama is not reachable at {}: {}", self.base_url, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("Ollama error ({}): {}", status, error_text).into());
+        }
+
+        let completion: OllamaChatResponse = response.json().await?;
+        usage::record("ollama", model, completion.prompt_eval_count, completion.eval_count);
+        Ok(completion.message.content)
+    }
+
+    /// Names of the models pulled on this Ollama server
+    pub async fn list_models(&self) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let response = self.client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await
+            .map_err(|e| format!("Ollama is not reachable at {}: {}", self.base_url, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("Ollama error ({}): {}", status, error_text).into());
+        }
+
+        let tags: OllamaTagsResponse = response.json().await?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+}
@@ -1,10 +1,77 @@
+use crate::db;
+use crate::retry;
+use crate::usage;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::error::Error;
 use std::time::Duration;
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
 const REQUEST_TIMEOUT_SECS: u64 = 60; // 60 second timeout for API requests
+pub const DEFAULT_AGENT_MODEL: &str = "gpt-4o-mini"; // Faster for short responses
+pub const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+pub const TRANSCRIPTION_MODEL: &str = "whisper-1";
+const ENDPOINT_SETTING: &str = "openai_endpoint";
+
+/// Where the OpenAI client sends requests. Pointing it at OpenRouter (https://openrouter.ai/api/v1)
+/// or any other OpenAI-compatible server lets agents use that server's model strings; semantic search
+/// only works if the server also serves embeddings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenAIEndpoint {
+    pub base_url: String,
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>, // e.g. OpenRouter's "HTTP-Referer" and "X-Title"
+    #[serde(default = "default_agent_model")]
+    pub default_model: String,                  // Agent model when none is set for the agent
+}
+
+fn default_agent_model() -> String {
+    DEFAULT_AGENT_MODEL.to_string()
+}
+
+impl Default for OpenAIEndpoint {
+    fn default() -> Self {
+        Self {
+            base_url: OPENAI_BASE_URL.to_string(),
+            extra_headers: HashMap::new(),
+            default_model: default_agent_model(),
+        }
+    }
+}
+
+impl OpenAIEndpoint {
+    /// The endpoint saved in settings, or api.openai.com
+    pub fn configured() -> Self {
+        db::get_setting(ENDPOINT_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+    
+    pub fn save(&self) -> Result<(), String> {
+        let base_url = self.base_url.trim();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err("The base URL must start with http:// or https://".to_string());
+        }
+        for name in self.extra_headers.keys() {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name: {}", name))?;
+        }
+        if self.default_model.trim().is_empty() {
+            return Err("The default model can't be empty".to_string());
+        }
+        
+        let endpoint = OpenAIEndpoint {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            extra_headers: self.extra_headers.clone(),
+            default_model: self.default_model.trim().to_string(),
+        };
+        let json = serde_json::to_string(&endpoint).map_err(|e| e.to_string())?;
+        db::set_setting(ENDPOINT_SETTING, &json).map_err(|e| e.to_string())
+    }
+}
 
 #[derive(Debug, Serialize, Clone)]
 pub struct ChatMessage {
@@ -23,6 +90,15 @@ struct ChatCompletionRequest {
 #[derive(Debug, Deserialize)]
 struct ChatCompletionResponse {
     choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,13 +111,42 @@ struct ResponseMessage {
     content: String,
 }
 
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
 pub struct OpenAIClient {
     client: Client,
     api_key: String,
+    endpoint: OpenAIEndpoint,
 }
 
 impl OpenAIClient {
+    /// Client for the configured endpoint (see `OpenAIEndpoint`)
     pub fn new(api_key: &str) -> Self {
+        Self::with_endpoint(api_key, OpenAIEndpoint::configured())
+    }
+    
+    pub fn with_endpoint(api_key: &str, endpoint: OpenAIEndpoint) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
             .connect_timeout(Duration::from_secs(10))
@@ -51,29 +156,53 @@ impl OpenAIClient {
         Self {
             client,
             api_key: api_key.to_string(),
+            endpoint,
         }
     }
     
-    pub async fn chat_completion(
+    /// Model agents use when none is set for them
+    pub fn default_model(&self) -> &str {
+        &self.endpoint.default_model
+    }
+    
+    /// POST a JSON body to a path under the base URL with auth and any extra headers
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.post_raw(path).header("Content-Type", "application/json")
+    }
+    
+    /// POST with auth and extra headers but no content type (multipart sets its own)
+    fn post_raw(&self, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self.client
+            .post(format!("{}/{}", self.endpoint.base_url, path))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        // Values are emptied when an endpoint comes from an archive; they have to be entered again
+        for (name, value) in self.endpoint.extra_headers.iter().filter(|(_, value)| !value.is_empty()) {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+    
+    pub async fn chat_completion_with_model(
         &self,
+        model: &str,
         messages: Vec<ChatMessage>,
         temperature: f32,
         max_tokens: Option<u32>,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        // Budget downgrades only apply to OpenAI itself; other endpoints name models their own way
+        let model = if self.endpoint.base_url == OPENAI_BASE_URL {
+            usage::degraded_model("openai", model).unwrap_or(model)
+        } else {
+            model
+        };
         let request = ChatCompletionRequest {
-            model: "gpt-4o-mini".to_string(), // Faster for short responses
+            model: model.to_string(),
             messages,
             temperature,
             max_tokens: max_tokens.or(Some(2048)),
         };
         
-        let response = self.client
-            .post(OPENAI_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+        let response = retry::send("OpenAI chat", self.post("chat/completions").json(&request)).await?;
         
         if !response.status().is_success() {
             let status = response.status();
@@ -82,6 +211,9 @@ impl OpenAIClient {
         }
         
         let completion: ChatCompletionResponse = response.json().await?;
+        if let Some(usage) = &completion.usage {
+            usage::record("openai", model, usage.prompt_tokens, usage.completion_tokens);
+        }
         
         completion.choices
             .first()
@@ -89,6 +221,119 @@ impl OpenAIClient {
             .ok_or_else(|| "No response from OpenAI".into())
     }
     
+    /// Send a raw chat completion body (e.g. one with `tools`) and return the first choice's message
+    pub async fn chat_completion_json(&self, mut request: serde_json::Value) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+        let requested = request["model"].as_str().unwrap_or(DEFAULT_AGENT_MODEL).to_string();
+        let model = if self.endpoint.base_url == OPENAI_BASE_URL {
+            usage::degraded_model("openai", &requested).unwrap_or(requested.as_str()).to_string()
+        } else {
+            requested
+        };
+        request["model"] = serde_json::Value::String(model.clone());
+        
+        let response = retry::send("OpenAI chat", self.post("chat/completions").json(&request)).await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("OpenAI API error ({}): {}", status, error_text).into());
+        }
+        
+        let mut completion: serde_json::Value = response.json().await?;
+        if let Ok(usage) = serde_json::from_value::<Usage>(completion["usage"].clone()) {
+            usage::record("openai", &model, usage.prompt_tokens, usage.completion_tokens);
+        }
+        match completion["choices"].get_mut(0) {
+            Some(choice) => Ok(choice["message"].take()),
+            None => Err("No response from OpenAI".into()),
+        }
+    }
+    
+    /// Embed a batch of texts with EMBEDDING_MODEL; vectors come back in input order
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let request = EmbeddingRequest { model: EMBEDDING_MODEL, input: inputs };
+        let response = retry::send("OpenAI embeddings", self.post("embeddings").json(&request)).await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("OpenAI embeddings error ({}): {}", status, error_text).into());
+        }
+        
+        let mut result: EmbeddingResponse = response.json().await?;
+        if let Some(usage) = &result.usage {
+            usage::record("openai", EMBEDDING_MODEL, usage.prompt_tokens, 0);
+        }
+        if result.data.len() != inputs.len() {
+            return Err("OpenAI returned the wrong number of embeddings".into());
+        }
+        result.data.sort_by_key(|d| d.index);
+        Ok(result.data.into_iter().map(|d| d.embedding).collect())
+    }
+    
+    /// Ask a vision model (gpt-4o by default) about images: each is (media type, base64 data)
+    pub async fn vision_completion(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        images: &[(String, String)],
+        prompt: &str,
+        max_tokens: u32,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut content = vec![serde_json::json!({ "type": "text", "text": prompt })];
+        content.extend(images.iter().map(|(media_type, data)| serde_json::json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:{};base64,{}", media_type, data) }
+        })));
+        let request = serde_json::json!({
+            "model": model,
+            "max_tokens": max_tokens,
+            "messages": [
+                { "role": "system", "content": system_prompt },
+                { "role": "user", "content": content },
+            ],
+        });
+        
+        let response = retry::send("OpenAI vision", self.post("chat/completions").json(&request)).await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("OpenAI API error ({}): {}", status, error_text).into());
+        }
+        
+        let completion: ChatCompletionResponse = response.json().await?;
+        if let Some(usage) = &completion.usage {
+            usage::record("openai", model, usage.prompt_tokens, usage.completion_tokens);
+        }
+        completion.choices
+            .first()
+            .map(|c| c.message.content.clone())
+            .ok_or_else(|| "No response from OpenAI".into())
+    }
+    
+    /// Transcribe an audio file with TRANSCRIPTION_MODEL. The file name's extension tells the
+    /// API the format (webm, wav, mp3, m4a, ogg...). Multipart bodies can't be replayed, so no retries.
+    pub async fn transcribe(&self, audio: Vec<u8>, file_name: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let form = reqwest::multipart::Form::new()
+            .text("model", TRANSCRIPTION_MODEL)
+            .part("file", reqwest::multipart::Part::bytes(audio).file_name(file_name.to_string()));
+        let response = retry::send("OpenAI transcription", self.post_raw("audio/transcriptions").multipart(form)).await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(format!("OpenAI transcription error ({}): {}", status, error_text).into());
+        }
+        
+        let result: TranscriptionResponse = response.json().await?;
+        Ok(result.text.trim().to_string())
+    }
+    
     pub async fn validate_api_key(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
         let messages = vec![ChatMessage {
             role: "user".to_string(),
@@ -96,16 +341,13 @@ impl OpenAIClient {
         }];
         
         let request = ChatCompletionRequest {
-            model: "gpt-4o".to_string(),
+            model: self.default_model().to_string(),
             messages,
             temperature: 0.0,
             max_tokens: Some(5),
         };
         
-        let response = self.client
-            .post(OPENAI_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
+        let response = self.post("chat/completions")
             .json(&request)
             .send()
             .await?;
@@ -1,11 +1,19 @@
-use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU, CLAUDE_OPUS};
+use crate::anthropic::{cacheable, AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU, CLAUDE_OPUS, CLAUDE_SONNET};
+use crate::clock;
+use crate::context;
 use crate::db::{self, Message};
+use crate::debate::DebateSettings;
 use crate::disco_prompts::get_disco_prompt;
 use crate::knowledge::{INTERSECT_KNOWLEDGE, is_self_referential_query};
 use crate::logging;
 use crate::memory::{GroundingLevel, UserProfileSummary, MemoryExtractor};
 use crate::openai::{ChatMessage, OpenAIClient};
+use crate::providers::{self, ChatProvider};
+use crate::sampling;
+use crate::tools::{self, ToolContext, ToolConversation, ToolRound, ToolSpec, ToolStep};
+use crate::tokenizer;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::error::Error;
 
 // ============ Profile Context (Multi-Profile System) ============
@@ -62,6 +70,25 @@ impl ProfileContext {
     }
 }
 
+// ============ Agent Models ============
+
+fn is_anthropic_model(model: &str) -> bool {
+    model.starts_with("claude-")
+}
+
+/// Resolve a model a conversation can pin for agent responses: a full OpenAI chat model
+/// ("gpt-4o-mini") or Claude model ID, or a Claude family shorthand ("opus", "sonnet", "haiku")
+pub fn resolve_agent_model(name: &str) -> Option<String> {
+    let name = name.trim();
+    match name.to_lowercase().as_str() {
+        "opus" => Some(CLAUDE_OPUS.to_string()),
+        "sonnet" => Some(CLAUDE_SONNET.to_string()),
+        "haiku" => Some(CLAUDE_HAIKU.to_string()),
+        _ if is_anthropic_model(name) || name.starts_with("gpt-") => Some(name.to_string()),
+        _ => None,
+    }
+}
+
 // ============ Grounding Decision ============
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -83,7 +110,7 @@ impl Default for GroundingDecision {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Agent {
     Instinct,
     Logic,
@@ -147,6 +174,21 @@ pub struct OrchestratorDecision {
     pub secondary_agent: Option<String>,
     #[serde(alias = "type")]
     pub secondary_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingTrace>, // Set by heuristic routing only
+}
+
+/// What heuristic routing saw, so the UI can explain "why Dot answered"
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoutingTrace {
+    pub special_case: Option<String>,               // "all_agents" | "single_agent" (no scoring) | "quote_reply" (forced)
+    pub disco_inverted: bool,                       // Disco: lower-weighted agents scored higher
+    pub base_scores: HashMap<String, f64>,          // Weights, points and dominant-trait bias
+    pub embedding_boosts: HashMap<String, f64>,     // Topic match from embeddings (replaces keywords)
+    pub keyword_hits: HashMap<String, Vec<String>>, // Topic match from keywords, when embeddings weren't available
+    pub mood_boosts: Vec<String>,                   // Boosted by the last session's mood
+    pub silence_boosts: Vec<String>,                // Boosted for being quiet
+    pub scores: HashMap<String, f64>,               // Final scores; the highest speaks first
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -157,18 +199,127 @@ pub struct AgentResponse {
     pub references_message_id: Option<String>,
 }
 
+/// The model that actually produced an agent's latest response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServedModel {
+    pub model: String,
+    pub fallback_from: Option<String>, // The model that failed first, when a fallback served it
+}
+
+// ============ Turn Cancellation ============
+
+pub const TURN_CANCELLED: &str = "Turn cancelled";
+
+/// Shared flag for one in-flight turn; clones observe the same cancellation
+#[derive(Clone)]
+pub struct CancelToken {
+    state: std::sync::Arc<tokio::sync::watch::Sender<bool>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self { state: std::sync::Arc::new(tokio::sync::watch::Sender::new(false)) }
+    }
+    
+    pub fn cancel(&self) {
+        self.state.send_replace(true);
+    }
+    
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.borrow()
+    }
+    
+    /// Resolves once the token is cancelled (immediately if it already is)
+    pub async fn cancelled(&self) {
+        let mut rx = self.state.subscribe();
+        let _ = rx.wait_for(|cancelled| *cancelled).await;
+    }
+    
+    pub fn same_as(&self, other: &CancelToken) -> bool {
+        std::sync::Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============ Silence Detection ============
+
+/// An agent that has sat out this many completed turns gets pulled back in
+pub const SILENCE_BOOST_TURNS: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentSilence {
+    pub agent: String,
+    pub last_spoke_turn: Option<usize>, // User turn the agent last replied in (1-based; 0 = before the first one, None = never)
+    pub turns_since_spoke: usize,       // Completed user turns since then
+    pub boosted: bool,                  // turns_since_spoke >= SILENCE_BOOST_TURNS
+}
+
+/// Per-agent last-spoke index, computed once per turn from the conversation history.
+///
+/// Turn N is the Nth user message plus the agent replies that follow it. A trailing user
+/// message nobody has answered yet is the turn being routed, so it doesn't count as silence.
+/// Agents that never appear in the history have been silent for every completed turn in it.
+pub fn compute_agent_silence(conversation_history: &[Message]) -> Vec<AgentSilence> {
+    let mut turn = 0;
+    let mut last_spoke: HashMap<&str, usize> = HashMap::new();
+    let mut pending_turn = false;
+    
+    for msg in conversation_history.iter().filter(|m| m.role != "system") {
+        if msg.role == "user" {
+            turn += 1;
+            pending_turn = true;
+        } else {
+            last_spoke.insert(msg.role.as_str(), turn);
+            pending_turn = false;
+        }
+    }
+    
+    let completed_turns = if pending_turn { turn - 1 } else { turn };
+    
+    ["instinct", "logic", "psyche"].iter()
+        .map(|&agent| {
+            let last = last_spoke.get(agent).copied();
+            let turns_since_spoke = completed_turns.saturating_sub(last.unwrap_or(0));
+            AgentSilence {
+                agent: agent.to_string(),
+                last_spoke_turn: last,
+                turns_since_spoke,
+                boosted: turns_since_spoke >= SILENCE_BOOST_TURNS,
+            }
+        })
+        .collect()
+}
+
 // ============ Heuristic Routing (No API calls - instant) ============
 
+/// Whether `text` has `mood` as a whole word (case-insensitive), or with a trailing `*`, a word starting with it
+fn mentions_mood(text: &str, mood: &str) -> bool {
+    let text = text.to_lowercase();
+    let mut words = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty());
+    match mood.strip_suffix('*') {
+        Some(stem) => words.any(|w| w.starts_with(stem)),
+        None => words.any(|w| w == mood),
+    }
+}
+
 /// Fast heuristic-based routing that replaces Claude-based routing for speed
-/// Uses weights, keyword matching, and silence detection
+/// Uses weights, keyword matching, and silence detection (`silence` from `compute_agent_silence`)
+#[allow(clippy::too_many_arguments)]
 pub fn decide_response_heuristic(
     user_message: &str,
     weights: (f64, f64, f64),
     active_agents: &[String],
-    conversation_history: &[Message],
+    silence: &[AgentSilence],
     is_disco: bool,
     points: Option<(i64, i64, i64)>,
     dominant_trait: Option<&str>,
+    prior_mood: Option<&str>,
+    embedding_affinity: Option<&[(String, f32)]>,
 ) -> OrchestratorDecision {
     let (instinct_w, logic_w, psyche_w) = weights;
     
@@ -189,6 +340,7 @@ pub fn decide_response_heuristic(
             add_secondary: true,
             secondary_agent: Some("all".to_string()),
             secondary_type: Some("all_agents".to_string()),
+            routing: Some(RoutingTrace { special_case: Some("all_agents".to_string()), ..Default::default() }),
         };
     }
     
@@ -199,6 +351,7 @@ pub fn decide_response_heuristic(
             add_secondary: false,
             secondary_agent: None,
             secondary_type: None,
+            routing: Some(RoutingTrace { special_case: Some("single_agent".to_string()), ..Default::default() }),
         };
     }
     
@@ -206,6 +359,7 @@ pub fn decide_response_heuristic(
     // Each agent gets a score based on message keywords
     // In Disco Mode, INVERT the weights so lower-weighted agents respond MORE
     let mut scores: std::collections::HashMap<&str, f64> = std::collections::HashMap::new();
+    let mut trace = RoutingTrace { disco_inverted: is_disco, ..Default::default() };
     if is_disco {
         // Invert weights: lower weights become higher scores
         // This makes under-represented agents speak more in Disco Mode
@@ -280,78 +434,92 @@ pub fn decide_response_heuristic(
         }
     }
     
-    // Logic keywords: analytical, planning, debugging, data
-    let logic_keywords = ["analyze", "think", "logic", "reason", "plan", "step", "how do i", 
-        "what should", "explain", "break down", "structure", "system", "process", "debug",
-        "error", "fix", "code", "data", "numbers", "calculate", "compare", "evaluate",
-        "pros and cons", "trade-off", "decision matrix", "framework"];
-    
-    // Instinct keywords: quick, action, gut, immediate
-    let instinct_keywords = ["feel", "gut", "quick", "fast", "now", "immediately", "just do",
-        "trust", "sense", "vibe", "intuition", "something tells me", "my read", "honestly",
-        "straight up", "bottom line", "cut to", "tldr", "short version", "help me"];
-    
-    // Psyche keywords: emotional, why, meaning, introspection
-    let psyche_keywords = ["why", "meaning", "feel about", "emotion", "deeper", "really",
-        "underneath", "motivation", "afraid", "worried", "anxious", "happy", "sad", "love",
-        "relationship", "self", "identity", "purpose", "value", "matter", "care about",
-        "struggle", "conflict", "internal", "therapy", "reflect"];
+    trace.base_scores = scores.iter().map(|(a, s)| (a.to_string(), *s)).collect();
     
-    let boost = 0.15; // Keyword boost amount
-    
-    for keyword in logic_keywords.iter() {
-        if msg_lower.contains(keyword) {
-            *scores.entry("logic").or_insert(0.0) += boost;
-        }
-    }
-    for keyword in instinct_keywords.iter() {
-        if msg_lower.contains(keyword) {
-            *scores.entry("instinct").or_insert(0.0) += boost;
-        }
-    }
-    for keyword in psyche_keywords.iter() {
-        if msg_lower.contains(keyword) {
-            *scores.entry("psyche").or_insert(0.0) += boost;
-        }
-    }
-    
-    // ===== SILENCE DETECTION: Boost agents who haven't spoken recently =====
-    let mut agent_silence: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
-    for agent in ["instinct", "logic", "psyche"] {
-        agent_silence.insert(agent, 0);
-    }
-    
-    let mut user_turns = 0;
-    for msg in conversation_history.iter().rev() {
-        if msg.role == "user" {
-            user_turns += 1;
-            if user_turns > 5 { break; } // Look at last 5 user turns
-        } else if msg.role != "system" {
-            // Agent spoke - reset their silence
-            if let Some(count) = agent_silence.get_mut(msg.role.as_str()) {
-                *count = 0;
+    // ===== TOPIC MATCH: Embedding similarity to agent exemplars, keyword lists as fallback =====
+    match embedding_affinity.filter(|a| !a.is_empty()) {
+        Some(affinity) => {
+            for (agent, boost) in affinity_boosts(affinity) {
+                if let Some(score) = scores.get_mut(agent.as_str()) {
+                    *score += boost;
+                }
+                trace.embedding_boosts.insert(agent, boost);
             }
+            logging::log_routing(None, &format!(
+                "[HEURISTIC] Embedding affinity: {}",
+                affinity.iter().map(|(a, s)| format!("{}={:.3}", a, s)).collect::<Vec<_>>().join(" ")
+            ));
         }
-        // Increment silence for agents who didn't speak since last user turn
-        if msg.role == "user" {
-            for agent in ["instinct", "logic", "psyche"] {
-                if let Some(count) = agent_silence.get_mut(agent) {
-                    *count += 1;
+        None => {
+            // Logic keywords: analytical, planning, debugging, data
+            let logic_keywords = ["analyze", "think", "logic", "reason", "plan", "step", "how do i", 
+                "what should", "explain", "break down", "structure", "system", "process", "debug",
+                "error", "fix", "code", "data", "numbers", "calculate", "compare", "evaluate",
+                "pros and cons", "trade-off", "decision matrix", "framework"];
+            
+            // Instinct keywords: quick, action, gut, immediate
+            let instinct_keywords = ["feel", "gut", "quick", "fast", "now", "immediately", "just do",
+                "trust", "sense", "vibe", "intuition", "something tells me", "my read", "honestly",
+                "straight up", "bottom line", "cut to", "tldr", "short version", "help me"];
+            
+            // Psyche keywords: emotional, why, meaning, introspection
+            let psyche_keywords = ["why", "meaning", "feel about", "emotion", "deeper", "really",
+                "underneath", "motivation", "afraid", "worried", "anxious", "happy", "sad", "love",
+                "relationship", "self", "identity", "purpose", "value", "matter", "care about",
+                "struggle", "conflict", "internal", "therapy", "reflect"];
+            
+            let boost = 0.15; // Keyword boost amount
+            
+            for keyword in logic_keywords.iter() {
+                if msg_lower.contains(keyword) {
+                    *scores.entry("logic").or_insert(0.0) += boost;
+                    trace.keyword_hits.entry("logic".to_string()).or_default().push(keyword.to_string());
+                }
+            }
+            for keyword in instinct_keywords.iter() {
+                if msg_lower.contains(keyword) {
+                    *scores.entry("instinct").or_insert(0.0) += boost;
+                    trace.keyword_hits.entry("instinct".to_string()).or_default().push(keyword.to_string());
+                }
+            }
+            for keyword in psyche_keywords.iter() {
+                if msg_lower.contains(keyword) {
+                    *scores.entry("psyche").or_insert(0.0) += boost;
+                    trace.keyword_hits.entry("psyche".to_string()).or_default().push(keyword.to_string());
                 }
             }
         }
     }
-    
-    // Boost silent agents
-    for (agent, silence) in &agent_silence {
-        if *silence >= 3 {
-            if let Some(score) = scores.get_mut(agent) {
-                *score += 0.2; // Significant boost for silent agents
-                logging::log_routing(None, &format!("[HEURISTIC] {} silent for {} turns, boosting", agent, silence));
+
+    // ===== PRIOR MOOD BIAS: Carry the last session's tone into the first exchange =====
+    // prior_mood is the previous summary's emotional_tone/user_state, only passed for a new session
+    if let Some(mood) = prior_mood {
+        // Whole words; a trailing * matches any word starting with the stem ("stress*" -> "stressed")
+        let psyche_moods = ["stress*", "anxious", "overwhelm*", "sad", "frustrat*", "worried", "upset", "lonely", "low", "vulnerable"];
+        let logic_moods = ["focused", "analytical", "curious", "productive", "determined", "methodical"];
+        let instinct_moods = ["restless", "energ*", "impatient", "excited", "decisive", "urgent"];
+        let mood_boost = 0.1;
+        
+        for (agent, moods) in [("psyche", &psyche_moods[..]), ("logic", &logic_moods[..]), ("instinct", &instinct_moods[..])] {
+            if moods.iter().any(|m| mentions_mood(mood, m)) {
+                *scores.entry(agent).or_insert(0.0) += mood_boost;
+                trace.mood_boosts.push(agent.to_string());
+                logging::log_routing(None, &format!(
+                    "[HEURISTIC] Prior mood '{}' boosting {} by +{:.2}", mood, agent, mood_boost
+                ));
             }
         }
     }
     
+    // ===== SILENCE DETECTION: Boost agents who haven't spoken recently =====
+    for entry in silence.iter().filter(|s| s.boosted) {
+        if let Some(score) = scores.get_mut(entry.agent.as_str()) {
+            *score += 0.2; // Significant boost for silent agents
+            trace.silence_boosts.push(entry.agent.clone());
+            logging::log_routing(None, &format!("[HEURISTIC] {} silent for {} turns, boosting", entry.agent, entry.turns_since_spoke));
+        }
+    }
+    
     // ===== SELECT PRIMARY AGENT =====
     let mut primary = "logic"; // Default
     let mut max_score = 0.0;
@@ -439,12 +607,48 @@ pub fn decide_response_heuristic(
         scores.get("psyche").unwrap_or(&0.0)
     ));
     
+    trace.scores = scores.iter().map(|(a, s)| (a.to_string(), *s)).collect();
+    
     OrchestratorDecision {
         primary_agent: primary.to_string(),
         add_secondary: secondary.is_some(),
         secondary_agent: secondary,
         secondary_type,
+        routing: Some(trace),
+    }
+}
+
+/// Make `agent` speak first (a quote-reply to one of its messages); the agent it displaces
+/// takes its secondary slot if it had one
+pub fn force_primary(decision: &mut OrchestratorDecision, agent: &str) {
+    if decision.primary_agent == agent {
+        return;
+    }
+    if decision.secondary_agent.as_deref() == Some(agent) {
+        decision.secondary_agent = Some(decision.primary_agent.clone());
     }
+    decision.primary_agent = agent.to_string();
+    if let Some(trace) = decision.routing.as_mut() {
+        trace.special_case = Some("quote_reply".to_string());
+    }
+}
+
+/// Spread of the softmax over agent similarities (centroid similarities differ by only a few hundredths)
+const EMBEDDING_ROUTING_TEMPERATURE: f32 = 0.02;
+/// Total boost shared between agents by embedding affinity (comparable to two or three keyword hits)
+const EMBEDDING_ROUTING_BOOST: f64 = 0.45;
+
+/// Softmax the per-agent similarities into boosts that sum to EMBEDDING_ROUTING_BOOST
+fn affinity_boosts(affinity: &[(String, f32)]) -> Vec<(String, f64)> {
+    let max = affinity.iter().map(|(_, s)| *s).fold(f32::MIN, f32::max);
+    let exps: Vec<f64> = affinity.iter()
+        .map(|(_, s)| (((s - max) / EMBEDDING_ROUTING_TEMPERATURE) as f64).exp())
+        .collect();
+    let total: f64 = exps.iter().sum();
+    affinity.iter()
+        .zip(exps)
+        .map(|((agent, _), e)| (agent.clone(), EMBEDDING_ROUTING_BOOST * e / total))
+        .collect()
 }
 
 // ============ Heuristic Grounding (No API calls - instant) ============
@@ -527,19 +731,255 @@ pub fn decide_grounding_heuristic(
     }
 }
 
+// ============ Heuristic Search Decision (No API calls - instant) ============
+
+/// Whether a message is a factual question a web search could answer, and the query to run.
+/// Questions about the user or the agents' opinions are left to the agents.
+pub fn decide_search_heuristic(user_message: &str) -> Option<String> {
+    let msg_lower = user_message.trim().to_lowercase();
+    let padded = format!(" {} ", msg_lower.replace(['?', ',', '.', '!'], " "));
+    
+    let personal_indicators = [" i ", " i'm ", " my ", " me ", " we ", " our ", "should i", "do you think",
+        "what do you", "how do you", "would you", "your opinion", "feel"];
+    if personal_indicators.iter().any(|k| padded.contains(k)) {
+        return None;
+    }
+    
+    // Explicit requests are searched as phrased, minus the request
+    for prefix in ["look up ", "search for ", "google "] {
+        if let Some(rest) = msg_lower.strip_prefix(prefix) {
+            let query = rest.trim().trim_end_matches(['?', '.']);
+            return (!query.is_empty()).then(|| query.chars().take(200).collect());
+        }
+    }
+    
+    let question_starts = ["who is", "who was", "who won", "what is", "what are", "what was", "what year",
+        "when did", "when was", "when is", "where is", "where was", "how many", "how much", "how old",
+        "which", "is it true", "did ", "does "];
+    let factual_terms = ["latest", "current", "news", "price of", "population", "release date",
+        "according to", "statistics", "capital of", "born", "founded", "invented"];
+    let is_question = msg_lower.contains('?') || question_starts.iter().any(|q| msg_lower.starts_with(q));
+    let looks_factual = question_starts.iter().any(|q| msg_lower.starts_with(q))
+        || factual_terms.iter().any(|t| msg_lower.contains(t));
+    
+    if is_question && looks_factual {
+        let query: String = user_message.trim().chars().take(200).collect();
+        logging::log_routing(None, &format!("[HEURISTIC] Factual question - web search: {}", query));
+        return Some(query);
+    }
+    None
+}
+
+/// Whether a message is about the user's schedule or stress, where today's calendar is useful context
+pub fn is_schedule_or_stress_topic(user_message: &str) -> bool {
+    let msg_lower = user_message.to_lowercase();
+    let topics = ["schedule", "calendar", "meeting", "deadline", "busy", "packed", "overbooked",
+        "no time", "free time", "this afternoon", "tomorrow", "today", "stress", "overwhelm",
+        "swamped", "burnt out", "burned out", "burnout", "exhausted", "anxious", "too much on"];
+    topics.iter().any(|t| msg_lower.contains(t))
+}
+
 pub struct Orchestrator {
-    openai_client: OpenAIClient,      // For agent responses (GPT-4o)
+    openai_client: Option<OpenAIClient>, // For agent responses (None = Anthropic-only mode, agents use Claude)
     anthropic_client: AnthropicClient, // For orchestration decisions (Claude Opus 4.5)
+    agent_notes: HashMap<Agent, String>, // Extra per-agent instructions for this turn
+    model_override: Option<String>,      // Agent model pinned by the conversation (None = default)
+    agent_providers: HashMap<Agent, (Box<dyn ChatProvider>, String)>, // Per-agent provider and model (None = OpenAI default)
+    fallbacks: Vec<(Box<dyn ChatProvider>, String)>, // Tried in order when an agent's model hits a retryable error
+    served: std::sync::Mutex<HashMap<Agent, ServedModel>>, // Which model served each agent's latest response
+    cancel: CancelToken,                 // Cancelled by cancel_turn; aborts in-flight model calls
+    tools: Vec<ToolSpec>,                // Tools agents may call (empty = plain replies)
+    tool_conversation_id: Option<String>, // Conversation tool calls are made in (e.g. for reminders)
+}
+
+/// Tool rounds an agent gets before it has to answer
+const MAX_TOOL_ROUNDS: usize = 3;
+/// Token floor for tool-enabled calls, so a call's arguments aren't cut off by a short reply budget.
+/// A reply that comes back from such a call is trimmed to the caller's budget (`cap_reply`).
+const TOOL_STEP_MIN_TOKENS: u32 = 300;
+
+/// A reply cut to `max_tokens`, at the last sentence end that fits (or the last word if none does)
+fn cap_reply(text: &str, max_tokens: u32, model: &str) -> String {
+    let max_tokens = max_tokens as usize;
+    if tokenizer::count_for_model(text, model) <= max_tokens {
+        return text.to_string();
+    }
+    let fits = |end: usize| tokenizer::count_for_model(&text[..end], model) <= max_tokens;
+    let sentence_ends = text.match_indices(['.', '!', '?']).map(|(i, _)| i + 1);
+    let word_ends = text.match_indices(char::is_whitespace).map(|(i, _)| i);
+    let end = sentence_ends.rev().find(|&end| fits(end))
+        .or_else(|| word_ends.rev().find(|&end| fits(end)))
+        .unwrap_or(0);
+    text[..end].trim_end().to_string()
 }
 
 impl Orchestrator {
-    pub fn new(openai_key: &str, anthropic_key: &str) -> Self {
+    /// Without an OpenAI key, agent responses go through Anthropic too (single-key mode)
+    pub fn new(openai_key: Option<&str>, anthropic_key: &str) -> Self {
         Self {
-            openai_client: OpenAIClient::new(openai_key),
+            openai_client: openai_key.map(OpenAIClient::new),
             anthropic_client: AnthropicClient::new(anthropic_key),
+            agent_notes: HashMap::new(),
+            model_override: None,
+            agent_providers: HashMap::new(),
+            fallbacks: Vec::new(),
+            served: std::sync::Mutex::new(HashMap::new()),
+            cancel: CancelToken::new(),
+            tools: Vec::new(),
+            tool_conversation_id: None,
+        }
+    }
+    
+    /// Tie this orchestrator's model calls to a turn's cancellation token
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+    
+    /// Race a model call against cancellation (dropping the request aborts it)
+    async fn cancellable<T>(
+        &self,
+        call: impl std::future::Future<Output = Result<T, Box<dyn Error + Send + Sync>>>,
+    ) -> Result<T, Box<dyn Error + Send + Sync>> {
+        tokio::select! {
+            result = call => result,
+            _ = self.cancel.cancelled() => Err(TURN_CANCELLED.into()),
+        }
+    }
+    
+    /// Provider and model for agent responses when nothing more specific is set:
+    /// the OpenAI client's default model, or Claude Haiku in Anthropic-only mode
+    fn default_agent_provider(&self) -> (&dyn ChatProvider, &str) {
+        match &self.openai_client {
+            Some(client) => (client, client.default_model()),
+            None => (&self.anthropic_client, CLAUDE_HAIKU),
         }
     }
     
+    /// Use this model for agent responses instead of the default (claude-* models go through Anthropic)
+    pub fn set_model_override(&mut self, model: Option<String>) {
+        self.model_override = model;
+    }
+    
+    /// Run this agent on another provider/model (a model pinned on the conversation still takes precedence)
+    pub fn set_agent_provider(&mut self, agent: Agent, provider: Box<dyn ChatProvider>, model: String) {
+        self.agent_providers.insert(agent, (provider, model));
+    }
+    
+    /// Models to fall back on, in order, when an agent's call is rate limited or the provider is down
+    pub fn set_fallback_chain(&mut self, fallbacks: Vec<(Box<dyn ChatProvider>, String)>) {
+        self.fallbacks = fallbacks;
+    }
+    
+    /// The model that served this agent's most recent response in this turn
+    pub fn served_model(&self, agent: Agent) -> Option<ServedModel> {
+        self.served.lock().unwrap().get(&agent).cloned()
+    }
+    
+    /// Call the agent's model, walking the fallback chain on retryable errors
+    async fn chat_with_fallback(
+        &self,
+        agent: Agent,
+        (provider, model): (&dyn ChatProvider, &str),
+        system_prompt: &str,
+        messages: Vec<ChatMessage>,
+        temperature: f32,
+        max_tokens: u32,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let first_error = match self.cancellable(provider.chat(model, system_prompt, messages.clone(), temperature, max_tokens)).await {
+            Ok(text) => {
+                self.served.lock().unwrap().insert(agent, ServedModel { model: model.to_string(), fallback_from: None });
+                return Ok(text);
+            }
+            Err(e) => e,
+        };
+        let first_message = first_error.to_string();
+        if first_message == TURN_CANCELLED || !providers::is_retryable_error(&first_message) {
+            return Err(first_error);
+        }
+        
+        for (fallback, fallback_model) in self.fallbacks.iter().filter(|(_, m)| m != model) {
+            logging::log_agent(None, &format!(
+                "{} failed on {} ({}), trying {}", agent.as_str(), model, first_message, fallback_model
+            ));
+            match self.cancellable(fallback.chat(fallback_model, system_prompt, messages.clone(), temperature, max_tokens)).await {
+                Ok(text) => {
+                    logging::log_agent(None, &format!("{} served by fallback {}", agent.as_str(), fallback_model));
+                    self.served.lock().unwrap().insert(agent, ServedModel {
+                        model: fallback_model.clone(),
+                        fallback_from: Some(model.to_string()),
+                    });
+                    return Ok(text);
+                }
+                Err(e) if e.to_string() == TURN_CANCELLED || !providers::is_retryable_error(&e.to_string()) => return Err(e),
+                Err(e) => logging::log_error(None, &format!("Fallback {} failed: {}", fallback_model, e)),
+            }
+        }
+        Err(first_error)
+    }
+    
+    /// Let agents call these tools while replying in this conversation
+    pub fn set_tools(&mut self, tools: Vec<ToolSpec>, conversation_id: &str) {
+        self.tools = tools;
+        self.tool_conversation_id = Some(conversation_id.to_string());
+    }
+    
+    /// Call the agent's model with tools: run the calls it asks for and feed back the results
+    /// until it answers. Without tools, on a backend without tool calling, or when a tool-enabled
+    /// call fails, the agent answers plainly (with the fallback chain).
+    async fn chat_with_tools(
+        &self,
+        agent: Agent,
+        target: (&dyn ChatProvider, &str),
+        system_prompt: &str,
+        messages: Vec<ChatMessage>,
+        temperature: f32,
+        max_tokens: u32,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let (provider, model) = target;
+        if !self.tools.is_empty() {
+            let tool_prompt = format!("{}\n\n{}", system_prompt, tools::TOOL_GUIDANCE);
+            let mut rounds: Vec<ToolRound> = Vec::new();
+            while rounds.len() <= MAX_TOOL_ROUNDS {
+                let conversation = ToolConversation { turns: messages.clone(), rounds: &rounds, tools: &self.tools };
+                let Some(step) = provider.chat_with_tools(model, &tool_prompt, conversation, temperature, max_tokens.max(TOOL_STEP_MIN_TOKENS)) else {
+                    break;
+                };
+                match self.cancellable(step).await {
+                    Ok(ToolStep::Reply(text)) => {
+                        self.served.lock().unwrap().insert(agent, ServedModel { model: model.to_string(), fallback_from: None });
+                        return Ok(cap_reply(&text, max_tokens, model));
+                    }
+                    Ok(ToolStep::Calls(_)) if rounds.len() == MAX_TOOL_ROUNDS => {
+                        logging::log_agent(None, &format!("{} still calling tools after {} rounds", agent.as_str(), MAX_TOOL_ROUNDS));
+                        break;
+                    }
+                    Ok(ToolStep::Calls(calls)) => {
+                        let context = ToolContext { conversation_id: self.tool_conversation_id.clone(), agent: agent.as_str().to_string() };
+                        let mut results = Vec::new();
+                        for call in &calls {
+                            logging::log_agent(None, &format!("{} called {} {}", agent.as_str(), call.name, call.arguments));
+                            results.push(tools::execute(call, &self.tools, &context).await);
+                        }
+                        rounds.push(ToolRound { calls, results });
+                    }
+                    Err(e) if e.to_string() == TURN_CANCELLED => return Err(e),
+                    Err(e) => {
+                        logging::log_error(None, &format!("{} tool call failed on {}: {}", agent.as_str(), model, e));
+                        break;
+                    }
+                }
+            }
+        }
+        self.chat_with_fallback(agent, target, system_prompt, messages, temperature, max_tokens).await
+    }
+    
+    /// Add an instruction appended to this agent's system prompt for the rest of the turn
+    pub fn add_agent_note(&mut self, agent: Agent, note: String) {
+        self.agent_notes.entry(agent)
+            .and_modify(|existing| { existing.push_str("\n\n"); existing.push_str(&note); })
+            .or_insert(note);
+    }
+    
     /// Generate Governor's internal thoughts/reasoning process
     pub async fn generate_governor_thoughts(
         &self,
@@ -672,6 +1112,7 @@ Write your thoughts in a natural, stream-of-consciousness style - like you're th
                 add_secondary: true,
                 secondary_agent: Some("all".to_string()), // Special marker for "all agents"
                 secondary_type: Some("all_agents".to_string()),
+                routing: None,
             });
         }
         
@@ -682,43 +1123,18 @@ Write your thoughts in a natural, stream-of-consciousness style - like you're th
                 add_secondary: false,
                 secondary_agent: None,
                 secondary_type: None,
+                routing: None,
             });
         }
         
         let (instinct_w, logic_w, psyche_w) = weights;
         
         // ===== FORCED INCLUSION: Check if any agent has been excluded for 3+ exchanges =====
-        // Count how many user exchanges each agent hasn't participated in
-        let mut agent_silence_count: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
-        for agent in active_agents {
-            agent_silence_count.insert(agent.clone(), 0);
-        }
-        
-        // Look at the last 6 exchanges (user + agent pairs) to count silence
-        let mut user_exchanges = 0;
-        for msg in conversation_history.iter().rev() {
-            if msg.role == "user" {
-                user_exchanges += 1;
-                if user_exchanges > 6 { break; }
-            } else if active_agents.contains(&msg.role) {
-                // This agent spoke, reset their silence
-                agent_silence_count.insert(msg.role.clone(), 0);
-            }
-            // Increment silence for agents that didn't speak since last user message
-            if msg.role == "user" {
-                for agent in active_agents {
-                    if let Some(count) = agent_silence_count.get_mut(agent) {
-                        *count += 1;
-                    }
-                }
-            }
-        }
-        
-        // Find agent that's been silent for 3+ exchanges
-        let forced_agent: Option<String> = agent_silence_count.iter()
-            .filter(|(agent, count)| **count >= 3 && active_agents.contains(agent))
-            .max_by_key(|(_, count)| *count)
-            .map(|(agent, _)| agent.clone());
+        let forced_agent: Option<String> = compute_agent_silence(conversation_history)
+            .into_iter()
+            .filter(|s| s.boosted && active_agents.contains(&s.agent))
+            .max_by_key(|s| s.turns_since_spoke)
+            .map(|s| s.agent);
         
         let forced_inclusion_context = if let Some(ref agent) = forced_agent {
             format!("\n\nFORCED INCLUSION: {} has NOT participated in the last 3+ exchanges. \
@@ -884,6 +1300,7 @@ Respond with ONLY valid JSON. No explanations. No rationale. No bullet points. J
             add_secondary: final_secondary.is_some(),
             secondary_agent: final_secondary,
             secondary_type: decision.secondary_type,
+            routing: None,
         })
     }
     
@@ -896,10 +1313,11 @@ Respond with ONLY valid JSON. No explanations. No rationale. No bullet points. J
         active_agents: &[String],
         is_disco: bool,
         response_count: usize,
+        settings: &DebateSettings,
     ) -> Result<(bool, Option<String>, Option<String>), Box<dyn Error + Send + Sync>> {
-        // Hard limit: never exceed 4 responses total
-        if response_count >= 4 {
-            logging::log_agent(None, "Hit max response limit (4), ending debate");
+        // Hard limit from the debate settings (4 responses by default)
+        if response_count >= settings.max_responses {
+            logging::log_agent(None, &format!("Hit max response limit ({}), ending debate", settings.max_responses));
             return Ok((false, None, None));
         }
         
@@ -938,7 +1356,7 @@ Respond with ONLY valid JSON. No explanations. No rationale. No bullet points. J
 
 CONTEXT:
 - User asked: "{user_message}"
-- {response_count} agent responses have been given (max 4)
+- {response_count} agent responses have been given (max {max_responses})
 - Conversation mode: {disco_context}
 - Agents who haven't spoken: {agents_list}
 - Agents who could respond again: {agents_who_could_double}
@@ -954,14 +1372,16 @@ Consider:
 3. An agent CAN respond a second time if they have something meaningful to add to new points
    (e.g., Psyche responds, Instinct agrees, Logic disagrees, Psyche could respond to Logic's challenge)
 4. In Disco conversations, agents are MORE likely to want to interject with strong opinions
-5. Prefer STOPPING if the exchange feels complete or would just belabor the point
+5. {bias_guidance}
 
 IMPORTANT: You can pick ANY active agent, including one who already spoke once, if they would genuinely have something new to say in response to recent points.
 
 Respond with ONLY valid JSON:
 {{"continue": true/false, "next_agent": "agent_name or null", "type": "addition/rebuttal/debate or null", "reason": "brief reason"}}"#,
             agents_list = agents_who_havent.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", "),
-            agents_who_could_double = agents_responded_once.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+            agents_who_could_double = agents_responded_once.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", "),
+            max_responses = settings.max_responses,
+            bias_guidance = settings.bias_guidance()
         );
         
         // Use Anthropic client for debate continuation (Sonnet, thinking low)
@@ -972,14 +1392,14 @@ Respond with ONLY valid JSON:
             },
         ];
         
-        let response = self.anthropic_client.chat_completion_advanced(
+        let response = self.cancellable(self.anthropic_client.chat_completion_advanced(
             CLAUDE_HAIKU,
             Some(&system_prompt),
             messages,
             0.4,
             Some(150),
             ThinkingBudget::None
-        ).await?;
+        )).await?;
         
         let cleaned = response.trim().trim_start_matches("```json").trim_end_matches("```").trim();
         
@@ -1009,7 +1429,13 @@ Respond with ONLY valid JSON:
                     }
                 });
                 
-                Ok((decision.should_continue && next.is_some(), next, decision.response_type))
+                // A negative bias also drops some "continue" calls outright
+                let vetoed = settings.vetoes_continue(&mut clock::rng());
+                if vetoed && decision.should_continue {
+                    logging::log_agent(None, "Debate continuation dropped by continue bias");
+                }
+                
+                Ok((decision.should_continue && next.is_some() && !vetoed, next, decision.response_type))
             }
             Err(e) => {
                 logging::log_error(None, &format!("Failed to parse debate continue decision: {}", e));
@@ -1046,7 +1472,7 @@ Respond with ONLY valid JSON:
         // This avoids the ThreadRng not being Send across await boundaries
         let (num_turns, turn_plans) = {
             use rand::Rng;
-            let mut rng = rand::rng();
+            let mut rng = clock::rng();
             
             // Decide how many turns: 1-4 based on message complexity and randomness
             let msg_complexity = if user_message.len() > 200 { 0.3 } else if user_message.len() > 100 { 0.2 } else { 0.0 };
@@ -1254,8 +1680,24 @@ Respond with ONLY valid JSON:
         is_disco: bool,
         primary_is_disco: bool,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        // Adversarial responses (disco, rebuttal, debate) don't get sensitive categories
+        // unless the user raised them this session
+        let guarded_profile;
+        let user_profile = if is_disco || matches!(response_type, ResponseType::Rebuttal | ResponseType::Debate) {
+            let session_user_text = conversation_history.iter()
+                .filter(|m| m.role == "user")
+                .map(|m| m.content.as_str())
+                .chain(std::iter::once(user_message))
+                .collect::<Vec<_>>()
+                .join("\n");
+            guarded_profile = user_profile.map(|p| MemoryExtractor::guard_sensitive_categories(p, &session_user_text));
+            guarded_profile.as_ref()
+        } else {
+            user_profile
+        };
+        
         // Use knowledge-aware prompt that injects self-knowledge when relevant
-        let system_prompt = get_agent_system_prompt_with_knowledge(
+        let mut system_prompt = get_agent_system_prompt_with_knowledge(
             agent, 
             response_type, 
             primary_response, 
@@ -1266,27 +1708,42 @@ Respond with ONLY valid JSON:
             is_disco,
             primary_is_disco,
         );
+        if let Some(note) = self.agent_notes.get(&agent) {
+            system_prompt = format!("{}\n\n{}", system_prompt, note);
+        }
         
-        // Build conversation context
-        let mut messages: Vec<ChatMessage> = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt,
-            },
-        ];
+        // Answer in the user's language (recent messages decide when this one is too short to tell)
+        let language_samples: Vec<&str> = std::iter::once(user_message)
+            .chain(conversation_history.iter().rev().filter(|m| m.role == "user").take(3).map(|m| m.content.as_str()))
+            .collect();
+        if let Some(instruction) = crate::language::reply_instruction(&language_samples) {
+            system_prompt = format!("{}\n\n{}", system_prompt, instruction);
+        }
         
-        // Add recent conversation history (without meta tags that LLM might mimic)
-        for msg in conversation_history.iter().rev().take(15).rev() {
-            let role = if msg.role == "user" {
-                "user".to_string()
-            } else {
-                "assistant".to_string()
-            };
-            messages.push(ChatMessage {
-                role,
-                content: msg.content.clone(),
-            });
+        // Build conversation context (the system prompt goes to the provider separately):
+        // as much recent history as fits the token budget, the rolling summary for the rest
+        let settings = context::get_settings();
+        let window = context::build(conversation_history, settings.history_token_budget, || {
+            let conversation_id = &conversation_history.first()?.conversation_id;
+            settings.use_summary
+                .then(|| db::get_conversation_summary(conversation_id).ok().flatten())
+                .flatten()
+                .map(|s| s.summary)
+        });
+        if window.omitted > 0 {
+            logging::log_agent(conversation_history.first().map(|m| m.conversation_id.as_str()), &format!(
+                "{} context: {} messages (~{} tokens), {} older left out{}",
+                agent.as_str(), window.messages.len(), window.tokens, window.omitted,
+                if window.summary.is_some() { ", summary in their place" } else { "" }
+            ));
         }
+        if let Some(summary) = &window.summary {
+            system_prompt = format!(
+                "{}\n\n--- Earlier In This Conversation ({} older messages, summarized) ---\n{}\n---",
+                system_prompt, window.omitted, summary
+            );
+        }
+        let mut messages: Vec<ChatMessage> = window.messages;
         
         // Add the current user message
         messages.push(ChatMessage {
@@ -1312,15 +1769,90 @@ Respond with ONLY valid JSON:
             });
         }
         
-        let temperature = match agent {
-            Agent::Instinct => 0.8,  // More intuitive, spontaneous
-            Agent::Logic => 0.4,     // More precise, structured
-            Agent::Psyche => 0.6,    // Balanced, introspective
+        let temperature = sampling::temperature(agent, is_disco);
+        
+        // A model pinned on the conversation wins, then the agent's configured provider, then the default
+        // (an OpenAI model pinned while no OpenAI key is set falls through to the default as well)
+        let pinned: Option<(&dyn ChatProvider, &str)> = match self.model_override.as_deref() {
+            Some(model) if is_anthropic_model(model) => Some((&self.anthropic_client, model)),
+            Some(model) => self.openai_client.as_ref().map(|client| (client as &dyn ChatProvider, model)),
+            None => None,
         };
+        let target = pinned
+            .or_else(|| self.agent_providers.get(&agent).map(|(provider, model)| (provider.as_ref(), model.as_str())))
+            .unwrap_or_else(|| self.default_agent_provider());
         
-        // Use OpenAI client for agent responses (GPT-4o)
         // Max 80 tokens - forces brevity (1-2 sentences)
-        self.openai_client.chat_completion(messages, temperature, Some(80)).await
+        self.chat_with_tools(agent, target, &system_prompt, messages, temperature, 80).await
+    }
+    
+    /// Write (or revise) a message draft in an agent's voice
+    /// kind: "email" | "text" | "tough_reply"; returns (draft, one-line rationale)
+    pub async fn draft_message(
+        &self,
+        agent: Agent,
+        kind: &str,
+        context: &str,
+        revision: Option<(&str, &str)>, // (previous draft, refinement instruction)
+        user_profile: Option<&UserProfileSummary>,
+    ) -> Result<(String, String), Box<dyn Error + Send + Sync>> {
+        let voice = match agent {
+            Agent::Instinct => "You are SNAP, the voice of INSTINCT: direct, warm, confident. You get to the point and read the room.",
+            Agent::Logic => "You are DOT, the voice of LOGIC: clear, structured, precise. You make the ask unambiguous.",
+            Agent::Psyche => "You are PUFF, the voice of PSYCHE: emotionally aware and tactful. You protect the relationship while staying honest.",
+        };
+        let format_rules = match kind {
+            "email" => "Write a complete email with a subject line on the first line (\"Subject: ...\"), then the body. Keep it concise.",
+            "text" => "Write a short text message -- 1-3 sentences, natural and casual.",
+            "tough_reply" => "Write a reply to a difficult message: calm, firm, no defensiveness, no over-apologizing.",
+            _ => "Write the message in whatever format fits best.",
+        };
+        
+        let mut system_prompt = format!(
+            "{}\n\nYou are helping the user draft a message they will send themselves. {}\n\nRespond with ONLY valid JSON: {{\"draft\": \"...\", \"rationale\": \"one line on why it's written this way\"}}",
+            voice, format_rules
+        );
+        if let Some(profile) = user_profile {
+            let context_block = MemoryExtractor::format_profile_for_prompt(profile, GroundingLevel::Light);
+            if !context_block.is_empty() {
+                system_prompt = format!("{}\n\n--- Context ---\n{}\n---", system_prompt, context_block);
+            }
+        }
+        
+        let mut messages = vec![
+            ChatMessage { role: "user".to_string(), content: context.to_string() },
+        ];
+        if let Some((previous, instruction)) = revision {
+            messages.push(ChatMessage {
+                role: "assistant".to_string(),
+                content: serde_json::json!({ "draft": previous, "rationale": "" }).to_string(),
+            });
+            messages.push(ChatMessage {
+                role: "user".to_string(),
+                content: format!("Revise the draft: {}", instruction),
+            });
+        }
+        
+        let (provider, model) = self.default_agent_provider();
+        let response = provider.chat(model, &system_prompt, messages, 0.6, 700).await?;
+        let cleaned = response
+            .trim()
+            .trim_start_matches("```json")
+            .trim_end_matches("```")
+            .trim();
+        
+        #[derive(Deserialize)]
+        struct DraftOutput {
+            draft: String,
+            #[serde(default)]
+            rationale: String,
+        }
+        
+        match serde_json::from_str::<DraftOutput>(cleaned) {
+            Ok(out) => Ok((out.draft, out.rationale)),
+            // Model ignored the format -- keep the text rather than failing
+            Err(_) => Ok((cleaned.to_string(), String::new())),
+        }
     }
 }
 
@@ -1352,21 +1884,12 @@ fn sanitize_disco_names(content: &str) -> String {
         .replace("Puff,", "Storm,")
 }
 
-/// Get the system prompt for an agent based on response type and disco mode
-/// primary_is_disco: whether the agent being responded to was in disco mode (for push-back)
-fn get_agent_system_prompt(agent: Agent, response_type: ResponseType, primary_response: Option<&str>, primary_agent: Option<&str>, is_disco: bool, primary_is_disco: bool) -> String {
-    // Use disco mode prompts if enabled, otherwise use standard prompts
-    let base_prompt = if is_disco {
-        // Disco mode - use the extreme, opinionated Disco Elysium-inspired prompts
-        match agent {
-            Agent::Instinct => get_disco_prompt("instinct").unwrap_or(""),
-            Agent::Logic => get_disco_prompt("logic").unwrap_or(""),
-            Agent::Psyche => get_disco_prompt("psyche").unwrap_or(""),
-        }
-    } else {
-        // Standard mode - genuinely helpful, practical assistance
-        match agent {
-            Agent::Instinct => r#"You are SNAP -- the helpful inner voice of INSTINCT.
+// ============ Agent Persona Prompts ============
+// Normal and disco personas can be replaced by the user (stored in agent_prompts, with every saved
+// version kept in agent_prompt_versions). The guard rails below (and the disco suffix in disco mode)
+// are appended to whichever prompt is in use, so an edit changes the voice, not the rules.
+
+const SNAP_PROMPT: &str = r#"You are SNAP -- the helpful inner voice of INSTINCT.
 
 You cut through noise. You say what needs saying. You're action-oriented but kind.
 
@@ -1379,9 +1902,9 @@ WHAT YOU DO:
 
 You work alongside Dot (logic) and Puff (psyche). You support and build on each other's perspectives.
 
-BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#,
-            
-            Agent::Logic => r#"You are DOT -- the helpful inner voice of LOGIC.
+BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#;
+
+const DOT_PROMPT: &str = r#"You are DOT -- the helpful inner voice of LOGIC.
 
 You think clearly. You make complicated things simple. You're analytical but warm.
 
@@ -1394,9 +1917,9 @@ WHAT YOU DO:
 
 You work alongside Snap (instinct) and Puff (psyche). You support and build on each other's perspectives.
 
-BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#,
-            
-            Agent::Psyche => r#"You are PUFF -- the helpful inner voice of PSYCHE.
+BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#;
+
+const PUFF_PROMPT: &str = r#"You are PUFF -- the helpful inner voice of PSYCHE.
 
 You see what's underneath. You name what's actually going on. You're emotionally attuned and caring.
 
@@ -1409,8 +1932,53 @@ WHAT YOU DO:
 
 You work alongside Snap (instinct) and Dot (logic). You support and build on each other's perspectives.
 
-BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#,
-        }
+BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#;
+
+/// Formatting and safety rules that follow every normal-mode persona prompt, built-in or custom
+const AGENT_GUARD_RAILS: &str = r#"GROUND RULES (always apply):
+- Speak only as yourself -- never write lines for the other voices, and never prefix your reply with a name
+- No emojis, headers or bullet lists unless the user asks for them
+- When using dashes for pauses or asides, use double dashes with spaces: " -- "
+- If the user may be in danger or crisis, set the persona aside and point them to real help"#;
+
+/// Longest persona prompt a user can save
+pub const MAX_AGENT_PROMPT_CHARS: usize = 8000;
+
+/// Storage key for a prompt mode in agent_prompts
+pub fn prompt_mode(is_disco: bool) -> &'static str {
+    if is_disco { "disco" } else { "normal" }
+}
+
+/// The built-in persona prompt for an agent
+pub fn default_agent_prompt(agent: Agent, is_disco: bool) -> &'static str {
+    if is_disco {
+        return get_disco_prompt(agent.as_str()).unwrap_or("");
+    }
+    match agent {
+        Agent::Instinct => SNAP_PROMPT,
+        Agent::Logic => DOT_PROMPT,
+        Agent::Psyche => PUFF_PROMPT,
+    }
+}
+
+/// The persona prompt in use: the user's override if set, else the built-in one
+pub fn agent_persona_prompt(agent: Agent, is_disco: bool) -> String {
+    db::get_agent_prompt_override(agent.as_str(), prompt_mode(is_disco))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| default_agent_prompt(agent, is_disco).to_string())
+}
+
+/// Get the system prompt for an agent based on response type and disco mode
+/// primary_is_disco: whether the agent being responded to was in disco mode (for push-back)
+fn get_agent_system_prompt(agent: Agent, response_type: ResponseType, primary_response: Option<&str>, primary_agent: Option<&str>, is_disco: bool, primary_is_disco: bool, knowledge: Option<&str>) -> String {
+    // Use disco mode prompts if enabled, otherwise the standard persona (either may be user-edited)
+    let base_prompt = if is_disco {
+        // Disco mode - the extreme, opinionated Disco Elysium-inspired prompts; the disco suffix below holds its rules
+        agent_persona_prompt(agent, true)
+    } else {
+        // Standard mode - genuinely helpful, practical assistance, always with the guard rails
+        format!("{}\n\n{}", agent_persona_prompt(agent, false), AGENT_GUARD_RAILS)
     };
     
     // Use correct agent names based on mode
@@ -1467,7 +2035,16 @@ BREVITY IS CRITICAL: 1-2 sentences max. Say one thing well, then stop."#,
         ""
     };
     
-    format!("{}\n\n{}\n\nCRITICAL: 1-2 sentences MAX. No name prefixes. No emojis. Be genuine. Dashes: \" -- \" with spaces.{}", base_prompt, response_context, disco_suffix)
+    // The persona (and self-knowledge, when asked about) is the same every turn, so it's cached;
+    // the response instructions quote the primary reply and change per call
+    let static_part = match knowledge {
+        Some(knowledge) => format!("{}\n\n{}", base_prompt, knowledge),
+        None => base_prompt,
+    };
+    cacheable(
+        &static_part,
+        &format!("\n\n{}\n\nCRITICAL: 1-2 sentences MAX. No name prefixes. No emojis. Be genuine. Dashes: \" -- \" with spaces.{}", response_context, disco_suffix),
+    )
 }
 
 /// Get the system prompt for an agent with grounding context and optional self-knowledge
@@ -1480,8 +2057,9 @@ fn get_agent_system_prompt_with_grounding(
     user_profile: Option<&UserProfileSummary>,
     is_disco: bool,
     primary_is_disco: bool,
+    knowledge: Option<&str>,
 ) -> String {
-    let base_prompt = get_agent_system_prompt(agent, response_type, primary_response, primary_agent, is_disco, primary_is_disco);
+    let base_prompt = get_agent_system_prompt(agent, response_type, primary_response, primary_agent, is_disco, primary_is_disco, knowledge);
     
     let mut full_prompt = base_prompt;
     
@@ -1517,8 +2095,12 @@ fn get_agent_system_prompt_with_knowledge(
     is_disco: bool,
     primary_is_disco: bool,
 ) -> String {
+    // Check if the user is asking about Intersect itself
+    // Don't inject knowledge in disco mode - it contains Snap/Dot/Puff references that leak
+    let knowledge = (!is_disco && is_self_referential_query(user_message)).then_some(INTERSECT_KNOWLEDGE);
+    
     let base_prompt = get_agent_system_prompt_with_grounding(
-        agent, response_type, primary_response, primary_agent, grounding, user_profile, is_disco, primary_is_disco
+        agent, response_type, primary_response, primary_agent, grounding, user_profile, is_disco, primary_is_disco, knowledge
     );
     
     let mut full_prompt = base_prompt;
@@ -1540,13 +2122,7 @@ fn get_agent_system_prompt_with_knowledge(
         }
     }
     
-    // Check if the user is asking about Intersect itself
-    // Don't inject knowledge in disco mode - it contains Snap/Dot/Puff references that leak
-    if !is_disco && is_self_referential_query(user_message) {
-        format!("{}\n\n{}", full_prompt, INTERSECT_KNOWLEDGE)
-    } else {
-        full_prompt
-    }
+    full_prompt
 }
 
 /// Format patterns for disco mode challenge - extracts challenge-relevant patterns
@@ -1641,6 +2217,8 @@ fn format_profile_condensed(profile: &UserProfileSummary) -> String {
 pub enum InteractionType {
     ChosenAsPrimary,
     ChosenAsSecondary,
+    // Change in a message's thumbs rating: +1 for a new up, -1 for clearing it, -2 for flipping it to down
+    ExplicitFeedback { delta: i64 },
 }
 
 /// Calculate variability based on message count
@@ -1660,7 +2238,7 @@ pub fn calculate_variability(total_messages: i64) -> f64 {
     1.0 - progress.sqrt()
 }
 
-/// Update agent weights based on interaction (legacy - used for primary/secondary selection)
+/// Update agent weights based on an interaction (primary/secondary selection, or explicit feedback)
 pub fn evolve_weights(
     current_weights: (f64, f64, f64),
     agent: Agent,
@@ -1670,6 +2248,8 @@ pub fn evolve_weights(
     let base_boost = match interaction {
         InteractionType::ChosenAsPrimary => 0.02,
         InteractionType::ChosenAsSecondary => 0.015,
+        // A direct signal from the user outweighs being picked by routing
+        InteractionType::ExplicitFeedback { delta } => 0.03 * delta as f64,
     };
     
     // Apply de-exponential variability
@@ -1716,6 +2296,75 @@ impl Default for EngagementAnalysis {
     }
 }
 
+impl EngagementAnalysis {
+    pub fn score_for(&self, agent: Agent) -> f64 {
+        match agent {
+            Agent::Logic => self.logic_score,
+            Agent::Instinct => self.instinct_score,
+            Agent::Psyche => self.psyche_score,
+        }
+    }
+}
+
+// ============ Response Style Memory ============
+
+/// A style needs this many engagement samples before it can shape the prompt
+const STYLE_MIN_SAMPLES: i64 = 3;
+/// ...and has to beat the alternative by this much engagement to count as a preference
+const STYLE_MIN_GAP: f64 = 0.2;
+
+/// Classify the *form* of an agent response: (dimension, style) pairs for
+/// "format" (list vs prose) and "ending" (question vs statement)
+pub fn classify_response_style(content: &str) -> [(&'static str, &'static str); 2] {
+    let is_list = content.lines()
+        .map(str::trim_start)
+        .filter(|line| {
+            line.starts_with("- ") || line.starts_with("* ") || line.starts_with("• ")
+                || line.split_once(". ").is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .count() >= 2;
+    let ends_with_question = content.trim_end().ends_with('?');
+    
+    [
+        ("format", if is_list { "list" } else { "prose" }),
+        ("ending", if ends_with_question { "question" } else { "statement" }),
+    ]
+}
+
+fn describe_style(style: &str) -> &'static str {
+    match style {
+        "list" => "short bullet points",
+        "prose" => "flowing prose rather than lists",
+        "question" => "ending on a question",
+        _ => "ending on a clear statement rather than a question",
+    }
+}
+
+/// Turn an agent's learned style preferences into a prompt note (None until a preference is clear)
+pub fn format_style_note(prefs: &[db::AgentStylePreference]) -> Option<String> {
+    let mut preferred = Vec::new();
+    for dimension in ["format", "ending"] {
+        let mut styles: Vec<&db::AgentStylePreference> = prefs.iter()
+            .filter(|p| p.dimension == dimension && p.samples >= STYLE_MIN_SAMPLES)
+            .collect();
+        if styles.len() < 2 {
+            continue;
+        }
+        styles.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        if styles[0].score - styles[1].score >= STYLE_MIN_GAP {
+            preferred.push(describe_style(&styles[0].style));
+        }
+    }
+    
+    if preferred.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "RESPONSE STYLE: This user engages more with you when you use {}. Lean that way when it fits -- content still comes first.",
+        preferred.join(" and ")
+    ))
+}
+
 /// Analyzes user messages to detect engagement patterns with agents
 pub struct EngagementAnalyzer {
     client: AnthropicClient, // Uses Claude Opus 4.5 for analysis
@@ -1987,7 +2636,7 @@ impl AgentRelationship {
         
         let days_ago = if let Some(ref last) = interaction.last_interaction {
             if let Ok(last_dt) = chrono::DateTime::parse_from_rfc3339(last) {
-                let now = chrono::Utc::now();
+                let now = clock::now();
                 (now - last_dt.with_timezone(&chrono::Utc)).num_days()
             } else {
                 999
@@ -2127,3 +2776,212 @@ pub fn parse_cold_start_response(
         current_weights
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Build a history from (role, content) pairs; only roles matter for silence detection
+    fn history(roles: &[&str]) -> Vec<Message> {
+        roles.iter()
+            .enumerate()
+            .map(|(i, role)| Message {
+                id: format!("m{}", i),
+                conversation_id: "c".to_string(),
+                role: role.to_string(),
+                content: String::new(),
+                response_type: None,
+                references_message_id: None,
+                timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+                attachments: Vec::new(),
+                starred: false,
+            })
+            .collect()
+    }
+    
+    fn silence_of<'a>(silence: &'a [AgentSilence], agent: &str) -> &'a AgentSilence {
+        silence.iter().find(|s| s.agent == agent).unwrap()
+    }
+    
+    #[test]
+    fn empty_history_has_no_silence() {
+        let silence = compute_agent_silence(&[]);
+        assert_eq!(silence.len(), 3);
+        assert!(silence.iter().all(|s| s.turns_since_spoke == 0 && !s.boosted && s.last_spoke_turn.is_none()));
+    }
+    
+    #[test]
+    fn pending_user_turn_is_not_silence() {
+        // Logic answered turn 1; turn 2 is the one being routed
+        let silence = compute_agent_silence(&history(&["user", "logic", "user"]));
+        
+        let logic = silence_of(&silence, "logic");
+        assert_eq!(logic.last_spoke_turn, Some(1));
+        assert_eq!(logic.turns_since_spoke, 0);
+        assert_eq!(silence_of(&silence, "psyche").turns_since_spoke, 1);
+    }
+    
+    #[test]
+    fn multiple_replies_in_one_turn_count_once() {
+        // The old loop added a turn per message walked; several replies must not inflate silence
+        let silence = compute_agent_silence(&history(&[
+            "user", "logic", "instinct", "logic",
+            "user", "logic", "instinct",
+            "user",
+        ]));
+        
+        assert_eq!(silence_of(&silence, "logic").turns_since_spoke, 0);
+        assert_eq!(silence_of(&silence, "instinct").turns_since_spoke, 0);
+        assert_eq!(silence_of(&silence, "psyche").turns_since_spoke, 2);
+        assert!(!silence_of(&silence, "psyche").boosted);
+    }
+    
+    #[test]
+    fn agent_is_boosted_after_sitting_out_three_turns() {
+        let silence = compute_agent_silence(&history(&[
+            "user", "psyche",
+            "user", "logic",
+            "user", "logic",
+            "user", "instinct",
+            "user",
+        ]));
+        
+        let psyche = silence_of(&silence, "psyche");
+        assert_eq!(psyche.last_spoke_turn, Some(1));
+        assert_eq!(psyche.turns_since_spoke, 3);
+        assert!(psyche.boosted);
+        assert_eq!(silence_of(&silence, "logic").turns_since_spoke, 1);
+        assert!(!silence_of(&silence, "logic").boosted);
+    }
+    
+    #[test]
+    fn system_notices_do_not_end_a_turn() {
+        // A Governor notice after the user message must not make the pending turn look answered
+        let silence = compute_agent_silence(&history(&["user", "logic", "user", "system"]));
+        assert_eq!(silence_of(&silence, "logic").turns_since_spoke, 0);
+    }
+    
+    #[test]
+    fn silence_boost_lifts_a_quiet_agent() {
+        let agents: Vec<String> = ["instinct", "logic", "psyche"].iter().map(|a| a.to_string()).collect();
+        let quiet = compute_agent_silence(&history(&[
+            "user", "logic", "user", "logic", "user", "logic", "user",
+        ]));
+        let baseline = decide_response_heuristic("hello", (0.33, 0.34, 0.33), &agents, &[], false, None, None, None, None);
+        let boosted = decide_response_heuristic("hello", (0.33, 0.34, 0.33), &agents, &quiet, false, None, None, None, None);
+        
+        assert_eq!(baseline.primary_agent, "logic");
+        assert_ne!(boosted.primary_agent, "logic");
+        
+        let trace = boosted.routing.unwrap();
+        assert!(trace.silence_boosts.contains(&boosted.primary_agent));
+        assert!(!trace.silence_boosts.contains(&"logic".to_string()));
+        assert!(trace.scores[&boosted.primary_agent] > trace.scores["logic"]);
+    }
+    
+    #[test]
+    fn embedding_affinity_routes_paraphrases_and_keywords_are_the_fallback() {
+        let agents: Vec<String> = ["instinct", "logic", "psyche"].iter().map(|a| a.to_string()).collect();
+        // No keyword from any list, but closest in meaning to psyche's exemplars
+        let message = "my sister barely spoke to me at the wedding";
+        let affinity = vec![
+            ("instinct".to_string(), 0.21),
+            ("logic".to_string(), 0.19),
+            ("psyche".to_string(), 0.27),
+        ];
+        
+        let fallback = decide_response_heuristic(message, (0.33, 0.34, 0.33), &agents, &[], false, None, None, None, None);
+        let embedded = decide_response_heuristic(message, (0.33, 0.34, 0.33), &agents, &[], false, None, None, None, Some(&affinity));
+        assert_eq!(fallback.primary_agent, "logic");
+        assert_eq!(embedded.primary_agent, "psyche");
+        
+        // Keywords still route when embeddings are unavailable
+        let keyword = decide_response_heuristic("why does this matter to me", (0.33, 0.34, 0.33), &agents, &[], false, None, None, None, None);
+        assert_eq!(keyword.primary_agent, "psyche");
+        let trace = keyword.routing.unwrap();
+        assert!(trace.keyword_hits["psyche"].contains(&"why".to_string()));
+        assert!(trace.embedding_boosts.is_empty());
+    }
+    
+    #[test]
+    fn prior_moods_match_whole_words() {
+        assert!(mentions_mood("Stressed; felt low after the review", "low"));
+        assert!(mentions_mood("Stressed; felt low after the review", "stress*"));
+        assert!(!mentions_mood("Wants to follow up with Sadie", "low"));
+        assert!(!mentions_mood("Wants to follow up with Sadie", "sad"));
+        assert!(mentions_mood("Sad, but hopeful", "sad"));
+        
+        let agents: Vec<String> = ["instinct", "logic", "psyche"].iter().map(|a| a.to_string()).collect();
+        let planning = decide_response_heuristic("hello", (0.33, 0.34, 0.33), &agents, &[], false, None, None, Some("Planning to follow up with Sadie"), None);
+        assert!(planning.routing.unwrap().mood_boosts.is_empty());
+        let low = decide_response_heuristic("hello", (0.33, 0.34, 0.33), &agents, &[], false, None, None, Some("Feeling low"), None);
+        assert_eq!(low.routing.unwrap().mood_boosts, vec!["psyche".to_string()]);
+    }
+    
+    #[test]
+    fn affinity_boosts_share_a_fixed_total() {
+        let boosts = affinity_boosts(&[("logic".to_string(), 0.3), ("psyche".to_string(), 0.3)]);
+        assert!((boosts[0].1 - EMBEDDING_ROUTING_BOOST / 2.0).abs() < 1e-9);
+        assert!((boosts.iter().map(|(_, b)| b).sum::<f64>() - EMBEDDING_ROUTING_BOOST).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn only_factual_questions_are_searched() {
+        assert!(decide_search_heuristic("Who won the 2022 World Cup?").is_some());
+        assert!(decide_search_heuristic("what's the latest news on the Artemis program?").is_some());
+        assert_eq!(decide_search_heuristic("look up the capital of Australia").as_deref(), Some("the capital of australia"));
+        
+        assert!(decide_search_heuristic("Should I quit my job?").is_none());
+        assert!(decide_search_heuristic("What do you think about stoicism?").is_none());
+        assert!(decide_search_heuristic("I had a long day").is_none());
+    }
+    
+    #[test]
+    fn schedule_and_stress_topics() {
+        assert!(is_schedule_or_stress_topic("I'm so overwhelmed by this week"));
+        assert!(is_schedule_or_stress_topic("Can I fit a workout in this afternoon?"));
+        assert!(!is_schedule_or_stress_topic("What do you think about stoicism?"));
+    }
+    
+    #[test]
+    fn quote_reply_forces_the_primary() {
+        let mut decision = OrchestratorDecision {
+            primary_agent: "logic".to_string(),
+            add_secondary: true,
+            secondary_agent: Some("psyche".to_string()),
+            secondary_type: Some("addition".to_string()),
+            routing: None,
+        };
+        force_primary(&mut decision, "psyche");
+        assert_eq!(decision.primary_agent, "psyche");
+        assert_eq!(decision.secondary_agent.as_deref(), Some("logic"));
+        force_primary(&mut decision, "instinct");
+        assert_eq!((decision.primary_agent.as_str(), decision.secondary_agent.as_deref()), ("instinct", Some("logic")));
+    }
+    
+    #[test]
+    fn explicit_feedback_moves_weights_both_ways() {
+        let start = (0.3, 0.4, 0.3);
+        let feedback = |weights, delta| evolve_weights(weights, Agent::Psyche, InteractionType::ExplicitFeedback { delta }, 0);
+        let up = feedback(start, 1);
+        let down = feedback(start, -1);
+        assert!(up.2 > start.2 && down.2 < start.2);
+        assert!((down.0 + down.1 + down.2 - 1.0).abs() < 1e-9);
+        // Clearing the thumbs up takes its boost back; flipping it lands where a plain thumbs down would
+        let cleared = feedback(up, -1);
+        assert!((cleared.2 - start.2).abs() < 0.002);
+        let flipped = feedback(up, -2);
+        assert!((flipped.2 - down.2).abs() < 0.002);
+        // Fully settled profiles don't move
+        assert_eq!(evolve_weights(start, Agent::Psyche, InteractionType::ExplicitFeedback { delta: 1 }, 10_000), start);
+    }
+    
+    #[test]
+    fn tool_replies_are_capped_at_the_callers_budget() {
+        let reply = "Take the walk first. Then answer the email, but only the one that matters today. Everything else can wait until tomorrow morning.";
+        assert_eq!(cap_reply(reply, 200, "gpt-4o-mini"), reply);
+        let capped = cap_reply(reply, 8, "gpt-4o-mini");
+        assert_eq!(capped, "Take the walk first.");
+        assert!(tokenizer::count_for_model(&cap_reply(reply, 3, "gpt-4o-mini"), "gpt-4o-mini") <= 3);
+    }
+}
@@ -0,0 +1,185 @@
+//! Personality type mapping for Intersect
+//!
+//! Maps a persona's trait weights, observed patterns and message volume onto the 16 types
+//! described in the knowledge base:
+//! - The dominant trait picks the group: Logic -> Analysts, Psyche -> Sentinels,
+//!   Instinct -> Explorers, and no clear leader -> Diplomats
+//! - The balance of the other two traits picks the type's third and fourth letters
+//! - Communication and emotional patterns lean the type extraverted or introverted
+//! - Confidence grows with message volume (nothing firm before MIN_MESSAGES) and with how
+//!   clearly the weights and patterns point one way
+
+use crate::db::{PersonaProfile, UserPattern};
+use serde::{Deserialize, Serialize};
+
+/// Messages needed before the assessment counts as an opinion rather than a first guess
+pub const MIN_MESSAGES: i64 = 100;
+/// Message volume at which volume stops limiting confidence
+const FULL_CONFIDENCE_MESSAGES: f64 = 500.0;
+/// A lead smaller than this between the top two weights means no trait clearly dominates
+const MIXED_MARGIN: f64 = 0.05;
+
+/// (code, name, group)
+const TYPES: [(&str, &str, &str); 16] = [
+    ("INTJ", "Architect", "Analysts"),
+    ("INTP", "Logician", "Analysts"),
+    ("ENTJ", "Commander", "Analysts"),
+    ("ENTP", "Debater", "Analysts"),
+    ("INFJ", "Advocate", "Diplomats"),
+    ("INFP", "Mediator", "Diplomats"),
+    ("ENFJ", "Protagonist", "Diplomats"),
+    ("ENFP", "Campaigner", "Diplomats"),
+    ("ISTJ", "Logistician", "Sentinels"),
+    ("ISFJ", "Defender", "Sentinels"),
+    ("ESTJ", "Executive", "Sentinels"),
+    ("ESFJ", "Consul", "Sentinels"),
+    ("ISTP", "Virtuoso", "Explorers"),
+    ("ISFP", "Adventurer", "Explorers"),
+    ("ESTP", "Entrepreneur", "Explorers"),
+    ("ESFP", "Entertainer", "Explorers"),
+];
+
+const EXTRAVERTED_WORDS: [&str; 8] = ["expressive", "outgoing", "social", "talkative", "energetic", "enthusiastic", "verbose", "thinks out loud"];
+const INTROVERTED_WORDS: [&str; 8] = ["reserved", "introspective", "reflective", "private", "concise", "quiet", "terse", "internal"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalityAssessment {
+    pub code: String,         // e.g. "INTJ"
+    pub name: String,         // e.g. "Architect"
+    pub group: String,        // "Analysts" | "Diplomats" | "Sentinels" | "Explorers"
+    pub confidence: f64,      // 0-1, drives the confidence bar
+    pub provisional: bool,    // Fewer than MIN_MESSAGES messages: a first guess
+    pub message_count: i64,
+}
+
+impl PersonalityAssessment {
+    /// One line for prompts and reports
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({}, {}) -- {:.0}% confidence{}",
+            self.name, self.code, self.group, self.confidence * 100.0,
+            if self.provisional { ", provisional" } else { "" }
+        )
+    }
+}
+
+/// Extraversion signal from pattern descriptions, from -1 (introverted) to 1 (extraverted)
+fn extraversion(patterns: &[UserPattern]) -> f64 {
+    let (mut score, mut weight) = (0.0, 0.0);
+    for pattern in patterns.iter().filter(|p| p.pattern_type == "communication_style" || p.pattern_type == "emotional_tendency") {
+        let desc = pattern.description.to_lowercase();
+        let extra = EXTRAVERTED_WORDS.iter().filter(|w| desc.contains(*w)).count() as f64;
+        let intro = INTROVERTED_WORDS.iter().filter(|w| desc.contains(*w)).count() as f64;
+        if extra + intro > 0.0 {
+            score += pattern.confidence * (extra - intro) / (extra + intro);
+            weight += pattern.confidence;
+        }
+    }
+    if weight > 0.0 { score / weight } else { 0.0 }
+}
+
+/// Assess a persona's personality type
+pub fn assess(profile: &PersonaProfile, patterns: &[UserPattern]) -> PersonalityAssessment {
+    let (logic, instinct, psyche) = (profile.logic_weight, profile.instinct_weight, profile.psyche_weight);
+    let mut ranked = [("logic", logic), ("instinct", instinct), ("psyche", psyche)];
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let margin = ranked[0].1 - ranked[1].1;
+
+    // Second letter pair: which of the two non-dominant traits leads
+    let (group, third, fourth) = if margin < MIXED_MARGIN {
+        ("Diplomats", 'F', if psyche >= instinct { 'J' } else { 'P' })
+    } else {
+        match ranked[0].0 {
+            "logic" => ("Analysts", 'T', if psyche >= instinct { 'J' } else { 'P' }),
+            "psyche" => ("Sentinels", if logic >= instinct { 'T' } else { 'F' }, 'J'),
+            _ => ("Explorers", if logic >= psyche { 'T' } else { 'F' }, 'P'),
+        }
+    };
+    let second = match group {
+        "Analysts" | "Diplomats" => 'N',
+        _ => 'S',
+    };
+    let lean = extraversion(patterns);
+    let first = if lean > 0.0 { 'E' } else { 'I' };
+    let code: String = [first, second, third, fourth].iter().collect();
+    let (_, name, _) = TYPES.iter().find(|(c, _, _)| *c == code).copied().unwrap_or(TYPES[0]);
+
+    // Clarity: how decisively the weights separate and how strong the E/I signal is
+    let weight_clarity = if group == "Diplomats" {
+        1.0 - margin / MIXED_MARGIN
+    } else {
+        (margin / 0.2).min(1.0)
+    };
+    let clarity = 0.7 * weight_clarity + 0.3 * lean.abs();
+    let volume = (profile.message_count as f64 / FULL_CONFIDENCE_MESSAGES).clamp(0.0, 1.0);
+    let confidence = (volume * (0.4 + 0.6 * clarity)).clamp(0.0, 1.0);
+
+    PersonalityAssessment {
+        code,
+        name: name.to_string(),
+        group: group.to_string(),
+        confidence,
+        provisional: profile.message_count < MIN_MESSAGES,
+        message_count: profile.message_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn persona(logic: f64, instinct: f64, psyche: f64, message_count: i64) -> PersonaProfile {
+        PersonaProfile {
+            id: "p".to_string(),
+            name: "Logic".to_string(),
+            is_default: true,
+            is_active: true,
+            dominant_trait: "logic".to_string(),
+            secondary_trait: "psyche".to_string(),
+            instinct_weight: instinct,
+            logic_weight: logic,
+            psyche_weight: psyche,
+            instinct_points: 4,
+            logic_points: 4,
+            psyche_points: 3,
+            message_count,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn pattern(description: &str) -> UserPattern {
+        UserPattern {
+            id: 1,
+            pattern_type: "communication_style".to_string(),
+            description: description.to_string(),
+            confidence: 0.8,
+            evidence: "[]".to_string(),
+            first_observed: String::new(),
+            last_updated: String::new(),
+            observation_count: 3,
+        }
+    }
+
+    #[test]
+    fn dominant_trait_picks_the_group_and_patterns_pick_extraversion() {
+        let analyst = assess(&persona(0.5, 0.2, 0.3, 600), &[pattern("Reserved and concise")]);
+        assert_eq!((analyst.code.as_str(), analyst.name.as_str()), ("INTJ", "Architect"));
+        assert!(!analyst.provisional);
+
+        let explorer = assess(&persona(0.2, 0.5, 0.3, 600), &[pattern("Expressive, thinks out loud")]);
+        assert_eq!((explorer.code.as_str(), explorer.group.as_str()), ("ESFP", "Explorers"));
+
+        let mixed = assess(&persona(0.34, 0.33, 0.33, 600), &[]);
+        assert_eq!(mixed.group, "Diplomats");
+    }
+
+    #[test]
+    fn confidence_grows_with_message_volume() {
+        let early = assess(&persona(0.5, 0.2, 0.3, 20), &[]);
+        let later = assess(&persona(0.5, 0.2, 0.3, 400), &[]);
+        assert!(early.provisional);
+        assert!(early.confidence < later.confidence);
+        assert!(later.confidence <= 1.0);
+    }
+}
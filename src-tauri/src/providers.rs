@@ -0,0 +1,399 @@
+//! Model providers for agent responses
+//!
+//! Snap, Dot and Puff can each run on a different backend:
+//! - "openai" (the default) and "anthropic" use the keys saved in the user profile; "openai" can be
+//!   pointed at OpenRouter or another compatible server (see `OpenAIEndpoint`), and the per-agent
+//!   model is then that server's model string (e.g. "meta-llama/llama-3.1-70b-instruct")
+//! - "gemini" uses the Google Gemini key saved alongside them
+//! - "ollama" talks to a local Ollama server, so agent replies work offline and cost nothing
+//! - The user profile holds the default provider; an agent's own choice is stored in app_settings
+//!   as `agent_provider.<agent>` = {"provider", "model"} and wins over it
+//!
+//! When a call fails with a rate limit, server error or timeout, the agent retries down a fallback
+//! chain of models (setting `model_fallback_chain`, see `get_fallback_chain`).
+//!
+//! Routing, the Governor and memory stay on Anthropic regardless of these settings.
+
+use crate::anthropic::{strip_cache_break, AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU, CLAUDE_SONNET};
+use crate::db::{self, UserProfile};
+use crate::gemini::{GeminiClient, DEFAULT_GEMINI_MODEL};
+use crate::ollama::{OllamaClient, DEFAULT_OLLAMA_MODEL, DEFAULT_OLLAMA_URL};
+use crate::openai::{ChatMessage, OpenAIClient, OpenAIEndpoint, DEFAULT_AGENT_MODEL};
+use crate::tools::{ToolCall, ToolConversation, ToolStep};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+
+pub const PROVIDERS: [&str; 4] = ["openai", "anthropic", "ollama", "gemini"];
+const OLLAMA_URL_SETTING: &str = "ollama_base_url";
+const FALLBACK_SETTING: &str = "model_fallback_chain";
+
+pub type ProviderFuture<'a> = Pin<Box<dyn Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + Send + 'a>>;
+pub type ToolStepFuture<'a> = Pin<Box<dyn Future<Output = Result<ToolStep, Box<dyn Error + Send + Sync>>> + Send + 'a>>;
+
+/// A chat backend that can voice an agent
+pub trait ChatProvider: Send + Sync {
+    /// Complete a conversation of user/assistant `turns` under a separate system prompt
+    fn chat<'a>(
+        &'a self,
+        model: &'a str,
+        system: &'a str,
+        turns: Vec<ChatMessage>,
+        temperature: f32,
+        max_tokens: u32,
+    ) -> ProviderFuture<'a>;
+
+    /// One step of a reply that may call tools: the model either answers or asks for calls.
+    /// None when the backend has no tool calling (the agent then answers without tools).
+    fn chat_with_tools<'a>(
+        &'a self,
+        _model: &'a str,
+        _system: &'a str,
+        _conversation: ToolConversation<'a>,
+        _temperature: f32,
+        _max_tokens: u32,
+    ) -> Option<ToolStepFuture<'a>> {
+        None
+    }
+}
+
+/// OpenAI-style APIs take the system prompt as the first message (and have no prompt caching markers)
+fn with_system(system: &str, turns: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    std::iter::once(ChatMessage { role: "system".to_string(), content: strip_cache_break(system) })
+        .chain(turns)
+        .collect()
+}
+
+impl ChatProvider for OpenAIClient {
+    fn chat<'a>(&'a self, model: &'a str, system: &'a str, turns: Vec<ChatMessage>, temperature: f32, max_tokens: u32) -> ProviderFuture<'a> {
+        Box::pin(self.chat_completion_with_model(model, with_system(system, turns), temperature, Some(max_tokens)))
+    }
+
+    fn chat_with_tools<'a>(&'a self, model: &'a str, system: &'a str, conversation: ToolConversation<'a>, temperature: f32, max_tokens: u32) -> Option<ToolStepFuture<'a>> {
+        let request = openai_tool_request(model, system, &conversation, temperature, max_tokens);
+        Some(Box::pin(async move { openai_tool_step(&self.chat_completion_json(request).await?) }))
+    }
+}
+
+/// OpenAI function calling: each round is an assistant message with `tool_calls` followed by
+/// one `tool` message per result
+fn openai_tool_request(model: &str, system: &str, conversation: &ToolConversation, temperature: f32, max_tokens: u32) -> Value {
+    let mut messages: Vec<Value> = with_system(system, conversation.turns.clone())
+        .into_iter()
+        .map(|m| json!({ "role": m.role, "content": m.content }))
+        .collect();
+    for round in conversation.rounds {
+        messages.push(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": round.calls.iter().map(|call| json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments.to_string() }
+            })).collect::<Vec<_>>()
+        }));
+        for (call, result) in round.calls.iter().zip(&round.results) {
+            messages.push(json!({ "role": "tool", "tool_call_id": call.id, "content": result }));
+        }
+    }
+    let tools: Vec<Value> = conversation.tools.iter()
+        .map(|tool| json!({
+            "type": "function",
+            "function": { "name": tool.name, "description": tool.description, "parameters": tool.parameters }
+        }))
+        .collect();
+    json!({
+        "model": model,
+        "messages": messages,
+        "tools": tools,
+        "temperature": temperature,
+        "max_tokens": max_tokens,
+    })
+}
+
+fn openai_tool_step(message: &Value) -> Result<ToolStep, Box<dyn Error + Send + Sync>> {
+    let calls: Vec<ToolCall> = message["tool_calls"].as_array()
+        .map(|calls| calls.iter()
+            .filter_map(|call| Some(ToolCall {
+                id: call["id"].as_str()?.to_string(),
+                name: call["function"]["name"].as_str()?.to_string(),
+                arguments: call["function"]["arguments"].as_str()
+                    .and_then(|args| serde_json::from_str(args).ok())
+                    .unwrap_or_else(|| json!({})),
+            }))
+            .collect())
+        .unwrap_or_default();
+    if !calls.is_empty() {
+        return Ok(ToolStep::Calls(calls));
+    }
+    message["content"].as_str()
+        .map(|text| ToolStep::Reply(text.to_string()))
+        .ok_or_else(|| "No response from OpenAI".into())
+}
+
+/// Anthropic tool use: each round is an assistant turn of `tool_use` blocks followed by a user
+/// turn of `tool_result` blocks
+fn anthropic_tool_request(model: &str, system: &str, conversation: &ToolConversation, temperature: f32, max_tokens: u32) -> Value {
+    let mut messages: Vec<Value> = conversation.turns.iter()
+        .map(|m| json!({ "role": m.role, "content": m.content }))
+        .collect();
+    for round in conversation.rounds {
+        messages.push(json!({
+            "role": "assistant",
+            "content": round.calls.iter()
+                .map(|call| json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments }))
+                .collect::<Vec<_>>()
+        }));
+        messages.push(json!({
+            "role": "user",
+            "content": round.calls.iter().zip(&round.results)
+                .map(|(call, result)| json!({ "type": "tool_result", "tool_use_id": call.id, "content": result }))
+                .collect::<Vec<_>>()
+        }));
+    }
+    let tools: Vec<Value> = conversation.tools.iter()
+        .map(|tool| json!({ "name": tool.name, "description": tool.description, "input_schema": tool.parameters }))
+        .collect();
+    json!({
+        "model": model,
+        "system": system,
+        "messages": messages,
+        "tools": tools,
+        "temperature": temperature,
+        "max_tokens": max_tokens,
+    })
+}
+
+fn anthropic_tool_step(content: &Value) -> Result<ToolStep, Box<dyn Error + Send + Sync>> {
+    let blocks = content.as_array().cloned().unwrap_or_default();
+    let calls: Vec<ToolCall> = blocks.iter()
+        .filter(|block| block["type"] == "tool_use")
+        .filter_map(|block| Some(ToolCall {
+            id: block["id"].as_str()?.to_string(),
+            name: block["name"].as_str()?.to_string(),
+            arguments: block["input"].clone(),
+        }))
+        .collect();
+    if !calls.is_empty() {
+        return Ok(ToolStep::Calls(calls));
+    }
+    blocks.iter()
+        .rfind(|block| block["type"] == "text")
+        .and_then(|block| block["text"].as_str())
+        .map(|text| ToolStep::Reply(text.to_string()))
+        .ok_or_else(|| "No text response from Claude".into())
+}
+
+impl ChatProvider for AnthropicClient {
+    fn chat<'a>(&'a self, model: &'a str, system: &'a str, turns: Vec<ChatMessage>, temperature: f32, max_tokens: u32) -> ProviderFuture<'a> {
+        let turns = turns.into_iter()
+            .map(|m| AnthropicMessage { role: m.role, content: m.content })
+            .collect();
+        Box::pin(self.chat_completion_advanced(model, Some(system), turns, temperature, Some(max_tokens), ThinkingBudget::None))
+    }
+
+    fn chat_with_tools<'a>(&'a self, model: &'a str, system: &'a str, conversation: ToolConversation<'a>, temperature: f32, max_tokens: u32) -> Option<ToolStepFuture<'a>> {
+        let request = anthropic_tool_request(model, system, &conversation, temperature, max_tokens);
+        Some(Box::pin(async move { anthropic_tool_step(&self.messages_json(request).await?) }))
+    }
+}
+
+impl ChatProvider for OllamaClient {
+    fn chat<'a>(&'a self, model: &'a str, system: &'a str, turns: Vec<ChatMessage>, temperature: f32, max_tokens: u32) -> ProviderFuture<'a> {
+        Box::pin(self.chat_completion(model, with_system(system, turns), temperature, Some(max_tokens)))
+    }
+}
+
+impl ChatProvider for GeminiClient {
+    fn chat<'a>(&'a self, model: &'a str, system: &'a str, turns: Vec<ChatMessage>, temperature: f32, max_tokens: u32) -> ProviderFuture<'a> {
+        Box::pin(self.chat_completion(model, with_system(system, turns), temperature, Some(max_tokens)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentProvider {
+    pub provider: String,       // "openai" | "anthropic" | "ollama" | "gemini"
+    pub model: Option<String>,  // None = the provider's default agent model
+}
+
+impl Default for AgentProvider {
+    fn default() -> Self {
+        Self { provider: "openai".to_string(), model: None }
+    }
+}
+
+impl AgentProvider {
+    pub fn model_or_default(&self) -> String {
+        self.model.clone().unwrap_or_else(|| default_model(&self.provider))
+    }
+}
+
+/// Default agent model per provider (for "openai", the one set on the configured endpoint)
+pub fn default_model(provider: &str) -> String {
+    match provider {
+        "anthropic" => CLAUDE_HAIKU.to_string(),
+        "ollama" => DEFAULT_OLLAMA_MODEL.to_string(),
+        "gemini" => DEFAULT_GEMINI_MODEL.to_string(),
+        _ => OpenAIEndpoint::configured().default_model,
+    }
+}
+
+fn setting_key(agent: &str) -> String {
+    format!("agent_provider.{}", agent)
+}
+
+fn check_provider(provider: &str) -> Result<(), String> {
+    if !PROVIDERS.contains(&provider) {
+        return Err(format!("Unknown provider \"{}\" (expected one of: {})", provider, PROVIDERS.join(", ")));
+    }
+    Ok(())
+}
+
+/// The provider an agent runs on: its own setting, else the profile's default provider
+pub fn get_agent_provider(agent: &str) -> AgentProvider {
+    db::get_setting(&setting_key(agent))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| AgentProvider {
+            provider: db::get_user_profile().map(|p| p.agent_provider).unwrap_or_else(|_| "openai".to_string()),
+            model: None,
+        })
+}
+
+pub fn set_agent_provider(agent: &str, provider: &AgentProvider) -> Result<(), String> {
+    check_provider(&provider.provider)?;
+    let json = serde_json::to_string(provider).map_err(|e| e.to_string())?;
+    db::set_setting(&setting_key(agent), &json).map_err(|e| e.to_string())
+}
+
+/// Change the profile's default provider (agents with their own setting keep it)
+pub fn set_default_provider(provider: &str) -> Result<(), String> {
+    check_provider(provider)?;
+    db::set_default_agent_provider(provider).map_err(|e| e.to_string())
+}
+
+pub fn ollama_base_url() -> String {
+    db::get_setting(OLLAMA_URL_SETTING)
+        .ok()
+        .flatten()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string())
+}
+
+pub fn set_ollama_base_url(url: &str) -> Result<(), String> {
+    let url = url.trim();
+    if !url.is_empty() && !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err("The Ollama URL must start with http:// or https://".to_string());
+    }
+    db::set_setting(OLLAMA_URL_SETTING, url).map_err(|e| e.to_string())
+}
+
+/// Build the client for an agent's provider with the keys saved in the profile
+pub fn build(provider: &AgentProvider, profile: &UserProfile) -> Result<Box<dyn ChatProvider>, String> {
+    let key = |key: &Option<String>, name: &str| key.clone().ok_or_else(|| format!("{} API key not set", name));
+    Ok(match provider.provider.as_str() {
+        "anthropic" => Box::new(AnthropicClient::new(&key(&profile.anthropic_key, "Anthropic")?)),
+        "ollama" => Box::new(OllamaClient::new(&ollama_base_url())),
+        "gemini" => Box::new(GeminiClient::new(&key(&profile.gemini_key, "Gemini")?)),
+        _ => Box::new(OpenAIClient::new(&key(&profile.api_key, "OpenAI")?)),
+    })
+}
+
+// ============ Fallback Chain ============
+
+/// Whether a failed call is worth retrying on another model: rate limits, server errors,
+/// timeouts and unreachable servers (a bad request would fail the same way everywhere)
+pub fn is_retryable_error(error: &str) -> bool {
+    let status_retryable = error.find('(')
+        .and_then(|i| error.get(i + 1..i + 4))
+        .and_then(|code| code.parse::<u16>().ok())
+        .map(|code| code == 429 || (500..600).contains(&code))
+        .unwrap_or(false);
+    status_retryable
+        || error.contains("timed out")
+        || error.contains("error sending request")
+        || error.contains("not reachable")
+}
+
+/// Models tried in order when an agent's model fails with a retryable error.
+/// Entries are model names (claude-* go to Anthropic, gemini-* to Gemini, anything else to the
+/// OpenAI endpoint) or "provider:model" (e.g. "ollama:llama3.1").
+pub fn get_fallback_chain() -> Vec<String> {
+    db::get_setting(FALLBACK_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| vec![DEFAULT_AGENT_MODEL.to_string(), CLAUDE_SONNET.to_string()])
+}
+
+pub fn set_fallback_chain(chain: &[String]) -> Result<(), String> {
+    let chain: Vec<String> = chain.iter()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect();
+    let json = serde_json::to_string(&chain).map_err(|e| e.to_string())?;
+    db::set_setting(FALLBACK_SETTING, &json).map_err(|e| e.to_string())
+}
+
+/// Which provider serves a fallback chain entry
+pub fn parse_fallback(entry: &str) -> AgentProvider {
+    if let Some((provider, model)) = entry.split_once(':') {
+        if PROVIDERS.contains(&provider) {
+            return AgentProvider { provider: provider.to_string(), model: Some(model.to_string()) };
+        }
+    }
+    let provider = if entry.starts_with("claude-") {
+        "anthropic"
+    } else if entry.starts_with("gemini-") {
+        "gemini"
+    } else {
+        "openai"
+    };
+    AgentProvider { provider: provider.to_string(), model: Some(entry.to_string()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_failures_fall_back() {
+        let cases = [
+            ("OpenAI API error (429 Too Many Requests): rate limited", true),
+            ("Anthropic API error (529 <unknown status code>): overloaded", true),
+            ("Anthropic API error (500 Internal Server Error): boom", true),
+            ("Ollama error (503 Service Unavailable): loading model", true),
+            ("error sending request for url (https://api.openai.com/v1/chat/completions)", true),
+            ("operation timed out", true),
+            ("Ollama is not reachable at http://localhost:11434", true),
+            ("Anthropic API error (400 Bad Request): prompt is too long", false),
+            ("OpenAI API error (401 Unauthorized): invalid key", false),
+            ("OpenAI API error (404 Not Found): model does not exist", false),
+            ("OpenAI API key not set", false),
+            ("Response had no content (finish reason: stop)", false),
+        ];
+        for (error, retryable) in cases {
+            assert_eq!(is_retryable_error(error), retryable, "{}", error);
+        }
+    }
+
+    #[test]
+    fn fallback_entries_name_their_provider() {
+        let cases = [
+            ("claude-sonnet-4-20250514", "anthropic", "claude-sonnet-4-20250514"),
+            ("gemini-2.5-flash", "gemini", "gemini-2.5-flash"),
+            ("gpt-4o", "openai", "gpt-4o"),
+            ("ollama:llama3.1", "ollama", "llama3.1"),
+            ("anthropic:claude-3-5-haiku-latest", "anthropic", "claude-3-5-haiku-latest"),
+            // Not a known provider prefix: the whole entry is an OpenAI-endpoint model (e.g. OpenRouter)
+            ("meta-llama/llama-3.1-70b:free", "openai", "meta-llama/llama-3.1-70b:free"),
+        ];
+        for (entry, provider, model) in cases {
+            let parsed = parse_fallback(entry);
+            assert_eq!((parsed.provider.as_str(), parsed.model.as_deref()), (provider, Some(model)), "{}", entry);
+        }
+    }
+}
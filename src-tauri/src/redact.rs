@@ -0,0 +1,277 @@
+//! PII redaction for Intersect's logs
+//!
+//! Everything `logging` writes goes through `redact` first, which masks:
+//! - Email addresses -> [email]
+//! - Phone numbers (10-15 digits, or 8+ after a leading +) -> [phone]
+//! - API keys (OpenAI/Anthropic `sk-`, Gemini `AIza`, and similar prefixes) -> [api-key]
+//! - Other secret-looking tokens (long, mixed-case with digits, near random) -> [api-key]
+//! - Names the user has told Intersect about (facts whose key mentions "name"), matched
+//!   case-sensitively as whole words -> [name]
+//!
+//! Names come from a cache refreshed with `refresh_names` after facts change, since logging
+//! can't query the database (it's called while the connection is held).
+//!
+//! For debugging, INTERSECT_LOG_UNREDACTED=1 or `set_enabled(false)` turns redaction off until
+//! the app restarts; it's never persisted.
+
+use crate::db;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+pub const UNREDACTED_ENV: &str = "INTERSECT_LOG_UNREDACTED";
+const KEY_PREFIXES: [&str; 6] = ["sk-", "sk_", "AIza", "xox", "ghp_", "gsk_"];
+const MIN_KEY_LEN: usize = 20;
+/// Unprefixed tokens must be at least this long, and this random (bits per character), to count as secrets
+const MIN_SECRET_LEN: usize = 24;
+const MIN_SECRET_ENTROPY: f64 = 4.2;
+/// Longer fact values are descriptions, not names
+const MAX_NAME_LEN: usize = 40;
+
+static ENABLED: Lazy<AtomicBool> = Lazy::new(|| {
+    let unredacted = std::env::var(UNREDACTED_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    AtomicBool::new(!unredacted)
+});
+static NAMES: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Names to mask from a fact value: the whole value and each capitalized word in it
+fn names_in(value: &str) -> Vec<String> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().count() > MAX_NAME_LEN {
+        return Vec::new();
+    }
+    let mut names = vec![value.to_string()];
+    names.extend(
+        value.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|w| w.chars().count() >= 3 && w.chars().next().is_some_and(char::is_uppercase))
+            .map(str::to_string)
+    );
+    names
+}
+
+/// Reload the names to mask from the user's facts
+pub fn refresh_names() {
+    let Ok(facts) = db::get_all_user_facts() else { return };
+    let mut names: Vec<String> = facts.iter()
+        .filter(|f| f.key.to_lowercase().contains("name"))
+        .flat_map(|f| names_in(&f.value))
+        .collect();
+    // Longest first, so "Ana Silva" is masked whole before "Ana"
+    names.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    names.dedup();
+    *NAMES.write().unwrap() = names;
+}
+
+/// The text with PII masked (unchanged when redaction is off)
+pub fn redact(text: &str) -> String {
+    if !is_enabled() {
+        return text.to_string();
+    }
+    let names = NAMES.read().unwrap();
+    redact_with(text, &names)
+}
+
+fn redact_with(text: &str, names: &[String]) -> String {
+    let text = mask_emails(text);
+    let text = mask_api_keys(&text);
+    let text = mask_phones(&text);
+    names.iter().fold(text, |text, name| mask_word(&text, name, "[name]"))
+}
+
+fn is_email_local(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._%+-".contains(c)
+}
+
+fn is_email_domain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '-'
+}
+
+fn mask_emails(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        let local_start = rest[..at].rfind(|c: char| !is_email_local(c)).map_or(0, |i| i + 1);
+        let domain_len = rest[at + 1..].find(|c: char| !is_email_domain(c)).unwrap_or(rest.len() - at - 1);
+        let domain = rest[at + 1..at + 1 + domain_len].trim_end_matches('.');
+        let tld_ok = domain.rsplit_once('.').is_some_and(|(host, tld)| {
+            !host.is_empty() && tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic())
+        });
+        if local_start < at && tld_ok {
+            out.push_str(&rest[..local_start]);
+            out.push_str("[email]");
+            rest = &rest[at + 1 + domain.len()..];
+        } else {
+            out.push_str(&rest[..=at]);
+            rest = &rest[at + 1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn mask_api_keys(text: &str) -> String {
+    let is_key_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(is_key_char) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = rest.find(|c: char| !is_key_char(c)).unwrap_or(rest.len());
+        let token = &rest[..len];
+        let prefixed = token.len() >= MIN_KEY_LEN && KEY_PREFIXES.iter().any(|p| token.starts_with(p));
+        if prefixed || looks_like_secret(token) {
+            out.push_str("[api-key]");
+        } else {
+            out.push_str(token);
+        }
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A token with no known key prefix that still reads like a secret: long, upper and lower case
+/// with digits, and close to random. Ids (UUIDs, hex hashes) and identifiers fall short.
+fn looks_like_secret(token: &str) -> bool {
+    if token.len() < MIN_SECRET_LEN {
+        return false;
+    }
+    let has = |class: fn(&char) -> bool| token.chars().any(|c| class(&c));
+    if !(has(char::is_ascii_uppercase) && has(char::is_ascii_lowercase) && has(char::is_ascii_digit)) {
+        return false;
+    }
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in token.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let len = token.len() as f64;
+    let entropy: f64 = counts.values().map(|&n| {
+        let p = n as f64 / len;
+        -p * p.log2()
+    }).sum();
+    entropy >= MIN_SECRET_ENTROPY
+}
+
+/// Digit runs that look like phone numbers; dates, times and ids next to letters are left alone
+fn mask_phones(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let starts = (chars[i] == '+' || chars[i] == '(' || chars[i].is_ascii_digit())
+            && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '-' || chars[i - 1] == '.'));
+        if !starts {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        // Extend over digits and short runs of separators ("555-0134", "(415) 555")
+        let mut end = i + 1;
+        while end < chars.len() {
+            if chars[end].is_ascii_digit() {
+                end += 1;
+                continue;
+            }
+            let separators = chars[end..].iter().take_while(|c| " -.()".contains(**c)).count();
+            if separators <= 2 && chars.get(end + separators).is_some_and(char::is_ascii_digit) {
+                end += separators;
+            } else {
+                break;
+            }
+        }
+        let run: String = chars[i..end].iter().collect();
+        let digits = run.chars().filter(char::is_ascii_digit).count();
+        let groups: Vec<usize> = run.split(|c: char| !c.is_ascii_digit())
+            .filter(|g| !g.is_empty())
+            .map(str::len)
+            .collect();
+        let date_like = groups.len() >= 3 && groups[..3] == [4, 2, 2];
+        let next = chars.get(end).copied();
+        let bounded = next.is_none_or(|c| !(c.is_alphanumeric() || c == ':'));
+        let long_enough = if run.starts_with('+') { digits >= 8 } else { digits >= 10 };
+        if long_enough && digits <= 15 && bounded && !date_like {
+            out.push_str("[phone]");
+        } else {
+            out.push_str(&run);
+        }
+        i = end;
+    }
+    out
+}
+
+/// Replace whole-word occurrences of `word`, matching case so "Will" the name leaves "will" alone
+fn mask_word(text: &str, word: &str, mask: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prev: Option<char> = None;
+    let mut i = 0;
+    while i < text.len() {
+        let at_boundary = prev.is_none_or(|c| !c.is_alphanumeric());
+        if at_boundary
+            && text[i..].starts_with(word)
+            && text[i + word.len()..].chars().next().is_none_or(|c| !c.is_alphanumeric())
+        {
+            out.push_str(mask);
+            prev = mask.chars().last();
+            i += word.len();
+            continue;
+        }
+        let c = text[i..].chars().next().unwrap();
+        out.push(c);
+        prev = Some(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_contact_details_and_keys() {
+        let text = "User message: mail me at ana.silva+work@example.co.uk or call +1 (415) 555-0134, key sk-ant-REDACTED";
+        assert_eq!(
+            redact_with(text, &[]),
+            "User message: mail me at [email] or call [phone], key [api-key]"
+        );
+        // Timestamps, ids and counts stay readable
+        let log = "Saved 12 facts at 2026-03-10 12:00:00 for conversation=550e8400 (1234567 bytes) to x@y";
+        assert_eq!(redact_with(log, &[]), log);
+    }
+
+    #[test]
+    fn masks_unprefixed_secrets_but_not_ids() {
+        assert_eq!(
+            redact_with("webhook secret Zx9Qp2LmT7vR4sKd8YwB3nHc saved", &[]),
+            "webhook secret [api-key] saved"
+        );
+        let log = "conversation=550e8400-e29b-41d4-a716-446655440000 in FlushPendingMessagesInternal2 (my-long-config_value-2024-Final)";
+        assert_eq!(redact_with(log, &[]), log);
+    }
+
+    #[test]
+    fn masks_names_from_facts_as_whole_words() {
+        let mut names = names_in("Ana Silva");
+        names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+        assert_eq!(
+            redact_with("Talked about Ana and Ana Silva, not Anastasia", &names),
+            "Talked about [name] and [name], not Anastasia"
+        );
+        // Only the name as written: ordinary words that happen to match stay
+        assert_eq!(
+            redact_with("Grace said grace; Mark will mark it", &names_in("Grace Mark")),
+            "[name] said grace; [name] will mark it"
+        );
+        assert!(names_in("she moved to Lisbon last spring after the divorce was final").is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//! Retries for model API requests
+//!
+//! Every chat completion and embedding request goes through `send`, which retries transient
+//! failures (rate limits, overloaded or failing servers, timeouts, dropped connections) with
+//! exponential backoff and jitter. A 429/503 that carries `Retry-After` waits as long as the
+//! server asks (up to MAX_RETRY_AFTER_SECS). The policy is stored in app_settings as `llm_retry`.
+//! Retries are logged; what's left after the last attempt is returned to the caller unchanged.
+
+use crate::db;
+use crate::logging;
+use rand::Rng;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const POLICY_SETTING: &str = "llm_retry";
+/// Longer Retry-After waits are capped; past this the caller is better off failing over
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,  // Total tries including the first; 1 disables retries
+    pub base_delay_ms: u64, // Delay before the first retry, doubled on each later one
+    pub max_delay_ms: u64,  // Ceiling for the backoff delay
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay_ms: 500, max_delay_ms: 8000 }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (1-based): exponential, capped, with jitter
+    /// in the upper half so concurrent agents don't retry in lockstep
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay_ms.saturating_mul(1u64 << (retry - 1).min(16));
+        let capped = exponential.min(self.max_delay_ms).max(1);
+        let jittered = rand::rng().random_range(capped / 2..=capped);
+        Duration::from_millis(jittered)
+    }
+}
+
+pub fn get_policy() -> RetryPolicy {
+    db::get_setting(POLICY_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_policy(policy: &RetryPolicy) -> Result<(), String> {
+    if policy.max_attempts == 0 || policy.max_attempts > 10 {
+        return Err("Attempts must be between 1 and 10".to_string());
+    }
+    if policy.max_delay_ms < policy.base_delay_ms {
+        return Err("The maximum delay can't be shorter than the base delay".to_string());
+    }
+    let json = serde_json::to_string(policy).map_err(|e| e.to_string())?;
+    db::set_setting(POLICY_SETTING, &json).map_err(|e| e.to_string())
+}
+
+/// 429, 529 (Anthropic "overloaded") and server-side failures are worth another try
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.is_request()
+}
+
+/// How long the server asked us to wait: `retry-after-ms` (OpenAI) or `Retry-After` in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    let wait = if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        Duration::from_millis(ms.max(0.0) as u64)
+    } else {
+        Duration::from_secs_f64(header("retry-after")?.parse::<f64>().ok()?.max(0.0))
+    };
+    Some(wait.min(Duration::from_secs(MAX_RETRY_AFTER_SECS)))
+}
+
+/// Send a request, retrying transient failures under the configured policy.
+/// `label` names the call in the logs (e.g. "OpenAI chat"). A request whose body can't be
+/// cloned (streams) is sent once.
+pub async fn send(label: &str, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    let policy = get_policy();
+    let mut attempt = 1;
+    loop {
+        let retry = (attempt < policy.max_attempts).then(|| request.try_clone()).flatten();
+        let Some(next) = retry else {
+            return request.send().await;
+        };
+
+        let (wait, reason) = match next.send().await {
+            Ok(response) if is_retryable_status(response.status()) => {
+                let wait = retry_after(&response).unwrap_or_else(|| policy.backoff(attempt));
+                (wait, format!("HTTP {}", response.status().as_u16()))
+            }
+            Ok(response) => return Ok(response),
+            Err(e) if is_retryable_error(&e) => (policy.backoff(attempt), e.to_string()),
+            Err(e) => return Err(e),
+        };
+
+        logging::log_error(None, &format!(
+            "{} failed ({}), retrying in {:.1}s (attempt {}/{})",
+            label, reason, wait.as_secs_f64(), attempt + 1, policy.max_attempts
+        ));
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        let cases = [
+            (408, true), (429, true), (500, true), (502, true), (503, true), (504, true), (529, true),
+            (400, false), (401, false), (403, false), (404, false), (413, false), (422, false), (501, false),
+        ];
+        for (code, retryable) in cases {
+            assert_eq!(is_retryable_status(StatusCode::from_u16(code).unwrap()), retryable, "{}", code);
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_within_the_cap() {
+        let policy = RetryPolicy { max_attempts: 10, base_delay_ms: 500, max_delay_ms: 8000 };
+        // (retry, ceiling before jitter)
+        let cases = [(1, 500), (2, 1000), (3, 2000), (4, 4000), (5, 8000), (6, 8000), (40, 8000)];
+        for (retry, ceiling) in cases {
+            for _ in 0..50 {
+                let wait = policy.backoff(retry).as_millis() as u64;
+                assert!((ceiling / 2..=ceiling).contains(&wait), "retry {} waited {}ms", retry, wait);
+            }
+        }
+
+        // A zero delay still gives the jitter a non-empty range
+        let zero = RetryPolicy { max_attempts: 3, base_delay_ms: 0, max_delay_ms: 0 };
+        assert!(zero.backoff(1) <= Duration::from_millis(1));
+    }
+
+    #[test]
+    fn policies_are_validated() {
+        let invalid = [
+            RetryPolicy { max_attempts: 0, base_delay_ms: 500, max_delay_ms: 8000 },
+            RetryPolicy { max_attempts: 11, base_delay_ms: 500, max_delay_ms: 8000 },
+            RetryPolicy { max_attempts: 3, base_delay_ms: 5000, max_delay_ms: 1000 },
+        ];
+        for policy in invalid {
+            assert!(set_policy(&policy).is_err(), "{:?}", policy);
+        }
+    }
+}
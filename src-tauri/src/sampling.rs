@@ -0,0 +1,83 @@
+//! Per-agent sampling for Intersect
+//!
+//! Each agent's reply temperature, with an optional override while it's in disco mode. The
+//! defaults match each voice: Snap loose and spontaneous, Dot precise, Puff in between.
+//! Stored in app_settings per agent as `agent_sampling.<agent>`.
+
+use crate::db;
+use crate::orchestrator::Agent;
+use serde::{Deserialize, Serialize};
+
+/// Every provider accepts 0-1 (OpenAI goes higher, Anthropic doesn't)
+pub const MAX_TEMPERATURE: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AgentSampling {
+    pub temperature: f32,
+    #[serde(default)]
+    pub disco_temperature: Option<f32>, // None = same as in normal mode
+}
+
+impl AgentSampling {
+    pub fn default_for(agent: Agent) -> Self {
+        let temperature = match agent {
+            Agent::Instinct => 0.8, // More intuitive, spontaneous
+            Agent::Logic => 0.4,    // More precise, structured
+            Agent::Psyche => 0.6,   // Balanced, introspective
+        };
+        Self { temperature, disco_temperature: None }
+    }
+
+    pub fn temperature(&self, is_disco: bool) -> f32 {
+        if is_disco {
+            self.disco_temperature.unwrap_or(self.temperature)
+        } else {
+            self.temperature
+        }
+    }
+}
+
+fn setting_key(agent: Agent) -> String {
+    format!("agent_sampling.{}", agent.as_str())
+}
+
+pub fn get(agent: Agent) -> AgentSampling {
+    db::get_setting(&setting_key(agent))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| AgentSampling::default_for(agent))
+}
+
+pub fn set(agent: Agent, sampling: &AgentSampling) -> Result<(), String> {
+    let in_range = |t: f32| (0.0..=MAX_TEMPERATURE).contains(&t);
+    if !in_range(sampling.temperature) || !sampling.disco_temperature.is_none_or(in_range) {
+        return Err(format!("Temperature must be between 0 and {}", MAX_TEMPERATURE));
+    }
+    let json = serde_json::to_string(sampling).map_err(|e| e.to_string())?;
+    db::set_setting(&setting_key(agent), &json).map_err(|e| e.to_string())
+}
+
+/// Back to the built-in temperatures; returns whether there was anything to clear
+pub fn reset(agent: Agent) -> Result<bool, String> {
+    db::delete_setting(&setting_key(agent)).map_err(|e| e.to_string())
+}
+
+/// The temperature for an agent's reply
+pub fn temperature(agent: Agent, is_disco: bool) -> f32 {
+    get(agent).temperature(is_disco)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disco_override_applies_only_in_disco() {
+        let mut dot = AgentSampling::default_for(Agent::Logic);
+        assert_eq!(dot.temperature(true), 0.4);
+        dot.disco_temperature = Some(0.9);
+        assert_eq!(dot.temperature(false), 0.4);
+        assert_eq!(dot.temperature(true), 0.9);
+    }
+}
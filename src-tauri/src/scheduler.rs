@@ -0,0 +1,496 @@
+//! Proactive scheduler for Intersect
+//!
+//! Handles:
+//! - Detecting follow-ups proposed during a conversation ("check in with me Friday")
+//! - A background tick that surfaces confirmed check-ins when they come due
+//! - Reminders agents set (the `set_reminder` tool), fired as native notifications when due
+//! - The evening journal prompt (see `journal`)
+//! - The notification center: persisted notifications emitted to the frontend
+//! - Theme spikes: a focused mini-report when a theme dominates recent conversations
+//! - A periodic (weekly by default) digest, stored as a report and raised as a notification
+
+use crate::anthropic::{AnthropicClient, AnthropicMessage, ThinkingBudget, CLAUDE_HAIKU, CLAUDE_SONNET};
+use crate::clock;
+use crate::db::{self, CheckIn, Notification, Reminder};
+use crate::journal;
+use crate::logging;
+use crate::usage;
+use crate::webhooks;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::Emitter;
+use tauri_plugin_notification::NotificationExt;
+
+const TICK_INTERVAL_SECS: u64 = 60;
+
+/// A theme spikes when it shows up in at least THEME_SPIKE_MIN of the last THEME_SPIKE_WINDOW conversations
+const THEME_SPIKE_WINDOW: usize = 6;
+const THEME_SPIKE_MIN: usize = 5;
+/// Don't report on the same theme more than once a week
+const THEME_REPORT_COOLDOWN_DAYS: i64 = 7;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+/// Start the background tick (idempotent - init_app may run more than once)
+pub fn start(app_handle: tauri::AppHandle) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let _ = APP_HANDLE.set(app_handle.clone());
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(TICK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            deliver_due_check_ins(&app_handle);
+            fire_due_reminders(&app_handle);
+            prompt_journal(&app_handle).await;
+            maybe_generate_digest().await;
+        }
+    });
+
+    logging::log_conversation(None, "Scheduler started");
+}
+
+/// Emit every scheduled check-in whose time has come, then mark it delivered
+fn deliver_due_check_ins(app_handle: &tauri::AppHandle) {
+    let now = clock::now().to_rfc3339();
+    let due = match db::get_due_check_ins(&now) {
+        Ok(due) => due,
+        Err(e) => {
+            logging::log_error(None, &format!("Scheduler failed to load check-ins: {}", e));
+            return;
+        }
+    };
+
+    for check_in in due {
+        if app_handle.emit("check-in-due", &check_in).is_ok() {
+            let _ = db::mark_check_in_delivered(&check_in.id);
+            logging::log_conversation(check_in.conversation_id.as_deref(), &format!(
+                "Check-in delivered: {}", check_in.topic
+            ));
+        }
+    }
+}
+
+// ============ Reminders ============
+
+fn reminder_title(reminder: &Reminder) -> String {
+    match reminder.agent.as_deref() {
+        Some("instinct") => "Reminder from Snap".to_string(),
+        Some("logic") => "Reminder from Dot".to_string(),
+        Some("psyche") => "Reminder from Puff".to_string(),
+        _ => "Reminder".to_string(),
+    }
+}
+
+/// Raise every pending reminder whose time has come (native notification plus the notification center)
+fn fire_due_reminders(app_handle: &tauri::AppHandle) {
+    let now = clock::now().to_rfc3339();
+    let due = match db::get_due_reminders(&now) {
+        Ok(due) => due,
+        Err(e) => {
+            logging::log_error(None, &format!("Scheduler failed to load reminders: {}", e));
+            return;
+        }
+    };
+
+    for reminder in due {
+        let title = reminder_title(&reminder);
+        if let Err(e) = app_handle.notification().builder().title(&title).body(&reminder.text).show() {
+            logging::log_error(None, &format!("Native notification failed: {}", e));
+        }
+        if let Err(e) = notify("reminder", Some(&reminder.id), &title, &reminder.text) {
+            logging::log_error(None, &format!("Failed to record reminder notification: {}", e));
+        }
+        let _ = db::mark_reminder_fired(&reminder.id);
+        let _ = app_handle.emit("reminder-due", &reminder);
+        logging::log_conversation(reminder.conversation_id.as_deref(), &format!("Reminder fired: {}", reminder.text));
+    }
+}
+
+/// Open tonight's journal when its time comes
+async fn prompt_journal(app_handle: &tauri::AppHandle) {
+    let Some(start) = journal::maybe_prompt_evening().await else { return };
+    let title = "Time to journal";
+    if let Err(e) = app_handle.notification().builder().title(title).body(&start.prompt).show() {
+        logging::log_error(None, &format!("Native notification failed: {}", e));
+    }
+    if let Err(e) = notify("journal", Some(&start.conversation_id), title, &start.prompt) {
+        logging::log_error(None, &format!("Failed to record journal notification: {}", e));
+    }
+    let _ = app_handle.emit("journal-prompt", &start);
+}
+
+// ============ Follow-up Detection ============
+
+/// Cheap pre-filter so we only ask the model when a follow-up is plausible
+fn mentions_follow_up(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    ["check in", "check back", "follow up", "follow-up", "remind me", "circle back", "touch base"]
+        .iter()
+        .any(|k| lower.contains(k))
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowUpDetection {
+    follow_up: bool,
+    topic: Option<String>,
+    due_at: Option<String>,
+}
+
+/// Look at one exchange and, if a follow-up was proposed, store it as a *proposed* check-in.
+/// Nothing is scheduled until the user confirms it.
+pub async fn propose_follow_up(
+    anthropic_key: &str,
+    conversation_id: &str,
+    user_message: &str,
+    responses: &[(String, String)],
+) -> Result<Option<CheckIn>, Box<dyn Error + Send + Sync>> {
+    let exchange_has_follow_up = mentions_follow_up(user_message)
+        || responses.iter().any(|(_, content)| mentions_follow_up(content));
+    if !exchange_has_follow_up {
+        return Ok(None);
+    }
+
+    let exchange = std::iter::once(format!("USER: {}", user_message))
+        .chain(responses.iter().map(|(agent, content)| format!("{}: {}", agent.to_uppercase(), content)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let system_prompt = format!(r#"You detect follow-up check-ins agreed or proposed in a conversation.
+
+The current local time is {}.
+
+If the user asks to be checked in with, or an agent offers a specific check-in, respond with:
+{{"follow_up": true, "topic": "short description", "due_at": "RFC 3339 timestamp with offset"}}
+Pick a sensible time of day (e.g. 10:00) when only a day is given. If no specific follow-up is proposed:
+{{"follow_up": false, "topic": null, "due_at": null}}
+
+Respond with ONLY valid JSON."#, clock::now_local().to_rfc3339());
+
+    let client = AnthropicClient::new(anthropic_key);
+    let response = client.chat_completion_advanced(
+        CLAUDE_HAIKU,
+        Some(&system_prompt),
+        vec![AnthropicMessage { role: "user".to_string(), content: exchange }],
+        0.0,
+        Some(150),
+        ThinkingBudget::None
+    ).await?;
+
+    let cleaned = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_end_matches("```")
+        .trim();
+
+    let detection: FollowUpDetection = match serde_json::from_str(cleaned) {
+        Ok(d) => d,
+        Err(_) => return Ok(None),
+    };
+    if !detection.follow_up {
+        return Ok(None);
+    }
+
+    let (Some(topic), Some(due_at)) = (detection.topic, detection.due_at) else {
+        return Ok(None);
+    };
+    let Ok(due) = chrono::DateTime::parse_from_rfc3339(&due_at) else {
+        return Ok(None);
+    };
+
+    let check_in = db::create_check_in(
+        Some(conversation_id),
+        &topic,
+        &due.with_timezone(&Utc).to_rfc3339(),
+        "proposed",
+    )?;
+    Ok(Some(check_in))
+}
+
+// ============ Notification Center ============
+
+/// Persist a notification and push it to the frontend.
+/// Stored even if the app window isn't listening, so nothing is lost.
+pub fn notify(kind: &str, subject: Option<&str>, title: &str, body: &str) -> Result<Notification, rusqlite::Error> {
+    let notification = db::create_notification(kind, subject, title, body)?;
+    if let Some(app_handle) = APP_HANDLE.get() {
+        let _ = app_handle.emit("notification", &notification);
+    }
+    logging::log_conversation(None, &format!("Notification raised ({}): {}", kind, title));
+    Ok(notification)
+}
+
+// ============ Theme Spikes ============
+
+/// Unmuted themes present in at least THEME_SPIKE_MIN of the last THEME_SPIKE_WINDOW conversations,
+/// paired with the matching conversation IDs
+fn find_theme_spikes() -> rusqlite::Result<Vec<(String, Vec<String>)>> {
+    let recent: Vec<String> = db::get_recent_conversations(THEME_SPIKE_WINDOW)?
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    if recent.len() < THEME_SPIKE_WINDOW {
+        return Ok(Vec::new());
+    }
+
+    let mut spikes = Vec::new();
+    for theme in db::get_all_recurring_themes()? {
+        let related: Vec<String> = theme.related_conversations
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default();
+        let hits: Vec<String> = recent.iter()
+            .filter(|id| related.contains(id))
+            .cloned()
+            .collect();
+        if hits.len() >= THEME_SPIKE_MIN {
+            spikes.push((theme.theme, hits));
+        }
+    }
+    Ok(spikes)
+}
+
+fn theme_on_cooldown(theme: &str) -> bool {
+    let last = db::get_last_notification_at("theme_spike", theme).ok().flatten();
+    last.and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
+        .map(|ts| clock::now().signed_duration_since(ts.with_timezone(&Utc)).num_days() < THEME_REPORT_COOLDOWN_DAYS)
+        .unwrap_or(false)
+}
+
+/// Check for spiking themes (run after a conversation is finalized) and raise a
+/// mini-report for each one instead of waiting for a periodic digest
+pub async fn check_theme_spikes(anthropic_key: &str) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let mut raised = 0;
+    for (theme, conversation_ids) in find_theme_spikes()? {
+        if theme_on_cooldown(&theme) {
+            continue;
+        }
+
+        let report = generate_theme_report(anthropic_key, &theme, &conversation_ids).await?;
+        notify(
+            "theme_spike",
+            Some(&theme),
+            &format!("\"{}\" keeps coming up", theme),
+            &report,
+        )?;
+        raised += 1;
+    }
+    Ok(raised)
+}
+
+async fn generate_theme_report(
+    anthropic_key: &str,
+    theme: &str,
+    conversation_ids: &[String],
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let summaries: Vec<String> = conversation_ids.iter()
+        .filter_map(|id| db::get_conversation_summary(id).ok().flatten())
+        .map(|s| format!("- {}", s.summary))
+        .collect();
+
+    let system_prompt = r#"You are the Governor, writing a short proactive note for the user.
+
+A theme has come up in most of their recent conversations. Write a focused mini-report on it:
+- 3 to 4 sentences, plain prose, no headers or bullet points
+- What keeps surfacing about this theme, and how it seems to be shifting
+- End with one gentle question or suggestion worth sitting with
+- Only use the summaries provided; never invent details
+- When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")"#;
+
+    let user_content = format!(
+        "Theme: {}\nMentioned in {} of the last {} conversations.\n\nConversation summaries:\n{}",
+        theme,
+        conversation_ids.len(),
+        THEME_SPIKE_WINDOW,
+        if summaries.is_empty() { "(no summaries available)".to_string() } else { summaries.join("\n") }
+    );
+
+    let client = AnthropicClient::new(anthropic_key);
+    let report = client.chat_completion_advanced(
+        CLAUDE_SONNET,
+        Some(system_prompt),
+        vec![AnthropicMessage { role: "user".to_string(), content: user_content }],
+        0.6,
+        Some(300),
+        ThinkingBudget::None
+    ).await?;
+
+    Ok(report.trim().to_string())
+}
+
+// ============ Weekly Digest ============
+
+const DIGEST_KIND: &str = "weekly_digest";
+const DIGEST_SETTING: &str = "weekly_digest";
+/// After a failed digest, wait this long before trying again rather than retrying every tick
+const DIGEST_RETRY_HOURS: i64 = 6;
+
+/// When the tick may next attempt a digest after a failure (None = no failure pending)
+static DIGEST_RETRY_AT: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    pub interval_days: i64, // How often a digest is written (7 = weekly)
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self { enabled: true, interval_days: 7 }
+    }
+}
+
+pub fn get_digest_settings() -> DigestSettings {
+    db::get_setting(DIGEST_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_digest_settings(settings: &DigestSettings) -> Result<(), String> {
+    if !(1..=31).contains(&settings.interval_days) {
+        return Err("The digest interval must be between 1 and 31 days".to_string());
+    }
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(DIGEST_SETTING, &json).map_err(|e| e.to_string())
+}
+
+/// Called from the tick: write a digest once the interval since the last one has passed
+/// and there was something to digest
+async fn maybe_generate_digest() {
+    let settings = get_digest_settings();
+    if !settings.enabled {
+        return;
+    }
+    let now = clock::now();
+    let interval = chrono::Duration::days(settings.interval_days);
+    let last_end = db::get_last_report_period_end(DIGEST_KIND).ok().flatten()
+        .and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
+        .map(|ts| ts.with_timezone(&Utc));
+    if last_end.is_some_and(|end| now - end < interval) {
+        return;
+    }
+    if DIGEST_RETRY_AT.lock().ok().and_then(|at| *at).is_some_and(|at| now < at) {
+        return;
+    }
+    if usage::skip_optional_calls("anthropic") {
+        return;
+    }
+    let Some(anthropic_key) = db::get_user_profile().ok().and_then(|p| p.anthropic_key) else { return };
+
+    // Cover the time since the last digest, but never more than one interval
+    let since = last_end.map_or(now - interval, |end| end.max(now - interval));
+    match generate_digest(&anthropic_key, &since.to_rfc3339(), &now.to_rfc3339()).await {
+        Ok(Some(report)) => logging::log_memory(None, &format!("Digest written: {}", report.title)),
+        Ok(None) => {}
+        Err(e) => {
+            if let Ok(mut at) = DIGEST_RETRY_AT.lock() {
+                *at = Some(now + chrono::Duration::hours(DIGEST_RETRY_HOURS));
+            }
+            logging::log_error(None, &format!("Digest failed, retrying in {}h: {}", DIGEST_RETRY_HOURS, e));
+        }
+    }
+}
+
+/// How each persona's weights moved over the period, for personas whose weights changed
+fn describe_weight_movement(since: &str, until: &str) -> Vec<String> {
+    let profiles = db::get_all_persona_profiles().unwrap_or_default();
+    profiles.iter().filter_map(|profile| {
+        let activity = db::get_persona_activity(&profile.id).ok()?;
+        let in_period: Vec<_> = activity.weight_history.iter()
+            .filter(|w| w.recorded_at.as_str() >= since && w.recorded_at.as_str() < until)
+            .collect();
+        let (first, last) = (in_period.first()?, in_period.last()?);
+        if in_period.len() < 2 {
+            return None;
+        }
+        Some(format!(
+            "{}: Logic {:.0}% -> {:.0}%, Instinct {:.0}% -> {:.0}%, Psyche {:.0}% -> {:.0}%",
+            profile.name,
+            first.logic_weight * 100.0, last.logic_weight * 100.0,
+            first.instinct_weight * 100.0, last.instinct_weight * 100.0,
+            first.psyche_weight * 100.0, last.psyche_weight * 100.0
+        ))
+    }).collect()
+}
+
+/// Write a digest of [since, until) from conversation summaries, newly learned facts and weight
+/// movement, store it as a report and raise a notification (in-app and native).
+/// Returns None if nothing happened.
+pub async fn generate_digest(
+    anthropic_key: &str,
+    since: &str,
+    until: &str,
+) -> Result<Option<db::Report>, Box<dyn Error + Send + Sync>> {
+    let summaries = db::get_summaries_between(since, until)?;
+    if summaries.is_empty() {
+        return Ok(None);
+    }
+    let facts = db::get_facts_learned_between(since, until)?;
+    let movement = describe_weight_movement(since, until);
+
+    let summaries_text = summaries.iter()
+        .map(|s| {
+            let topics: Vec<String> = serde_json::from_str(&s.key_topics).unwrap_or_default();
+            let tone = s.emotional_tone.as_deref().map(|t| format!(" Tone: {}.", t)).unwrap_or_default();
+            format!("- {} (topics: {}){}", s.summary, topics.join(", "), tone)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let facts_text = if facts.is_empty() {
+        "(none)".to_string()
+    } else {
+        facts.iter().map(|f| format!("- {}: {}", f.key, f.value)).collect::<Vec<_>>().join("\n")
+    };
+    let movement_text = if movement.is_empty() { "(no change)".to_string() } else { movement.join("\n") };
+
+    let system_prompt = r#"You are the Governor of Intersect, writing the user's periodic digest.
+
+Write a short digest of their recent conversations:
+- 4 to 6 sentences of plain prose, addressed to the user as "you"; no headers or bullet points
+- What they spent their time on, what seemed to shift, and anything newly learned about them
+- If their weights moved, say in plain words which way of thinking they leaned on more
+- End with one thing worth carrying into the next stretch
+- Only use what's provided; never invent details
+- When using dashes for pauses or asides, ALWAYS use double dashes with spaces: " -- " (not " - ")"#;
+
+    let user_content = format!(
+        "CONVERSATIONS ({}):\n{}\n\nNEWLY LEARNED:\n{}\n\nWEIGHT MOVEMENT:\n{}",
+        summaries.len(), summaries_text, facts_text, movement_text
+    );
+
+    let client = AnthropicClient::new(anthropic_key);
+    let body = client.chat_completion_advanced(
+        CLAUDE_SONNET,
+        Some(system_prompt),
+        vec![AnthropicMessage { role: "user".to_string(), content: user_content }],
+        0.6,
+        Some(400),
+        ThinkingBudget::None
+    ).await?;
+    let body = body.trim().to_string();
+
+    let interval_days = get_digest_settings().interval_days;
+    let title = if interval_days == 7 {
+        "Your week in review".to_string()
+    } else {
+        format!("Your last {} days in review", interval_days)
+    };
+    let report = db::save_report(DIGEST_KIND, &title, &body, since, until)?;
+    notify(DIGEST_KIND, Some(&report.id), &title, &body)?;
+    if let Some(app_handle) = APP_HANDLE.get() {
+        if let Err(e) = app_handle.notification().builder().title(&title).body(&body).show() {
+            logging::log_error(None, &format!("Native notification failed: {}", e));
+        }
+    }
+    webhooks::emit("report.generated", serde_json::json!(report));
+    Ok(Some(report))
+}
@@ -0,0 +1,191 @@
+//! Web search grounding for Intersect
+//!
+//! Optional: with a search provider and key set, factual questions (see
+//! `decide_search_heuristic` in the orchestrator) are searched before the primary agent answers,
+//! and the top results are passed to that agent in a note marked as externally sourced.
+//! - "brave" uses the Brave Search API (`X-Subscription-Token`)
+//! - "serpapi" uses SerpAPI's Google engine
+//!
+//! Settings are stored in app_settings as `web_search`; with no provider it's off.
+
+use crate::db;
+use crate::links::html_to_text;
+use crate::logging;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+const SETTINGS_KEY: &str = "web_search";
+pub const SEARCH_PROVIDERS: [&str; 2] = ["brave", "serpapi"];
+/// Search runs before the primary response, so a slow provider is dropped rather than waited on
+const SEARCH_TIMEOUT_SECS: u64 = 6;
+const MAX_RESULTS_LIMIT: usize = 10;
+const MAX_SNIPPET_CHARS: usize = 400;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SearchSettings {
+    pub provider: Option<String>, // "brave" | "serpapi"; None = off
+    pub api_key: Option<String>,
+    pub max_results: usize,       // Results passed to the agent, 1-10
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self { provider: None, api_key: None, max_results: 3 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+pub type SearchFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, String>> + Send + 'a>>;
+
+/// A web search backend
+pub trait SearchProvider: Send + Sync {
+    fn search<'a>(&'a self, client: &'a Client, query: &'a str, count: usize) -> SearchFuture<'a>;
+}
+
+struct Brave {
+    api_key: String,
+}
+
+impl SearchProvider for Brave {
+    fn search<'a>(&'a self, client: &'a Client, query: &'a str, count: usize) -> SearchFuture<'a> {
+        Box::pin(async move {
+            let response = client.get("https://api.search.brave.com/res/v1/web/search")
+                .header("X-Subscription-Token", &self.api_key)
+                .header("Accept", "application/json")
+                .query(&[("q", query), ("count", &count.to_string())])
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let body = read_json(response).await?;
+            Ok(collect_results(&body["web"]["results"], "url", "description"))
+        })
+    }
+}
+
+struct SerpApi {
+    api_key: String,
+}
+
+impl SearchProvider for SerpApi {
+    fn search<'a>(&'a self, client: &'a Client, query: &'a str, count: usize) -> SearchFuture<'a> {
+        Box::pin(async move {
+            let response = client.get("https://serpapi.com/search.json")
+                .query(&[("engine", "google"), ("q", query), ("num", &count.to_string()), ("api_key", &self.api_key)])
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let body = read_json(response).await?;
+            Ok(collect_results(&body["organic_results"], "link", "snippet"))
+        })
+    }
+}
+
+async fn read_json(response: reqwest::Response) -> Result<Value, String> {
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Search API error ({}): {}", status, text.chars().take(200).collect::<String>()));
+    }
+    response.json().await.map_err(|e| e.to_string())
+}
+
+/// Results from a JSON array of {title, <url_field>, <snippet_field>} (snippets may hold markup)
+fn collect_results(items: &Value, url_field: &str, snippet_field: &str) -> Vec<SearchResult> {
+    items.as_array()
+        .map(|items| items.iter()
+            .filter_map(|item| {
+                let url = item[url_field].as_str()?.to_string();
+                let title = html_to_text(item["title"].as_str().unwrap_or(&url)).1;
+                let snippet: String = html_to_text(item[snippet_field].as_str().unwrap_or("")).1
+                    .chars().take(MAX_SNIPPET_CHARS).collect();
+                Some(SearchResult { title, url, snippet })
+            })
+            .collect())
+        .unwrap_or_default()
+}
+
+pub fn get_settings() -> SearchSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_settings(settings: &SearchSettings) -> Result<(), String> {
+    let api_key = settings.api_key.as_deref().map(str::trim).filter(|k| !k.is_empty()).map(str::to_string);
+    if let Some(provider) = &settings.provider {
+        if !SEARCH_PROVIDERS.contains(&provider.as_str()) {
+            return Err(format!("Unknown search provider: {}", provider));
+        }
+        if api_key.is_none() {
+            return Err(format!("An API key is needed for {}", provider));
+        }
+    }
+    if !(1..=MAX_RESULTS_LIMIT).contains(&settings.max_results) {
+        return Err(format!("Max results must be between 1 and {}", MAX_RESULTS_LIMIT));
+    }
+    let settings = SearchSettings { api_key, ..settings.clone() };
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+fn provider_for(settings: &SearchSettings) -> Option<Box<dyn SearchProvider>> {
+    let api_key = settings.api_key.clone()?;
+    match settings.provider.as_deref()? {
+        "brave" => Some(Box::new(Brave { api_key })),
+        "serpapi" => Some(Box::new(SerpApi { api_key })),
+        _ => None,
+    }
+}
+
+/// Run a search, keeping at most `max_results` results
+async fn search(provider: &dyn SearchProvider, settings: &SearchSettings, query: &str) -> Result<Vec<SearchResult>, String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(SEARCH_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut results = provider.search(&client, query, settings.max_results).await?;
+    results.truncate(settings.max_results);
+    Ok(results)
+}
+
+/// Prompt note with search results for `query`, or None when search is off, fails or finds nothing
+pub async fn grounding_note(conversation_id: &str, query: &str) -> Option<String> {
+    let settings = get_settings();
+    let provider = provider_for(&settings)?;
+    let results = match search(provider.as_ref(), &settings, query).await {
+        Ok(results) if !results.is_empty() => results,
+        Ok(_) => return None,
+        Err(e) => {
+            logging::log_error(Some(conversation_id), &format!("Web search failed: {}", e));
+            return None;
+        }
+    };
+    logging::log_routing(Some(conversation_id), &format!(
+        "Web search for \"{}\": {} result(s)", query, results.len()
+    ));
+
+    let listed = results.iter()
+        .enumerate()
+        .map(|(i, r)| format!("{}. {} ({})\n{}", i + 1, r.title, r.url, r.snippet))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(format!(
+        "EXTERNALLY SOURCED -- WEB SEARCH RESULTS (from a search engine, not from the user or your own knowledge; \
+they may be incomplete or wrong. Use them for facts the question depends on, say when you're relying on them, \
+and name the source):\n{}",
+        listed
+    ))
+}
@@ -0,0 +1,412 @@
+//! Semantic search for Intersect
+//!
+//! Finds things by meaning rather than exact words ("my sister's wedding" also finds "Maya's big day"):
+//! - Messages, facts and summaries are embedded with OpenAI embeddings and stored in the `embeddings` table
+//! - Indexing is incremental: only new or edited sources are embedded, in small batches
+//! - Search embeds the query and ranks every stored vector by cosine similarity (a local brute-force scan
+//!   is plenty for one person's history). It doesn't wait for indexing: a catch-up pass starts in the
+//!   background and whatever is indexed so far is searched
+//! - Routing compares a user message against per-agent exemplar centroids, so paraphrases still reach
+//!   the right agent (see `agent_affinity`)
+//! - Ingested document chunks are indexed alongside, and the closest ones ground agent replies
+//!   (see `document_snippets`)
+
+use crate::clock;
+use crate::db::{self, EmbeddingSource};
+use crate::logging;
+use crate::openai::{OpenAIClient, EMBEDDING_MODEL};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::error::Error;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const EMBED_BATCH_SIZE: usize = 64;
+/// Cap per indexing pass so a large backlog doesn't hold up a search
+const MAX_EMBEDS_PER_PASS: usize = 512;
+/// Embeddings are truncated to this many characters (well under the model's token limit)
+const MAX_EMBED_CHARS: usize = 8000;
+/// Hits below this similarity are noise
+const MIN_SIMILARITY: f32 = 0.25;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticHit {
+    pub source_type: String,        // "message" | "fact" | "summary" | "document"
+    pub source_id: String,
+    pub conversation_id: Option<String>,
+    pub conversation_title: Option<String>,
+    pub content: String,
+    pub score: f32,
+}
+
+/// Cosine similarity of two vectors (0.0 if either is empty or they differ in length)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+fn embed_text(content: &str) -> String {
+    content.chars().take(MAX_EMBED_CHARS).collect()
+}
+
+/// Set while a background indexing pass started by `search` is running
+static BACKGROUND_INDEXING: AtomicBool = AtomicBool::new(false);
+
+/// Embed new and edited sources (up to MAX_EMBEDS_PER_PASS) and drop stale vectors; returns how many were embedded
+pub async fn index_pending(openai_key: &str) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let client = OpenAIClient::new(openai_key);
+    index_pending_with(|inputs| {
+        let client = &client;
+        async move { client.embed(&inputs).await }
+    }).await
+}
+
+async fn index_pending_with<F, Fut>(embed: F) -> Result<usize, Box<dyn Error + Send + Sync>>
+where
+    F: Fn(Vec<String>) -> Fut,
+    Fut: Future<Output = Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>>>,
+{
+    let pruned = db::prune_embeddings(EMBEDDING_MODEL)?;
+    if pruned > 0 {
+        logging::log_memory(None, &format!("Pruned {} stale embeddings", pruned));
+    }
+
+    let mut embedded = 0;
+    while embedded < MAX_EMBEDS_PER_PASS {
+        let batch: Vec<EmbeddingSource> = db::get_unembedded_sources(EMBEDDING_MODEL, EMBED_BATCH_SIZE)?;
+        if batch.is_empty() {
+            break;
+        }
+
+        let inputs: Vec<String> = batch.iter().map(|s| embed_text(&s.content)).collect();
+        let vectors = embed(inputs).await?;
+        for (source, vector) in batch.iter().zip(&vectors) {
+            db::save_embedding(source, EMBEDDING_MODEL, vector)?;
+        }
+        embedded += batch.len();
+    }
+
+    if embedded > 0 {
+        logging::log_memory(None, &format!("Embedded {} sources for semantic search", embedded));
+    }
+    Ok(embedded)
+}
+
+/// Start a catch-up indexing pass in the background, unless one is already running
+fn spawn_background_index(openai_key: &str) {
+    if BACKGROUND_INDEXING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let openai_key = openai_key.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = index_pending(&openai_key).await {
+            logging::log_error(None, &format!("Semantic indexing failed: {}", e));
+        }
+        BACKGROUND_INDEXING.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Stored vectors at least MIN_SIMILARITY to the query, the `limit` closest first
+fn rank(query_vector: &[f32], embeddings: Vec<db::StoredEmbedding>, limit: usize) -> Vec<(f32, EmbeddingSource)> {
+    let mut scored: Vec<(f32, EmbeddingSource)> = embeddings
+        .into_iter()
+        .map(|e| (cosine_similarity(query_vector, &e.vector), e.source))
+        .filter(|(score, _)| *score >= MIN_SIMILARITY)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(limit);
+    scored
+}
+
+/// The `limit` closest indexed sources to the query. Sources not indexed yet are embedded in the
+/// background, so a search right after a large import doesn't wait for them.
+pub async fn search(openai_key: &str, query: &str, limit: usize) -> Result<Vec<SemanticHit>, Box<dyn Error + Send + Sync>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    spawn_background_index(openai_key);
+
+    let client = OpenAIClient::new(openai_key);
+    let query_vector = client.embed(&[embed_text(query)]).await?
+        .into_iter()
+        .next()
+        .ok_or("No embedding returned for query")?;
+
+    let scored = rank(&query_vector, db::get_embeddings(EMBEDDING_MODEL, None)?, limit);
+
+    let hits = scored.into_iter()
+        .map(|(score, source)| {
+            let conversation_title = source.conversation_id.as_deref()
+                .and_then(|id| db::get_conversation(id).ok().flatten())
+                .and_then(|c| c.title);
+            SemanticHit {
+                source_type: source.source_type,
+                source_id: source.source_id,
+                conversation_id: source.conversation_id,
+                conversation_title,
+                content: source.content,
+                score,
+            }
+        })
+        .collect();
+    Ok(hits)
+}
+
+// ============ Embedding Routing ============
+
+/// Routing waits at most this long for the message embedding before falling back to keywords
+const ROUTING_EMBED_TIMEOUT_SECS: u64 = 3;
+/// After a failed or slow embedding call, turns route by keywords alone for this long
+const ROUTING_EMBED_BACKOFF_SECS: i64 = 300;
+
+/// Phrases typical of what each agent is best at; their mean embedding is the agent's centroid
+const AGENT_EXEMPLARS: [(&str, &[&str]); 3] = [
+    ("logic", &[
+        "Help me plan out the steps for this project",
+        "What are the pros and cons of each option?",
+        "Can you break this problem down for me?",
+        "I need to compare these two offers and decide",
+        "Why isn't this working and how do I fix it?",
+        "Walk me through how this system works",
+        "Help me figure out a budget",
+        "What's the most efficient way to approach this?",
+    ]),
+    ("instinct", &[
+        "Should I just go for it?",
+        "Give me the short version, what would you do?",
+        "I have a feeling something is off here",
+        "I need to make a call right now",
+        "My gut says no but I can't explain why",
+        "Cut to the chase",
+        "Honestly, is this a good idea or not?",
+        "I keep overthinking, just tell me what to do",
+    ]),
+    ("psyche", &[
+        "I've been feeling really down lately",
+        "Why do I keep doing this to myself?",
+        "I'm anxious about how my family sees me",
+        "Things with my partner have been hard",
+        "I don't know what I want out of life anymore",
+        "It hurt more than I expected when they said that",
+        "I feel like I'm not good enough",
+        "What does it mean that this bothers me so much?",
+    ]),
+];
+
+/// (agent, mean exemplar embedding)
+type AgentCentroids = Vec<(String, Vec<f32>)>;
+
+/// Agent centroids, computed once per run (None until the first successful embedding call)
+static AGENT_CENTROIDS: Lazy<tokio::sync::Mutex<Option<AgentCentroids>>> =
+    Lazy::new(|| tokio::sync::Mutex::new(None));
+
+/// Until when embedding routing is skipped after a failure
+static ROUTING_BACKOFF_UNTIL: Lazy<std::sync::Mutex<Option<DateTime<Utc>>>> =
+    Lazy::new(|| std::sync::Mutex::new(None));
+
+fn routing_backed_off() -> bool {
+    ROUTING_BACKOFF_UNTIL.lock().ok().and_then(|until| *until).is_some_and(|until| clock::now() < until)
+}
+
+fn back_off_routing() {
+    if let Ok(mut until) = ROUTING_BACKOFF_UNTIL.lock() {
+        *until = Some(clock::now() + chrono::Duration::seconds(ROUTING_EMBED_BACKOFF_SECS));
+    }
+}
+
+async fn agent_centroids(openai_key: &str) -> Result<AgentCentroids, Box<dyn Error + Send + Sync>> {
+    let mut cached = AGENT_CENTROIDS.lock().await;
+    if let Some(centroids) = cached.as_ref() {
+        return Ok(centroids.clone());
+    }
+
+    let client = OpenAIClient::new(openai_key);
+    let mut centroids = Vec::with_capacity(AGENT_EXEMPLARS.len());
+    for (agent, phrases) in AGENT_EXEMPLARS {
+        let inputs: Vec<String> = phrases.iter().map(|p| p.to_string()).collect();
+        let vectors = client.embed(&inputs).await?;
+        let dims = vectors.first().map(|v| v.len()).unwrap_or(0);
+        let mut centroid = vec![0.0f32; dims];
+        for vector in &vectors {
+            for (c, v) in centroid.iter_mut().zip(vector) {
+                *c += v / vectors.len() as f32;
+            }
+        }
+        centroids.push((agent.to_string(), centroid));
+    }
+
+    *cached = Some(centroids.clone());
+    logging::log_routing(None, "Agent routing centroids computed");
+    Ok(centroids)
+}
+
+/// Similarity of a user message to each agent's centroid, or None if embeddings are unavailable
+/// (no key, API failure or timeout) so routing falls back to keywords
+pub async fn agent_affinity(openai_key: &str, user_message: &str) -> Option<Vec<(String, f32)>> {
+    if openai_key.trim().is_empty() || routing_backed_off() {
+        return None;
+    }
+    let compute = async {
+        let centroids = agent_centroids(openai_key).await?;
+        let client = OpenAIClient::new(openai_key);
+        let message_vector = client.embed(&[embed_text(user_message)]).await?
+            .into_iter()
+            .next()
+            .ok_or("No embedding returned for message")?;
+        let affinity: Vec<(String, f32)> = centroids.into_iter()
+            .map(|(agent, centroid)| {
+                let score = cosine_similarity(&message_vector, &centroid);
+                (agent, score)
+            })
+            .collect();
+        Ok::<_, Box<dyn Error + Send + Sync>>(affinity)
+    };
+
+    match tokio::time::timeout(Duration::from_secs(ROUTING_EMBED_TIMEOUT_SECS), compute).await {
+        Ok(Ok(affinity)) => Some(affinity),
+        Ok(Err(e)) => {
+            back_off_routing();
+            logging::log_error(None, &format!(
+                "Embedding routing unavailable, using keywords for {}s: {}", ROUTING_EMBED_BACKOFF_SECS, e
+            ));
+            None
+        }
+        Err(_) => {
+            back_off_routing();
+            logging::log_routing(None, &format!(
+                "Embedding routing timed out, using keywords for {}s", ROUTING_EMBED_BACKOFF_SECS
+            ));
+            None
+        }
+    }
+}
+
+// ============ Document Grounding ============
+
+/// Document chunks must be at least this close to a message to be quoted to agents
+const MIN_DOCUMENT_SIMILARITY: f32 = 0.4;
+/// Grounding waits at most this long for the message's embedding before going without
+const DOCUMENT_GROUNDING_TIMEOUT_SECS: u64 = 5;
+
+/// The ingested document chunks closest to a message, best first. Empty when nothing is close
+/// enough, or when embeddings are unavailable or slow. Only chunks already indexed are searched:
+/// they're embedded when the document is added, or by the indexing pass after each conversation.
+pub async fn document_snippets(openai_key: &str, user_message: &str, limit: usize) -> Vec<(db::DocumentChunk, f32)> {
+    let compute = async {
+        let client = OpenAIClient::new(openai_key);
+        let message_vector = client.embed(&[embed_text(user_message)]).await?
+            .into_iter()
+            .next()
+            .ok_or("No embedding returned for message")?;
+        let mut scored: Vec<(f32, String)> = db::get_embeddings(EMBEDDING_MODEL, Some("document"))?
+            .into_iter()
+            .map(|e| (cosine_similarity(&message_vector, &e.vector), e.source.source_id))
+            .filter(|(score, _)| *score >= MIN_DOCUMENT_SIMILARITY)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(limit);
+        Ok::<_, Box<dyn Error + Send + Sync>>(scored)
+    };
+
+    let scored = match tokio::time::timeout(Duration::from_secs(DOCUMENT_GROUNDING_TIMEOUT_SECS), compute).await {
+        Ok(Ok(scored)) => scored,
+        Ok(Err(e)) => {
+            logging::log_error(None, &format!("Document grounding unavailable: {}", e));
+            return Vec::new();
+        }
+        Err(_) => {
+            logging::log_memory(None, "Document grounding timed out");
+            return Vec::new();
+        }
+    };
+    scored.into_iter()
+        .filter_map(|(score, id)| {
+            let chunk = db::get_document_chunk(id.parse().ok()?).ok().flatten()?;
+            Some((chunk, score))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{fresh_db, message};
+
+    fn stored(source_id: &str, vector: Vec<f32>) -> db::StoredEmbedding {
+        db::StoredEmbedding {
+            source: EmbeddingSource {
+                source_type: "message".to_string(),
+                source_id: source_id.to_string(),
+                conversation_id: None,
+                content: source_id.to_string(),
+            },
+            vector,
+        }
+    }
+
+    #[test]
+    fn ranking_keeps_the_closest_above_the_floor() {
+        let embeddings = vec![
+            stored("orthogonal", vec![0.0, 1.0]),
+            stored("close", vec![0.9, 0.1]),
+            stored("exact", vec![2.0, 0.0]),
+            stored("opposite", vec![-1.0, 0.0]),
+            stored("near", vec![0.7, 0.7]),
+        ];
+        let ranked = rank(&[1.0, 0.0], embeddings.clone(), 10);
+        let ids: Vec<&str> = ranked.iter().map(|(_, s)| s.source_id.as_str()).collect();
+        assert_eq!(ids, vec!["exact", "close", "near"]);
+        assert!((ranked[0].0 - 1.0).abs() < 1e-6);
+
+        let top = rank(&[1.0, 0.0], embeddings, 1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].1.source_id, "exact");
+    }
+
+    #[test]
+    fn cosine_similarity_handles_mismatched_and_zero_vectors() {
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert!((cosine_similarity(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn indexing_embeds_only_new_and_edited_sources() {
+        let _db = fresh_db();
+        db::create_conversation("c", false).unwrap();
+        let mut first = message("c", "user", "How do I plan the move?", "2025-01-31T09:00:00+00:00");
+        db::save_message(&first).unwrap();
+        db::save_message(&message("c", "logic", "Start with a moving checklist.", "2025-01-31T09:00:01+00:00")).unwrap();
+        db::save_message(&message("c", "user", "ok", "2025-01-31T09:00:02+00:00")).unwrap(); // Too short to index
+
+        let calls = std::sync::Mutex::new(Vec::new());
+        let fake_embed = |inputs: Vec<String>| {
+            calls.lock().unwrap().push(inputs.clone());
+            async move { Ok(inputs.iter().map(|_| vec![1.0, 0.0]).collect()) }
+        };
+
+        let index = || tauri::async_runtime::block_on(index_pending_with(&fake_embed)).unwrap();
+
+        assert_eq!(index(), 2);
+        assert_eq!(index(), 0);
+        assert_eq!(db::get_embeddings(EMBEDDING_MODEL, Some("message")).unwrap().len(), 2);
+
+        first.content = "How do I plan the move to Denver?".to_string();
+        db::save_message(&first).unwrap();
+        assert_eq!(index(), 1);
+        assert_eq!(calls.lock().unwrap().last().unwrap(), &vec![first.content.clone()]);
+    }
+}
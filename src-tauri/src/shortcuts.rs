@@ -0,0 +1,274 @@
+//! `intersect://` URL scheme for macOS Shortcuts, Raycast and other automations
+//!
+//! - `intersect://send?text=...[&conversation_id=...]` sends a message (a new conversation
+//!   when no id is given); the callback gets `result` (the reply) and `conversation_id`
+//! - `intersect://new-disco[?text=...]` starts a Disco conversation, optionally with a first message
+//! - `intersect://governor-report[?profile_id=...]` writes a Governor report; the callback gets `result`
+//!
+//! Any action takes x-callback-url parameters: `x-success` is opened with the results appended,
+//! `x-error` with `errorMessage`. Callbacks must use an app scheme (`shortcuts://`, `raycast://`);
+//! web and file URLs are rejected so a link on a web page can't send replies or memory off-device.
+//! Any page can open an `intersect://` link, so every action waits for the user to confirm it.
+//! URLs that arrive before the app has initialized (a cold launch from a link) are held until
+//! `start` runs.
+
+use crate::logging;
+use crate::orchestrator::Agent;
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+pub const SCHEME: &str = "intersect";
+/// Schemes a callback may not use: anything that would hand results to a web page or the filesystem
+const BLOCKED_CALLBACK_SCHEMES: [&str; 10] = ["http", "https", "ws", "wss", "ftp", "file", "data", "javascript", "blob", "about"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShortcutAction {
+    Send { text: String, conversation_id: Option<String> },
+    NewDisco { text: Option<String> },
+    GovernorReport { profile_id: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortcutRequest {
+    pub action: ShortcutAction,
+    pub x_success: Option<String>,
+    pub x_error: Option<String>,
+}
+
+/// Emitted as "shortcut-handled" so the window can open the conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutOutcome {
+    pub action: String,
+    pub conversation_id: Option<String>,
+    pub error: Option<String>,
+}
+
+// URLs received before init; None once the app is ready
+static PENDING: Lazy<Mutex<Option<Vec<String>>>> = Lazy::new(|| Mutex::new(Some(Vec::new())));
+
+/// Parse an `intersect://` URL into an action
+pub fn parse(url: &str) -> Result<ShortcutRequest, String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("Not an {}:// URL", SCHEME));
+    }
+    let param = |name: &str| url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    // `intersect://send` puts the action in the host; `intersect:///send` in the path
+    let action_name = url.host_str()
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| url.path().trim_matches('/').to_string());
+
+    let action = match action_name.as_str() {
+        "send" => ShortcutAction::Send {
+            text: param("text").ok_or("send needs a text parameter")?,
+            conversation_id: param("conversation_id"),
+        },
+        "new-disco" => ShortcutAction::NewDisco { text: param("text") },
+        "governor-report" => ShortcutAction::GovernorReport { profile_id: param("profile_id") },
+        other => return Err(format!("Unknown action: {}", other)),
+    };
+    let callback = |name: &str| -> Result<Option<String>, String> {
+        let Some(value) = param(name) else { return Ok(None) };
+        let scheme = Url::parse(&value).map_err(|_| format!("{} isn't a URL", name))?.scheme().to_string();
+        if scheme == SCHEME || BLOCKED_CALLBACK_SCHEMES.contains(&scheme.as_str()) {
+            return Err(format!("{} can't use a {}:// URL", name, scheme));
+        }
+        Ok(Some(value))
+    };
+    Ok(ShortcutRequest { action, x_success: callback("x-success")?, x_error: callback("x-error")? })
+}
+
+impl ShortcutAction {
+    /// What the confirmation dialog says the link wants to do
+    fn describe(&self) -> String {
+        match self {
+            ShortcutAction::Send { text, conversation_id } => {
+                let preview: String = text.chars().take(200).collect();
+                let target = if conversation_id.is_some() { "an existing conversation" } else { "a new conversation" };
+                format!("Send this message to {}?\n\n\"{}\"", target, preview)
+            }
+            ShortcutAction::NewDisco { text: Some(_) } => "Start a Disco conversation with a message?".to_string(),
+            ShortcutAction::NewDisco { text: None } => "Start a Disco conversation?".to_string(),
+            ShortcutAction::GovernorReport { .. } => "Write a Governor report?".to_string(),
+        }
+    }
+}
+
+/// Ask the user before acting on a link; false if they decline
+async fn confirm(app_handle: &tauri::AppHandle, request: &ShortcutRequest) -> bool {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+    let mut message = request.action.describe();
+    if let Some(callback) = &request.x_success {
+        message.push_str(&format!("\n\nThe result will be sent to {}", callback));
+    }
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app_handle.dialog()
+        .message(message)
+        .title("A link wants to use Intersect")
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Cancel".to_string()))
+        .show(move |allowed| {
+            let _ = tx.send(allowed);
+        });
+    rx.await.unwrap_or(false)
+}
+
+/// Handle URLs held since launch and everything that arrives from now on
+pub fn start(app_handle: tauri::AppHandle) {
+    let held = PENDING.lock().unwrap().take().unwrap_or_default();
+    for url in held {
+        spawn_handle(app_handle.clone(), url);
+    }
+}
+
+/// Entry point for URLs the OS opens us with (RunEvent::Opened, macOS only)
+#[cfg_attr(not(any(target_os = "macos", target_os = "ios")), allow(dead_code))]
+pub fn open_urls(app_handle: &tauri::AppHandle, urls: Vec<String>) {
+    {
+        let mut pending = PENDING.lock().unwrap();
+        if let Some(held) = pending.as_mut() {
+            held.extend(urls);
+            return;
+        }
+    }
+    for url in urls {
+        spawn_handle(app_handle.clone(), url);
+    }
+}
+
+fn spawn_handle(app_handle: tauri::AppHandle, url: String) {
+    tauri::async_runtime::spawn(async move {
+        handle(&app_handle, &url).await;
+    });
+}
+
+async fn handle(app_handle: &tauri::AppHandle, url: &str) {
+    let request = match parse(url) {
+        Ok(request) => request,
+        Err(e) => {
+            logging::log_error(None, &format!("Ignored {} URL: {}", SCHEME, e));
+            return;
+        }
+    };
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    if !confirm(app_handle, &request).await {
+        logging::log_conversation(None, "Shortcut action declined");
+        return;
+    }
+
+    let (action, result) = match request.action {
+        ShortcutAction::Send { text, conversation_id } => ("send", send(app_handle, conversation_id, text, false).await),
+        ShortcutAction::NewDisco { text } => ("new-disco", new_disco(app_handle, text).await),
+        ShortcutAction::GovernorReport { profile_id } => (
+            "governor-report",
+            crate::generate_governor_report(profile_id).await.map(|report| (None, Some(report))),
+        ),
+    };
+    logging::log_conversation(None, &format!("Shortcut action {} ({})", action, if result.is_ok() { "ok" } else { "failed" }));
+
+    let (outcome, callback) = match result {
+        Ok((conversation_id, reply)) => {
+            let mut params = Vec::new();
+            if let Some(reply) = reply {
+                params.push(("result", reply));
+            }
+            if let Some(id) = &conversation_id {
+                params.push(("conversation_id", id.clone()));
+            }
+            (
+                ShortcutOutcome { action: action.to_string(), conversation_id, error: None },
+                request.x_success.and_then(|url| callback_url(&url, &params)),
+            )
+        }
+        Err(e) => {
+            logging::log_error(None, &format!("Shortcut action {} failed: {}", action, e));
+            let callback = request.x_error.and_then(|url| callback_url(&url, &[("errorMessage", e.clone())]));
+            (ShortcutOutcome { action: action.to_string(), conversation_id: None, error: Some(e) }, callback)
+        }
+    };
+    let _ = app_handle.emit("shortcut-handled", &outcome);
+
+    if let Some(callback) = callback {
+        if let Err(e) = app_handle.opener().open_url(callback, None::<&str>) {
+            logging::log_error(None, &format!("Couldn't open shortcut callback: {}", e));
+        }
+    }
+}
+
+/// (conversation id, reply text)
+type ActionResult = Result<(Option<String>, Option<String>), String>;
+
+async fn send(app_handle: &tauri::AppHandle, conversation_id: Option<String>, text: String, disco: bool) -> ActionResult {
+    let conversation_id = match conversation_id {
+        Some(id) => id,
+        None => crate::create_conversation(disco)?.id,
+    };
+    // A Disco conversation is game mode: every agent in disco
+    let disco_agents: Vec<String> = if disco {
+        [Agent::Instinct, Agent::Logic, Agent::Psyche].iter().map(|a| a.as_str().to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    let result = crate::send_message(app_handle.clone(), conversation_id.clone(), text, None, disco_agents, None, None, None).await?;
+    let reply = result.governor_response.clone().unwrap_or_else(|| {
+        result.responses.iter().map(|r| r.content.as_str()).collect::<Vec<_>>().join("\n\n")
+    });
+    Ok((Some(conversation_id), Some(reply).filter(|r| !r.is_empty())))
+}
+
+async fn new_disco(app_handle: &tauri::AppHandle, text: Option<String>) -> ActionResult {
+    match text {
+        Some(text) => send(app_handle, None, text, true).await,
+        None => Ok((Some(crate::create_conversation(true)?.id), None)),
+    }
+}
+
+/// The callback URL with `params` appended, or None if it isn't a URL
+fn callback_url(base: &str, params: &[(&str, String)]) -> Option<String> {
+    let mut url = Url::parse(base).ok()?;
+    {
+        let mut query = url.query_pairs_mut();
+        for (name, value) in params {
+            query.append_pair(name, value);
+        }
+    }
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_actions_and_callbacks() {
+        let request = parse("intersect://send?text=hello%20there&x-success=raycast%3A%2F%2Fdone").unwrap();
+        assert_eq!(request.action, ShortcutAction::Send { text: "hello there".to_string(), conversation_id: None });
+        assert_eq!(request.x_success.as_deref(), Some("raycast://done"));
+        assert_eq!(parse("intersect:///new-disco").unwrap().action, ShortcutAction::NewDisco { text: None });
+        assert_eq!(
+            parse("intersect://governor-report?profile_id=p1").unwrap().action,
+            ShortcutAction::GovernorReport { profile_id: Some("p1".to_string()) }
+        );
+        assert!(parse("intersect://send").is_err());
+        assert!(parse("intersect://launch-missiles").is_err());
+        assert!(parse("https://send?text=hi").is_err());
+        assert!(parse("intersect://send?text=hi&x-success=https%3A%2F%2Fevil.example%2F").is_err());
+        assert!(parse("intersect://governor-report?x-error=file%3A%2F%2F%2Ftmp%2Fx").is_err());
+        assert_eq!(
+            callback_url("shortcuts://x-callback-url/done?a=1", &[("result", "a b".to_string())]).as_deref(),
+            Some("shortcuts://x-callback-url/done?a=1&result=a+b")
+        );
+    }
+}
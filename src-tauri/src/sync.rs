@@ -0,0 +1,772 @@
+//! End-to-end encrypted sync for Intersect
+//!
+//! Lets two Macs share one memory through storage the user picks: a folder (e.g. in iCloud Drive),
+//! an S3-compatible bucket, or a WebDAV server. Each device uploads one encrypted snapshot of its
+//! conversations, facts and persona profiles (`devices/<device id>.bin`) and merges everyone
+//! else's into its own database (see the Sync section in db.rs for how conflicts resolve).
+//!
+//! - The key is derived from a passphrase with Argon2id; only the salt and a check value for
+//!   telling a wrong passphrase apart are stored remotely, in plain `sync.json`
+//! - Snapshots are XChaCha20-Poly1305 sealed (random nonce prefixed), so the backend only sees
+//!   ciphertext
+//! - A background tick syncs every `interval_minutes`; `sync_now` runs one on demand
+//! - Attachment files, settings and API keys aren't synced
+//!
+//! Settings are stored in app_settings as `cloud_sync`. The derived key and the backend's
+//! credentials are kept in the Keychain (see keychain.rs) so database backups and snapshots never
+//! carry them next to the ciphertext.
+
+use crate::clock;
+use crate::db::{self, SyncApplied, SyncData};
+use crate::keychain;
+use crate::logging;
+use crate::redact;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::{Client, Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::Emitter;
+
+const SETTINGS_KEY: &str = "cloud_sync";
+/// Where older versions kept the key; moved to the Keychain on first use
+const LEGACY_KEY_SETTING: &str = "cloud_sync_key";
+const FORMAT: &str = "intersect-sync";
+const FORMAT_VERSION: i64 = 1;
+/// Everything lives under this folder / key prefix on the backend
+const ROOT: &str = "intersect-sync";
+const CHECK_PLAINTEXT: &[u8] = b"intersect-sync-check";
+const NONCE_LEN: usize = 24;
+const MIN_PASSPHRASE_LEN: usize = 8;
+const MIN_INTERVAL_MINUTES: u64 = 5;
+const MAX_INTERVAL_MINUTES: u64 = 24 * 60;
+const REQUEST_TIMEOUT_SECS: u64 = 60;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+/// One sync at a time: a tick and a manual sync would merge the same snapshots twice
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Where the snapshots are kept
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncBackend {
+    /// A local folder another service syncs, e.g. iCloud Drive
+    Folder { path: String },
+    /// Any S3-compatible bucket (AWS, R2, B2, MinIO); path-style requests
+    S3 {
+        endpoint: String, // e.g. "https://s3.us-east-1.amazonaws.com"
+        region: String,
+        bucket: String,
+        access_key_id: String,
+        secret_access_key: String,
+    },
+    WebDav { url: String, username: String, password: String },
+}
+
+impl SyncBackend {
+    /// The same backend with credentials blanked, for showing in the UI
+    fn redacted(&self) -> Self {
+        match self.clone() {
+            SyncBackend::S3 { endpoint, region, bucket, access_key_id, .. } => {
+                SyncBackend::S3 { endpoint, region, bucket, access_key_id, secret_access_key: String::new() }
+            }
+            SyncBackend::WebDav { url, username, .. } => SyncBackend::WebDav { url, username, password: String::new() },
+            folder => folder,
+        }
+    }
+
+    /// The backend's secret (S3 secret key or WebDAV password), if it has one
+    fn credential(&self) -> Option<&str> {
+        let credential = match self {
+            SyncBackend::S3 { secret_access_key, .. } => secret_access_key,
+            SyncBackend::WebDav { password, .. } => password,
+            SyncBackend::Folder { .. } => return None,
+        };
+        Some(credential.as_str()).filter(|c| !c.is_empty())
+    }
+
+    fn with_credential(&self, credential: String) -> Self {
+        match self.clone() {
+            SyncBackend::S3 { endpoint, region, bucket, access_key_id, .. } => {
+                SyncBackend::S3 { endpoint, region, bucket, access_key_id, secret_access_key: credential }
+            }
+            SyncBackend::WebDav { url, username, .. } => SyncBackend::WebDav { url, username, password: credential },
+            folder => folder,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let missing = match self {
+            SyncBackend::Folder { path } => {
+                if !path.trim().is_empty() && !PathBuf::from(path).is_dir() {
+                    return Err(format!("Folder not found: {}", path));
+                }
+                path.trim().is_empty()
+            }
+            SyncBackend::S3 { endpoint, region, bucket, access_key_id, secret_access_key } => {
+                [endpoint, region, bucket, access_key_id, secret_access_key].iter().any(|v| v.trim().is_empty())
+            }
+            SyncBackend::WebDav { url, .. } => url.trim().is_empty(),
+        };
+        if missing {
+            return Err("Fill in every field for the sync backend".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SyncSettings {
+    pub enabled: bool,
+    pub backend: Option<SyncBackend>,
+    pub device_id: String,
+    pub device_name: String,
+    pub interval_minutes: u64,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+    pub last_pushed_hash: Option<String>, // Skips re-uploading an unchanged snapshot
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: None,
+            device_id: String::new(),
+            device_name: String::new(),
+            interval_minutes: 15,
+            last_synced_at: None,
+            last_error: None,
+            last_pushed_hash: None,
+        }
+    }
+}
+
+fn get_settings() -> SyncSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &SyncSettings) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// Settings for the UI, with backend credentials blanked
+pub fn get_status() -> SyncSettings {
+    let mut settings = get_settings();
+    settings.backend = settings.backend.map(|b| b.redacted());
+    settings.last_pushed_hash = None;
+    settings
+}
+
+/// How often the background sync runs
+pub fn set_interval(minutes: u64) -> Result<(), String> {
+    if !(MIN_INTERVAL_MINUTES..=MAX_INTERVAL_MINUTES).contains(&minutes) {
+        return Err(format!(
+            "Sync interval must be between {} and {} minutes", MIN_INTERVAL_MINUTES, MAX_INTERVAL_MINUTES
+        ));
+    }
+    let mut settings = get_settings();
+    settings.interval_minutes = minutes;
+    save_settings(&settings)
+}
+
+// ============ Encryption ============
+
+/// Plain metadata next to the snapshots: enough to derive the key and check a passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RemoteMeta {
+    format: String,
+    version: i64,
+    salt: String,  // base64
+    check: String, // base64 of CHECK_PLAINTEXT sealed with the key
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Couldn't derive the sync key: {}", e))?;
+    Ok(key)
+}
+
+/// nonce || ciphertext
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rng().fill_bytes(&mut nonce);
+    let ciphertext = XChaCha20Poly1305::new(key.into())
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "Encryption failed".to_string())?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < NONCE_LEN {
+        return Err("Sync data is truncated".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Couldn't decrypt sync data (wrong passphrase or corrupted file)".to_string())
+}
+
+fn new_meta(passphrase: &str) -> Result<(RemoteMeta, [u8; 32]), String> {
+    let mut salt = [0u8; 16];
+    rand::rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let meta = RemoteMeta {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        salt: b64.encode(salt),
+        check: b64.encode(seal(&key, CHECK_PLAINTEXT)?),
+    };
+    Ok((meta, key))
+}
+
+/// The key for existing sync data, if the passphrase is the one it was set up with
+fn unlock(meta: &RemoteMeta, passphrase: &str) -> Result<[u8; 32], String> {
+    if meta.format != FORMAT || meta.version > FORMAT_VERSION {
+        return Err(format!("Unsupported sync data (format {} v{})", meta.format, meta.version));
+    }
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let salt = b64.decode(&meta.salt).map_err(|e| e.to_string())?;
+    let check = b64.decode(&meta.check).map_err(|e| e.to_string())?;
+    let key = derive_key(passphrase, &salt)?;
+    match open(&key, &check) {
+        Ok(plaintext) if plaintext == CHECK_PLAINTEXT => Ok(key),
+        _ => Err("Wrong passphrase for the existing sync data".to_string()),
+    }
+}
+
+fn load_key() -> Result<[u8; 32], String> {
+    let hex = match keychain::read_secret(keychain::SYNC_KEY_ACCOUNT)? {
+        Some(hex) => hex,
+        None => {
+            let hex = db::get_setting(LEGACY_KEY_SETTING)
+                .map_err(|e| e.to_string())?
+                .ok_or("Sync isn't set up on this device")?;
+            keychain::write_secret(keychain::SYNC_KEY_ACCOUNT, &hex)?;
+            db::delete_setting(LEGACY_KEY_SETTING).map_err(|e| e.to_string())?;
+            hex
+        }
+    };
+    from_hex(&hex).try_into().map_err(|_| "Stored sync key is invalid; set up sync again".to_string())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
+
+/// Keep the backend's credential in the Keychain (hex, since passwords aren't shell-safe)
+fn store_credential(backend: &SyncBackend) -> Result<(), String> {
+    match backend.credential() {
+        Some(credential) => keychain::write_secret(keychain::SYNC_CREDENTIALS_ACCOUNT, &to_hex(credential.as_bytes())),
+        None => keychain::delete_secret(keychain::SYNC_CREDENTIALS_ACCOUNT),
+    }
+}
+
+/// The configured backend with its credential filled in from the Keychain. Settings from older
+/// versions that still hold the credential are moved over on first use.
+fn load_backend(backend: SyncBackend) -> Result<SyncBackend, String> {
+    if backend.credential().is_some() {
+        store_credential(&backend)?;
+        let mut settings = get_settings();
+        settings.backend = Some(backend.redacted());
+        save_settings(&settings)?;
+        return Ok(backend);
+    }
+    if matches!(backend, SyncBackend::Folder { .. }) {
+        return Ok(backend);
+    }
+    let credential = keychain::read_secret(keychain::SYNC_CREDENTIALS_ACCOUNT)?
+        .map(|hex| String::from_utf8(from_hex(&hex)).map_err(|_| "Stored sync credentials are invalid; set up sync again".to_string()))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(backend.with_credential(credential))
+}
+
+// ============ Backends ============
+
+fn client() -> Result<Client, String> {
+    Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn check_status(response: &reqwest::Response, what: &str) -> Result<(), String> {
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed: HTTP {}", what, response.status()))
+    }
+}
+
+impl SyncBackend {
+    /// Read a file under ROOT; None if it doesn't exist
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            SyncBackend::Folder { path } => {
+                let file = PathBuf::from(path).join(ROOT).join(name);
+                match tokio::fs::read(&file).await {
+                    Ok(bytes) => Ok(Some(bytes)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(format!("Couldn't read {}: {}", file.display(), e)),
+                }
+            }
+            SyncBackend::S3 { .. } | SyncBackend::WebDav { .. } => {
+                let response = self.request(Method::GET, &format!("{}/{}", ROOT, name), "", Vec::new()).await?;
+                if response.status() == StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                check_status(&response, "Download")?;
+                Ok(Some(response.bytes().await.map_err(|e| e.to_string())?.to_vec()))
+            }
+        }
+    }
+
+    /// Write a file under ROOT, creating folders as needed
+    async fn put(&self, name: &str, bytes: Vec<u8>) -> Result<(), String> {
+        match self {
+            SyncBackend::Folder { path } => {
+                let file = PathBuf::from(path).join(ROOT).join(name);
+                if let Some(parent) = file.parent() {
+                    tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+                }
+                // Write then rename, so a syncing folder never sees half a file
+                let partial = file.with_extension("partial");
+                tokio::fs::write(&partial, bytes).await.map_err(|e| e.to_string())?;
+                tokio::fs::rename(&partial, &file).await.map_err(|e| e.to_string())
+            }
+            SyncBackend::S3 { .. } => {
+                let response = self.request(Method::PUT, &format!("{}/{}", ROOT, name), "", bytes).await?;
+                check_status(&response, "Upload")
+            }
+            SyncBackend::WebDav { .. } => {
+                // Collections have to exist before files go in them
+                let file = format!("{}/{}", ROOT, name);
+                let parts: Vec<&str> = file.split('/').collect();
+                for depth in 1..parts.len() {
+                    let mkcol = Method::from_bytes(b"MKCOL").map_err(|e| e.to_string())?;
+                    // 405 means it's already there
+                    let response = self.request(mkcol, &format!("{}/", parts[..depth].join("/")), "", Vec::new()).await?;
+                    if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+                        check_status(&response, "Creating the sync folder")?;
+                    }
+                }
+                let response = self.request(Method::PUT, &file, "", bytes).await?;
+                check_status(&response, "Upload")
+            }
+        }
+    }
+
+    /// Names of the files in a folder under ROOT
+    async fn list(&self, dir: &str) -> Result<Vec<String>, String> {
+        let prefix = format!("{}/{}/", ROOT, dir);
+        let paths = match self {
+            SyncBackend::Folder { path } => {
+                let mut names = Vec::new();
+                let mut entries = match tokio::fs::read_dir(PathBuf::from(path).join(&prefix)).await {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+                    Err(e) => return Err(e.to_string()),
+                };
+                while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+                    names.push(entry.file_name().to_string_lossy().to_string());
+                }
+                names
+            }
+            SyncBackend::S3 { .. } => {
+                let query = format!("list-type=2&prefix={}", uri_encode(&prefix, true));
+                let response = self.request(Method::GET, "", &query, Vec::new()).await?;
+                check_status(&response, "Listing")?;
+                xml_values(&response.text().await.map_err(|e| e.to_string())?, "Key")
+            }
+            SyncBackend::WebDav { .. } => {
+                let propfind = Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?;
+                let response = self.request(propfind, &prefix, "", Vec::new()).await?;
+                if response.status() == StatusCode::NOT_FOUND {
+                    return Ok(Vec::new());
+                }
+                check_status(&response, "Listing")?;
+                xml_values(&response.text().await.map_err(|e| e.to_string())?, "href")
+            }
+        };
+        Ok(paths.iter()
+            .filter_map(|p| p.trim_end_matches('/').rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// An HTTP request to `path` on an S3 or WebDAV backend, signed or authenticated
+    async fn request(&self, method: Method, path: &str, query: &str, body: Vec<u8>) -> Result<reqwest::Response, String> {
+        let client = client()?;
+        let request = match self {
+            SyncBackend::S3 { endpoint, region, bucket, access_key_id, secret_access_key } => {
+                let canonical_uri = format!("/{}/{}", uri_encode(bucket, true), uri_encode(path, false));
+                let mut url = Url::parse(&format!("{}{}", endpoint.trim_end_matches('/'), canonical_uri))
+                    .map_err(|e| format!("Invalid S3 endpoint: {}", e))?;
+                if !query.is_empty() {
+                    url.set_query(Some(query));
+                }
+                let host = match (url.host_str(), url.port()) {
+                    (Some(host), Some(port)) => format!("{}:{}", host, port),
+                    (Some(host), None) => host.to_string(),
+                    (None, _) => return Err("Invalid S3 endpoint".to_string()),
+                };
+                let signed = sign_s3(&S3Request {
+                    method: method.as_str(),
+                    host: &host,
+                    canonical_uri: &canonical_uri,
+                    query,
+                    payload: &body,
+                    region,
+                    access_key_id,
+                    secret_access_key,
+                    now: clock::now(),
+                });
+                let mut request = client.request(method, url);
+                for (name, value) in signed {
+                    request = request.header(name, value);
+                }
+                request
+            }
+            SyncBackend::WebDav { url, username, password } => {
+                let mut request = client.request(method.clone(), format!("{}/{}", url.trim_end_matches('/'), path));
+                if !username.is_empty() {
+                    request = request.basic_auth(username, Some(password));
+                }
+                if method.as_str() == "PROPFIND" {
+                    request = request.header("Depth", "1");
+                }
+                request
+            }
+            SyncBackend::Folder { .. } => return Err("Folders aren't reached over HTTP".to_string()),
+        };
+        request.body(body).send().await.map_err(|e| format!("Sync request failed: {}", e))
+    }
+}
+
+/// Text of every `<name>` element, namespace prefix or not (enough for S3 listings and PROPFIND)
+fn xml_values(xml: &str, name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else { break };
+        let tag = &rest[..end];
+        let local = tag.rsplit(':').next().unwrap_or(tag);
+        rest = &rest[end + 1..];
+        if local.eq_ignore_ascii_case(name) {
+            if let Some(close) = rest.find("</") {
+                values.push(rest[..close].trim().to_string());
+            }
+        }
+    }
+    values
+}
+
+/// AWS-style percent encoding; '/' is kept in paths
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    value.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b'/' if !encode_slash => "/".to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+struct S3Request<'a> {
+    method: &'a str,
+    host: &'a str,
+    canonical_uri: &'a str,
+    query: &'a str, // Already canonical: encoded and sorted
+    payload: &'a [u8],
+    region: &'a str,
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    now: chrono::DateTime<chrono::Utc>,
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date);
+    let k_region = hmac_sha256(&k_date, region);
+    let k_service = hmac_sha256(&k_region, service);
+    hmac_sha256(&k_service, "aws4_request")
+}
+
+/// Signature V4 headers for an S3 request
+fn sign_s3(request: &S3Request) -> Vec<(&'static str, String)> {
+    let amz_date = request.now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = request.now.format("%Y%m%d").to_string();
+    let payload_hash = to_hex(&Sha256::digest(request.payload));
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        request.method, request.canonical_uri, request.query, request.host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, request.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date, scope, to_hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = to_hex(&hmac_sha256(
+        &signing_key(request.secret_access_key, &date, request.region, "s3"),
+        &string_to_sign,
+    ));
+    vec![
+        ("x-amz-date", amz_date),
+        ("x-amz-content-sha256", payload_hash),
+        ("Authorization", format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            request.access_key_id, scope, signed_headers, signature
+        )),
+    ]
+}
+
+// ============ Sync ============
+
+/// What one device uploads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    device_id: String,
+    device_name: String,
+    generated_at: String,
+    data: SyncData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncReport {
+    pub devices: Vec<String>, // Names of the other devices merged in
+    pub applied: SyncApplied,
+    pub uploaded: bool,       // False when nothing changed since the last upload
+    pub synced_at: String,
+}
+
+fn snapshot_name(device_id: &str) -> String {
+    format!("devices/{}.bin", device_id)
+}
+
+/// Turn sync on with a backend and passphrase. Joins existing sync data on the backend (the
+/// passphrase has to match) or starts it fresh.
+pub async fn configure(backend: SyncBackend, passphrase: &str, device_name: Option<String>) -> Result<SyncSettings, String> {
+    backend.validate()?;
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("Use a passphrase of at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+
+    let key = match backend.get("sync.json").await? {
+        Some(bytes) => {
+            let meta: RemoteMeta = serde_json::from_slice(&bytes)
+                .map_err(|e| format!("Unreadable sync.json on the backend: {}", e))?;
+            unlock(&meta, passphrase)?
+        }
+        None => {
+            let (meta, key) = new_meta(passphrase)?;
+            let json = serde_json::to_vec_pretty(&meta).map_err(|e| e.to_string())?;
+            backend.put("sync.json", json).await?;
+            key
+        }
+    };
+    keychain::write_secret(keychain::SYNC_KEY_ACCOUNT, &to_hex(&key))?;
+    db::delete_setting(LEGACY_KEY_SETTING).map_err(|e| e.to_string())?;
+    store_credential(&backend)?;
+
+    let mut settings = get_settings();
+    if settings.device_id.is_empty() {
+        settings.device_id = uuid::Uuid::new_v4().to_string();
+    }
+    settings.device_name = device_name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("Mac {}", &settings.device_id[..8]));
+    settings.enabled = true;
+    settings.backend = Some(backend.redacted());
+    settings.last_error = None;
+    settings.last_pushed_hash = None;
+    save_settings(&settings)?;
+    logging::log_conversation(None, &format!("Cloud sync set up as \"{}\"", settings.device_name));
+    Ok(get_status())
+}
+
+/// Stop syncing and forget the key; data on the backend is left alone
+pub fn disable() -> Result<(), String> {
+    let mut settings = get_settings();
+    settings.enabled = false;
+    settings.last_pushed_hash = None;
+    save_settings(&settings)?;
+    keychain::delete_secret(keychain::SYNC_KEY_ACCOUNT)?;
+    keychain::delete_secret(keychain::SYNC_CREDENTIALS_ACCOUNT)?;
+    db::delete_setting(LEGACY_KEY_SETTING).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Merge every other device's snapshot, then upload this device's
+pub async fn sync_now() -> Result<SyncReport, String> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A sync is already running".to_string());
+    }
+    let result = run().await;
+    RUNNING.store(false, Ordering::SeqCst);
+
+    let mut settings = get_settings();
+    match &result {
+        Ok(report) => {
+            settings.last_synced_at = Some(report.synced_at.clone());
+            settings.last_error = None;
+        }
+        Err(e) => settings.last_error = Some(e.clone()),
+    }
+    save_settings(&settings)?;
+    result
+}
+
+async fn run() -> Result<SyncReport, String> {
+    let settings = get_settings();
+    let backend = settings.backend.clone()
+        .filter(|_| settings.enabled)
+        .ok_or("Sync isn't set up on this device")?;
+    let backend = load_backend(backend)?;
+    let key = load_key()?;
+    let own = format!("{}.bin", settings.device_id);
+    let mut report = SyncReport::default();
+
+    for name in backend.list("devices").await? {
+        if name == own || !name.ends_with(".bin") {
+            continue;
+        }
+        let Some(sealed) = backend.get(&format!("devices/{}", name)).await? else { continue };
+        let snapshot: Snapshot = serde_json::from_slice(&open(&key, &sealed)?)
+            .map_err(|e| format!("Unreadable snapshot {}: {}", name, e))?;
+        let busy = crate::busy_conversations();
+        let applied = db::run_blocking(move || db::apply_sync_data(&snapshot.data, &busy).map(|a| (snapshot.device_name, a)))
+            .await?
+            .map_err(|e| e.to_string())?;
+        report.devices.push(applied.0);
+        report.applied.add(applied.1);
+    }
+
+    if !report.applied.is_empty() {
+        redact::refresh_names();
+    }
+
+    // Uploaded after merging, so the other devices converge on the same state
+    let data = db::run_blocking(db::export_sync_data).await?.map_err(|e| e.to_string())?;
+    let data_json = serde_json::to_vec(&data).map_err(|e| e.to_string())?;
+    let hash = to_hex(&Sha256::digest(&data_json));
+    if settings.last_pushed_hash.as_deref() != Some(hash.as_str()) {
+        let snapshot = Snapshot {
+            device_id: settings.device_id.clone(),
+            device_name: settings.device_name.clone(),
+            generated_at: clock::now().to_rfc3339(),
+            data,
+        };
+        let json = serde_json::to_vec(&snapshot).map_err(|e| e.to_string())?;
+        backend.put(&snapshot_name(&settings.device_id), seal(&key, &json)?).await?;
+        let mut updated = get_settings();
+        updated.last_pushed_hash = Some(hash);
+        save_settings(&updated)?;
+        report.uploaded = true;
+    }
+
+    report.synced_at = clock::now().to_rfc3339();
+    Ok(report)
+}
+
+/// Background sync on the configured interval; emits `sync-applied` when another device's
+/// changes came in so the UI can refresh
+pub fn start(app_handle: &tauri::AppHandle) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        // Let startup finish before the first sync
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        loop {
+            let settings = get_settings();
+            if settings.enabled {
+                match sync_now().await {
+                    Ok(report) if !report.applied.is_empty() => {
+                        logging::log_conversation(None, &format!(
+                            "Cloud sync merged changes from {}: {:?}", report.devices.join(", "), report.applied
+                        ));
+                        let _ = app_handle.emit("sync-applied", &report);
+                    }
+                    Ok(_) => {}
+                    Err(e) => logging::log_error(None, &format!("Cloud sync failed: {}", e)),
+                }
+            }
+            let minutes = settings.interval_minutes.clamp(MIN_INTERVAL_MINUTES, MAX_INTERVAL_MINUTES);
+            tokio::time::sleep(Duration::from_secs(minutes * 60)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_data_opens_only_with_the_right_passphrase() {
+        let (meta, key) = new_meta("correct horse battery").unwrap();
+        let sealed = seal(&key, b"hello").unwrap();
+        assert_eq!(open(&unlock(&meta, "correct horse battery").unwrap(), &sealed).unwrap(), b"hello");
+        assert!(unlock(&meta, "wrong passphrase").is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open(&key, &tampered).is_err());
+    }
+
+    #[test]
+    fn credentials_come_off_the_stored_backend() {
+        let webdav = SyncBackend::WebDav { url: "https://dav.example".into(), username: "me".into(), password: "p@ss w'rd".into() };
+        assert_eq!(webdav.credential(), Some("p@ss w'rd"));
+        assert_eq!(webdav.redacted().credential(), None);
+        assert_eq!(webdav.redacted().with_credential("p@ss w'rd".into()), webdav);
+        assert_eq!(String::from_utf8(from_hex(&to_hex("p@ss w'rd".as_bytes()))).unwrap(), "p@ss w'rd");
+        assert_eq!(SyncBackend::Folder { path: "/tmp".into() }.credential(), None);
+    }
+
+    #[test]
+    fn signing_key_matches_aws_reference() {
+        // From the AWS docs on deriving a Signature V4 signing key
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(to_hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn listings_are_read_from_s3_and_webdav_xml() {
+        let s3 = "<ListBucketResult><Contents><Key>intersect-sync/devices/a.bin</Key></Contents>\
+                  <Contents><Key>intersect-sync/devices/b.bin</Key></Contents></ListBucketResult>";
+        assert_eq!(xml_values(s3, "Key"), vec!["intersect-sync/devices/a.bin", "intersect-sync/devices/b.bin"]);
+        let dav = "<d:multistatus><d:response><d:href>/dav/intersect-sync/devices/</d:href></d:response>\
+                   <d:response><d:href>/dav/intersect-sync/devices/a.bin</d:href></d:response></d:multistatus>";
+        assert_eq!(xml_values(dav, "href").len(), 2);
+    }
+}
@@ -0,0 +1,44 @@
+//! Local token counting for Intersect
+//!
+//! OpenAI models are counted exactly with their tiktoken encoding. Anthropic doesn't publish
+//! Claude's tokenizer, so Claude is estimated from cl100k_base with a markup (Claude's tokenizer
+//! splits the same text into somewhat more tokens). Gemini, Ollama and unknown models get the
+//! Claude estimate too, which errs on the high side.
+
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton};
+
+/// Claude tokens per cl100k_base token, roughly
+const CLAUDE_MARKUP: f64 = 1.1;
+
+/// Estimated Claude tokens in a text (the default for agent calls)
+pub fn count(text: &str) -> usize {
+    if text.is_empty() {
+        return 0;
+    }
+    (cl100k_base_singleton().encode_ordinary(text).len() as f64 * CLAUDE_MARKUP).ceil() as usize
+}
+
+/// Tokens in a text for a specific model: exact for OpenAI models, estimated otherwise
+pub fn count_for_model(text: &str, model: &str) -> usize {
+    match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => o200k_base_singleton().encode_ordinary(text).len(),
+        Some(Tokenizer::Cl100kBase) => cl100k_base_singleton().encode_ordinary(text).len(),
+        _ => count(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_exact_for_openai_and_marked_up_for_claude() {
+        let text = "How do I stop procrastinating on the things that matter?";
+        let exact = count_for_model(text, "gpt-4o-mini");
+        assert!(exact > 5 && exact < 20);
+        assert!(count(text) >= count_for_model(text, "gpt-4"));
+        assert_eq!(count_for_model(text, "claude-sonnet-4-5"), count(text));
+        assert_eq!(count(""), 0);
+    }
+}
@@ -0,0 +1,430 @@
+//! Tools agents can call for Intersect
+//!
+//! Agents can look things up before they reply, through OpenAI function calling or Anthropic
+//! tool use (other providers answer without tools):
+//! - `search_memory`: the user's remembered facts and past messages (semantic with an OpenAI key)
+//! - `list_recent_conversations`: titles and summaries of recent conversations
+//! - `fetch_url`: the text of a web page (robots.txt respected, see `links`); off by default,
+//!   since a prompt injection could use it to send what `search_memory` found to another site
+//! - `date_math`: today's date, adding to a date, days between dates, weekdays
+//! - `set_reminder`: remind the user of something at a given time (fired by the scheduler)
+//!
+//! Tools from the user's MCP servers (see `mcp`) are offered alongside these.
+//!
+//! The Orchestrator runs the call/execute loop (`chat_with_tools`). Each tool can be turned off;
+//! flags are stored in app_settings as `agent_tools`. Built-in tools not listed are on except
+//! `fetch_url`; MCP tools can do anything their server allows, so each one stays off until the
+//! user turns it on.
+
+use crate::db;
+use crate::links;
+use crate::logging;
+use crate::mcp;
+use crate::openai::ChatMessage;
+use crate::semantic;
+use chrono::{DateTime, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const SETTINGS_KEY: &str = "agent_tools";
+/// Built-in tools that stay off until the user turns them on
+const OFF_BY_DEFAULT: [&str; 1] = ["fetch_url"];
+/// Tool output is cut here so one result can't crowd out the conversation
+const MAX_RESULT_CHARS: usize = 4000;
+
+/// Added to the system prompt when tools are offered
+pub const TOOL_GUIDANCE: &str = "TOOLS: You can call tools to look things up before replying (the user's memories, \
+recent conversations, a web page, date math). Only call one when your reply depends on it. Then reply as briefly as \
+you normally would, in your own voice -- don't narrate the lookup. When you give the user a deadline or they ask to \
+be reminded, set a reminder and mention it in a few words.";
+
+/// A tool as offered to the model: name, what it does, JSON Schema for its arguments
+#[derive(Debug, Clone)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+/// A call the model asked for
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// One round of calls and their results (results[i] answers calls[i])
+#[derive(Debug, Clone)]
+pub struct ToolRound {
+    pub calls: Vec<ToolCall>,
+    pub results: Vec<String>,
+}
+
+/// Who is calling a tool, for tools that record where they were used
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    pub conversation_id: Option<String>,
+    pub agent: String,
+}
+
+/// What a tool-enabled model call came back with
+pub enum ToolStep {
+    Reply(String),
+    Calls(Vec<ToolCall>),
+}
+
+/// The conversation so far for a tool-enabled call: plain turns, then the tool rounds
+pub struct ToolConversation<'a> {
+    pub turns: Vec<ChatMessage>,
+    pub rounds: &'a [ToolRound],
+    pub tools: &'a [ToolSpec],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ToolSettings {
+    pub enabled: HashMap<String, bool>, // Tool name -> on/off; missing = `on_by_default`
+}
+
+impl ToolSettings {
+    fn is_enabled(&self, name: &str) -> bool {
+        self.enabled.get(name).copied().unwrap_or_else(|| on_by_default(name))
+    }
+}
+
+/// Whether a tool the user hasn't switched either way is offered
+fn on_by_default(name: &str) -> bool {
+    !name.starts_with(mcp::TOOL_PREFIX) && !OFF_BY_DEFAULT.contains(&name)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+}
+
+/// Every built-in tool
+pub fn all_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec {
+            name: "search_memory".to_string(),
+            description: "Search what you know about the user: remembered facts and past messages from earlier conversations.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "query": { "type": "string", "description": "What to look for" } },
+                "required": ["query"]
+            }),
+        },
+        ToolSpec {
+            name: "list_recent_conversations".to_string(),
+            description: "List the user's most recent conversations with their titles and summaries.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "limit": { "type": "integer", "description": "How many (1-10, default 5)" } }
+            }),
+        },
+        ToolSpec {
+            name: "fetch_url".to_string(),
+            description: "Read the text of a web page.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "url": { "type": "string", "description": "http(s) URL" } },
+                "required": ["url"]
+            }),
+        },
+        ToolSpec {
+            name: "date_math".to_string(),
+            description: "Date arithmetic in the user's local calendar. operation: \"today\"; \"add\" (date plus days/weeks/months/years, \
+negative to subtract); \"difference\" (days from date to other_date); \"weekday\" (day of the week of date). Dates are YYYY-MM-DD; date defaults to today.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "operation": { "type": "string", "enum": ["today", "add", "difference", "weekday"] },
+                    "date": { "type": "string" },
+                    "other_date": { "type": "string" },
+                    "days": { "type": "integer" },
+                    "weeks": { "type": "integer" },
+                    "months": { "type": "integer" },
+                    "years": { "type": "integer" }
+                },
+                "required": ["operation"]
+            }),
+        },
+        ToolSpec {
+            name: "set_reminder".to_string(),
+            description: "Remind the user of something later with a notification, e.g. a deadline you agreed on. due_at is \
+an RFC 3339 timestamp or local \"YYYY-MM-DD HH:MM\" (a bare date means 9:00 that day) and must be in the future.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "What to remind them of, addressed to the user" },
+                    "due_at": { "type": "string" }
+                },
+                "required": ["text", "due_at"]
+            }),
+        },
+    ]
+}
+
+pub fn get_settings() -> ToolSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Built-in and MCP tools with each one's on/off state
+pub async fn list_tools() -> Vec<ToolInfo> {
+    let settings = get_settings();
+    all_tools().into_iter()
+        .chain(mcp::tool_specs().await)
+        .map(|tool| ToolInfo {
+            enabled: settings.is_enabled(&tool.name),
+            name: tool.name,
+            description: tool.description,
+        })
+        .collect()
+}
+
+pub fn set_tool_enabled(name: &str, enabled: bool) -> Result<(), String> {
+    if !name.starts_with(mcp::TOOL_PREFIX) && !all_tools().iter().any(|t| t.name == name) {
+        return Err(format!("Unknown tool: {}", name));
+    }
+    let mut settings = get_settings();
+    settings.enabled.insert(name.to_string(), enabled);
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// The tools agents are offered this turn
+pub async fn enabled_tools() -> Vec<ToolSpec> {
+    let settings = get_settings();
+    all_tools().into_iter()
+        .chain(mcp::tool_specs().await)
+        .filter(|tool| settings.is_enabled(&tool.name))
+        .collect()
+}
+
+fn str_arg<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, String> {
+    arguments[name].as_str()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+fn parse_date(arguments: &Value, name: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    match arguments[name].as_str() {
+        Some(date) => NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| format!("{} must be YYYY-MM-DD", name)),
+        None => Ok(today),
+    }
+}
+
+fn format_date(date: NaiveDate) -> String {
+    format!("{} ({})", date.format("%Y-%m-%d"), date.format("%A"))
+}
+
+/// date_math against a given "today"
+fn date_math(arguments: &Value, today: NaiveDate) -> Result<String, String> {
+    let date = parse_date(arguments, "date", today)?;
+    match arguments["operation"].as_str().unwrap_or("today") {
+        "today" => Ok(format_date(today)),
+        "weekday" => Ok(format_date(date)),
+        "add" => {
+            let arg = |name: &str| arguments[name].as_i64().unwrap_or(0);
+            let months = arg("months").saturating_add(arg("years").saturating_mul(12));
+            let days = arg("days").saturating_add(arg("weeks").saturating_mul(7));
+            let shifted = match u32::try_from(months.unsigned_abs()) {
+                Ok(n) if months >= 0 => date.checked_add_months(Months::new(n)),
+                Ok(n) => date.checked_sub_months(Months::new(n)),
+                Err(_) => None,
+            };
+            shifted
+                .and_then(|d| d.checked_add_signed(chrono::TimeDelta::try_days(days)?))
+                .map(format_date)
+                .ok_or_else(|| "Date out of range".to_string())
+        }
+        "difference" => {
+            let other = parse_date(arguments, "other_date", today)?;
+            let days = (other - date).num_days();
+            Ok(format!("{} days from {} to {} (about {} weeks)", days, format_date(date), format_date(other), days / 7))
+        }
+        other => Err(format!("Unknown operation: {}", other)),
+    }
+}
+
+/// When a reminder is due: RFC 3339, or local "YYYY-MM-DD HH:MM" / "YYYY-MM-DDTHH:MM" / "YYYY-MM-DD" (9:00)
+fn parse_due(value: &str, now: DateTime<Local>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    let due = match DateTime::parse_from_rfc3339(value) {
+        Ok(due) => due.with_timezone(&Utc),
+        Err(_) => {
+            let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+                .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+                .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default())))
+                .map_err(|_| "due_at must be RFC 3339 or YYYY-MM-DD HH:MM".to_string())?;
+            Local.from_local_datetime(&naive)
+                .earliest()
+                .ok_or("due_at doesn't exist in the local time zone")?
+                .with_timezone(&Utc)
+        }
+    };
+    if due <= now.with_timezone(&Utc) {
+        return Err(format!("due_at is in the past (it's {} now)", now.format("%Y-%m-%d %H:%M")));
+    }
+    Ok(due)
+}
+
+fn set_reminder(arguments: &Value, context: &ToolContext) -> Result<String, String> {
+    let text = str_arg(arguments, "text")?;
+    let due = parse_due(str_arg(arguments, "due_at")?, crate::clock::now_local())?;
+    let reminder = db::create_reminder(context.conversation_id.as_deref(), Some(&context.agent), text, &due.to_rfc3339())
+        .map_err(|e| e.to_string())?;
+    logging::log_agent(context.conversation_id.as_deref(), &format!(
+        "{} set a reminder for {}: {}", context.agent, reminder.due_at, reminder.text
+    ));
+    Ok(format!("Reminder set for {}: {}", due.with_timezone(&Local).format("%A %Y-%m-%d %H:%M"), text))
+}
+
+async fn search_memory(arguments: &Value) -> Result<String, String> {
+    let query = str_arg(arguments, "query")?;
+    let mut lines: Vec<String> = Vec::new();
+
+    // Semantic search covers facts, messages and summaries; keyword search is the fallback
+    if let Some(key) = db::get_user_profile().ok().and_then(|p| p.api_key) {
+        match semantic::search(&key, query, 6).await {
+            Ok(hits) => lines.extend(hits.into_iter().map(|hit| match hit.conversation_title {
+                Some(title) => format!("[{} in \"{}\"] {}", hit.source_type, title, hit.content),
+                None => format!("[{}] {}", hit.source_type, hit.content),
+            })),
+            Err(e) => logging::log_error(None, &format!("search_memory semantic search failed: {}", e)),
+        }
+    }
+    if lines.is_empty() {
+        let words: Vec<String> = query.to_lowercase().split_whitespace()
+            .filter(|w| w.len() > 2)
+            .map(str::to_string)
+            .collect();
+        let facts = db::get_all_user_facts().map_err(|e| e.to_string())?;
+        lines.extend(facts.iter()
+            .filter(|f| {
+                let text = format!("{} {}", f.key, f.value).to_lowercase();
+                words.iter().any(|w| text.contains(w))
+            })
+            .take(5)
+            .map(|f| format!("[fact] {}: {}", f.key, f.value)));
+        let hits = db::search_messages(query, 5).map_err(|e| e.to_string())?;
+        lines.extend(hits.into_iter().map(|hit| format!(
+            "[{} in \"{}\"] {}",
+            hit.role,
+            hit.conversation_title.unwrap_or_else(|| "Untitled".to_string()),
+            hit.snippet.replace("<mark>", "").replace("</mark>", "")
+        )));
+    }
+
+    if lines.is_empty() {
+        Ok("Nothing found.".to_string())
+    } else {
+        Ok(lines.join("\n"))
+    }
+}
+
+fn list_recent_conversations(arguments: &Value) -> Result<String, String> {
+    let limit = arguments["limit"].as_u64().unwrap_or(5).clamp(1, 10) as usize;
+    let conversations = db::get_recent_conversations(limit).map_err(|e| e.to_string())?;
+    if conversations.is_empty() {
+        return Ok("No conversations yet.".to_string());
+    }
+    Ok(conversations.iter()
+        .map(|c| format!(
+            "- {} ({}){}",
+            c.title.as_deref().unwrap_or("Untitled"),
+            c.updated_at.get(..10).unwrap_or(&c.updated_at),
+            c.summary.as_deref().map(|s| format!(": {}", s)).unwrap_or_default()
+        ))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+async fn fetch_url(arguments: &Value) -> Result<String, String> {
+    let (title, text) = links::fetch_text(str_arg(arguments, "url")?).await?;
+    Ok(match title {
+        Some(title) => format!("Title: {}\n\n{}", title, text),
+        None => text,
+    })
+}
+
+/// Run a tool call. Errors come back as text so the model can see what went wrong.
+/// Only tools offered for this turn run, whatever name the model comes up with.
+pub async fn execute(call: &ToolCall, offered: &[ToolSpec], context: &ToolContext) -> String {
+    if !offered.iter().any(|tool| tool.name == call.name) {
+        logging::log_agent(None, &format!("{} asked for {}, which wasn't offered", context.agent, call.name));
+        return "Error: Tool not available".to_string();
+    }
+    let result = match call.name.as_str() {
+        "search_memory" => search_memory(&call.arguments).await,
+        "list_recent_conversations" => list_recent_conversations(&call.arguments),
+        "fetch_url" => fetch_url(&call.arguments).await,
+        "date_math" => date_math(&call.arguments, crate::clock::now_local().date_naive()),
+        "set_reminder" => set_reminder(&call.arguments, context),
+        name if name.starts_with(mcp::TOOL_PREFIX) => mcp::call_tool(name, &call.arguments).await,
+        other => Err(format!("Unknown tool: {}", other)),
+    };
+    match result {
+        Ok(output) => output.chars().take(MAX_RESULT_CHARS).collect(),
+        Err(e) => {
+            logging::log_error(None, &format!("Tool {} failed: {}", call.name, e));
+            format!("Error: {}", e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_math_adds_and_diffs() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        assert_eq!(date_math(&json!({ "operation": "today" }), today).unwrap(), "2026-01-31 (Saturday)");
+        // Month arithmetic clamps to the end of shorter months
+        assert_eq!(date_math(&json!({ "operation": "add", "months": 1 }), today).unwrap(), "2026-02-28 (Saturday)");
+        assert_eq!(date_math(&json!({ "operation": "add", "weeks": -1, "days": 1 }), today).unwrap(), "2026-01-25 (Sunday)");
+        assert!(date_math(&json!({ "operation": "difference", "other_date": "2026-03-02" }), today).unwrap().starts_with("30 days"));
+        assert!(date_math(&json!({ "operation": "add", "date": "31/01/2026" }), today).is_err());
+    }
+
+    #[test]
+    fn reminder_due_times() {
+        let now = Local.with_ymd_and_hms(2026, 3, 10, 15, 0, 0).unwrap();
+        let friday_nine = Local.with_ymd_and_hms(2026, 3, 13, 9, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(parse_due("2026-03-13", now).unwrap(), friday_nine);
+        assert_eq!(parse_due("2026-03-13 09:00", now).unwrap(), friday_nine);
+        assert_eq!(parse_due(&friday_nine.to_rfc3339(), now).unwrap(), friday_nine);
+        assert!(parse_due("2026-03-10 14:00", now).is_err());
+        assert!(parse_due("next friday", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn tools_not_offered_for_the_turn_do_not_run() {
+        let context = ToolContext { conversation_id: None, agent: "logic".to_string() };
+        let offered: Vec<ToolSpec> = all_tools().into_iter().filter(|t| t.name == "date_math").collect();
+        let call = |name: &str| ToolCall { id: "1".to_string(), name: name.to_string(), arguments: json!({ "operation": "today" }) };
+        assert_eq!(execute(&call("fetch_url"), &offered, &context).await, "Error: Tool not available");
+        assert_eq!(execute(&call("mcp__github__delete_repo"), &offered, &context).await, "Error: Tool not available");
+        assert!(!execute(&call("date_math"), &offered, &context).await.starts_with("Error"));
+    }
+
+    #[test]
+    fn mcp_tools_need_opting_in() {
+        let mut settings = ToolSettings::default();
+        assert!(settings.is_enabled("date_math"));
+        assert!(!settings.is_enabled("fetch_url"));
+        assert!(!settings.is_enabled("mcp__github__delete_repo"));
+        settings.enabled.insert("mcp__github__delete_repo".to_string(), true);
+        assert!(settings.is_enabled("mcp__github__delete_repo"));
+    }
+}
@@ -0,0 +1,243 @@
+//! API usage, spend tracking and monthly budgets for Intersect
+//!
+//! - Every successful model or embedding call records its token counts and an estimated cost in `api_usage`
+//! - Costs come from a per-model price table (USD per million tokens); local Ollama models are free
+//! - A monthly budget can be set per provider. Crossing 80% raises a warning notification and crossing
+//!   100% raises another. Past the limit the budget can also downgrade calls to that provider's cheaper
+//!   model (`degrade`) and skip optional calls -- memory extraction, summaries, trait analysis and
+//!   follow-up detection (`skip_optional`).
+
+use crate::anthropic::CLAUDE_HAIKU;
+use crate::clock;
+use crate::db;
+use crate::logging;
+use crate::scheduler;
+use chrono::{Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const BUDGETS_SETTING: &str = "spend_budgets";
+/// Share of a budget at which the "approaching" warning goes out
+const WARN_FRACTION: f64 = 0.8;
+
+/// (model prefix, input USD / million tokens, output USD / million tokens); the first matching prefix wins
+const PRICES: [(&str, f64, f64); 12] = [
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("text-embedding-3-small", 0.02, 0.0),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-opus-4", 15.00, 75.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("gemini-1.5-pro", 1.25, 5.00),
+];
+/// Unknown hosted models (e.g. OpenRouter model strings) are priced like gpt-4o, so budgets err on the safe side
+const UNKNOWN_MODEL_PRICE: (f64, f64) = (2.50, 10.00);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpendBudget {
+    pub monthly_limit_usd: f64,
+    #[serde(default)]
+    pub degrade: bool,       // Past the limit, use the provider's cheaper model
+    #[serde(default)]
+    pub skip_optional: bool, // Past the limit, skip memory extraction, summaries and other optional calls
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub provider: String,
+    pub spent_usd: f64,
+    pub budget: Option<SpendBudget>,
+    pub state: String, // "ok" | "warning" | "exceeded" ("ok" when there's no budget)
+}
+
+pub fn estimate_cost(provider: &str, model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+    if provider == "ollama" {
+        return 0.0;
+    }
+    let (input_price, output_price) = PRICES.iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|(_, input, output)| (*input, *output))
+        .unwrap_or(UNKNOWN_MODEL_PRICE);
+    (input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0
+}
+
+/// Record one call's usage and raise a budget notification if it crossed a threshold
+pub fn record(provider: &str, model: &str, input_tokens: u64, output_tokens: u64) {
+    let cost = estimate_cost(provider, model, input_tokens, output_tokens);
+    if let Err(e) = db::record_api_usage(provider, model, input_tokens, output_tokens, cost) {
+        logging::log_error(None, &format!("Failed to record API usage: {}", e));
+        return;
+    }
+    check_thresholds(provider);
+}
+
+/// Start of the current calendar month (UTC) as RFC 3339
+pub fn month_start() -> String {
+    let now = clock::now();
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+        .to_rfc3339()
+}
+
+pub fn get_budgets() -> HashMap<String, SpendBudget> {
+    db::get_setting(BUDGETS_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Set (or with None, remove) a provider's monthly budget
+pub fn set_budget(provider: &str, budget: Option<SpendBudget>) -> Result<(), String> {
+    let mut budgets = get_budgets();
+    match budget {
+        Some(budget) if budget.monthly_limit_usd > 0.0 => { budgets.insert(provider.to_string(), budget); }
+        Some(_) => return Err("The monthly limit must be more than $0".to_string()),
+        None => { budgets.remove(provider); }
+    }
+    let json = serde_json::to_string(&budgets).map_err(|e| e.to_string())?;
+    db::set_setting(BUDGETS_SETTING, &json).map_err(|e| e.to_string())
+}
+
+pub fn budget_status(provider: &str) -> BudgetStatus {
+    let spent_usd = db::get_spend_since(provider, &month_start()).unwrap_or(0.0);
+    let budget = get_budgets().remove(provider);
+    let state = budget_state(spent_usd, budget.as_ref());
+    BudgetStatus { provider: provider.to_string(), spent_usd, budget, state: state.to_string() }
+}
+
+fn budget_state(spent_usd: f64, budget: Option<&SpendBudget>) -> &'static str {
+    match budget {
+        Some(b) if spent_usd >= b.monthly_limit_usd => "exceeded",
+        Some(b) if spent_usd >= b.monthly_limit_usd * WARN_FRACTION => "warning",
+        _ => "ok",
+    }
+}
+
+fn over_budget_with(provider: &str, flag: impl Fn(&SpendBudget) -> bool) -> bool {
+    let status = budget_status(provider);
+    status.state == "exceeded" && status.budget.as_ref().is_some_and(flag)
+}
+
+/// Whether optional calls to this provider (memory extraction, summaries, trait analysis,
+/// follow-up detection) should be skipped
+pub fn skip_optional_calls(provider: &str) -> bool {
+    over_budget_with(provider, |b| b.skip_optional)
+}
+
+/// The cheaper model to use instead of `model` when the provider is over a degrading budget
+pub fn degraded_model(provider: &str, model: &str) -> Option<&'static str> {
+    let cheaper = match provider {
+        "anthropic" => CLAUDE_HAIKU,
+        "openai" => "gpt-4o-mini",
+        "gemini" => "gemini-2.0-flash",
+        _ => return None,
+    };
+    if model == cheaper || !over_budget_with(provider, |b| b.degrade) {
+        return None;
+    }
+    logging::log_routing(None, &format!("{} budget exceeded, using {} instead of {}", provider, cheaper, model));
+    Some(cheaper)
+}
+
+/// Notify once per month per provider when spend first reaches the warning level and again at the limit
+fn check_thresholds(provider: &str) {
+    let status = budget_status(provider);
+    let Some(budget) = &status.budget else { return };
+    if status.state == "ok" {
+        return;
+    }
+
+    let month = clock::now().format("%Y-%m").to_string();
+    let alert_key = format!("spend_budget_alert.{}", provider);
+    let already = db::get_setting(&alert_key).ok().flatten().unwrap_or_default();
+    if !should_alert(&already, &month, &status.state) {
+        return;
+    }
+    let _ = db::set_setting(&alert_key, &format!("{}:{}", month, status.state));
+
+    let (title, action) = if status.state == "exceeded" {
+        let mut effects = Vec::new();
+        if budget.degrade {
+            effects.push("cheaper models are used");
+        }
+        if budget.skip_optional {
+            effects.push("memory extraction, summaries and other optional calls are paused");
+        }
+        let action = if effects.is_empty() {
+            String::new()
+        } else {
+            format!(" Until next month {}.", effects.join(" and "))
+        };
+        (format!("{} budget reached", provider_label(provider)), action)
+    } else {
+        (format!("{} budget almost used", provider_label(provider)), String::new())
+    };
+    let body = format!(
+        "You've used ${:.2} of your ${:.2} monthly {} budget.{}",
+        status.spent_usd, budget.monthly_limit_usd, provider_label(provider), action
+    );
+    if let Err(e) = scheduler::notify("budget", Some(provider), &title, &body) {
+        logging::log_error(None, &format!("Budget notification failed: {}", e));
+    }
+}
+
+/// Whether a threshold alert for `state` is still due this month, given the last alert's marker
+/// ("<month>:<state>"). Once the limit was reported, the warning isn't repeated.
+fn should_alert(already: &str, month: &str, state: &str) -> bool {
+    already != format!("{}:{}", month, state) && already != format!("{}:exceeded", month)
+}
+
+fn provider_label(provider: &str) -> &str {
+    match provider {
+        "openai" => "OpenAI",
+        "anthropic" => "Anthropic",
+        "gemini" => "Gemini",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(limit: f64) -> SpendBudget {
+        SpendBudget { monthly_limit_usd: limit, degrade: false, skip_optional: true }
+    }
+
+    #[test]
+    fn budget_state_follows_the_thresholds() {
+        assert_eq!(budget_state(100.0, None), "ok");
+        assert_eq!(budget_state(7.99, Some(&budget(10.0))), "ok");
+        assert_eq!(budget_state(8.0, Some(&budget(10.0))), "warning");
+        assert_eq!(budget_state(9.99, Some(&budget(10.0))), "warning");
+        assert_eq!(budget_state(10.0, Some(&budget(10.0))), "exceeded");
+        assert_eq!(budget_state(25.0, Some(&budget(10.0))), "exceeded");
+    }
+
+    #[test]
+    fn alerts_go_out_once_per_state_per_month() {
+        assert!(should_alert("", "2026-10", "warning"));
+        assert!(!should_alert("2026-10:warning", "2026-10", "warning"));
+        assert!(should_alert("2026-10:warning", "2026-10", "exceeded"));
+        assert!(!should_alert("2026-10:exceeded", "2026-10", "exceeded"));
+        // Spend doesn't go back down within a month, but a reported limit never turns into a warning
+        assert!(!should_alert("2026-10:exceeded", "2026-10", "warning"));
+        assert!(should_alert("2026-09:exceeded", "2026-10", "warning"));
+    }
+
+    #[test]
+    fn costs_use_the_first_matching_price() {
+        assert_eq!(estimate_cost("ollama", "llama3", 1_000_000, 1_000_000), 0.0);
+        // gpt-4o-mini must not be priced as gpt-4o
+        assert!((estimate_cost("openai", "gpt-4o-mini-2024-07-18", 1_000_000, 1_000_000) - 0.75).abs() < 1e-9);
+        assert!((estimate_cost("openai", "gpt-4o", 1_000_000, 0) - 2.50).abs() < 1e-9);
+        assert!((estimate_cost("openrouter", "mistral/large", 0, 1_000_000) - UNKNOWN_MODEL_PRICE.1).abs() < 1e-9);
+    }
+}
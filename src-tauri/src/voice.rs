@@ -0,0 +1,124 @@
+//! Voice input for Intersect
+//!
+//! Dictated audio is transcribed locally with whisper.cpp when it's set up (the `whisper-cli`
+//! binary and a ggml model file), and with OpenAI's Whisper API otherwise or when the local run
+//! fails. whisper.cpp reads WAV reliably; other formats are passed through and fall back to the
+//! API if it can't decode them. Settings are stored in app_settings as `voice_transcription`.
+
+use crate::db;
+use crate::logging;
+use crate::openai::OpenAIClient;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SETTINGS_KEY: &str = "voice_transcription";
+/// Largest recording accepted (the Whisper API's own limit is 25 MB)
+pub const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+pub struct VoiceSettings {
+    pub whisper_cli_path: Option<String>,   // whisper.cpp binary; None = API only
+    pub whisper_model_path: Option<String>, // ggml model file, e.g. ggml-base.en.bin
+    pub use_api_fallback: Option<bool>,     // None = true
+}
+
+impl VoiceSettings {
+    fn local(&self) -> Option<(&str, &str)> {
+        Some((self.whisper_cli_path.as_deref()?, self.whisper_model_path.as_deref()?))
+    }
+
+    fn api_fallback(&self) -> bool {
+        self.use_api_fallback.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcription {
+    pub text: String,
+    pub engine: String, // "whisper.cpp" | "openai"
+}
+
+pub fn get_settings() -> VoiceSettings {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_settings(settings: &VoiceSettings) -> Result<(), String> {
+    let trimmed = |p: &Option<String>| p.as_deref().map(str::trim).filter(|p| !p.is_empty()).map(str::to_string);
+    let settings = VoiceSettings {
+        whisper_cli_path: trimmed(&settings.whisper_cli_path),
+        whisper_model_path: trimmed(&settings.whisper_model_path),
+        use_api_fallback: settings.use_api_fallback,
+    };
+    if let Some(model) = &settings.whisper_model_path {
+        if !Path::new(model).is_file() {
+            return Err(format!("Whisper model not found: {}", model));
+        }
+    }
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// Run whisper.cpp on an audio file and return the plain transcript
+async fn transcribe_local(cli: &str, model: &str, audio_path: &Path) -> Result<String, String> {
+    let output = tokio::process::Command::new(cli)
+        .arg("-m").arg(model)
+        .arg("-f").arg(audio_path)
+        .args(["--no-timestamps", "--no-prints"])
+        .output()
+        .await
+        .map_err(|e| format!("Couldn't run whisper.cpp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("whisper.cpp failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let text = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(text)
+}
+
+/// Transcribe a recording, locally if whisper.cpp is configured, else (or on failure) via the API.
+/// `file_name` carries the format for the API; bytes are written to a temp file for whisper.cpp.
+pub async fn transcribe(audio: Vec<u8>, file_name: &str, openai_key: Option<&str>) -> Result<Transcription, String> {
+    if audio.is_empty() {
+        return Err("No audio to transcribe".to_string());
+    }
+    if audio.len() > MAX_AUDIO_BYTES {
+        return Err(format!("Recording is too large (max {} MB)", MAX_AUDIO_BYTES / 1024 / 1024));
+    }
+    let settings = get_settings();
+
+    if let Some((cli, model)) = settings.local() {
+        let extension = Path::new(file_name).extension().and_then(|e| e.to_str()).unwrap_or("wav");
+        let temp: PathBuf = std::env::temp_dir().join(format!("intersect-voice-{}.{}", uuid::Uuid::new_v4(), extension));
+        let result = match tokio::fs::write(&temp, &audio).await {
+            Ok(()) => transcribe_local(cli, model, &temp).await,
+            Err(e) => Err(format!("Couldn't stage audio: {}", e)),
+        };
+        let _ = tokio::fs::remove_file(&temp).await;
+        match result {
+            Ok(text) if !text.is_empty() => {
+                return Ok(Transcription { text, engine: "whisper.cpp".to_string() });
+            }
+            Ok(_) => logging::log_error(None, "whisper.cpp returned an empty transcript"),
+            Err(e) => logging::log_error(None, &e),
+        }
+        if !settings.api_fallback() {
+            return Err("Local transcription failed and the API fallback is off".to_string());
+        }
+    }
+
+    let key = openai_key.ok_or("Transcription needs whisper.cpp or an OpenAI API key")?;
+    let text = OpenAIClient::new(key)
+        .transcribe(audio, file_name)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Transcription { text, engine: "openai".to_string() })
+}
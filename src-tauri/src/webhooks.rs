@@ -0,0 +1,210 @@
+//! Webhook notifications for Intersect
+//!
+//! Registered URLs get a JSON POST when something worth piping elsewhere happens:
+//! - `fact.learned`: a fact is learned with confidence >= HIGH_CONFIDENCE (explicit "remember
+//!   that..." facts always qualify)
+//! - `trait.shifted`: the dominant trait changes after an exchange
+//! - `report.generated`: a weekly digest is written
+//!
+//! The body is {"event", "timestamp", "data"}. A webhook with a secret also gets
+//! `X-Intersect-Signature: sha256=<hex HMAC-SHA256 of the body>`. Deliveries run in the
+//! background, retry on network and 5xx errors, and record the outcome on the webhook.
+//!
+//! Webhooks are stored in app_settings as `webhooks`.
+
+use crate::clock;
+use crate::db;
+use crate::logging;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SETTINGS_KEY: &str = "webhooks";
+pub const EVENTS: [&str; 3] = ["fact.learned", "trait.shifted", "report.generated"];
+/// Extracted facts below this confidence don't fire `fact.learned`
+pub const HIGH_CONFIDENCE: f64 = 0.8;
+const DELIVERY_ATTEMPTS: u32 = 3;
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,        // Empty = every event
+    #[serde(default)]
+    pub secret: Option<String>,     // Signs deliveries when set
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub last_status: Option<String>, // "200", "error: ...", set after each delivery
+    #[serde(default)]
+    pub last_delivered_at: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Webhook {
+    fn wants(&self, event: &str) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.iter().any(|e| e == event))
+    }
+}
+
+// Serializes read-modify-write of the stored list (deliveries update it concurrently)
+static STORE: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+pub fn get_webhooks() -> Vec<Webhook> {
+    db::get_setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_webhooks(webhooks: &[Webhook]) -> Result<(), String> {
+    let json = serde_json::to_string(webhooks).map_err(|e| e.to_string())?;
+    db::set_setting(SETTINGS_KEY, &json).map_err(|e| e.to_string())
+}
+
+pub fn add_webhook(url: &str, events: Vec<String>, secret: Option<String>) -> Result<Webhook, String> {
+    let url = url.trim();
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Webhook URLs must be http(s)".to_string());
+    }
+    if let Some(unknown) = events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+        return Err(format!("Unknown event \"{}\" (expected one of: {})", unknown, EVENTS.join(", ")));
+    }
+    let webhook = Webhook {
+        id: uuid::Uuid::new_v4().to_string(),
+        url: url.to_string(),
+        events,
+        secret: secret.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        enabled: true,
+        last_status: None,
+        last_delivered_at: None,
+    };
+
+    let _guard = STORE.lock().unwrap();
+    let mut webhooks = get_webhooks();
+    webhooks.push(webhook.clone());
+    save_webhooks(&webhooks)?;
+    Ok(webhook)
+}
+
+pub fn remove_webhook(id: &str) -> Result<bool, String> {
+    let _guard = STORE.lock().unwrap();
+    let mut webhooks = get_webhooks();
+    let before = webhooks.len();
+    webhooks.retain(|w| w.id != id);
+    if webhooks.len() == before {
+        return Ok(false);
+    }
+    save_webhooks(&webhooks)?;
+    Ok(true)
+}
+
+fn record_outcome(id: &str, status: &str) {
+    let _guard = STORE.lock().unwrap();
+    let mut webhooks = get_webhooks();
+    if let Some(webhook) = webhooks.iter_mut().find(|w| w.id == id) {
+        webhook.last_status = Some(status.to_string());
+        webhook.last_delivered_at = Some(clock::now().to_rfc3339());
+        let _ = save_webhooks(&webhooks);
+    }
+}
+
+/// Hex HMAC-SHA256 of a body
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body.as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// POST one event to one webhook, retrying network errors and 5xx. Returns the final status.
+async fn deliver(webhook: &Webhook, event: &str, body: &str) -> Result<u16, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut last_error = String::new();
+    for attempt in 0..DELIVERY_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+        }
+        let mut request = client.post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", format!("Intersect/{}", env!("CARGO_PKG_VERSION")))
+            .header("X-Intersect-Event", event)
+            .body(body.to_string());
+        if let Some(secret) = &webhook.secret {
+            request = request.header("X-Intersect-Signature", format!("sha256={}", sign(secret, body)));
+        }
+        match request.send().await {
+            Ok(response) if response.status().is_server_error() => last_error = format!("HTTP {}", response.status()),
+            Ok(response) => return Ok(response.status().as_u16()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+async fn deliver_and_record(webhook: Webhook, event: String, body: String) {
+    let status = match deliver(&webhook, &event, &body).await {
+        Ok(code) => code.to_string(),
+        Err(e) => {
+            logging::log_error(None, &format!("Webhook {} failed for {}: {}", webhook.url, event, e));
+            format!("error: {}", e)
+        }
+    };
+    record_outcome(&webhook.id, &status);
+}
+
+fn payload(event: &str, data: Value) -> String {
+    json!({ "event": event, "timestamp": clock::now().to_rfc3339(), "data": data }).to_string()
+}
+
+/// Send an event to every webhook subscribed to it, in the background
+pub fn emit(event: &str, data: Value) {
+    let webhooks: Vec<Webhook> = get_webhooks().into_iter().filter(|w| w.wants(event)).collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    let body = payload(event, data);
+    for webhook in webhooks {
+        tauri::async_runtime::spawn(deliver_and_record(webhook, event.to_string(), body.clone()));
+    }
+}
+
+/// Send a `ping` to one webhook now and report the response status
+pub async fn test(id: &str) -> Result<u16, String> {
+    let webhook = get_webhooks().into_iter().find(|w| w.id == id).ok_or("Webhook not found")?;
+    let body = payload("ping", json!({ "webhook_id": webhook.id }));
+    let result = deliver(&webhook, "ping", &body).await;
+    record_outcome(&webhook.id, &match &result {
+        Ok(code) => code.to_string(),
+        Err(e) => format!("error: {}", e),
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_reference_hmac() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+import { useEffect, useRef, useState } from 'react';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { quickCapture } from '../hooks/useTauri';
+
+// The small window the quick capture hotkey opens (route #/capture): Enter files the text
+// in the Inbox, Escape puts the window away
+export function QuickCapture() {
+  const [text, setText] = useState('');
+  const [isSaving, setIsSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+  const inputRef = useRef<HTMLTextAreaElement>(null);
+
+  // The window is hidden rather than closed, so refocus whenever it comes back
+  useEffect(() => {
+    const focus = () => inputRef.current?.focus();
+    focus();
+    window.addEventListener('focus', focus);
+    return () => window.removeEventListener('focus', focus);
+  }, []);
+
+  const submit = async () => {
+    if (!text.trim() || isSaving) return;
+    setIsSaving(true);
+    setError(null);
+    try {
+      // The backend hides the window once the text is filed
+      await quickCapture(text);
+      setText('');
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleKeyDown = (e: React.KeyboardEvent<HTMLTextAreaElement>) => {
+    if (e.key === 'Enter' && !e.shiftKey) {
+      e.preventDefault();
+      submit();
+    } else if (e.key === 'Escape') {
+      e.preventDefault();
+      setError(null);
+      getCurrentWindow().hide();
+    }
+  };
+
+  return (
+    <div className="h-screen w-screen flex flex-col justify-center bg-obsidian/95 border border-smoke/40 rounded-xl px-4">
+      <textarea
+        ref={inputRef}
+        value={text}
+        onChange={(e) => setText(e.target.value)}
+        onKeyDown={handleKeyDown}
+        placeholder="Capture a thought for your Inbox…"
+        disabled={isSaving}
+        rows={2}
+        className="w-full bg-transparent text-pearl font-mono text-sm resize-none outline-none border-none placeholder:text-ash/40"
+        style={{ boxShadow: 'none' }}
+      />
+      <div className="flex items-center justify-between pt-1 font-mono text-[10px] text-ash/50">
+        <span className={error ? 'text-red-400' : undefined}>{error ?? (isSaving ? 'Saving…' : 'Enter to capture · Esc to close')}</span>
+      </div>
+    </div>
+  );
+}
@@ -221,6 +221,7 @@ export async function getConversationMessages(conversationId: string): Promise<M
     response_type: string | null;
     references_message_id: string | null;
     timestamp: string;
+    starred: boolean;
   }[]>('get_conversation_messages', { conversationId });
   
   return messages.map(m => ({
@@ -231,6 +232,7 @@ export async function getConversationMessages(conversationId: string): Promise<M
     responseType: m.response_type as Message['responseType'],
     referencesMessageId: m.references_message_id || undefined,
     timestamp: new Date(m.timestamp),
+    starred: m.starred,
   }));
 }
 
@@ -492,3 +494,19 @@ export async function completeJourneySession(sessionId: string, acceptanceSummar
 export async function getJourneySessionsCompleted(profileId: string): Promise<number> {
   return invoke<number>('get_journey_sessions_completed', { profileId });
 }
+
+// Quick capture
+export interface CaptureResult {
+  conversationId: string;
+  mode: 'send' | 'store';
+  messageId: string | null;
+}
+
+export async function quickCapture(text: string): Promise<CaptureResult> {
+  const raw = await invoke<{ conversation_id: string; mode: 'send' | 'store'; message_id: string | null }>('quick_capture', { text });
+  return {
+    conversationId: raw.conversation_id,
+    mode: raw.mode,
+    messageId: raw.message_id,
+  };
+}
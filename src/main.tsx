@@ -1,10 +1,12 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
 import App from "./App";
+import { QuickCapture } from "./components/QuickCapture";
 import "./index.css";
 
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
-    <App />
+    {/* The quick capture window loads index.html#/capture and gets only the capture form */}
+    {window.location.hash === "#/capture" ? <QuickCapture /> : <App />}
   </React.StrictMode>,
 );
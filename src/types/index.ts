@@ -29,6 +29,7 @@ export interface Message {
   isStreaming?: boolean;
   isDisco?: boolean;  // Whether this message was generated in Disco Mode
   agentName?: string; // For governor_thoughts: which agent said this
+  starred?: boolean;  // Saved as an insight
 }
 
 // Agent response from backend